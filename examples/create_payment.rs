@@ -1,49 +1,15 @@
 use anyhow::Context;
-use truelayer_rust::{
-    apis::{
-        auth::Credentials,
-        payments::{
-            Beneficiary, CreatePaymentRequest, CreatePaymentUserRequest, Currency,
-            PaymentMethodRequest, ProviderSelectionRequest,
-        },
-    },
-    client::Environment,
-    pollable::{PollOptions, PollableUntilTerminalState},
-    TrueLayerClient,
-};
-use url::Url;
-
-#[derive(serde::Deserialize, Debug)]
-struct Config {
-    client_id: String,
-    client_secret: String,
-    key_id: String,
-    private_key: String,
-    return_uri: Url,
-}
-
-impl Config {
-    fn read() -> anyhow::Result<Self> {
-        config::Config::builder()
-            .add_source(config::File::with_name("config"))
-            .build()?
-            .try_deserialize()
-            .context("Failed to assemble the required configuration")
-    }
-}
+use truelayer_rust::prelude::*;
 
 async fn run() -> anyhow::Result<()> {
-    let config = Config::read()?;
+    let config = truelayer_rust::config::load()?;
+    let return_uri = config
+        .get("return_uri")
+        .context("Missing return_uri setting")?
+        .to_string();
 
     // Setup TrueLayer client
-    let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
-        client_id: config.client_id,
-        client_secret: config.client_secret.into(),
-        scope: "payments".to_string(),
-    })
-    .with_signing_key(&config.key_id, config.private_key.into_bytes())
-    .with_environment(Environment::Sandbox)
-    .build();
+    let tl = config.client_builder().build();
 
     // List all merchant accounts
     let merchant_accounts = tl.merchant_accounts.list().await?;
@@ -84,6 +50,8 @@ async fn run() -> anyhow::Result<()> {
                 phone: None,
             },
             metadata: None,
+            retry: None,
+            related_products: None,
         })
         .await?;
 
@@ -92,8 +60,9 @@ async fn run() -> anyhow::Result<()> {
     tracing::info!(
         "HPP Link: {}",
         tl.payments
-            .get_hosted_payments_page_link(&res.id, &res.resource_token, config.return_uri.as_str())
-            .await
+            .hosted_payments_page_link(&res.id, &res.resource_token)
+            .return_uri(&return_uri)
+            .build()?
     );
 
     tracing::info!("Begin waiting...");
@@ -1,44 +1,11 @@
-use anyhow::Context;
 use dialoguer::{console::style, theme::ColorfulTheme, Confirm, Input, Select};
-use truelayer_rust::{
-    apis::{
-        auth::Credentials,
-        merchant_accounts::{SetupSweepingRequest, SweepingFrequency},
-    },
-    client::Environment,
-    TrueLayerClient,
-};
-
-#[derive(serde::Deserialize, Debug)]
-struct Config {
-    client_id: String,
-    client_secret: String,
-    key_id: String,
-    private_key: String,
-}
-
-impl Config {
-    fn read() -> anyhow::Result<Self> {
-        config::Config::builder()
-            .add_source(config::File::with_name("config"))
-            .build()?
-            .try_deserialize()
-            .context("Failed to assemble the required configuration")
-    }
-}
+use truelayer_rust::apis::merchant_accounts::{SetupSweepingRequest, SweepingFrequency};
 
 async fn run() -> anyhow::Result<()> {
-    let config = Config::read()?;
+    let config = truelayer_rust::config::load()?;
 
     // Setup TrueLayer client
-    let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
-        client_id: config.client_id,
-        client_secret: config.client_secret.into(),
-        scope: "payments".to_string(),
-    })
-    .with_signing_key(&config.key_id, config.private_key.into_bytes())
-    .with_environment(Environment::Sandbox)
-    .build();
+    let tl = config.client_builder().build();
 
     // List all merchant accounts
     let merchant_accounts = tl.merchant_accounts.list().await?;
@@ -0,0 +1,288 @@
+//! Helpers to work safely with amounts expressed in the minor unit of a currency.
+//!
+//! All amounts and balances in this crate are modeled as [`u64`] values in minor units
+//! (e.g., pennies for GBP). JSON amounts are deserialized strictly: floating-point
+//! or negative numbers are rejected instead of being silently truncated.
+//!
+//! Amounts which TrueLayer reports as signed decimals in the major unit, like the balances and
+//! transactions of the Data API, are modeled as [`MajorAmount`]s, never as floating-point numbers.
+
+use serde::{
+    de::{Error as _, Unexpected, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+    fmt::{Display, Formatter},
+    str::FromStr,
+};
+
+/// Adds two amounts in minor units, returning `None` on overflow.
+///
+/// ```
+/// # use truelayer_rust::amounts::checked_add_minor;
+/// assert_eq!(checked_add_minor(100, 250), Some(350));
+/// assert_eq!(checked_add_minor(u64::MAX, 1), None);
+/// ```
+pub fn checked_add_minor(a: u64, b: u64) -> Option<u64> {
+    a.checked_add(b)
+}
+
+/// Subtracts `b` from `a`, returning `None` if the result would be negative.
+pub fn checked_sub_minor(a: u64, b: u64) -> Option<u64> {
+    a.checked_sub(b)
+}
+
+/// Sums a sequence of amounts in minor units, returning `None` on overflow.
+///
+/// ```
+/// # use truelayer_rust::amounts::checked_sum_minor;
+/// assert_eq!(checked_sum_minor([100, 200, 300]), Some(600));
+/// assert_eq!(checked_sum_minor([u64::MAX, 1]), None);
+/// ```
+pub fn checked_sum_minor<I: IntoIterator<Item = u64>>(amounts: I) -> Option<u64> {
+    amounts
+        .into_iter()
+        .try_fold(0u64, |acc, amount| acc.checked_add(amount))
+}
+
+/// Deserializes an amount in minor units, rejecting floating-point and negative numbers.
+pub(crate) fn deserialize_minor<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u64, D::Error> {
+    deserializer.deserialize_u64(MinorAmountVisitor)
+}
+
+/// Same as [`deserialize_minor`], for optional amounts.
+pub(crate) fn deserialize_optional_minor<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    deserializer.deserialize_option(OptionalMinorAmountVisitor)
+}
+
+struct MinorAmountVisitor;
+
+impl<'de> Visitor<'de> for MinorAmountVisitor {
+    type Value = u64;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("a non-negative integer amount in minor units")
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(v)
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        u64::try_from(v).map_err(|_| E::invalid_value(Unexpected::Signed(v), &self))
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Err(E::invalid_type(Unexpected::Float(v), &self))
+    }
+}
+
+struct OptionalMinorAmountVisitor;
+
+impl<'de> Visitor<'de> for OptionalMinorAmountVisitor {
+    type Value = Option<u64>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("an optional non-negative integer amount in minor units")
+    }
+
+    fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserialize_minor(deserializer).map(Some)
+    }
+}
+
+/// Error returned when a string is not a valid [`MajorAmount`].
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+#[error("Invalid decimal amount {0:?}")]
+pub struct InvalidMajorAmount(String);
+
+/// Signed decimal amount in the major unit of a currency (e.g., pounds for GBP).
+///
+/// The amount keeps the decimal representation it was received with, so that it can be converted
+/// exactly to minor units with [`to_minor`](MajorAmount::to_minor). It is serialized as a string.
+///
+/// ```
+/// # use truelayer_rust::amounts::MajorAmount;
+/// let amount: MajorAmount = "-24.25".parse().unwrap();
+/// assert_eq!(amount.to_minor(2), Some(-2425));
+/// assert_eq!(amount.to_minor(1), None);
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct MajorAmount(String);
+
+impl MajorAmount {
+    /// Returns the decimal representation of the amount, e.g. `-24.25`.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Converts the amount to minor units, given the number of decimals of its currency
+    /// (e.g., 2 for GBP).
+    ///
+    /// Returns `None` if the amount has more decimals than the currency, or on overflow.
+    pub fn to_minor(&self, decimals: u32) -> Option<i64> {
+        let (negative, digits) = match self.0.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, self.0.as_str()),
+        };
+        let (units, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.len() > decimals as usize {
+            return None;
+        }
+
+        let scale = 10i64.checked_pow(decimals)?;
+        let fraction = format!("{:0<width$}", fraction, width = decimals as usize);
+        let minor =
+            units
+                .parse::<i64>()
+                .ok()?
+                .checked_mul(scale)?
+                .checked_add(if fraction.is_empty() {
+                    0
+                } else {
+                    fraction.parse::<i64>().ok()?
+                })?;
+
+        Some(if negative { -minor } else { minor })
+    }
+}
+
+impl FromStr for MajorAmount {
+    type Err = InvalidMajorAmount;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix('-').unwrap_or(s);
+        let (units, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+
+        if units.is_empty()
+            || !is_digits(units)
+            || !is_digits(fraction)
+            || (digits.contains('.') && fraction.is_empty())
+        {
+            return Err(InvalidMajorAmount(s.to_string()));
+        }
+
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl Display for MajorAmount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for MajorAmount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for MajorAmount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(MajorAmountVisitor)
+    }
+}
+
+struct MajorAmountVisitor;
+
+impl<'de> Visitor<'de> for MajorAmountVisitor {
+    type Value = MajorAmount;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("a decimal amount in the major unit")
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(MajorAmount(v.to_string()))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(MajorAmount(v.to_string()))
+    }
+
+    /// JSON numbers with a fraction are only available as `f64`: their shortest representation,
+    /// which is the one sent by TrueLayer, is kept.
+    fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        if !v.is_finite() {
+            return Err(E::invalid_value(Unexpected::Float(v), &self));
+        }
+        Ok(MajorAmount(v.to_string()))
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse()
+            .map_err(|_| E::invalid_value(Unexpected::Str(v), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize, Debug)]
+    struct Amounts {
+        #[serde(deserialize_with = "deserialize_minor")]
+        amount_in_minor: u64,
+        #[serde(default, deserialize_with = "deserialize_optional_minor")]
+        optional_amount_in_minor: Option<u64>,
+    }
+
+    #[test]
+    fn accepts_integers() {
+        let amounts: Amounts = serde_json::from_str(
+            r#"{ "amount_in_minor": 18446744073709551615, "optional_amount_in_minor": 1 }"#,
+        )
+        .unwrap();
+
+        assert_eq!(amounts.amount_in_minor, u64::MAX);
+        assert_eq!(amounts.optional_amount_in_minor, Some(1));
+
+        let amounts: Amounts = serde_json::from_str(r#"{ "amount_in_minor": 1 }"#).unwrap();
+        assert_eq!(amounts.optional_amount_in_minor, None);
+    }
+
+    #[test]
+    fn rejects_floats_and_negatives() {
+        for json in [
+            r#"{ "amount_in_minor": 100.0 }"#,
+            r#"{ "amount_in_minor": 1.5 }"#,
+            r#"{ "amount_in_minor": -1 }"#,
+            r#"{ "amount_in_minor": 1, "optional_amount_in_minor": 0.5 }"#,
+        ] {
+            assert!(serde_json::from_str::<Amounts>(json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn major_amounts_are_kept_exact() {
+        let amounts: Vec<MajorAmount> =
+            serde_json::from_str(r#"[1161.2, -24.25, 1000, "0.10", 0.1]"#).unwrap();
+        let minor: Vec<Option<i64>> = amounts.iter().map(|a| a.to_minor(2)).collect();
+
+        assert_eq!(
+            minor,
+            vec![Some(116120), Some(-2425), Some(100000), Some(10), Some(10)]
+        );
+        assert_eq!(amounts[0].as_str(), "1161.2");
+        assert_eq!(serde_json::to_string(&amounts[1]).unwrap(), r#""-24.25""#);
+        assert_eq!("0.125".parse::<MajorAmount>().unwrap().to_minor(2), None);
+        for invalid in ["", "-", "1.", ".5", "1e3", "12a"] {
+            assert!(invalid.parse::<MajorAmount>().is_err(), "{}", invalid);
+        }
+    }
+}
@@ -22,4 +22,18 @@ impl AuthApi {
         // Just delegate to the authenticator
         self.inner.authenticator.get_access_token().await
     }
+
+    /// Returns an [`AccessToken`](crate::apis::auth::AccessToken) restricted to the given scope,
+    /// cached independently of the token used by the client.
+    ///
+    /// Only supported with [`Credentials::ClientCredentials`](crate::apis::auth::Credentials::ClientCredentials).
+    pub async fn get_access_token_for_scope(
+        &self,
+        scope: &str,
+    ) -> Result<AuthenticationResult, Error> {
+        self.inner
+            .authenticator
+            .get_access_token_for_scope(scope)
+            .await
+    }
 }
@@ -2,6 +2,8 @@
 
 mod api;
 mod model;
+mod token_store;
 
 pub use api::AuthApi;
 pub use model::*;
+pub use token_store::{InMemoryTokenStore, StoredToken, TokenStore, TokenStoreKey};
@@ -1,7 +1,8 @@
+use crate::runtime::sync::Semaphore;
 use chrono::{DateTime, Utc};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
-use std::ops::Deref;
+use std::{ops::Deref, sync::Arc};
 
 /// Credentials used to authenticate against TrueLayer's APIs.
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -74,14 +75,48 @@ impl AuthenticationResult {
     }
 }
 
+/// What the client should do when an access token is due for refresh but the refresh fails,
+/// for example because the authentication server is unavailable.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum TokenRefreshFailurePolicy {
+    /// Return the refresh error immediately. This is the default.
+    #[default]
+    FailFast,
+    /// Keep serving the cached token while retrying the refresh on every request,
+    /// as long as the cached token is valid for at least `leeway`.
+    ServeStale { leeway: std::time::Duration },
+}
+
+/// Limit on the number of concurrent requests to the Auth server.
+///
+/// Cloning an `AuthConcurrencyLimit` returns a handle to the same limit, so that it can be shared
+/// by multiple clients, e.g. one per tenant, running in the same process.
+#[derive(Debug, Clone)]
+pub struct AuthConcurrencyLimit(pub(crate) Arc<Semaphore>);
+
+impl AuthConcurrencyLimit {
+    /// Allows at most `max_concurrent_requests` concurrent requests to the Auth server.
+    ///
+    /// A limit of `0` would block all the requests forever, so it is raised to `1`.
+    pub fn new(max_concurrent_requests: usize) -> Self {
+        Self(Arc::new(Semaphore::new(max_concurrent_requests.max(1))))
+    }
+}
+
 /// Opaque access token used to authenticate to TrueLayer APIs.
-#[derive(Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AccessToken {
     pub(crate) token: Token,
     pub(crate) expires_at: Option<DateTime<Utc>>,
 }
 
 impl AccessToken {
+    /// Wraps a token expiring at the given date, e.g. to restore it from a
+    /// [`TokenStore`](crate::apis::auth::TokenStore).
+    pub fn new(token: Token, expires_at: Option<DateTime<Utc>>) -> Self {
+        Self { token, expires_at }
+    }
+
     /// Actual token contents held by this `AccessToken` instance.
     pub fn token(&self) -> &Token {
         &self.token
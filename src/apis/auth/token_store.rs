@@ -0,0 +1,109 @@
+use crate::apis::auth::{AccessToken, Token};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Debug, sync::RwLock};
+
+/// Identifies the tokens of one authenticator in a [`TokenStore`].
+///
+/// Clients configured with different token audiences or requesting specific scopes
+/// store their tokens under different keys, even if they share the same credentials.
+/// Tokens obtained on behalf of a user are keyed by the grant of that user, so that they are
+/// never served to another user of the same application.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct TokenStoreKey {
+    pub client_id: String,
+    /// Audience of the tokens, see
+    /// [`with_token_audience`](crate::client::TrueLayerClientBuilder::with_token_audience).
+    pub audience: Option<String>,
+    /// Scope of the tokens: the requested scope, or the scope of the client credentials.
+    /// `None` for user-delegated credentials.
+    pub scope: Option<String>,
+    /// Fingerprint of the authorization code or refresh token the client was configured with,
+    /// for user-delegated credentials. `None` for client credentials.
+    pub grant: Option<String>,
+}
+
+/// Tokens persisted in a [`TokenStore`].
+///
+/// Both tokens are serialized in clear, so that they can be read back by other processes:
+/// stores must be protected accordingly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredToken {
+    pub access_token: AccessToken,
+    /// Latest refresh token issued by the Auth server, which replaces the one of the credentials.
+    pub refresh_token: Option<Token>,
+}
+
+/// Storage of the tokens obtained by a [`TrueLayerClient`](crate::TrueLayerClient).
+///
+/// Tokens are always cached in memory by each client, and the store is only read when the cached
+/// access token is missing or due for refresh. Backing the store with Redis or a database lets multiple
+/// processes share the same tokens, and a restarted process resume with the latest refresh token
+/// instead of authenticating again.
+///
+/// Only access tokens obtained with client credentials are shared between clients: with user-delegated
+/// credentials, the store only hands over the latest refresh token of the same grant.
+///
+/// Failures of the store are logged and otherwise ignored: tokens are requested from the Auth server instead.
+#[async_trait]
+pub trait TokenStore: Debug + Send + Sync {
+    /// Returns the tokens stored with the given key, if any.
+    async fn load(&self, key: &TokenStoreKey) -> Result<Option<StoredToken>, anyhow::Error>;
+
+    /// Stores the tokens with the given key, replacing any previous tokens.
+    async fn save(&self, key: &TokenStoreKey, token: StoredToken) -> Result<(), anyhow::Error>;
+
+    /// Stores the tokens with the given key only if the refresh token currently stored is `expected`,
+    /// returning whether they were stored.
+    ///
+    /// Used after a refresh token rotation, so that a client never replaces the refresh token
+    /// rotated in the meantime by another client. Must be atomic.
+    async fn compare_and_swap(
+        &self,
+        key: &TokenStoreKey,
+        expected: Option<&Token>,
+        token: StoredToken,
+    ) -> Result<bool, anyhow::Error>;
+}
+
+/// In-memory [`TokenStore`], used by default.
+#[derive(Debug, Default)]
+pub struct InMemoryTokenStore {
+    tokens: RwLock<HashMap<TokenStoreKey, StoredToken>>,
+}
+
+impl InMemoryTokenStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TokenStore for InMemoryTokenStore {
+    async fn load(&self, key: &TokenStoreKey) -> Result<Option<StoredToken>, anyhow::Error> {
+        Ok(self.tokens.read().unwrap().get(key).cloned())
+    }
+
+    async fn save(&self, key: &TokenStoreKey, token: StoredToken) -> Result<(), anyhow::Error> {
+        self.tokens.write().unwrap().insert(key.clone(), token);
+        Ok(())
+    }
+
+    async fn compare_and_swap(
+        &self,
+        key: &TokenStoreKey,
+        expected: Option<&Token>,
+        token: StoredToken,
+    ) -> Result<bool, anyhow::Error> {
+        let mut tokens = self.tokens.write().unwrap();
+        let current = tokens
+            .get(key)
+            .and_then(|stored| stored.refresh_token.as_ref());
+        if current.map(Token::expose_secret) != expected.map(Token::expose_secret) {
+            return Ok(false);
+        }
+
+        tokens.insert(key.clone(), token);
+        Ok(true)
+    }
+}
@@ -0,0 +1,302 @@
+use crate::{
+    apis::{
+        data::{Account, Balance, DataResponse, Transaction},
+        TrueLayerClientInner,
+    },
+    Error,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::sync::Arc;
+use urlencoding::encode;
+
+/// TrueLayer Data APIs client.
+///
+/// The Data API accesses the accounts of a user, hence the [`TrueLayerClient`](crate::TrueLayerClient)
+/// must be built with the credentials obtained when the user granted consent, i.e.
+/// [`Credentials::AuthorizationCode`](crate::apis::auth::Credentials::AuthorizationCode)
+/// or [`Credentials::RefreshToken`](crate::apis::auth::Credentials::RefreshToken).
+#[derive(Clone, Debug)]
+pub struct DataApi {
+    inner: Arc<TrueLayerClientInner>,
+}
+
+impl DataApi {
+    pub(crate) fn new(inner: Arc<TrueLayerClientInner>) -> Self {
+        Self { inner }
+    }
+
+    /// Lists all the accounts the user granted access to.
+    #[tracing::instrument(name = "List Accounts", skip(self))]
+    pub async fn list_accounts(&self) -> Result<Vec<Account>, Error> {
+        let res: DataResponse<Account> = self
+            .inner
+            .client
+            .get(
+                self.inner
+                    .environment
+                    .payments_url()
+                    .join("/data/v1/accounts")
+                    .unwrap(),
+            )
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        Ok(res.results)
+    }
+
+    /// Gets the balance of an account.
+    ///
+    /// If there's no account with the given id, `None` is returned.
+    #[tracing::instrument(name = "Get Account Balance", skip(self))]
+    pub async fn get_account_balance(&self, account_id: &str) -> Result<Option<Balance>, Error> {
+        let res = self
+            .inner
+            .client
+            .get(
+                self.inner
+                    .environment
+                    .payments_url()
+                    .join(&format!("/data/v1/accounts/{}/balance", encode(account_id)))
+                    .unwrap(),
+            )
+            .send()
+            .await
+            .map_err(Error::from);
+
+        // Return `None` if the server returned 404
+        let balance = match res {
+            Ok(body) => body
+                .json::<DataResponse<Balance>>()
+                .await?
+                .results
+                .into_iter()
+                .next(),
+            Err(Error::ApiError(api_error)) if api_error.status == 404 => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(balance)
+    }
+
+    /// Lists the transactions of an account which happened in the given time range.
+    #[tracing::instrument(name = "List Account Transactions", skip(self))]
+    pub async fn list_transactions(
+        &self,
+        account_id: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Transaction>, Error> {
+        let res: DataResponse<Transaction> = self
+            .inner
+            .client
+            .get(
+                self.inner
+                    .environment
+                    .payments_url()
+                    .join(&format!(
+                        "/data/v1/accounts/{}/transactions",
+                        encode(account_id)
+                    ))
+                    .unwrap(),
+            )
+            .query(&[
+                ("from", from.to_rfc3339_opts(SecondsFormat::Secs, true)),
+                ("to", to.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            ])
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        Ok(res.results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apis::{auth::Credentials, data::TransactionType, payments::Currency},
+        authenticator::Authenticator,
+        client::Environment,
+        middlewares::error_handling::ErrorHandlingMiddleware,
+        pollable::PollOptions,
+    };
+    use chrono::TimeZone;
+    use serde_json::json;
+    use url::Url;
+    use wiremock::{
+        matchers::{method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    async fn mock_client_and_server() -> (TrueLayerClientInner, MockServer) {
+        let mock_server = MockServer::start().await;
+
+        let credentials = Credentials::AuthorizationCode {
+            client_id: "client-id".into(),
+            client_secret: "client-secret".into(),
+            code: "code".into(),
+            redirect_uri: "https://my.redirect.uri".into(),
+        };
+
+        let authenticator = Authenticator::new(
+            reqwest::Client::new().into(),
+            Url::parse(&mock_server.uri()).unwrap(),
+            credentials,
+        );
+
+        let inner = TrueLayerClientInner {
+            client: reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+                .with(ErrorHandlingMiddleware)
+                .build(),
+            authenticator,
+            environment: Environment::from_single_url(&Url::parse(&mock_server.uri()).unwrap()),
+            deprecations: Default::default(),
+            rate_limits: Default::default(),
+            poll_options: PollOptions::default().into_dyn(),
+            poll_budget: None,
+            flow_cache: Default::default(),
+            flow_events: Default::default(),
+            idempotency_ledger: Default::default(),
+            payout_guardrails: None,
+            request_traces: Default::default(),
+            implicit_behaviors: true,
+        };
+
+        (inner, mock_server)
+    }
+
+    #[tokio::test]
+    async fn list_accounts_and_balance() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = DataApi::new(Arc::new(inner));
+
+        Mock::given(method("GET"))
+            .and(path("/data/v1/accounts"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [{
+                    "account_id": "account-id",
+                    "account_type": "TRANSACTION",
+                    "display_name": "Club Lloyds",
+                    "currency": "GBP",
+                    "account_number": {
+                        "iban": "GB35LOYD12345678901234",
+                        "number": "12345678",
+                        "sort_code": "12-34-56",
+                        "swift_bic": "LOYDGB2L"
+                    },
+                    "provider": {
+                        "provider_id": "lloyds"
+                    },
+                    "update_timestamp": "2022-08-01T10:00:00Z"
+                }],
+                "status": "Succeeded"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/data/v1/accounts/account-id/balance"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [{
+                    "currency": "GBP",
+                    "available": 1161.2,
+                    "current": 1161.2,
+                    "overdraft": 1000,
+                    "update_timestamp": "2022-08-01T10:00:00Z"
+                }],
+                "status": "Succeeded"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let accounts = api.list_accounts().await.unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].account_id, "account-id");
+        assert_eq!(
+            accounts[0].account_number.sort_code.as_deref(),
+            Some("12-34-56")
+        );
+
+        let balance = api
+            .get_account_balance("account-id")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(balance.currency, Currency::Gbp);
+        assert_eq!(balance.available.to_minor(2), Some(116120));
+        assert_eq!(balance.overdraft.unwrap().to_minor(2), Some(100000));
+    }
+
+    #[tokio::test]
+    async fn get_account_balance_not_found() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = DataApi::new(Arc::new(inner));
+
+        Mock::given(method("GET"))
+            .and(path("/data/v1/accounts/non-existent/balance"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(api
+            .get_account_balance("non-existent")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn list_transactions() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = DataApi::new(Arc::new(inner));
+
+        Mock::given(method("GET"))
+            .and(path("/data/v1/accounts/account-id/transactions"))
+            .and(query_param("from", "2022-08-01T00:00:00Z"))
+            .and(query_param("to", "2022-09-01T00:00:00Z"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [{
+                    "transaction_id": "transaction-id",
+                    "timestamp": "2022-08-03T00:00:00Z",
+                    "description": "SAINSBURYS",
+                    "amount": -24.25,
+                    "currency": "GBP",
+                    "transaction_type": "DEBIT",
+                    "transaction_category": "PURCHASE",
+                    "transaction_classification": ["Shopping", "Groceries"],
+                    "merchant_name": "Sainsbury's",
+                    "running_balance": {
+                        "amount": 1161.2,
+                        "currency": "GBP"
+                    }
+                }],
+                "status": "Succeeded"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let transactions = api
+            .list_transactions(
+                "account-id",
+                Utc.with_ymd_and_hms(2022, 8, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 9, 1, 0, 0, 0).unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].amount.to_minor(2), Some(-2425));
+        assert_eq!(transactions[0].transaction_type, TransactionType::Debit);
+        assert_eq!(
+            transactions[0].merchant_name.as_deref(),
+            Some("Sainsbury's")
+        );
+    }
+}
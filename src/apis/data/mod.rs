@@ -0,0 +1,10 @@
+//! APIs and models related to the Data API, to access the accounts of a user
+//! who granted consent through the authorization code flow.
+//!
+//! This module is only available with the `data` feature.
+
+mod api;
+mod model;
+
+pub use api::DataApi;
+pub use model::*;
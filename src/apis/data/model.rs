@@ -0,0 +1,87 @@
+use crate::{amounts::MajorAmount, apis::payments::Currency};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Envelope of all the responses of the Data API.
+#[derive(Deserialize)]
+pub(crate) struct DataResponse<T> {
+    pub results: Vec<T>,
+}
+
+/// Bank account of the user.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Account {
+    pub account_id: String,
+    pub account_type: String,
+    pub display_name: String,
+    pub currency: Currency,
+    pub account_number: AccountNumber,
+    pub provider: DataProvider,
+    pub update_timestamp: DateTime<Utc>,
+}
+
+/// Identifiers of a bank account. Which ones are set depends on the country of the account.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct AccountNumber {
+    pub iban: Option<String>,
+    pub swift_bic: Option<String>,
+    pub number: Option<String>,
+    pub sort_code: Option<String>,
+}
+
+/// Bank holding an account.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct DataProvider {
+    pub provider_id: String,
+    pub display_name: Option<String>,
+    pub logo_uri: Option<String>,
+}
+
+/// Balance of a bank account.
+///
+/// Amounts are decimals in the major unit of the currency (e.g., pounds for GBP),
+/// as reported by the bank. They can be negative.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Balance {
+    pub currency: Currency,
+    pub available: MajorAmount,
+    pub current: MajorAmount,
+    pub overdraft: Option<MajorAmount>,
+    pub update_timestamp: DateTime<Utc>,
+}
+
+/// Transaction of a bank account.
+///
+/// Amounts are decimals in the major unit of the currency (e.g., pounds for GBP),
+/// as reported by the bank. Debits are negative.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Transaction {
+    pub transaction_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub description: String,
+    pub amount: MajorAmount,
+    pub currency: Currency,
+    pub transaction_type: TransactionType,
+    pub transaction_category: String,
+    #[serde(default)]
+    pub transaction_classification: Vec<String>,
+    pub merchant_name: Option<String>,
+    pub running_balance: Option<RunningBalance>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TransactionType {
+    Debit,
+    Credit,
+    /// Transaction type not supported by this version of the library.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Balance of the account right after a transaction, when reported by the bank.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct RunningBalance {
+    pub amount: MajorAmount,
+    pub currency: Currency,
+}
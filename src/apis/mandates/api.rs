@@ -0,0 +1,443 @@
+use crate::{
+    apis::{
+        mandates::{CreateMandateRequest, CreateMandateResponse, Mandate},
+        payments::{StartAuthorizationFlowRequest, StartAuthorizationFlowResponse},
+        TrueLayerClientInner,
+    },
+    common::IDEMPOTENCY_KEY_HEADER,
+    request::{ApiRequest, RequestOptions},
+    Error,
+};
+use serde_json::json;
+use std::sync::Arc;
+use urlencoding::encode;
+use uuid::Uuid;
+
+/// TrueLayer mandates APIs client, to set up Variable Recurring Payments (VRP).
+#[derive(Clone, Debug)]
+pub struct MandatesApi {
+    inner: Arc<TrueLayerClientInner>,
+}
+
+impl MandatesApi {
+    pub(crate) fn new(inner: Arc<TrueLayerClientInner>) -> Self {
+        Self { inner }
+    }
+
+    /// Creates a new mandate.
+    ///
+    /// A new random idempotency key is generated for the request, unless one is set on the
+    /// returned [`ApiRequest`] with [`idempotency_key`](ApiRequest::idempotency_key).
+    pub fn create<'a>(
+        &'a self,
+        create_mandate_request: &'a CreateMandateRequest,
+    ) -> ApiRequest<'a, CreateMandateResponse> {
+        ApiRequest::new(move |options| self.create_with_options(create_mandate_request, options))
+    }
+
+    /// Creates a new mandate using the given idempotency key.
+    ///
+    /// Makes the request safe to retry, see [`ApiRequest::idempotency_key`](crate::request::ApiRequest::idempotency_key).
+    pub async fn create_with_idempotency_key(
+        &self,
+        create_mandate_request: &CreateMandateRequest,
+        idempotency_key: &str,
+    ) -> Result<CreateMandateResponse, Error> {
+        self.create(create_mandate_request)
+            .idempotency_key(idempotency_key)
+            .await
+    }
+
+    /// Creates a new mandate with the given options.
+    ///
+    /// Equivalent to configuring the [`ApiRequest`] returned by [`create`](Self::create).
+    #[tracing::instrument(
+        name = "Create Mandate",
+        skip(self, create_mandate_request, options),
+        fields(
+            mandate_type = ?create_mandate_request.mandate.r#type,
+            currency = %create_mandate_request.currency,
+            correlation_id = options.correlation_id.as_deref(),
+        )
+    )]
+    pub async fn create_with_options(
+        &self,
+        create_mandate_request: &CreateMandateRequest,
+        mut options: RequestOptions,
+    ) -> Result<CreateMandateResponse, Error> {
+        let res = options
+            .apply(
+                self.inner.client.post(
+                    self.inner
+                        .environment
+                        .payments_url()
+                        .join("/mandates")
+                        .unwrap(),
+                ),
+            )?
+            .json(create_mandate_request)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        Ok(res)
+    }
+
+    /// Gets the details of an existing mandate.
+    ///
+    /// If there's no mandate with the given id, `None` is returned.
+    #[tracing::instrument(name = "Get Mandate by ID", skip(self))]
+    pub async fn get_by_id(&self, id: &str) -> Result<Option<Mandate>, Error> {
+        let res = self
+            .inner
+            .client
+            .get(
+                self.inner
+                    .environment
+                    .payments_url()
+                    .join(&format!("/mandates/{}", encode(id)))
+                    .unwrap(),
+            )
+            .send()
+            .await
+            .map_err(Error::from);
+
+        // Return `None` if the server returned 404
+        let mandate = match res {
+            Ok(body) => Some(body.json().await?),
+            Err(Error::ApiError(api_error)) if api_error.status == 404 => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(mandate)
+    }
+
+    /// Revokes a mandate, so that no more payments can be made on it.
+    #[tracing::instrument(name = "Revoke Mandate", skip(self))]
+    pub async fn revoke(&self, id: &str) -> Result<(), Error> {
+        // Generate a new random idempotency-key for this request
+        let idempotency_key = Uuid::new_v4();
+
+        self.inner
+            .client
+            .post(
+                self.inner
+                    .environment
+                    .payments_url()
+                    .join(&format!("/mandates/{}/revoke", encode(id)))
+                    .unwrap(),
+            )
+            .json(&json!({}))
+            .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Starts the authorization flow for a mandate.
+    #[tracing::instrument(name = "Start Mandate Authorization Flow", skip(self, req))]
+    pub async fn start_authorization_flow(
+        &self,
+        mandate_id: &str,
+        req: &StartAuthorizationFlowRequest,
+    ) -> Result<StartAuthorizationFlowResponse, Error> {
+        // Generate a new random idempotency-key for this request
+        let idempotency_key = Uuid::new_v4();
+
+        let res = self
+            .inner
+            .client
+            .post(
+                self.inner
+                    .environment
+                    .payments_url()
+                    .join(&format!(
+                        "/mandates/{}/authorization-flow",
+                        encode(mandate_id)
+                    ))
+                    .unwrap(),
+            )
+            .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
+            .json(req)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apis::{
+            auth::Credentials,
+            mandates::{
+                Constraints, MandateDetail, MandateProviderSelection, MandateStatus, MandateType,
+                PeriodAlignment, PeriodicLimit, PeriodicLimits, RevocationSource,
+            },
+            payments::{
+                AuthorizationFlowNextAction, AuthorizationFlowResponseStatus, Beneficiary,
+                CreatePaymentUserRequest, Currency, ProviderSelectionSupported, RedirectSupported,
+            },
+        },
+        authenticator::Authenticator,
+        client::Environment,
+        middlewares::error_handling::ErrorHandlingMiddleware,
+        pollable::PollOptions,
+    };
+    use chrono::{TimeZone, Utc};
+    use url::Url;
+    use wiremock::{
+        matchers::{body_partial_json, header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    async fn mock_client_and_server() -> (MandatesApi, MockServer) {
+        let mock_server = MockServer::start().await;
+
+        let credentials = Credentials::ClientCredentials {
+            client_id: "client-id".into(),
+            client_secret: "client-secret".into(),
+            scope: "mock".into(),
+        };
+
+        let authenticator = Authenticator::new(
+            reqwest::Client::new().into(),
+            Url::parse(&mock_server.uri()).unwrap(),
+            credentials,
+        );
+
+        let inner = TrueLayerClientInner {
+            client: reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+                .with(ErrorHandlingMiddleware)
+                .build(),
+            authenticator,
+            environment: Environment::from_single_url(&Url::parse(&mock_server.uri()).unwrap()),
+            deprecations: Default::default(),
+            rate_limits: Default::default(),
+            poll_options: PollOptions::default().into_dyn(),
+            poll_budget: None,
+            flow_cache: Default::default(),
+            flow_events: Default::default(),
+            idempotency_ledger: Default::default(),
+            payout_guardrails: None,
+            request_traces: Default::default(),
+            implicit_behaviors: true,
+        };
+
+        (MandatesApi::new(Arc::new(inner)), mock_server)
+    }
+
+    #[tokio::test]
+    async fn create() {
+        let (api, mock_server) = mock_client_and_server().await;
+
+        Mock::given(method("POST"))
+            .and(path("/mandates"))
+            .and(header_exists(IDEMPOTENCY_KEY_HEADER))
+            .and(body_partial_json(json!({
+                "mandate": {
+                    "type": "sweeping",
+                    "provider_selection": {
+                        "type": "preselected",
+                        "provider_id": "provider-id"
+                    },
+                    "beneficiary": {
+                        "type": "merchant_account",
+                        "merchant_account_id": "merchant-account-id"
+                    }
+                },
+                "currency": "GBP",
+                "user": {
+                    "id": "user-id"
+                },
+                "constraints": {
+                    "maximum_individual_amount": 1000,
+                    "periodic_limits": {
+                        "month": {
+                            "maximum_amount": 5000,
+                            "period_alignment": "calendar"
+                        }
+                    }
+                }
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": "mandate-id",
+                "resource_token": "resource-token",
+                "user": {
+                    "id": "user-id"
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let res = api
+            .create(&CreateMandateRequest {
+                mandate: MandateDetail {
+                    r#type: MandateType::Sweeping,
+                    provider_selection: MandateProviderSelection::Preselected {
+                        provider_id: "provider-id".to_string(),
+                        remitter: None,
+                    },
+                    beneficiary: Beneficiary::MerchantAccount {
+                        merchant_account_id: "merchant-account-id".to_string(),
+                        account_holder_name: None,
+                    },
+                    reference: None,
+                },
+                currency: Currency::Gbp,
+                user: CreatePaymentUserRequest::ExistingUser {
+                    id: "user-id".to_string(),
+                },
+                constraints: Constraints {
+                    valid_from: None,
+                    valid_to: None,
+                    maximum_individual_amount: 1000,
+                    periodic_limits: PeriodicLimits {
+                        month: Some(PeriodicLimit {
+                            maximum_amount: 5000,
+                            period_alignment: PeriodAlignment::Calendar,
+                        }),
+                        ..Default::default()
+                    },
+                },
+                metadata: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(res.id, "mandate-id");
+        assert_eq!(res.resource_token.expose_secret(), "resource-token");
+        assert_eq!(res.user.id, "user-id");
+    }
+
+    #[tokio::test]
+    async fn get_by_id() {
+        let (api, mock_server) = mock_client_and_server().await;
+
+        Mock::given(method("GET"))
+            .and(path("/mandates/mandate-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "mandate-id",
+                "currency": "GBP",
+                "mandate": {
+                    "type": "commercial",
+                    "provider_selection": {
+                        "type": "user_selected",
+                        "provider_id": "provider-id"
+                    },
+                    "beneficiary": {
+                        "type": "merchant_account",
+                        "merchant_account_id": "merchant-account-id"
+                    },
+                    "reference": "some-reference"
+                },
+                "constraints": {
+                    "maximum_individual_amount": 1000
+                },
+                "user": {
+                    "id": "user-id"
+                },
+                "created_at": "2022-04-01T00:00:00Z",
+                "status": "revoked",
+                "revoked_at": "2022-04-02T00:00:00Z",
+                "revocation_source": "provider"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/mandates/non-existent"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let mandate = api.get_by_id("mandate-id").await.unwrap().unwrap();
+
+        assert_eq!(mandate.mandate.r#type, MandateType::Commercial);
+        assert_eq!(mandate.constraints.maximum_individual_amount, 1000);
+        assert!(mandate.constraints.periodic_limits.is_empty());
+        assert_eq!(
+            mandate.status,
+            MandateStatus::Revoked {
+                revoked_at: Utc.with_ymd_and_hms(2022, 4, 2, 0, 0, 0).unwrap(),
+                revocation_source: RevocationSource::Provider,
+                authorization_flow: None,
+            }
+        );
+        assert!(api.get_by_id("non-existent").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn revoke() {
+        let (api, mock_server) = mock_client_and_server().await;
+
+        Mock::given(method("POST"))
+            .and(path("/mandates/mandate-id/revoke"))
+            .and(header_exists(IDEMPOTENCY_KEY_HEADER))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        api.revoke("mandate-id").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn start_authorization_flow() {
+        let (api, mock_server) = mock_client_and_server().await;
+
+        Mock::given(method("POST"))
+            .and(path("/mandates/mandate-id/authorization-flow"))
+            .and(header_exists(IDEMPOTENCY_KEY_HEADER))
+            .and(body_partial_json(json!({
+                "provider_selection": {},
+                "redirect": {
+                    "return_uri": "https://my.return.uri"
+                }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "status": "authorizing",
+                "authorization_flow": {
+                    "actions": {
+                        "next": {
+                            "type": "redirect",
+                            "uri": "https://my.provider.uri"
+                        }
+                    }
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let res = api
+            .start_authorization_flow(
+                "mandate-id",
+                &StartAuthorizationFlowRequest {
+                    provider_selection: Some(ProviderSelectionSupported {}),
+                    redirect: Some(RedirectSupported {
+                        return_uri: "https://my.return.uri".to_string(),
+                        direct_return_uri: None,
+                    }),
+                    consent: None,
+                    form: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(res.status, AuthorizationFlowResponseStatus::Authorizing);
+        assert!(matches!(
+            res.authorization_flow.unwrap().actions.unwrap().next,
+            AuthorizationFlowNextAction::Redirect { .. }
+        ));
+    }
+}
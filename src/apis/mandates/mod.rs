@@ -0,0 +1,7 @@
+//! APIs and models related to mandates for Variable Recurring Payments (VRP).
+
+mod api;
+mod model;
+
+pub use api::MandatesApi;
+pub use model::*;
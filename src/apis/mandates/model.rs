@@ -0,0 +1,293 @@
+use crate::{
+    apis::{
+        auth::Token,
+        payments::{
+            AuthorizationFlow, Beneficiary, CreatePaymentUserRequest, CreatePaymentUserResponse,
+            Currency, FailureStage, ProviderFilter, Remitter, User,
+        },
+    },
+    pollable::{HasId, HasStatus, IsInTerminalState},
+    Error, Pollable, TrueLayerClient,
+};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct CreateMandateRequest {
+    pub mandate: MandateDetail,
+    pub currency: Currency,
+    pub user: CreatePaymentUserRequest,
+    pub constraints: Constraints,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct MandateDetail {
+    pub r#type: MandateType,
+    pub provider_selection: MandateProviderSelection,
+    pub beneficiary: Beneficiary,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum MandateType {
+    /// Mandate to move funds between accounts owned by the same user.
+    Sweeping,
+    /// Mandate for recurring payments to a merchant.
+    Commercial,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MandateProviderSelection {
+    UserSelected {
+        filter: Option<ProviderFilter>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        provider_id: Option<String>,
+    },
+    Preselected {
+        provider_id: String,
+        remitter: Option<Remitter>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateMandateResponse {
+    pub id: String,
+    pub resource_token: Token,
+    pub user: CreatePaymentUserResponse,
+}
+
+#[async_trait]
+impl Pollable for CreateMandateResponse {
+    type Output = Mandate;
+
+    async fn poll_once(&self, tl: &TrueLayerClient) -> Result<Self::Output, Error> {
+        tl.mandates
+            .get_by_id(&self.id)
+            .await
+            .transpose()
+            .unwrap_or_else(|| Err(Error::Other(anyhow!("Mandate returned 404 while polling"))))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Mandate {
+    pub id: String,
+    pub currency: Currency,
+    pub mandate: MandateDetail,
+    pub constraints: Constraints,
+    pub user: Option<User>,
+    pub created_at: DateTime<Utc>,
+    pub metadata: Option<HashMap<String, String>>,
+    #[serde(flatten)]
+    pub status: MandateStatus,
+}
+
+#[async_trait]
+impl Pollable for Mandate {
+    type Output = Mandate;
+
+    async fn poll_once(&self, tl: &TrueLayerClient) -> Result<Self::Output, Error> {
+        tl.mandates
+            .get_by_id(&self.id)
+            .await
+            .transpose()
+            .unwrap_or_else(|| Err(Error::Other(anyhow!("Mandate returned 404 while polling"))))
+    }
+}
+
+impl IsInTerminalState for Mandate {
+    /// A mandate is considered to be in a terminal state if it is `Authorized`, `Failed` or `Revoked`.
+    ///
+    /// Note that an `Authorized` mandate can still be revoked later on.
+    fn is_in_terminal_state(&self) -> bool {
+        matches!(
+            self.status,
+            MandateStatus::Authorized { .. }
+                | MandateStatus::Failed { .. }
+                | MandateStatus::Revoked { .. }
+        )
+    }
+}
+
+impl HasId for Mandate {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl HasStatus for Mandate {
+    fn has_same_status(&self, other: &Self) -> bool {
+        std::mem::discriminant(&self.status) == std::mem::discriminant(&other.status)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum MandateStatus {
+    AuthorizationRequired,
+    Authorizing {
+        authorization_flow: AuthorizationFlow,
+    },
+    Authorized {
+        authorized_at: DateTime<Utc>,
+        authorization_flow: Option<AuthorizationFlow>,
+    },
+    Failed {
+        failed_at: DateTime<Utc>,
+        failure_stage: FailureStage,
+        failure_reason: String,
+        authorization_flow: Option<AuthorizationFlow>,
+    },
+    Revoked {
+        revoked_at: DateTime<Utc>,
+        revocation_source: RevocationSource,
+        authorization_flow: Option<AuthorizationFlow>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationSource {
+    /// Revoked by the client through the API.
+    Client,
+    /// Revoked by the user directly from their bank.
+    Provider,
+    /// Any other source not yet known to this library.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Limits within which payments can be made on a mandate.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Constraints {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub valid_from: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub valid_to: Option<DateTime<Utc>>,
+    #[serde(deserialize_with = "crate::amounts::deserialize_minor")]
+    pub maximum_individual_amount: u64,
+    #[serde(default, skip_serializing_if = "PeriodicLimits::is_empty")]
+    pub periodic_limits: PeriodicLimits,
+}
+
+/// Maximum cumulative amounts which can be paid on a mandate over each [`Period`](crate::apis::mandates::Period).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct PeriodicLimits {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day: Option<PeriodicLimit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week: Option<PeriodicLimit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fortnight: Option<PeriodicLimit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub month: Option<PeriodicLimit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub half_year: Option<PeriodicLimit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<PeriodicLimit>,
+}
+
+impl PeriodicLimits {
+    /// Returns the limit configured for the given period, if any.
+    pub fn get(&self, period: Period) -> Option<&PeriodicLimit> {
+        match period {
+            Period::Day => self.day.as_ref(),
+            Period::Week => self.week.as_ref(),
+            Period::Fortnight => self.fortnight.as_ref(),
+            Period::Month => self.month.as_ref(),
+            Period::HalfYear => self.half_year.as_ref(),
+            Period::Year => self.year.as_ref(),
+        }
+    }
+
+    /// Sets the limit for the given period.
+    pub fn set(&mut self, period: Period, limit: Option<PeriodicLimit>) {
+        let slot = match period {
+            Period::Day => &mut self.day,
+            Period::Week => &mut self.week,
+            Period::Fortnight => &mut self.fortnight,
+            Period::Month => &mut self.month,
+            Period::HalfYear => &mut self.half_year,
+            Period::Year => &mut self.year,
+        };
+        *slot = limit;
+    }
+
+    /// Iterates over all the configured limits, from the shortest period to the longest one.
+    pub fn iter(&self) -> impl Iterator<Item = (Period, &PeriodicLimit)> {
+        Period::ALL
+            .into_iter()
+            .filter_map(move |period| self.get(period).map(|limit| (period, limit)))
+    }
+
+    /// Returns `true` if no limit is configured.
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct PeriodicLimit {
+    #[serde(deserialize_with = "crate::amounts::deserialize_minor")]
+    pub maximum_amount: u64,
+    pub period_alignment: PeriodAlignment,
+}
+
+/// How the boundaries of a [`Period`](crate::apis::mandates::Period) are computed.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PeriodAlignment {
+    /// Periods start from the date the mandate was authorized.
+    Consent,
+    /// Periods are aligned to the calendar (e.g., a month starts on the 1st).
+    Calendar,
+}
+
+/// Period over which a limit or a recurring operation applies.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    Day,
+    Week,
+    Fortnight,
+    Month,
+    HalfYear,
+    Year,
+}
+
+impl Period {
+    /// All the periods, from the shortest to the longest.
+    pub const ALL: [Period; 6] = [
+        Period::Day,
+        Period::Week,
+        Period::Fortnight,
+        Period::Month,
+        Period::HalfYear,
+        Period::Year,
+    ];
+
+    /// Returns the exact duration of this period, or `None` for periods whose length
+    /// depends on the calendar (months, half years and years).
+    ///
+    /// ```
+    /// # use chrono::Duration;
+    /// # use truelayer_rust::apis::mandates::Period;
+    /// assert_eq!(Period::Fortnight.fixed_duration(), Some(Duration::days(14)));
+    /// assert_eq!(Period::Month.fixed_duration(), None);
+    /// ```
+    pub fn fixed_duration(&self) -> Option<Duration> {
+        match self {
+            Period::Day => Some(Duration::days(1)),
+            Period::Week => Some(Duration::weeks(1)),
+            Period::Fortnight => Some(Duration::weeks(2)),
+            Period::Month | Period::HalfYear | Period::Year => None,
+        }
+    }
+}
@@ -1,15 +1,18 @@
 use crate::{
     apis::{
         merchant_accounts::{
-            ListPaymentSourcesRequest, ListTransactionsRequest, MerchantAccount,
-            SetupSweepingRequest, SweepingSettings, Transaction,
+            Balances, ListPaymentSourcesRequest, ListTransactionsRequest, MerchantAccount,
+            SetupSweepingRequest, SweepingSettings, Transaction, TransactionsPage,
         },
         payments::PaymentSource,
         TrueLayerClientInner,
     },
     common::IDEMPOTENCY_KEY_HEADER,
+    pagination::{paginate, Paginated, PaginatedListResponse},
     Error,
 };
+use chrono::Utc;
+use futures::{stream::BoxStream, TryStreamExt};
 use serde::Deserialize;
 use std::sync::Arc;
 use urlencoding::encode;
@@ -47,6 +50,21 @@ impl MerchantAccountsApi {
         Ok(res.items)
     }
 
+    /// Gets the balances of all the merchant accounts, e.g. to compute the cash position
+    /// in each currency with [`Balances::by_currency`].
+    #[tracing::instrument(name = "Get Merchant Account Balances", skip(self))]
+    pub async fn get_balances(&self) -> Result<Balances, Error> {
+        let merchant_accounts = self.list().await?;
+        let updated_at = Utc::now();
+
+        Ok(Balances {
+            accounts: merchant_accounts
+                .iter()
+                .map(|account| (account.id.clone(), account.balance(updated_at)))
+                .collect(),
+        })
+    }
+
     /// Gets the details of an existing merchant account.
     ///
     /// If there's no merchant account with the given id, `None` is returned.
@@ -110,7 +128,7 @@ impl MerchantAccountsApi {
                     .payments_url()
                     .join(&format!(
                         "/merchant-accounts/{}/sweeping",
-                        merchant_account_id
+                        encode(merchant_account_id)
                     ))
                     .unwrap(),
             )
@@ -136,7 +154,7 @@ impl MerchantAccountsApi {
                     .payments_url()
                     .join(&format!(
                         "/merchant-accounts/{}/sweeping",
-                        merchant_account_id
+                        encode(merchant_account_id)
                     ))
                     .unwrap(),
             )
@@ -183,14 +201,51 @@ impl MerchantAccountsApi {
         Ok(settings)
     }
 
-    /// Gets the transactions of a single merchant account.
+    /// Gets all the transactions of a single merchant account in the given time range,
+    /// following the pagination cursors until the last page.
+    ///
+    /// To fetch pages one at a time, use [`list_transactions_page`](Self::list_transactions_page),
+    /// or use [`list_transactions_stream`](Self::list_transactions_stream) to process transactions
+    /// as they are fetched.
     #[tracing::instrument(name = "List Transactions", skip(self, request))]
     pub async fn list_transactions(
         &self,
         merchant_account_id: &str,
         request: &ListTransactionsRequest,
     ) -> Result<Vec<Transaction>, Error> {
-        let res: ListResponse<_> = self
+        self.list_transactions_stream(merchant_account_id, request)
+            .try_collect()
+            .await
+    }
+
+    /// Returns a stream of all the transactions of a single merchant account in the given time range,
+    /// fetching the following pages as the stream is consumed.
+    pub fn list_transactions_stream<'a>(
+        &'a self,
+        merchant_account_id: &'a str,
+        request: &ListTransactionsRequest,
+    ) -> BoxStream<'a, Result<Transaction, Error>> {
+        let request = request.clone();
+        paginate(move |cursor| {
+            let request = ListTransactionsRequest {
+                cursor: cursor.or_else(|| request.cursor.clone()),
+                ..request.clone()
+            };
+            async move {
+                self.list_transactions_page(merchant_account_id, &request)
+                    .await
+            }
+        })
+    }
+
+    /// Gets a single page of the transactions of a merchant account.
+    #[tracing::instrument(name = "List Transactions Page", skip(self, request))]
+    pub async fn list_transactions_page(
+        &self,
+        merchant_account_id: &str,
+        request: &ListTransactionsRequest,
+    ) -> Result<TransactionsPage, Error> {
+        let res: PaginatedListResponse<_> = self
             .inner
             .client
             .get(
@@ -209,10 +264,11 @@ impl MerchantAccountsApi {
             .json()
             .await?;
 
-        Ok(res.items)
+        Ok(res.into())
     }
 
-    /// Gets the payment sources from which the merchant account has received payment.
+    /// Gets the payment sources from which the merchant account has received payment,
+    /// following the pagination cursors until the last page.
     #[tracing::instrument(
         name = "List Payment Sources",
         skip(self, request),
@@ -225,7 +281,35 @@ impl MerchantAccountsApi {
         merchant_account_id: &str,
         request: &ListPaymentSourcesRequest,
     ) -> Result<Vec<PaymentSource>, Error> {
-        let res: ListResponse<_> = self
+        self.list_payment_sources_stream(merchant_account_id, request)
+            .try_collect()
+            .await
+    }
+
+    /// Returns a stream of the payment sources from which the merchant account has received payment,
+    /// fetching the following pages as the stream is consumed.
+    pub fn list_payment_sources_stream<'a>(
+        &'a self,
+        merchant_account_id: &'a str,
+        request: &ListPaymentSourcesRequest,
+    ) -> BoxStream<'a, Result<PaymentSource, Error>> {
+        let request = request.clone();
+        paginate(move |cursor| {
+            let request = request.clone();
+            async move {
+                self.list_payment_sources_page(merchant_account_id, &request, cursor)
+                    .await
+            }
+        })
+    }
+
+    async fn list_payment_sources_page(
+        &self,
+        merchant_account_id: &str,
+        request: &ListPaymentSourcesRequest,
+        cursor: Option<String>,
+    ) -> Result<Paginated<PaymentSource>, Error> {
+        let mut req = self
             .inner
             .client
             .get(
@@ -238,13 +322,14 @@ impl MerchantAccountsApi {
                     ))
                     .unwrap(),
             )
-            .query(request)
-            .send()
-            .await?
-            .json()
-            .await?;
+            .query(request);
+        if let Some(cursor) = cursor {
+            req = req.query(&[("cursor", cursor)]);
+        }
 
-        Ok(res.items)
+        let res: PaginatedListResponse<_> = req.send().await?.json().await?;
+
+        Ok(res.into())
     }
 }
 
@@ -269,6 +354,7 @@ mod tests {
         authenticator::Authenticator,
         client::Environment,
         middlewares::error_handling::ErrorHandlingMiddleware,
+        pollable::PollOptions,
     };
     use chrono::{SecondsFormat, Utc};
     use reqwest::Url;
@@ -299,6 +385,16 @@ mod tests {
                 .build(),
             authenticator,
             environment: Environment::from_single_url(&Url::parse(&mock_server.uri()).unwrap()),
+            deprecations: Default::default(),
+            rate_limits: Default::default(),
+            poll_options: PollOptions::default().into_dyn(),
+            poll_budget: None,
+            flow_cache: Default::default(),
+            flow_events: Default::default(),
+            idempotency_ledger: Default::default(),
+            payout_guardrails: None,
+            request_traces: Default::default(),
+            implicit_behaviors: true,
         };
 
         (MerchantAccountsApi::new(Arc::new(inner)), mock_server)
@@ -368,6 +464,47 @@ mod tests {
         assert_eq!(merchant_accounts, vec![]);
     }
 
+    #[tokio::test]
+    async fn get_balances_aggregates_accounts_by_currency() {
+        let (api, mock_server) = mock_client_and_server().await;
+
+        let account = |id: &str, currency: &str, available: u64, current: u64| {
+            json!({
+                "id": id,
+                "currency": currency,
+                "account_identifiers": [],
+                "available_balance_in_minor": available,
+                "current_balance_in_minor": current,
+                "account_holder_name": "Mr. Holder"
+            })
+        };
+        Mock::given(method("GET"))
+            .and(path("/merchant-accounts"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [
+                    account("gbp-1", "GBP", 100, 200),
+                    account("gbp-2", "GBP", 10, 20),
+                    account("eur-1", "EUR", 1, 2)
+                ]
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let balances = api.get_balances().await.unwrap();
+
+        assert_eq!(balances.accounts.len(), 3);
+        assert_eq!(balances.accounts["gbp-2"].available_in_minor, 10);
+
+        let gbp = balances.total(&Currency::Gbp).unwrap();
+        assert_eq!(gbp.available_in_minor, 110);
+        assert_eq!(gbp.current_in_minor, 220);
+        let eur = balances.total(&Currency::Eur).unwrap();
+        assert_eq!(eur.available_in_minor, 1);
+        assert_eq!(eur.current_in_minor, 2);
+        assert!(balances.total(&Currency::Pln).is_none());
+    }
+
     #[tokio::test]
     async fn get_by_id() {
         let (api, mock_server) = mock_client_and_server().await;
@@ -409,6 +546,13 @@ mod tests {
                 account_holder_name: "Mr. Holder".to_string()
             })
         );
+
+        let merchant_account = merchant_account.unwrap();
+        assert_eq!(
+            merchant_account.sort_code_account_number(),
+            Some(("sort-code", "account-number"))
+        );
+        assert_eq!(merchant_account.iban(), None);
     }
 
     #[tokio::test]
@@ -798,6 +942,8 @@ mod tests {
                     from: now,
                     to: now,
                     r#type: None,
+                    cursor: None,
+                    limit: None,
                 },
             )
             .await
@@ -909,6 +1055,8 @@ mod tests {
                     from: now,
                     to: now,
                     r#type: None,
+                    cursor: None,
+                    limit: None,
                 },
             )
             .await
@@ -917,6 +1065,123 @@ mod tests {
         assert_eq!(transactions, vec![]);
     }
 
+    #[tokio::test]
+    async fn list_transactions_follows_pagination() {
+        let (api, mock_server) = mock_client_and_server().await;
+
+        let merchant_account_id = "merchant-account-id".to_string();
+        let now = Utc::now();
+        let now_str = now.to_rfc3339_opts(SecondsFormat::Millis, true);
+
+        // Second page, mounted first so that it takes precedence when the cursor is set
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/merchant-accounts/{}/transactions",
+                merchant_account_id
+            )))
+            .and(query_param("from", &now_str))
+            .and(query_param("to", &now_str))
+            .and(query_param("cursor", "next-page"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [
+                    {
+                        "id": "transaction-id-2",
+                        "currency": "GBP",
+                        "amount_in_minor": 50,
+                        "type": "refund",
+                        "status": "settled",
+                        "created_at": &now,
+                        "settled_at": &now,
+                        "beneficiary": {
+                            "type": "payment_source",
+                            "user_id": "payer-user-id",
+                            "payment_source_id": "payment-source-id",
+                            "reference": "refund-reference"
+                        },
+                        "context_code": "withdrawal",
+                        "refund_id": "refund-id",
+                        "payment_id": "payment-id"
+                    }
+                ],
+                "pagination": {}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // First page
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/merchant-accounts/{}/transactions",
+                merchant_account_id
+            )))
+            .and(query_param("from", &now_str))
+            .and(query_param("to", &now_str))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [
+                    {
+                        "id": "transaction-id-1",
+                        "currency": "GBP",
+                        "amount_in_minor": 100,
+                        "type": "payout",
+                        "status": "pending",
+                        "created_at": &now,
+                        "beneficiary": {
+                            "type": "payment_source",
+                            "user_id": "payout-user-id",
+                            "payment_source_id": "payment-source-id",
+                            "reference": "payout-reference"
+                        },
+                        "context_code": "internal",
+                        "payout_id": "payout-id"
+                    }
+                ],
+                "pagination": {
+                    "next_cursor": "next-page"
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let transactions = api
+            .list_transactions(
+                &merchant_account_id,
+                &ListTransactionsRequest {
+                    from: now,
+                    to: now,
+                    r#type: None,
+                    cursor: None,
+                    limit: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            transactions
+                .iter()
+                .map(|t| t.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["transaction-id-1", "transaction-id-2"]
+        );
+        assert_eq!(
+            transactions[1].r#type,
+            TransactionType::Refund {
+                status: TransactionPayoutStatus::Settled { settled_at: now },
+                created_at: now,
+                beneficiary: PayoutBeneficiary::PaymentSource {
+                    user_id: "payer-user-id".into(),
+                    payment_source_id: "payment-source-id".into(),
+                    reference: "refund-reference".into(),
+                },
+                context_code: TransactionPayoutContextCode::Withdrawal,
+                refund_id: "refund-id".into(),
+                payment_id: "payment-id".into(),
+            }
+        );
+    }
+
     #[tokio::test]
     async fn list_transactions_not_found() {
         let (api, mock_server) = mock_client_and_server().await;
@@ -944,6 +1209,8 @@ mod tests {
                     from: now,
                     to: now,
                     r#type: None,
+                    cursor: None,
+                    limit: None,
                 },
             )
             .await;
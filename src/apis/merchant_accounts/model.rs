@@ -1,22 +1,108 @@
-use crate::apis::{
-    payments::{AccountIdentifier, Currency, PaymentSource, Remitter},
-    payouts::PayoutBeneficiary,
+use crate::{
+    apis::{
+        mandates::Period,
+        payments::{AccountIdentifier, Currency, PaymentSource, Remitter},
+        payouts::PayoutBeneficiary,
+    },
+    pagination::Paginated,
 };
-use chrono::{DateTime, SecondsFormat, Utc};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct MerchantAccount {
     pub id: String,
     pub currency: Currency,
     pub account_identifiers: Vec<AccountIdentifier>,
+    #[serde(deserialize_with = "crate::amounts::deserialize_minor")]
     pub available_balance_in_minor: u64,
+    #[serde(deserialize_with = "crate::amounts::deserialize_minor")]
     pub current_balance_in_minor: u64,
     pub account_holder_name: String,
 }
 
+impl MerchantAccount {
+    /// Returns the IBAN of this merchant account, if it has one.
+    pub fn iban(&self) -> Option<&str> {
+        self.account_identifiers.iter().find_map(|id| match id {
+            AccountIdentifier::Iban { iban } => Some(iban.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Returns the sort code and account number of this merchant account, if it has them.
+    pub fn sort_code_account_number(&self) -> Option<(&str, &str)> {
+        self.account_identifiers.iter().find_map(|id| match id {
+            AccountIdentifier::SortCodeAccountNumber {
+                sort_code,
+                account_number,
+            } => Some((sort_code.as_str(), account_number.as_str())),
+            _ => None,
+        })
+    }
+
+    /// Returns the balance of this merchant account, as retrieved at `updated_at`.
+    pub fn balance(&self, updated_at: DateTime<Utc>) -> Balance {
+        Balance {
+            currency: self.currency.clone(),
+            available_in_minor: self.available_balance_in_minor,
+            current_in_minor: self.current_balance_in_minor,
+            updated_at,
+        }
+    }
+}
+
+/// Balance of a merchant account, or of all the merchant accounts in a currency.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Balance {
+    pub currency: Currency,
+    /// Funds which can be paid out right away.
+    pub available_in_minor: u64,
+    /// Funds in the account, including those not yet available, e.g. pending payouts.
+    pub current_in_minor: u64,
+    /// When the balance was retrieved from TrueLayer.
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Balances of all the merchant accounts of a client, returned by
+/// [`MerchantAccountsApi::get_balances`](crate::apis::merchant_accounts::MerchantAccountsApi::get_balances).
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Balances {
+    /// Balance of each merchant account, by merchant account id.
+    pub accounts: BTreeMap<String, Balance>,
+}
+
+impl Balances {
+    /// Returns the total balance of the merchant accounts in each currency.
+    ///
+    /// The `updated_at` of each total is the oldest of the balances summed up.
+    pub fn by_currency(&self) -> HashMap<Currency, Balance> {
+        let mut totals: HashMap<Currency, Balance> = HashMap::new();
+        for balance in self.accounts.values() {
+            match totals.get_mut(&balance.currency) {
+                Some(total) => {
+                    total.available_in_minor += balance.available_in_minor;
+                    total.current_in_minor += balance.current_in_minor;
+                    total.updated_at = total.updated_at.min(balance.updated_at);
+                }
+                None => {
+                    totals.insert(balance.currency.clone(), balance.clone());
+                }
+            }
+        }
+        totals
+    }
+
+    /// Returns the total balance of the merchant accounts in the given currency, if there are any.
+    pub fn total(&self, currency: &Currency) -> Option<Balance> {
+        self.by_currency().remove(currency)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct SetupSweepingRequest {
+    #[serde(deserialize_with = "crate::amounts::deserialize_minor")]
     pub max_amount_in_minor: u64,
     pub currency: Currency,
     pub frequency: SweepingFrequency,
@@ -30,14 +116,60 @@ pub enum SweepingFrequency {
     Fortnightly,
 }
 
+impl SweepingFrequency {
+    /// Returns the [`Period`](crate::apis::mandates::Period) between two consecutive sweeps.
+    pub fn period(&self) -> Period {
+        match self {
+            SweepingFrequency::Daily => Period::Day,
+            SweepingFrequency::Weekly => Period::Week,
+            SweepingFrequency::Fortnightly => Period::Fortnight,
+        }
+    }
+
+    /// Returns the time between two consecutive sweeps.
+    pub fn interval(&self) -> Duration {
+        self.period()
+            .fixed_duration()
+            .expect("Sweeping periods have a fixed duration")
+    }
+}
+
+impl TryFrom<Period> for SweepingFrequency {
+    type Error = Period;
+
+    /// Converts a [`Period`](crate::apis::mandates::Period) into a sweeping frequency,
+    /// returning the period back if sweeping does not support it.
+    fn try_from(period: Period) -> Result<Self, Self::Error> {
+        match period {
+            Period::Day => Ok(SweepingFrequency::Daily),
+            Period::Week => Ok(SweepingFrequency::Weekly),
+            Period::Fortnight => Ok(SweepingFrequency::Fortnightly),
+            period => Err(period),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct SweepingSettings {
+    #[serde(deserialize_with = "crate::amounts::deserialize_minor")]
     pub max_amount_in_minor: u64,
     pub currency: Currency,
     pub frequency: SweepingFrequency,
     pub destination: AccountIdentifier,
 }
 
+impl From<SweepingSettings> for SetupSweepingRequest {
+    /// Builds a request which reapplies the given settings, e.g. after tweaking one of its fields.
+    /// The destination is not part of the request, as it's pre-configured on the merchant account.
+    fn from(settings: SweepingSettings) -> Self {
+        Self {
+            max_amount_in_minor: settings.max_amount_in_minor,
+            currency: settings.currency,
+            frequency: settings.frequency,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct ListPaymentSourcesRequest {
     pub user_id: String,
@@ -50,19 +182,31 @@ pub struct ListTransactionsRequest {
     #[serde(serialize_with = "serialize_timestamp")]
     pub to: DateTime<Utc>,
     pub r#type: Option<TransactionTypeFilter>,
+    /// Cursor of the page to fetch, from [`Paginated::next_cursor`].
+    /// If `None`, the first page is fetched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Maximum number of transactions per page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
 }
 
+/// Single page of transactions of a merchant account.
+pub type TransactionsPage = Paginated<Transaction>;
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum TransactionTypeFilter {
     Payment,
     Payout,
+    Refund,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct Transaction {
     pub id: String,
     pub currency: Currency,
+    #[serde(deserialize_with = "crate::amounts::deserialize_minor")]
     pub amount_in_minor: u64,
     #[serde(flatten)]
     pub r#type: TransactionType,
@@ -90,6 +234,15 @@ pub enum TransactionType {
         context_code: TransactionPayoutContextCode,
         payout_id: String,
     },
+    Refund {
+        #[serde(flatten)]
+        status: TransactionPayoutStatus,
+        created_at: DateTime<Utc>,
+        beneficiary: PayoutBeneficiary,
+        context_code: TransactionPayoutContextCode,
+        refund_id: String,
+        payment_id: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
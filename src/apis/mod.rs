@@ -1,19 +1,49 @@
 //! Clients for the various TrueLayer APIs.
 
-use crate::{authenticator::Authenticator, client::Environment};
+use crate::{
+    apis::payments::flow::{FlowCache, FlowEventListeners},
+    authenticator::Authenticator,
+    client::Environment,
+    deprecations::DeprecationRegistry,
+    guardrails::Guardrails,
+    idempotency::IdempotencyLedger,
+    pollable::{DynRetryPolicy, PollBudget, PollOptions},
+    rate_limits::RateLimitRegistry,
+    support::RequestTraceLog,
+};
 use reqwest_middleware::ClientWithMiddleware;
-use std::fmt::{Debug, Formatter};
+use std::{
+    fmt::{Debug, Formatter},
+    sync::Arc,
+};
 
 pub mod auth;
+#[cfg(feature = "data")]
+pub mod data;
+pub mod mandates;
 pub mod merchant_accounts;
+pub mod payment_links;
 pub mod payments;
 pub mod payments_providers;
 pub mod payouts;
+pub mod verification;
+pub mod webhooks;
 
 pub(crate) struct TrueLayerClientInner {
     pub(crate) client: ClientWithMiddleware,
     pub(crate) authenticator: Authenticator,
     pub(crate) environment: Environment,
+    pub(crate) deprecations: DeprecationRegistry,
+    pub(crate) rate_limits: RateLimitRegistry,
+    pub(crate) poll_options: PollOptions<DynRetryPolicy>,
+    pub(crate) poll_budget: Option<PollBudget>,
+    pub(crate) flow_cache: Arc<FlowCache>,
+    pub(crate) flow_events: FlowEventListeners,
+    pub(crate) idempotency_ledger: Arc<IdempotencyLedger>,
+    pub(crate) payout_guardrails: Option<Arc<Guardrails>>,
+    pub(crate) request_traces: RequestTraceLog,
+    /// See [`with_implicit_behaviors`](crate::client::TrueLayerClientBuilder::with_implicit_behaviors).
+    pub(crate) implicit_behaviors: bool,
 }
 
 impl Debug for TrueLayerClientInner {
@@ -0,0 +1,400 @@
+use crate::{
+    apis::{
+        payment_links::{CreatePaymentLinkRequest, CreatePaymentLinkResponse, PaymentLink},
+        payments::Payment,
+        TrueLayerClientInner,
+    },
+    request::{ApiRequest, RequestOptions},
+    Error,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use urlencoding::encode;
+
+/// TrueLayer payment links APIs client.
+#[derive(Clone, Debug)]
+pub struct PaymentLinksApi {
+    inner: Arc<TrueLayerClientInner>,
+}
+
+impl PaymentLinksApi {
+    pub(crate) fn new(inner: Arc<TrueLayerClientInner>) -> Self {
+        Self { inner }
+    }
+
+    /// Creates a new payment link.
+    ///
+    /// A new random idempotency key is generated for the request, unless one is set on the
+    /// returned [`ApiRequest`] with [`idempotency_key`](ApiRequest::idempotency_key).
+    pub fn create<'a>(
+        &'a self,
+        create_payment_link_request: &'a CreatePaymentLinkRequest,
+    ) -> ApiRequest<'a, CreatePaymentLinkResponse> {
+        ApiRequest::new(move |options| {
+            self.create_with_options(create_payment_link_request, options)
+        })
+    }
+
+    /// Creates a new payment link using the given idempotency key.
+    ///
+    /// Makes the request safe to retry, see [`ApiRequest::idempotency_key`](crate::request::ApiRequest::idempotency_key).
+    pub async fn create_with_idempotency_key(
+        &self,
+        create_payment_link_request: &CreatePaymentLinkRequest,
+        idempotency_key: &str,
+    ) -> Result<CreatePaymentLinkResponse, Error> {
+        self.create(create_payment_link_request)
+            .idempotency_key(idempotency_key)
+            .await
+    }
+
+    /// Creates a new payment link with the given options.
+    ///
+    /// Equivalent to configuring the [`ApiRequest`] returned by [`create`](Self::create).
+    #[tracing::instrument(
+        name = "Create Payment Link",
+        skip(self, create_payment_link_request, options),
+        fields(
+            link_type = ?create_payment_link_request.r#type,
+            amount_in_minor = create_payment_link_request.payment_configuration.amount_in_minor,
+            currency = % create_payment_link_request.payment_configuration.currency,
+            correlation_id = options.correlation_id.as_deref(),
+        )
+    )]
+    pub async fn create_with_options(
+        &self,
+        create_payment_link_request: &CreatePaymentLinkRequest,
+        mut options: RequestOptions,
+    ) -> Result<CreatePaymentLinkResponse, Error> {
+        let res = options
+            .apply(
+                self.inner.client.post(
+                    self.inner
+                        .environment
+                        .payments_url()
+                        .join("/payment-links")
+                        .unwrap(),
+                ),
+            )?
+            .json(create_payment_link_request)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        Ok(res)
+    }
+
+    /// Gets the details of an existing payment link.
+    ///
+    /// If there's no payment link with the given id, `None` is returned.
+    #[tracing::instrument(name = "Get Payment Link by ID", skip(self))]
+    pub async fn get_by_id(&self, id: &str) -> Result<Option<PaymentLink>, Error> {
+        let res = self
+            .inner
+            .client
+            .get(
+                self.inner
+                    .environment
+                    .payments_url()
+                    .join(&format!("/payment-links/{}", encode(id)))
+                    .unwrap(),
+            )
+            .send()
+            .await
+            .map_err(Error::from);
+
+        // Return `None` if the server returned 404
+        let payment_link = match res {
+            Ok(body) => Some(body.json().await?),
+            Err(Error::ApiError(api_error)) if api_error.status == 404 => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(payment_link)
+    }
+
+    /// Lists the payments made through the given payment link.
+    #[tracing::instrument(name = "List Payment Link Payments", skip(self))]
+    pub async fn list_payments_for_link(&self, id: &str) -> Result<Vec<Payment>, Error> {
+        let res: ListResponse<_> = self
+            .inner
+            .client
+            .get(
+                self.inner
+                    .environment
+                    .payments_url()
+                    .join(&format!("/payment-links/{}/payments", encode(id)))
+                    .unwrap(),
+            )
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        Ok(res.items)
+    }
+}
+
+#[derive(Deserialize)]
+struct ListResponse<T> {
+    pub items: Vec<T>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apis::{
+            auth::Credentials,
+            payment_links::{PaymentLinkDisablementReason, PaymentLinkStatus, PaymentLinkType},
+            payments::{
+                Beneficiary, CreatePaymentRequest, CreatePaymentUserRequest, Currency,
+                PaymentMethodRequest, PaymentStatus, ProviderSelectionRequest,
+            },
+        },
+        authenticator::Authenticator,
+        client::Environment,
+        common::IDEMPOTENCY_KEY_HEADER,
+        middlewares::error_handling::ErrorHandlingMiddleware,
+        pollable::PollOptions,
+    };
+    use chrono::{Duration, TimeZone, Utc};
+    use serde_json::json;
+    use url::Url;
+    use wiremock::{
+        matchers::{body_partial_json, header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    async fn mock_client_and_server() -> (TrueLayerClientInner, MockServer) {
+        let mock_server = MockServer::start().await;
+
+        let credentials = Credentials::ClientCredentials {
+            client_id: "client-id".into(),
+            client_secret: "client-secret".into(),
+            scope: "mock".into(),
+        };
+
+        let authenticator = Authenticator::new(
+            reqwest::Client::new().into(),
+            Url::parse(&mock_server.uri()).unwrap(),
+            credentials,
+        );
+
+        let inner = TrueLayerClientInner {
+            client: reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+                .with(ErrorHandlingMiddleware)
+                .build(),
+            authenticator,
+            environment: Environment::from_single_url(&Url::parse(&mock_server.uri()).unwrap()),
+            deprecations: Default::default(),
+            rate_limits: Default::default(),
+            poll_options: PollOptions::default().into_dyn(),
+            poll_budget: None,
+            flow_cache: Default::default(),
+            flow_events: Default::default(),
+            idempotency_ledger: Default::default(),
+            payout_guardrails: None,
+            request_traces: Default::default(),
+            implicit_behaviors: true,
+        };
+
+        (inner, mock_server)
+    }
+
+    fn payment_configuration_json() -> serde_json::Value {
+        json!({
+            "amount_in_minor": 100,
+            "currency": "GBP",
+            "payment_method": {
+                "type": "bank_transfer",
+                "provider_selection": {
+                    "type": "user_selected"
+                },
+                "beneficiary": {
+                    "type": "merchant_account",
+                    "merchant_account_id": "merchant-account-id"
+                }
+            },
+            "user": {
+                "id": "user-id"
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn create() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentLinksApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path("/payment-links"))
+            .and(header_exists(IDEMPOTENCY_KEY_HEADER))
+            .and(body_partial_json(json!({
+                "type": "single_use",
+                "expires_at": "2022-04-02T00:00:00Z",
+                "reference": "some-reference",
+                "payment_configuration": payment_configuration_json()
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payment-link-id",
+                "uri": "https://payment.truelayer-sandbox.com/link/payment-link-id"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let res = api
+            .create(&CreatePaymentLinkRequest {
+                r#type: PaymentLinkType::SingleUse,
+                expires_at: Some(Utc.with_ymd_and_hms(2022, 4, 2, 0, 0, 0).unwrap()),
+                reference: Some("some-reference".to_string()),
+                return_uri: None,
+                payment_configuration: CreatePaymentRequest {
+                    amount_in_minor: 100,
+                    currency: Currency::Gbp,
+                    payment_method: PaymentMethodRequest::BankTransfer {
+                        provider_selection: ProviderSelectionRequest::UserSelected {
+                            filter: None,
+                            scheme_selection: None,
+                        },
+                        beneficiary: Beneficiary::MerchantAccount {
+                            merchant_account_id: "merchant-account-id".to_string(),
+                            account_holder_name: None,
+                        },
+                    },
+                    user: CreatePaymentUserRequest::ExistingUser {
+                        id: "user-id".to_string(),
+                    },
+                    metadata: None,
+                    retry: None,
+                    related_products: None,
+                },
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(res.id, "payment-link-id");
+        assert_eq!(
+            res.uri,
+            "https://payment.truelayer-sandbox.com/link/payment-link-id"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_by_id_disabled() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentLinksApi::new(Arc::new(inner));
+
+        Mock::given(method("GET"))
+            .and(path("/payment-links/payment-link-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payment-link-id",
+                "type": "single_use",
+                "created_at": "2022-04-01T00:00:00Z",
+                "expires_at": "2022-04-02T00:00:00Z",
+                "payment_configuration": payment_configuration_json(),
+                "status": "disabled",
+                "disabled_at": "2022-04-01T12:00:00Z",
+                "disablement_reason": "single_use_limit_reached"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let link = api.get_by_id("payment-link-id").await.unwrap().unwrap();
+
+        assert_eq!(link.r#type, PaymentLinkType::SingleUse);
+        assert_eq!(
+            link.status,
+            PaymentLinkStatus::Disabled {
+                disabled_at: Utc.with_ymd_and_hms(2022, 4, 1, 12, 0, 0).unwrap(),
+                disablement_reason: PaymentLinkDisablementReason::SingleUseLimitReached,
+            }
+        );
+        assert!(!link.is_usable_at(link.created_at));
+    }
+
+    #[tokio::test]
+    async fn get_by_id_active_until_expiry() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentLinksApi::new(Arc::new(inner));
+
+        Mock::given(method("GET"))
+            .and(path("/payment-links/payment-link-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payment-link-id",
+                "type": "reusable",
+                "created_at": "2022-04-01T00:00:00Z",
+                "expires_at": "2022-04-02T00:00:00Z",
+                "payment_configuration": payment_configuration_json(),
+                "status": "active"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let link = api.get_by_id("payment-link-id").await.unwrap().unwrap();
+        let expires_at = link.expires_at.unwrap();
+
+        assert_eq!(link.status, PaymentLinkStatus::Active);
+        assert!(link.is_usable_at(expires_at - Duration::seconds(1)));
+        assert!(!link.is_usable_at(expires_at));
+    }
+
+    #[tokio::test]
+    async fn get_by_id_not_found() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentLinksApi::new(Arc::new(inner));
+
+        Mock::given(method("GET"))
+            .and(path("/payment-links/non-existent"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(api.get_by_id("non-existent").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn list_payments_for_link() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentLinksApi::new(Arc::new(inner));
+
+        Mock::given(method("GET"))
+            .and(path("/payment-links/payment-link-id/payments"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{
+                    "id": "payment-id",
+                    "amount_in_minor": 100,
+                    "currency": "GBP",
+                    "payment_method": {
+                        "type": "bank_transfer",
+                        "provider_selection": {
+                            "type": "user_selected"
+                        },
+                        "beneficiary": {
+                            "type": "merchant_account",
+                            "merchant_account_id": "merchant-account-id"
+                        }
+                    },
+                    "user": {
+                        "id": "user-id"
+                    },
+                    "created_at": "2022-04-01T00:00:00Z",
+                    "status": "authorization_required"
+                }]
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let payments = api.list_payments_for_link("payment-link-id").await.unwrap();
+
+        assert_eq!(payments.len(), 1);
+        assert_eq!(payments[0].id, "payment-id");
+        assert_eq!(payments[0].status, PaymentStatus::AuthorizationRequired);
+    }
+}
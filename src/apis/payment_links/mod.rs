@@ -0,0 +1,7 @@
+//! APIs and models related to payment links.
+
+mod api;
+mod model;
+
+pub use api::PaymentLinksApi;
+pub use model::*;
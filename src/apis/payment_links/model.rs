@@ -0,0 +1,78 @@
+use crate::apis::payments::CreatePaymentRequest;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct CreatePaymentLinkRequest {
+    pub r#type: PaymentLinkType,
+    /// Time after which the link can no longer be used. If not set, TrueLayer's default expiry applies.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub return_uri: Option<String>,
+    /// Configuration of the payments created through the link.
+    pub payment_configuration: CreatePaymentRequest,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentLinkType {
+    /// The link can be used to make only one successful payment.
+    SingleUse,
+    /// The link can be used to make any number of payments until it expires or is disabled.
+    Reusable,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct CreatePaymentLinkResponse {
+    pub id: String,
+    /// URI of the page where the payer can make a payment.
+    pub uri: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct PaymentLink {
+    pub id: String,
+    pub r#type: PaymentLinkType,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub reference: Option<String>,
+    pub return_uri: Option<String>,
+    pub payment_configuration: CreatePaymentRequest,
+    #[serde(flatten)]
+    pub status: PaymentLinkStatus,
+}
+
+impl PaymentLink {
+    /// Returns whether the link can still be used to make a payment at the given time.
+    pub fn is_usable_at(&self, at: DateTime<Utc>) -> bool {
+        matches!(self.status, PaymentLinkStatus::Active)
+            && !matches!(self.expires_at, Some(expires_at) if at >= expires_at)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PaymentLinkStatus {
+    Active,
+    Disabled {
+        disabled_at: DateTime<Utc>,
+        disablement_reason: PaymentLinkDisablementReason,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentLinkDisablementReason {
+    /// The link was disabled by the merchant.
+    Disabled,
+    /// The link expired.
+    Expired,
+    /// A single use link was used to make a successful payment.
+    SingleUseLimitReached,
+    /// Any other reason not yet known to this library.
+    #[serde(other)]
+    Unknown,
+}
@@ -2,19 +2,27 @@ use crate::{
     apis::{
         auth::Token,
         payments::{
-            refunds::{CreateRefundRequest, CreateRefundResponse, Refund},
-            CreatePaymentRequest, CreatePaymentResponse, Payment, StartAuthorizationFlowRequest,
-            StartAuthorizationFlowResponse, SubmitConsentActionResponse, SubmitFormActionRequest,
-            SubmitFormActionResponse, SubmitProviderReturnParametersRequest,
-            SubmitProviderReturnParametersResponse, SubmitProviderSelectionActionRequest,
-            SubmitProviderSelectionActionResponse,
+            flow::{action_succeeded, FlowAction, FlowCache, FlowEvent},
+            refunds::{
+                CreateRefundRequest, CreateRefundResponse, Refund, RefundBatchItem,
+                RefundBatchOutcome, RefundBatchReport,
+            },
+            CreatePaymentRequest, CreatePaymentResponse, HppLinkBuilder, ListPaymentsRequest,
+            Payment, PaymentEvent, PaymentMethodRequest, PaymentsPage, ProviderSelectionRequest,
+            SignupPlusUserData, StartAuthorizationFlowRequest, StartAuthorizationFlowResponse,
+            SubmitConsentActionResponse, SubmitFormActionRequest, SubmitFormActionResponse,
+            SubmitProviderReturnParametersRequest, SubmitProviderReturnParametersResponse,
+            SubmitProviderSelectionActionRequest, SubmitProviderSelectionActionResponse,
         },
         TrueLayerClientInner,
     },
     common::IDEMPOTENCY_KEY_HEADER,
+    pagination::{paginate, PaginatedListResponse},
+    request::{ApiRequest, RequestOptions},
     Error,
 };
-use reqwest::Url;
+use chrono::Utc;
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
 use serde::Deserialize;
 use serde_json::json;
 use std::sync::Arc;
@@ -33,41 +41,93 @@ impl PaymentsApi {
     }
 
     /// Creates a new payment.
+    ///
+    /// A new random idempotency key is generated for the request, unless one is set on the
+    /// returned [`ApiRequest`] with [`idempotency_key`](ApiRequest::idempotency_key).
+    pub fn create<'a>(
+        &'a self,
+        create_payment_request: &'a CreatePaymentRequest,
+    ) -> ApiRequest<'a, CreatePaymentResponse> {
+        ApiRequest::new(move |options| self.create_with_options(create_payment_request, options))
+    }
+
+    /// Creates a new payment using the given idempotency key.
+    ///
+    /// Makes the request safe to retry, see [`ApiRequest::idempotency_key`](crate::request::ApiRequest::idempotency_key).
+    pub async fn create_with_idempotency_key(
+        &self,
+        create_payment_request: &CreatePaymentRequest,
+        idempotency_key: &str,
+    ) -> Result<CreatePaymentResponse, Error> {
+        self.create(create_payment_request)
+            .idempotency_key(idempotency_key)
+            .await
+    }
+
+    /// Creates a new payment with the given options.
+    ///
+    /// Equivalent to configuring the [`ApiRequest`] returned by [`create`](Self::create).
     #[tracing::instrument(
         name = "Create Payment",
-        skip(self, create_payment_request),
+        skip(self, create_payment_request, options),
         fields(
             amount_in_minor = create_payment_request.amount_in_minor,
             currency = %create_payment_request.currency,
+            correlation_id = options.correlation_id.as_deref(),
         )
     )]
-    pub async fn create(
+    pub async fn create_with_options(
         &self,
         create_payment_request: &CreatePaymentRequest,
+        mut options: RequestOptions,
     ) -> Result<CreatePaymentResponse, Error> {
-        // Generate a new random idempotency-key for this request
-        let idempotency_key = Uuid::new_v4();
-
-        let res = self
-            .inner
-            .client
-            .post(
-                self.inner
-                    .environment
-                    .payments_url()
-                    .join("/payments")
-                    .unwrap(),
-            )
-            .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
+        let idempotency_key = options.idempotency_key();
+        let res = options
+            .apply(
+                self.inner.client.post(
+                    self.inner
+                        .environment
+                        .payments_url()
+                        .join("/payments")
+                        .unwrap(),
+                ),
+            )?
             .json(create_payment_request)
             .send()
             .await?
-            .json()
+            .json::<CreatePaymentResponse>()
             .await?;
+        self.inner
+            .idempotency_ledger
+            .record(&idempotency_key, &res.id);
+
+        // Starting the authorization flow of the payment might return the form of the provider
+        let PaymentMethodRequest::BankTransfer {
+            provider_selection, ..
+        } = &create_payment_request.payment_method;
+        if let ProviderSelectionRequest::Preselected { provider_id, .. } = provider_selection {
+            self.inner
+                .flow_cache
+                .remember_provider(&res.id, provider_id);
+        }
 
         Ok(res)
     }
 
+    /// Gets the payment originally created with the idempotency key of an
+    /// [`Error::IdempotencyConflict`](crate::Error::IdempotencyConflict).
+    ///
+    /// Only the payments recently created with this client, in this process, can be found:
+    /// the ids of the created payments are only remembered in memory. `None` is returned
+    /// for any other payment, or if the error is not an idempotency conflict.
+    #[tracing::instrument(name = "Get Original Payment", skip_all)]
+    pub async fn get_original(&self, error: &Error) -> Result<Option<Payment>, Error> {
+        match self.inner.idempotency_ledger.original_resource_id(error) {
+            Some(id) => self.get_by_id(&id).await,
+            None => Ok(None),
+        }
+    }
+
     /// Starts the authorization flow for a payment.
     #[tracing::instrument(name = "Start Authorization Flow", skip(self, req))]
     pub async fn start_authorization_flow(
@@ -78,27 +138,41 @@ impl PaymentsApi {
         // Generate a new random idempotency-key for this request
         let idempotency_key = Uuid::new_v4();
 
+        let res: Result<StartAuthorizationFlowResponse, Error> = async {
+            Ok(self
+                .inner
+                .client
+                .post(
+                    self.inner
+                        .environment
+                        .payments_url()
+                        .join(&format!(
+                            "/payments/{}/authorization-flow",
+                            encode(payment_id)
+                        ))
+                        .unwrap(),
+                )
+                .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
+                .json(req)
+                .send()
+                .await?
+                .json()
+                .await?)
+        }
+        .await;
         let res = self
-            .inner
-            .client
-            .post(
-                self.inner
-                    .environment
-                    .payments_url()
-                    .join(&format!(
-                        "/payments/{}/authorization-flow",
-                        encode(payment_id)
-                    ))
-                    .unwrap(),
-            )
-            .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
-            .json(req)
-            .send()
-            .await?
-            .json()
-            .await?;
-
-        Ok(res)
+            .detect_stale_step(payment_id, FlowAction::Start, res)
+            .await;
+        self.inner
+            .flow_events
+            .observe(payment_id, FlowAction::Start, &res);
+        if let Ok(res) = &res {
+            self.inner
+                .flow_cache
+                .observe_start(payment_id, res.authorization_flow.as_ref());
+        }
+
+        res
     }
 
     /// Submits the provider details selected by the PSU.
@@ -111,29 +185,87 @@ impl PaymentsApi {
         // Generate a new random idempotency-key for this request
         let idempotency_key = Uuid::new_v4();
 
+        let res: Result<SubmitProviderSelectionActionResponse, Error> = async {
+            Ok(self
+                .inner
+                .client
+                .post(
+                    self.inner
+                        .environment
+                        .payments_url()
+                        .join(&format!(
+                            "/payments/{}/authorization-flow/actions/provider-selection",
+                            encode(payment_id)
+                        ))
+                        .unwrap(),
+                )
+                .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
+                .json(req)
+                .send()
+                .await?
+                .json::<SubmitProviderSelectionActionResponse>()
+                .await?)
+        }
+        .await;
         let res = self
-            .inner
-            .client
-            .post(
-                self.inner
-                    .environment
-                    .payments_url()
-                    .join(&format!(
-                        "/payments/{}/authorization-flow/actions/provider-selection",
-                        encode(payment_id)
-                    ))
-                    .unwrap(),
-            )
-            .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
-            .json(req)
-            .send()
-            .await?
-            .json()
-            .await?;
+            .detect_stale_step(payment_id, FlowAction::ProviderSelection, res)
+            .await;
+        if action_succeeded(&res) {
+            self.inner.flow_events.emit(FlowEvent::ProviderSelected {
+                payment_id: payment_id.to_string(),
+                provider_id: req.provider_id.clone(),
+            });
+        }
+        self.inner
+            .flow_events
+            .observe(payment_id, FlowAction::ProviderSelection, &res);
+        let res = res?;
+
+        self.inner
+            .flow_cache
+            .observe(&req.provider_id, res.authorization_flow.as_ref());
 
         Ok(res)
     }
 
+    /// Translates the rejection of an action of the authorization flow into an
+    /// [`Error::StaleAuthorizationStep`] if the flow of the payment has already moved past it.
+    ///
+    /// The payment is only fetched again if implicit behaviors are enabled.
+    async fn detect_stale_step<T>(
+        &self,
+        payment_id: &str,
+        action: FlowAction,
+        res: Result<T, Error>,
+    ) -> Result<T, Error> {
+        let source = match res {
+            Err(Error::ApiError(e))
+                if matches!(e.status, 400 | 409 | 422) && self.inner.implicit_behaviors =>
+            {
+                e
+            }
+            res => return res,
+        };
+
+        match self.get_by_id(payment_id).await {
+            Ok(Some(payment)) if action.is_stale_in(&payment.status) => {
+                Err(Error::StaleAuthorizationStep {
+                    payment_id: payment_id.to_string(),
+                    action,
+                    current: Box::new(payment.status),
+                    source,
+                })
+            }
+            _ => Err(Error::ApiError(source)),
+        }
+    }
+
+    /// Returns the cache of the data returned by the authorization flows of payments,
+    /// like the form schemas of each provider.
+    pub fn flow(&self) -> &FlowCache {
+        &self.inner.flow_cache
+    }
+
     /// Formally submits the consent provided by the PSU
     #[tracing::instrument(name = "Submit Consent", skip(self))]
     pub async fn submit_consent(
@@ -142,27 +274,41 @@ impl PaymentsApi {
     ) -> Result<SubmitConsentActionResponse, Error> {
         let idempotency_key = Uuid::new_v4();
 
+        let res: Result<SubmitConsentActionResponse, Error> = async {
+            Ok(self
+                .inner
+                .client
+                .post(
+                    self.inner
+                        .environment
+                        .payments_url()
+                        .join(&format!(
+                            "/payments/{}/authorization-flow/actions/consent",
+                            encode(payment_id)
+                        ))
+                        .unwrap(),
+                )
+                .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
+                .json(&json!({}))
+                .send()
+                .await?
+                .json()
+                .await?)
+        }
+        .await;
         let res = self
-            .inner
-            .client
-            .post(
-                self.inner
-                    .environment
-                    .payments_url()
-                    .join(&format!(
-                        "/payments/{}/authorization-flow/actions/consent",
-                        encode(payment_id)
-                    ))
-                    .unwrap(),
-            )
-            .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
-            .json(&json!({}))
-            .send()
-            .await?
-            .json()
-            .await?;
+            .detect_stale_step(payment_id, FlowAction::Consent, res)
+            .await;
+        if action_succeeded(&res) {
+            self.inner.flow_events.emit(FlowEvent::ConsentSubmitted {
+                payment_id: payment_id.to_string(),
+            });
+        }
+        self.inner
+            .flow_events
+            .observe(payment_id, FlowAction::Consent, &res);
 
-        Ok(res)
+        res
     }
 
     /// Submits the form inputs entered by the PSU.
@@ -175,27 +321,36 @@ impl PaymentsApi {
         // Generate a new random idempotency-key for this request
         let idempotency_key = Uuid::new_v4();
 
+        let res: Result<SubmitFormActionResponse, Error> = async {
+            Ok(self
+                .inner
+                .client
+                .post(
+                    self.inner
+                        .environment
+                        .payments_url()
+                        .join(&format!(
+                            "/payments/{}/authorization-flow/actions/form",
+                            encode(payment_id)
+                        ))
+                        .unwrap(),
+                )
+                .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
+                .json(req)
+                .send()
+                .await?
+                .json()
+                .await?)
+        }
+        .await;
         let res = self
-            .inner
-            .client
-            .post(
-                self.inner
-                    .environment
-                    .payments_url()
-                    .join(&format!(
-                        "/payments/{}/authorization-flow/actions/form",
-                        encode(payment_id)
-                    ))
-                    .unwrap(),
-            )
-            .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
-            .json(req)
-            .send()
-            .await?
-            .json()
-            .await?;
+            .detect_stale_step(payment_id, FlowAction::Form, res)
+            .await;
+        self.inner
+            .flow_events
+            .observe(payment_id, FlowAction::Form, &res);
 
-        Ok(res)
+        res
     }
 
     /// Attempts to cancel a payment.
@@ -221,6 +376,71 @@ impl PaymentsApi {
         Ok(())
     }
 
+    /// Cancels a payment, then gets its updated details.
+    ///
+    /// Cancelled payments are reported as failed, see [`PaymentStatus::is_cancelled`].
+    /// If there's no payment with the given id, `None` is returned.
+    ///
+    /// [`PaymentStatus::is_cancelled`]: crate::apis::payments::PaymentStatus::is_cancelled
+    #[tracing::instrument(name = "Cancel and Get", skip(self))]
+    pub async fn cancel_and_get(&self, payment_id: &str) -> Result<Option<Payment>, Error> {
+        match self.cancel(payment_id).await {
+            Ok(()) => {}
+            Err(Error::ApiError(api_error)) if api_error.status == 404 => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        self.get_by_id(payment_id).await
+    }
+
+    /// Gets all the payments matching the given filters, following the pagination
+    /// cursors until the last page.
+    ///
+    /// To fetch pages one at a time, use [`list_page`](Self::list_page),
+    /// or use [`list_stream`](Self::list_stream) to process payments as they are fetched.
+    #[tracing::instrument(name = "List Payments", skip(self, request))]
+    pub async fn list(&self, request: &ListPaymentsRequest) -> Result<Vec<Payment>, Error> {
+        self.list_stream(request).try_collect().await
+    }
+
+    /// Returns a stream of all the payments matching the given filters,
+    /// fetching the following pages as the stream is consumed.
+    pub fn list_stream<'a>(
+        &'a self,
+        request: &ListPaymentsRequest,
+    ) -> BoxStream<'a, Result<Payment, Error>> {
+        let request = request.clone();
+        paginate(move |cursor| {
+            let request = ListPaymentsRequest {
+                cursor: cursor.or_else(|| request.cursor.clone()),
+                ..request.clone()
+            };
+            async move { self.list_page(&request).await }
+        })
+    }
+
+    /// Gets a single page of the payments matching the given filters.
+    #[tracing::instrument(name = "List Payments Page", skip(self, request))]
+    pub async fn list_page(&self, request: &ListPaymentsRequest) -> Result<PaymentsPage, Error> {
+        let res: PaginatedListResponse<_> = self
+            .inner
+            .client
+            .get(
+                self.inner
+                    .environment
+                    .payments_url()
+                    .join("/payments")
+                    .unwrap(),
+            )
+            .query(request)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        Ok(res.into())
+    }
+
     /// Gets the details of an existing payment.
     ///
     /// If there's no payment with the given id, `None` is returned.
@@ -250,25 +470,64 @@ impl PaymentsApi {
         Ok(payment)
     }
 
-    /// Creates a link to the TrueLayer Hosted Payments Page.
+    /// Gets the history of status transitions of an existing payment.
+    ///
+    /// If there's no payment with the given id, `None` is returned.
+    /// See [`Payment::events`](crate::apis::payments::Payment::events) for how the history is built.
+    #[tracing::instrument(name = "Get Payment Events", skip(self))]
+    pub async fn get_events(&self, id: &str) -> Result<Option<Vec<PaymentEvent>>, Error> {
+        Ok(self.get_by_id(id).await?.map(|payment| payment.events()))
+    }
+
+    /// Gets the identity of the payer of a payment created with Signup+ enabled
+    /// (see [`RelatedProducts`](crate::apis::payments::RelatedProducts)), as verified by their bank.
+    ///
+    /// If TrueLayer has no identity data for the payment, `None` is returned.
+    #[tracing::instrument(name = "Get Signup+ User Data", skip(self))]
+    pub async fn get_signup_plus_user_data(
+        &self,
+        payment_id: &str,
+    ) -> Result<Option<SignupPlusUserData>, Error> {
+        let mut url = self
+            .inner
+            .environment
+            .payments_url()
+            .join("/signup-plus/payments")
+            .unwrap();
+        url.query_pairs_mut().append_pair("payment_id", payment_id);
+
+        let res = self.inner.client.get(url).send().await.map_err(Error::from);
+
+        // Return `None` if the server returned 404
+        let user_data = match res {
+            Ok(body) => Some(body.json().await?),
+            Err(Error::ApiError(api_error)) if api_error.status == 404 => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(user_data)
+    }
+
+    /// Starts building a link to the TrueLayer Hosted Payments Page of the environment of this client.
     ///
-    /// Note that the `return_uri` must be configured in your TrueLayer console.
-    pub async fn get_hosted_payments_page_link(
+    /// ```rust,no_run
+    /// # use truelayer_rust::{apis::payments::HppLanguage, TrueLayerClient};
+    /// # fn run(tl: TrueLayerClient, res: truelayer_rust::apis::payments::CreatePaymentResponse) {
+    /// let link = tl
+    ///     .payments
+    ///     .hosted_payments_page_link(&res.id, &res.resource_token)
+    ///     .return_uri("https://my.return.uri")
+    ///     .language(HppLanguage::Fr)
+    ///     .build()
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn hosted_payments_page_link(
         &self,
         payment_id: &str,
         resource_token: &Token,
-        return_uri: &str,
-    ) -> Url {
-        let mut new_uri = self.inner.environment.hpp_url().join("/payments").unwrap();
-
-        new_uri.set_fragment(Some(&format!(
-            "payment_id={}&resource_token={}&return_uri={}",
-            payment_id,
-            resource_token.expose_secret(),
-            return_uri
-        )));
-
-        new_uri
+    ) -> HppLinkBuilder {
+        HppLinkBuilder::new(&self.inner.environment, payment_id, resource_token)
     }
 
     /// Submit direct return query and fragment parameters returned from the provider.
@@ -301,31 +560,60 @@ impl PaymentsApi {
     }
 
     /// Creates a refund for a payment.
+    ///
+    /// A new random idempotency key is generated for the request, unless one is set on the
+    /// returned [`ApiRequest`] with [`idempotency_key`](ApiRequest::idempotency_key).
+    pub fn create_refund<'a>(
+        &'a self,
+        payment_id: &'a str,
+        create_refund_request: &'a CreateRefundRequest,
+    ) -> ApiRequest<'a, CreateRefundResponse> {
+        ApiRequest::new(move |options| {
+            self.create_refund_with_options(payment_id, create_refund_request, options)
+        })
+    }
+
+    /// Creates a refund for a payment using the given idempotency key.
+    ///
+    /// Makes the request safe to retry, see [`ApiRequest::idempotency_key`](crate::request::ApiRequest::idempotency_key).
+    pub async fn create_refund_with_idempotency_key(
+        &self,
+        payment_id: &str,
+        create_refund_request: &CreateRefundRequest,
+        idempotency_key: &str,
+    ) -> Result<CreateRefundResponse, Error> {
+        self.create_refund(payment_id, create_refund_request)
+            .idempotency_key(idempotency_key)
+            .await
+    }
+
+    /// Creates a refund for a payment with the given options.
+    ///
+    /// Equivalent to configuring the [`ApiRequest`] returned by [`create_refund`](Self::create_refund).
     #[tracing::instrument(
         name = "Create Refund",
-        skip(self, create_refund_request),
+        skip(self, create_refund_request, options),
         fields(
             amount_in_minor = create_refund_request.amount_in_minor,
+            correlation_id = options.correlation_id.as_deref(),
         )
     )]
-    pub async fn create_refund(
+    pub async fn create_refund_with_options(
         &self,
         payment_id: &str,
         create_refund_request: &CreateRefundRequest,
+        mut options: RequestOptions,
     ) -> Result<CreateRefundResponse, Error> {
-        let idempotency_key = Uuid::new_v4();
-
-        let res = self
-            .inner
-            .client
-            .post(
-                self.inner
-                    .environment
-                    .payments_url()
-                    .join(&format!("/payments/{}/refunds", encode(payment_id)))
-                    .unwrap(),
-            )
-            .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
+        let res = options
+            .apply(
+                self.inner.client.post(
+                    self.inner
+                        .environment
+                        .payments_url()
+                        .join(&format!("/payments/{}/refunds", encode(payment_id)))
+                        .unwrap(),
+                ),
+            )?
             .json(create_refund_request)
             .send()
             .await?
@@ -335,6 +623,61 @@ impl PaymentsApi {
         Ok(res)
     }
 
+    /// Creates a batch of refunds, sending at most `max_concurrency` requests at the same time.
+    ///
+    /// Each item is sent with its own idempotency key, and the failure of an item does not
+    /// stop the others: the returned report contains the outcome of every item, so that
+    /// failed items can be retried later with the same key.
+    ///
+    /// Before sending each request, the batch waits for the rate limit window to reset
+    /// if TrueLayer reported that no requests are left in the current one
+    /// (see [`rate_limits`](crate::rate_limits)).
+    #[tracing::instrument(name = "Refund Batch", skip(self, items), fields(items = items.len()))]
+    pub async fn refund_batch(
+        &self,
+        items: Vec<RefundBatchItem>,
+        max_concurrency: usize,
+    ) -> RefundBatchReport {
+        let outcomes = futures::stream::iter(items)
+            .map(|item| async move {
+                self.wait_for_rate_limit_reset().await;
+
+                let result = self
+                    .create_refund_with_idempotency_key(
+                        &item.payment_id,
+                        &item.request,
+                        &item.idempotency_key,
+                    )
+                    .await;
+                if let Err(e) = &result {
+                    tracing::warn!(payment_id = %item.payment_id, error = %e, "Failed to create refund");
+                }
+
+                RefundBatchOutcome { item, result }
+            })
+            .buffered(max_concurrency.max(1))
+            .collect()
+            .await;
+
+        RefundBatchReport { outcomes }
+    }
+
+    /// Waits until the rate limit window of the payments host resets, if no requests are left in the current one.
+    async fn wait_for_rate_limit_reset(&self) {
+        let host = self.inner.environment.payments_url();
+        let status = match host.host_str().and_then(|h| self.inner.rate_limits.get(h)) {
+            Some(status) => status,
+            None => return,
+        };
+
+        if let (Some(0), Some(reset_at)) = (status.remaining, status.reset_at) {
+            if let Ok(wait) = (reset_at - Utc::now()).to_std() {
+                tracing::debug!(?wait, "Rate limit exhausted, waiting for reset");
+                crate::runtime::sleep(wait).await;
+            }
+        }
+    }
+
     /// Gets the details of an existing refund.
     ///
     /// If there's no refund with the given id for the given payment id, `None` is returned.
@@ -406,25 +749,33 @@ mod tests {
         apis::{
             auth::Credentials,
             payments::{
-                refunds::RefundStatus, AdditionalInputType, AuthorizationFlowNextAction,
+                refunds::RefundStatus, AccountIdentifier, AdditionalInput,
+                AdditionalInputDisplayText, AdditionalInputType, AuthorizationFlowNextAction,
                 AuthorizationFlowResponseStatus, Beneficiary, ConsentSupported, CountryCode,
-                CreatePaymentStatus, CreatePaymentUserRequest, Currency, FailureStage,
-                FormSupported, PaymentMethod, PaymentMethodRequest, PaymentStatus, Provider,
-                ProviderSelection, ProviderSelectionRequest, ProviderSelectionSupported,
-                RedirectSupported, SchemeSelection, SubmitProviderReturnParametersResponseResource,
-                User,
+                CreatePaymentStatus, CreatePaymentUserRequest, Currency, CustomerSegment,
+                FailureStage, FormSupported, HppLanguage, Locale, PaymentEventType, PaymentMethod,
+                PaymentMethodRequest, PaymentRetry, PaymentStatus, PaymentStatusFilter, Provider,
+                ProviderFilter, ProviderFilterExcludes, ProviderSelection,
+                ProviderSelectionRequest, ProviderSelectionSupported, RedirectSupported,
+                ReleaseChannel, Remitter, SchemeSelection,
+                SubmitProviderReturnParametersResponseResource, User,
             },
         },
         authenticator::Authenticator,
         client::Environment,
+        common::CORRELATION_ID_HEADER,
         middlewares::error_handling::ErrorHandlingMiddleware,
+        pollable::{IsInTerminalState, PollOptions},
+    };
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use reqwest::{
+        header::{HeaderName, HeaderValue},
+        Url,
     };
-    use chrono::Utc;
-    use reqwest::Url;
     use serde_json::json;
     use std::collections::HashMap;
     use wiremock::{
-        matchers::{body_partial_json, header_exists, method, path},
+        matchers::{body_partial_json, header, header_exists, method, path, query_param},
         Mock, MockServer, ResponseTemplate,
     };
 
@@ -449,6 +800,16 @@ mod tests {
                 .build(),
             authenticator,
             environment: Environment::from_single_url(&Url::parse(&mock_server.uri()).unwrap()),
+            deprecations: Default::default(),
+            rate_limits: Default::default(),
+            poll_options: PollOptions::default().into_dyn(),
+            poll_budget: None,
+            flow_cache: Default::default(),
+            flow_events: Default::default(),
+            idempotency_ledger: Default::default(),
+            payout_guardrails: None,
+            request_traces: Default::default(),
+            implicit_behaviors: true,
         };
 
         (inner, mock_server)
@@ -515,6 +876,8 @@ mod tests {
                     id: "user-id".to_string(),
                 },
                 metadata: None,
+                retry: None,
+                related_products: None,
             })
             .await
             .unwrap();
@@ -526,54 +889,322 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn start_authorization_flow() {
+    async fn create_with_provider_filter_and_user_selected_scheme() {
         let (inner, mock_server) = mock_client_and_server().await;
         let api = PaymentsApi::new(Arc::new(inner));
 
-        let payment_id = "payment-id";
-
         Mock::given(method("POST"))
-            .and(path(format!("/payments/{}/authorization-flow", payment_id)))
-            .and(header_exists(IDEMPOTENCY_KEY_HEADER))
+            .and(path("/payments"))
             .and(body_partial_json(json!({
-                "provider_selection": {},
-                "redirect": {
-                    "return_uri": "https://my.return.uri"
-                },
-                "consent": {},
-            })))
-            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-                "authorization_flow": {
-                    "actions": {
-                        "next": {
-                            "type": "provider_selection",
-                            "providers": [
-                                {
-                                    "id": "ob-bank-name",
-                                    "display_name": "Bank Name",
-                                    "icon_uri": "https://truelayer-provider-assets.s3.amazonaws.com/global/icon/generic.svg",
-                                    "logo_uri": "https://truelayer-provider-assets.s3.amazonaws.com/global/logos/generic.svg",
-                                    "bg_color": "#000000",
-                                    "country_code": "GB"
-                                }
-                            ]
+                "payment_method": {
+                    "type": "bank_transfer",
+                    "provider_selection": {
+                        "type": "user_selected",
+                        "filter": {
+                            "countries": ["GB", "IE"],
+                            "release_channel": "public_beta",
+                            "customer_segments": ["retail", "business"],
+                            "excludes": {
+                                "provider_ids": ["excluded-provider-id"]
+                            }
+                        },
+                        "scheme_selection": {
+                            "type": "user_selected"
                         }
                     }
+                }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payment-id",
+                "resource_token": "resource-token",
+                "user": {
+                    "id": "user-id"
                 },
-                "status": "authorizing"
+                "status": "authorization_required"
             })))
             .expect(1)
             .mount(&mock_server)
             .await;
 
         let res = api
-            .start_authorization_flow(
-                payment_id,
-                &StartAuthorizationFlowRequest {
-                    provider_selection: Some(ProviderSelectionSupported {}),
-                    redirect: Some(RedirectSupported {
-                        return_uri: "https://my.return.uri".to_string(),
-                        direct_return_uri: None,
+            .create(&CreatePaymentRequest {
+                amount_in_minor: 100,
+                currency: Currency::Gbp,
+                payment_method: PaymentMethodRequest::BankTransfer {
+                    provider_selection: ProviderSelectionRequest::UserSelected {
+                        filter: Some(ProviderFilter {
+                            countries: Some(vec![CountryCode::GB, CountryCode::IE]),
+                            release_channel: Some(ReleaseChannel::PublicBeta),
+                            customer_segments: Some(vec![
+                                CustomerSegment::Retail,
+                                CustomerSegment::Business,
+                            ]),
+                            excludes: Some(ProviderFilterExcludes {
+                                provider_ids: Some(vec!["excluded-provider-id".to_string()]),
+                            }),
+                            ..Default::default()
+                        }),
+                        scheme_selection: Some(SchemeSelection::UserSelected),
+                    },
+                    beneficiary: Beneficiary::MerchantAccount {
+                        merchant_account_id: "merchant-account-id".to_string(),
+                        account_holder_name: None,
+                    },
+                },
+                user: CreatePaymentUserRequest::ExistingUser {
+                    id: "user-id".to_string(),
+                },
+                metadata: None,
+                retry: None,
+                related_products: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(res.id, "payment-id");
+    }
+
+    #[tokio::test]
+    async fn create_with_preselected_provider_and_remitter() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path("/payments"))
+            .and(body_partial_json(json!({
+                "payment_method": {
+                    "type": "bank_transfer",
+                    "provider_selection": {
+                        "type": "preselected",
+                        "provider_id": "provider-id",
+                        "scheme_id": "polish_domestic_standard",
+                        "remitter": {
+                            "account_holder_name": "Rem Itter",
+                            "account_identifier": {
+                                "type": "nrb",
+                                "nrb": "12345678901234567890123456"
+                            }
+                        }
+                    }
+                }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payment-id",
+                "resource_token": "resource-token",
+                "user": {
+                    "id": "user-id"
+                },
+                "status": "authorization_required"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let res = api
+            .create(&CreatePaymentRequest {
+                amount_in_minor: 100,
+                currency: Currency::Pln,
+                payment_method: PaymentMethodRequest::BankTransfer {
+                    provider_selection: ProviderSelectionRequest::Preselected {
+                        provider_id: "provider-id".to_string(),
+                        scheme_id: "polish_domestic_standard".to_string(),
+                        remitter: Some(Remitter::new(
+                            "Rem Itter",
+                            AccountIdentifier::Nrb {
+                                nrb: "12345678901234567890123456".to_string(),
+                            },
+                        )),
+                    },
+                    beneficiary: Beneficiary::MerchantAccount {
+                        merchant_account_id: "merchant-account-id".to_string(),
+                        account_holder_name: None,
+                    },
+                },
+                user: CreatePaymentUserRequest::ExistingUser {
+                    id: "user-id".to_string(),
+                },
+                metadata: None,
+                retry: None,
+                related_products: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(res.id, "payment-id");
+    }
+
+    #[tokio::test]
+    async fn create_with_idempotency_key() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path("/payments"))
+            .and(header(IDEMPOTENCY_KEY_HEADER, "my-idempotency-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payment-id",
+                "resource_token": "resource-token",
+                "user": {
+                    "id": "user-id"
+                },
+                "status": "authorization_required"
+            })))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let req = CreatePaymentRequest {
+            amount_in_minor: 100,
+            currency: Currency::Gbp,
+            payment_method: PaymentMethodRequest::BankTransfer {
+                provider_selection: ProviderSelectionRequest::UserSelected {
+                    filter: None,
+                    scheme_selection: None,
+                },
+                beneficiary: Beneficiary::MerchantAccount {
+                    merchant_account_id: "merchant-account-id".to_string(),
+                    account_holder_name: None,
+                },
+            },
+            user: CreatePaymentUserRequest::ExistingUser {
+                id: "user-id".to_string(),
+            },
+            metadata: None,
+            retry: None,
+            related_products: None,
+        };
+
+        // Retrying with the same key sends the same idempotency key
+        for _ in 0..2 {
+            let res = api
+                .create_with_idempotency_key(&req, "my-idempotency-key")
+                .await
+                .unwrap();
+            assert_eq!(res.id, "payment-id");
+        }
+    }
+
+    #[tokio::test]
+    async fn create_with_per_call_options() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path("/payments"))
+            .and(header(IDEMPOTENCY_KEY_HEADER, "my-idempotency-key"))
+            .and(header(CORRELATION_ID_HEADER, "my-correlation-id"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({
+                        "id": "payment-id",
+                        "resource_token": "resource-token",
+                        "user": {
+                            "id": "user-id"
+                        },
+                        "status": "authorization_required"
+                    }))
+                    .set_delay(std::time::Duration::from_millis(500)),
+            )
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let req = CreatePaymentRequest {
+            amount_in_minor: 100,
+            currency: Currency::Gbp,
+            payment_method: PaymentMethodRequest::BankTransfer {
+                provider_selection: ProviderSelectionRequest::UserSelected {
+                    filter: None,
+                    scheme_selection: None,
+                },
+                beneficiary: Beneficiary::MerchantAccount {
+                    merchant_account_id: "merchant-account-id".to_string(),
+                    account_holder_name: None,
+                },
+            },
+            user: CreatePaymentUserRequest::ExistingUser {
+                id: "user-id".to_string(),
+            },
+            metadata: None,
+            retry: None,
+            related_products: None,
+        };
+        let create = || {
+            api.create(&req)
+                .idempotency_key("my-idempotency-key")
+                .correlation_id("my-correlation-id")
+        };
+
+        let res = create().await.unwrap();
+        assert_eq!(res.id, "payment-id");
+
+        // The timeout only applies to the call it is set on
+        let err = create()
+            .timeout(std::time::Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::HttpError(e) if e.is_timeout()));
+
+        // Headers managed by the library cannot be overridden, and the request is not sent
+        let err = create()
+            .header(
+                HeaderName::from_static("idempotency-key"),
+                HeaderValue::from_static("other-idempotency-key"),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::ReservedHeader(name) if name == "idempotency-key"));
+    }
+
+    #[tokio::test]
+    async fn start_authorization_flow() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        let payment_id = "payment-id";
+
+        Mock::given(method("POST"))
+            .and(path(format!("/payments/{}/authorization-flow", payment_id)))
+            .and(header_exists(IDEMPOTENCY_KEY_HEADER))
+            .and(body_partial_json(json!({
+                "provider_selection": {},
+                "redirect": {
+                    "return_uri": "https://my.return.uri"
+                },
+                "consent": {},
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "authorization_flow": {
+                    "actions": {
+                        "next": {
+                            "type": "provider_selection",
+                            "providers": [
+                                {
+                                    "id": "ob-bank-name",
+                                    "display_name": "Bank Name",
+                                    "icon_uri": "https://truelayer-provider-assets.s3.amazonaws.com/global/icon/generic.svg",
+                                    "logo_uri": "https://truelayer-provider-assets.s3.amazonaws.com/global/logos/generic.svg",
+                                    "bg_color": "#000000",
+                                    "country_code": "GB"
+                                }
+                            ]
+                        }
+                    }
+                },
+                "status": "authorizing"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let res = api
+            .start_authorization_flow(
+                payment_id,
+                &StartAuthorizationFlowRequest {
+                    provider_selection: Some(ProviderSelectionSupported {}),
+                    redirect: Some(RedirectSupported {
+                        return_uri: "https://my.return.uri".to_string(),
+                        direct_return_uri: None,
                     }),
                     form: Some(FormSupported {
                         input_types: vec![
@@ -610,6 +1241,114 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn list_follows_pagination() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        let payment_json = |id: &str| {
+            json!({
+                "id": id,
+                "amount_in_minor": 100,
+                "currency": "GBP",
+                "payment_method": {
+                    "type": "bank_transfer",
+                    "provider_selection": {
+                        "type": "user_selected"
+                    },
+                    "beneficiary": {
+                        "type": "merchant_account",
+                        "merchant_account_id": "merchant-account-id"
+                    }
+                },
+                "user": {
+                    "id": "user-id"
+                },
+                "created_at": "2022-04-01T00:00:00Z",
+                "status": "failed",
+                "failed_at": "2022-04-01T00:00:00Z",
+                "failure_stage": "authorizing",
+                "failure_reason": "authorization_failed"
+            })
+        };
+
+        // Second page, mounted first so that it takes precedence when the cursor is set
+        Mock::given(method("GET"))
+            .and(path("/payments"))
+            .and(query_param("status", "failed"))
+            .and(query_param("from", "2022-04-01T00:00:00.000Z"))
+            .and(query_param("user_id", "user-id"))
+            .and(query_param("cursor", "next-page"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [payment_json("payment-id-2")],
+                "pagination": {}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // First page
+        Mock::given(method("GET"))
+            .and(path("/payments"))
+            .and(query_param("status", "failed"))
+            .and(query_param("from", "2022-04-01T00:00:00.000Z"))
+            .and(query_param("user_id", "user-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [payment_json("payment-id-1")],
+                "pagination": {
+                    "next_cursor": "next-page"
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let payments = api
+            .list(&ListPaymentsRequest {
+                status: Some(PaymentStatusFilter::Failed),
+                from: Some(Utc.with_ymd_and_hms(2022, 4, 1, 0, 0, 0).unwrap()),
+                user_id: Some("user-id".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let ids: Vec<_> = payments.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["payment-id-1", "payment-id-2"]);
+    }
+
+    #[tokio::test]
+    async fn hosted_payments_page_link_with_language() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        let link = api
+            .hosted_payments_page_link("payment-id", &Token::new("resource-token"))
+            .return_uri("https://return.uri")
+            .language(HppLanguage::from(&Locale::other("fr-BE")))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            link.as_str(),
+            format!(
+                "{}/payments#payment_id=payment-id&resource_token=resource-token&return_uri=https%3A%2F%2Freturn.uri&lang=fr",
+                mock_server.uri()
+            )
+        );
+    }
+
+    #[test]
+    fn hpp_language_falls_back_for_unsupported_locales() {
+        assert_eq!(HppLanguage::from(&Locale::EnIe), HppLanguage::En);
+        assert_eq!(HppLanguage::from(&Locale::PtPt), HppLanguage::Pt);
+        assert_eq!(HppLanguage::from_tag("ja-JP"), None);
+        assert_eq!(
+            HppLanguage::from(&Locale::other("ja-JP")),
+            HppLanguage::default()
+        );
+    }
+
     #[tokio::test]
     async fn submit_provider_selection() {
         let (inner, mock_server) = mock_client_and_server().await;
@@ -667,67 +1406,393 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn submit_provider_selection_failure() {
-        let (inner, mock_server) = mock_client_and_server().await;
+    async fn flow_events_are_emitted() {
+        let (mut inner, mock_server) = mock_client_and_server().await;
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let events_clone = events.clone();
+        inner
+            .flow_events
+            .push(move |event| events_clone.lock().unwrap().push(event.clone()));
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path(
+                "/payments/payment-id/authorization-flow/actions/provider-selection",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "authorization_flow": {
+                    "actions": {
+                        "next": {
+                            "type": "redirect",
+                            "uri": "https://my.redirect.uri/auth?session=secret#state"
+                        }
+                    }
+                },
+                "status": "authorizing"
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(
+                "/payments/payment-id/authorization-flow/actions/consent",
+            ))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&mock_server)
+            .await;
+
+        api.submit_provider_selection(
+            "payment-id",
+            &SubmitProviderSelectionActionRequest {
+                provider_id: "mock-provider-id".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        api.submit_consent("payment-id").await.unwrap_err();
+
+        let events = events.lock().unwrap();
+        assert_eq!(
+            events[..2],
+            [
+                FlowEvent::ProviderSelected {
+                    payment_id: "payment-id".to_string(),
+                    provider_id: "mock-provider-id".to_string()
+                },
+                FlowEvent::RedirectIssued {
+                    payment_id: "payment-id".to_string(),
+                    uri: "https://my.redirect.uri/auth".to_string()
+                }
+            ]
+        );
+        assert!(matches!(
+            &events[2..],
+            [FlowEvent::ActionFailed {
+                action: FlowAction::Consent,
+                failure_stage: None,
+                ..
+            }]
+        ));
+    }
+
+    #[tokio::test]
+    async fn failed_flows_emit_only_failures() {
+        let (mut inner, mock_server) = mock_client_and_server().await;
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let events_clone = events.clone();
+        inner
+            .flow_events
+            .push(move |event| events_clone.lock().unwrap().push(event.clone()));
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path(
+                "/payments/payment-id/authorization-flow/actions/consent",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "status": "failed",
+                "failure_stage": "authorizing",
+                "failure_reason": "authorization_failed"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        api.submit_consent("payment-id").await.unwrap();
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            [FlowEvent::ActionFailed {
+                payment_id: "payment-id".to_string(),
+                action: FlowAction::Consent,
+                failure_stage: Some(FailureStage::Authorizing),
+                failure_reason: "authorization_failed".to_string()
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn submit_provider_selection_caches_form_schema() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        let provider_id = "mock-provider-id";
+
+        Mock::given(method("POST"))
+            .and(path(
+                "/payments/payment-id/authorization-flow/actions/provider-selection",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "authorization_flow": {
+                    "actions": {
+                        "next": {
+                            "type": "form",
+                            "inputs": [{
+                                "type": "select",
+                                "id": "branch",
+                                "mandatory": true,
+                                "display_text": {
+                                    "key": "branch.display_text",
+                                    "default": "Branch"
+                                },
+                                "options": []
+                            }]
+                        }
+                    }
+                },
+                "status": "authorizing"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(api.flow().form_schema(provider_id).is_none());
+
+        api.submit_provider_selection(
+            "payment-id",
+            &SubmitProviderSelectionActionRequest {
+                provider_id: provider_id.to_string(),
+            },
+        )
+        .await
+        .unwrap();
+
+        let inputs = api.flow().form_schema(provider_id).unwrap();
+        assert!(matches!(&inputs[..], [AdditionalInput::Select { id, .. }] if id == "branch"));
+        assert!(api.flow().form_schema("another-provider").is_none());
+
+        api.flow().clear();
+        assert!(api.flow().form_schema(provider_id).is_none());
+    }
+
+    #[tokio::test]
+    async fn submit_provider_selection_failure() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        let payment_id = "payment-id";
+        let provider_id = "mock-provider-id";
+
+        Mock::given(method("POST"))
+            .and(path(format!(
+                "/payments/{}/authorization-flow/actions/provider-selection",
+                payment_id
+            )))
+            .and(header_exists(IDEMPOTENCY_KEY_HEADER))
+            .and(body_partial_json(json!({ "provider_id": provider_id })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "authorization_flow": {
+                    "actions": {
+                        "next": {
+                            "type": "redirect",
+                            "uri": "https://my.redirect.uri"
+                        }
+                    }
+                },
+                "status": "failed",
+                "failure_stage": "authorizing",
+                "failure_reason": "mock_reason"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let res = api
+            .submit_provider_selection(
+                payment_id,
+                &SubmitProviderSelectionActionRequest {
+                    provider_id: provider_id.to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res.status,
+            AuthorizationFlowResponseStatus::Failed {
+                failure_stage: FailureStage::Authorizing,
+                failure_reason: "mock_reason".to_string()
+            }
+        );
+        assert!(res
+            .authorization_flow
+            .as_ref()
+            .unwrap()
+            .configuration
+            .is_none());
+        assert_eq!(
+            res.authorization_flow.unwrap().actions.unwrap().next,
+            AuthorizationFlowNextAction::Redirect {
+                uri: "https://my.redirect.uri".to_string(),
+                metadata: None
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn stale_provider_selection_is_reported_with_current_status() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        // The provider was already selected from another tab, the flow now expects the consent
+        Mock::given(method("POST"))
+            .and(path(
+                "/payments/stale-payment-id/authorization-flow/actions/provider-selection",
+            ))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/payments/stale-payment-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "stale-payment-id",
+                "amount_in_minor": 100,
+                "currency": "GBP",
+                "payment_method": {
+                    "type": "bank_transfer",
+                    "provider_selection": {
+                        "type": "user_selected"
+                    },
+                    "beneficiary": {
+                        "type": "merchant_account",
+                        "merchant_account_id": "merchant-account-id",
+                    }
+                },
+                "user": {
+                    "id": "user-id"
+                },
+                "created_at": Utc::now(),
+                "status": "authorizing",
+                "authorization_flow": {
+                    "actions": {
+                        "next": {
+                            "type": "consent",
+                            "subsequent_action_hint": "redirect"
+                        }
+                    }
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let err = api
+            .submit_provider_selection(
+                "stale-payment-id",
+                &SubmitProviderSelectionActionRequest {
+                    provider_id: "mock-provider-id".to_string(),
+                },
+            )
+            .await
+            .unwrap_err();
+
+        match err {
+            Error::StaleAuthorizationStep {
+                payment_id,
+                action,
+                current,
+                source,
+            } => {
+                assert_eq!(payment_id, "stale-payment-id");
+                assert_eq!(action, FlowAction::ProviderSelection);
+                assert!(FlowAction::Consent.is_expected_in(&current));
+                assert_eq!(source.status, 400);
+            }
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        // Rejections of the expected action are returned as they are
+        Mock::given(method("POST"))
+            .and(path(
+                "/payments/payment-id/authorization-flow/actions/consent",
+            ))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/payments/payment-id"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(matches!(
+            api.submit_consent("payment-id").await,
+            Err(Error::ApiError(e)) if e.status == 400
+        ));
+    }
+
+    #[tokio::test]
+    async fn flows_without_a_known_next_action_are_not_stale() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path(
+                "/payments/payment-id/authorization-flow/actions/consent",
+            ))
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/payments/payment-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payment-id",
+                "amount_in_minor": 100,
+                "currency": "GBP",
+                "payment_method": {
+                    "type": "bank_transfer",
+                    "provider_selection": {
+                        "type": "user_selected"
+                    },
+                    "beneficiary": {
+                        "type": "merchant_account",
+                        "merchant_account_id": "merchant-account-id",
+                    }
+                },
+                "user": {
+                    "id": "user-id"
+                },
+                "created_at": Utc::now(),
+                "status": "authorizing",
+                "authorization_flow": {}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(matches!(
+            api.submit_consent("payment-id").await,
+            Err(Error::ApiError(e)) if e.status == 400
+        ));
+    }
+
+    #[tokio::test]
+    async fn stale_steps_are_not_checked_without_implicit_behaviors() {
+        let (mut inner, mock_server) = mock_client_and_server().await;
+        inner.implicit_behaviors = false;
         let api = PaymentsApi::new(Arc::new(inner));
 
-        let payment_id = "payment-id";
-        let provider_id = "mock-provider-id";
-
         Mock::given(method("POST"))
-            .and(path(format!(
-                "/payments/{}/authorization-flow/actions/provider-selection",
-                payment_id
-            )))
-            .and(header_exists(IDEMPOTENCY_KEY_HEADER))
-            .and(body_partial_json(json!({ "provider_id": provider_id })))
-            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-                "authorization_flow": {
-                    "actions": {
-                        "next": {
-                            "type": "redirect",
-                            "uri": "https://my.redirect.uri"
-                        }
-                    }
-                },
-                "status": "failed",
-                "failure_stage": "authorizing",
-                "failure_reason": "mock_reason"
-            })))
+            .and(path(
+                "/payments/payment-id/authorization-flow/actions/consent",
+            ))
+            .respond_with(ResponseTemplate::new(400))
             .expect(1)
             .mount(&mock_server)
             .await;
+        Mock::given(method("GET"))
+            .and(path("/payments/payment-id"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
 
-        let res = api
-            .submit_provider_selection(
-                payment_id,
-                &SubmitProviderSelectionActionRequest {
-                    provider_id: provider_id.to_string(),
-                },
-            )
-            .await
-            .unwrap();
-
-        assert_eq!(
-            res.status,
-            AuthorizationFlowResponseStatus::Failed {
-                failure_stage: FailureStage::Authorizing,
-                failure_reason: "mock_reason".to_string()
-            }
-        );
-        assert!(res
-            .authorization_flow
-            .as_ref()
-            .unwrap()
-            .configuration
-            .is_none());
-        assert_eq!(
-            res.authorization_flow.unwrap().actions.unwrap().next,
-            AuthorizationFlowNextAction::Redirect {
-                uri: "https://my.redirect.uri".to_string(),
-                metadata: None
-            }
-        );
+        assert!(matches!(
+            api.submit_consent("payment-id").await,
+            Err(Error::ApiError(e)) if e.status == 400
+        ));
     }
 
     #[tokio::test]
@@ -777,6 +1842,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn submit_form_inputs_missing_inputs() {
+        let display_text = AdditionalInputDisplayText {
+            key: "key".to_string(),
+            default: "Default".to_string(),
+        };
+        let select = |id: &str, mandatory: bool| AdditionalInput::Select {
+            id: id.to_string(),
+            mandatory,
+            display_text: display_text.clone(),
+            description: None,
+            options: vec![],
+        };
+        let form_inputs = [
+            select("provided", true),
+            select("blank", true),
+            select("absent", true),
+            select("optional", false),
+        ];
+
+        let request = SubmitFormActionRequest {
+            inputs: HashMap::from([
+                ("provided".to_string(), "value".to_string()),
+                ("blank".to_string(), " ".to_string()),
+            ]),
+        };
+
+        assert_eq!(request.missing_inputs(&form_inputs), ["blank", "absent"]);
+    }
+
     #[tokio::test]
     async fn submit_form_inputs() {
         let (inner, mock_server) = mock_client_and_server().await;
@@ -849,6 +1944,69 @@ mod tests {
         api.cancel(payment_id).await.unwrap();
     }
 
+    #[tokio::test]
+    async fn cancel_and_get() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        let payment_id = "payment-id";
+
+        Mock::given(method("POST"))
+            .and(path(format!("/payments/{}/actions/cancel", payment_id)))
+            .and(header_exists(IDEMPOTENCY_KEY_HEADER))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/payments/{}", payment_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": payment_id,
+                "amount_in_minor": 100,
+                "currency": "GBP",
+                "payment_method": {
+                    "type": "bank_transfer",
+                    "provider_selection": {
+                        "type": "user_selected"
+                    },
+                    "beneficiary": {
+                        "type": "merchant_account",
+                        "merchant_account_id": "merchant-account-id"
+                    }
+                },
+                "user": {
+                    "id": "user-id"
+                },
+                "created_at": "2022-04-01T00:00:00Z",
+                "status": "failed",
+                "failed_at": "2022-04-01T00:01:00Z",
+                "failure_stage": "authorization_required",
+                "failure_reason": "canceled"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let payment = api.cancel_and_get(payment_id).await.unwrap().unwrap();
+
+        assert!(payment.status.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn cancel_and_get_not_found() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path("/payments/non-existent/actions/cancel"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(api.cancel_and_get("non-existent").await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn get_by_id_successful() {
         let (inner, mock_server) = mock_client_and_server().await;
@@ -910,6 +2068,195 @@ mod tests {
         assert_eq!(payment.status, PaymentStatus::AuthorizationRequired);
     }
 
+    #[tokio::test]
+    async fn get_events() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        let payment_id = "some-known-payment-id";
+        Mock::given(method("GET"))
+            .and(path(format!("/payments/{}", payment_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": payment_id,
+                "amount_in_minor": 100,
+                "currency": "GBP",
+                "payment_method": {
+                    "type": "bank_transfer",
+                    "provider_selection": {
+                        "type": "user_selected"
+                    },
+                    "beneficiary": {
+                        "type": "merchant_account",
+                        "merchant_account_id": "merchant-account-id",
+                    }
+                },
+                "user": {
+                    "id": "user-id"
+                },
+                "created_at": "2022-04-01T00:00:00Z",
+                "status": "settled",
+                "payment_source": {
+                    "id": "payment-source-id"
+                },
+                "executed_at": "2022-04-01T00:01:00Z",
+                "settled_at": "2022-04-01T00:02:00Z"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let events = api.get_events(payment_id).await.unwrap().unwrap();
+
+        assert_eq!(
+            events,
+            vec![
+                PaymentEvent {
+                    r#type: PaymentEventType::Created,
+                    occurred_at: Utc.with_ymd_and_hms(2022, 4, 1, 0, 0, 0).unwrap()
+                },
+                PaymentEvent {
+                    r#type: PaymentEventType::Executed,
+                    occurred_at: Utc.with_ymd_and_hms(2022, 4, 1, 0, 1, 0).unwrap()
+                },
+                PaymentEvent {
+                    r#type: PaymentEventType::Settled,
+                    occurred_at: Utc.with_ymd_and_hms(2022, 4, 1, 0, 2, 0).unwrap()
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn get_by_id_with_failed_attempts() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        let payment_id = "some-retried-payment-id";
+        Mock::given(method("GET"))
+            .and(path(format!("/payments/{}", payment_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": payment_id,
+                "amount_in_minor": 100,
+                "currency": "GBP",
+                "payment_method": {
+                    "type": "bank_transfer",
+                    "provider_selection": {
+                        "type": "user_selected"
+                    },
+                    "beneficiary": {
+                        "type": "merchant_account",
+                        "merchant_account_id": "merchant-account-id",
+                    }
+                },
+                "user": {
+                    "id": "user-id"
+                },
+                "created_at": "2022-04-01T00:00:00Z",
+                "retry": {
+                    "smart": {
+                        "for": "P7D",
+                        "ensure_minimum_balance_in_minor": 1000
+                    }
+                },
+                "attempts": [{
+                    "failed_at": "2022-04-01T00:01:00Z",
+                    "failure_stage": "authorized",
+                    "failure_reason": "insufficient_funds"
+                }],
+                "status": "attempt_failed",
+                "failed_at": "2022-04-02T00:01:00Z",
+                "failure_stage": "authorized",
+                "failure_reason": "insufficient_funds"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let payment = api.get_by_id(payment_id).await.unwrap().unwrap();
+
+        assert_eq!(
+            payment.retry,
+            Some(PaymentRetry::Smart {
+                retry_for: "P7D".to_string(),
+                ensure_minimum_balance_in_minor: Some(1000),
+            })
+        );
+        assert!(!payment.is_in_terminal_state());
+        assert_eq!(
+            payment.events(),
+            vec![
+                PaymentEvent {
+                    r#type: PaymentEventType::Created,
+                    occurred_at: Utc.with_ymd_and_hms(2022, 4, 1, 0, 0, 0).unwrap()
+                },
+                PaymentEvent {
+                    r#type: PaymentEventType::AttemptFailed {
+                        failure_stage: FailureStage::Authorized,
+                        failure_reason: "insufficient_funds".to_string(),
+                    },
+                    occurred_at: Utc.with_ymd_and_hms(2022, 4, 1, 0, 1, 0).unwrap()
+                },
+                PaymentEvent {
+                    r#type: PaymentEventType::AttemptFailed {
+                        failure_stage: FailureStage::Authorized,
+                        failure_reason: "insufficient_funds".to_string(),
+                    },
+                    occurred_at: Utc.with_ymd_and_hms(2022, 4, 2, 0, 1, 0).unwrap()
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn get_signup_plus_user_data() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        Mock::given(method("GET"))
+            .and(path("/signup-plus/payments"))
+            .and(query_param("payment_id", "payment-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "title": "Mr",
+                "first_name": "Sherlock",
+                "last_name": "Holmes",
+                "date_of_birth": "1854-01-06",
+                "address": {
+                    "address_line1": "221B Baker Street",
+                    "city": "London",
+                    "zip": "NW1 6XE",
+                    "country_code": "GB"
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/signup-plus/payments"))
+            .and(query_param("payment_id", "unknown-payment-id"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let user_data = api
+            .get_signup_plus_user_data("payment-id")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(user_data.first_name, "Sherlock");
+        assert_eq!(
+            user_data.date_of_birth,
+            Some(NaiveDate::from_ymd_opt(1854, 1, 6).unwrap())
+        );
+        assert_eq!(user_data.address.unwrap().country_code, CountryCode::GB);
+
+        assert!(api
+            .get_signup_plus_user_data("unknown-payment-id")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
     #[tokio::test]
     async fn get_by_id_not_found() {
         let (inner, mock_server) = mock_client_and_server().await;
@@ -1000,6 +2347,78 @@ mod tests {
         assert_eq!(res.id, refund_id);
     }
 
+    #[tokio::test]
+    async fn refund_batch_reports_partial_failures() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsApi::new(Arc::new(inner));
+
+        for payment_id in ["payment-1", "payment-3"] {
+            Mock::given(method("POST"))
+                .and(path(format!("/payments/{}/refunds", payment_id)))
+                .and(header(
+                    IDEMPOTENCY_KEY_HEADER,
+                    format!("key-{}", payment_id).as_str(),
+                ))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_json(json!({ "id": format!("refund-{}", payment_id) })),
+                )
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(path("/payments/payment-2/refunds"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "type": "https://docs.truelayer.com/docs/error-types#refund-limit-exceeded",
+                "title": "Refund Limit Exceeded",
+                "status": 400,
+                "trace_id": "trace-id",
+                "detail": "The refund amount exceeds the amount left to refund"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let items = ["payment-1", "payment-2", "payment-3"]
+            .into_iter()
+            .map(|payment_id| RefundBatchItem {
+                idempotency_key: format!("key-{}", payment_id),
+                ..RefundBatchItem::new(
+                    payment_id,
+                    CreateRefundRequest {
+                        amount_in_minor: None,
+                        reference: "some-reference".into(),
+                        metadata: None,
+                    },
+                )
+            })
+            .collect();
+
+        let report = api.refund_batch(items, 2).await;
+
+        assert!(!report.is_complete());
+        assert_eq!(
+            report
+                .outcomes
+                .iter()
+                .map(|o| o.item.payment_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["payment-1", "payment-2", "payment-3"]
+        );
+        assert_eq!(
+            report
+                .succeeded()
+                .map(|(_, res)| res.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["refund-payment-1", "refund-payment-3"]
+        );
+        let failed: Vec<_> = report.failed().collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0.idempotency_key, "key-payment-2");
+        assert!(matches!(failed[0].1, Error::ApiError(e) if e.status == 400));
+    }
+
     #[tokio::test]
     async fn get_refund_by_id() {
         let (inner, mock_server) = mock_client_and_server().await;
@@ -0,0 +1,506 @@
+//! Local copies of the provider icons and logos referenced by provider selection actions.
+//!
+//! Checkout frontends served by the backend can use a [`ProviderAssetPrefetcher`] to download
+//! the assets of the providers returned by the `provider_selection` action of an authorization flow
+//! into an [`AssetStore`], then serve them from there instead of hotlinking TrueLayer asset URLs.
+//!
+//! ```rust,no_run
+//! # use truelayer_rust::apis::payments::{
+//! #     assets::{InMemoryAssetStore, ProviderAssetPrefetcher},
+//! #     AuthorizationFlowNextAction,
+//! # };
+//! # async fn run(next: AuthorizationFlowNextAction) {
+//! let prefetcher = ProviderAssetPrefetcher::new(InMemoryAssetStore::new());
+//!
+//! if let AuthorizationFlowNextAction::ProviderSelection { providers } = &next {
+//!     let summary = prefetcher.prefetch(providers).await;
+//!     for (uri, error) in &summary.failed {
+//!         tracing::warn!(%uri, %error, "Could not prefetch provider asset");
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::{apis::payments::Provider, Error};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::{header, redirect, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, RwLock},
+};
+
+/// Domains serving the provider assets, including their subdomains.
+const DEFAULT_ALLOWED_HOSTS: [&str; 3] = [
+    "truelayer.com",
+    "truelayer-sandbox.com",
+    "truelayer-provider-assets.s3.amazonaws.com",
+];
+
+/// Default limit on the size of a single asset.
+const DEFAULT_MAX_ASSET_SIZE: u64 = 1024 * 1024;
+
+/// Maximum number of redirects followed to download an asset.
+const MAX_REDIRECTS: usize = 10;
+
+/// A provider icon or logo downloaded from TrueLayer.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ProviderAsset {
+    /// Value of the `Content-Type` header the asset was served with, e.g. `image/svg+xml`.
+    pub content_type: Option<String>,
+    /// Value of the `ETag` header the asset was served with, used to revalidate it.
+    pub etag: Option<String>,
+    pub bytes: Vec<u8>,
+    /// When the asset was last downloaded or revalidated.
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Storage of the assets downloaded by a [`ProviderAssetPrefetcher`], keyed by their TrueLayer URL.
+#[async_trait]
+pub trait AssetStore: Send + Sync {
+    /// Returns the asset stored for the given URL, if any.
+    async fn load(&self, uri: &str) -> Result<Option<ProviderAsset>, anyhow::Error>;
+
+    /// Stores the asset for the given URL, replacing any previous version.
+    async fn save(&self, uri: &str, asset: ProviderAsset) -> Result<(), anyhow::Error>;
+}
+
+/// In-memory [`AssetStore`].
+#[derive(Debug, Default)]
+pub struct InMemoryAssetStore {
+    assets: RwLock<HashMap<String, ProviderAsset>>,
+}
+
+impl InMemoryAssetStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AssetStore for InMemoryAssetStore {
+    async fn load(&self, uri: &str) -> Result<Option<ProviderAsset>, anyhow::Error> {
+        Ok(self.assets.read().unwrap().get(uri).cloned())
+    }
+
+    async fn save(&self, uri: &str, asset: ProviderAsset) -> Result<(), anyhow::Error> {
+        self.assets.write().unwrap().insert(uri.to_string(), asset);
+        Ok(())
+    }
+}
+
+/// Whether an asset had to be downloaded again by [`ProviderAssetPrefetcher::fetch`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FetchOutcome {
+    /// The asset was not stored yet, or had changed, and was downloaded.
+    Downloaded,
+    /// The stored asset is still current.
+    NotModified,
+}
+
+/// Result of [`ProviderAssetPrefetcher::prefetch`].
+#[derive(Debug, Default)]
+pub struct PrefetchSummary {
+    /// Number of assets downloaded.
+    pub downloaded: usize,
+    /// Number of stored assets which were still current.
+    pub not_modified: usize,
+    /// URLs of the assets which could not be prefetched, with the reason.
+    pub failed: Vec<(String, Error)>,
+}
+
+/// Downloads provider icons and logos into an [`AssetStore`], revalidating the stored
+/// versions with their `ETag` so that unchanged assets are not downloaded again.
+///
+/// Assets are fetched over HTTPS with a plain HTTP client, without TrueLayer credentials. Only the assets
+/// hosted by TrueLayer are downloaded, up to 1 MiB each by default. Redirects are followed only
+/// as long as they stay on the allowed hosts.
+#[derive(Clone)]
+pub struct ProviderAssetPrefetcher {
+    client: reqwest::Client,
+    store: Arc<dyn AssetStore>,
+    allowed_hosts: Vec<String>,
+    max_asset_size: u64,
+    /// Only disabled by tests, to download assets from local mock servers.
+    require_https: bool,
+}
+
+impl ProviderAssetPrefetcher {
+    /// Creates a new prefetcher saving the assets into `store`.
+    pub fn new(store: impl AssetStore + 'static) -> Self {
+        Self {
+            // Redirects are followed by `fetch`, which checks them against the allowed hosts
+            client: reqwest::Client::builder()
+                .redirect(redirect::Policy::none())
+                .build()
+                .expect("Failed to build HTTP client"),
+            store: Arc::new(store),
+            allowed_hosts: DEFAULT_ALLOWED_HOSTS.map(String::from).to_vec(),
+            max_asset_size: DEFAULT_MAX_ASSET_SIZE,
+            require_https: true,
+        }
+    }
+
+    /// Uses the given HTTP client to download the assets, e.g. to go through an egress proxy.
+    ///
+    /// The client should not follow redirects itself (see [`redirect::Policy::none`]), so that
+    /// each of them is checked against the allowed hosts before being requested. Assets whose
+    /// final URL is not on an allowed host are rejected either way.
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Replaces the hosts assets can be downloaded from, including their subdomains.
+    ///
+    /// Defaults to the TrueLayer domains. Assets hosted anywhere else fail with
+    /// [`Error::UntrustedAssetHost`] without being requested.
+    pub fn with_allowed_hosts(
+        mut self,
+        hosts: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.allowed_hosts = hosts
+            .into_iter()
+            .map(|host| host.into().trim_matches('.').to_ascii_lowercase())
+            .collect();
+        self
+    }
+
+    /// Limits the size of each asset, failing larger ones with [`Error::ResponseTooLarge`].
+    /// Defaults to 1 MiB.
+    pub fn with_max_asset_size(mut self, max_size: u64) -> Self {
+        self.max_asset_size = max_size;
+        self
+    }
+
+    fn is_allowed(&self, uri: &str) -> bool {
+        let url = match Url::parse(uri) {
+            Ok(url) => url,
+            Err(_) => return false,
+        };
+        if url.scheme() != "https" && (self.require_https || url.scheme() != "http") {
+            return false;
+        }
+        let host = match url.host_str() {
+            Some(host) => host.trim_end_matches('.').to_ascii_lowercase(),
+            None => return false,
+        };
+
+        self.allowed_hosts.iter().any(|allowed| {
+            host == *allowed
+                || host
+                    .strip_suffix(allowed.as_str())
+                    .map_or(false, |subdomain| subdomain.ends_with('.'))
+        })
+    }
+
+    /// Downloads the icons and logos of the given providers, skipping those already
+    /// stored and unchanged.
+    ///
+    /// Failures do not stop the prefetch, and are reported in the returned summary.
+    #[tracing::instrument(name = "Prefetch Provider Assets", skip_all)]
+    pub async fn prefetch<'a>(
+        &self,
+        providers: impl IntoIterator<Item = &'a Provider>,
+    ) -> PrefetchSummary {
+        let uris: BTreeSet<&str> = providers
+            .into_iter()
+            .flat_map(|provider| [provider.icon_uri.as_deref(), provider.logo_uri.as_deref()])
+            .flatten()
+            .collect();
+
+        let mut summary = PrefetchSummary::default();
+        for uri in uris {
+            match self.fetch(uri).await {
+                Ok(FetchOutcome::Downloaded) => summary.downloaded += 1,
+                Ok(FetchOutcome::NotModified) => summary.not_modified += 1,
+                Err(e) => summary.failed.push((uri.to_string(), e)),
+            }
+        }
+
+        summary
+    }
+
+    /// Makes sure the store holds the current version of the asset at the given URL.
+    pub async fn fetch(&self, uri: &str) -> Result<FetchOutcome, Error> {
+        if !self.is_allowed(uri) {
+            return Err(Error::UntrustedAssetHost(uri.to_string()));
+        }
+
+        let stored = self.store.load(uri).await.map_err(Error::Other)?;
+        let etag = stored.as_ref().and_then(|asset| asset.etag.as_deref());
+        let response = self.send(uri, etag).await?;
+
+        let (outcome, asset) = match (response.status(), stored) {
+            (StatusCode::NOT_MODIFIED, Some(stored)) => (
+                FetchOutcome::NotModified,
+                ProviderAsset {
+                    fetched_at: Utc::now(),
+                    ..stored
+                },
+            ),
+            _ => {
+                let mut response = response.error_for_status()?;
+                let header_value = |name: header::HeaderName| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                        .map(str::to_string)
+                };
+                let content_type = header_value(header::CONTENT_TYPE);
+                let etag = header_value(header::ETAG);
+                let limit = self.max_asset_size;
+                if response.content_length().map_or(false, |len| len > limit) {
+                    return Err(Error::ResponseTooLarge { limit });
+                }
+                let mut bytes = Vec::new();
+                while let Some(chunk) = response.chunk().await? {
+                    bytes.extend_from_slice(&chunk);
+                    if bytes.len() as u64 > limit {
+                        return Err(Error::ResponseTooLarge { limit });
+                    }
+                }
+
+                (
+                    FetchOutcome::Downloaded,
+                    ProviderAsset {
+                        content_type,
+                        etag,
+                        bytes,
+                        fetched_at: Utc::now(),
+                    },
+                )
+            }
+        };
+
+        self.store.save(uri, asset).await.map_err(Error::Other)?;
+        Ok(outcome)
+    }
+
+    /// Requests an asset, following the redirects which stay on the allowed hosts.
+    async fn send(&self, uri: &str, etag: Option<&str>) -> Result<reqwest::Response, Error> {
+        let mut url = uri.to_string();
+        for _ in 0..=MAX_REDIRECTS {
+            let mut request = self.client.get(&url);
+            if let Some(etag) = etag {
+                request = request.header(header::IF_NONE_MATCH, etag);
+            }
+            let response = request.send().await?;
+
+            // Custom clients may have followed redirects on their own
+            if !self.is_allowed(response.url().as_str()) {
+                return Err(Error::UntrustedAssetHost(response.url().to_string()));
+            }
+
+            if !response.status().is_redirection() || response.status() == StatusCode::NOT_MODIFIED
+            {
+                return Ok(response);
+            }
+
+            let location = response
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| response.url().join(location).ok())
+                .ok_or_else(|| {
+                    Error::Other(anyhow::anyhow!(
+                        "Redirect from {} without a valid location",
+                        response.url()
+                    ))
+                })?;
+            if !self.is_allowed(location.as_str()) {
+                return Err(Error::UntrustedAssetHost(location.to_string()));
+            }
+            url = location.to_string();
+        }
+
+        Err(Error::Other(anyhow::anyhow!(
+            "Too many redirects downloading {}",
+            uri
+        )))
+    }
+
+    /// Returns the stored version of the asset at the given URL, if it was prefetched.
+    pub async fn get(&self, uri: &str) -> Result<Option<ProviderAsset>, Error> {
+        self.store.load(uri).await.map_err(Error::Other)
+    }
+}
+
+impl std::fmt::Debug for ProviderAssetPrefetcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProviderAssetPrefetcher").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{header as header_matcher, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    /// Prefetcher downloading assets from local mock servers over plain HTTP.
+    fn local_prefetcher() -> ProviderAssetPrefetcher {
+        ProviderAssetPrefetcher {
+            require_https: false,
+            ..ProviderAssetPrefetcher::new(InMemoryAssetStore::new())
+                .with_allowed_hosts(["127.0.0.1"])
+        }
+    }
+
+    #[tokio::test]
+    async fn unchanged_assets_are_revalidated_with_their_etag() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/icon.svg"))
+            .and(header_matcher("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/icon.svg"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .set_body_raw("<svg/>", "image/svg+xml"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let icon_uri = format!("{}/icon.svg", mock_server.uri());
+        let providers = [Provider {
+            id: "ob-bank".to_string(),
+            display_name: None,
+            icon_uri: Some(icon_uri.clone()),
+            logo_uri: Some(format!("{}/missing.svg", mock_server.uri())),
+            bg_color: None,
+            country_code: None,
+        }];
+        let prefetcher = local_prefetcher();
+
+        let summary = prefetcher.prefetch(&providers).await;
+        assert_eq!(summary.downloaded, 1);
+        assert_eq!(summary.not_modified, 0);
+        assert_eq!(summary.failed.len(), 1);
+
+        let summary = prefetcher.prefetch(&providers).await;
+        assert_eq!(summary.downloaded, 0);
+        assert_eq!(summary.not_modified, 1);
+
+        let icon = prefetcher.get(&icon_uri).await.unwrap().unwrap();
+        assert_eq!(icon.bytes, b"<svg/>");
+        assert_eq!(icon.content_type.as_deref(), Some("image/svg+xml"));
+        assert_eq!(icon.etag.as_deref(), Some("\"v1\""));
+    }
+
+    #[tokio::test]
+    async fn assets_outside_of_the_allowed_hosts_are_not_requested() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let prefetcher = ProviderAssetPrefetcher::new(InMemoryAssetStore::new());
+        let uri = format!("{}/icon.svg", mock_server.uri());
+        assert!(matches!(
+            prefetcher.fetch(&uri).await,
+            Err(Error::UntrustedAssetHost(untrusted)) if untrusted == uri
+        ));
+
+        assert!(
+            prefetcher.is_allowed("https://truelayer-provider-assets.s3.amazonaws.com/ob-bank.svg")
+        );
+        assert!(prefetcher.is_allowed("https://assets.truelayer.com/ob-bank.svg"));
+        assert!(!prefetcher.is_allowed("https://eviltruelayer.com/ob-bank.svg"));
+        assert!(!prefetcher.is_allowed("https://truelayer.com.evil.com/ob-bank.svg"));
+        assert!(!prefetcher.is_allowed("http://assets.truelayer.com/ob-bank.svg"));
+        assert!(!prefetcher.is_allowed("ftp://assets.truelayer.com/ob-bank.svg"));
+    }
+
+    #[test]
+    fn allowed_hosts_are_normalized() {
+        let prefetcher = ProviderAssetPrefetcher::new(InMemoryAssetStore::new())
+            .with_allowed_hosts(["Assets.Example.COM."]);
+
+        assert!(prefetcher.is_allowed("https://assets.example.com/ob-bank.svg"));
+        assert!(prefetcher.is_allowed("https://cdn.ASSETS.example.com./ob-bank.svg"));
+    }
+
+    #[tokio::test]
+    async fn redirects_are_only_followed_on_the_allowed_hosts() {
+        let mock_server = MockServer::start().await;
+        let port = mock_server.address().port();
+        Mock::given(method("GET"))
+            .and(path("/moved.svg"))
+            .respond_with(ResponseTemplate::new(301).insert_header("Location", "/icon.svg"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/icon.svg"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("<svg/>", "image/svg+xml"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        // Same server, but through a host which is not allowed
+        Mock::given(method("GET"))
+            .and(path("/escaped.svg"))
+            .respond_with(ResponseTemplate::new(302).insert_header(
+                "Location",
+                format!("http://localhost:{}/elsewhere.svg", port).as_str(),
+            ))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/elsewhere.svg"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let prefetcher = local_prefetcher();
+
+        let moved = format!("{}/moved.svg", mock_server.uri());
+        assert_eq!(
+            prefetcher.fetch(&moved).await.unwrap(),
+            FetchOutcome::Downloaded
+        );
+        assert_eq!(
+            prefetcher.get(&moved).await.unwrap().unwrap().bytes,
+            b"<svg/>"
+        );
+
+        let escaped = format!("{}/escaped.svg", mock_server.uri());
+        assert!(matches!(
+            prefetcher.fetch(&escaped).await,
+            Err(Error::UntrustedAssetHost(untrusted))
+                if untrusted == format!("http://localhost:{}/elsewhere.svg", port)
+        ));
+        assert_eq!(prefetcher.get(&escaped).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn oversized_assets_are_rejected() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/logo.png"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0; 64]))
+            .mount(&mock_server)
+            .await;
+
+        let prefetcher = local_prefetcher().with_max_asset_size(16);
+        let uri = format!("{}/logo.png", mock_server.uri());
+
+        assert!(matches!(
+            prefetcher.fetch(&uri).await,
+            Err(Error::ResponseTooLarge { limit: 16 })
+        ));
+        assert_eq!(prefetcher.get(&uri).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter},
+    sync::RwLock,
+};
+
+use crate::apis::payments::{
+    AccountIdentifier, Beneficiary, Reference, ReferenceError, ReferenceScheme,
+};
+
+/// Error returned when the details of a [`BeneficiaryTemplate`] are not valid.
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum BeneficiaryTemplateError {
+    #[error("Account holder name is empty")]
+    EmptyAccountHolderName,
+    #[error("Invalid sort code {0:?}: it must be made of 6 digits")]
+    InvalidSortCode(String),
+    #[error("Invalid account number {0:?}: it must be made of 8 digits")]
+    InvalidAccountNumber(String),
+    #[error("Invalid IBAN {0:?}")]
+    InvalidIban(String),
+    #[error("Invalid reference: {0}")]
+    InvalidReference(#[from] ReferenceError),
+    /// The scheme of the reference cannot be inferred from a BBAN or an NRB,
+    /// use [`BeneficiaryTemplate::with_reference_scheme`] instead.
+    #[error("Unknown reference scheme for {0} account identifiers")]
+    UnknownReferenceScheme(&'static str),
+}
+
+/// Validated details of an external account which is frequently paid into, e.g. a supplier.
+///
+/// Templates are validated once when they are created, so that payments built from them
+/// with [`beneficiary_template`](crate::apis::payments::CreatePaymentRequestBuilder::beneficiary_template)
+/// never fail because of a typo in the account details.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BeneficiaryTemplate {
+    account_holder_name: String,
+    account_identifier: AccountIdentifier,
+    reference: Reference,
+}
+
+impl BeneficiaryTemplate {
+    /// Validates the details of an external account.
+    ///
+    /// The reference is validated against the rules of the scheme implied by the account identifier:
+    /// Faster Payments for sort code and account number, SEPA for IBAN. The scheme of BBAN and NRB
+    /// identifiers cannot be inferred, use [`with_reference_scheme`](BeneficiaryTemplate::with_reference_scheme)
+    /// for them.
+    pub fn new(
+        account_holder_name: impl Into<String>,
+        account_identifier: AccountIdentifier,
+        reference: impl Into<String>,
+    ) -> Result<Self, BeneficiaryTemplateError> {
+        let scheme = match &account_identifier {
+            AccountIdentifier::SortCodeAccountNumber { .. } => ReferenceScheme::FasterPayments,
+            AccountIdentifier::Iban { .. } => ReferenceScheme::Sepa,
+            AccountIdentifier::Bban { .. } => {
+                return Err(BeneficiaryTemplateError::UnknownReferenceScheme("BBAN"))
+            }
+            AccountIdentifier::Nrb { .. } => {
+                return Err(BeneficiaryTemplateError::UnknownReferenceScheme("NRB"))
+            }
+        };
+
+        Self::with_reference_scheme(account_holder_name, account_identifier, reference, scheme)
+    }
+
+    /// Validates the details of an external account, with a reference validated against the rules
+    /// of the given scheme.
+    ///
+    /// IBANs are normalized by removing whitespace and uppercasing them.
+    pub fn with_reference_scheme(
+        account_holder_name: impl Into<String>,
+        account_identifier: AccountIdentifier,
+        reference: impl Into<String>,
+        scheme: ReferenceScheme,
+    ) -> Result<Self, BeneficiaryTemplateError> {
+        let account_holder_name = account_holder_name.into();
+        if account_holder_name.trim().is_empty() {
+            return Err(BeneficiaryTemplateError::EmptyAccountHolderName);
+        }
+
+        let account_identifier = match account_identifier {
+            AccountIdentifier::SortCodeAccountNumber {
+                sort_code,
+                account_number,
+            } => {
+                if !is_digits(&sort_code, 6) {
+                    return Err(BeneficiaryTemplateError::InvalidSortCode(sort_code));
+                }
+                if !is_digits(&account_number, 8) {
+                    return Err(BeneficiaryTemplateError::InvalidAccountNumber(
+                        account_number,
+                    ));
+                }
+                AccountIdentifier::SortCodeAccountNumber {
+                    sort_code,
+                    account_number,
+                }
+            }
+            AccountIdentifier::Iban { iban } => {
+                let normalized: String = iban
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect::<String>()
+                    .to_ascii_uppercase();
+                if !is_iban(&normalized) {
+                    return Err(BeneficiaryTemplateError::InvalidIban(iban));
+                }
+                AccountIdentifier::Iban { iban: normalized }
+            }
+            account_identifier => account_identifier,
+        };
+
+        Ok(Self {
+            account_holder_name,
+            account_identifier,
+            reference: Reference::new(reference, scheme)?,
+        })
+    }
+
+    pub fn account_holder_name(&self) -> &str {
+        &self.account_holder_name
+    }
+
+    pub fn account_identifier(&self) -> &AccountIdentifier {
+        &self.account_identifier
+    }
+
+    pub fn reference(&self) -> &Reference {
+        &self.reference
+    }
+
+    /// Builds the beneficiary of a payment into the account described by this template.
+    pub fn to_beneficiary(&self) -> Beneficiary {
+        Beneficiary::ExternalAccount {
+            account_holder_name: self.account_holder_name.clone(),
+            account_identifier: self.account_identifier.clone(),
+            reference: self.reference.to_string(),
+        }
+    }
+}
+
+/// Storage of [`BeneficiaryTemplate`]s, referenced by name.
+///
+/// [`InMemoryBeneficiaryTemplates`] is enough for templates loaded at startup.
+/// Lookups are synchronous, so implementations must not block on I/O: templates kept elsewhere
+/// should be loaded ahead of time and registered.
+pub trait BeneficiaryTemplateStore: Send + Sync {
+    /// Returns the template registered with the given name, if any.
+    fn get(&self, name: &str) -> Option<BeneficiaryTemplate>;
+
+    /// Registers a template with the given name, replacing any previous template with the same name.
+    fn register(&self, name: String, template: BeneficiaryTemplate);
+}
+
+/// In-memory [`BeneficiaryTemplateStore`].
+#[derive(Default)]
+pub struct InMemoryBeneficiaryTemplates {
+    templates: RwLock<HashMap<String, BeneficiaryTemplate>>,
+}
+
+impl InMemoryBeneficiaryTemplates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Debug for InMemoryBeneficiaryTemplates {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InMemoryBeneficiaryTemplates")
+            .field("templates", &self.templates.read().unwrap().len())
+            .finish()
+    }
+}
+
+impl BeneficiaryTemplateStore for InMemoryBeneficiaryTemplates {
+    fn get(&self, name: &str) -> Option<BeneficiaryTemplate> {
+        self.templates.read().unwrap().get(name).cloned()
+    }
+
+    fn register(&self, name: String, template: BeneficiaryTemplate) {
+        self.templates.write().unwrap().insert(name, template);
+    }
+}
+
+fn is_digits(value: &str, len: usize) -> bool {
+    value.len() == len && value.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Checks the format and the checksum of an IBAN without whitespace, as per ISO 13616.
+fn is_iban(iban: &str) -> bool {
+    if !(15..=34).contains(&iban.len())
+        || !iban.chars().all(|c| c.is_ascii_alphanumeric())
+        || !iban[..2].chars().all(|c| c.is_ascii_alphabetic())
+        || !iban[2..4].chars().all(|c| c.is_ascii_digit())
+    {
+        return false;
+    }
+
+    // Move the first four characters to the end and compute the remainder mod 97,
+    // with letters replaced by two digits (A = 10, ..., Z = 35)
+    let remainder = iban[4..]
+        .chars()
+        .chain(iban[..4].chars())
+        .map(|c| c.to_digit(36).unwrap())
+        .fold(0, |acc, d| {
+            if d < 10 {
+                (acc * 10 + d) % 97
+            } else {
+                (acc * 100 + d) % 97
+            }
+        });
+
+    remainder == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_are_validated_on_creation() {
+        assert_eq!(
+            BeneficiaryTemplate::new(
+                " ",
+                AccountIdentifier::Iban {
+                    iban: "GB33BUKB20201555555555".to_string()
+                },
+                "Invoice"
+            ),
+            Err(BeneficiaryTemplateError::EmptyAccountHolderName)
+        );
+        assert_eq!(
+            BeneficiaryTemplate::new(
+                "Supplier X",
+                AccountIdentifier::SortCodeAccountNumber {
+                    sort_code: "12-34-56".to_string(),
+                    account_number: "12345678".to_string()
+                },
+                "Invoice"
+            ),
+            Err(BeneficiaryTemplateError::InvalidSortCode(
+                "12-34-56".to_string()
+            ))
+        );
+        assert_eq!(
+            BeneficiaryTemplate::new(
+                "Supplier X",
+                AccountIdentifier::Iban {
+                    iban: "GB34BUKB20201555555555".to_string()
+                },
+                "Invoice"
+            ),
+            Err(BeneficiaryTemplateError::InvalidIban(
+                "GB34BUKB20201555555555".to_string()
+            ))
+        );
+        assert_eq!(
+            BeneficiaryTemplate::new(
+                "Supplier X",
+                AccountIdentifier::Nrb {
+                    nrb: "61109010140000071219812874".to_string()
+                },
+                "Invoice"
+            ),
+            Err(BeneficiaryTemplateError::UnknownReferenceScheme("NRB"))
+        );
+        assert_eq!(
+            BeneficiaryTemplate::new(
+                "Supplier X",
+                AccountIdentifier::SortCodeAccountNumber {
+                    sort_code: "123456".to_string(),
+                    account_number: "12345678".to_string()
+                },
+                "Invoice #2022-0042-A"
+            ),
+            Err(BeneficiaryTemplateError::InvalidReference(
+                ReferenceError::InvalidCharacter('#')
+            ))
+        );
+    }
+
+    #[test]
+    fn registered_templates_are_found_by_name() {
+        let templates = InMemoryBeneficiaryTemplates::new();
+        let template = BeneficiaryTemplate::new(
+            "Supplier X",
+            AccountIdentifier::Iban {
+                iban: "gb33 bukb 2020 1555 5555 55".to_string(),
+            },
+            "Invoice 2022/0042",
+        )
+        .unwrap();
+        templates.register("supplier-x".to_string(), template.clone());
+
+        assert_eq!(templates.get("supplier-x"), Some(template));
+        assert_eq!(templates.get("supplier-y"), None);
+        assert_eq!(
+            templates.get("supplier-x").unwrap().to_beneficiary(),
+            Beneficiary::ExternalAccount {
+                account_holder_name: "Supplier X".to_string(),
+                account_identifier: AccountIdentifier::Iban {
+                    iban: "GB33BUKB20201555555555".to_string(),
+                },
+                reference: "Invoice 2022/0042".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use crate::apis::payments::{
+    Beneficiary, BeneficiaryTemplateStore, CreatePaymentRequest, CreatePaymentUserRequest,
+    Currency, PaymentMethodRequest, PaymentRetry, ProviderSelectionRequest, RelatedProducts,
+};
+
+/// Error returned when a [`CreatePaymentRequestBuilder`] cannot build a request.
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum CreatePaymentRequestBuilderError {
+    /// A mandatory field was not set.
+    #[error("Missing mandatory field: {0}")]
+    MissingField(&'static str),
+    /// The beneficiary template is not registered in the store.
+    #[error("Unknown beneficiary template: {0}")]
+    UnknownBeneficiaryTemplate(String),
+    /// A beneficiary template was set, but the request was built without a store to resolve it.
+    #[error(
+        "Beneficiary template {0} cannot be resolved without a store, use build_with_templates"
+    )]
+    MissingTemplateStore(String),
+}
+
+#[derive(Debug, Clone)]
+enum BeneficiarySource {
+    Beneficiary(Beneficiary),
+    Template(String),
+}
+
+/// Builder for a [`CreatePaymentRequest`] paid by bank transfer.
+///
+/// ```rust
+/// # use truelayer_rust::apis::payments::{
+/// #     AccountIdentifier, BeneficiaryTemplate, BeneficiaryTemplateStore, CreatePaymentRequest,
+/// #     CreatePaymentUserRequest, Currency, InMemoryBeneficiaryTemplates,
+/// # };
+/// let templates = InMemoryBeneficiaryTemplates::new();
+/// templates.register(
+///     "supplier-x".to_string(),
+///     BeneficiaryTemplate::new(
+///         "Supplier X",
+///         AccountIdentifier::SortCodeAccountNumber {
+///             sort_code: "123456".to_string(),
+///             account_number: "12345678".to_string(),
+///         },
+///         "Invoice 42",
+///     )
+///     .unwrap(),
+/// );
+///
+/// let request = CreatePaymentRequest::builder()
+///     .amount_in_minor(100)
+///     .currency(Currency::Gbp)
+///     .beneficiary_template("supplier-x")
+///     .user(CreatePaymentUserRequest::ExistingUser {
+///         id: "user-id".to_string(),
+///     })
+///     .build_with_templates(&templates)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CreatePaymentRequestBuilder {
+    amount_in_minor: Option<u64>,
+    currency: Option<Currency>,
+    provider_selection: Option<ProviderSelectionRequest>,
+    beneficiary: Option<BeneficiarySource>,
+    user: Option<CreatePaymentUserRequest>,
+    metadata: Option<HashMap<String, String>>,
+    retry: Option<PaymentRetry>,
+    related_products: Option<RelatedProducts>,
+}
+
+impl CreatePaymentRequest {
+    /// Returns a builder for a new payment request.
+    pub fn builder() -> CreatePaymentRequestBuilder {
+        CreatePaymentRequestBuilder::default()
+    }
+}
+
+impl CreatePaymentRequestBuilder {
+    pub fn amount_in_minor(mut self, amount_in_minor: u64) -> Self {
+        self.amount_in_minor = Some(amount_in_minor);
+        self
+    }
+
+    pub fn currency(mut self, currency: Currency) -> Self {
+        self.currency = Some(currency);
+        self
+    }
+
+    /// Sets how the provider is selected. Defaults to letting the user select it without any filter.
+    pub fn provider_selection(mut self, provider_selection: ProviderSelectionRequest) -> Self {
+        self.provider_selection = Some(provider_selection);
+        self
+    }
+
+    pub fn beneficiary(mut self, beneficiary: Beneficiary) -> Self {
+        self.beneficiary = Some(BeneficiarySource::Beneficiary(beneficiary));
+        self
+    }
+
+    /// Pays into the account of the [`BeneficiaryTemplate`](crate::apis::payments::BeneficiaryTemplate)
+    /// registered with the given name. The template is resolved by
+    /// [`build_with_templates`](CreatePaymentRequestBuilder::build_with_templates).
+    pub fn beneficiary_template(mut self, name: impl Into<String>) -> Self {
+        self.beneficiary = Some(BeneficiarySource::Template(name.into()));
+        self
+    }
+
+    pub fn user(mut self, user: CreatePaymentUserRequest) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    pub fn metadata(mut self, metadata: HashMap<String, String>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn retry(mut self, retry: PaymentRetry) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn related_products(mut self, related_products: RelatedProducts) -> Self {
+        self.related_products = Some(related_products);
+        self
+    }
+
+    /// Builds the request.
+    ///
+    /// Fails with [`MissingTemplateStore`](CreatePaymentRequestBuilderError::MissingTemplateStore)
+    /// if a beneficiary template was set, use
+    /// [`build_with_templates`](CreatePaymentRequestBuilder::build_with_templates) instead.
+    pub fn build(self) -> Result<CreatePaymentRequest, CreatePaymentRequestBuilderError> {
+        self.build_with(|name| {
+            Err(CreatePaymentRequestBuilderError::MissingTemplateStore(
+                name.to_string(),
+            ))
+        })
+    }
+
+    /// Builds the request, resolving the beneficiary template from the given store.
+    pub fn build_with_templates(
+        self,
+        templates: &dyn BeneficiaryTemplateStore,
+    ) -> Result<CreatePaymentRequest, CreatePaymentRequestBuilderError> {
+        self.build_with(|name| {
+            templates
+                .get(name)
+                .map(|template| template.to_beneficiary())
+                .ok_or_else(|| {
+                    CreatePaymentRequestBuilderError::UnknownBeneficiaryTemplate(name.to_string())
+                })
+        })
+    }
+
+    fn build_with(
+        self,
+        resolve_template: impl FnOnce(&str) -> Result<Beneficiary, CreatePaymentRequestBuilderError>,
+    ) -> Result<CreatePaymentRequest, CreatePaymentRequestBuilderError> {
+        use CreatePaymentRequestBuilderError::MissingField;
+
+        let beneficiary = match self.beneficiary.ok_or(MissingField("beneficiary"))? {
+            BeneficiarySource::Beneficiary(beneficiary) => beneficiary,
+            BeneficiarySource::Template(name) => resolve_template(&name)?,
+        };
+
+        Ok(CreatePaymentRequest {
+            amount_in_minor: self
+                .amount_in_minor
+                .ok_or(MissingField("amount_in_minor"))?,
+            currency: self.currency.ok_or(MissingField("currency"))?,
+            payment_method: PaymentMethodRequest::BankTransfer {
+                provider_selection: self.provider_selection.unwrap_or(
+                    ProviderSelectionRequest::UserSelected {
+                        filter: None,
+                        scheme_selection: None,
+                    },
+                ),
+                beneficiary,
+            },
+            user: self.user.ok_or(MissingField("user"))?,
+            metadata: self.metadata,
+            retry: self.retry,
+            related_products: self.related_products,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::payments::{
+        AccountIdentifier, BeneficiaryTemplate, InMemoryBeneficiaryTemplates,
+    };
+
+    fn builder() -> CreatePaymentRequestBuilder {
+        CreatePaymentRequest::builder()
+            .amount_in_minor(100)
+            .currency(Currency::Gbp)
+            .user(CreatePaymentUserRequest::ExistingUser {
+                id: "user-id".to_string(),
+            })
+    }
+
+    #[test]
+    fn build_with_beneficiary_template() {
+        let templates = InMemoryBeneficiaryTemplates::new();
+        let template = BeneficiaryTemplate::new(
+            "Supplier X",
+            AccountIdentifier::SortCodeAccountNumber {
+                sort_code: "123456".to_string(),
+                account_number: "12345678".to_string(),
+            },
+            "Invoice 42",
+        )
+        .unwrap();
+        templates.register("supplier-x".to_string(), template.clone());
+
+        let request = builder()
+            .beneficiary_template("supplier-x")
+            .build_with_templates(&templates)
+            .unwrap();
+
+        assert!(matches!(
+            request.payment_method,
+            PaymentMethodRequest::BankTransfer { beneficiary, .. }
+                if beneficiary == template.to_beneficiary()
+        ));
+    }
+
+    #[test]
+    fn build_fails_on_unknown_template_or_missing_fields() {
+        assert_eq!(
+            builder()
+                .beneficiary_template("supplier-x")
+                .build_with_templates(&InMemoryBeneficiaryTemplates::new()),
+            Err(
+                CreatePaymentRequestBuilderError::UnknownBeneficiaryTemplate(
+                    "supplier-x".to_string()
+                )
+            )
+        );
+        assert_eq!(
+            builder().beneficiary_template("supplier-x").build(),
+            Err(CreatePaymentRequestBuilderError::MissingTemplateStore(
+                "supplier-x".to_string()
+            ))
+        );
+        assert_eq!(
+            builder().build(),
+            Err(CreatePaymentRequestBuilderError::MissingField(
+                "beneficiary"
+            ))
+        );
+    }
+}
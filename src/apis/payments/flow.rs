@@ -0,0 +1,432 @@
+//! Caching of the data returned by the authorization flow of payments,
+//! and analytics events about its progress.
+
+use crate::{
+    apis::payments::{
+        AdditionalInput, AuthorizationFlow, AuthorizationFlowActions, AuthorizationFlowNextAction,
+        AuthorizationFlowResponseStatus, FailureStage, PaymentStatus,
+        StartAuthorizationFlowResponse, SubmitConsentActionResponse, SubmitFormActionResponse,
+        SubmitProviderSelectionActionResponse,
+    },
+    runtime::Instant,
+    Error,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{Debug, Formatter},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Default time for which form schemas are cached.
+pub const DEFAULT_FORM_SCHEMA_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Maximum number of payments whose preselected provider is remembered by a [`FlowCache`].
+const PRESELECTED_PROVIDERS_CAPACITY: usize = 10_000;
+
+/// Cache of the form schemas returned by the `form` action of the authorization flow, by provider.
+///
+/// Banks ask every user for the same inputs, so embedded integrations can render the form
+/// of a provider straight away from the cache, without waiting for the authorization flow
+/// of each payment. Schemas are cached when
+/// [`submit_provider_selection`](crate::apis::payments::PaymentsApi::submit_provider_selection)
+/// returns a form, or when [`start_authorization_flow`](crate::apis::payments::PaymentsApi::start_authorization_flow)
+/// does for a payment with a preselected provider recently created with the same client.
+/// They expire after the TTL configured with
+/// [`with_form_schema_ttl`](crate::client::TrueLayerClientBuilder::with_form_schema_ttl).
+#[derive(Debug)]
+pub struct FlowCache {
+    ttl: Duration,
+    form_schemas: Mutex<HashMap<String, CachedFormSchema>>,
+    /// Providers of the payments created with a preselected provider, by payment id, oldest first.
+    preselected_providers: Mutex<(HashMap<String, String>, VecDeque<String>)>,
+}
+
+#[derive(Debug)]
+struct CachedFormSchema {
+    inputs: Arc<[AdditionalInput]>,
+    cached_at: Instant,
+}
+
+impl FlowCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            form_schemas: Mutex::new(HashMap::new()),
+            preselected_providers: Mutex::new((HashMap::new(), VecDeque::new())),
+        }
+    }
+
+    /// Returns the inputs of the form last returned for the given provider,
+    /// unless they expired.
+    pub fn form_schema(&self, provider_id: &str) -> Option<Arc<[AdditionalInput]>> {
+        let mut form_schemas = self.form_schemas.lock().unwrap();
+
+        match form_schemas.get(provider_id) {
+            Some(cached) if cached.cached_at.elapsed() < self.ttl => Some(cached.inputs.clone()),
+            Some(_) => {
+                form_schemas.remove(provider_id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Removes all the cached form schemas.
+    pub fn clear(&self) {
+        self.form_schemas.lock().unwrap().clear();
+    }
+
+    /// Caches the form schema of the given authorization flow, if its next action is a form.
+    pub(crate) fn observe(
+        &self,
+        provider_id: &str,
+        authorization_flow: Option<&AuthorizationFlow>,
+    ) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        if let Some(AuthorizationFlowNextAction::Form { inputs }) = authorization_flow
+            .and_then(|flow| flow.actions.as_ref())
+            .map(|actions| &actions.next)
+        {
+            self.form_schemas.lock().unwrap().insert(
+                provider_id.to_string(),
+                CachedFormSchema {
+                    inputs: inputs.as_slice().into(),
+                    cached_at: Instant::now(),
+                },
+            );
+        }
+    }
+
+    /// Remembers the provider preselected for a payment, until its authorization flow is started.
+    pub(crate) fn remember_provider(&self, payment_id: &str, provider_id: &str) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        let mut entries = self.preselected_providers.lock().unwrap();
+        let (providers, payment_ids) = &mut *entries;
+        if providers
+            .insert(payment_id.to_string(), provider_id.to_string())
+            .is_none()
+        {
+            payment_ids.push_back(payment_id.to_string());
+        }
+
+        // Forget the oldest payments, whose authorization flow was likely never started
+        while payment_ids.len() > PRESELECTED_PROVIDERS_CAPACITY {
+            if let Some(payment_id) = payment_ids.pop_front() {
+                providers.remove(&payment_id);
+            }
+        }
+    }
+
+    /// Caches the form schema returned when starting the authorization flow of a payment,
+    /// if its provider was preselected.
+    pub(crate) fn observe_start(
+        &self,
+        payment_id: &str,
+        authorization_flow: Option<&AuthorizationFlow>,
+    ) {
+        // The id stays in the queue until it is pushed out by newer payments
+        let provider_id = self
+            .preselected_providers
+            .lock()
+            .unwrap()
+            .0
+            .remove(payment_id);
+
+        if let Some(provider_id) = provider_id {
+            self.observe(&provider_id, authorization_flow);
+        }
+    }
+}
+
+impl Default for FlowCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_FORM_SCHEMA_TTL)
+    }
+}
+
+/// Action of the authorization flow of a payment.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum FlowAction {
+    /// [`start_authorization_flow`](crate::apis::payments::PaymentsApi::start_authorization_flow).
+    Start,
+    /// [`submit_provider_selection`](crate::apis::payments::PaymentsApi::submit_provider_selection).
+    ProviderSelection,
+    /// [`submit_consent`](crate::apis::payments::PaymentsApi::submit_consent).
+    Consent,
+    /// [`submit_form_inputs`](crate::apis::payments::PaymentsApi::submit_form_inputs).
+    Form,
+}
+
+impl FlowAction {
+    /// Returns `true` if this action is the one expected next for a payment in the given status.
+    pub fn is_expected_in(&self, status: &PaymentStatus) -> bool {
+        let next = match status {
+            PaymentStatus::AuthorizationRequired => return *self == FlowAction::Start,
+            PaymentStatus::Authorizing {
+                authorization_flow:
+                    AuthorizationFlow {
+                        actions: Some(AuthorizationFlowActions { next }),
+                        ..
+                    },
+            } => next,
+            _ => return false,
+        };
+
+        matches!(
+            (self, next),
+            (
+                FlowAction::ProviderSelection,
+                AuthorizationFlowNextAction::ProviderSelection { .. }
+            ) | (
+                FlowAction::Consent,
+                AuthorizationFlowNextAction::Consent { .. }
+            ) | (FlowAction::Form, AuthorizationFlowNextAction::Form { .. })
+        )
+    }
+
+    /// Returns `true` if the flow of a payment in the given status is known to have moved past
+    /// this action. Flows whose next action is not known yet are never considered past it.
+    pub(crate) fn is_stale_in(&self, status: &PaymentStatus) -> bool {
+        let next_unknown = matches!(
+            status,
+            PaymentStatus::Authorizing {
+                authorization_flow: AuthorizationFlow { actions: None, .. },
+            }
+        );
+
+        !next_unknown && !self.is_expected_in(status)
+    }
+}
+
+/// Progress of the authorization flow of a payment, reported to the listeners registered with
+/// [`with_flow_event_listener`](crate::client::TrueLayerClientBuilder::with_flow_event_listener).
+///
+/// Events are meant for product analytics, e.g. to measure the drop-off at each step of a checkout.
+/// They never carry the form inputs or the return parameters submitted by the user, and redirect
+/// URIs are stripped of their query and fragment, which can hold session tokens. Failure reasons
+/// are error messages, which can mention the details of the payment: review them before
+/// forwarding the events to third parties.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum FlowEvent {
+    /// The user selected a provider.
+    ProviderSelected {
+        payment_id: String,
+        provider_id: String,
+    },
+    /// The user must be redirected to the provider.
+    RedirectIssued {
+        payment_id: String,
+        /// Redirect URI, without its query and fragment.
+        uri: String,
+    },
+    /// The user gave their consent to the payment.
+    ConsentSubmitted { payment_id: String },
+    /// An action of the flow failed, either because the request failed
+    /// or because TrueLayer reported the flow as failed.
+    ActionFailed {
+        payment_id: String,
+        action: FlowAction,
+        /// Stage at which the flow failed, if reported by TrueLayer.
+        failure_stage: Option<FailureStage>,
+        failure_reason: String,
+    },
+}
+
+impl FlowEvent {
+    /// Returns the id of the payment whose flow emitted this event.
+    pub fn payment_id(&self) -> &str {
+        match self {
+            FlowEvent::ProviderSelected { payment_id, .. }
+            | FlowEvent::RedirectIssued { payment_id, .. }
+            | FlowEvent::ConsentSubmitted { payment_id }
+            | FlowEvent::ActionFailed { payment_id, .. } => payment_id,
+        }
+    }
+}
+
+/// Listeners of the [`FlowEvent`]s emitted by a client.
+#[derive(Clone, Default)]
+pub(crate) struct FlowEventListeners(Vec<Arc<dyn Fn(&FlowEvent) + Send + Sync + 'static>>);
+
+impl Debug for FlowEventListeners {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FlowEventListeners")
+            .field(&self.0.len())
+            .finish()
+    }
+}
+
+impl FlowEventListeners {
+    pub(crate) fn push(&mut self, listener: impl Fn(&FlowEvent) + Send + Sync + 'static) {
+        self.0.push(Arc::new(listener));
+    }
+
+    pub(crate) fn emit(&self, event: FlowEvent) {
+        for listener in &self.0 {
+            listener(&event);
+        }
+    }
+
+    /// Emits the events describing the outcome of an action.
+    pub(crate) fn observe<R: FlowActionResponse>(
+        &self,
+        payment_id: &str,
+        action: FlowAction,
+        res: &Result<R, Error>,
+    ) {
+        if self.0.is_empty() {
+            return;
+        }
+
+        let res = match res {
+            Ok(res) => res,
+            Err(e) => {
+                return self.emit(FlowEvent::ActionFailed {
+                    payment_id: payment_id.to_string(),
+                    action,
+                    failure_stage: None,
+                    failure_reason: e.to_string(),
+                })
+            }
+        };
+
+        if let AuthorizationFlowResponseStatus::Failed {
+            failure_stage,
+            failure_reason,
+        } = res.status()
+        {
+            return self.emit(FlowEvent::ActionFailed {
+                payment_id: payment_id.to_string(),
+                action,
+                failure_stage: Some(failure_stage.clone()),
+                failure_reason: failure_reason.clone(),
+            });
+        }
+
+        if let Some(AuthorizationFlowNextAction::Redirect { uri, .. }) = res
+            .authorization_flow()
+            .and_then(|flow| flow.actions.as_ref())
+            .map(|actions| &actions.next)
+        {
+            self.emit(FlowEvent::RedirectIssued {
+                payment_id: payment_id.to_string(),
+                uri: uri.split(['?', '#']).next().unwrap_or_default().to_string(),
+            });
+        }
+    }
+}
+
+/// Returns `true` if an action was accepted, and did not fail the authorization flow.
+pub(crate) fn action_succeeded<R: FlowActionResponse>(res: &Result<R, Error>) -> bool {
+    matches!(res, Ok(res) if !matches!(res.status(), AuthorizationFlowResponseStatus::Failed { .. }))
+}
+
+/// Response of an action of the authorization flow.
+pub(crate) trait FlowActionResponse {
+    fn authorization_flow(&self) -> Option<&AuthorizationFlow>;
+    fn status(&self) -> &AuthorizationFlowResponseStatus;
+}
+
+impl FlowActionResponse for StartAuthorizationFlowResponse {
+    fn authorization_flow(&self) -> Option<&AuthorizationFlow> {
+        self.authorization_flow.as_ref()
+    }
+
+    fn status(&self) -> &AuthorizationFlowResponseStatus {
+        &self.status
+    }
+}
+
+impl FlowActionResponse for SubmitProviderSelectionActionResponse {
+    fn authorization_flow(&self) -> Option<&AuthorizationFlow> {
+        self.authorization_flow.as_ref()
+    }
+
+    fn status(&self) -> &AuthorizationFlowResponseStatus {
+        &self.status
+    }
+}
+
+impl FlowActionResponse for SubmitConsentActionResponse {
+    fn authorization_flow(&self) -> Option<&AuthorizationFlow> {
+        self.authorization_flow.as_ref()
+    }
+
+    fn status(&self) -> &AuthorizationFlowResponseStatus {
+        &self.status
+    }
+}
+
+impl FlowActionResponse for SubmitFormActionResponse {
+    fn authorization_flow(&self) -> Option<&AuthorizationFlow> {
+        self.authorization_flow.as_ref()
+    }
+
+    fn status(&self) -> &AuthorizationFlowResponseStatus {
+        &self.status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::payments::{AdditionalInputDisplayText, AuthorizationFlowActions};
+
+    fn form_flow() -> AuthorizationFlow {
+        AuthorizationFlow {
+            actions: Some(AuthorizationFlowActions {
+                next: AuthorizationFlowNextAction::Form {
+                    inputs: vec![AdditionalInput::Select {
+                        id: "branch".to_string(),
+                        mandatory: true,
+                        display_text: AdditionalInputDisplayText {
+                            key: "branch.display_text".to_string(),
+                            default: "Branch".to_string(),
+                        },
+                        description: None,
+                        options: vec![],
+                    }],
+                },
+            }),
+            configuration: None,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn form_schemas_expire() {
+        let cache = FlowCache::new(Duration::from_secs(60));
+        cache.observe("provider-id", Some(&form_flow()));
+        assert!(cache.form_schema("provider-id").is_some());
+
+        tokio::time::advance(Duration::from_secs(61)).await;
+        assert!(cache.form_schema("provider-id").is_none());
+    }
+
+    #[test]
+    fn forms_returned_when_starting_preselected_payments_are_cached() {
+        let cache = FlowCache::new(DEFAULT_FORM_SCHEMA_TTL);
+        cache.remember_provider("payment-id", "provider-id");
+
+        // The provider of other payments is unknown
+        cache.observe_start("other-payment-id", Some(&form_flow()));
+        assert!(cache.form_schema("provider-id").is_none());
+
+        cache.observe_start("payment-id", Some(&form_flow()));
+        assert!(cache.form_schema("provider-id").is_some());
+        assert!(cache.preselected_providers.lock().unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn zero_ttl_disables_cache() {
+        let cache = FlowCache::new(Duration::ZERO);
+        cache.observe("provider-id", Some(&form_flow()));
+        assert!(cache.form_schema("provider-id").is_none());
+    }
+}
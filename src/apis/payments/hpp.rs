@@ -0,0 +1,240 @@
+use crate::{
+    apis::{
+        auth::Token,
+        payments::{HppLanguage, Locale},
+    },
+    client::Environment,
+};
+use reqwest::Url;
+use std::{fmt, time::Duration};
+
+/// Longest time the HPP can wait for the final result of a payment before redirecting the user.
+pub const MAX_HPP_WAIT_FOR_RESULT: Duration = Duration::from_secs(60);
+
+/// Error returned when a [`HppLinkBuilder`] cannot build a link.
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum HppLinkBuilderError {
+    /// The `return_uri` was not set.
+    #[error("Missing return_uri")]
+    MissingReturnUri,
+    /// The maximum wait exceeds [`MAX_HPP_WAIT_FOR_RESULT`].
+    #[error("Maximum wait for result of {0:?} exceeds {MAX_HPP_WAIT_FOR_RESULT:?}")]
+    MaxWaitTooLong(Duration),
+}
+
+/// Where the HPP appends the parameters describing the outcome of the payment to the `return_uri`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub enum HppResultMode {
+    /// In the query string, readable by the server serving the `return_uri`.
+    #[default]
+    Query,
+    /// In the fragment, which browsers never send to servers.
+    Fragment,
+}
+
+impl HppResultMode {
+    /// Returns the value of the `result_mode` parameter for this mode.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HppResultMode::Query => "query",
+            HppResultMode::Fragment => "fragment",
+        }
+    }
+}
+
+impl fmt::Display for HppResultMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Builder for a link to the TrueLayer Hosted Payments Page (HPP).
+///
+/// The link points to the HPP of the environment the builder was created for, so the same code
+/// produces Sandbox links in tests and Live links in production.
+///
+/// ```rust
+/// # use truelayer_rust::{apis::payments::{HppLanguage, HppLinkBuilder}, client::Environment};
+/// # use std::time::Duration;
+/// let link = HppLinkBuilder::new(&Environment::Sandbox, "payment-id", &"resource-token".into())
+///     .return_uri("https://my.return.uri")
+///     .language(HppLanguage::Fr)
+///     .max_wait_for_result(Duration::from_secs(30))
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(link.host_str(), Some("payment.truelayer-sandbox.com"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HppLinkBuilder {
+    hpp_url: Url,
+    payment_id: String,
+    resource_token: Token,
+    return_uri: Option<String>,
+    result_mode: Option<HppResultMode>,
+    language: Option<HppLanguage>,
+    max_wait_for_result: Option<Duration>,
+}
+
+impl HppLinkBuilder {
+    /// Starts building a link to the HPP of the given environment for a payment.
+    ///
+    /// Use [`PaymentsApi::hosted_payments_page_link`](crate::apis::payments::PaymentsApi::hosted_payments_page_link)
+    /// to start from the environment of a client.
+    pub fn new(environment: &Environment, payment_id: &str, resource_token: &Token) -> Self {
+        Self {
+            hpp_url: environment.hpp_url(),
+            payment_id: payment_id.to_string(),
+            resource_token: resource_token.clone(),
+            return_uri: None,
+            result_mode: None,
+            language: None,
+            max_wait_for_result: None,
+        }
+    }
+
+    /// Sets where the user is sent back once the payment is authorized. Mandatory.
+    ///
+    /// Note that the `return_uri` must be configured in your TrueLayer console.
+    pub fn return_uri(mut self, return_uri: impl Into<String>) -> Self {
+        self.return_uri = Some(return_uri.into());
+        self
+    }
+
+    /// Sets where the outcome of the payment is appended to the `return_uri`. Defaults to the HPP default.
+    pub fn result_mode(mut self, result_mode: HppResultMode) -> Self {
+        self.result_mode = Some(result_mode);
+        self
+    }
+
+    /// Sets the language of the HPP. Defaults to the language of the browser of the user.
+    pub fn language(mut self, language: HppLanguage) -> Self {
+        self.language = Some(language);
+        self
+    }
+
+    /// Sets the language of the HPP from the locale of the user, e.g. the one of their account.
+    ///
+    /// The HPP keeps using the language of the browser of the user if it is not translated
+    /// into the language of the locale.
+    pub fn locale(mut self, locale: &Locale) -> Self {
+        self.language = HppLanguage::from_tag(locale.as_str());
+        self
+    }
+
+    /// Sets how long the HPP waits for the final result of the payment before redirecting the user,
+    /// with a granularity of one second and a maximum of [`MAX_HPP_WAIT_FOR_RESULT`].
+    pub fn max_wait_for_result(mut self, max_wait_for_result: Duration) -> Self {
+        self.max_wait_for_result = Some(max_wait_for_result);
+        self
+    }
+
+    /// Builds the link.
+    pub fn build(self) -> Result<Url, HppLinkBuilderError> {
+        let return_uri = self
+            .return_uri
+            .ok_or(HppLinkBuilderError::MissingReturnUri)?;
+
+        let mut fragment = form_urlencoded::Serializer::new(String::new());
+        fragment
+            .append_pair("payment_id", &self.payment_id)
+            .append_pair("resource_token", self.resource_token.expose_secret())
+            .append_pair("return_uri", &return_uri);
+        if let Some(result_mode) = self.result_mode {
+            fragment.append_pair("result_mode", result_mode.as_str());
+        }
+        if let Some(language) = self.language {
+            fragment.append_pair("lang", language.as_str());
+        }
+        if let Some(max_wait) = self.max_wait_for_result {
+            if max_wait > MAX_HPP_WAIT_FOR_RESULT {
+                return Err(HppLinkBuilderError::MaxWaitTooLong(max_wait));
+            }
+            fragment.append_pair("max_wait_for_result", &max_wait.as_secs().to_string());
+        }
+
+        let mut link = self.hpp_url.join("/payments").unwrap();
+        link.set_fragment(Some(&fragment.finish()));
+
+        Ok(link)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_include_all_the_parameters_set() {
+        let link = HppLinkBuilder::new(&Environment::Live, "payment-id", &"resource-token".into())
+            .return_uri("https://return.uri")
+            .result_mode(HppResultMode::Fragment)
+            .language(HppLanguage::De)
+            .max_wait_for_result(Duration::from_secs(45))
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            link.as_str(),
+            "https://payment.truelayer.com/payments#payment_id=payment-id&resource_token=resource-token\
+            &return_uri=https%3A%2F%2Freturn.uri&result_mode=fragment&lang=de&max_wait_for_result=45"
+        );
+    }
+
+    #[test]
+    fn parameters_are_encoded() {
+        let link = HppLinkBuilder::new(&Environment::Live, "payment id", &"resource-token".into())
+            .return_uri("https://return.uri/?order=1&step=2#done")
+            .build()
+            .unwrap();
+
+        let parameters: Vec<(String, String)> =
+            form_urlencoded::parse(link.fragment().unwrap().as_bytes())
+                .into_owned()
+                .collect();
+        assert_eq!(
+            parameters,
+            [
+                ("payment_id", "payment id"),
+                ("resource_token", "resource-token"),
+                ("return_uri", "https://return.uri/?order=1&step=2#done"),
+            ]
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+        );
+    }
+
+    #[test]
+    fn locales_set_the_language_when_supported() {
+        let builder =
+            HppLinkBuilder::new(&Environment::Live, "payment-id", &"resource-token".into())
+                .return_uri("https://return.uri");
+
+        let link = builder.clone().locale(&Locale::FrFr).build().unwrap();
+        assert!(link.fragment().unwrap().ends_with("&lang=fr"));
+
+        // Unsupported languages are left to the browser of the user
+        let link = builder.locale(&Locale::other("ja-JP")).build().unwrap();
+        assert!(!link.fragment().unwrap().contains("lang="));
+    }
+
+    #[test]
+    fn invalid_links_are_rejected() {
+        let builder = HppLinkBuilder::new(
+            &Environment::Sandbox,
+            "payment-id",
+            &"resource-token".into(),
+        );
+
+        assert_eq!(
+            builder.clone().build(),
+            Err(HppLinkBuilderError::MissingReturnUri)
+        );
+        assert_eq!(
+            builder
+                .return_uri("https://return.uri")
+                .max_wait_for_result(Duration::from_secs(61))
+                .build(),
+            Err(HppLinkBuilderError::MaxWaitTooLong(Duration::from_secs(61)))
+        );
+    }
+}
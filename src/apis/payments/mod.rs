@@ -1,7 +1,18 @@
 //! APIs and models related to payments.
 
 mod api;
+pub mod assets;
+mod beneficiary_templates;
+mod builder;
+pub mod flow;
+mod hpp;
 mod model;
+mod reference;
+pub mod ui;
 
 pub use api::PaymentsApi;
+pub use beneficiary_templates::*;
+pub use builder::*;
+pub use hpp::*;
 pub use model::*;
+pub use reference::*;
@@ -1,20 +1,82 @@
-use crate::{apis::auth::Token, pollable::IsInTerminalState, Error, Pollable, TrueLayerClient};
+use crate::{
+    apis::auth::Token,
+    pagination::Paginated,
+    pollable::{HasId, HasStatus, IsInTerminalState},
+    Error, Pollable, TrueLayerClient,
+};
 use anyhow::anyhow;
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     collections::HashMap,
+    convert::Infallible,
     fmt::{Display, Formatter},
+    str::FromStr,
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct CreatePaymentRequest {
+    #[serde(deserialize_with = "crate::amounts::deserialize_minor")]
     pub amount_in_minor: u64,
     pub currency: Currency,
     pub payment_method: PaymentMethodRequest,
     pub user: CreatePaymentUserRequest,
     pub metadata: Option<HashMap<String, String>>,
+    /// Lets TrueLayer retry the payment if an attempt fails, e.g. for insufficient funds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<PaymentRetry>,
+    /// Other TrueLayer products to use alongside the payment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub related_products: Option<RelatedProducts>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct RelatedProducts {
+    /// Enables Signup+, to retrieve the verified identity of the payer once the payment is settled with
+    /// [`get_signup_plus_user_data`](crate::apis::payments::PaymentsApi::get_signup_plus_user_data).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signup_plus: Option<SignupPlus>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct SignupPlus {}
+
+/// Identity of a payer, as verified by their bank through Signup+.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct SignupPlusUserData {
+    pub title: Option<String>,
+    pub first_name: String,
+    pub last_name: String,
+    pub date_of_birth: Option<NaiveDate>,
+    pub address: Option<SignupPlusAddress>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct SignupPlusAddress {
+    pub address_line1: String,
+    pub address_line2: Option<String>,
+    pub city: String,
+    pub state: Option<String>,
+    pub zip: String,
+    pub country_code: CountryCode,
+}
+
+/// Configuration of the automatic retries of a payment.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentRetry {
+    /// Failed attempts are retried according to TrueLayer's default schedule.
+    Standard {},
+    /// Failed attempts are retried when the payer is most likely to have enough funds.
+    Smart {
+        /// How long to keep retrying, as an ISO 8601 duration (e.g. `P7D`).
+        #[serde(rename = "for")]
+        retry_for: String,
+        /// Balance which must be left in the payer's account after the payment.
+        ensure_minimum_balance_in_minor: Option<u64>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
@@ -126,6 +188,63 @@ impl Pollable for CreatePaymentResponse {
     }
 }
 
+/// Minimal set of information needed by TrueLayer's web and mobile SDKs to authorize a payment.
+///
+/// Use this type to hand off a payment to a frontend instead of serializing whole response structs,
+/// which may contain personal information that should not leave the backend.
+///
+/// ```rust
+/// # use truelayer_rust::apis::payments::FrontendToken;
+/// let token = FrontendToken::new("payment-id", "resource-token");
+///
+/// // The resource token is still redacted when printed with Debug
+/// assert!(!format!("{:?}", token).contains("resource-token"));
+///
+/// // But it is included in the serialized forms meant to be sent to the frontend
+/// assert!(token.to_json().contains("resource-token"));
+/// assert!(!token.to_base64().is_empty());
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FrontendToken {
+    pub payment_id: String,
+    pub resource_token: Token,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl FrontendToken {
+    /// Builds a new token for the given payment.
+    pub fn new(payment_id: impl Into<String>, resource_token: impl Into<Token>) -> Self {
+        Self {
+            payment_id: payment_id.into(),
+            resource_token: resource_token.into(),
+            expires_at: None,
+        }
+    }
+
+    /// Sets the instant after which the resource token can no longer be used.
+    pub fn with_expires_at(mut self, expires_at: DateTime<Utc>) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Serializes this token as compact JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("FrontendToken is always serializable")
+    }
+
+    /// Serializes this token as compact JSON encoded with URL-safe base64 without padding.
+    pub fn to_base64(&self) -> String {
+        URL_SAFE_NO_PAD.encode(self.to_json())
+    }
+}
+
+impl From<&CreatePaymentResponse> for FrontendToken {
+    fn from(res: &CreatePaymentResponse) -> Self {
+        FrontendToken::new(res.id.clone(), res.resource_token.clone())
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct CreatePaymentUserResponse {
     pub id: String,
@@ -134,16 +253,90 @@ pub struct CreatePaymentUserResponse {
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct Payment {
     pub id: String,
+    #[serde(deserialize_with = "crate::amounts::deserialize_minor")]
     pub amount_in_minor: u64,
     pub currency: Currency,
     pub user: User,
     pub payment_method: PaymentMethod,
     pub created_at: DateTime<Utc>,
     pub metadata: Option<HashMap<String, String>>,
+    /// Retry configuration of the payment, if retries were requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<PaymentRetry>,
+    /// Attempts of the payment which failed and were retried, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<PaymentAttempt>,
     #[serde(flatten)]
     pub status: PaymentStatus,
 }
 
+/// A failed attempt of a payment with retries.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct PaymentAttempt {
+    pub failed_at: DateTime<Utc>,
+    pub failure_stage: FailureStage,
+    pub failure_reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct ListPaymentsRequest {
+    /// Only return payments in this status.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<PaymentStatusFilter>,
+    /// Only return payments created at or after this time.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_timestamp"
+    )]
+    pub from: Option<DateTime<Utc>>,
+    /// Only return payments created before this time.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_timestamp"
+    )]
+    pub to: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<Currency>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// Cursor of the page to fetch, from [`Paginated::next_cursor`].
+    /// If `None`, the first page is fetched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Maximum number of payments per page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentStatusFilter {
+    AuthorizationRequired,
+    Authorizing,
+    Authorized,
+    Executed,
+    Settled,
+    Failed,
+}
+
+/// Single page of payments.
+pub type PaymentsPage = Paginated<Payment>;
+
+fn serialize_optional_timestamp<S>(
+    timestamp: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match timestamp {
+        Some(timestamp) => {
+            serializer.serialize_str(&timestamp.to_rfc3339_opts(SecondsFormat::Millis, true))
+        }
+        None => serializer.serialize_none(),
+    }
+}
+
 #[async_trait]
 impl Pollable for Payment {
     type Output = Payment;
@@ -169,6 +362,119 @@ impl IsInTerminalState for Payment {
     }
 }
 
+impl HasId for Payment {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl HasStatus for Payment {
+    fn has_same_status(&self, other: &Self) -> bool {
+        std::mem::discriminant(&self.status) == std::mem::discriminant(&other.status)
+    }
+}
+
+impl Payment {
+    /// Returns the status transitions of this payment which carry a timestamp, in chronological order.
+    ///
+    /// TrueLayer does not expose a dedicated events endpoint, so the history is rebuilt
+    /// from the timestamps returned alongside the current status of the payment.
+    pub fn events(&self) -> Vec<PaymentEvent> {
+        let mut events = vec![PaymentEvent {
+            r#type: PaymentEventType::Created,
+            occurred_at: self.created_at,
+        }];
+        events.extend(self.attempts.iter().map(|attempt| PaymentEvent {
+            r#type: PaymentEventType::AttemptFailed {
+                failure_stage: attempt.failure_stage.clone(),
+                failure_reason: attempt.failure_reason.clone(),
+            },
+            occurred_at: attempt.failed_at,
+        }));
+
+        match &self.status {
+            PaymentStatus::Executed { executed_at, .. } => events.push(PaymentEvent {
+                r#type: PaymentEventType::Executed,
+                occurred_at: *executed_at,
+            }),
+            PaymentStatus::Settled {
+                executed_at,
+                settled_at,
+                ..
+            } => {
+                events.push(PaymentEvent {
+                    r#type: PaymentEventType::Executed,
+                    occurred_at: *executed_at,
+                });
+                events.push(PaymentEvent {
+                    r#type: PaymentEventType::Settled,
+                    occurred_at: *settled_at,
+                });
+            }
+            PaymentStatus::Failed {
+                failed_at,
+                failure_stage,
+                failure_reason,
+                ..
+            } => events.push(PaymentEvent {
+                r#type: PaymentEventType::Failed {
+                    failure_stage: failure_stage.clone(),
+                    failure_reason: failure_reason.clone(),
+                },
+                occurred_at: *failed_at,
+            }),
+            PaymentStatus::AttemptFailed {
+                failed_at,
+                failure_stage,
+                failure_reason,
+                ..
+            } if !self
+                .attempts
+                .iter()
+                .any(|attempt| attempt.failed_at == *failed_at) =>
+            {
+                events.push(PaymentEvent {
+                    r#type: PaymentEventType::AttemptFailed {
+                        failure_stage: failure_stage.clone(),
+                        failure_reason: failure_reason.clone(),
+                    },
+                    occurred_at: *failed_at,
+                })
+            }
+            PaymentStatus::AuthorizationRequired
+            | PaymentStatus::Authorizing { .. }
+            | PaymentStatus::Authorized { .. }
+            | PaymentStatus::AttemptFailed { .. } => {}
+        }
+
+        events
+    }
+}
+
+/// A status transition of a payment.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct PaymentEvent {
+    #[serde(flatten)]
+    pub r#type: PaymentEventType,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PaymentEventType {
+    Created,
+    Executed,
+    Settled,
+    Failed {
+        failure_stage: FailureStage,
+        failure_reason: String,
+    },
+    AttemptFailed {
+        failure_stage: FailureStage,
+        failure_reason: String,
+    },
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(tag = "status", rename_all = "snake_case")]
 pub enum PaymentStatus {
@@ -197,6 +503,31 @@ pub enum PaymentStatus {
         failure_reason: String,
         authorization_flow: Option<AuthorizationFlow>,
     },
+    /// The last attempt of a payment with retries failed, and TrueLayer will try again.
+    AttemptFailed {
+        failed_at: DateTime<Utc>,
+        failure_stage: FailureStage,
+        failure_reason: String,
+        authorization_flow: Option<AuthorizationFlow>,
+    },
+}
+
+impl PaymentStatus {
+    /// Failure reason of the payments cancelled with
+    /// [`PaymentsApi::cancel`](crate::apis::payments::PaymentsApi::cancel).
+    pub const CANCELLED_FAILURE_REASON: &'static str = "canceled";
+
+    /// Returns `true` if the payment was cancelled before being authorized.
+    ///
+    /// TrueLayer has no dedicated status for cancelled payments: they are reported
+    /// as `Failed` with the [`CANCELLED_FAILURE_REASON`](Self::CANCELLED_FAILURE_REASON).
+    pub fn is_cancelled(&self) -> bool {
+        matches!(
+            self,
+            PaymentStatus::Failed { failure_reason, .. }
+                if failure_reason == Self::CANCELLED_FAILURE_REASON
+        )
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
@@ -225,6 +556,9 @@ pub enum FailureStage {
     AuthorizationRequired,
     Authorizing,
     Authorized,
+    /// Any other stage not yet known to this library.
+    #[serde(other)]
+    Unknown,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
@@ -298,30 +632,59 @@ pub enum ProviderSelection {
     },
 }
 
+/// How the payment scheme is chosen when the provider is selected by the user.
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum SchemeSelection {
+    /// Only providers supporting an instant scheme are shown to the user.
     InstantOnly { allow_remitter_fee: Option<bool> },
+    /// Instant schemes are used when available, falling back to non-instant ones.
     InstantPreferred { allow_remitter_fee: Option<bool> },
+    /// The user picks the scheme, if the selected provider supports more than one.
+    UserSelected,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+/// Account from which the payer is expected to pay.
+///
+/// When set on a preselected provider, TrueLayer checks that the payment is made from this account,
+/// and the payer does not need to select it again in their bank.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
 pub struct Remitter {
     pub account_holder_name: Option<String>,
+    /// Identifier of the account, e.g. IBAN, sort code and account number or NRB.
     pub account_identifier: Option<AccountIdentifier>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+impl Remitter {
+    /// Creates a new remitter with both the account holder name and the account identifier.
+    pub fn new(
+        account_holder_name: impl Into<String>,
+        account_identifier: AccountIdentifier,
+    ) -> Self {
+        Self {
+            account_holder_name: Some(account_holder_name.into()),
+            account_identifier: Some(account_identifier),
+        }
+    }
+}
+
+/// Restricts the providers shown to the user.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
 pub struct ProviderFilter {
     pub countries: Option<Vec<CountryCode>>,
+    /// Minimum release channel of the providers. Providers in a more stable channel are also shown.
     pub release_channel: Option<ReleaseChannel>,
     pub customer_segments: Option<Vec<CustomerSegment>>,
+    /// Only show these providers. Takes precedence over all the other criteria.
     pub provider_ids: Option<Vec<String>>,
     pub excludes: Option<ProviderFilterExcludes>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
-#[serde(rename_all = "UPPERCASE")]
+/// ISO 3166-1 alpha-2 country code.
+///
+/// Countries supported by TrueLayer have their own variant. Any other value returned by the APIs
+/// is preserved in the [`Other`](CountryCode::Other) variant, built with [`CountryCode::other`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum CountryCode {
     AT,
     BE,
@@ -339,6 +702,286 @@ pub enum CountryCode {
     PL,
     PT,
     RO,
+    Other(OtherCountryCode),
+}
+
+/// Country code without its own [`CountryCode`] variant, always in uppercase.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OtherCountryCode(String);
+
+impl OtherCountryCode {
+    /// Returns the two letter code of this country.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl CountryCode {
+    /// Builds a country code from its two letter code, regardless of its case.
+    ///
+    /// Codes of the countries which have their own variant return that variant,
+    /// so that the same country is always represented the same way.
+    pub fn other(code: &str) -> CountryCode {
+        let code = code.to_ascii_uppercase();
+        match code.as_str() {
+            "AT" => CountryCode::AT,
+            "BE" => CountryCode::BE,
+            "DE" => CountryCode::DE,
+            "DK" => CountryCode::DK,
+            "ES" => CountryCode::ES,
+            "FI" => CountryCode::FI,
+            "FR" => CountryCode::FR,
+            "GB" => CountryCode::GB,
+            "IE" => CountryCode::IE,
+            "IT" => CountryCode::IT,
+            "LT" => CountryCode::LT,
+            "NL" => CountryCode::NL,
+            "NO" => CountryCode::NO,
+            "PL" => CountryCode::PL,
+            "PT" => CountryCode::PT,
+            "RO" => CountryCode::RO,
+            _ => CountryCode::Other(OtherCountryCode(code)),
+        }
+    }
+
+    /// Returns the two letter code of this country.
+    pub fn as_str(&self) -> &str {
+        match self {
+            CountryCode::AT => "AT",
+            CountryCode::BE => "BE",
+            CountryCode::DE => "DE",
+            CountryCode::DK => "DK",
+            CountryCode::ES => "ES",
+            CountryCode::FI => "FI",
+            CountryCode::FR => "FR",
+            CountryCode::GB => "GB",
+            CountryCode::IE => "IE",
+            CountryCode::IT => "IT",
+            CountryCode::LT => "LT",
+            CountryCode::NL => "NL",
+            CountryCode::NO => "NO",
+            CountryCode::PL => "PL",
+            CountryCode::PT => "PT",
+            CountryCode::RO => "RO",
+            CountryCode::Other(code) => code.as_str(),
+        }
+    }
+}
+
+impl FromStr for CountryCode {
+    type Err = Infallible;
+
+    /// Parses a country code, see [`CountryCode::other`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(CountryCode::other(s))
+    }
+}
+
+impl Display for CountryCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for CountryCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for CountryCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(CountryCode::other(&s))
+    }
+}
+
+/// Language and region (BCP 47) used to localise TrueLayer user facing pages.
+///
+/// Locales supported by TrueLayer have their own variant. Any other value is preserved
+/// in the [`Other`](Locale::Other) variant, built with [`Locale::other`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Locale {
+    DeDe,
+    EnGb,
+    EnIe,
+    EsEs,
+    FrFr,
+    ItIt,
+    LtLt,
+    NlNl,
+    PlPl,
+    PtPt,
+    Other(OtherLocale),
+}
+
+/// Locale without its own [`Locale`] variant, always in the canonical case of BCP 47
+/// and separated by `-`, e.g. `fr-BE` or `zh-Hant-TW`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct OtherLocale(String);
+
+impl OtherLocale {
+    /// Returns the BCP 47 tag of this locale.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Locale {
+    /// Builds a locale from a BCP 47 tag, regardless of its case and of the separator (`-` or `_`).
+    ///
+    /// Tags of the locales which have their own variant return that variant,
+    /// so that the same locale is always represented the same way.
+    pub fn other(tag: &str) -> Locale {
+        let tag = tag
+            .split(['-', '_'])
+            .enumerate()
+            .map(|(i, subtag)| {
+                let mut subtag = subtag.to_ascii_lowercase();
+                match (i, subtag.len()) {
+                    (0, _) => {}
+                    // Region
+                    (_, 2) => subtag.make_ascii_uppercase(),
+                    // Script
+                    (_, 4) => {
+                        if let Some(first) = subtag.get_mut(..1) {
+                            first.make_ascii_uppercase()
+                        }
+                    }
+                    _ => {}
+                }
+                subtag
+            })
+            .collect::<Vec<_>>()
+            .join("-");
+
+        match tag.as_str() {
+            "de-DE" => Locale::DeDe,
+            "en-GB" => Locale::EnGb,
+            "en-IE" => Locale::EnIe,
+            "es-ES" => Locale::EsEs,
+            "fr-FR" => Locale::FrFr,
+            "it-IT" => Locale::ItIt,
+            "lt-LT" => Locale::LtLt,
+            "nl-NL" => Locale::NlNl,
+            "pl-PL" => Locale::PlPl,
+            "pt-PT" => Locale::PtPt,
+            _ => Locale::Other(OtherLocale(tag)),
+        }
+    }
+
+    /// Returns the BCP 47 tag of this locale.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Locale::DeDe => "de-DE",
+            Locale::EnGb => "en-GB",
+            Locale::EnIe => "en-IE",
+            Locale::EsEs => "es-ES",
+            Locale::FrFr => "fr-FR",
+            Locale::ItIt => "it-IT",
+            Locale::LtLt => "lt-LT",
+            Locale::NlNl => "nl-NL",
+            Locale::PlPl => "pl-PL",
+            Locale::PtPt => "pt-PT",
+            Locale::Other(tag) => tag.as_str(),
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = Infallible;
+
+    /// Parses a BCP 47 tag, see [`Locale::other`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Locale::other(s))
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for Locale {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Locale {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(Locale::other(&s))
+    }
+}
+
+/// Language of the TrueLayer Hosted Payments Page, sent as the `lang` parameter of the HPP link.
+///
+/// Only the languages the HPP is translated into have a variant, as any other value
+/// would be silently ignored by the HPP.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HppLanguage {
+    De,
+    #[default]
+    En,
+    Es,
+    Fr,
+    It,
+    Lt,
+    Nl,
+    Pl,
+    Pt,
+}
+
+impl HppLanguage {
+    /// Returns the value of the `lang` parameter for this language.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HppLanguage::De => "de",
+            HppLanguage::En => "en",
+            HppLanguage::Es => "es",
+            HppLanguage::Fr => "fr",
+            HppLanguage::It => "it",
+            HppLanguage::Lt => "lt",
+            HppLanguage::Nl => "nl",
+            HppLanguage::Pl => "pl",
+            HppLanguage::Pt => "pt",
+        }
+    }
+
+    /// Returns the HPP language matching the primary language of a BCP 47 tag
+    /// (e.g. `fr` or `fr-BE`), or `None` if the HPP does not support it.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag.split(['-', '_']).next().unwrap_or_default();
+        Some(match language.to_ascii_lowercase().as_str() {
+            "de" => HppLanguage::De,
+            "en" => HppLanguage::En,
+            "es" => HppLanguage::Es,
+            "fr" => HppLanguage::Fr,
+            "it" => HppLanguage::It,
+            "lt" => HppLanguage::Lt,
+            "nl" => HppLanguage::Nl,
+            "pl" => HppLanguage::Pl,
+            "pt" => HppLanguage::Pt,
+            _ => return None,
+        })
+    }
+}
+
+impl From<&Locale> for HppLanguage {
+    /// Picks the HPP language for the given locale, falling back to
+    /// [`HppLanguage::default()`] for languages the HPP does not support.
+    fn from(locale: &Locale) -> Self {
+        HppLanguage::from_tag(locale.as_str()).unwrap_or_default()
+    }
+}
+
+impl Display for HppLanguage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
@@ -357,7 +1000,7 @@ pub enum CustomerSegment {
     Corporate,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
 pub struct ProviderFilterExcludes {
     pub provider_ids: Option<Vec<String>>,
 }
@@ -450,6 +1093,26 @@ pub enum AdditionalInput {
     },
 }
 
+impl AdditionalInput {
+    /// Returns the id of this input, used as key when submitting the form.
+    pub fn id(&self) -> &str {
+        match self {
+            AdditionalInput::Text { id, .. }
+            | AdditionalInput::Select { id, .. }
+            | AdditionalInput::TextWithImage { id, .. } => id,
+        }
+    }
+
+    /// Returns `true` if a value must be submitted for this input.
+    pub fn is_mandatory(&self) -> bool {
+        match self {
+            AdditionalInput::Text { mandatory, .. }
+            | AdditionalInput::Select { mandatory, .. }
+            | AdditionalInput::TextWithImage { mandatory, .. } => *mandatory,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct AdditionalInputDisplayText {
     pub key: String,
@@ -565,6 +1228,21 @@ pub struct SubmitFormActionRequest {
     pub inputs: HashMap<String, String>,
 }
 
+impl SubmitFormActionRequest {
+    /// Returns the ids of the mandatory inputs of a `form` action which have no value
+    /// in this request, so that they can be reported to the user before submitting the form.
+    pub fn missing_inputs<'a>(&self, form_inputs: &'a [AdditionalInput]) -> Vec<&'a str> {
+        form_inputs
+            .iter()
+            .filter(|input| input.is_mandatory())
+            .filter(|input| {
+                !matches!(self.inputs.get(input.id()), Some(value) if !value.trim().is_empty())
+            })
+            .map(AdditionalInput::id)
+            .collect()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct SubmitFormActionResponse {
     pub authorization_flow: Option<AuthorizationFlow>,
@@ -606,13 +1284,24 @@ pub mod refunds {
     use async_trait::async_trait;
     use chrono::{DateTime, Utc};
     use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
 
-    use crate::{pollable::IsInTerminalState, Error, Pollable, TrueLayerClient};
+    use crate::{
+        pollable::{HasId, HasStatus, IsInTerminalState},
+        Error, Pollable, TrueLayerClient,
+    };
 
     use super::Currency;
 
     #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
     pub struct CreateRefundRequest {
+        /// Amount to refund. If `None`, the full amount of the payment is refunded.
+        ///
+        /// Multiple partial refunds can be created for the same payment, up to its total amount.
+        #[serde(
+            default,
+            deserialize_with = "crate::amounts::deserialize_optional_minor"
+        )]
         pub amount_in_minor: Option<u64>,
         pub reference: String,
         pub metadata: Option<HashMap<String, String>>,
@@ -623,6 +1312,63 @@ pub mod refunds {
         pub id: String,
     }
 
+    /// Refund to create with [`PaymentsApi::refund_batch`](crate::apis::payments::PaymentsApi::refund_batch).
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct RefundBatchItem {
+        pub payment_id: String,
+        pub request: CreateRefundRequest,
+        /// Idempotency key of the refund creation request.
+        ///
+        /// Persist it alongside the item to safely retry the item in a later batch
+        /// without refunding the same payment twice.
+        pub idempotency_key: String,
+    }
+
+    impl RefundBatchItem {
+        /// Creates a new item with a random idempotency key.
+        pub fn new(payment_id: impl Into<String>, request: CreateRefundRequest) -> Self {
+            Self {
+                payment_id: payment_id.into(),
+                request,
+                idempotency_key: Uuid::new_v4().to_string(),
+            }
+        }
+    }
+
+    /// Outcome of a single item of a refund batch.
+    #[derive(Debug)]
+    pub struct RefundBatchOutcome {
+        pub item: RefundBatchItem,
+        pub result: Result<CreateRefundResponse, Error>,
+    }
+
+    /// Report of a refund batch, with one outcome for each item in the same order as they were submitted.
+    #[derive(Debug)]
+    pub struct RefundBatchReport {
+        pub outcomes: Vec<RefundBatchOutcome>,
+    }
+
+    impl RefundBatchReport {
+        /// Items refunded successfully.
+        pub fn succeeded(&self) -> impl Iterator<Item = (&RefundBatchItem, &CreateRefundResponse)> {
+            self.outcomes
+                .iter()
+                .filter_map(|o| o.result.as_ref().ok().map(|res| (&o.item, res)))
+        }
+
+        /// Items which could not be refunded, to be retried later with the same idempotency key.
+        pub fn failed(&self) -> impl Iterator<Item = (&RefundBatchItem, &Error)> {
+            self.outcomes
+                .iter()
+                .filter_map(|o| o.result.as_ref().err().map(|e| (&o.item, e)))
+        }
+
+        /// Returns `true` if all the items were refunded successfully.
+        pub fn is_complete(&self) -> bool {
+            self.outcomes.iter().all(|o| o.result.is_ok())
+        }
+    }
+
     #[async_trait]
     impl Pollable for (&str, CreateRefundResponse) {
         type Output = Refund;
@@ -639,6 +1385,7 @@ pub mod refunds {
     #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
     pub struct Refund {
         pub id: String,
+        #[serde(deserialize_with = "crate::amounts::deserialize_minor")]
         pub amount_in_minor: u64,
         pub currency: Currency,
         pub reference: String,
@@ -671,6 +1418,18 @@ pub mod refunds {
         }
     }
 
+    impl HasId for Refund {
+        fn id(&self) -> &str {
+            &self.id
+        }
+    }
+
+    impl HasStatus for Refund {
+        fn has_same_status(&self, other: &Self) -> bool {
+            std::mem::discriminant(&self.status) == std::mem::discriminant(&other.status)
+        }
+    }
+
     #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
     #[serde(tag = "status", rename_all = "snake_case")]
     pub enum RefundStatus {
@@ -685,3 +1444,93 @@ pub mod refunds {
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const COUNTRY_CODES: [CountryCode; 16] = [
+        CountryCode::AT,
+        CountryCode::BE,
+        CountryCode::DE,
+        CountryCode::DK,
+        CountryCode::ES,
+        CountryCode::FI,
+        CountryCode::FR,
+        CountryCode::GB,
+        CountryCode::IE,
+        CountryCode::IT,
+        CountryCode::LT,
+        CountryCode::NL,
+        CountryCode::NO,
+        CountryCode::PL,
+        CountryCode::PT,
+        CountryCode::RO,
+    ];
+
+    const LOCALES: [Locale; 10] = [
+        Locale::DeDe,
+        Locale::EnGb,
+        Locale::EnIe,
+        Locale::EsEs,
+        Locale::FrFr,
+        Locale::ItIt,
+        Locale::LtLt,
+        Locale::NlNl,
+        Locale::PlPl,
+        Locale::PtPt,
+    ];
+
+    #[test]
+    fn country_codes_round_trip() {
+        for country_code in COUNTRY_CODES {
+            let json = serde_json::to_value(&country_code).unwrap();
+            assert_eq!(json, country_code.as_str());
+            assert_eq!(
+                serde_json::from_value::<CountryCode>(json).unwrap(),
+                country_code
+            );
+            assert_eq!(CountryCode::other(country_code.as_str()), country_code);
+            assert_eq!(
+                CountryCode::other(&country_code.as_str().to_ascii_lowercase()),
+                country_code
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_country_codes_are_normalized() {
+        let country_code = CountryCode::other("zz");
+        assert!(matches!(&country_code, CountryCode::Other(code) if code.as_str() == "ZZ"));
+        assert_eq!(serde_json::to_value(&country_code).unwrap(), "ZZ");
+        assert_eq!(
+            serde_json::from_value::<CountryCode>(json!("Zz")).unwrap(),
+            country_code
+        );
+    }
+
+    #[test]
+    fn locales_round_trip() {
+        for locale in LOCALES {
+            let json = serde_json::to_value(&locale).unwrap();
+            assert_eq!(json, locale.as_str());
+            assert_eq!(serde_json::from_value::<Locale>(json).unwrap(), locale);
+            assert_eq!(Locale::other(locale.as_str()), locale);
+            assert_eq!(Locale::other(&locale.as_str().to_ascii_lowercase()), locale);
+            assert_eq!(Locale::other(&locale.as_str().replace('-', "_")), locale);
+        }
+    }
+
+    #[test]
+    fn unknown_locales_are_normalized() {
+        let locale = Locale::other("fr_be");
+        assert!(matches!(&locale, Locale::Other(tag) if tag.as_str() == "fr-BE"));
+        assert_eq!(serde_json::to_value(&locale).unwrap(), "fr-BE");
+        assert_eq!(
+            serde_json::from_value::<Locale>(json!("FR-be")).unwrap(),
+            locale
+        );
+        assert_eq!(Locale::other("ZH_hant_tw").as_str(), "zh-Hant-TW");
+    }
+}
@@ -0,0 +1,198 @@
+use std::{fmt, ops::Deref};
+
+use serde::{Deserialize, Serialize};
+
+use crate::apis::payments::Currency;
+
+/// Payment scheme whose rules a [`Reference`] must comply with.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ReferenceScheme {
+    /// UK Faster Payments: up to 18 characters among letters, digits, spaces and `- & / .`.
+    FasterPayments,
+    /// SEPA Credit Transfer and SEPA Instant: up to 140 characters
+    /// among letters, digits, spaces and `/ - ? : ( ) . , ' +`.
+    Sepa,
+}
+
+impl ReferenceScheme {
+    /// Scheme used by default by TrueLayer for payments and payouts in the given currency,
+    /// or `None` if the currency is not settled over a scheme with known reference rules.
+    pub fn for_currency(currency: &Currency) -> Option<Self> {
+        match currency {
+            Currency::Gbp => Some(ReferenceScheme::FasterPayments),
+            Currency::Eur => Some(ReferenceScheme::Sepa),
+            _ => None,
+        }
+    }
+
+    /// Maximum number of characters of a reference.
+    pub fn max_len(&self) -> usize {
+        match self {
+            ReferenceScheme::FasterPayments => 18,
+            ReferenceScheme::Sepa => 140,
+        }
+    }
+
+    /// Returns `true` if the character is allowed in a reference.
+    pub fn is_allowed(&self, c: char) -> bool {
+        c.is_ascii_alphanumeric()
+            || c == ' '
+            || match self {
+                ReferenceScheme::FasterPayments => matches!(c, '-' | '&' | '/' | '.'),
+                ReferenceScheme::Sepa => {
+                    matches!(
+                        c,
+                        '/' | '-' | '?' | ':' | '(' | ')' | '.' | ',' | '\'' | '+'
+                    )
+                }
+            }
+    }
+}
+
+/// Error returned when a string is not a valid [`Reference`] for a scheme.
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum ReferenceError {
+    #[error("Reference is empty")]
+    Empty,
+    #[error("Reference is {len} characters long, but at most {max_len} are allowed")]
+    TooLong { len: usize, max_len: usize },
+    #[error("Reference contains the invalid character {0:?}")]
+    InvalidCharacter(char),
+}
+
+/// Payment or payout reference which complies with the rules of a payment scheme.
+///
+/// Banks silently truncate or mangle references which do not comply with the rules of the scheme,
+/// which makes payments hard to reconcile. A `Reference` is either validated strictly with
+/// [`new`](Reference::new), or explicitly adapted to the scheme with [`truncate_lossy`](Reference::truncate_lossy).
+///
+/// ```rust
+/// # use truelayer_rust::apis::payments::{Reference, ReferenceScheme};
+/// assert!(Reference::new("Invoice 2022/0042", ReferenceScheme::FasterPayments).is_ok());
+/// assert!(Reference::new("Invoice #2022-0042-A", ReferenceScheme::FasterPayments).is_err());
+///
+/// let reference =
+///     Reference::truncate_lossy("Invoice #2022-0042-A", ReferenceScheme::FasterPayments).unwrap();
+/// assert_eq!(reference.as_str(), "Invoice 2022-0042-");
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
+#[serde(transparent)]
+pub struct Reference(String);
+
+impl Reference {
+    /// Validates a reference against the rules of the given scheme.
+    pub fn new(value: impl Into<String>, scheme: ReferenceScheme) -> Result<Self, ReferenceError> {
+        let value = value.into();
+
+        if value.trim().is_empty() {
+            return Err(ReferenceError::Empty);
+        }
+        if let Some(c) = value.chars().find(|&c| !scheme.is_allowed(c)) {
+            return Err(ReferenceError::InvalidCharacter(c));
+        }
+        let len = value.chars().count();
+        if len > scheme.max_len() {
+            return Err(ReferenceError::TooLong {
+                len,
+                max_len: scheme.max_len(),
+            });
+        }
+
+        Ok(Self(value))
+    }
+
+    /// Adapts a reference to the rules of the given scheme by dropping the characters which are
+    /// not allowed, collapsing repeated spaces and truncating it to the maximum length.
+    ///
+    /// Fails only if nothing is left of the reference.
+    pub fn truncate_lossy(value: &str, scheme: ReferenceScheme) -> Result<Self, ReferenceError> {
+        let mut adapted = String::with_capacity(value.len());
+        for c in value.chars().filter(|&c| scheme.is_allowed(c)) {
+            if c == ' ' && (adapted.is_empty() || adapted.ends_with(' ')) {
+                continue;
+            }
+            adapted.push(c);
+        }
+
+        let adapted: String = adapted.chars().take(scheme.max_len()).collect();
+        Self::new(adapted.trim_end(), scheme)
+    }
+
+    /// Returns the reference as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Reference {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<Reference> for String {
+    fn from(reference: Reference) -> Self {
+        reference.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strict_mode_rejects_non_compliant_references() {
+        assert_eq!(
+            Reference::new("   ", ReferenceScheme::Sepa),
+            Err(ReferenceError::Empty)
+        );
+        assert_eq!(
+            Reference::new("a".repeat(19), ReferenceScheme::FasterPayments),
+            Err(ReferenceError::TooLong {
+                len: 19,
+                max_len: 18
+            })
+        );
+        assert_eq!(
+            Reference::new("Order (42)", ReferenceScheme::FasterPayments),
+            Err(ReferenceError::InvalidCharacter('('))
+        );
+        assert_eq!(
+            Reference::new("Order (42)", ReferenceScheme::Sepa)
+                .unwrap()
+                .as_str(),
+            "Order (42)"
+        );
+    }
+
+    #[test]
+    fn lossy_mode_adapts_references() {
+        assert_eq!(
+            Reference::truncate_lossy(
+                "Café   Order #42 for Mr. Holder",
+                ReferenceScheme::FasterPayments
+            )
+            .unwrap()
+            .as_str(),
+            "Caf Order 42 for M"
+        );
+        assert_eq!(
+            Reference::truncate_lossy("Order 42 / ", ReferenceScheme::FasterPayments)
+                .unwrap()
+                .as_str(),
+            "Order 42 /"
+        );
+        assert_eq!(
+            Reference::truncate_lossy("###", ReferenceScheme::Sepa),
+            Err(ReferenceError::Empty)
+        );
+    }
+}
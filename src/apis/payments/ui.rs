@@ -0,0 +1,255 @@
+//! Ready-to-render data for embedded provider selection UIs.
+
+use serde::Serialize;
+
+use crate::apis::{
+    payments::{CountryCode, Provider, ReleaseChannel},
+    payments_providers::{self, ProviderAvailabilityStatus},
+};
+
+/// Snapshot of the providers a user can choose from during the authorization flow of a payment,
+/// enriched with the provider metadata returned by the payments providers API.
+///
+/// The snapshot is meant to be serialized and handed over as-is to a frontend rendering
+/// a provider selection screen.
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub struct ProviderCatalogSnapshot {
+    /// Providers grouped by country, in the order in which TrueLayer returned them.
+    pub countries: Vec<ProviderCountryGroup>,
+}
+
+/// Providers available in a single country.
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub struct ProviderCountryGroup {
+    /// Country of the providers in this group, or `None` for providers without a country.
+    pub country_code: Option<CountryCode>,
+    pub providers: Vec<CatalogProvider>,
+}
+
+/// A provider as displayed in a provider selection screen.
+#[derive(Serialize, Debug, Clone, Eq, PartialEq)]
+pub struct CatalogProvider {
+    pub id: String,
+    pub display_name: Option<String>,
+    /// Lowercase, accent-free form of the display name, suitable for client-side search.
+    pub search_name: String,
+    /// URL to fetch the provider's icon from.
+    pub icon_uri: Option<String>,
+    /// URL to fetch the provider's logo from.
+    pub logo_uri: Option<String>,
+    pub bg_color: Option<String>,
+    pub release_channel: Option<ReleaseChannel>,
+    pub scheme_ids: Vec<String>,
+    pub availability: Option<ProviderAvailabilityStatus>,
+}
+
+impl ProviderCatalogSnapshot {
+    /// Builds a snapshot from the providers returned by the `provider_selection` action of an
+    /// authorization flow and the metadata of (a superset of) those providers.
+    ///
+    /// Metadata is optional: providers without a match in `metadata` are included using only
+    /// the information from the authorization flow.
+    pub fn new(
+        providers: Vec<Provider>,
+        metadata: impl IntoIterator<Item = payments_providers::Provider>,
+    ) -> Self {
+        let mut metadata: Vec<payments_providers::Provider> = metadata.into_iter().collect();
+        let mut countries: Vec<ProviderCountryGroup> = Vec::new();
+
+        for provider in providers {
+            let meta = metadata
+                .iter()
+                .position(|m| m.id == provider.id)
+                .map(|idx| metadata.swap_remove(idx));
+
+            let country_code = provider
+                .country_code
+                .or_else(|| meta.as_ref().and_then(|m| m.country_code.clone()));
+            let display_name = provider
+                .display_name
+                .or_else(|| meta.as_ref().and_then(|m| m.display_name.clone()));
+            let bank_transfer = meta
+                .as_ref()
+                .and_then(|m| m.capabilities.payments.bank_transfer.as_ref());
+
+            let entry = CatalogProvider {
+                search_name: normalize_for_search(display_name.as_deref().unwrap_or(&provider.id)),
+                icon_uri: provider
+                    .icon_uri
+                    .or_else(|| meta.as_ref().and_then(|m| m.icon_uri.clone())),
+                logo_uri: provider
+                    .logo_uri
+                    .or_else(|| meta.as_ref().and_then(|m| m.logo_uri.clone())),
+                bg_color: provider
+                    .bg_color
+                    .or_else(|| meta.as_ref().and_then(|m| m.bg_color.clone())),
+                release_channel: bank_transfer.map(|b| b.release_channel.clone()),
+                scheme_ids: bank_transfer
+                    .map(|b| b.schemes.iter().map(|s| s.id.clone()).collect())
+                    .unwrap_or_default(),
+                availability: meta
+                    .as_ref()
+                    .and_then(|m| m.availability.as_ref())
+                    .map(|a| a.recommended_status.clone()),
+                id: provider.id,
+                display_name,
+            };
+
+            match countries
+                .iter_mut()
+                .find(|g| g.country_code == country_code)
+            {
+                Some(group) => group.providers.push(entry),
+                None => countries.push(ProviderCountryGroup {
+                    country_code,
+                    providers: vec![entry],
+                }),
+            }
+        }
+
+        Self { countries }
+    }
+
+    /// Iterates over all the providers in the snapshot.
+    pub fn providers(&self) -> impl Iterator<Item = &CatalogProvider> {
+        self.countries.iter().flat_map(|g| g.providers.iter())
+    }
+
+    /// Returns the providers whose name contains `query`, ignoring case and accents.
+    pub fn search<'a>(&'a self, query: &str) -> impl Iterator<Item = &'a CatalogProvider> {
+        let query = normalize_for_search(query);
+        self.providers()
+            .filter(move |p| p.search_name.contains(&query))
+    }
+}
+
+/// Lowercases `s`, strips the accents from common latin letters and collapses
+/// whitespace and punctuation into single spaces.
+fn normalize_for_search(s: &str) -> String {
+    let mut normalized = String::with_capacity(s.len());
+    for c in s.chars().flat_map(char::to_lowercase) {
+        let c = match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ą' => 'a',
+            'ç' | 'ć' | 'č' => 'c',
+            'è' | 'é' | 'ê' | 'ë' | 'ę' | 'ě' => 'e',
+            'ì' | 'í' | 'î' | 'ï' => 'i',
+            'ł' => 'l',
+            'ñ' | 'ń' | 'ň' => 'n',
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+            'ř' => 'r',
+            'ś' | 'š' | 'ș' | 'ş' => 's',
+            'ț' | 'ţ' | 'ť' => 't',
+            'ù' | 'ú' | 'û' | 'ü' | 'ů' => 'u',
+            'ý' | 'ÿ' => 'y',
+            'ź' | 'ż' | 'ž' => 'z',
+            'ß' => {
+                normalized.push_str("ss");
+                continue;
+            }
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        };
+        if c != ' ' || !(normalized.is_empty() || normalized.ends_with(' ')) {
+            normalized.push(c);
+        }
+    }
+    normalized.truncate(normalized.trim_end().len());
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::apis::payments_providers::{
+        capabilities, Capabilities, PaymentScheme, ProviderAvailability,
+    };
+
+    fn provider(id: &str, name: &str, country_code: Option<CountryCode>) -> Provider {
+        Provider {
+            id: id.to_string(),
+            display_name: Some(name.to_string()),
+            icon_uri: None,
+            logo_uri: None,
+            bg_color: None,
+            country_code,
+        }
+    }
+
+    #[test]
+    fn joins_metadata_and_groups_by_country() {
+        let snapshot = ProviderCatalogSnapshot::new(
+            vec![
+                provider("ob-bank-a", "Bank A", Some(CountryCode::GB)),
+                provider("xs2a-bank-b", "Bänk B", Some(CountryCode::DE)),
+                provider("ob-bank-c", "Bank C", Some(CountryCode::GB)),
+            ],
+            vec![payments_providers::Provider {
+                id: "ob-bank-a".to_string(),
+                display_name: Some("Bank A".to_string()),
+                icon_uri: Some("https://truelayer-provider-assets/a-icon.svg".to_string()),
+                logo_uri: Some("https://truelayer-provider-assets/a-logo.svg".to_string()),
+                bg_color: Some("#FFFFFF".to_string()),
+                country_code: Some(CountryCode::GB),
+                capabilities: Capabilities {
+                    payments: capabilities::Payments {
+                        bank_transfer: Some(capabilities::BankTransfer {
+                            release_channel: ReleaseChannel::GeneralAvailability,
+                            schemes: vec![PaymentScheme {
+                                id: "faster_payments_service".to_string(),
+                            }],
+                        }),
+                    },
+                },
+                availability: Some(ProviderAvailability {
+                    recommended_status: ProviderAvailabilityStatus::Healthy,
+                    updated_at: Utc::now(),
+                }),
+            }],
+        );
+
+        assert_eq!(snapshot.countries.len(), 2);
+        assert_eq!(snapshot.countries[0].country_code, Some(CountryCode::GB));
+        assert_eq!(
+            snapshot.countries[0]
+                .providers
+                .iter()
+                .map(|p| p.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["ob-bank-a", "ob-bank-c"]
+        );
+
+        let bank_a = &snapshot.countries[0].providers[0];
+        assert_eq!(
+            bank_a.logo_uri.as_deref(),
+            Some("https://truelayer-provider-assets/a-logo.svg")
+        );
+        assert_eq!(bank_a.scheme_ids, vec!["faster_payments_service"]);
+        assert_eq!(
+            bank_a.availability,
+            Some(ProviderAvailabilityStatus::Healthy)
+        );
+
+        let bank_c = &snapshot.countries[0].providers[1];
+        assert_eq!(bank_c.logo_uri, None);
+        assert!(bank_c.scheme_ids.is_empty());
+
+        assert_eq!(
+            snapshot
+                .search("bank b")
+                .map(|p| p.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["xs2a-bank-b"]
+        );
+    }
+
+    #[test]
+    fn normalizes_names_for_search() {
+        assert_eq!(
+            normalize_for_search("  Łódź  Bänk-Śląski "),
+            "lodz bank slaski"
+        );
+        assert_eq!(normalize_for_search("Straße"), "strasse");
+    }
+}
@@ -1,10 +1,18 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
 
+use futures::Stream;
+use serde::Deserialize;
 use urlencoding::encode;
 
 use crate::{apis::TrueLayerClientInner, Error};
 
-use super::model::Provider;
+use super::model::{
+    Provider, ProviderAvailabilityChange, ProviderAvailabilityStatus, SearchProvidersRequest,
+};
 
 /// TrueLayer payments APIs client.
 #[derive(Clone, Debug)]
@@ -49,32 +57,139 @@ impl PaymentsProvidersApi {
 
         Ok(provider)
     }
+
+    /// Searches the providers available to the client which match the given criteria.
+    ///
+    /// This is meant to build custom provider selection screens.
+    #[tracing::instrument(name = "Search Providers", skip(self, request))]
+    pub async fn search(&self, request: &SearchProvidersRequest) -> Result<Vec<Provider>, Error> {
+        let res: SearchProvidersResponse = self
+            .inner
+            .client
+            .post(
+                self.inner
+                    .environment
+                    .payments_url()
+                    .join("/payments-providers/search")
+                    .unwrap(),
+            )
+            .json(request)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        Ok(res.items)
+    }
+
+    /// Continuously polls the given providers and emits an event every time the
+    /// recommended availability status of one of them changes.
+    ///
+    /// The first poll emits the current status of every provider which reports its availability.
+    /// Providers which are not found or do not report their availability are silently skipped.
+    /// Polling stops when the stream is dropped.
+    pub fn availability_stream<I, S>(
+        &self,
+        provider_ids: I,
+        poll_interval: Duration,
+    ) -> impl Stream<Item = Result<ProviderAvailabilityChange, Error>> + Send
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let state = AvailabilityPollState {
+            api: self.clone(),
+            provider_ids: provider_ids.into_iter().map(Into::into).collect(),
+            poll_interval,
+            last_statuses: HashMap::new(),
+            pending: VecDeque::new(),
+            started: false,
+        };
+
+        futures::stream::unfold(state, |mut state| async move {
+            loop {
+                // Drain the changes detected in the last round of polling first
+                if let Some(change) = state.pending.pop_front() {
+                    return Some((Ok(change), state));
+                }
+
+                if state.started {
+                    crate::runtime::sleep(state.poll_interval).await;
+                }
+                state.started = true;
+
+                for provider_id in state.provider_ids.clone() {
+                    let availability = match state.api.get_by_id(&provider_id).await {
+                        Ok(provider) => match provider.and_then(|p| p.availability) {
+                            Some(availability) => availability,
+                            None => continue,
+                        },
+                        Err(e) => return Some((Err(e), state)),
+                    };
+
+                    let previous_status = state
+                        .last_statuses
+                        .insert(provider_id.clone(), availability.recommended_status.clone());
+                    if previous_status.as_ref() != Some(&availability.recommended_status) {
+                        state.pending.push_back(ProviderAvailabilityChange {
+                            provider_id,
+                            previous_status,
+                            availability,
+                        });
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchProvidersResponse {
+    items: Vec<Provider>,
+}
+
+/// Internal state of [`PaymentsProvidersApi::availability_stream`].
+struct AvailabilityPollState {
+    api: PaymentsProvidersApi,
+    provider_ids: Vec<String>,
+    poll_interval: Duration,
+    last_statuses: HashMap<String, ProviderAvailabilityStatus>,
+    pending: VecDeque<ProviderAvailabilityChange>,
+    started: bool,
 }
 
 #[cfg(test)]
 mod tests {
-    use std::sync::Arc;
+    use std::{sync::Arc, time::Duration};
 
+    use futures::StreamExt;
     use reqwest::Url;
     use serde_json::json;
     use wiremock::{
-        matchers::{method, path},
+        matchers::{body_partial_json, method, path},
         Mock, MockServer, ResponseTemplate,
     };
 
     use crate::{
         apis::{
             auth::Credentials,
-            payments::CountryCode,
+            payments::{
+                CountryCode, Currency, ProviderSelectionSupported, ReleaseChannel,
+                StartAuthorizationFlowRequest,
+            },
             payments_providers::{
                 api::PaymentsProvidersApi,
-                model::{capabilities, Capabilities, PaymentScheme},
+                model::{
+                    capabilities, Capabilities, PaymentScheme, ProviderAvailabilityStatus,
+                    SearchProvidersAuthorizationFlow, SearchProvidersRequest,
+                },
             },
             TrueLayerClientInner,
         },
         authenticator::Authenticator,
         client::Environment,
         middlewares::error_handling::ErrorHandlingMiddleware,
+        pollable::PollOptions,
     };
 
     async fn mock_client_and_server() -> (TrueLayerClientInner, MockServer) {
@@ -98,6 +213,16 @@ mod tests {
                 .build(),
             authenticator,
             environment: Environment::from_single_url(&Url::parse(&mock_server.uri()).unwrap()),
+            deprecations: Default::default(),
+            rate_limits: Default::default(),
+            poll_options: PollOptions::default().into_dyn(),
+            poll_budget: None,
+            flow_cache: Default::default(),
+            flow_events: Default::default(),
+            idempotency_ledger: Default::default(),
+            payout_guardrails: None,
+            request_traces: Default::default(),
+            implicit_behaviors: true,
         };
 
         (inner, mock_server)
@@ -166,6 +291,133 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn search() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsProvidersApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path("/payments-providers/search"))
+            .and(body_partial_json(json!({
+                "countries": ["GB"],
+                "currencies": ["GBP"],
+                "release_channel": "public_beta",
+                "authorization_flow": {
+                    "configuration": {
+                        "provider_selection": {}
+                    }
+                }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [
+                    {
+                        "id": "provider-id",
+                        "display_name": "Mock Payments Provider",
+                        "country_code": "GB",
+                        "capabilities": {
+                            "payments": {
+                                "bank_transfer": {
+                                    "release_channel": "public_beta",
+                                    "schemes": [
+                                        {
+                                            "id": "faster_payments_service"
+                                        }
+                                    ]
+                                }
+                            }
+                        }
+                    }
+                ]
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let providers = api
+            .search(&SearchProvidersRequest {
+                countries: Some(vec![CountryCode::GB]),
+                currencies: Some(vec![Currency::Gbp]),
+                release_channel: Some(ReleaseChannel::PublicBeta),
+                customer_segments: None,
+                authorization_flow: SearchProvidersAuthorizationFlow {
+                    configuration: StartAuthorizationFlowRequest {
+                        provider_selection: Some(ProviderSelectionSupported {}),
+                        redirect: None,
+                        consent: None,
+                        form: None,
+                    },
+                },
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].id, "provider-id");
+        assert_eq!(providers[0].country_code, Some(CountryCode::GB));
+        assert_eq!(
+            providers[0]
+                .capabilities
+                .payments
+                .bank_transfer
+                .as_ref()
+                .map(|b| &b.release_channel),
+            Some(&ReleaseChannel::PublicBeta)
+        );
+    }
+
+    #[tokio::test]
+    async fn availability_stream_emits_changes() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PaymentsProvidersApi::new(Arc::new(inner));
+
+        let provider_json = |status: &str| {
+            json!({
+                "id": "provider-id",
+                "capabilities": {
+                    "payments": {}
+                },
+                "availability": {
+                    "recommended_status": status,
+                    "updated_at": "2022-04-01T00:00:00Z"
+                }
+            })
+        };
+
+        // Healthy twice, then unhealthy
+        Mock::given(method("GET"))
+            .and(path("/payments-providers/provider-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(provider_json("healthy")))
+            .up_to_n_times(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/payments-providers/provider-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(provider_json("unhealthy")))
+            .mount(&mock_server)
+            .await;
+
+        let changes = api
+            .availability_stream(["provider-id"], Duration::from_millis(10))
+            .take(2)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(changes[0].previous_status, None);
+        assert_eq!(
+            changes[0].availability.recommended_status,
+            ProviderAvailabilityStatus::Healthy
+        );
+        assert_eq!(
+            changes[1].previous_status,
+            Some(ProviderAvailabilityStatus::Healthy)
+        );
+        assert_eq!(
+            changes[1].availability.recommended_status,
+            ProviderAvailabilityStatus::Unhealthy
+        );
+    }
+
     #[tokio::test]
     async fn get_by_id_not_found() {
         let (inner, mock_server) = mock_client_and_server().await;
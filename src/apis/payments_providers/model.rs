@@ -1,6 +1,9 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::apis::payments::CountryCode;
+use crate::apis::payments::{
+    CountryCode, Currency, CustomerSegment, ReleaseChannel, StartAuthorizationFlowRequest,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct Provider {
@@ -11,6 +14,7 @@ pub struct Provider {
     pub bg_color: Option<String>,
     pub country_code: Option<CountryCode>,
     pub capabilities: Capabilities,
+    pub availability: Option<ProviderAvailability>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
@@ -41,3 +45,48 @@ pub mod capabilities {
 pub struct PaymentScheme {
     pub id: String,
 }
+
+/// Criteria to search the providers available to the client.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct SearchProvidersRequest {
+    pub countries: Option<Vec<CountryCode>>,
+    pub currencies: Option<Vec<Currency>>,
+    /// Minimum release channel of the providers. Providers in a more stable channel are also returned.
+    pub release_channel: Option<ReleaseChannel>,
+    pub customer_segments: Option<Vec<CustomerSegment>>,
+    /// Authorization flow features supported by the integration. Providers which need
+    /// unsupported features (e.g., a form) are excluded.
+    pub authorization_flow: SearchProvidersAuthorizationFlow,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct SearchProvidersAuthorizationFlow {
+    pub configuration: StartAuthorizationFlowRequest,
+}
+
+/// Health of a provider as recently observed by TrueLayer.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct ProviderAvailability {
+    pub recommended_status: ProviderAvailabilityStatus,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderAvailabilityStatus {
+    Healthy,
+    Unhealthy,
+    /// Status not supported by this version of the library.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Change in the availability of a provider emitted by
+/// [`PaymentsProvidersApi::availability_stream`](crate::apis::payments_providers::PaymentsProvidersApi::availability_stream).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ProviderAvailabilityChange {
+    pub provider_id: String,
+    /// Status observed in the previous poll, or `None` if this is the first observation.
+    pub previous_status: Option<ProviderAvailabilityStatus>,
+    pub availability: ProviderAvailability,
+}
@@ -1,11 +1,15 @@
 use crate::{
     apis::{
+        payments::{Payment, PaymentStatus},
         payouts::{CreatePayoutRequest, CreatePayoutResponse, Payout},
         TrueLayerClientInner,
     },
-    common::IDEMPOTENCY_KEY_HEADER,
-    Error,
+    idempotency::IdempotencyStore,
+    pollable::{OnStatusChange, PollError, PollOptions},
+    request::{ApiRequest, RequestOptions},
+    Error, Pollable, TrueLayerClient,
 };
+use retry_policies::RetryPolicy;
 use std::sync::Arc;
 use urlencoding::encode;
 use uuid::Uuid;
@@ -22,39 +26,104 @@ impl PayoutsApi {
     }
 
     /// Payout from one of your merchant accounts.
+    ///
+    /// A new random idempotency key is generated for the request, unless one is set on the
+    /// returned [`ApiRequest`] with [`idempotency_key`](ApiRequest::idempotency_key).
+    pub fn create<'a>(
+        &'a self,
+        create_payout_request: &'a CreatePayoutRequest,
+    ) -> ApiRequest<'a, CreatePayoutResponse> {
+        ApiRequest::new(move |options| self.create_with_options(create_payout_request, options))
+    }
+
+    /// Payout from one of your merchant accounts using the given idempotency key.
+    ///
+    /// Makes the request safe to retry, see [`ApiRequest::idempotency_key`](crate::request::ApiRequest::idempotency_key).
+    pub async fn create_with_idempotency_key(
+        &self,
+        create_payout_request: &CreatePayoutRequest,
+        idempotency_key: &str,
+    ) -> Result<CreatePayoutResponse, Error> {
+        self.create(create_payout_request)
+            .idempotency_key(idempotency_key)
+            .await
+    }
+
+    /// Payout from one of your merchant accounts with the given options.
+    ///
+    /// Equivalent to configuring the [`ApiRequest`] returned by [`create`](Self::create).
     #[tracing::instrument(
         name = "Create Payout",
-        skip(self, create_payout_request),
+        skip(self, create_payout_request, options),
         fields(
             amount_in_minor = create_payout_request.amount_in_minor,
             currency = % create_payout_request.currency,
+            correlation_id = options.correlation_id.as_deref(),
         )
     )]
-    pub async fn create(
+    pub async fn create_with_options(
         &self,
         create_payout_request: &CreatePayoutRequest,
+        mut options: RequestOptions,
     ) -> Result<CreatePayoutResponse, Error> {
-        // Generate a new random idempotency-key for this request
-        let idempotency_key = Uuid::new_v4();
+        let idempotency_key = options.idempotency_key();
+        if let Some(guardrails) = &self.inner.payout_guardrails {
+            guardrails.check(create_payout_request, &idempotency_key)?;
+        }
 
         let res = self
-            .inner
-            .client
-            .post(
-                self.inner
-                    .environment
-                    .payments_url()
-                    .join("/payouts")
-                    .unwrap(),
-            )
-            .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
+            .send_create(create_payout_request, options)
+            .await
+            .map_err(|e| {
+                // Only the payouts which may have been accepted by TrueLayer count towards the daily totals
+                if let Some(guardrails) = &self.inner.payout_guardrails {
+                    if !may_have_been_created(&e) {
+                        guardrails.release(&idempotency_key);
+                    }
+                }
+                e
+            })?;
+        self.inner
+            .idempotency_ledger
+            .record(&idempotency_key, &res.id);
+
+        Ok(res)
+    }
+
+    async fn send_create(
+        &self,
+        create_payout_request: &CreatePayoutRequest,
+        mut options: RequestOptions,
+    ) -> Result<CreatePayoutResponse, Error> {
+        Ok(options
+            .apply(
+                self.inner.client.post(
+                    self.inner
+                        .environment
+                        .payments_url()
+                        .join("/payouts")
+                        .unwrap(),
+                ),
+            )?
             .json(create_payout_request)
             .send()
             .await?
-            .json()
-            .await?;
+            .json::<CreatePayoutResponse>()
+            .await?)
+    }
 
-        Ok(res)
+    /// Gets the payout originally created with the idempotency key of an
+    /// [`Error::IdempotencyConflict`](crate::Error::IdempotencyConflict).
+    ///
+    /// Only the payouts recently created with this client, in this process, can be found:
+    /// the ids of the created payouts are only remembered in memory. `None` is returned
+    /// for any other payout, or if the error is not an idempotency conflict.
+    #[tracing::instrument(name = "Get Original Payout", skip_all)]
+    pub async fn get_original(&self, error: &Error) -> Result<Option<Payout>, Error> {
+        match self.inner.idempotency_ledger.original_resource_id(error) {
+            Some(id) => self.get_by_id(&id).await,
+            None => Ok(None),
+        }
     }
 
     /// Gets the details of an existing payout.
@@ -85,6 +154,92 @@ impl PayoutsApi {
 
         Ok(payout)
     }
+
+    /// Waits for a payment to settle into a merchant account, then creates a payout.
+    ///
+    /// The intent to create the payout is persisted in the given [`IdempotencyStore`] before
+    /// waiting, so that calling this function again for the same payment (even after a restart)
+    /// never creates more than one payout: if the payout was already created, its id is returned
+    /// straight away, otherwise the same idempotency key is reused for the creation request.
+    ///
+    /// Returns [`Error::PaymentNotFound`] if the payment does not exist, and [`Error::PaymentFailed`]
+    /// if it fails instead of settling.
+    #[tracing::instrument(
+        name = "Schedule Payout After Settlement",
+        skip(self, create_payout_request, store, poll_options)
+    )]
+    pub async fn schedule_after_settlement<R, C>(
+        &self,
+        payment_id: &str,
+        create_payout_request: &CreatePayoutRequest,
+        store: &dyn IdempotencyStore,
+        poll_options: PollOptions<R, C>,
+    ) -> Result<CreatePayoutResponse, PollError>
+    where
+        R: RetryPolicy + Send + Sync,
+        C: OnStatusChange<Payment> + Send + Sync,
+    {
+        let intent_key = format!("payout-after-settlement/{}", payment_id);
+        let payout_key = format!("{}/payout-id", intent_key);
+
+        // The payout has already been created
+        if let Some(id) = store.get(&payout_key).await? {
+            return Ok(CreatePayoutResponse { id });
+        }
+
+        // Persist the intent before doing anything else, reusing the idempotency key stored
+        // by a concurrent or previous call if any
+        let idempotency_key = store
+            .set_if_absent(&intent_key, &Uuid::new_v4().to_string())
+            .await?;
+
+        // Wait for the payment to settle
+        let tl = TrueLayerClient::from_inner(self.inner.clone());
+        let payment =
+            tl.payments
+                .get_by_id(payment_id)
+                .await?
+                .ok_or_else(|| Error::PaymentNotFound {
+                    payment_id: payment_id.to_string(),
+                })?;
+        let payment = payment
+            .poll_until(&tl, poll_options, |p| {
+                matches!(
+                    p.status,
+                    PaymentStatus::Settled { .. } | PaymentStatus::Failed { .. }
+                )
+            })
+            .await?;
+        if let PaymentStatus::Failed { failure_reason, .. } = payment.status {
+            return Err(Error::PaymentFailed {
+                payment_id: payment_id.to_string(),
+                failure_reason,
+            }
+            .into());
+        }
+
+        let res = self
+            .create_with_idempotency_key(create_payout_request, &idempotency_key)
+            .await?;
+        store.set(&payout_key, &res.id).await?;
+
+        Ok(res)
+    }
+}
+
+/// Returns `true` if a payout may have been created by TrueLayer despite the error, i.e. if
+/// the request failed in transit or its response could not be read.
+///
+/// Payouts which were never sent, or which TrueLayer answered with an error, were definitely
+/// not created.
+fn may_have_been_created(e: &Error) -> bool {
+    match e {
+        Error::HttpError(e) => !e.is_builder(),
+        Error::ResponseTooLarge { .. } | Error::ResponseBodyTimeout { .. } | Error::Other(_) => {
+            true
+        }
+        _ => false,
+    }
 }
 
 #[cfg(test)]
@@ -94,10 +249,16 @@ mod tests {
         apis::{
             auth::Credentials,
             payments::{AccountIdentifier, Currency},
-            payouts::{PayoutBeneficiary, PayoutStatus},
+            payouts::{
+                PayoutBeneficiary, PayoutBeneficiaryType, PayoutEvent, PayoutEventType,
+                PayoutStatus,
+            },
         },
         authenticator::Authenticator,
         client::Environment,
+        common::IDEMPOTENCY_KEY_HEADER,
+        guardrails::{GuardrailViolation, Guardrails},
+        idempotency::InMemoryIdempotencyStore,
         middlewares::error_handling::ErrorHandlingMiddleware,
     };
     use chrono::{TimeZone, Utc};
@@ -129,6 +290,16 @@ mod tests {
                 .build(),
             authenticator,
             environment: Environment::from_single_url(&Url::parse(&mock_server.uri()).unwrap()),
+            deprecations: Default::default(),
+            rate_limits: Default::default(),
+            poll_options: PollOptions::default().into_dyn(),
+            poll_budget: None,
+            flow_cache: Default::default(),
+            flow_events: Default::default(),
+            idempotency_ledger: Default::default(),
+            payout_guardrails: None,
+            request_traces: Default::default(),
+            implicit_behaviors: true,
         };
 
         (inner, mock_server)
@@ -175,6 +346,44 @@ mod tests {
                     },
                     reference: "some-reference".to_string(),
                 },
+                scheme_selection: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(res.id, "payout-id");
+    }
+
+    #[tokio::test]
+    async fn create_to_business_account() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PayoutsApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path("/payouts"))
+            .and(body_partial_json(json!({
+                "merchant_account_id": "merchant-account-id",
+                "beneficiary": {
+                    "type": "business_account",
+                    "reference": "some-reference"
+                }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payout-id"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let res = api
+            .create(&CreatePayoutRequest {
+                merchant_account_id: "merchant-account-id".to_string(),
+                amount_in_minor: 100,
+                currency: Currency::Gbp,
+                beneficiary: PayoutBeneficiary::BusinessAccount {
+                    reference: "some-reference".to_string(),
+                },
+                scheme_selection: None,
             })
             .await
             .unwrap();
@@ -182,6 +391,312 @@ mod tests {
         assert_eq!(res.id, "payout-id");
     }
 
+    #[tokio::test]
+    async fn payouts_breaking_guardrails_are_not_sent() {
+        let (mut inner, mock_server) = mock_client_and_server().await;
+        inner.payout_guardrails = Some(Arc::new(
+            Guardrails::new()
+                .with_allowed_beneficiary_types([PayoutBeneficiaryType::BusinessAccount]),
+        ));
+        let api = PayoutsApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path("/payouts"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let res = api
+            .create(&CreatePayoutRequest {
+                merchant_account_id: "merchant-account-id".to_string(),
+                amount_in_minor: 100,
+                currency: Currency::Gbp,
+                beneficiary: PayoutBeneficiary::ExternalAccount {
+                    account_holder_name: "Mr. Holder".to_string(),
+                    account_identifier: AccountIdentifier::Iban {
+                        iban: "some-iban".to_string(),
+                    },
+                    reference: "some-reference".to_string(),
+                },
+                scheme_selection: None,
+            })
+            .await;
+
+        assert!(matches!(
+            res,
+            Err(Error::PayoutBlocked(
+                GuardrailViolation::BeneficiaryTypeNotAllowed(
+                    PayoutBeneficiaryType::ExternalAccount
+                )
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn payouts_rejected_by_truelayer_do_not_count_towards_the_daily_total() {
+        let (mut inner, mock_server) = mock_client_and_server().await;
+        inner.payout_guardrails = Some(Arc::new(
+            Guardrails::new().with_max_daily_total(Currency::Gbp, 150),
+        ));
+        let api = PayoutsApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path("/payouts"))
+            .respond_with(ResponseTemplate::new(429).append_header("Retry-After", "1"))
+            .expect(1)
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/payouts"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payout-id"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let request = CreatePayoutRequest {
+            merchant_account_id: "merchant-account-id".to_string(),
+            amount_in_minor: 100,
+            currency: Currency::Gbp,
+            beneficiary: PayoutBeneficiary::BusinessAccount {
+                reference: "some-reference".to_string(),
+            },
+            scheme_selection: None,
+        };
+
+        assert!(matches!(
+            api.create(&request).await,
+            Err(Error::RateLimited { .. })
+        ));
+        assert_eq!(api.create(&request).await.unwrap().id, "payout-id");
+
+        // The payout created by TrueLayer counts towards the total
+        assert!(matches!(
+            api.create(&request).await,
+            Err(Error::PayoutBlocked(
+                GuardrailViolation::DailyTotalExceeded { .. }
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn schedule_after_settlement_creates_payout_once() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PayoutsApi::new(Arc::new(inner));
+        let store = InMemoryIdempotencyStore::new();
+
+        Mock::given(method("GET"))
+            .and(path("/payments/payment-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payment-id",
+                "amount_in_minor": 100,
+                "currency": "GBP",
+                "payment_method": {
+                    "type": "bank_transfer",
+                    "provider_selection": {
+                        "type": "user_selected"
+                    },
+                    "beneficiary": {
+                        "type": "merchant_account",
+                        "merchant_account_id": "merchant-account-id",
+                    }
+                },
+                "user": {
+                    "id": "user-id"
+                },
+                "created_at": "2022-04-01T00:00:00Z",
+                "status": "settled",
+                "payment_source": {
+                    "id": "payment-source-id"
+                },
+                "executed_at": "2022-04-01T00:00:00Z",
+                "settled_at": "2022-04-01T00:00:00Z"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/payouts"))
+            .and(header_exists(IDEMPOTENCY_KEY_HEADER))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payout-id"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let request = CreatePayoutRequest {
+            merchant_account_id: "merchant-account-id".to_string(),
+            amount_in_minor: 100,
+            currency: Currency::Gbp,
+            beneficiary: PayoutBeneficiary::PaymentSource {
+                user_id: "user-id".to_string(),
+                payment_source_id: "payment-source-id".to_string(),
+                reference: "some-reference".to_string(),
+            },
+            scheme_selection: None,
+        };
+
+        // Schedule twice, the second call must not create another payout
+        for _ in 0..2 {
+            let res = api
+                .schedule_after_settlement("payment-id", &request, &store, PollOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(res.id, "payout-id");
+        }
+    }
+
+    #[tokio::test]
+    async fn schedule_after_settlement_fails_with_payment() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PayoutsApi::new(Arc::new(inner));
+        let store = InMemoryIdempotencyStore::new();
+
+        Mock::given(method("GET"))
+            .and(path("/payments/payment-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payment-id",
+                "amount_in_minor": 100,
+                "currency": "GBP",
+                "payment_method": {
+                    "type": "bank_transfer",
+                    "provider_selection": {
+                        "type": "user_selected"
+                    },
+                    "beneficiary": {
+                        "type": "merchant_account",
+                        "merchant_account_id": "merchant-account-id",
+                    }
+                },
+                "user": {
+                    "id": "user-id"
+                },
+                "created_at": "2022-04-01T00:00:00Z",
+                "status": "failed",
+                "failed_at": "2022-04-01T00:00:00Z",
+                "failure_stage": "authorizing",
+                "failure_reason": "authorization_failed"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/payouts"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let request = CreatePayoutRequest {
+            merchant_account_id: "merchant-account-id".to_string(),
+            amount_in_minor: 100,
+            currency: Currency::Gbp,
+            beneficiary: PayoutBeneficiary::PaymentSource {
+                user_id: "user-id".to_string(),
+                payment_source_id: "payment-source-id".to_string(),
+                reference: "some-reference".to_string(),
+            },
+            scheme_selection: None,
+        };
+
+        // The intent stored before waiting is kept for the next attempt
+        store
+            .set("payout-after-settlement/payment-id", "stored-key")
+            .await
+            .unwrap();
+        let res = api
+            .schedule_after_settlement("payment-id", &request, &store, PollOptions::default())
+            .await;
+
+        assert!(matches!(
+            res,
+            Err(PollError::Error(Error::PaymentFailed { payment_id, failure_reason }))
+                if payment_id == "payment-id" && failure_reason == "authorization_failed"
+        ));
+        assert_eq!(
+            store
+                .set_if_absent("payout-after-settlement/payment-id", "other-key")
+                .await
+                .unwrap(),
+            "stored-key"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_original_after_idempotency_conflict() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PayoutsApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path("/payouts"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payout-id"
+            })))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/payouts"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+                "type": "https://docs.truelayer.com/docs/error-types#idempotency-key-reuse",
+                "title": "Idempotency-Key Reuse",
+                "status": 422,
+                "trace_id": "trace-id",
+                "detail": "The Idempotency-Key value has already been used for a different request."
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/payouts/payout-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payout-id",
+                "merchant_account_id": "merchant-account-id",
+                "amount_in_minor": 100,
+                "currency": "GBP",
+                "beneficiary": {
+                    "type": "business_account",
+                    "reference": "some-reference"
+                },
+                "status": "pending",
+                "created_at": "2022-04-01T00:00:00Z"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let request = |amount_in_minor| CreatePayoutRequest {
+            merchant_account_id: "merchant-account-id".to_string(),
+            amount_in_minor,
+            currency: Currency::Gbp,
+            beneficiary: PayoutBeneficiary::BusinessAccount {
+                reference: "some-reference".to_string(),
+            },
+            scheme_selection: None,
+        };
+        api.create_with_idempotency_key(&request(100), "idempotency-key")
+            .await
+            .unwrap();
+        let err = api
+            .create_with_idempotency_key(&request(200), "idempotency-key")
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            Error::IdempotencyConflict { ref trace_id, .. }
+                if trace_id.as_deref() == Some("trace-id")
+        ));
+        let original = api.get_original(&err).await.unwrap().unwrap();
+        assert_eq!(original.id, "payout-id");
+        assert_eq!(original.amount_in_minor, 100);
+    }
+
     #[tokio::test]
     async fn get_by_id_successful() {
         let (inner, mock_server) = mock_client_and_server().await;
@@ -243,6 +758,60 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn get_by_id_failed() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = PayoutsApi::new(Arc::new(inner));
+
+        let payout_id = "some-failed-payout-id";
+        Mock::given(method("GET"))
+            .and(path(format!("/payouts/{}", payout_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": payout_id,
+                "merchant_account_id": "some-merchant-account-id",
+                "amount_in_minor": 100,
+                "currency": "GBP",
+                "beneficiary": {
+                    "type": "payment_source",
+                    "user_id": "some-user-id",
+                    "payment_source_id": "some-payment-source-id",
+                    "reference": "some-reference"
+                },
+                "status": "failed",
+                "created_at": "2022-04-01T00:00:00Z",
+                "failed_at": "2022-04-01T00:01:00Z",
+                "failure_reason": "insufficient_funds"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let payout = api.get_by_id(payout_id).await.unwrap().unwrap();
+
+        assert_eq!(
+            payout.status,
+            PayoutStatus::Failed {
+                failed_at: Utc.with_ymd_and_hms(2022, 4, 1, 0, 1, 0).unwrap(),
+                failure_reason: "insufficient_funds".to_string()
+            }
+        );
+        assert_eq!(
+            payout.events(),
+            vec![
+                PayoutEvent {
+                    r#type: PayoutEventType::Created,
+                    occurred_at: Utc.with_ymd_and_hms(2022, 4, 1, 0, 0, 0).unwrap()
+                },
+                PayoutEvent {
+                    r#type: PayoutEventType::Failed {
+                        failure_reason: "insufficient_funds".to_string()
+                    },
+                    occurred_at: Utc.with_ymd_and_hms(2022, 4, 1, 0, 1, 0).unwrap()
+                },
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn get_by_id_not_found() {
         let (inner, mock_server) = mock_client_and_server().await;
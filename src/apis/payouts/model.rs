@@ -1,6 +1,6 @@
 use crate::{
     apis::payments::{AccountIdentifier, Currency},
-    pollable::IsInTerminalState,
+    pollable::{HasId, HasStatus, IsInTerminalState},
     Error, Pollable, TrueLayerClient,
 };
 use anyhow::anyhow;
@@ -11,9 +11,25 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreatePayoutRequest {
     pub merchant_account_id: String,
+    #[serde(deserialize_with = "crate::amounts::deserialize_minor")]
     pub amount_in_minor: u64,
     pub currency: Currency,
     pub beneficiary: PayoutBeneficiary,
+    /// Payment scheme to use for the payout. If `None`, TrueLayer picks the best available scheme.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheme_selection: Option<PayoutSchemeSelection>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PayoutSchemeSelection {
+    /// Only use an instant scheme (e.g. SEPA Instant), failing the payout if none is available.
+    InstantOnly,
+    /// Use an instant scheme if available, falling back to a regular scheme
+    /// (e.g. SEPA Credit Transfer) otherwise.
+    InstantPreferred,
+    /// Use the given scheme.
+    Preselected { scheme_id: String },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -37,26 +53,55 @@ impl Pollable for CreatePayoutResponse {
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum PayoutBeneficiary {
+    /// Open-loop payout to an arbitrary bank account, identified either by IBAN
+    /// or by sort code and account number.
     ExternalAccount {
         account_holder_name: String,
         account_identifier: AccountIdentifier,
         reference: String,
     },
+    /// Closed-loop payout to the account which funded a previous payment into the merchant account.
     PaymentSource {
         user_id: String,
         payment_source_id: String,
         reference: String,
     },
+    /// Payout to the business account registered for the merchant account,
+    /// e.g. to withdraw the funds collected through payments.
+    BusinessAccount { reference: String },
+}
+
+impl PayoutBeneficiary {
+    /// Returns the type of this beneficiary.
+    pub fn r#type(&self) -> PayoutBeneficiaryType {
+        match self {
+            PayoutBeneficiary::ExternalAccount { .. } => PayoutBeneficiaryType::ExternalAccount,
+            PayoutBeneficiary::PaymentSource { .. } => PayoutBeneficiaryType::PaymentSource,
+            PayoutBeneficiary::BusinessAccount { .. } => PayoutBeneficiaryType::BusinessAccount,
+        }
+    }
+}
+
+/// Type of a [`PayoutBeneficiary`].
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutBeneficiaryType {
+    ExternalAccount,
+    PaymentSource,
+    BusinessAccount,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Payout {
     pub id: String,
     pub merchant_account_id: String,
+    #[serde(deserialize_with = "crate::amounts::deserialize_minor")]
     pub amount_in_minor: u64,
     pub currency: Currency,
     pub beneficiary: PayoutBeneficiary,
     pub created_at: DateTime<Utc>,
+    /// Payment scheme used to execute the payout, once known.
+    pub scheme_id: Option<String>,
     #[serde(flatten)]
     pub status: PayoutStatus,
 }
@@ -75,15 +120,93 @@ impl Pollable for Payout {
 }
 
 impl IsInTerminalState for Payout {
-    /// A payout is considered to be in a terminal state if it is `Executed` or `Failed`.
+    /// A payout is considered to be in a terminal state if it is `Executed`, `Failed` or `Returned`.
     fn is_in_terminal_state(&self) -> bool {
         matches!(
             self.status,
-            PayoutStatus::Executed { .. } | PayoutStatus::Failed { .. }
+            PayoutStatus::Executed { .. }
+                | PayoutStatus::Failed { .. }
+                | PayoutStatus::Returned { .. }
         )
     }
 }
 
+impl HasId for Payout {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl HasStatus for Payout {
+    fn has_same_status(&self, other: &Self) -> bool {
+        std::mem::discriminant(&self.status) == std::mem::discriminant(&other.status)
+    }
+}
+
+impl Payout {
+    /// Returns the status transitions of this payout which carry a timestamp, in chronological order.
+    ///
+    /// The history is rebuilt from the timestamps returned alongside the current status of the payout.
+    pub fn events(&self) -> Vec<PayoutEvent> {
+        let mut events = vec![PayoutEvent {
+            r#type: PayoutEventType::Created,
+            occurred_at: self.created_at,
+        }];
+
+        match &self.status {
+            PayoutStatus::Executed { executed_at } => events.push(PayoutEvent {
+                r#type: PayoutEventType::Executed,
+                occurred_at: *executed_at,
+            }),
+            PayoutStatus::Failed {
+                failed_at,
+                failure_reason,
+            } => events.push(PayoutEvent {
+                r#type: PayoutEventType::Failed {
+                    failure_reason: failure_reason.clone(),
+                },
+                occurred_at: *failed_at,
+            }),
+            PayoutStatus::Returned {
+                executed_at,
+                returned_at,
+                return_reason,
+            } => {
+                events.push(PayoutEvent {
+                    r#type: PayoutEventType::Executed,
+                    occurred_at: *executed_at,
+                });
+                events.push(PayoutEvent {
+                    r#type: PayoutEventType::Returned {
+                        return_reason: return_reason.clone(),
+                    },
+                    occurred_at: *returned_at,
+                });
+            }
+            PayoutStatus::Pending | PayoutStatus::Authorized => {}
+        }
+
+        events
+    }
+}
+
+/// A status transition of a payout.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct PayoutEvent {
+    #[serde(flatten)]
+    pub r#type: PayoutEventType,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PayoutEventType {
+    Created,
+    Executed,
+    Failed { failure_reason: String },
+    Returned { return_reason: String },
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 #[serde(tag = "status", rename_all = "snake_case")]
 pub enum PayoutStatus {
@@ -96,4 +219,11 @@ pub enum PayoutStatus {
         failed_at: DateTime<Utc>,
         failure_reason: String,
     },
+    /// The payout was executed, but the beneficiary's bank sent the funds back
+    /// to the merchant account, e.g. because the beneficiary account is closed.
+    Returned {
+        executed_at: DateTime<Utc>,
+        returned_at: DateTime<Utc>,
+        return_reason: String,
+    },
 }
@@ -0,0 +1,277 @@
+use crate::{
+    apis::{
+        verification::{CreateVerificationRequest, CreateVerificationResponse, Verification},
+        TrueLayerClientInner,
+    },
+    common::IDEMPOTENCY_KEY_HEADER,
+    Error,
+};
+use std::sync::Arc;
+use urlencoding::encode;
+use uuid::Uuid;
+
+/// TrueLayer verification APIs client.
+#[derive(Clone, Debug)]
+pub struct VerificationApi {
+    inner: Arc<TrueLayerClientInner>,
+}
+
+impl VerificationApi {
+    pub(crate) fn new(inner: Arc<TrueLayerClientInner>) -> Self {
+        Self { inner }
+    }
+
+    /// Starts the verification of the ownership of a bank account.
+    ///
+    /// Verifications are performed asynchronously: use [`get_verification_result`](Self::get_verification_result)
+    /// or poll the returned [`CreateVerificationResponse`] to get the outcome.
+    #[tracing::instrument(name = "Create Verification", skip(self, create_verification_request))]
+    pub async fn create_verification(
+        &self,
+        create_verification_request: &CreateVerificationRequest,
+    ) -> Result<CreateVerificationResponse, Error> {
+        // Generate a new random idempotency-key for this request
+        let idempotency_key = Uuid::new_v4();
+
+        let res = self
+            .inner
+            .client
+            .post(
+                self.inner
+                    .environment
+                    .payments_url()
+                    .join("/verifications")
+                    .unwrap(),
+            )
+            .header(IDEMPOTENCY_KEY_HEADER, idempotency_key.to_string())
+            .json(create_verification_request)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        Ok(res)
+    }
+
+    /// Gets the status and, once completed, the outcome of an existing verification.
+    ///
+    /// If there's no verification with the given id, `None` is returned.
+    #[tracing::instrument(name = "Get Verification Result", skip(self))]
+    pub async fn get_verification_result(&self, id: &str) -> Result<Option<Verification>, Error> {
+        let res = self
+            .inner
+            .client
+            .get(
+                self.inner
+                    .environment
+                    .payments_url()
+                    .join(&format!("/verifications/{}", encode(id)))
+                    .unwrap(),
+            )
+            .send()
+            .await
+            .map_err(Error::from);
+
+        // Return `None` if the server returned 404
+        let verification = match res {
+            Ok(body) => Some(body.json().await?),
+            Err(Error::ApiError(api_error)) if api_error.status == 404 => None,
+            Err(e) => return Err(e),
+        };
+
+        Ok(verification)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apis::{
+            auth::Credentials,
+            payments::AccountIdentifier,
+            verification::{VerificationOutcome, VerificationStatus},
+        },
+        authenticator::Authenticator,
+        client::Environment,
+        middlewares::error_handling::ErrorHandlingMiddleware,
+        pollable::{IsInTerminalState, PollOptions},
+    };
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+    use url::Url;
+    use wiremock::{
+        matchers::{body_partial_json, header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    async fn mock_client_and_server() -> (TrueLayerClientInner, MockServer) {
+        let mock_server = MockServer::start().await;
+
+        let credentials = Credentials::ClientCredentials {
+            client_id: "client-id".into(),
+            client_secret: "client-secret".into(),
+            scope: "mock".into(),
+        };
+
+        let authenticator = Authenticator::new(
+            reqwest::Client::new().into(),
+            Url::parse(&mock_server.uri()).unwrap(),
+            credentials,
+        );
+
+        let inner = TrueLayerClientInner {
+            client: reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+                .with(ErrorHandlingMiddleware)
+                .build(),
+            authenticator,
+            environment: Environment::from_single_url(&Url::parse(&mock_server.uri()).unwrap()),
+            deprecations: Default::default(),
+            rate_limits: Default::default(),
+            poll_options: PollOptions::default().into_dyn(),
+            poll_budget: None,
+            flow_cache: Default::default(),
+            flow_events: Default::default(),
+            idempotency_ledger: Default::default(),
+            payout_guardrails: None,
+            request_traces: Default::default(),
+            implicit_behaviors: true,
+        };
+
+        (inner, mock_server)
+    }
+
+    #[tokio::test]
+    async fn create_verification() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = VerificationApi::new(Arc::new(inner));
+
+        Mock::given(method("POST"))
+            .and(path("/verifications"))
+            .and(header_exists(IDEMPOTENCY_KEY_HEADER))
+            .and(body_partial_json(json!({
+                "account_holder_name": "Mr. Holder",
+                "account_identifier": {
+                    "type": "sort_code_account_number",
+                    "sort_code": "123456",
+                    "account_number": "12345678"
+                }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "verification-id"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let res = api
+            .create_verification(&CreateVerificationRequest {
+                account_holder_name: "Mr. Holder".to_string(),
+                account_identifier: AccountIdentifier::SortCodeAccountNumber {
+                    sort_code: "123456".to_string(),
+                    account_number: "12345678".to_string(),
+                },
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(res.id, "verification-id");
+    }
+
+    #[tokio::test]
+    async fn get_verification_result() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = VerificationApi::new(Arc::new(inner));
+
+        Mock::given(method("GET"))
+            .and(path("/verifications/verification-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "verification-id",
+                "account_holder_name": "Mr. Holder",
+                "account_identifier": {
+                    "type": "iban",
+                    "iban": "some-iban"
+                },
+                "created_at": "2022-08-01T10:00:00Z",
+                "status": "completed",
+                "completed_at": "2022-08-01T10:00:05Z",
+                "outcome": {
+                    "type": "partial_match",
+                    "matched_name": "Mr. J. Holder"
+                }
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let verification = api
+            .get_verification_result("verification-id")
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(verification.is_in_terminal_state());
+        assert_eq!(
+            verification.status,
+            VerificationStatus::Completed {
+                completed_at: Utc.with_ymd_and_hms(2022, 8, 1, 10, 0, 5).unwrap(),
+                outcome: VerificationOutcome::PartialMatch {
+                    matched_name: "Mr. J. Holder".to_string()
+                }
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn get_verification_result_with_unknown_status() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = VerificationApi::new(Arc::new(inner));
+
+        Mock::given(method("GET"))
+            .and(path("/verifications/verification-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "verification-id",
+                "account_holder_name": "Mr. Holder",
+                "account_identifier": {
+                    "type": "iban",
+                    "iban": "some-iban"
+                },
+                "created_at": "2022-08-01T10:00:00Z",
+                "status": "awaiting_review",
+                "review_deadline": "2022-08-02T10:00:00Z"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let verification = api
+            .get_verification_result("verification-id")
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(!verification.is_in_terminal_state());
+        assert_eq!(verification.status, VerificationStatus::Unknown);
+        assert_eq!(verification.outcome(), None);
+    }
+
+    #[tokio::test]
+    async fn get_verification_result_not_found() {
+        let (inner, mock_server) = mock_client_and_server().await;
+        let api = VerificationApi::new(Arc::new(inner));
+
+        Mock::given(method("GET"))
+            .and(path("/verifications/verification-id"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let res = api
+            .get_verification_result("verification-id")
+            .await
+            .unwrap();
+
+        assert!(res.is_none());
+    }
+}
@@ -0,0 +1,7 @@
+//! APIs and models related to the verification of the ownership of bank accounts.
+
+mod api;
+mod model;
+
+pub use api::VerificationApi;
+pub use model::*;
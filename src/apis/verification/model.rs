@@ -0,0 +1,132 @@
+use crate::{
+    apis::payments::AccountIdentifier,
+    pollable::{HasId, HasStatus, IsInTerminalState},
+    Error, Pollable, TrueLayerClient,
+};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Request to verify that a bank account is owned by the given account holder.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateVerificationRequest {
+    pub account_holder_name: String,
+    pub account_identifier: AccountIdentifier,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreateVerificationResponse {
+    pub id: String,
+}
+
+#[async_trait]
+impl Pollable for CreateVerificationResponse {
+    type Output = Verification;
+
+    async fn poll_once(&self, tl: &TrueLayerClient) -> Result<Self::Output, Error> {
+        tl.verification
+            .get_verification_result(&self.id)
+            .await
+            .transpose()
+            .unwrap_or_else(|| {
+                Err(Error::Other(anyhow!(
+                    "Verification returned 404 while polling"
+                )))
+            })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Verification {
+    pub id: String,
+    pub account_holder_name: String,
+    pub account_identifier: AccountIdentifier,
+    pub created_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub status: VerificationStatus,
+}
+
+#[async_trait]
+impl Pollable for Verification {
+    type Output = Verification;
+
+    async fn poll_once(&self, tl: &TrueLayerClient) -> Result<Self::Output, Error> {
+        tl.verification
+            .get_verification_result(&self.id)
+            .await
+            .transpose()
+            .unwrap_or_else(|| {
+                Err(Error::Other(anyhow!(
+                    "Verification returned 404 while polling"
+                )))
+            })
+    }
+}
+
+impl IsInTerminalState for Verification {
+    /// A verification is considered to be in a terminal state if it is `Completed` or `Failed`.
+    fn is_in_terminal_state(&self) -> bool {
+        matches!(
+            self.status,
+            VerificationStatus::Completed { .. } | VerificationStatus::Failed { .. }
+        )
+    }
+}
+
+impl HasId for Verification {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl HasStatus for Verification {
+    fn has_same_status(&self, other: &Self) -> bool {
+        std::mem::discriminant(&self.status) == std::mem::discriminant(&other.status)
+    }
+}
+
+impl Verification {
+    /// Returns the outcome of the verification, or `None` if it has not completed yet.
+    pub fn outcome(&self) -> Option<&VerificationOutcome> {
+        match &self.status {
+            VerificationStatus::Completed { outcome, .. } => Some(outcome),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum VerificationStatus {
+    Pending,
+    Completed {
+        completed_at: DateTime<Utc>,
+        outcome: VerificationOutcome,
+    },
+    /// The verification could not be performed, e.g. because the bank of the account
+    /// does not support it. This says nothing about the ownership of the account.
+    Failed {
+        failed_at: DateTime<Utc>,
+        failure_reason: String,
+    },
+    /// Status not supported by this version of the library.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Result of the comparison between the given account holder name and the name held by the bank.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VerificationOutcome {
+    /// The names match.
+    Match,
+    /// The names are similar, e.g. because of a typo or a missing middle name.
+    /// The name held by the bank is returned so that it can be checked.
+    PartialMatch { matched_name: String },
+    /// The names do not match.
+    NoMatch,
+    /// Outcome not supported by this version of the library.
+    #[serde(other)]
+    Unknown,
+}
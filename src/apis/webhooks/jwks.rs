@@ -0,0 +1,344 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::{
+    apis::webhooks::{Jwk, Jwks},
+    client::UnauthenticatedClient,
+    error::WebhookVerificationError,
+    runtime::{sync::RwLock, Instant},
+    Error,
+};
+
+/// Cache of the public keys used by TrueLayer to sign webhooks.
+///
+/// Keys are fetched lazily and kept for a configurable time to live:
+/// - shortly before they expire, keys are refreshed in the background, so that verifying
+///   a webhook never has to wait for TrueLayer unless the cache is empty or stale;
+/// - looking up a key id which is not in the cache triggers a refresh, so that newly rotated
+///   keys are picked up immediately. To avoid hammering TrueLayer with signatures referencing
+///   bogus key ids, such refreshes happen at most once per `min_refresh_interval`;
+/// - keys which disappear from TrueLayer's key set are rejected straight away, as TrueLayer
+///   publishes both keys while rotating them. An optional grace period keeps accepting them for
+///   a while, see [`with_rotation_grace_period`](JwksCache::with_rotation_grace_period).
+///
+/// Cloning a `JwksCache` is cheap and the clones share the same keys.
+#[derive(Debug, Clone)]
+pub struct JwksCache {
+    inner: Arc<JwksCacheInner>,
+}
+
+#[derive(Debug)]
+struct JwksCacheInner {
+    client: UnauthenticatedClient,
+    ttl: Duration,
+    prefetch_before_expiry: Duration,
+    min_refresh_interval: Duration,
+    rotation_grace_period: Duration,
+    state: RwLock<Option<CachedJwks>>,
+    prefetching: AtomicBool,
+}
+
+#[derive(Debug)]
+struct CachedJwks {
+    jwks: Jwks,
+    /// Keys no longer published by TrueLayer, with the time they were last seen.
+    retired: Vec<(Jwk, Instant)>,
+    fetched_at: Instant,
+}
+
+impl CachedJwks {
+    /// Currently published keys followed by the retired keys still in their grace period.
+    fn all_keys(&self, grace_period: Duration) -> Jwks {
+        Jwks {
+            keys: self
+                .jwks
+                .keys
+                .iter()
+                .chain(
+                    self.retired
+                        .iter()
+                        .filter(|(_, last_seen)| last_seen.elapsed() < grace_period)
+                        .map(|(key, _)| key),
+                )
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+impl JwksCache {
+    /// Creates a new empty cache fetching keys with the given client.
+    ///
+    /// By default, keys are cached for 15 minutes and prefetched 1 minute before they expire,
+    /// unless [implicit behaviors](UnauthenticatedClient::with_implicit_behaviors) are disabled on the client.
+    /// Unknown key ids trigger a refresh at most once per minute,
+    /// and keys are rejected as soon as they stop being published.
+    pub fn new(client: UnauthenticatedClient) -> Self {
+        Self {
+            inner: Arc::new(JwksCacheInner {
+                client,
+                ttl: Duration::from_secs(15 * 60),
+                prefetch_before_expiry: Duration::from_secs(60),
+                min_refresh_interval: Duration::from_secs(60),
+                rotation_grace_period: Duration::ZERO,
+                state: RwLock::new(None),
+                prefetching: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    /// Sets for how long the keys are cached.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cache has already been cloned.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.inner_mut().ttl = ttl;
+        self
+    }
+
+    /// Sets how long before their expiration the keys are refreshed in the background.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cache has already been cloned.
+    pub fn with_prefetch_before_expiry(mut self, prefetch_before_expiry: Duration) -> Self {
+        self.inner_mut().prefetch_before_expiry = prefetch_before_expiry;
+        self
+    }
+
+    /// Sets the minimum time between two refreshes triggered by an unknown key id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cache has already been cloned.
+    pub fn with_min_refresh_interval(mut self, min_refresh_interval: Duration) -> Self {
+        self.inner_mut().min_refresh_interval = min_refresh_interval;
+        self
+    }
+
+    /// Sets for how long keys are still accepted after TrueLayer stops publishing them. Defaults to zero.
+    ///
+    /// Only use it if webhooks signed right before a rotation fail to verify: keys withdrawn by TrueLayer,
+    /// e.g. because they were compromised, are also accepted during the grace period.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cache has already been cloned.
+    pub fn with_rotation_grace_period(mut self, rotation_grace_period: Duration) -> Self {
+        self.inner_mut().rotation_grace_period = rotation_grace_period;
+        self
+    }
+
+    fn inner_mut(&mut self) -> &mut JwksCacheInner {
+        Arc::get_mut(&mut self.inner).expect("JwksCache must be configured before being cloned")
+    }
+
+    /// Client used to fetch the keys.
+    pub fn client(&self) -> &UnauthenticatedClient {
+        &self.inner.client
+    }
+
+    /// Returns a set of keys containing the key with the given id, refreshing the cache if needed.
+    ///
+    /// If TrueLayer does not publish a key with the given id, a warning is logged and
+    /// [`WebhookVerificationError::UnknownKid`](crate::error::WebhookVerificationError::UnknownKid) is returned,
+    /// which usually points to a key rotation issue or to a forged webhook.
+    pub async fn get(&self, kid: &str) -> Result<Jwks, Error> {
+        let inner = &self.inner;
+
+        {
+            let state = inner.state.read().await;
+            if let Some(cached) = state.as_ref() {
+                let age = cached.fetched_at.elapsed();
+                if age < inner.ttl {
+                    let jwks = cached.all_keys(inner.rotation_grace_period);
+                    if jwks.find(kid).is_some() {
+                        if age >= inner.ttl.saturating_sub(inner.prefetch_before_expiry)
+                            && inner.client.implicit_behaviors()
+                        {
+                            self.spawn_prefetch();
+                        }
+                        return Ok(jwks);
+                    }
+                    if age < inner.min_refresh_interval {
+                        return Err(unknown_kid(kid));
+                    }
+                }
+            }
+        }
+
+        let jwks = self.refresh(Some(kid)).await?;
+        if jwks.find(kid).is_some() {
+            Ok(jwks)
+        } else {
+            Err(unknown_kid(kid))
+        }
+    }
+
+    /// Fetches the keys from TrueLayer, unless another task refreshed them in the meantime.
+    async fn refresh(&self, kid: Option<&str>) -> Result<Jwks, Error> {
+        let inner = &self.inner;
+        let mut state = inner.state.write().await;
+
+        // Another task might have refreshed the keys while we were waiting for the lock
+        if let Some(cached) = state.as_ref() {
+            if cached.fetched_at.elapsed() < inner.min_refresh_interval {
+                return Ok(cached.all_keys(inner.rotation_grace_period));
+            }
+        }
+
+        tracing::debug!(kid, "Refreshing webhook signing keys");
+        let jwks = inner.client.get_jwks().await?;
+
+        // Retire the keys which are no longer published
+        let now = Instant::now();
+        let mut retired = Vec::new();
+        if let Some(previous) = state.take() {
+            for key in previous.jwks.keys {
+                if jwks.find(&key.kid).is_none() {
+                    tracing::info!(kid = %key.kid, "Webhook signing key rotated out");
+                    retired.push((key, now));
+                }
+            }
+            retired.extend(previous.retired.into_iter().filter(|(key, last_seen)| {
+                last_seen.elapsed() < inner.rotation_grace_period && jwks.find(&key.kid).is_none()
+            }));
+        }
+
+        let cached = CachedJwks {
+            jwks,
+            retired,
+            fetched_at: now,
+        };
+        let all_keys = cached.all_keys(inner.rotation_grace_period);
+        *state = Some(cached);
+
+        Ok(all_keys)
+    }
+
+    /// Refreshes the keys in the background, unless a refresh is already in progress.
+    fn spawn_prefetch(&self) {
+        if self.inner.prefetching.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let cache = self.clone();
+        crate::runtime::spawn(async move {
+            if let Err(e) = cache.refresh(None).await {
+                tracing::warn!(error = %e, "Failed to prefetch webhook signing keys");
+            }
+            cache.inner.prefetching.store(false, Ordering::Release);
+        });
+    }
+}
+
+fn unknown_kid(kid: &str) -> Error {
+    tracing::warn!(
+        kid,
+        "Webhook signed with a key id not published by TrueLayer"
+    );
+    WebhookVerificationError::UnknownKid(kid.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Environment;
+    use reqwest::Url;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn jwks(kids: &[&str]) -> serde_json::Value {
+        json!({
+            "keys": kids
+                .iter()
+                .map(|kid| json!({ "kty": "EC", "kid": kid, "crv": "P-521", "x": "x", "y": "y" }))
+                .collect::<Vec<_>>()
+        })
+    }
+
+    #[tokio::test]
+    async fn rotated_keys_overlap_and_unknown_kids_are_reported() {
+        // Keys get rotated from `old-kid` to `new-kid` after the first fetch
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/jwks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(jwks(&["old-kid"])))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/jwks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(jwks(&["new-kid"])))
+            .mount(&mock_server)
+            .await;
+
+        let cache = JwksCache::new(UnauthenticatedClient::new(Environment::from_single_url(
+            &Url::parse(&mock_server.uri()).unwrap(),
+        )))
+        .with_min_refresh_interval(Duration::ZERO)
+        .with_rotation_grace_period(Duration::from_secs(15 * 60));
+
+        assert!(cache
+            .get("old-kid")
+            .await
+            .unwrap()
+            .find("old-kid")
+            .is_some());
+
+        // Unknown kid triggers a refresh, but the old key is still accepted during the grace period
+        let keys = cache.get("new-kid").await.unwrap();
+        assert!(keys.find("new-kid").is_some());
+        assert!(keys.find("old-kid").is_some());
+        assert!(cache.get("old-kid").await.is_ok());
+
+        // Keys never published are reported with a dedicated error
+        assert!(matches!(
+            cache.get("bogus-kid").await,
+            Err(Error::WebhookVerificationError(WebhookVerificationError::UnknownKid(kid)))
+                if kid == "bogus-kid"
+        ));
+    }
+
+    #[tokio::test]
+    async fn withdrawn_keys_are_rejected_by_default() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/jwks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(jwks(&["old-kid"])))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/jwks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(jwks(&["new-kid"])))
+            .mount(&mock_server)
+            .await;
+
+        let cache = JwksCache::new(UnauthenticatedClient::new(Environment::from_single_url(
+            &Url::parse(&mock_server.uri()).unwrap(),
+        )))
+        .with_min_refresh_interval(Duration::ZERO);
+
+        assert!(cache.get("old-kid").await.is_ok());
+
+        let keys = cache.get("new-kid").await.unwrap();
+        assert!(keys.find("new-kid").is_some());
+        assert!(keys.find("old-kid").is_none());
+        assert!(matches!(
+            cache.get("old-kid").await,
+            Err(Error::WebhookVerificationError(
+                WebhookVerificationError::UnknownKid(_)
+            ))
+        ));
+    }
+}
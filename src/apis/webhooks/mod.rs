@@ -0,0 +1,11 @@
+//! Verification, routing and models of webhooks sent by TrueLayer.
+
+mod jwks;
+mod model;
+mod router;
+mod verifier;
+
+pub use jwks::JwksCache;
+pub use model::*;
+pub use router::{HandlerOutcome, WebhookHandler, WebhookRouter, WebhookRouterMetrics};
+pub use verifier::WebhookVerifier;
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::apis::{mandates::RevocationSource, payments::FailureStage};
+
+/// Set of public keys used by TrueLayer to sign webhooks, as a JSON Web Key Set.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Jwks {
+    pub keys: Vec<Jwk>,
+}
+
+impl Jwks {
+    /// Returns the key with the given id, if present.
+    pub fn find(&self, kid: &str) -> Option<&Jwk> {
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+}
+
+/// Public key used by TrueLayer to sign webhooks, as a JSON Web Key.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Jwk {
+    pub kty: String,
+    pub kid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alg: Option<String>,
+    #[serde(rename = "use", skip_serializing_if = "Option::is_none")]
+    pub r#use: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+}
+
+/// Webhook sent by TrueLayer, as received in the body of the webhook request.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Webhook {
+    /// Unique identifier of the event. Redeliveries of the same event share the same id.
+    pub event_id: String,
+    pub event_version: u32,
+    #[serde(flatten)]
+    pub event: WebhookEvent,
+}
+
+/// Event notified by a [`Webhook`].
+///
+/// Event types which are not known to this version of the SDK are deserialized
+/// as [`Unknown`](WebhookEvent::Unknown), so that new events added by TrueLayer never break deserialization.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    PaymentAuthorized {
+        payment_id: String,
+        authorized_at: DateTime<Utc>,
+        metadata: Option<HashMap<String, String>>,
+    },
+    PaymentExecuted {
+        payment_id: String,
+        executed_at: DateTime<Utc>,
+        metadata: Option<HashMap<String, String>>,
+    },
+    PaymentCreditable {
+        payment_id: String,
+        creditable_at: DateTime<Utc>,
+        metadata: Option<HashMap<String, String>>,
+    },
+    PaymentSettled {
+        payment_id: String,
+        settled_at: DateTime<Utc>,
+        metadata: Option<HashMap<String, String>>,
+    },
+    PaymentSettlementStalled {
+        payment_id: String,
+        settlement_stalled_at: DateTime<Utc>,
+        metadata: Option<HashMap<String, String>>,
+    },
+    PaymentFailed {
+        payment_id: String,
+        failed_at: DateTime<Utc>,
+        failure_stage: FailureStage,
+        failure_reason: String,
+        metadata: Option<HashMap<String, String>>,
+    },
+    PayoutExecuted {
+        payout_id: String,
+        executed_at: DateTime<Utc>,
+    },
+    PayoutFailed {
+        payout_id: String,
+        failed_at: DateTime<Utc>,
+        failure_reason: String,
+    },
+    RefundExecuted {
+        payment_id: String,
+        refund_id: String,
+        executed_at: DateTime<Utc>,
+    },
+    RefundFailed {
+        payment_id: String,
+        refund_id: String,
+        failed_at: DateTime<Utc>,
+        failure_reason: String,
+    },
+    MandateAuthorized {
+        mandate_id: String,
+        authorized_at: DateTime<Utc>,
+        metadata: Option<HashMap<String, String>>,
+    },
+    MandateFailed {
+        mandate_id: String,
+        failed_at: DateTime<Utc>,
+        failure_stage: FailureStage,
+        failure_reason: String,
+        metadata: Option<HashMap<String, String>>,
+    },
+    MandateRevoked {
+        mandate_id: String,
+        revoked_at: DateTime<Utc>,
+        revocation_source: RevocationSource,
+        metadata: Option<HashMap<String, String>>,
+    },
+    /// Event type not supported by this version of the SDK.
+    #[serde(other)]
+    Unknown,
+}
+
+impl WebhookEvent {
+    /// Id of the payment, refund, payout or mandate the event refers to,
+    /// or `None` for unknown events.
+    pub fn resource_id(&self) -> Option<&str> {
+        match self {
+            WebhookEvent::PaymentAuthorized { payment_id, .. }
+            | WebhookEvent::PaymentExecuted { payment_id, .. }
+            | WebhookEvent::PaymentCreditable { payment_id, .. }
+            | WebhookEvent::PaymentSettled { payment_id, .. }
+            | WebhookEvent::PaymentSettlementStalled { payment_id, .. }
+            | WebhookEvent::PaymentFailed { payment_id, .. } => Some(payment_id),
+            WebhookEvent::PayoutExecuted { payout_id, .. }
+            | WebhookEvent::PayoutFailed { payout_id, .. } => Some(payout_id),
+            WebhookEvent::RefundExecuted { refund_id, .. }
+            | WebhookEvent::RefundFailed { refund_id, .. } => Some(refund_id),
+            WebhookEvent::MandateAuthorized { mandate_id, .. }
+            | WebhookEvent::MandateFailed { mandate_id, .. }
+            | WebhookEvent::MandateRevoked { mandate_id, .. } => Some(mandate_id),
+            WebhookEvent::Unknown => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn known_events_round_trip() {
+        let json = json!({
+            "type": "mandate_revoked",
+            "event_id": "event-id",
+            "event_version": 1,
+            "mandate_id": "mandate-id",
+            "revoked_at": "2022-03-01T12:00:00Z",
+            "revocation_source": "client",
+            "metadata": { "key": "value" }
+        });
+
+        let webhook: Webhook = serde_json::from_value(json.clone()).unwrap();
+        assert!(matches!(
+            webhook.event,
+            WebhookEvent::MandateRevoked {
+                revocation_source: RevocationSource::Client,
+                ..
+            }
+        ));
+        assert_eq!(serde_json::to_value(&webhook).unwrap(), json);
+    }
+
+    #[test]
+    fn unknown_events_are_deserialized_as_unknown() {
+        let webhook: Webhook = serde_json::from_value(json!({
+            "type": "some_future_event",
+            "event_id": "event-id",
+            "event_version": 3,
+            "some_future_field": 42
+        }))
+        .unwrap();
+
+        assert_eq!(webhook.event_id, "event-id");
+        assert_eq!(webhook.event, WebhookEvent::Unknown);
+    }
+}
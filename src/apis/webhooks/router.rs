@@ -0,0 +1,300 @@
+use crate::{apis::webhooks::Webhook, apis::webhooks::WebhookVerifier, Error};
+use async_trait::async_trait;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// Outcome of the processing of a webhook by a [`WebhookHandler`].
+#[derive(Debug)]
+pub enum HandlerOutcome {
+    /// The webhook was processed, or had already been processed.
+    Processed,
+    /// The webhook could not be processed because of a transient failure, e.g. a database being unavailable.
+    /// TrueLayer will deliver it again later.
+    Retryable(anyhow::Error),
+    /// The webhook can never be processed, e.g. because it refers to an unknown resource.
+    /// It is acknowledged anyway, so that TrueLayer stops delivering it.
+    Fatal(anyhow::Error),
+}
+
+/// Processes the webhooks accepted by a [`WebhookRouter`].
+///
+/// TrueLayer delivers webhooks at least once: handlers must be idempotent, e.g. by keeping track
+/// of the [`event_id`](Webhook::event_id)s already processed.
+///
+/// Implemented by async closures taking a [`Webhook`] and returning a [`HandlerOutcome`].
+#[async_trait]
+pub trait WebhookHandler: Send + Sync {
+    async fn handle(&self, webhook: Webhook) -> HandlerOutcome;
+}
+
+#[async_trait]
+impl<F, Fut> WebhookHandler for F
+where
+    F: Fn(Webhook) -> Fut + Send + Sync,
+    Fut: Future<Output = HandlerOutcome> + Send,
+{
+    async fn handle(&self, webhook: Webhook) -> HandlerOutcome {
+        self(webhook).await
+    }
+}
+
+/// Number of webhooks routed by a [`WebhookRouter`], by outcome,
+/// returned by [`WebhookRouter::metrics`].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct WebhookRouterMetrics {
+    /// Webhooks processed by the handler.
+    pub processed: u64,
+    /// Webhooks whose processing failed with a retryable error, and which TrueLayer will deliver again.
+    pub retryable: u64,
+    /// Webhooks whose processing failed with a fatal error, and which were acknowledged anyway.
+    pub fatal: u64,
+    /// Webhooks rejected before reaching the handler, because their signature is invalid.
+    pub rejected: u64,
+    /// Webhooks signed by TrueLayer whose body could not be parsed, e.g. because of a breaking change
+    /// of their schema. They are acknowledged without reaching the handler, since delivering them
+    /// again would not help.
+    pub unparseable: u64,
+}
+
+/// Verifies incoming webhooks and hands them over to a [`WebhookHandler`], translating its outcome
+/// into the HTTP status code to respond to TrueLayer with.
+///
+/// TrueLayer delivers again the webhooks which are not acknowledged with a `2xx` status code,
+/// so the router responds with:
+/// - `200 OK` for [`Processed`](HandlerOutcome::Processed) and [`Fatal`](HandlerOutcome::Fatal) outcomes,
+///   and for correctly signed webhooks whose body cannot be parsed;
+/// - `503 Service Unavailable` for [`Retryable`](HandlerOutcome::Retryable) outcomes, and when the
+///   signature cannot be checked because the signing keys cannot be fetched;
+/// - `400 Bad Request` or `401 Unauthorized` for webhooks which fail verification
+///   (see [`WebhookVerificationError::http_status`](crate::error::WebhookVerificationError::http_status)).
+///
+/// The router is independent of any HTTP framework:
+///
+/// ```rust,no_run
+/// # use truelayer_rust::{apis::webhooks::{HandlerOutcome, Webhook, WebhookRouter, WebhookVerifier}, client::Environment};
+/// # async fn handle(headers: Vec<(String, Vec<u8>)>, body: Vec<u8>) {
+/// let router = WebhookRouter::new(
+///     WebhookVerifier::new(Environment::Sandbox),
+///     |webhook: Webhook| async move {
+///         println!("Received {}", webhook.event_id);
+///         HandlerOutcome::Processed
+///     },
+/// );
+///
+/// let status = router
+///     .route(
+///         "/webhooks/truelayer",
+///         headers.iter().map(|(k, v)| (k.as_str(), v.as_slice())),
+///         &body,
+///     )
+///     .await;
+/// # }
+/// ```
+pub struct WebhookRouter {
+    verifier: WebhookVerifier,
+    handler: Box<dyn WebhookHandler>,
+    counters: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    processed: AtomicU64,
+    retryable: AtomicU64,
+    fatal: AtomicU64,
+    rejected: AtomicU64,
+    unparseable: AtomicU64,
+}
+
+impl WebhookRouter {
+    /// Creates a new router handing the webhooks accepted by `verifier` over to `handler`.
+    pub fn new(verifier: WebhookVerifier, handler: impl WebhookHandler + 'static) -> Self {
+        Self {
+            verifier,
+            handler: Box::new(handler),
+            counters: Arc::new(Counters::default()),
+        }
+    }
+
+    /// Verifies and processes a webhook received on the given path,
+    /// returning the HTTP status code to respond with.
+    ///
+    /// See [`WebhookVerifier::verify`] for the expected `headers` and `body`.
+    #[tracing::instrument(name = "Route Webhook", skip(self, headers, body))]
+    pub async fn route<'a>(
+        &self,
+        path: &str,
+        headers: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+        body: &[u8],
+    ) -> u16 {
+        if let Err(e) = self.verifier.verify(path, headers, body).await {
+            return match e {
+                Error::WebhookVerificationError(e) => {
+                    tracing::warn!(error = %e, "Rejected webhook");
+                    self.counters.rejected.fetch_add(1, Ordering::Relaxed);
+                    e.http_status()
+                }
+                e => {
+                    tracing::error!(error = %e, "Could not verify webhook");
+                    self.counters.retryable.fetch_add(1, Ordering::Relaxed);
+                    503
+                }
+            };
+        }
+
+        self.parse_and_dispatch(body).await
+    }
+
+    /// Parses a verified webhook and hands it over to the handler.
+    async fn parse_and_dispatch(&self, body: &[u8]) -> u16 {
+        match serde_json::from_slice(body) {
+            Ok(webhook) => self.dispatch(webhook).await,
+            Err(e) => {
+                // The webhook comes from TrueLayer, delivering it again would fail the same way
+                tracing::error!(error = %e, "Could not parse verified webhook, acknowledging it");
+                self.counters.unparseable.fetch_add(1, Ordering::Relaxed);
+                200
+            }
+        }
+    }
+
+    /// Hands a verified webhook over to the handler.
+    async fn dispatch(&self, webhook: Webhook) -> u16 {
+        let event_id = webhook.event_id.clone();
+        match self.handler.handle(webhook).await {
+            HandlerOutcome::Processed => {
+                self.counters.processed.fetch_add(1, Ordering::Relaxed);
+                200
+            }
+            HandlerOutcome::Retryable(e) => {
+                tracing::warn!(%event_id, error = %e, "Webhook processing failed, waiting for redelivery");
+                self.counters.retryable.fetch_add(1, Ordering::Relaxed);
+                503
+            }
+            HandlerOutcome::Fatal(e) => {
+                tracing::error!(%event_id, error = %e, "Webhook processing failed permanently, acknowledging it");
+                self.counters.fatal.fetch_add(1, Ordering::Relaxed);
+                200
+            }
+        }
+    }
+
+    /// Returns the number of webhooks routed so far, by outcome.
+    pub fn metrics(&self) -> WebhookRouterMetrics {
+        WebhookRouterMetrics {
+            processed: self.counters.processed.load(Ordering::Relaxed),
+            retryable: self.counters.retryable.load(Ordering::Relaxed),
+            fatal: self.counters.fatal.load(Ordering::Relaxed),
+            rejected: self.counters.rejected.load(Ordering::Relaxed),
+            unparseable: self.counters.unparseable.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl std::fmt::Debug for WebhookRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookRouter")
+            .field("verifier", &self.verifier)
+            .field("metrics", &self.metrics())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{apis::webhooks::WebhookEvent, client::Environment};
+    use anyhow::anyhow;
+    use std::sync::atomic::AtomicU32;
+
+    fn webhook(event_id: &str) -> Webhook {
+        Webhook {
+            event_id: event_id.to_string(),
+            event_version: 1,
+            event: WebhookEvent::Unknown,
+        }
+    }
+
+    #[tokio::test]
+    async fn outcomes_are_mapped_to_status_codes() {
+        let attempts = Arc::new(AtomicU32::new(0));
+        let router = WebhookRouter::new(WebhookVerifier::new(Environment::Sandbox), {
+            let attempts = attempts.clone();
+            move |webhook: Webhook| {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    match (webhook.event_id.as_str(), attempt) {
+                        ("flaky", 0) => HandlerOutcome::Retryable(anyhow!("Database unavailable")),
+                        ("poison", _) => HandlerOutcome::Fatal(anyhow!("Unknown payment")),
+                        _ => HandlerOutcome::Processed,
+                    }
+                }
+            }
+        });
+
+        // Retryable failures are not acknowledged, so that TrueLayer delivers the webhook again
+        assert_eq!(router.dispatch(webhook("flaky")).await, 503);
+        assert_eq!(router.dispatch(webhook("flaky")).await, 200);
+
+        // Fatal failures are acknowledged to stop redeliveries
+        assert_eq!(router.dispatch(webhook("poison")).await, 200);
+
+        assert_eq!(
+            router.metrics(),
+            WebhookRouterMetrics {
+                processed: 1,
+                retryable: 1,
+                fatal: 1,
+                rejected: 0,
+                unparseable: 0
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn unparseable_verified_webhooks_are_acknowledged() {
+        let router = WebhookRouter::new(
+            WebhookVerifier::new(Environment::Sandbox),
+            |_: Webhook| async { HandlerOutcome::Processed },
+        );
+
+        assert_eq!(
+            router
+                .parse_and_dispatch(br#"{"event_id":"event-id"}"#)
+                .await,
+            200
+        );
+        assert_eq!(
+            router.metrics(),
+            WebhookRouterMetrics {
+                unparseable: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn unsigned_webhooks_are_rejected_before_reaching_the_handler() {
+        let router = WebhookRouter::new(
+            WebhookVerifier::new(Environment::Sandbox),
+            |_: Webhook| async { HandlerOutcome::Processed },
+        );
+
+        let status = router
+            .route("/webhooks/truelayer", [], br#"{"event_id":"event-id"}"#)
+            .await;
+
+        assert_eq!(status, 400);
+        assert_eq!(
+            router.metrics(),
+            WebhookRouterMetrics {
+                rejected: 1,
+                ..Default::default()
+            }
+        );
+    }
+}
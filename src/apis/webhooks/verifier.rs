@@ -0,0 +1,454 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use crate::{
+    apis::webhooks::{JwksCache, Webhook},
+    client::{Environment, UnauthenticatedClient},
+    common::{TL_SIGNATURE_HEADER, TL_WEBHOOK_TIMESTAMP_HEADER},
+    error::WebhookVerificationError,
+    Error,
+};
+
+/// Verifier of the signatures of incoming webhooks.
+///
+/// Webhooks are signed by TrueLayer with a detached JWS in the `Tl-Signature` header.
+/// The keys used to verify the signatures are fetched from TrueLayer and cached
+/// in a [`JwksCache`](crate::apis::webhooks::JwksCache).
+///
+/// Verification failures are reported as [`Error::WebhookVerificationError`](crate::Error::WebhookVerificationError),
+/// whose [`WebhookVerificationError`](crate::error::WebhookVerificationError) tells which check failed.
+///
+/// ```rust,no_run
+/// # use truelayer_rust::{apis::webhooks::WebhookVerifier, client::Environment};
+/// # async fn handle(headers: Vec<(String, Vec<u8>)>, body: Vec<u8>) -> Result<(), truelayer_rust::Error> {
+/// let verifier = WebhookVerifier::new(Environment::Sandbox);
+///
+/// let webhook = verifier
+///     .verify_and_parse(
+///         "/webhooks/truelayer",
+///         headers.iter().map(|(k, v)| (k.as_str(), v.as_slice())),
+///         &body,
+///     )
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct WebhookVerifier {
+    jwks: JwksCache,
+    timestamp_tolerance: Option<Duration>,
+}
+
+impl WebhookVerifier {
+    /// Creates a new verifier for webhooks sent by the given environment.
+    pub fn new(environment: Environment) -> Self {
+        Self::with_jwks_cache(JwksCache::new(UnauthenticatedClient::new(environment)))
+    }
+
+    /// Creates a new verifier using a preconfigured cache of keys.
+    pub fn with_jwks_cache(jwks: JwksCache) -> Self {
+        Self {
+            jwks,
+            timestamp_tolerance: None,
+        }
+    }
+
+    /// Rejects webhooks whose `X-Tl-Webhook-Timestamp` header is further than `tolerance`
+    /// from the current time, to protect against replayed webhooks. Disabled by default.
+    ///
+    /// The timestamp is checked only after the signature, so that it can be trusted.
+    pub fn with_timestamp_tolerance(mut self, tolerance: Duration) -> Self {
+        self.timestamp_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Verifies the signature of a webhook received on the given path.
+    ///
+    /// `headers` must contain all the headers of the webhook request, including `Tl-Signature`.
+    /// `body` must be the raw body of the request, exactly as received.
+    ///
+    /// If the request carries a `Content-Digest` or `Digest` header with a SHA-256 digest,
+    /// the body is also checked against it.
+    #[tracing::instrument(name = "Verify Webhook", skip(self, headers, body))]
+    pub async fn verify<'a>(
+        &self,
+        path: &str,
+        headers: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+        body: &[u8],
+    ) -> Result<(), Error> {
+        let headers: Vec<(&str, &[u8])> = headers.into_iter().collect();
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, value)| std::str::from_utf8(value).ok())
+        };
+
+        let signature = header(TL_SIGNATURE_HEADER)
+            .flatten()
+            .ok_or_else(|| WebhookVerificationError::MissingHeader(TL_SIGNATURE_HEADER.into()))?;
+
+        for digest_header in ["Content-Digest", "Digest"] {
+            if let Some(digest) = header(digest_header) {
+                let digest = digest
+                    .ok_or_else(|| WebhookVerificationError::MissingHeader(digest_header.into()))?;
+                if !sha256_digest_matches(digest, body) {
+                    return Err(
+                        WebhookVerificationError::BodyDigestMismatch(digest_header.into()).into(),
+                    );
+                }
+            }
+        }
+
+        // Only trust keys published by TrueLayer
+        let jws_header = truelayer_signing::extract_jws_header(signature)
+            .map_err(|e| WebhookVerificationError::MalformedJws(e.into()))?;
+        let jku = jws_header.jku.as_deref().ok_or_else(|| {
+            WebhookVerificationError::MalformedJws(anyhow::anyhow!("Missing jku in JWS header"))
+        })?;
+        let jwks_url = self.jwks.client().jwks_url();
+        if jku != jwks_url.as_str() {
+            return Err(WebhookVerificationError::UntrustedJku(jku.to_string()).into());
+        }
+
+        let jwks = self.jwks.get(&jws_header.kid).await?;
+        let jwks = serde_json::to_vec(&jwks).map_err(|e| Error::Other(e.into()))?;
+
+        truelayer_signing::verify_with_jwks(&jwks)
+            .method("POST")
+            .path(path)
+            .headers(headers.iter().copied())
+            .body(body)
+            .verify(signature)
+            .map_err(|e| WebhookVerificationError::SignatureMismatch(e.into()))?;
+
+        if let Some(tolerance) = self.timestamp_tolerance {
+            let timestamp = header(TL_WEBHOOK_TIMESTAMP_HEADER)
+                .flatten()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.with_timezone(&Utc))
+                .ok_or_else(|| {
+                    WebhookVerificationError::MissingHeader(TL_WEBHOOK_TIMESTAMP_HEADER.into())
+                })?;
+
+            let skew =
+                Duration::from_millis((Utc::now() - timestamp).num_milliseconds().unsigned_abs());
+            if skew > tolerance {
+                return Err(WebhookVerificationError::StaleTimestamp {
+                    timestamp,
+                    tolerance,
+                }
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verifies the signature of a webhook received on the given path and deserializes its body.
+    ///
+    /// See [`verify`](crate::apis::webhooks::WebhookVerifier::verify).
+    pub async fn verify_and_parse<'a>(
+        &self,
+        path: &str,
+        headers: impl IntoIterator<Item = (&'a str, &'a [u8])>,
+        body: &[u8],
+    ) -> Result<Webhook, Error> {
+        self.verify(path, headers, body).await?;
+        serde_json::from_slice(body).map_err(|e| Error::Other(e.into()))
+    }
+}
+
+/// Checks the SHA-256 entry of a `Content-Digest` (`sha-256=:<base64>:`)
+/// or `Digest` (`SHA-256=<base64>`) header, ignoring all the other algorithms.
+fn sha256_digest_matches(header: &str, body: &[u8]) -> bool {
+    let expected = STANDARD.encode(Sha256::digest(body));
+
+    let mut sha256_values = header
+        .split(',')
+        .filter_map(|entry| entry.trim().split_once('='))
+        .filter(|(algorithm, _)| algorithm.trim().eq_ignore_ascii_case("sha-256"))
+        .map(|(_, value)| value.trim().trim_matches(':'))
+        .peekable();
+
+    sha256_values.peek().is_none() || sha256_values.any(|value| value == expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::webhooks::WebhookEvent;
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use openssl::{
+        bn::{BigNum, BigNumContext},
+        ec::{EcGroup, EcKey},
+        nid::Nid,
+        pkey::Private,
+    };
+    use reqwest::Url;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const WEBHOOK_PATH: &str = "/webhooks/truelayer";
+    const TIMESTAMP_HEADER: &str = "X-TL-Webhook-Timestamp";
+
+    struct Setup {
+        mock_server: MockServer,
+        verifier: WebhookVerifier,
+        key: EcKey<Private>,
+    }
+
+    impl Setup {
+        async fn new(expected_jwks_fetches: u64) -> Self {
+            let group = EcGroup::from_curve_name(Nid::SECP521R1).unwrap();
+            let key = EcKey::generate(&group).unwrap();
+
+            let (mut x, mut y) = (BigNum::new().unwrap(), BigNum::new().unwrap());
+            key.public_key()
+                .affine_coordinates(&group, &mut x, &mut y, &mut BigNumContext::new().unwrap())
+                .unwrap();
+
+            let mock_server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(path("/.well-known/jwks"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "keys": [
+                        {
+                            "kty": "EC",
+                            "kid": "webhook-kid",
+                            "crv": "P-521",
+                            "alg": "ES512",
+                            "x": URL_SAFE_NO_PAD.encode(x.to_vec_padded(66).unwrap()),
+                            "y": URL_SAFE_NO_PAD.encode(y.to_vec_padded(66).unwrap()),
+                        }
+                    ]
+                })))
+                .expect(expected_jwks_fetches)
+                .mount(&mock_server)
+                .await;
+
+            let verifier = WebhookVerifier::new(Environment::from_single_url(
+                &Url::parse(&mock_server.uri()).unwrap(),
+            ));
+
+            Self {
+                mock_server,
+                verifier,
+                key,
+            }
+        }
+
+        fn sign(&self, jku: &str, body: &[u8]) -> String {
+            truelayer_signing::sign_with_pem("webhook-kid", &self.key.private_key_to_pem().unwrap())
+                .method("POST")
+                .path(WEBHOOK_PATH)
+                .header(TIMESTAMP_HEADER, b"2022-03-01T12:00:00Z")
+                .body(body)
+                .jku(jku)
+                .sign()
+                .unwrap()
+        }
+    }
+
+    fn webhook_body() -> Vec<u8> {
+        serde_json::to_vec(&json!({
+            "type": "payment_executed",
+            "event_version": 1,
+            "event_id": "event-id",
+            "payment_id": "payment-id",
+            "executed_at": "2022-03-01T12:00:00Z",
+            "metadata": null
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn verifies_and_parses_valid_webhooks() {
+        let setup = Setup::new(1).await;
+        let body = webhook_body();
+        let signature = setup.sign(
+            &format!("{}/.well-known/jwks", setup.mock_server.uri()),
+            &body,
+        );
+
+        // Verify twice, the keys must be fetched only once
+        for _ in 0..2 {
+            let webhook = setup
+                .verifier
+                .verify_and_parse(
+                    WEBHOOK_PATH,
+                    [
+                        (TIMESTAMP_HEADER, b"2022-03-01T12:00:00Z".as_slice()),
+                        (TL_SIGNATURE_HEADER, signature.as_bytes()),
+                    ],
+                    &body,
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(webhook.event_id, "event-id");
+            assert!(matches!(
+                webhook.event,
+                WebhookEvent::PaymentExecuted { ref payment_id, .. } if payment_id == "payment-id"
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_tampered_webhooks() {
+        let setup = Setup::new(1).await;
+        let body = webhook_body();
+        let signature = setup.sign(
+            &format!("{}/.well-known/jwks", setup.mock_server.uri()),
+            &body,
+        );
+
+        let mut tampered = body.clone();
+        tampered.extend_from_slice(b" ");
+
+        let res = setup
+            .verifier
+            .verify(
+                WEBHOOK_PATH,
+                [
+                    (TIMESTAMP_HEADER, b"2022-03-01T12:00:00Z".as_slice()),
+                    (TL_SIGNATURE_HEADER, signature.as_bytes()),
+                ],
+                &tampered,
+            )
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::WebhookVerificationError(
+                WebhookVerificationError::SignatureMismatch(_)
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_untrusted_jku_without_fetching_keys() {
+        let setup = Setup::new(0).await;
+        let body = webhook_body();
+        let signature = setup.sign("https://attacker.example/.well-known/jwks", &body);
+
+        let res = setup
+            .verifier
+            .verify(
+                WEBHOOK_PATH,
+                [
+                    (TIMESTAMP_HEADER, b"2022-03-01T12:00:00Z".as_slice()),
+                    (TL_SIGNATURE_HEADER, signature.as_bytes()),
+                ],
+                &body,
+            )
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::WebhookVerificationError(WebhookVerificationError::UntrustedJku(ref jku)))
+                if jku == "https://attacker.example/.well-known/jwks"
+        ));
+
+        // Missing signature
+        let res = setup
+            .verifier
+            .verify(
+                WEBHOOK_PATH,
+                [(TIMESTAMP_HEADER, b"2022-03-01T12:00:00Z".as_slice())],
+                &body,
+            )
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::WebhookVerificationError(WebhookVerificationError::MissingHeader(ref h)))
+                if h == TL_SIGNATURE_HEADER
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_stale_timestamps() {
+        let setup = Setup::new(1).await;
+        let body = webhook_body();
+        let signature = setup.sign(
+            &format!("{}/.well-known/jwks", setup.mock_server.uri()),
+            &body,
+        );
+        let verifier = WebhookVerifier::with_jwks_cache(setup.verifier.jwks.clone())
+            .with_timestamp_tolerance(Duration::from_secs(300));
+
+        let res = verifier
+            .verify(
+                WEBHOOK_PATH,
+                [
+                    (TIMESTAMP_HEADER, b"2022-03-01T12:00:00Z".as_slice()),
+                    (TL_SIGNATURE_HEADER, signature.as_bytes()),
+                ],
+                &body,
+            )
+            .await;
+        let err = match res {
+            Err(Error::WebhookVerificationError(e)) => e,
+            res => panic!("Unexpected result: {:?}", res),
+        };
+        assert!(matches!(
+            err,
+            WebhookVerificationError::StaleTimestamp { tolerance, .. }
+                if tolerance == Duration::from_secs(300)
+        ));
+        assert!(err.is_suspicious());
+        assert_eq!(err.http_status(), 401);
+    }
+
+    #[tokio::test]
+    async fn rejects_body_digest_mismatch_without_fetching_keys() {
+        let setup = Setup::new(0).await;
+        let body = webhook_body();
+        let signature = setup.sign(
+            &format!("{}/.well-known/jwks", setup.mock_server.uri()),
+            &body,
+        );
+        let digest = format!("sha-256=:{}:", STANDARD.encode(Sha256::digest(b"other")));
+
+        let res = setup
+            .verifier
+            .verify(
+                WEBHOOK_PATH,
+                [
+                    (TIMESTAMP_HEADER, b"2022-03-01T12:00:00Z".as_slice()),
+                    (TL_SIGNATURE_HEADER, signature.as_bytes()),
+                    ("Content-Digest", digest.as_bytes()),
+                ],
+                &body,
+            )
+            .await;
+        assert!(matches!(
+            res,
+            Err(Error::WebhookVerificationError(
+                WebhookVerificationError::BodyDigestMismatch(ref h)
+            )) if h == "Content-Digest"
+        ));
+    }
+
+    #[test]
+    fn sha256_digests() {
+        let digest = STANDARD.encode(Sha256::digest(b"body"));
+
+        assert!(sha256_digest_matches(
+            &format!("sha-256=:{}:", digest),
+            b"body"
+        ));
+        assert!(sha256_digest_matches(
+            &format!("SHA=abc, SHA-256={}", digest),
+            b"body"
+        ));
+        assert!(sha256_digest_matches("sha-512=:abc:", b"body"));
+        assert!(!sha256_digest_matches(
+            &format!("sha-256=:{}:", digest),
+            b"other"
+        ));
+    }
+}
@@ -0,0 +1,130 @@
+//! Auditing of the interactions with TrueLayer.
+//!
+//! Interceptors registered with
+//! [`with_response_interceptor`](crate::client::TrueLayerClientBuilder::with_response_interceptor)
+//! are invoked with a [`ResponseSummary`] after every HTTP call sent to TrueLayer has completed,
+//! including retries and calls to the Auth server. This is meant for teams which must keep
+//! an immutable audit log of every interaction with their payments provider.
+//!
+//! Summaries never contain request or response bodies, nor any header.
+
+use reqwest::{Method, Url};
+use std::{
+    fmt::{Debug, Formatter},
+    sync::Arc,
+    time::Duration,
+};
+
+/// Collections of TrueLayer resources whose paths are followed by the id of a resource.
+const RESOURCE_COLLECTIONS: &[&str] = &[
+    "payments",
+    "refunds",
+    "payouts",
+    "merchant-accounts",
+    "mandates",
+    "payments-providers",
+    "payment-links",
+    "verifications",
+    // Data APIs, e.g. `/data/v1/accounts/{id}/balance`
+    "accounts",
+];
+
+/// Path segments which follow a collection name but are not resource ids.
+const COLLECTION_ACTIONS: &[&str] = &["search"];
+
+/// Summary of a completed HTTP call to TrueLayer.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ResponseSummary {
+    /// HTTP method of the request.
+    pub method: Method,
+    /// Host to which the request was sent.
+    pub host: String,
+    /// Path of the request, with all the resource ids replaced by `{id}`,
+    /// e.g. `/payments/{id}/refunds/{id}`.
+    pub path_template: String,
+    /// Ids of the resources found in the path, in order of appearance.
+    pub resource_ids: Vec<String>,
+    /// HTTP status code of the response, or `None` if no response was received
+    /// (e.g., because of a network error).
+    pub status: Option<u16>,
+    /// Time elapsed between sending the request and receiving the response headers.
+    pub duration: Duration,
+}
+
+impl ResponseSummary {
+    pub(crate) fn new(method: Method, url: &Url, status: Option<u16>, duration: Duration) -> Self {
+        let (path_template, resource_ids) = templatize_path(url.path());
+
+        Self {
+            method,
+            host: url.host_str().unwrap_or_default().to_string(),
+            path_template,
+            resource_ids,
+            status,
+            duration,
+        }
+    }
+}
+
+/// Callback invoked with the summary of every completed call.
+#[derive(Clone)]
+pub(crate) struct ResponseInterceptor(pub Arc<dyn Fn(&ResponseSummary) + Send + Sync + 'static>);
+
+impl Debug for ResponseInterceptor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResponseInterceptor")
+            .finish_non_exhaustive()
+    }
+}
+
+/// Splits a path into its template and the resource ids it contains.
+pub(crate) fn templatize_path(path: &str) -> (String, Vec<String>) {
+    let mut template = String::with_capacity(path.len());
+    let mut ids = Vec::new();
+    let mut previous: Option<&str> = None;
+
+    for segment in path.split('/').skip(1) {
+        template.push('/');
+
+        let is_id = matches!(previous, Some(p) if RESOURCE_COLLECTIONS.contains(&p))
+            && !segment.is_empty()
+            && !COLLECTION_ACTIONS.contains(&segment);
+        if is_id {
+            template.push_str("{id}");
+            ids.push(
+                urlencoding::decode(segment)
+                    .map(|s| s.into_owned())
+                    .unwrap_or_else(|_| segment.to_string()),
+            );
+            previous = None;
+        } else {
+            template.push_str(segment);
+            previous = Some(segment);
+        }
+    }
+
+    (template, ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("/payments", "/payments", &[] ; "collection")]
+    #[test_case("/payments/payment-id", "/payments/{id}", &["payment-id"] ; "resource")]
+    #[test_case("/payments/p-id/refunds/r-id", "/payments/{id}/refunds/{id}", &["p-id", "r-id"] ; "nested resource")]
+    #[test_case("/payments/p-id/actions/cancel", "/payments/{id}/actions/cancel", &["p-id"] ; "action")]
+    #[test_case("/merchant-accounts/m%20id/transactions", "/merchant-accounts/{id}/transactions", &["m id"] ; "encoded id")]
+    #[test_case("/payment-links/l-id/payments", "/payment-links/{id}/payments", &["l-id"] ; "payment link payments")]
+    #[test_case("/verifications/v-id", "/verifications/{id}", &["v-id"] ; "verification")]
+    #[test_case("/data/v1/accounts/a-id/transactions", "/data/v1/accounts/{id}/transactions", &["a-id"] ; "data account transactions")]
+    #[test_case("/payments-providers/search", "/payments-providers/search", &[] ; "collection action")]
+    #[test_case("/connect/token", "/connect/token", &[] ; "auth")]
+    #[test_case("/", "/", &[] ; "root")]
+    fn path_templates(path: &str, expected_template: &str, expected_ids: &[&str]) {
+        let (template, ids) = templatize_path(path);
+        assert_eq!(template, expected_template);
+        assert_eq!(ids, expected_ids);
+    }
+}
@@ -1,27 +1,114 @@
 use crate::{
-    apis::auth::{AccessToken, AuthenticationResult, Credentials},
+    apis::auth::{
+        AccessToken, AuthConcurrencyLimit, AuthenticationResult, Credentials, InMemoryTokenStore,
+        StoredToken, Token, TokenRefreshFailurePolicy, TokenStore, TokenStoreKey,
+    },
     error::Error,
+    runtime::sync::{mpsc, oneshot},
 };
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::{Duration, Utc};
+use rand::Rng;
 use reqwest::Url;
 use reqwest_middleware::ClientWithMiddleware;
-use tokio::sync::{mpsc, oneshot};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 /// Manager for credentials and access tokens.
 #[derive(Debug, Clone)]
 pub struct Authenticator {
-    tx: mpsc::UnboundedSender<oneshot::Sender<Result<AuthenticationResult, Error>>>,
+    tx: mpsc::UnboundedSender<Command>,
     pub(crate) client_id: String,
+    pub(crate) stale_tokens_served: Arc<AtomicU64>,
+}
+
+/// How long before their expiration access tokens are refreshed by default.
+pub const DEFAULT_REFRESH_MARGIN: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// Optional settings of an [`Authenticator`].
+#[derive(Debug, Clone)]
+pub struct AuthenticatorOptions {
+    /// Audience of the requested tokens, sent as a `resource` indicator.
+    pub audience: Option<String>,
+    /// What to do when a token is due for refresh but the refresh fails.
+    pub refresh_failure_policy: TokenRefreshFailurePolicy,
+    /// Counter of the stale tokens served because of [`TokenRefreshFailurePolicy::ServeStale`].
+    /// Can be shared between multiple authenticators.
+    pub stale_tokens_served: Arc<AtomicU64>,
+    /// Upper bound of the random delay before requesting the first token.
+    ///
+    /// All the requests received in the meantime are served with the same token.
+    pub startup_jitter: std::time::Duration,
+    /// Limit on the concurrent requests to the Auth server, possibly shared with other authenticators.
+    pub concurrency_limit: Option<AuthConcurrencyLimit>,
+    /// How long before their expiration access tokens are refreshed.
+    pub refresh_margin: std::time::Duration,
+    /// Where tokens are persisted, possibly shared with other authenticators and processes.
+    pub token_store: Arc<dyn TokenStore>,
+}
+
+impl Default for AuthenticatorOptions {
+    fn default() -> Self {
+        Self {
+            audience: None,
+            refresh_failure_policy: TokenRefreshFailurePolicy::default(),
+            stale_tokens_served: Arc::default(),
+            startup_jitter: std::time::Duration::ZERO,
+            concurrency_limit: None,
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            token_store: Arc::new(InMemoryTokenStore::new()),
+        }
+    }
 }
 
 impl Authenticator {
     /// Starts a new authenticator with the given initial credentials.
     pub fn new(client: ClientWithMiddleware, auth_url: Url, credentials: Credentials) -> Self {
+        Self::with_audience(client, auth_url, credentials, None)
+    }
+
+    /// Starts a new authenticator which requests tokens restricted to the given audience
+    /// (sent as a `resource` indicator, as per [RFC 8707](https://datatracker.ietf.org/doc/html/rfc8707)).
+    pub fn with_audience(
+        client: ClientWithMiddleware,
+        auth_url: Url,
+        credentials: Credentials,
+        audience: Option<String>,
+    ) -> Self {
+        Self::with_options(
+            client,
+            auth_url,
+            credentials,
+            AuthenticatorOptions {
+                audience,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Starts a new authenticator with the given options.
+    pub fn with_options(
+        client: ClientWithMiddleware,
+        auth_url: Url,
+        credentials: Credentials,
+        options: AuthenticatorOptions,
+    ) -> Self {
+        let stale_tokens_served = options.stale_tokens_served.clone();
         let state = AuthenticatorState {
             client,
             auth_url,
+            grant: grant_fingerprint(&credentials),
             credentials: credentials.clone(),
-            access_token: None,
+            options,
+            access_tokens: HashMap::new(),
+            code_exchanged: false,
+            stored_refresh_token: None,
         };
 
         // Spawn a long running task which will running forever until the authenticator is dropped
@@ -32,13 +119,14 @@ impl Authenticator {
             process_loop(state, rx).await;
         });
         #[cfg(not(test))]
-        tokio::spawn(async move {
+        crate::runtime::spawn(async move {
             process_loop(state, rx).await;
         });
 
         Self {
             tx,
             client_id: credentials.client_id().into(),
+            stale_tokens_served,
         }
     }
 
@@ -51,11 +139,51 @@ impl Authenticator {
     ///
     /// If the client is already authenticated, this is a no-op.
     pub async fn get_access_token(&self) -> Result<AuthenticationResult, Error> {
+        self.send_get_access_token(None).await
+    }
+
+    /// Same as [`get_access_token`](Authenticator::get_access_token), but for a token restricted to the given scope
+    /// instead of the scope of the credentials. Tokens are cached and refreshed independently for each scope.
+    ///
+    /// Only supported with [`Credentials::ClientCredentials`].
+    pub async fn get_access_token_for_scope(
+        &self,
+        scope: &str,
+    ) -> Result<AuthenticationResult, Error> {
+        self.send_get_access_token(Some(scope.to_string())).await
+    }
+
+    async fn send_get_access_token(
+        &self,
+        scope: Option<String>,
+    ) -> Result<AuthenticationResult, Error> {
         let (tx, rx) = oneshot::channel();
-        self.tx.send(tx).unwrap();
+        self.tx.send(Command::GetAccessToken(scope, tx)).unwrap();
 
         rx.await.unwrap()
     }
+
+    /// Discards the given access token, e.g. because it was rejected by TrueLayer before its expiration,
+    /// so that the next call to [`get_access_token`](Authenticator::get_access_token) requests a new one.
+    ///
+    /// This is a no-op if the authenticator already replaced the token with a different one.
+    pub fn invalidate_access_token(&self, access_token: &AccessToken) {
+        self.tx
+            .send(Command::InvalidateAccessToken(access_token.clone()))
+            .unwrap();
+    }
+}
+
+/// Commands processed by the authenticator task.
+///
+/// Commands are processed one at a time, so that concurrent callers waiting for a token refresh
+/// are all served by a single request to the Auth server.
+enum Command {
+    GetAccessToken(
+        Option<String>,
+        oneshot::Sender<Result<AuthenticationResult, Error>>,
+    ),
+    InvalidateAccessToken(AccessToken),
 }
 
 /// Internal state of the authenticator.
@@ -63,20 +191,53 @@ struct AuthenticatorState {
     client: ClientWithMiddleware,
     auth_url: Url,
     credentials: Credentials,
-    access_token: Option<AccessToken>,
+    options: AuthenticatorOptions,
+    /// Cached access tokens, by requested scope (`None` for the scope of the credentials).
+    access_tokens: HashMap<Option<String>, AccessToken>,
+    /// Whether the authorization code of the credentials has already been exchanged for a token.
+    code_exchanged: bool,
+    /// Fingerprint of the initial user-delegated credentials, see [`TokenStoreKey::grant`].
+    grant: Option<String>,
+    /// Refresh token found in the token store by the latest lookup.
+    stored_refresh_token: Option<Token>,
 }
 
-async fn process_loop(
-    mut state: AuthenticatorState,
-    mut rx: mpsc::UnboundedReceiver<oneshot::Sender<Result<AuthenticationResult, Error>>>,
-) {
+async fn process_loop(mut state: AuthenticatorState, mut rx: mpsc::UnboundedReceiver<Command>) {
     // Infinite loop waiting for commands from the main client
-    while let Some(reply) = rx.recv().await {
-        if reply
-            .send(process_get_access_token(&mut state).await)
-            .is_err()
-        {
-            tracing::warn!("Receiver dropped before the reply");
+    while let Some(command) = rx.recv().await {
+        match command {
+            Command::GetAccessToken(scope, reply) => {
+                if reply
+                    .send(process_get_access_token(&mut state, scope).await)
+                    .is_err()
+                {
+                    tracing::warn!("Receiver dropped before the reply");
+                }
+            }
+            Command::InvalidateAccessToken(rejected) => {
+                // Expire the token rather than dropping it, so that it's never served as stale
+                let mut expired = Vec::new();
+                for (scope, token) in state.access_tokens.iter_mut() {
+                    if token.token.expose_secret() == rejected.token.expose_secret() {
+                        tracing::info!("Discarding rejected access token");
+                        token.expires_at = Some(now());
+                        expired.push((scope.clone(), token.clone()));
+                    }
+                }
+
+                // Make sure it's not adopted again from the token store. User-delegated access tokens
+                // are never adopted, and saving them could overwrite a refresh token rotated elsewhere.
+                if state.grant.is_some() {
+                    continue;
+                }
+                for (scope, token) in expired {
+                    let stored = StoredToken {
+                        access_token: token,
+                        refresh_token: state.credentials.refresh_token().cloned(),
+                    };
+                    save_token(&state, scope.as_deref(), stored).await;
+                }
+            }
         }
     }
 }
@@ -84,10 +245,11 @@ async fn process_loop(
 #[tracing::instrument(name = "Get Access Token", level = "debug", skip(state))]
 async fn process_get_access_token(
     state: &mut AuthenticatorState,
+    scope: Option<String>,
 ) -> Result<AuthenticationResult, Error> {
     // If we are already authenticated, do nothing
-    if let Some(token) = &state.access_token {
-        if !should_refresh_token(token) {
+    if let Some(token) = state.access_tokens.get(&scope) {
+        if !should_refresh_token(token, state.options.refresh_margin) {
             tracing::debug!("Reusing existing access token");
             return Ok(AuthenticationResult {
                 access_token: token.clone(),
@@ -96,12 +258,108 @@ async fn process_get_access_token(
         }
     }
 
+    // Another process, or a previous run of this one, might have stored a newer token
+    if let Some(res) = load_stored_token(state, &scope).await {
+        return Ok(res);
+    }
+
+    // On startup, wait a random delay to spread the load of large fleets starting at the same time.
+    // Concurrent callers are queued in the meantime and all served with the same token.
+    if state.access_tokens.is_empty() && !state.options.startup_jitter.is_zero() {
+        let jitter =
+            rand::thread_rng().gen_range(std::time::Duration::ZERO..state.options.startup_jitter);
+        tracing::debug!(?jitter, "Delaying first access token request");
+        crate::runtime::sleep(jitter).await;
+    }
+
+    // Wait for our turn if too many authenticators are contacting the Auth server
+    let _permit = match &state.options.concurrency_limit {
+        Some(limit) => Some(
+            limit
+                .0
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("Auth concurrency semaphore is never closed"),
+        ),
+        None => None,
+    };
+
+    match request_access_token(state, scope.as_deref()).await {
+        Ok(res) => Ok(res),
+        Err(e) => match (
+            state.options.refresh_failure_policy,
+            state.access_tokens.get(&scope),
+        ) {
+            // Keep serving the cached token, if it's still valid for long enough
+            (TokenRefreshFailurePolicy::ServeStale { leeway }, Some(token))
+                if is_usable_while_stale(token, leeway) =>
+            {
+                let served = state
+                    .options
+                    .stale_tokens_served
+                    .fetch_add(1, Ordering::Relaxed)
+                    + 1;
+                tracing::warn!(
+                    error = %e,
+                    expires_at = ?token.expires_at,
+                    stale_tokens_served = served,
+                    "Failed to refresh access token, serving stale token"
+                );
+
+                Ok(AuthenticationResult {
+                    access_token: token.clone(),
+                    refresh_token: state.credentials.refresh_token().cloned(),
+                })
+            }
+            _ => Err(e),
+        },
+    }
+}
+
+async fn request_access_token(
+    state: &mut AuthenticatorState,
+    scope: Option<&str>,
+) -> Result<AuthenticationResult, Error> {
+    // Authorization codes are single use, replaying one would only get an opaque error from the Auth server
+    if state.code_exchanged && matches!(state.credentials, Credentials::AuthorizationCode { .. }) {
+        return Err(Error::Other(anyhow::anyhow!(
+            "The authorization code has already been exchanged and no refresh token was issued: \
+             the user must grant consent again"
+        )));
+    }
+
+    // Only client credentials can request a specific scope
+    let scoped_credentials = match (scope, &state.credentials) {
+        (None, _) => None,
+        (Some(scope), Credentials::ClientCredentials { .. }) => {
+            let mut credentials = state.credentials.clone();
+            if let Credentials::ClientCredentials {
+                scope: ref mut credentials_scope,
+                ..
+            } = credentials
+            {
+                *credentials_scope = scope.to_string();
+            }
+            Some(credentials)
+        }
+        (Some(scope), _) => {
+            return Err(Error::Other(anyhow::anyhow!(
+            "Cannot request an access token for scope {}: only supported with client credentials",
+            scope
+        )))
+        }
+    };
+
     // Post to the auth server with the current credentials.
     // This will use whatever authentication method the user set up.
     let res: RawAuthenticationResponse = state
         .client
         .post(state.auth_url.join("/connect/token").unwrap())
-        .json(&state.credentials)
+        .json(&TokenRequest {
+            credentials: scoped_credentials.as_ref().unwrap_or(&state.credentials),
+            resource: state.options.audience.as_deref(),
+        })
         .send()
         .await?
         .json()
@@ -119,7 +377,10 @@ async fn process_get_access_token(
         token: res.access_token.into(),
         expires_at: Some(now() + Duration::seconds(res.expires_in)),
     };
-    state.access_token = Some(token.clone());
+    state
+        .access_tokens
+        .insert(scope.map(str::to_string), token.clone());
+    state.code_exchanged = true;
 
     tracing::info!("Got new access token");
 
@@ -134,17 +395,166 @@ async fn process_get_access_token(
         tracing::info!("Switching to refresh token for subsequent authentication requests");
     }
 
+    let stored = StoredToken {
+        access_token: token.clone(),
+        refresh_token: state.credentials.refresh_token().cloned(),
+    };
+    if state.grant.is_some() {
+        save_rotated_token(state, stored).await;
+    } else {
+        save_token(state, scope, stored).await;
+    }
+
     Ok(AuthenticationResult {
         access_token: token,
         refresh_token: res.refresh_token.map(Token::from),
     })
 }
 
-/// Returns `true` if the token is close to expiration (10 minutes before actual expiration)
-/// and should be refreshed. If this token does not expire, this function always returns `false`.
-fn should_refresh_token(token: &AccessToken) -> bool {
-    token.expires_at.map_or(false, |expires_at| {
-        now() >= expires_at - Duration::minutes(10)
+/// Identifies the grant of user-delegated credentials without revealing it.
+///
+/// The fingerprint is computed once from the initial credentials, so that it does not change
+/// when the refresh token is rotated.
+fn grant_fingerprint(credentials: &Credentials) -> Option<String> {
+    let grant = match credentials {
+        Credentials::AuthorizationCode { code, .. } => format!("authorization_code:{}", code),
+        Credentials::RefreshToken { refresh_token, .. } => {
+            format!("refresh_token:{}", refresh_token.expose_secret())
+        }
+        Credentials::ClientCredentials { .. } => return None,
+    };
+
+    Some(URL_SAFE_NO_PAD.encode(Sha256::digest(grant)))
+}
+
+/// Key of the tokens of the given scope in the token store.
+fn token_store_key(state: &AuthenticatorState, scope: Option<&str>) -> TokenStoreKey {
+    let scope = match &state.credentials {
+        Credentials::ClientCredentials {
+            scope: credentials_scope,
+            ..
+        } => Some(scope.unwrap_or(credentials_scope).to_string()),
+        _ => None,
+    };
+
+    TokenStoreKey {
+        client_id: state.credentials.client_id().to_string(),
+        audience: state.options.audience.clone(),
+        scope,
+        grant: state.grant.clone(),
+    }
+}
+
+/// Switches to a refresh token persisted in the token store, which supersedes the one of the credentials.
+fn adopt_refresh_token(state: &mut AuthenticatorState, refresh_token: Token) {
+    if state.credentials.refresh_token().map(Token::expose_secret)
+        != Some(refresh_token.expose_secret())
+    {
+        tracing::info!("Switching to stored refresh token");
+        state.credentials = Credentials::RefreshToken {
+            client_id: state.credentials.client_id().to_string(),
+            client_secret: state.credentials.client_secret().clone(),
+            refresh_token,
+        };
+    }
+}
+
+/// Adopts the token persisted in the token store, if it does not need a refresh yet.
+///
+/// Only access tokens obtained with client credentials are adopted. With user-delegated credentials,
+/// the persisted refresh token of the same grant is adopted instead.
+async fn load_stored_token(
+    state: &mut AuthenticatorState,
+    scope: &Option<String>,
+) -> Option<AuthenticationResult> {
+    let key = token_store_key(state, scope.as_deref());
+    let stored = match state.options.token_store.load(&key).await {
+        Ok(stored) => stored,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to load tokens from the token store");
+            return None;
+        }
+    };
+    state.stored_refresh_token = stored
+        .as_ref()
+        .and_then(|stored| stored.refresh_token.clone());
+    let stored = stored?;
+
+    if state.grant.is_some() {
+        if let Some(refresh_token) = stored.refresh_token {
+            adopt_refresh_token(state, refresh_token);
+        }
+        return None;
+    }
+
+    if should_refresh_token(&stored.access_token, state.options.refresh_margin) {
+        return None;
+    }
+
+    tracing::debug!("Reusing stored access token");
+    state
+        .access_tokens
+        .insert(scope.clone(), stored.access_token.clone());
+    Some(AuthenticationResult {
+        access_token: stored.access_token,
+        refresh_token: state.credentials.refresh_token().cloned(),
+    })
+}
+
+/// Persists the tokens obtained with user-delegated credentials, unless another client rotated
+/// the refresh token of the same grant in the meantime.
+async fn save_rotated_token(state: &mut AuthenticatorState, token: StoredToken) {
+    let key = token_store_key(state, None);
+    let refresh_token = token.refresh_token.clone();
+    let swapped = state
+        .options
+        .token_store
+        .compare_and_swap(&key, state.stored_refresh_token.as_ref(), token)
+        .await;
+    match swapped {
+        Ok(true) => state.stored_refresh_token = refresh_token,
+        Ok(false) => {
+            tracing::warn!("Refresh token rotated by another client, switching to it");
+            let stored = state.options.token_store.load(&key).await;
+            match stored {
+                Ok(stored) => {
+                    state.stored_refresh_token = stored.and_then(|stored| stored.refresh_token);
+                    if let Some(refresh_token) = state.stored_refresh_token.clone() {
+                        adopt_refresh_token(state, refresh_token);
+                    }
+                }
+                Err(e) => tracing::warn!(error = %e, "Failed to load tokens from the token store"),
+            }
+        }
+        Err(e) => tracing::warn!(error = %e, "Failed to save tokens to the token store"),
+    }
+}
+
+/// Persists a token in the token store.
+async fn save_token(state: &AuthenticatorState, scope: Option<&str>, token: StoredToken) {
+    let key = token_store_key(state, scope);
+    if let Err(e) = state.options.token_store.save(&key, token).await {
+        tracing::warn!(error = %e, "Failed to save tokens to the token store");
+    }
+}
+
+/// Returns `true` if the token expires within the given margin and should be refreshed.
+/// If this token does not expire, this function always returns `false`.
+fn should_refresh_token(token: &AccessToken, margin: std::time::Duration) -> bool {
+    match (token.expires_at, Duration::from_std(margin)) {
+        (None, _) => false,
+        (Some(expires_at), Ok(margin)) => now() >= expires_at - margin,
+        (Some(_), Err(_)) => true,
+    }
+}
+
+/// Returns `true` if the token does not expire within the given leeway.
+fn is_usable_while_stale(token: &AccessToken, leeway: std::time::Duration) -> bool {
+    let leeway = Duration::from_std(leeway).unwrap_or_else(|_| Duration::max_value());
+    token.expires_at.map_or(true, |expires_at| {
+        expires_at
+            .checked_sub_signed(leeway)
+            .map_or(false, |deadline| now() < deadline)
     })
 }
 
@@ -153,10 +563,18 @@ fn should_refresh_token(token: &AccessToken) -> bool {
 fn now() -> chrono::DateTime<Utc> {
     Utc::now()
 }
-use crate::apis::auth::Token;
 #[cfg(test)]
 use tests::mocked_time::now;
 
+/// Body of an authentication request.
+#[derive(serde::Serialize)]
+struct TokenRequest<'a> {
+    #[serde(flatten)]
+    credentials: &'a Credentials,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resource: Option<&'a str>,
+}
+
 /// Successful response of an authentication request.
 #[derive(serde::Deserialize)]
 struct RawAuthenticationResponse {
@@ -393,6 +811,369 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn authorization_code_is_exchanged_once_and_refresh_tokens_are_rotated() {
+        mocked_time::scope(Utc::now(), async move {
+            let mock_server = MockServer::start().await;
+            let token_response = |i: u32| {
+                ResponseTemplate::new(200).set_body_json(json!({
+                    "token_type": "Bearer",
+                    "access_token": format!("{}-{}", MOCK_ACCESS_TOKEN, i),
+                    "expires_in": 3600,
+                    "refresh_token": format!("{}-{}", MOCK_REFRESH_TOKEN, i)
+                }))
+            };
+            Mock::given(method("POST"))
+                .and(path("/connect/token"))
+                .and(body_partial_json(json!({
+                    "grant_type": "authorization_code",
+                    "code": "mock-code",
+                    "redirect_uri": "https://my.redirect.uri"
+                })))
+                .respond_with(token_response(0))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+            for i in 0..2 {
+                Mock::given(method("POST"))
+                    .and(path("/connect/token"))
+                    .and(body_partial_json(json!({
+                        "grant_type": "refresh_token",
+                        "refresh_token": format!("{}-{}", MOCK_REFRESH_TOKEN, i)
+                    })))
+                    .respond_with(token_response(i + 1))
+                    .expect(1)
+                    .mount(&mock_server)
+                    .await;
+            }
+
+            let authenticator = Authenticator::new(
+                reqwest::Client::new().into(),
+                Url::parse(&mock_server.uri()).unwrap(),
+                Credentials::AuthorizationCode {
+                    client_id: MOCK_CLIENT_ID.into(),
+                    client_secret: MOCK_CLIENT_SECRET.into(),
+                    code: "mock-code".into(),
+                    redirect_uri: "https://my.redirect.uri".into(),
+                },
+            );
+
+            // Each refresh uses the refresh token issued by the previous one
+            for i in 0..3 {
+                let res = authenticator.get_access_token().await.unwrap();
+                assert_eq!(
+                    res.refresh_token.unwrap().expose_secret(),
+                    format!("{}-{}", MOCK_REFRESH_TOKEN, i)
+                );
+                mocked_time::set_now(res.access_token.expires_at().unwrap());
+            }
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn authorization_code_is_not_replayed() {
+        mocked_time::scope(Utc::now(), async move {
+            let mock_server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/connect/token"))
+                .respond_with(mock_response(false))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+
+            let authenticator = Authenticator::new(
+                reqwest::Client::new().into(),
+                Url::parse(&mock_server.uri()).unwrap(),
+                Credentials::AuthorizationCode {
+                    client_id: MOCK_CLIENT_ID.into(),
+                    client_secret: MOCK_CLIENT_SECRET.into(),
+                    code: "mock-code".into(),
+                    redirect_uri: "https://my.redirect.uri".into(),
+                },
+            );
+
+            let res = authenticator.get_access_token().await.unwrap();
+            mocked_time::set_now(res.access_token.expires_at().unwrap());
+
+            assert!(authenticator.get_access_token().await.is_err());
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn access_token_is_refreshed_with_configured_margin() {
+        mocked_time::scope(Utc::now(), async move {
+            let mock_server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/connect/token"))
+                .respond_with(mock_response(false))
+                .expect(2)
+                .mount(&mock_server)
+                .await;
+
+            let authenticator = Authenticator::with_options(
+                reqwest::Client::new().into(),
+                Url::parse(&mock_server.uri()).unwrap(),
+                Credentials::ClientCredentials {
+                    client_id: MOCK_CLIENT_ID.into(),
+                    client_secret: MOCK_CLIENT_SECRET.into(),
+                    scope: "mock".into(),
+                },
+                AuthenticatorOptions {
+                    refresh_margin: std::time::Duration::from_secs(60),
+                    ..Default::default()
+                },
+            );
+
+            let res1 = authenticator.get_access_token().await.unwrap();
+
+            // Still valid for longer than the margin
+            mocked_time::set_now(res1.access_token.expires_at().unwrap() - Duration::seconds(61));
+            let res2 = authenticator.get_access_token().await.unwrap();
+            assert_eq!(
+                res1.access_token.expose_secret(),
+                res2.access_token.expose_secret()
+            );
+
+            // Within the margin
+            mocked_time::set_now(res1.access_token.expires_at().unwrap() - Duration::seconds(60));
+            let res3 = authenticator.get_access_token().await.unwrap();
+            assert_ne!(
+                res1.access_token.expose_secret(),
+                res3.access_token.expose_secret()
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn access_tokens_are_cached_per_scope() {
+        mocked_time::scope(Utc::now(), async move {
+            let mock_server = MockServer::start().await;
+            for scope in ["mock", "payments"] {
+                Mock::given(method("POST"))
+                    .and(path("/connect/token"))
+                    .and(body_partial_json(json!({
+                        "grant_type": "client_credentials",
+                        "scope": scope
+                    })))
+                    .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                        "token_type": "Bearer",
+                        "access_token": format!("{}-{}", MOCK_ACCESS_TOKEN, scope),
+                        "expires_in": 3600
+                    })))
+                    .expect(1)
+                    .mount(&mock_server)
+                    .await;
+            }
+
+            let authenticator = mock_authenticator(&mock_server.uri());
+
+            // Concurrent callers share the same request for each scope
+            let (default1, default2, scoped1, scoped2) = tokio::join!(
+                authenticator.get_access_token(),
+                authenticator.get_access_token(),
+                authenticator.get_access_token_for_scope("payments"),
+                authenticator.get_access_token_for_scope("payments"),
+            );
+
+            for (res, scope) in [
+                (default1, "mock"),
+                (default2, "mock"),
+                (scoped1, "payments"),
+                (scoped2, "payments"),
+            ] {
+                assert_eq!(
+                    res.unwrap().access_token.expose_secret(),
+                    format!("{}-{}", MOCK_ACCESS_TOKEN, scope)
+                );
+            }
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn client_credentials_tokens_are_shared_through_the_token_store() {
+        mocked_time::scope(Utc::now(), async move {
+            let mock_server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/connect/token"))
+                .respond_with(mock_response(false))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+
+            let token_store: Arc<dyn TokenStore> = Arc::new(InMemoryTokenStore::new());
+            let start = || {
+                Authenticator::with_options(
+                    reqwest::Client::new().into(),
+                    Url::parse(&mock_server.uri()).unwrap(),
+                    Credentials::ClientCredentials {
+                        client_id: MOCK_CLIENT_ID.into(),
+                        client_secret: MOCK_CLIENT_SECRET.into(),
+                        scope: "mock".into(),
+                    },
+                    AuthenticatorOptions {
+                        token_store: token_store.clone(),
+                        ..Default::default()
+                    },
+                )
+            };
+
+            // A second process reuses the token obtained by the first one
+            let res = start().get_access_token().await.unwrap();
+            let res2 = start().get_access_token().await.unwrap();
+            assert_eq!(
+                res2.access_token.expose_secret(),
+                res.access_token.expose_secret()
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn user_tokens_are_stored_per_grant() {
+        mocked_time::scope(Utc::now(), async move {
+            let mock_server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/connect/token"))
+                .and(body_partial_json(
+                    json!({ "grant_type": "authorization_code", "code": "code-a" }),
+                ))
+                .respond_with(mock_response(true))
+                .expect(1)
+                .named("Authorization code A mock")
+                .mount(&mock_server)
+                .await;
+            Mock::given(method("POST"))
+                .and(path("/connect/token"))
+                .and(body_partial_json(
+                    json!({ "grant_type": "authorization_code", "code": "code-b" }),
+                ))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "token_type": "Bearer",
+                    "access_token": "user-b-access-token",
+                    "expires_in": 3600,
+                    "refresh_token": "user-b-refresh-token"
+                })))
+                .expect(1)
+                .named("Authorization code B mock")
+                .mount(&mock_server)
+                .await;
+            Mock::given(method("POST"))
+                .and(path("/connect/token"))
+                .and(body_partial_json(json!({
+                    "grant_type": "refresh_token",
+                    "refresh_token": MOCK_REFRESH_TOKEN
+                })))
+                .respond_with(mock_response(true))
+                .expect(1)
+                .named("Refresh token mock")
+                .mount(&mock_server)
+                .await;
+
+            let token_store: Arc<dyn TokenStore> = Arc::new(InMemoryTokenStore::new());
+            let start = |code: &str| {
+                Authenticator::with_options(
+                    reqwest::Client::new().into(),
+                    Url::parse(&mock_server.uri()).unwrap(),
+                    Credentials::AuthorizationCode {
+                        client_id: MOCK_CLIENT_ID.into(),
+                        client_secret: MOCK_CLIENT_SECRET.into(),
+                        code: code.into(),
+                        redirect_uri: "https://mock.redirect".into(),
+                    },
+                    AuthenticatorOptions {
+                        token_store: token_store.clone(),
+                        ..Default::default()
+                    },
+                )
+            };
+
+            // Users of the same application never get each other's tokens
+            let res_a = start("code-a").get_access_token().await.unwrap();
+            let res_b = start("code-b").get_access_token().await.unwrap();
+            assert_eq!(res_b.access_token.expose_secret(), "user-b-access-token");
+
+            // A restarted process refreshes the token of its own user with the stored refresh token,
+            // instead of replaying the authorization code or reusing the access token of another process
+            let res_a2 = start("code-a").get_access_token().await.unwrap();
+            assert_ne!(
+                res_a2.access_token.expose_secret(),
+                res_a.access_token.expose_secret()
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn rotated_refresh_tokens_are_never_overwritten() {
+        let token_store = InMemoryTokenStore::new();
+        let key = TokenStoreKey {
+            client_id: MOCK_CLIENT_ID.to_string(),
+            audience: None,
+            scope: None,
+            grant: Some("grant".to_string()),
+        };
+        let stored = |refresh_token: &str| StoredToken {
+            access_token: AccessToken {
+                token: "access-token".into(),
+                expires_at: None,
+            },
+            refresh_token: Some(refresh_token.into()),
+        };
+
+        assert!(token_store
+            .compare_and_swap(&key, None, stored("refresh-1"))
+            .await
+            .unwrap());
+        assert!(token_store
+            .compare_and_swap(&key, Some(&"refresh-1".into()), stored("refresh-2"))
+            .await
+            .unwrap());
+
+        // A client which loaded the first refresh token cannot replace the rotated one
+        assert!(!token_store
+            .compare_and_swap(&key, Some(&"refresh-1".into()), stored("refresh-3"))
+            .await
+            .unwrap());
+        let current = token_store.load(&key).await.unwrap().unwrap();
+        assert_eq!(current.refresh_token.unwrap().expose_secret(), "refresh-2");
+    }
+
+    #[tokio::test]
+    async fn invalidated_access_token_is_replaced() {
+        mocked_time::scope(Utc::now(), async move {
+            let mock_server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/connect/token"))
+                .respond_with(mock_response(false))
+                .expect(2)
+                .mount(&mock_server)
+                .await;
+
+            let authenticator = mock_authenticator(&mock_server.uri());
+
+            let auth_result1 = authenticator.get_access_token().await.unwrap();
+            authenticator.invalidate_access_token(&auth_result1.access_token);
+            let auth_result2 = authenticator.get_access_token().await.unwrap();
+            assert_eq!(
+                auth_result2.access_token.expose_secret(),
+                format!("{}-1", MOCK_ACCESS_TOKEN)
+            );
+
+            // Invalidating a token which was already replaced does nothing
+            authenticator.invalidate_access_token(&auth_result1.access_token);
+            let auth_result3 = authenticator.get_access_token().await.unwrap();
+            assert_eq!(
+                auth_result2.access_token.expose_secret(),
+                auth_result3.access_token.expose_secret()
+            );
+        })
+        .await;
+    }
+
     #[tokio::test]
     async fn concurrent_requests_are_batched() {
         // Setup mock server
@@ -431,4 +1212,181 @@ mod tests {
             );
         }
     }
+
+    #[tokio::test]
+    async fn audience_is_sent_as_resource_indicator() {
+        mocked_time::scope(Utc::now(), async move {
+            // Setup mock server
+            let mock_server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/connect/token"))
+                .and(body_partial_json(json!({
+                    "grant_type": "client_credentials",
+                    "client_id": MOCK_CLIENT_ID,
+                    "resource": "https://api.truelayer.com/payouts"
+                })))
+                .respond_with(mock_response(false))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+
+            // Setup authenticator
+            let authenticator = Authenticator::with_audience(
+                reqwest::Client::new().into(),
+                Url::parse(&mock_server.uri()).unwrap(),
+                Credentials::ClientCredentials {
+                    client_id: MOCK_CLIENT_ID.into(),
+                    client_secret: MOCK_CLIENT_SECRET.into(),
+                    scope: "payouts".into(),
+                },
+                Some("https://api.truelayer.com/payouts".into()),
+            );
+
+            let res = authenticator.get_access_token().await.unwrap();
+            assert_eq!(
+                res.access_token.expose_secret(),
+                format!("{}-0", MOCK_ACCESS_TOKEN)
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn huge_stale_token_leeways_do_not_overflow() {
+        mocked_time::scope(Utc::now(), async {
+            let token = AccessToken {
+                token: "access-token".into(),
+                expires_at: Some(mocked_time::now() + chrono::Duration::minutes(5)),
+            };
+
+            assert!(is_usable_while_stale(
+                &token,
+                std::time::Duration::from_secs(60)
+            ));
+            assert!(!is_usable_while_stale(&token, std::time::Duration::MAX));
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn stale_token_is_served_only_if_configured() {
+        for policy in [
+            TokenRefreshFailurePolicy::FailFast,
+            TokenRefreshFailurePolicy::ServeStale {
+                leeway: std::time::Duration::from_secs(60),
+            },
+        ] {
+            mocked_time::scope(Utc::now(), async move {
+                // Setup mock server: the auth server goes down after the first token is issued
+                let mock_server = MockServer::start().await;
+                Mock::given(method("POST"))
+                    .and(path("/connect/token"))
+                    .respond_with(mock_response(false))
+                    .up_to_n_times(1)
+                    .mount(&mock_server)
+                    .await;
+                Mock::given(method("POST"))
+                    .and(path("/connect/token"))
+                    .respond_with(ResponseTemplate::new(503))
+                    .mount(&mock_server)
+                    .await;
+
+                let authenticator = Authenticator::with_options(
+                    reqwest::Client::new().into(),
+                    Url::parse(&mock_server.uri()).unwrap(),
+                    Credentials::ClientCredentials {
+                        client_id: MOCK_CLIENT_ID.into(),
+                        client_secret: MOCK_CLIENT_SECRET.into(),
+                        scope: "mock".into(),
+                    },
+                    AuthenticatorOptions {
+                        refresh_failure_policy: policy,
+                        ..Default::default()
+                    },
+                );
+
+                let res = authenticator.get_access_token().await.unwrap();
+                let expires_at = res.access_token.expires_at().unwrap();
+
+                // Fast forward time until the token should be refreshed
+                mocked_time::set_now(expires_at - Duration::minutes(5));
+
+                let stale = authenticator.get_access_token().await;
+                match policy {
+                    TokenRefreshFailurePolicy::FailFast => assert!(stale.is_err()),
+                    TokenRefreshFailurePolicy::ServeStale { .. } => {
+                        assert_eq!(
+                            stale.unwrap().access_token.expose_secret(),
+                            res.access_token.expose_secret()
+                        );
+                        assert_eq!(authenticator.stale_tokens_served.load(Ordering::Relaxed), 1);
+                    }
+                }
+
+                // Within the leeway, the stale token is never served
+                mocked_time::set_now(expires_at - Duration::seconds(30));
+                assert!(authenticator.get_access_token().await.is_err());
+            })
+            .await;
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn startup_requests_are_coalesced_and_limited() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/connect/token"))
+            .respond_with(mock_response(false))
+            .expect(2) // One for each authenticator
+            .mount(&mock_server)
+            .await;
+
+        // Two authenticators sharing a limit of one concurrent request
+        let limit = AuthConcurrencyLimit::new(1);
+        let authenticators = [(); 2].map(|_| {
+            Authenticator::with_options(
+                reqwest::Client::new().into(),
+                Url::parse(&mock_server.uri()).unwrap(),
+                Credentials::ClientCredentials {
+                    client_id: MOCK_CLIENT_ID.into(),
+                    client_secret: MOCK_CLIENT_SECRET.into(),
+                    scope: "mock".into(),
+                },
+                AuthenticatorOptions {
+                    startup_jitter: std::time::Duration::from_millis(50),
+                    concurrency_limit: Some(limit.clone()),
+                    ..Default::default()
+                },
+            )
+        });
+
+        // Take the only slot of the limit, as if another client was contacting the Auth server
+        let permit = limit.0.clone().acquire_owned().await.unwrap();
+
+        // Many parallel callers on each authenticator
+        let handles = authenticators
+            .iter()
+            .flat_map(|authenticator| std::iter::repeat(authenticator).take(50))
+            .map(|authenticator| {
+                let authenticator = authenticator.clone();
+                mocked_time::spawn(async move { authenticator.get_access_token().await.unwrap() })
+            })
+            .collect::<Vec<_>>();
+
+        // Well past the startup jitter, no request is sent until the slot is released
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        assert!(mock_server.received_requests().await.unwrap().is_empty());
+
+        drop(permit);
+        futures::future::join_all(handles)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+    }
+
+    #[test]
+    fn zero_concurrency_limits_allow_one_request() {
+        assert_eq!(AuthConcurrencyLimit::new(0).0.available_permits(), 1);
+    }
 }
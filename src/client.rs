@@ -1,34 +1,77 @@
 //! Module containing the main TrueLayer API client.
 //! This is where the main [`TrueLayerClient`](crate::client::TrueLayerClient) is.
 
+#[cfg(feature = "data")]
+use crate::apis::data::DataApi;
+#[cfg(feature = "schema-validation")]
+use crate::middlewares::schema_validation::SchemaValidationMiddleware;
 use crate::{
     apis::{
-        auth::{AuthApi, Credentials},
+        auth::{
+            AuthApi, AuthConcurrencyLimit, Credentials, InMemoryTokenStore,
+            TokenRefreshFailurePolicy, TokenStore,
+        },
+        mandates::MandatesApi,
         merchant_accounts::MerchantAccountsApi,
-        payments::PaymentsApi,
+        payment_links::PaymentLinksApi,
+        payments::{
+            flow::{FlowCache, FlowEvent, FlowEventListeners, DEFAULT_FORM_SCHEMA_TTL},
+            PaymentsApi,
+        },
         payments_providers::PaymentsProvidersApi,
         payouts::PayoutsApi,
+        verification::VerificationApi,
+        webhooks::Jwks,
         TrueLayerClientInner,
     },
-    authenticator::Authenticator,
+    audit::{ResponseInterceptor, ResponseSummary},
+    authenticator::{Authenticator, AuthenticatorOptions, DEFAULT_REFRESH_MARGIN},
     common::{
         DEFAULT_AUTH_URL, DEFAULT_HOSTED_PAYMENTS_PAGE_URL, DEFAULT_PAYMENTS_URL,
         DEFAULT_SANDBOX_AUTH_URL, DEFAULT_SANDBOX_HOSTED_PAYMENTS_PAGE_URL,
-        DEFAULT_SANDBOX_PAYMENTS_URL,
+        DEFAULT_SANDBOX_PAYMENTS_URL, DEFAULT_SANDBOX_WEBHOOKS_URL, DEFAULT_WEBHOOKS_URL,
     },
+    deprecations::{DeprecationNotice, DeprecationRegistry},
+    guardrails::Guardrails,
+    idempotency::IdempotencyLedger,
     middlewares::{
         authentication::AuthenticationMiddleware,
+        body_limits::BodyLimitsMiddleware,
+        deprecation::DeprecationMiddleware,
         error_handling::ErrorHandlingMiddleware,
+        failover::FailoverMiddleware,
         inject_user_agent::InjectUserAgentMiddleware,
+        live_safeguard::LiveSafeguardMiddleware,
+        observer::ObserverMiddleware,
+        rate_limiter::RateLimiterMiddleware,
+        rate_limits::RateLimitHeadersMiddleware,
+        response_interceptor::ResponseInterceptorMiddleware,
+        retry_after::RetryAfterMiddleware,
         retry_idempotent::{DynRetryPolicy, RetryIdempotentMiddleware},
+        scopes::ScopesMiddleware,
         signing::SigningMiddleware,
+        timeout::DefaultTimeoutMiddleware,
     },
+    observer::RequestObserver,
+    pollable::{PollBudget, PollBudgetMetrics, PollOptions},
+    rate_limits::{RateLimitPolicy, RateLimitRegistry, RateLimitStatus},
+    safeguard::Confirmation,
+    support::RequestTraceLog,
+    transport::TransportConfig,
+    Error,
 };
 use reqwest::Url;
-use reqwest_middleware::ClientWithMiddleware;
+use reqwest_middleware::{ClientWithMiddleware, Middleware};
 use reqwest_retry::{policies::ExponentialBackoff, RetryPolicy};
 use reqwest_tracing::TracingMiddleware;
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 /// Client for TrueLayer public APIs.
 ///
@@ -86,6 +129,16 @@ pub struct TrueLayerClient {
     pub payouts: PayoutsApi,
     /// Merchant Accounts APIs client.
     pub merchant_accounts: MerchantAccountsApi,
+    /// Mandates APIs client.
+    pub mandates: MandatesApi,
+    /// Payment Links APIs client.
+    pub payment_links: PaymentLinksApi,
+    /// Verification APIs client.
+    pub verification: VerificationApi,
+    /// Data APIs client.
+    #[cfg(feature = "data")]
+    pub data: DataApi,
+    pub(crate) inner: Arc<TrueLayerClientInner>,
 }
 
 impl TrueLayerClient {
@@ -98,87 +151,464 @@ impl TrueLayerClient {
     pub fn builder(credentials: Credentials) -> TrueLayerClientBuilder {
         TrueLayerClientBuilder::new(credentials)
     }
+
+    /// Builds all the API clients on top of the same shared inner state.
+    pub(crate) fn from_inner(inner: Arc<TrueLayerClientInner>) -> TrueLayerClient {
+        TrueLayerClient {
+            auth: AuthApi::new(inner.clone()),
+            payments: PaymentsApi::new(inner.clone()),
+            payments_providers: PaymentsProvidersApi::new(inner.clone()),
+            payouts: PayoutsApi::new(inner.clone()),
+            merchant_accounts: MerchantAccountsApi::new(inner.clone()),
+            mandates: MandatesApi::new(inner.clone()),
+            payment_links: PaymentLinksApi::new(inner.clone()),
+            verification: VerificationApi::new(inner.clone()),
+            #[cfg(feature = "data")]
+            data: DataApi::new(inner.clone()),
+            inner,
+        }
+    }
+
+    /// Returns the deprecation notices received from TrueLayer so far, one for each endpoint.
+    ///
+    /// Notices are collected from the `Deprecation` and `Sunset` headers returned by TrueLayer
+    /// and are also logged as warnings the first time they are received.
+    /// See [`deprecations`](crate::deprecations) for more details.
+    pub fn deprecations(&self) -> Vec<DeprecationNotice> {
+        self.inner.deprecations.snapshot()
+    }
+
+    /// Returns the latest rate limit state reported by each TrueLayer host.
+    ///
+    /// States are collected from the `RateLimit-*` headers returned by TrueLayer, when present.
+    /// See [`rate_limits`](crate::rate_limits) for more details.
+    pub fn rate_limit_snapshot(&self) -> Vec<RateLimitStatus> {
+        self.inner.rate_limits.snapshot()
+    }
+
+    /// Returns the default options to poll resources, configured with
+    /// [`with_poll_options`](crate::client::TrueLayerClientBuilder::with_poll_options).
+    ///
+    /// ```no_run
+    /// # use truelayer_rust::{PollableUntilTerminalState, TrueLayerClient};
+    /// # async fn run(tl: TrueLayerClient, payment: truelayer_rust::apis::payments::Payment) {
+    /// let payment = payment
+    ///     .poll_until_terminal_state(&tl, tl.poll_options())
+    ///     .await;
+    /// # }
+    /// ```
+    pub fn poll_options(&self) -> PollOptions<DynRetryPolicy> {
+        self.inner.poll_options.clone()
+    }
+
+    /// Returns the consumption of the client-wide poll budget configured with
+    /// [`with_poll_budget`](crate::client::TrueLayerClientBuilder::with_poll_budget), if any.
+    pub fn poll_budget_metrics(&self) -> Option<PollBudgetMetrics> {
+        self.inner.poll_budget.as_ref().map(PollBudget::metrics)
+    }
+
+    /// Returns how many times a stale access token has been used because it could not be refreshed.
+    ///
+    /// Always zero unless [`TokenRefreshFailurePolicy::ServeStale`](crate::apis::auth::TokenRefreshFailurePolicy::ServeStale)
+    /// is configured. Each stale token served is also logged as a warning.
+    pub fn stale_tokens_served(&self) -> u64 {
+        self.inner
+            .authenticator
+            .stale_tokens_served
+            .load(Ordering::Relaxed)
+    }
 }
 
 /// Builder for a [`TrueLayerClient`](crate::client::TrueLayerClient).
 #[derive(Debug)]
 pub struct TrueLayerClientBuilder {
-    client: reqwest::Client,
+    client: Option<reqwest::Client>,
+    transport: TransportConfig,
+    timeout: Option<Duration>,
     retry_policy: Option<DynRetryPolicy>,
     environment: Environment,
     credentials: Credentials,
     signing_key: Option<(String, Vec<u8>)>,
+    canonical_json_bodies: bool,
+    failover_threshold: u32,
+    failover_probe_interval: Duration,
+    token_audiences: HashMap<ApiGroup, String>,
+    token_refresh_failure_policy: TokenRefreshFailurePolicy,
+    auth_startup_jitter: Duration,
+    auth_concurrency_limit: Option<AuthConcurrencyLimit>,
+    token_refresh_margin: Duration,
+    token_store: Arc<dyn TokenStore>,
+    scopes: Option<Vec<String>>,
+    rate_limit_policy: Option<RateLimitPolicy>,
+    rate_limit: Option<u32>,
+    payout_guardrails: Option<Arc<Guardrails>>,
+    live_safeguard: Option<Confirmation>,
+    implicit_behaviors: bool,
+    response_interceptors: Vec<ResponseInterceptor>,
+    request_observers: Vec<Arc<dyn RequestObserver>>,
+    custom_middlewares: CustomMiddlewares,
+    flow_events: FlowEventListeners,
+    poll_options: PollOptions<DynRetryPolicy>,
+    poll_budget: Option<PollBudget>,
+    form_schema_ttl: Duration,
+    #[cfg(feature = "schema-validation")]
+    schema_validation: bool,
 }
 
 impl TrueLayerClientBuilder {
     /// Creates a new builder to configure a [`TrueLayerClient`](crate::client::TrueLayerClient).
     pub fn new(credentials: Credentials) -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client: None,
+            transport: TransportConfig::default(),
+            timeout: None,
             retry_policy: Some(DynRetryPolicy(Arc::new(
                 ExponentialBackoff::builder().build_with_max_retries(3),
             ))),
             environment: Environment::Live,
             credentials,
             signing_key: None,
+            canonical_json_bodies: false,
+            failover_threshold: 3,
+            failover_probe_interval: Duration::from_secs(60),
+            token_audiences: HashMap::new(),
+            token_refresh_failure_policy: TokenRefreshFailurePolicy::default(),
+            auth_startup_jitter: Duration::ZERO,
+            auth_concurrency_limit: None,
+            token_refresh_margin: DEFAULT_REFRESH_MARGIN,
+            token_store: Arc::new(InMemoryTokenStore::new()),
+            scopes: None,
+            rate_limit_policy: None,
+            rate_limit: None,
+            payout_guardrails: None,
+            live_safeguard: None,
+            implicit_behaviors: true,
+            response_interceptors: Vec::new(),
+            request_observers: Vec::new(),
+            custom_middlewares: CustomMiddlewares::default(),
+            flow_events: FlowEventListeners::default(),
+            poll_options: PollOptions::default().into_dyn(),
+            poll_budget: None,
+            form_schema_ttl: DEFAULT_FORM_SCHEMA_TTL,
+            #[cfg(feature = "schema-validation")]
+            schema_validation: false,
         }
     }
 
+    /// Creates a new builder preconfigured with the recommended settings for the Sandbox environment.
+    ///
+    /// Compared to [`new`](crate::client::TrueLayerClientBuilder::new), this preset:
+    /// - connects to [`Environment::Sandbox`](crate::client::Environment::Sandbox),
+    /// - sets a 10 seconds connect timeout and a 60 seconds request timeout,
+    ///   to accommodate the slower Sandbox mock banks,
+    /// - retries transient failures up to 5 times with short backoffs, which makes test suites
+    ///   more resilient to Sandbox hiccups,
+    /// - retries rate limited idempotent requests up to 5 times, after the delay requested by TrueLayer.
+    ///
+    /// The presets set neither a [client-side rate limit](TrueLayerClientBuilder::with_rate_limit),
+    /// since the right one depends on the limits agreed with TrueLayer for each client id, nor a
+    /// logging level, since this crate only emits [`tracing`] events and their level is chosen by
+    /// the subscriber installed by the application.
+    ///
+    /// Every setting can still be overridden by the other builder methods.
+    pub fn sandbox_defaults(credentials: Credentials) -> Self {
+        Self::new(credentials)
+            .with_environment(Environment::Sandbox)
+            .with_transport(|transport| {
+                transport
+                    .with_connect_timeout(Duration::from_secs(10))
+                    .with_timeout(Duration::from_secs(60))
+            })
+            .with_retry_policy(Arc::new(
+                ExponentialBackoff::builder()
+                    .retry_bounds(Duration::from_millis(100), Duration::from_secs(5))
+                    .build_with_max_retries(5),
+            ) as Arc<dyn RetryPolicy + Send + Sync>)
+            .with_rate_limit_policy(RateLimitPolicy::new(5))
+    }
+
+    /// Creates a new builder preconfigured with the recommended settings for the Live environment.
+    ///
+    /// Compared to [`new`](crate::client::TrueLayerClientBuilder::new), this preset:
+    /// - connects to [`Environment::Live`](crate::client::Environment::Live),
+    /// - sets a 5 seconds connect timeout and a 30 seconds request timeout,
+    ///   so that a stuck connection never blocks the caller indefinitely,
+    /// - retries transient failures up to 3 times, with backoffs capped at 30 seconds
+    ///   to avoid hammering TrueLayer during an incident,
+    /// - retries rate limited idempotent requests up to 3 times, giving up straight away
+    ///   if TrueLayer asks to wait longer than 30 seconds.
+    ///
+    /// Like [`sandbox_defaults`](TrueLayerClientBuilder::sandbox_defaults), it sets neither a
+    /// client-side rate limit nor a logging level.
+    ///
+    /// Every setting can still be overridden by the other builder methods.
+    pub fn production_defaults(credentials: Credentials) -> Self {
+        Self::new(credentials)
+            .with_environment(Environment::Live)
+            .with_transport(|transport| {
+                transport
+                    .with_connect_timeout(Duration::from_secs(5))
+                    .with_timeout(Duration::from_secs(30))
+            })
+            .with_retry_policy(Arc::new(
+                ExponentialBackoff::builder()
+                    .retry_bounds(Duration::from_millis(500), Duration::from_secs(30))
+                    .build_with_max_retries(3),
+            ) as Arc<dyn RetryPolicy + Send + Sync>)
+            .with_rate_limit_policy(RateLimitPolicy::new(3).with_max_wait(Duration::from_secs(30)))
+    }
+
     /// Consumes the builder and builds a new [`TrueLayerClient`](crate::client::TrueLayerClient).
-    pub fn build(self) -> TrueLayerClient {
-        // Build an authenticator
-        let authenticator = Authenticator::new(
-            build_client_with_middleware(
-                self.client.clone(),
-                self.retry_policy.clone(),
-                None,
-                None,
-            ),
-            self.environment.auth_url(),
-            self.credentials,
-        );
+    pub fn build(mut self) -> TrueLayerClient {
+        // Strip everything which might send requests the caller did not explicitly ask for
+        if !self.implicit_behaviors {
+            self.retry_policy = None;
+            self.rate_limit_policy = None;
+            self.token_refresh_failure_policy = TokenRefreshFailurePolicy::FailFast;
+            self.auth_startup_jitter = Duration::ZERO;
+            self.form_schema_ttl = Duration::ZERO;
+        }
+
+        let client = self
+            .client
+            .unwrap_or_else(|| self.transport.build_http_client());
+
+        // Fail over to the fallback environments, if any
+        let failover_middleware = self
+            .environment
+            .failover_base_urls()
+            .filter(|_| self.implicit_behaviors)
+            .map(|environments| {
+                FailoverMiddleware::new(
+                    environments,
+                    self.failover_threshold,
+                    self.failover_probe_interval,
+                    self.environment
+                        .webhooks_url()
+                        .join("/.well-known/jwks")
+                        .unwrap(),
+                    client.clone(),
+                    self.request_observers.clone(),
+                )
+            });
+
+        let body_limits_middleware = self.transport.body_limits_middleware();
+        let timeout_middleware = self.timeout.map(DefaultTimeoutMiddleware::new);
+
+        // Collect deprecation notices and rate limits from all the clients
+        let deprecations = DeprecationRegistry::default();
+        let rate_limits = RateLimitRegistry::default();
 
         // Prepare the middlewares
-        let auth_middleware = Some(AuthenticationMiddleware {
-            authenticator: authenticator.clone(),
-        });
         let signing_middleware = self
             .signing_key
             .map(|(key_id, private_key)| SigningMiddleware {
                 key_id,
                 private_key,
+                canonicalize_json: self.canonical_json_bodies,
             });
 
-        // Build the actual TL client
-        let inner = Arc::new(TrueLayerClientInner {
-            client: build_client_with_middleware(
-                self.client,
-                self.retry_policy.clone(),
-                auth_middleware,
-                signing_middleware,
-            ),
-            environment: self.environment,
-            authenticator,
-        });
+        // Validate the requests sent to the APIs, if enabled
+        let mut request_middlewares: Vec<Arc<dyn Middleware>> = Vec::new();
+        if let Some(scopes) = &self.scopes {
+            request_middlewares.push(Arc::new(ScopesMiddleware::new(scopes.clone())));
+        }
+        #[cfg(feature = "schema-validation")]
+        if self.schema_validation {
+            request_middlewares.push(Arc::new(SchemaValidationMiddleware));
+        }
+        if let Some(confirmation) = &self.live_safeguard {
+            if self.environment.is_live() {
+                request_middlewares
+                    .push(Arc::new(LiveSafeguardMiddleware::new(confirmation.clone())));
+            }
+        }
 
-        TrueLayerClient {
-            auth: AuthApi::new(inner.clone()),
-            payments: PaymentsApi::new(inner.clone()),
-            payments_providers: PaymentsProvidersApi::new(inner.clone()),
-            payouts: PayoutsApi::new(inner.clone()),
-            merchant_accounts: MerchantAccountsApi::new(inner),
+        let retry_after_middleware = self.rate_limit_policy.map(RetryAfterMiddleware::new);
+
+        // All the APIs share the same bucket
+        let rate_limiter_middleware = self.rate_limit.map(RateLimiterMiddleware::new);
+
+        // Count the stale tokens served by all the authenticators
+        let stale_tokens_served = Arc::new(AtomicU64::new(0));
+
+        // Form schemas and created resources do not depend on the audience of the tokens
+        let flow_cache = Arc::new(FlowCache::new(self.form_schema_ttl));
+        let idempotency_ledger = Arc::new(IdempotencyLedger::default());
+
+        // Remember the most recent calls of all the clients for support bundles
+        let request_traces = RequestTraceLog::default();
+        self.response_interceptors
+            .push(request_traces.interceptor());
+
+        // Builds the shared state of a group of APIs, with its own authenticator
+        let build_inner = |audience: Option<String>| {
+            // Build an authenticator
+            let authenticator = Authenticator::with_options(
+                build_client_with_middleware(
+                    client.clone(),
+                    self.retry_policy.clone(),
+                    deprecations.clone(),
+                    rate_limits.clone(),
+                    self.response_interceptors.clone(),
+                    self.request_observers.clone(),
+                    self.custom_middlewares.clone(),
+                    body_limits_middleware.clone(),
+                    timeout_middleware.clone(),
+                    None,
+                    None,
+                    failover_middleware.clone(),
+                    Vec::new(),
+                    None,
+                    None,
+                ),
+                self.environment.auth_url(),
+                self.credentials.clone(),
+                AuthenticatorOptions {
+                    audience,
+                    refresh_failure_policy: self.token_refresh_failure_policy,
+                    stale_tokens_served: stale_tokens_served.clone(),
+                    startup_jitter: self.auth_startup_jitter,
+                    concurrency_limit: self.auth_concurrency_limit.clone(),
+                    refresh_margin: self.token_refresh_margin,
+                    token_store: self.token_store.clone(),
+                },
+            );
+
+            let auth_middleware = Some(AuthenticationMiddleware {
+                authenticator: authenticator.clone(),
+                retry_unauthorized: self.implicit_behaviors,
+            });
+
+            // Build the actual TL client
+            Arc::new(TrueLayerClientInner {
+                client: build_client_with_middleware(
+                    client.clone(),
+                    self.retry_policy.clone(),
+                    deprecations.clone(),
+                    rate_limits.clone(),
+                    self.response_interceptors.clone(),
+                    self.request_observers.clone(),
+                    self.custom_middlewares.clone(),
+                    body_limits_middleware.clone(),
+                    timeout_middleware.clone(),
+                    auth_middleware,
+                    signing_middleware.clone(),
+                    failover_middleware.clone(),
+                    request_middlewares.clone(),
+                    retry_after_middleware.clone(),
+                    rate_limiter_middleware.clone(),
+                ),
+                environment: self.environment.clone(),
+                authenticator,
+                deprecations: deprecations.clone(),
+                rate_limits: rate_limits.clone(),
+                poll_options: self.poll_options.clone(),
+                poll_budget: self.poll_budget.clone(),
+                flow_cache: flow_cache.clone(),
+                flow_events: self.flow_events.clone(),
+                idempotency_ledger: idempotency_ledger.clone(),
+                payout_guardrails: self.payout_guardrails.clone(),
+                request_traces: request_traces.clone(),
+                implicit_behaviors: self.implicit_behaviors,
+            })
+        };
+
+        let mut tl = TrueLayerClient::from_inner(build_inner(None));
+
+        // API groups with a specific audience get their own tokens
+        for (api_group, audience) in &self.token_audiences {
+            let inner = build_inner(Some(audience.clone()));
+            match api_group {
+                ApiGroup::Payments => tl.payments = PaymentsApi::new(inner),
+                ApiGroup::PaymentsProviders => {
+                    tl.payments_providers = PaymentsProvidersApi::new(inner)
+                }
+                ApiGroup::Payouts => tl.payouts = PayoutsApi::new(inner),
+                ApiGroup::MerchantAccounts => {
+                    tl.merchant_accounts = MerchantAccountsApi::new(inner)
+                }
+                ApiGroup::Mandates => tl.mandates = MandatesApi::new(inner),
+                ApiGroup::PaymentLinks => tl.payment_links = PaymentLinksApi::new(inner),
+                ApiGroup::Verification => tl.verification = VerificationApi::new(inner),
+                #[cfg(feature = "data")]
+                ApiGroup::Data => tl.data = DataApi::new(inner),
+            }
         }
+
+        tl
     }
 
-    /// Sets a specific reqwest [`Client`](reqwest::Client) to use.
+    /// Sets a specific reqwest [`Client`](reqwest::Client) to use, e.g. to control TLS settings,
+    /// proxies, connection pools and timeouts.
+    ///
+    /// The client is wrapped in the same middleware stack as the default one, so authentication,
+    /// signing, retries and all the other features of the builder keep working. The client is also
+    /// used to request access tokens.
+    ///
+    /// The connection settings configured with [`with_transport`](crate::client::TrueLayerClientBuilder::with_transport)
+    /// are ignored when a custom client is provided, but its response body limits still apply.
+    ///
+    /// ```rust,no_run
+    /// # use truelayer_rust::{TrueLayerClient, apis::auth::Credentials};
+    /// # use std::time::Duration;
+    /// # let credentials: Credentials = unreachable!();
+    /// let http_client = reqwest::Client::builder()
+    ///     .proxy(reqwest::Proxy::https("http://egress-proxy.internal:3128").unwrap())
+    ///     .pool_max_idle_per_host(4)
+    ///     .timeout(Duration::from_secs(30))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let tl = TrueLayerClient::builder(credentials)
+    ///     .with_http_client(http_client)
+    ///     .build();
+    /// ```
     pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
-        self.client = client;
+        self.client = Some(client);
+        self
+    }
+
+    /// Sets a default timeout for each request sent to TrueLayer, from when it starts connecting
+    /// until the response body is read.
+    ///
+    /// Unlike the timeout of [`TransportConfig::with_timeout`](crate::transport::TransportConfig::with_timeout),
+    /// it also applies to clients configured with [`with_http_client`](crate::client::TrueLayerClientBuilder::with_http_client),
+    /// and can be overridden for a single call with [`ApiRequest::timeout`](crate::request::ApiRequest::timeout).
+    /// Retried requests get the full timeout again for each attempt.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Customizes the low-level settings of the HTTP transport, like static IP pinning
+    /// or a custom DNS resolver for TrueLayer hosts. See [`TransportConfig`](crate::transport::TransportConfig).
+    ///
+    /// ```rust,no_run
+    /// # use truelayer_rust::{TrueLayerClient, apis::auth::Credentials};
+    /// # let credentials: Credentials = unreachable!();
+    /// let tl = TrueLayerClient::builder(credentials)
+    ///     .with_transport(|transport| {
+    ///         transport.pin_host("api.truelayer.com", ["192.0.2.10:443".parse().unwrap()])
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn with_transport(
+        mut self,
+        configure: impl FnOnce(TransportConfig) -> TransportConfig,
+    ) -> Self {
+        self.transport = configure(self.transport);
         self
     }
 
-    /// Sets a specific [`RetryPolicy`](retry_policies::RetryPolicy) to use when retrying transient failures.
+    /// Sets a specific [`RetryPolicy`](crate::deps::RetryPolicy) to use when retrying transient failures.
+    ///
+    /// Only connection errors, timeouts, `408` and `5xx` responses are retried with this policy.
+    /// Rate limited requests are handled by [`with_rate_limit_policy`](TrueLayerClientBuilder::with_rate_limit_policy).
     ///
     /// To disable automatic retrying of failed requests, use `None`.
+    /// Policies should be built from the types re-exported in [`deps`](crate::deps)
+    /// to avoid version mismatches with the `retry-policies` crate.
     pub fn with_retry_policy(
         mut self,
         retry_policy: impl Into<Option<Arc<dyn RetryPolicy + Send + Sync + 'static>>>,
@@ -196,28 +626,625 @@ impl TrueLayerClientBuilder {
         self
     }
 
+    /// Enables canonicalization of signed JSON request bodies.
+    ///
+    /// When enabled, JSON bodies of signed requests are rewritten with sorted object keys and without
+    /// insignificant whitespace before being signed and sent. This keeps the transmitted bytes,
+    /// and therefore their signatures, stable even if the serialization order of the models changes.
+    ///
+    /// Has no effect unless a signing key is configured with
+    /// [`with_signing_key`](crate::client::TrueLayerClientBuilder::with_signing_key).
+    pub fn with_canonical_json_bodies(mut self, enabled: bool) -> Self {
+        self.canonical_json_bodies = enabled;
+        self
+    }
+
     /// Sets the environment to which this client should connect
     pub fn with_environment(mut self, environment: Environment) -> Self {
         self.environment = environment;
         self
     }
+
+    /// Configures when to switch to the fallback environments configured with
+    /// [`Environment::with_fallbacks`](crate::client::Environment::with_fallbacks).
+    ///
+    /// The client fails over after `failure_threshold` consecutive connection failures,
+    /// and probes the primary environment every `probe_interval` to fail back to it, by fetching
+    /// its [JWKS](crate::client::UnauthenticatedClient::jwks_url) in the background. Switches are traced and reported to
+    /// [`RequestObserver::on_failover`](crate::observer::RequestObserver::on_failover).
+    /// Defaults to 3 failures and 60 seconds.
+    pub fn with_failover_policy(
+        mut self,
+        failure_threshold: u32,
+        probe_interval: Duration,
+    ) -> Self {
+        self.failover_threshold = failure_threshold.max(1);
+        self.failover_probe_interval = probe_interval;
+        self
+    }
+
+    /// Requests access tokens restricted to the given audience for all the calls made
+    /// by the APIs client of `api_group`.
+    ///
+    /// The audience is sent to the Auth server as a `resource` indicator (see [RFC 8707]).
+    /// Each API group with a configured audience obtains and caches its own access token,
+    /// while the others share a token with no audience restriction. Since each token is obtained
+    /// independently with the same credentials, this is meant to be used with
+    /// [`Credentials::ClientCredentials`](crate::apis::auth::Credentials::ClientCredentials).
+    ///
+    /// [RFC 8707]: https://datatracker.ietf.org/doc/html/rfc8707
+    pub fn with_token_audience(mut self, api_group: ApiGroup, audience: impl Into<String>) -> Self {
+        self.token_audiences.insert(api_group, audience.into());
+        self
+    }
+
+    /// Configures what to do when an access token is due for refresh but the Auth server cannot issue a new one.
+    ///
+    /// By default, the refresh error is returned immediately
+    /// ([`TokenRefreshFailurePolicy::FailFast`](crate::apis::auth::TokenRefreshFailurePolicy::FailFast)).
+    /// With [`TokenRefreshFailurePolicy::ServeStale`](crate::apis::auth::TokenRefreshFailurePolicy::ServeStale),
+    /// requests keep using the cached token while it is still valid, and the refresh is retried on each request.
+    /// The number of stale tokens served is available through
+    /// [`TrueLayerClient::stale_tokens_served`](crate::client::TrueLayerClient::stale_tokens_served).
+    pub fn with_token_refresh_failure_policy(mut self, policy: TokenRefreshFailurePolicy) -> Self {
+        self.token_refresh_failure_policy = policy;
+        self
+    }
+
+    /// Delays the first access token request by a random amount of time up to `max_jitter`.
+    ///
+    /// All the requests sent while waiting share the same token. This spreads the load on the
+    /// Auth server when large fleets of services start at the same time. Disabled by default.
+    pub fn with_auth_startup_jitter(mut self, max_jitter: Duration) -> Self {
+        self.auth_startup_jitter = max_jitter;
+        self
+    }
+
+    /// Limits the number of concurrent requests to the Auth server.
+    ///
+    /// The same [`AuthConcurrencyLimit`](crate::apis::auth::AuthConcurrencyLimit) can be shared
+    /// by multiple clients to enforce a single limit for the whole process.
+    pub fn with_auth_concurrency_limit(mut self, limit: AuthConcurrencyLimit) -> Self {
+        self.auth_concurrency_limit = Some(limit);
+        self
+    }
+
+    /// Sets how long before their expiration access tokens are refreshed. Defaults to 10 minutes.
+    ///
+    /// A larger margin leaves more room to retry a failed refresh before the cached token expires.
+    pub fn with_token_refresh_margin(mut self, margin: Duration) -> Self {
+        self.token_refresh_margin = margin;
+        self
+    }
+
+    /// Sets where access and refresh tokens are persisted. Defaults to an [`InMemoryTokenStore`].
+    ///
+    /// Share a store backed by Redis or a database between processes to reuse the same tokens,
+    /// and to resume with the latest refresh token after a restart.
+    pub fn with_token_store(mut self, token_store: Arc<dyn TokenStore>) -> Self {
+        self.token_store = token_store;
+        self
+    }
+
+    /// Retries the idempotent requests rejected with `429 Too Many Requests` after the delay
+    /// requested by TrueLayer, instead of failing with [`Error::RateLimited`](crate::Error::RateLimited).
+    ///
+    /// Disabled by default: the [retry policy](TrueLayerClientBuilder::with_retry_policy) never
+    /// retries `429` responses, which fail straight away with `Error::RateLimited`.
+    pub fn with_rate_limit_policy(mut self, policy: RateLimitPolicy) -> Self {
+        self.rate_limit_policy = Some(policy);
+        self
+    }
+
+    /// Limits the requests sent to the TrueLayer APIs to `requests_per_second`, with a minimum of one.
+    ///
+    /// Requests beyond the limit wait for their turn instead of being rejected, so that batch jobs,
+    /// e.g. sending thousands of payouts, do not trip the TrueLayer rate limits. The limit is shared
+    /// by all the APIs of the client and applies to retries too. Token requests are not limited.
+    ///
+    /// Disabled by default.
+    pub fn with_rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.rate_limit = Some(requests_per_second);
+        self
+    }
+
+    /// Checks every payout against the given [`Guardrails`] before sending it.
+    ///
+    /// Payouts breaking any of the rules fail with [`Error::PayoutBlocked`](crate::Error::PayoutBlocked)
+    /// without being sent. See [`guardrails`](crate::guardrails) for more details.
+    pub fn with_payout_guardrails(mut self, guardrails: Guardrails) -> Self {
+        self.payout_guardrails = Some(Arc::new(guardrails));
+        self
+    }
+
+    /// Blocks the mutating calls to the Live environment not allowed by the given [`Confirmation`],
+    /// e.g. to protect staging deployments sharing the binaries of production ones.
+    ///
+    /// Blocked calls fail with [`Error::LiveCallBlocked`](crate::Error::LiveCallBlocked) without
+    /// being sent. Has no effect on clients connected to other environments.
+    /// See [`safeguard`](crate::safeguard) for more details.
+    pub fn with_live_safeguard(mut self, confirmation: Confirmation) -> Self {
+        self.live_safeguard = Some(confirmation);
+        self
+    }
+
+    /// Sets the scopes of the access tokens, e.g. `&["payments", "recurring_payments:sweeping"]`.
+    ///
+    /// With [`Credentials::ClientCredentials`](crate::apis::auth::Credentials::ClientCredentials),
+    /// the scopes replace the scope of the credentials. With the other credentials, they must match
+    /// the scopes granted by the user.
+    ///
+    /// Calls to APIs not covered by any of the scopes (see [`ApiGroup::required_scopes_for`]) then fail
+    /// with [`Error::MissingScope`](crate::Error::MissingScope) without being sent.
+    pub fn with_scopes(mut self, scopes: &[&str]) -> Self {
+        let scopes: Vec<String> = scopes.iter().map(|s| s.to_string()).collect();
+        if let Credentials::ClientCredentials { scope, .. } = &mut self.credentials {
+            *scope = scopes.join(" ");
+        }
+        self.scopes = Some(scopes);
+        self
+    }
+
+    /// Enables or disables all the behaviors which make the client send requests,
+    /// or alter their outcome, without being explicitly asked to. Enabled by default.
+    ///
+    /// Disabling implicit behaviors is meant for teams with strict audit requirements, where each request
+    /// to TrueLayer must correspond to exactly one call to the SDK. When disabled, regardless of the other
+    /// settings of the builder:
+    /// - failed requests are never retried, as if [`with_retry_policy(None)`](crate::client::TrueLayerClientBuilder::with_retry_policy)
+    ///   was configured, and rate limited requests are not retried even if a
+    ///   [`RateLimitPolicy`](crate::rate_limits::RateLimitPolicy) was configured;
+    /// - requests are never failed over to the fallbacks of an
+    ///   [`Environment::WithFallbacks`](crate::client::Environment::WithFallbacks), and the primary environment
+    ///   is never probed in the background;
+    /// - stale access tokens are never served when a refresh fails, as with
+    ///   [`TokenRefreshFailurePolicy::FailFast`](crate::apis::auth::TokenRefreshFailurePolicy::FailFast);
+    /// - the first access token request is not delayed by the
+    ///   [startup jitter](crate::client::TrueLayerClientBuilder::with_auth_startup_jitter);
+    /// - requests rejected with `401 Unauthorized` are not retried with a new access token,
+    ///   the next call uses a new one instead;
+    /// - form schemas are not cached by the [`FlowCache`](crate::apis::payments::flow::FlowCache);
+    /// - payments are not fetched again to detect
+    ///   [stale authorization steps](crate::Error::StaleAuthorizationStep) when an action is rejected.
+    ///
+    /// Limits which can only delay or reduce the requests sent, like the
+    /// [client-side rate limit](crate::client::TrueLayerClientBuilder::with_rate_limit) and the
+    /// [poll budget](crate::client::TrueLayerClientBuilder::with_poll_budget), still apply.
+    /// Access tokens are still requested on demand and cached until they are about to expire,
+    /// since every authenticated call needs one. Webhook signing keys are still prefetched by
+    /// [`JwksCache`](crate::apis::webhooks::JwksCache)s, unless their client is built with
+    /// [`UnauthenticatedClient::with_implicit_behaviors`](crate::client::UnauthenticatedClient::with_implicit_behaviors).
+    pub fn with_implicit_behaviors(mut self, enabled: bool) -> Self {
+        self.implicit_behaviors = enabled;
+        self
+    }
+
+    /// Registers a callback invoked with a [`ResponseSummary`](crate::audit::ResponseSummary)
+    /// after every completed HTTP call to TrueLayer, including retries and calls to the Auth server.
+    ///
+    /// Interceptors run synchronously on the task which sent the request, so they should not block.
+    /// Multiple interceptors are invoked in registration order. See [`audit`](crate::audit) for more details.
+    ///
+    /// ```rust,no_run
+    /// # use truelayer_rust::{TrueLayerClient, apis::auth::Credentials};
+    /// # let credentials: Credentials = unreachable!();
+    /// let tl = TrueLayerClient::builder(credentials)
+    ///     .with_response_interceptor(|summary| {
+    ///         tracing::info!(
+    ///             method = %summary.method,
+    ///             path = %summary.path_template,
+    ///             ids = ?summary.resource_ids,
+    ///             status = ?summary.status,
+    ///             duration_ms = summary.duration.as_millis() as u64,
+    ///             "TrueLayer call"
+    ///         )
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn with_response_interceptor(
+        mut self,
+        interceptor: impl Fn(&ResponseSummary) + Send + Sync + 'static,
+    ) -> Self {
+        self.response_interceptors
+            .push(ResponseInterceptor(Arc::new(interceptor)));
+        self
+    }
+
+    /// Registers a [`RequestObserver`](crate::observer::RequestObserver) notified of every attempt
+    /// of every HTTP call to TrueLayer, including retries and calls to the Auth server.
+    ///
+    /// Multiple observers are notified in registration order. See [`observer`](crate::observer) for more details.
+    pub fn with_request_observer(mut self, observer: impl RequestObserver + 'static) -> Self {
+        self.request_observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Adds a custom [`Middleware`](crate::deps::Middleware) to the stack of every HTTP client,
+    /// e.g. to inject headers expected by an egress proxy, log traffic or inject faults in tests.
+    ///
+    /// Custom middlewares run in registration order on every attempt of every call, including retries
+    /// and calls to the Auth server, after the request has been authenticated and signed: they must
+    /// not alter the body nor the signed headers of the requests.
+    ///
+    /// ```rust,no_run
+    /// # use truelayer_rust::{apis::auth::Credentials, deps::{Extensions, Middleware, Next}, TrueLayerClient};
+    /// # use reqwest::{Request, Response};
+    /// struct ProxyHeader;
+    ///
+    /// #[async_trait::async_trait]
+    /// impl Middleware for ProxyHeader {
+    ///     async fn handle(
+    ///         &self,
+    ///         mut req: Request,
+    ///         extensions: &mut Extensions,
+    ///         next: Next<'_>,
+    ///     ) -> reqwest_middleware::Result<Response> {
+    ///         req.headers_mut().insert("x-egress-tenant", "payments".parse().unwrap());
+    ///         next.run(req, extensions).await
+    ///     }
+    /// }
+    ///
+    /// # let credentials: Credentials = unreachable!();
+    /// let tl = TrueLayerClient::builder(credentials)
+    ///     .with_middleware(ProxyHeader)
+    ///     .build();
+    /// ```
+    pub fn with_middleware(mut self, middleware: impl Middleware) -> Self {
+        self.custom_middlewares.0.push(Arc::new(middleware));
+        self
+    }
+
+    /// Registers a callback invoked with a [`FlowEvent`](crate::apis::payments::flow::FlowEvent)
+    /// as the authorization flows of payments progress through the [`PaymentsApi`](crate::apis::payments::PaymentsApi).
+    ///
+    /// Listeners run synchronously on the task which called the API, so they should not block.
+    /// Multiple listeners are invoked in registration order.
+    ///
+    /// ```rust,no_run
+    /// # use truelayer_rust::{TrueLayerClient, apis::{auth::Credentials, payments::flow::FlowEvent}};
+    /// # let credentials: Credentials = unreachable!();
+    /// let tl = TrueLayerClient::builder(credentials)
+    ///     .with_flow_event_listener(|event| {
+    ///         if let FlowEvent::ActionFailed { action, failure_reason, .. } = event {
+    ///             tracing::info!(payment_id = event.payment_id(), ?action, failure_reason, "Checkout failed")
+    ///         }
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn with_flow_event_listener(
+        mut self,
+        listener: impl Fn(&FlowEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.flow_events.push(listener);
+        self
+    }
+
+    /// Sets the default [`PollOptions`](crate::pollable::PollOptions) of the client,
+    /// returned by [`TrueLayerClient::poll_options`](crate::client::TrueLayerClient::poll_options).
+    ///
+    /// Options passed explicitly to each poll are not affected. If a concurrency budget is set with
+    /// [`with_max_concurrency`](crate::pollable::PollOptions::with_max_concurrency), it is shared
+    /// by all the polls using the default options. Since the default options are used to poll any
+    /// resource, they cannot have a [status change callback](crate::pollable::PollOptions::on_status_change).
+    pub fn with_poll_options<R>(mut self, poll_options: PollOptions<R>) -> Self
+    where
+        R: RetryPolicy + Send + Sync + 'static,
+    {
+        self.poll_options = poll_options.into_dyn();
+        self
+    }
+
+    /// Limits the number of polls made by all the [`Pollable`](crate::Pollable) resources
+    /// with this client to `max_polls_per_minute`, regardless of the options of each poll.
+    ///
+    /// A budget of `0` is unlimited. See [`PollBudget`](crate::pollable::PollBudget) for more details.
+    /// The consumption of the budget is available through [`TrueLayerClient::poll_budget_metrics`](crate::client::TrueLayerClient::poll_budget_metrics).
+    pub fn with_poll_budget(mut self, max_polls_per_minute: u32) -> Self {
+        self.poll_budget = Some(PollBudget::per_minute(max_polls_per_minute));
+        self
+    }
+
+    /// Sets for how long the form schemas returned by the authorization flow are cached,
+    /// see [`PaymentsApi::flow`](crate::apis::payments::PaymentsApi::flow).
+    ///
+    /// Defaults to one hour. Use [`Duration::ZERO`] to disable the cache.
+    pub fn with_form_schema_ttl(mut self, ttl: Duration) -> Self {
+        self.form_schema_ttl = ttl;
+        self
+    }
+
+    /// Validates the body of each request against the bundled JSON Schema of its endpoint before sending it,
+    /// failing with [`Error::SchemaValidation`](crate::Error::SchemaValidation) if it does not conform.
+    ///
+    /// Disabled by default. See [`schema_validation`](crate::schema_validation) for more details.
+    #[cfg(feature = "schema-validation")]
+    pub fn with_schema_validation(mut self, enabled: bool) -> Self {
+        self.schema_validation = enabled;
+        self
+    }
 }
 
+/// Group of TrueLayer APIs, each one served by one of the API clients in a
+/// [`TrueLayerClient`](crate::client::TrueLayerClient).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ApiGroup {
+    /// APIs served by [`TrueLayerClient::payments`](crate::client::TrueLayerClient::payments).
+    Payments,
+    /// APIs served by [`TrueLayerClient::payments_providers`](crate::client::TrueLayerClient::payments_providers).
+    PaymentsProviders,
+    /// APIs served by [`TrueLayerClient::payouts`](crate::client::TrueLayerClient::payouts).
+    Payouts,
+    /// APIs served by [`TrueLayerClient::merchant_accounts`](crate::client::TrueLayerClient::merchant_accounts).
+    MerchantAccounts,
+    /// APIs served by [`TrueLayerClient::mandates`](crate::client::TrueLayerClient::mandates).
+    Mandates,
+    /// APIs served by [`TrueLayerClient::payment_links`](crate::client::TrueLayerClient::payment_links).
+    PaymentLinks,
+    /// APIs served by [`TrueLayerClient::verification`](crate::client::TrueLayerClient::verification).
+    Verification,
+    /// APIs served by [`TrueLayerClient::data`](crate::client::TrueLayerClient::data).
+    #[cfg(feature = "data")]
+    Data,
+}
+
+impl ApiGroup {
+    /// Returns the scopes any of which grants access to at least one API of this group.
+    ///
+    /// Some endpoints require a narrower scope, see [`required_scopes_for`](ApiGroup::required_scopes_for).
+    pub fn required_scopes(&self) -> &'static [&'static str] {
+        match self {
+            ApiGroup::Payments
+            | ApiGroup::PaymentsProviders
+            | ApiGroup::Payouts
+            | ApiGroup::MerchantAccounts
+            | ApiGroup::PaymentLinks => &["payments"],
+            ApiGroup::Mandates => &[
+                "recurring_payments:sweeping",
+                "recurring_payments:commercial",
+            ],
+            ApiGroup::Verification => &["verification"],
+            #[cfg(feature = "data")]
+            ApiGroup::Data => &["accounts", "balance", "transactions"],
+        }
+    }
+
+    /// Returns the scopes any of which grants access to the endpoint of this group serving the given path.
+    ///
+    /// Unlike the other groups, each Data API requires its own scope: `balance` for the balances,
+    /// `transactions` for the transactions and `accounts` for everything else.
+    #[cfg_attr(not(feature = "data"), allow(unused_variables))]
+    pub fn required_scopes_for(&self, path: &str) -> &'static [&'static str] {
+        match self {
+            ApiGroup::Payments
+            | ApiGroup::PaymentsProviders
+            | ApiGroup::Payouts
+            | ApiGroup::MerchantAccounts
+            | ApiGroup::Mandates
+            | ApiGroup::PaymentLinks
+            | ApiGroup::Verification => self.required_scopes(),
+            #[cfg(feature = "data")]
+            ApiGroup::Data => match path.trim_end_matches('/').rsplit('/').next() {
+                Some("balance") => &["balance"],
+                Some("transactions") => &["transactions"],
+                _ => &["accounts"],
+            },
+        }
+    }
+
+    /// Returns the group of the API serving the given path, if any.
+    pub(crate) fn from_path(path: &str) -> Option<ApiGroup> {
+        match path.trim_start_matches('/').split('/').next()? {
+            "payments" => Some(ApiGroup::Payments),
+            "payments-providers" => Some(ApiGroup::PaymentsProviders),
+            "payouts" => Some(ApiGroup::Payouts),
+            "merchant-accounts" => Some(ApiGroup::MerchantAccounts),
+            "mandates" => Some(ApiGroup::Mandates),
+            "payment-links" => Some(ApiGroup::PaymentLinks),
+            "verifications" => Some(ApiGroup::Verification),
+            #[cfg(feature = "data")]
+            "data" => Some(ApiGroup::Data),
+            _ => None,
+        }
+    }
+}
+
+/// Lightweight client for the TrueLayer endpoints which do not require authentication,
+/// like the public keys used to verify webhook signatures.
+///
+/// Unlike [`TrueLayerClient`](crate::client::TrueLayerClient), this client needs no credentials,
+/// so that services which only verify webhooks do not need access to any client secret.
+#[derive(Clone)]
+pub struct UnauthenticatedClient {
+    client: ClientWithMiddleware,
+    environment: Environment,
+    http_client: reqwest::Client,
+    body_limits_middleware: Option<BodyLimitsMiddleware>,
+    implicit_behaviors: bool,
+}
+
+impl UnauthenticatedClient {
+    /// Builds a new client connecting to the given environment.
+    pub fn new(environment: Environment) -> Self {
+        Self::with_http_client(reqwest::Client::new(), environment)
+    }
+
+    /// Builds a new client connecting to the given environment using a specific reqwest [`Client`](reqwest::Client).
+    pub fn with_http_client(client: reqwest::Client, environment: Environment) -> Self {
+        Self::build(client, None, environment, true)
+    }
+
+    /// Builds a new client connecting to the given environment with the given transport settings.
+    ///
+    /// This is useful to protect webhook verifiers from pathological responses, e.g. with
+    /// [`TransportConfig::with_max_response_size`](crate::transport::TransportConfig::with_max_response_size).
+    pub fn with_transport(transport: TransportConfig, environment: Environment) -> Self {
+        Self::build(
+            transport.build_http_client(),
+            transport.body_limits_middleware(),
+            environment,
+            true,
+        )
+    }
+
+    /// Enables or disables the behaviors which make the client send requests without being
+    /// explicitly asked to, like [`with_implicit_behaviors`](TrueLayerClientBuilder::with_implicit_behaviors)
+    /// does for [`TrueLayerClient`](crate::client::TrueLayerClient). Enabled by default.
+    ///
+    /// When disabled, failed requests are never retried, and the
+    /// [`JwksCache`](crate::apis::webhooks::JwksCache)s using this client never refresh
+    /// the keys in the background.
+    pub fn with_implicit_behaviors(self, enabled: bool) -> Self {
+        Self::build(
+            self.http_client,
+            self.body_limits_middleware,
+            self.environment,
+            enabled,
+        )
+    }
+
+    /// See [`with_implicit_behaviors`](UnauthenticatedClient::with_implicit_behaviors).
+    pub(crate) fn implicit_behaviors(&self) -> bool {
+        self.implicit_behaviors
+    }
+
+    fn build(
+        http_client: reqwest::Client,
+        body_limits_middleware: Option<BodyLimitsMiddleware>,
+        environment: Environment,
+        implicit_behaviors: bool,
+    ) -> Self {
+        let retry_policy = implicit_behaviors.then(|| {
+            DynRetryPolicy(Arc::new(
+                ExponentialBackoff::builder().build_with_max_retries(3),
+            ))
+        });
+
+        Self {
+            client: build_client_with_middleware(
+                http_client.clone(),
+                retry_policy,
+                DeprecationRegistry::default(),
+                RateLimitRegistry::default(),
+                Vec::new(),
+                Vec::new(),
+                CustomMiddlewares::default(),
+                body_limits_middleware.clone(),
+                None,
+                None,
+                None,
+                None,
+                Vec::new(),
+                None,
+                None,
+            ),
+            environment,
+            http_client,
+            body_limits_middleware,
+            implicit_behaviors,
+        }
+    }
+
+    /// URL of the set of public keys used by TrueLayer to sign webhooks.
+    ///
+    /// Webhook signatures referencing any other URL in their `jku` header must be rejected.
+    pub fn jwks_url(&self) -> Url {
+        self.environment
+            .webhooks_url()
+            .join("/.well-known/jwks")
+            .unwrap()
+    }
+
+    /// Fetches the set of public keys currently used by TrueLayer to sign webhooks.
+    #[tracing::instrument(name = "Get JWKS", skip(self))]
+    pub async fn get_jwks(&self) -> Result<Jwks, Error> {
+        let res = self
+            .client
+            .get(self.jwks_url())
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        Ok(res)
+    }
+}
+
+impl std::fmt::Debug for UnauthenticatedClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnauthenticatedClient")
+            .field("environment", &self.environment)
+            .field("implicit_behaviors", &self.implicit_behaviors)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Middlewares added with [`with_middleware`](TrueLayerClientBuilder::with_middleware), in registration order.
+#[derive(Clone, Default)]
+struct CustomMiddlewares(Vec<Arc<dyn Middleware>>);
+
+impl std::fmt::Debug for CustomMiddlewares {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("CustomMiddlewares")
+            .field(&self.0.len())
+            .finish()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn build_client_with_middleware(
     client: reqwest::Client,
     retry_policy: Option<DynRetryPolicy>,
+    deprecations: DeprecationRegistry,
+    rate_limits: RateLimitRegistry,
+    response_interceptors: Vec<ResponseInterceptor>,
+    request_observers: Vec<Arc<dyn RequestObserver>>,
+    custom_middlewares: CustomMiddlewares,
+    body_limits_middleware: Option<BodyLimitsMiddleware>,
+    timeout_middleware: Option<DefaultTimeoutMiddleware>,
     auth_middleware: Option<AuthenticationMiddleware>,
     signing_middleware: Option<SigningMiddleware>,
+    failover_middleware: Option<FailoverMiddleware>,
+    request_middlewares: Vec<Arc<dyn Middleware>>,
+    retry_after_middleware: Option<RetryAfterMiddleware>,
+    rate_limiter_middleware: Option<RateLimiterMiddleware>,
 ) -> ClientWithMiddleware {
     let mut builder = reqwest_middleware::ClientBuilder::new(client)
         .with(InjectUserAgentMiddleware::new())
         .with(TracingMiddleware::default())
         .with(ErrorHandlingMiddleware);
 
+    if let Some(body_limits_middleware) = body_limits_middleware {
+        builder = builder.with(body_limits_middleware);
+    }
+
+    if let Some(timeout_middleware) = timeout_middleware {
+        builder = builder.with(timeout_middleware);
+    }
+
+    builder = builder.with(DeprecationMiddleware::new(deprecations)).with(
+        RateLimitHeadersMiddleware::new(rate_limits, request_observers.clone()),
+    );
+
+    // Validate requests once, before they are retried
+    for middleware in request_middlewares {
+        builder = builder.with_arc(middleware);
+    }
+
     if let Some(retry_policy) = retry_policy {
         builder = builder.with(RetryIdempotentMiddleware::new(retry_policy));
     }
 
+    // Rate limited requests are retried after the requested delay rather than with backoffs
+    if let Some(retry_after_middleware) = retry_after_middleware {
+        builder = builder.with(retry_after_middleware);
+    }
+
+    // Every attempt, including retries, counts towards the client-side rate limit
+    if let Some(rate_limiter_middleware) = rate_limiter_middleware {
+        builder = builder.with(rate_limiter_middleware);
+    }
+
     if let Some(auth_middleware) = auth_middleware {
         builder = builder.with(auth_middleware);
     }
@@ -226,22 +1253,55 @@ fn build_client_with_middleware(
         builder = builder.with(signing_middleware);
     }
 
+    if let Some(failover_middleware) = failover_middleware {
+        builder = builder.with(failover_middleware);
+    }
+
+    // Custom middlewares see every attempt, authenticated and signed
+    for middleware in custom_middlewares.0 {
+        builder = builder.with_arc(middleware);
+    }
+
+    // Innermost, to see every single attempt with its final URL
+    if !response_interceptors.is_empty() {
+        builder = builder.with(ResponseInterceptorMiddleware::new(response_interceptors));
+    }
+    if !request_observers.is_empty() {
+        builder = builder.with(ObserverMiddleware::new(request_observers));
+    }
+
     builder.build()
 }
 
 /// TrueLayer environment to which a [`TrueLayerClient`](crate::client::TrueLayerClient) should connect.
 #[derive(Debug, Clone)]
 #[allow(clippy::large_enum_variant)]
+#[non_exhaustive]
 pub enum Environment {
     /// TrueLayer Live environment.
     Live,
     /// TrueLayer Sandbox environment.
     Sandbox,
-    /// Custom environment. This variant is mainly used for tests.
+    /// Custom environment, mainly used for tests and to override some of the URLs of a preset.
+    ///
+    /// Build it with [`from_single_url`](Environment::from_single_url) or by overriding the URLs
+    /// of a preset, e.g. with [`with_payments_url`](Environment::with_payments_url).
+    #[non_exhaustive]
     Custom {
         auth_url: Url,
         payments_url: Url,
         hpp_url: Url,
+        webhooks_url: Url,
+        /// Whether the URLs lead to TrueLayer Live, e.g. through a proxy.
+        /// Derived from the host of the URLs, or inherited from the preset whose URLs are overridden.
+        live: bool,
+    },
+    /// Primary environment with an ordered list of fallbacks to use when it becomes unreachable.
+    ///
+    /// Use [`with_fallbacks`](crate::client::Environment::with_fallbacks) to build this variant.
+    WithFallbacks {
+        primary: Box<Environment>,
+        fallbacks: Vec<Environment>,
     },
 }
 
@@ -252,6 +1312,112 @@ impl Environment {
             auth_url: url.clone(),
             payments_url: url.clone(),
             hpp_url: url.clone(),
+            webhooks_url: url.clone(),
+            live: url.host_str() == Url::parse(DEFAULT_PAYMENTS_URL).unwrap().host_str(),
+        }
+    }
+
+    /// TrueLayer Sandbox environment.
+    ///
+    /// The URL of each service can be overridden independently, e.g. to go through a proxy:
+    ///
+    /// ```rust
+    /// # use truelayer_rust::client::Environment;
+    /// # use reqwest::Url;
+    /// let environment = Environment::sandbox()
+    ///     .with_payments_url(Url::parse("https://egress-proxy.internal").unwrap());
+    ///
+    /// assert_eq!(environment.payments_url().as_str(), "https://egress-proxy.internal/");
+    /// assert_eq!(environment.auth_url().as_str(), "https://auth.truelayer-sandbox.com/");
+    /// ```
+    pub fn sandbox() -> Environment {
+        Environment::Sandbox
+    }
+
+    /// TrueLayer Live environment, whose URLs can be overridden like those of the [`sandbox`](Environment::sandbox).
+    pub fn production() -> Environment {
+        Environment::Live
+    }
+
+    /// Overrides the base URL for authentication-related requests.
+    pub fn with_auth_url(self, url: Url) -> Environment {
+        self.customized(|environment| {
+            if let Environment::Custom { auth_url, .. } = environment {
+                *auth_url = url;
+            }
+        })
+    }
+
+    /// Overrides the base URL for payments-related requests.
+    pub fn with_payments_url(self, url: Url) -> Environment {
+        self.customized(|environment| {
+            if let Environment::Custom { payments_url, .. } = environment {
+                *payments_url = url;
+            }
+        })
+    }
+
+    /// Overrides the base URL for the Hosted Payments Page.
+    pub fn with_hpp_url(self, url: Url) -> Environment {
+        self.customized(|environment| {
+            if let Environment::Custom { hpp_url, .. } = environment {
+                *hpp_url = url;
+            }
+        })
+    }
+
+    /// Overrides the base URL from which the public keys used to sign webhooks are fetched.
+    pub fn with_webhooks_jwks_url(self, url: Url) -> Environment {
+        self.customized(|environment| {
+            if let Environment::Custom { webhooks_url, .. } = environment {
+                *webhooks_url = url;
+            }
+        })
+    }
+
+    /// Turns this environment into an `Environment::Custom` with the same URLs, then customizes it.
+    /// For environments with fallbacks, only the primary environment is customized.
+    fn customized(self, customize: impl FnOnce(&mut Environment)) -> Environment {
+        match self {
+            Environment::WithFallbacks { primary, fallbacks } => Environment::WithFallbacks {
+                primary: Box::new(primary.customized(customize)),
+                fallbacks,
+            },
+            environment => {
+                let mut custom = Environment::Custom {
+                    auth_url: environment.auth_url(),
+                    payments_url: environment.payments_url(),
+                    hpp_url: environment.hpp_url(),
+                    webhooks_url: environment.webhooks_url(),
+                    live: environment.is_live(),
+                };
+                customize(&mut custom);
+                custom
+            }
+        }
+    }
+
+    /// Adds an ordered list of fallback environments (e.g., regional egress points)
+    /// to which the client switches when this environment becomes unreachable.
+    ///
+    /// See [`with_failover_policy`](crate::client::TrueLayerClientBuilder::with_failover_policy)
+    /// to configure when to switch.
+    pub fn with_fallbacks(self, fallbacks: Vec<Environment>) -> Environment {
+        match self {
+            Environment::WithFallbacks {
+                primary,
+                fallbacks: mut existing,
+            } => {
+                existing.extend(fallbacks);
+                Environment::WithFallbacks {
+                    primary,
+                    fallbacks: existing,
+                }
+            }
+            primary => Environment::WithFallbacks {
+                primary: Box::new(primary),
+                fallbacks,
+            },
         }
     }
 
@@ -261,6 +1427,7 @@ impl Environment {
             Environment::Live => Url::parse(DEFAULT_AUTH_URL).unwrap(),
             Environment::Sandbox => Url::parse(DEFAULT_SANDBOX_AUTH_URL).unwrap(),
             Environment::Custom { auth_url, .. } => auth_url.clone(),
+            Environment::WithFallbacks { primary, .. } => primary.auth_url(),
         }
     }
 
@@ -270,6 +1437,7 @@ impl Environment {
             Environment::Live => Url::parse(DEFAULT_PAYMENTS_URL).unwrap(),
             Environment::Sandbox => Url::parse(DEFAULT_SANDBOX_PAYMENTS_URL).unwrap(),
             Environment::Custom { payments_url, .. } => payments_url.clone(),
+            Environment::WithFallbacks { primary, .. } => primary.payments_url(),
         }
     }
 
@@ -279,6 +1447,463 @@ impl Environment {
             Environment::Live => Url::parse(DEFAULT_HOSTED_PAYMENTS_PAGE_URL).unwrap(),
             Environment::Sandbox => Url::parse(DEFAULT_SANDBOX_HOSTED_PAYMENTS_PAGE_URL).unwrap(),
             Environment::Custom { hpp_url, .. } => hpp_url.clone(),
+            Environment::WithFallbacks { primary, .. } => primary.hpp_url(),
+        }
+    }
+
+    /// Base URL from which TrueLayer serves the public keys used to sign webhooks.
+    pub fn webhooks_url(&self) -> Url {
+        match self {
+            Environment::Live => Url::parse(DEFAULT_WEBHOOKS_URL).unwrap(),
+            Environment::Sandbox => Url::parse(DEFAULT_SANDBOX_WEBHOOKS_URL).unwrap(),
+            Environment::Custom { webhooks_url, .. } => webhooks_url.clone(),
+            Environment::WithFallbacks { primary, .. } => primary.webhooks_url(),
+        }
+    }
+
+    /// Whether this environment can send requests to TrueLayer Live: directly, through custom URLs
+    /// overriding the ones of [`production`](Environment::production) (e.g. a proxy), or through
+    /// any of its fallbacks.
+    pub fn is_live(&self) -> bool {
+        match self {
+            Environment::Live => true,
+            Environment::Sandbox => false,
+            Environment::Custom {
+                live, payments_url, ..
+            } => {
+                *live
+                    || payments_url.host_str()
+                        == Url::parse(DEFAULT_PAYMENTS_URL).unwrap().host_str()
+            }
+            Environment::WithFallbacks { primary, fallbacks } => {
+                primary.is_live() || fallbacks.iter().any(Environment::is_live)
+            }
         }
     }
+
+    /// Base URLs of the primary environment followed by all the fallbacks,
+    /// or `None` if there are no fallbacks.
+    fn failover_base_urls(&self) -> Option<Vec<[Url; 3]>> {
+        match self {
+            Environment::WithFallbacks { primary, fallbacks } if !fallbacks.is_empty() => Some(
+                std::iter::once(primary.as_ref())
+                    .chain(fallbacks)
+                    .map(|e| [e.auth_url(), e.payments_url(), e.hpp_url()])
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::RequestOptions;
+    use serde_json::json;
+    use std::str::FromStr;
+    use wiremock::{
+        matchers::{header, header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn environment_urls_can_be_overridden_independently() {
+        let proxy = Url::parse("https://proxy.internal").unwrap();
+        let environment = Environment::production()
+            .with_payments_url(proxy.clone())
+            .with_webhooks_jwks_url(proxy.clone());
+
+        assert_eq!(environment.payments_url(), proxy);
+        assert_eq!(environment.webhooks_url(), proxy);
+        assert_eq!(environment.auth_url(), Environment::Live.auth_url());
+        assert_eq!(environment.hpp_url(), Environment::Live.hpp_url());
+
+        // Overrides apply to the primary environment only
+        let environment = Environment::sandbox()
+            .with_fallbacks(vec![Environment::Live])
+            .with_auth_url(proxy.clone());
+        assert_eq!(environment.auth_url(), proxy);
+        assert_eq!(
+            environment.payments_url(),
+            Environment::Sandbox.payments_url()
+        );
+    }
+
+    #[test]
+    fn live_environments_are_detected_through_proxies_and_fallbacks() {
+        let proxy = Url::parse("https://proxy.internal").unwrap();
+
+        assert!(Environment::production().is_live());
+        assert!(!Environment::sandbox().is_live());
+        assert!(Environment::production()
+            .with_payments_url(proxy.clone())
+            .is_live());
+        assert!(!Environment::sandbox()
+            .with_payments_url(proxy.clone())
+            .is_live());
+        assert!(Environment::sandbox()
+            .with_fallbacks(vec![Environment::Live])
+            .is_live());
+        assert!(!Environment::from_single_url(&proxy).is_live());
+    }
+
+    #[tokio::test]
+    async fn implicit_behaviors_can_be_disabled() {
+        let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
+            client_id: "client-id".into(),
+            client_secret: "client-secret".into(),
+            scope: "payments".into(),
+        })
+        .with_poll_budget(10)
+        .with_implicit_behaviors(false)
+        .build();
+        assert!(tl.poll_budget_metrics().is_some());
+
+        let client = UnauthenticatedClient::new(Environment::Sandbox);
+        assert!(client.implicit_behaviors());
+        assert!(!client.with_implicit_behaviors(false).implicit_behaviors());
+    }
+
+    #[tokio::test]
+    async fn unauthenticated_client_gets_jwks() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/.well-known/jwks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "keys": [
+                    {
+                        "kty": "EC",
+                        "kid": "some-kid",
+                        "crv": "P-521",
+                        "alg": "ES512",
+                        "x": "some-x",
+                        "y": "some-y"
+                    }
+                ]
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        // No access token must be requested
+        Mock::given(header_exists("Authorization"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let client = UnauthenticatedClient::new(Environment::from_single_url(
+            &Url::parse(&mock_server.uri()).unwrap(),
+        ));
+
+        let jwks = client.get_jwks().await.unwrap();
+        assert_eq!(jwks.keys.len(), 1);
+        assert_eq!(jwks.find("some-kid").unwrap().crv.as_deref(), Some("P-521"));
+        assert!(jwks.find("other-kid").is_none());
+    }
+
+    #[tokio::test]
+    async fn calls_outside_the_configured_scopes_fail_fast() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/connect/token"))
+            .and(wiremock::matchers::body_partial_json(json!({
+                "scope": "payments verification"
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "token_type": "Bearer",
+                "access_token": "access-token",
+                "expires_in": 3600
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/payments/payment-id"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
+            client_id: "client-id".into(),
+            client_secret: "client-secret".into(),
+            scope: "mock".into(),
+        })
+        .with_environment(Environment::from_single_url(
+            &Url::parse(&mock_server.uri()).unwrap(),
+        ))
+        .with_scopes(&["payments", "verification"])
+        .build();
+
+        assert!(tl.payments.get_by_id("payment-id").await.unwrap().is_none());
+        assert!(matches!(
+            tl.mandates.get_by_id("mandate-id").await,
+            Err(Error::MissingScope {
+                api_group: ApiGroup::Mandates,
+                ..
+            })
+        ));
+    }
+
+    #[cfg(feature = "data")]
+    #[test]
+    fn data_apis_require_the_scope_of_their_endpoint() {
+        assert_eq!(
+            ApiGroup::Data.required_scopes_for("/data/v1/accounts"),
+            &["accounts"]
+        );
+        assert_eq!(
+            ApiGroup::Data.required_scopes_for("/data/v1/accounts/account-id/balance"),
+            &["balance"]
+        );
+        assert_eq!(
+            ApiGroup::Data.required_scopes_for("/data/v1/accounts/account-id/transactions"),
+            &["transactions"]
+        );
+        assert_eq!(
+            ApiGroup::Mandates.required_scopes_for("/mandates/mandate-id"),
+            ApiGroup::Mandates.required_scopes()
+        );
+    }
+
+    #[tokio::test]
+    async fn custom_middlewares_apply_to_all_requests() {
+        struct TenantHeader;
+
+        #[async_trait::async_trait]
+        impl Middleware for TenantHeader {
+            async fn handle(
+                &self,
+                mut req: reqwest::Request,
+                extensions: &mut task_local_extensions::Extensions,
+                next: reqwest_middleware::Next<'_>,
+            ) -> reqwest_middleware::Result<reqwest::Response> {
+                req.headers_mut().insert(
+                    "x-tenant",
+                    reqwest::header::HeaderValue::from_static("tenant"),
+                );
+                next.run(req, extensions).await
+            }
+        }
+
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/connect/token"))
+            .and(header("x-tenant", "tenant"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "token_type": "Bearer",
+                "access_token": "access-token",
+                "expires_in": 3600
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/payments/payment-id"))
+            .and(header("x-tenant", "tenant"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
+            client_id: "client-id".into(),
+            client_secret: "client-secret".into(),
+            scope: "mock".into(),
+        })
+        .with_environment(Environment::from_single_url(
+            &Url::parse(&mock_server.uri()).unwrap(),
+        ))
+        .with_middleware(TenantHeader)
+        .build();
+
+        assert!(tl.payments.get_by_id("payment-id").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn custom_http_clients_are_wrapped_in_the_middleware_stack() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/connect/token"))
+            .and(header("x-custom-client", "yes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "token_type": "Bearer",
+                "access_token": "access-token",
+                "expires_in": 3600
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/payments/payment-id"))
+            .and(header("x-custom-client", "yes"))
+            .and(header("authorization", "Bearer access-token"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let http_client = reqwest::Client::builder()
+            .default_headers(
+                [(
+                    reqwest::header::HeaderName::from_static("x-custom-client"),
+                    reqwest::header::HeaderValue::from_static("yes"),
+                )]
+                .into_iter()
+                .collect(),
+            )
+            .build()
+            .unwrap();
+        let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
+            client_id: "client-id".into(),
+            client_secret: "client-secret".into(),
+            scope: "mock".into(),
+        })
+        .with_environment(Environment::from_single_url(
+            &Url::parse(&mock_server.uri()).unwrap(),
+        ))
+        .with_http_client(http_client)
+        .build();
+
+        assert!(tl.payments.get_by_id("payment-id").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn default_timeout_can_be_overridden_per_call() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/connect/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "token_type": "Bearer",
+                "access_token": "access-token",
+                "expires_in": 3600
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/payouts"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "id": "payout-id" }))
+                    .set_delay(Duration::from_millis(300)),
+            )
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
+            client_id: "client-id".into(),
+            client_secret: "client-secret".into(),
+            scope: "mock".into(),
+        })
+        .with_environment(Environment::from_single_url(
+            &Url::parse(&mock_server.uri()).unwrap(),
+        ))
+        .with_http_client(reqwest::Client::new())
+        .with_timeout(Duration::from_millis(50))
+        .with_retry_policy(None)
+        .build();
+        let req = crate::apis::payouts::CreatePayoutRequest {
+            merchant_account_id: "merchant-account-id".to_string(),
+            amount_in_minor: 100,
+            currency: crate::apis::payments::Currency::Gbp,
+            beneficiary: crate::apis::payouts::PayoutBeneficiary::BusinessAccount {
+                reference: "some-reference".to_string(),
+            },
+            scheme_selection: None,
+        };
+
+        // The default timeout applies to custom HTTP clients too
+        let err = tl.payouts.create(&req).await.unwrap_err();
+        assert!(matches!(err, Error::HttpError(e) if e.is_timeout()));
+
+        let res = tl
+            .payouts
+            .create_with_options(
+                &req,
+                RequestOptions {
+                    timeout: Some(Duration::from_secs(5)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.id, "payout-id");
+    }
+
+    #[tokio::test]
+    async fn mutating_requests_are_signed_automatically() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/connect/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "token_type": "Bearer",
+                "access_token": "access-token",
+                "expires_in": 3600
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/payouts"))
+            .and(header_exists(crate::common::TL_SIGNATURE_HEADER))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "id": "payout-id" })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let key = openssl::ec::EcKey::generate(
+            &openssl::ec::EcGroup::from_curve_name(openssl::nid::Nid::SECP521R1).unwrap(),
+        )
+        .unwrap();
+        let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
+            client_id: "client-id".into(),
+            client_secret: "client-secret".into(),
+            scope: "mock".into(),
+        })
+        .with_environment(Environment::from_single_url(
+            &Url::parse(&mock_server.uri()).unwrap(),
+        ))
+        .with_signing_key("key-id", key.private_key_to_pem().unwrap())
+        .build();
+
+        tl.payouts
+            .create(&crate::apis::payouts::CreatePayoutRequest {
+                merchant_account_id: "merchant-account-id".to_string(),
+                amount_in_minor: 100,
+                currency: crate::apis::payments::Currency::Gbp,
+                beneficiary: crate::apis::payouts::PayoutBeneficiary::PaymentSource {
+                    user_id: "user-id".to_string(),
+                    payment_source_id: "payment-source-id".to_string(),
+                    reference: "some-reference".to_string(),
+                },
+                scheme_selection: None,
+            })
+            .await
+            .unwrap();
+
+        // The signature covers method, path, idempotency key and the exact body sent
+        let requests = mock_server.received_requests().await.unwrap();
+        let req = requests
+            .iter()
+            .find(|r| r.url.path() == "/payouts")
+            .unwrap();
+        let header = |name: &str| {
+            req.headers
+                .get(&wiremock::http::HeaderName::from_str(name).unwrap())
+                .unwrap()
+                .last()
+                .to_string()
+        };
+        truelayer_signing::verify_with_pem(key.public_key_to_pem().unwrap().as_slice())
+            .method("POST")
+            .path("/payouts")
+            .header(
+                crate::common::IDEMPOTENCY_KEY_HEADER,
+                header(crate::common::IDEMPOTENCY_KEY_HEADER).as_bytes(),
+            )
+            .body(&req.body)
+            .verify(&header(crate::common::TL_SIGNATURE_HEADER))
+            .unwrap();
+    }
 }
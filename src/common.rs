@@ -5,8 +5,12 @@ pub static DEFAULT_HOSTED_PAYMENTS_PAGE_URL: &str = "https://payment.truelayer.c
 pub static DEFAULT_SANDBOX_AUTH_URL: &str = "https://auth.truelayer-sandbox.com";
 pub static DEFAULT_SANDBOX_PAYMENTS_URL: &str = "https://api.truelayer-sandbox.com";
 pub static DEFAULT_SANDBOX_HOSTED_PAYMENTS_PAGE_URL: &str = "https://payment.truelayer-sandbox.com";
+pub static DEFAULT_WEBHOOKS_URL: &str = "https://webhooks.truelayer.com";
+pub static DEFAULT_SANDBOX_WEBHOOKS_URL: &str = "https://webhooks.truelayer-sandbox.com";
 
 // Header names
 pub static IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
 pub static TL_SIGNATURE_HEADER: &str = "Tl-Signature";
+pub static TL_WEBHOOK_TIMESTAMP_HEADER: &str = "X-Tl-Webhook-Timestamp";
 pub static TL_CORRELATION_ID_HEADER: &str = "X-Tl-Correlation-Id";
+pub static CORRELATION_ID_HEADER: &str = "X-Correlation-Id";
@@ -0,0 +1,486 @@
+//! Loading of the settings needed to build a [`TrueLayerClient`](crate::TrueLayerClient)
+//! from a file, environment variables and command line flags.
+//!
+//! Settings are looked up by key in the following order, the first match winning:
+//! 1. command line flags, e.g. `--client-id some-client-id` or `--client-id=some-client-id`.
+//!    Flags without a value, like `--verbose`, are set to `true`;
+//! 2. environment variables prefixed by `TRUELAYER_`, e.g. `TRUELAYER_CLIENT_ID`;
+//! 3. a TOML or JSON file containing strings, e.g. `client_id = "some-client-id"`.
+//!
+//! The recognized keys are:
+//! - `client_id` and `client_secret` (mandatory);
+//! - `scope` for client credentials, defaulting to `payments`;
+//! - `code` and `redirect_uri` to use an authorization code, or `refresh_token` to use a refresh token,
+//!   instead of client credentials;
+//! - `key_id` with either `private_key` (PEM encoded) or `private_key_path` to sign requests;
+//! - `environment`, either `live`, `sandbox` or the base URL of a custom environment. Defaults to `sandbox`,
+//!   so that a misconfigured tool never moves real money.
+//!
+//! Any other key can be read with [`Config::get`].
+//!
+//! ```rust,no_run
+//! # fn main() -> Result<(), truelayer_rust::config::ConfigError> {
+//! let config = truelayer_rust::config::load()?;
+//! let tl = config.client_builder().build();
+//! let return_uri = config.get("return_uri");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    apis::auth::{Credentials, Token},
+    client::{Environment, TrueLayerClientBuilder},
+};
+use reqwest::Url;
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Formatter},
+    path::{Path, PathBuf},
+};
+
+/// Prefix of the environment variables read by the loader.
+pub const ENV_PREFIX: &str = "TRUELAYER_";
+
+/// Files read by [`load`] if they exist, relative to the working directory.
+/// The first one found wins.
+pub const DEFAULT_CONFIG_FILES: [&str; 2] = ["config.toml", "config.json"];
+
+/// Keys of the recognized settings, which always need a value when passed as flags.
+const KNOWN_KEYS: [&str; 10] = [
+    "client_id",
+    "client_secret",
+    "scope",
+    "code",
+    "redirect_uri",
+    "refresh_token",
+    "key_id",
+    "private_key",
+    "private_key_path",
+    "environment",
+];
+
+/// Keys of the settings whose values are redacted by the [`Debug`] implementation of [`Config`].
+const SECRET_KEYS: [&str; 4] = ["client_secret", "code", "refresh_token", "private_key"];
+
+/// Error returned when the configuration cannot be loaded.
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Missing mandatory setting: {0}")]
+    MissingSetting(&'static str),
+    #[error("Missing value for command line flag --{0}")]
+    MissingFlagValue(String),
+    #[error("Invalid environment {0:?}: expected live, sandbox or a URL")]
+    InvalidEnvironment(String),
+    #[error("Cannot read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid configuration file {path}: {source}")]
+    InvalidFile {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+/// Key and PEM encoded private key used to sign requests.
+#[derive(Clone)]
+pub struct Secrets {
+    pub key_id: String,
+    pub private_key: Vec<u8>,
+}
+
+impl Debug for Secrets {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Secrets")
+            .field("key_id", &self.key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Settings loaded by a [`ConfigLoader`].
+#[derive(Clone)]
+pub struct Config {
+    pub credentials: Credentials,
+    /// Signing key, if configured.
+    pub secrets: Option<Secrets>,
+    pub environment: Environment,
+    values: HashMap<String, String>,
+}
+
+impl Config {
+    /// Returns the raw value of a setting, after applying the precedence rules.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Returns a client builder configured with the loaded credentials, signing key and environment.
+    pub fn client_builder(&self) -> TrueLayerClientBuilder {
+        let mut builder = TrueLayerClientBuilder::new(self.credentials.clone())
+            .with_environment(self.environment.clone());
+
+        if let Some(secrets) = &self.secrets {
+            builder = builder.with_signing_key(&secrets.key_id, secrets.private_key.clone());
+        }
+
+        builder
+    }
+}
+
+impl Debug for Config {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let values: HashMap<&str, &str> = self
+            .values
+            .iter()
+            .map(|(key, value)| {
+                let value = if SECRET_KEYS.contains(&key.as_str()) {
+                    "[REDACTED]"
+                } else {
+                    value.as_str()
+                };
+                (key.as_str(), value)
+            })
+            .collect();
+
+        f.debug_struct("Config")
+            .field("client_id", &self.credentials.client_id())
+            .field("secrets", &self.secrets)
+            .field("environment", &self.environment)
+            .field("values", &values)
+            .finish()
+    }
+}
+
+/// Loads the configuration from the first of [`DEFAULT_CONFIG_FILES`] which exists, the environment
+/// variables and the flags of the current process.
+///
+/// Environment variables and flags which are not valid unicode are ignored.
+pub fn load() -> Result<Config, ConfigError> {
+    let mut loader = ConfigLoader::new()
+        .with_env_vars(std::env::vars_os().filter_map(|(name, value)| {
+            Some((name.into_string().ok()?, value.into_string().ok()?))
+        }))
+        .with_args(
+            std::env::args_os()
+                .skip(1)
+                .filter_map(|arg| arg.into_string().ok()),
+        )?;
+
+    if let Some(file) = DEFAULT_CONFIG_FILES
+        .into_iter()
+        .find(|file| Path::new(file).exists())
+    {
+        loader = loader.with_file(file)?;
+    }
+
+    loader.load()
+}
+
+/// Loader of a [`Config`] from multiple sources.
+///
+/// Sources can be added in any order: flags always take precedence over environment variables,
+/// which take precedence over files. If multiple files are added, the last one wins.
+#[derive(Debug, Default)]
+pub struct ConfigLoader {
+    files: HashMap<String, String>,
+    env_vars: HashMap<String, String>,
+    flags: HashMap<String, String>,
+}
+
+impl ConfigLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads settings from a file, parsed as TOML if its extension is `.toml` and as JSON otherwise.
+    pub fn with_file(mut self, path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let values: HashMap<String, String> = if path
+            .extension()
+            .map_or(false, |extension| extension == "toml")
+        {
+            toml::from_str(&contents).map_err(|e| Box::new(e) as _)
+        } else {
+            serde_json::from_str(&contents).map_err(|e| Box::new(e) as _)
+        }
+        .map_err(|source| ConfigError::InvalidFile {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        self.files.extend(values);
+        Ok(self)
+    }
+
+    /// Reads settings from environment variables prefixed by [`ENV_PREFIX`]. Other variables are ignored.
+    pub fn with_env_vars(mut self, vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.env_vars
+            .extend(vars.into_iter().filter_map(|(name, value)| {
+                name.strip_prefix(ENV_PREFIX)
+                    .map(|key| (key.to_lowercase(), value))
+            }));
+        self
+    }
+
+    /// Reads settings from command line flags, excluding the name of the program.
+    ///
+    /// Arguments which are not flags are ignored. Flags followed by another flag or by nothing are
+    /// set to `true`, except for the recognized settings listed in [`config`](crate::config).
+    pub fn with_args(
+        mut self,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self, ConfigError> {
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            let flag = match arg.strip_prefix("--") {
+                Some(flag) => flag,
+                None => continue,
+            };
+
+            let (name, value) = match flag.split_once('=') {
+                Some((name, value)) => (name.replace('-', "_"), value.to_string()),
+                None => {
+                    let name = flag.replace('-', "_");
+                    let value = match args.next_if(|next| !next.starts_with("--")) {
+                        Some(value) => value,
+                        None if KNOWN_KEYS.contains(&name.as_str()) => {
+                            return Err(ConfigError::MissingFlagValue(flag.to_string()))
+                        }
+                        None => "true".to_string(),
+                    };
+                    (name, value)
+                }
+            };
+
+            self.flags.insert(name, value);
+        }
+
+        Ok(self)
+    }
+
+    /// Builds the [`Config`] out of all the sources.
+    pub fn load(self) -> Result<Config, ConfigError> {
+        let mut values = self.files;
+        values.extend(self.env_vars);
+        values.extend(self.flags);
+
+        let get = |key: &'static str| values.get(key).cloned();
+        let require = |key: &'static str| get(key).ok_or(ConfigError::MissingSetting(key));
+
+        let client_id = require("client_id")?;
+        let client_secret = Token::new(require("client_secret")?);
+        let credentials = match (get("refresh_token"), get("code")) {
+            (Some(refresh_token), _) => Credentials::RefreshToken {
+                client_id,
+                client_secret,
+                refresh_token: refresh_token.into(),
+            },
+            (None, Some(code)) => Credentials::AuthorizationCode {
+                client_id,
+                client_secret,
+                code,
+                redirect_uri: require("redirect_uri")?,
+            },
+            (None, None) => Credentials::ClientCredentials {
+                client_id,
+                client_secret,
+                scope: get("scope").unwrap_or_else(|| "payments".to_string()),
+            },
+        };
+
+        let secrets = match get("key_id") {
+            Some(key_id) => {
+                let private_key = match (get("private_key"), get("private_key_path")) {
+                    (Some(private_key), _) => private_key.into_bytes(),
+                    (None, Some(path)) => {
+                        std::fs::read(&path).map_err(|source| ConfigError::Io {
+                            path: path.into(),
+                            source,
+                        })?
+                    }
+                    (None, None) => return Err(ConfigError::MissingSetting("private_key")),
+                };
+                Some(Secrets {
+                    key_id,
+                    private_key,
+                })
+            }
+            None => None,
+        };
+
+        let environment = match get("environment").as_deref() {
+            None | Some("sandbox") => Environment::Sandbox,
+            Some("live") => Environment::Live,
+            Some(other) => Url::parse(other)
+                .map(|url| Environment::from_single_url(&url))
+                .map_err(|_| ConfigError::InvalidEnvironment(other.to_string()))?,
+        };
+
+        Ok(Config {
+            credentials,
+            secrets,
+            environment,
+            values,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn flags_take_precedence_over_env_vars() {
+        let config = ConfigLoader::new()
+            .with_env_vars([
+                (
+                    "TRUELAYER_CLIENT_ID".to_string(),
+                    "env-client-id".to_string(),
+                ),
+                (
+                    "TRUELAYER_CLIENT_SECRET".to_string(),
+                    "env-secret".to_string(),
+                ),
+                (
+                    "TRUELAYER_RETURN_URI".to_string(),
+                    "https://env.uri".to_string(),
+                ),
+                ("PATH".to_string(), "/usr/bin".to_string()),
+            ])
+            .with_args(args(&[
+                "run",
+                "--client-id",
+                "flag-client-id",
+                "--return-uri=https://flag.uri",
+                "--environment=live",
+                "--verbose",
+                "--dry-run",
+            ]))
+            .unwrap()
+            .load()
+            .unwrap();
+
+        assert_eq!(config.credentials.client_id(), "flag-client-id");
+        assert_eq!(
+            config.credentials.client_secret().expose_secret(),
+            "env-secret"
+        );
+        assert!(matches!(
+            config.credentials,
+            Credentials::ClientCredentials { ref scope, .. } if scope == "payments"
+        ));
+        assert!(matches!(config.environment, Environment::Live));
+        assert_eq!(config.get("return_uri"), Some("https://flag.uri"));
+        assert_eq!(config.get("path"), None);
+        assert_eq!(config.get("verbose"), Some("true"));
+        assert_eq!(config.get("dry_run"), Some("true"));
+        assert!(config.secrets.is_none());
+    }
+
+    #[test]
+    fn env_vars_take_precedence_over_files() {
+        let path =
+            std::env::temp_dir().join(format!("truelayer-config-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"{
+                "client_id": "file-client-id",
+                "client_secret": "file-secret",
+                "refresh_token": "file-refresh-token",
+                "key_id": "file-key-id",
+                "private_key": "file-private-key",
+                "environment": "http://localhost:8080"
+            }"#,
+        )
+        .unwrap();
+
+        let config = ConfigLoader::new()
+            .with_file(&path)
+            .unwrap()
+            .with_env_vars([(
+                "TRUELAYER_CLIENT_ID".to_string(),
+                "env-client-id".to_string(),
+            )])
+            .load()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.credentials.client_id(), "env-client-id");
+        assert_eq!(
+            config.credentials.refresh_token().unwrap().expose_secret(),
+            "file-refresh-token"
+        );
+        let secrets = config.secrets.unwrap();
+        assert_eq!(secrets.key_id, "file-key-id");
+        assert_eq!(secrets.private_key, b"file-private-key");
+        assert_eq!(
+            config.environment.payments_url().as_str(),
+            "http://localhost:8080/"
+        );
+    }
+
+    #[test]
+    fn toml_files_are_supported_and_secrets_are_redacted() {
+        let path =
+            std::env::temp_dir().join(format!("truelayer-config-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"
+                client_id = "file-client-id"
+                client_secret = "file-secret"
+                return_uri = "https://file.uri"
+            "#,
+        )
+        .unwrap();
+
+        let config = ConfigLoader::new()
+            .with_file(&path)
+            .unwrap()
+            .load()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.credentials.client_id(), "file-client-id");
+        assert_eq!(config.get("return_uri"), Some("https://file.uri"));
+        let debug = format!("{:?}", config);
+        assert!(!debug.contains("file-secret"));
+        assert!(debug.contains("https://file.uri"));
+    }
+
+    #[test]
+    fn missing_settings_are_reported() {
+        assert!(matches!(
+            ConfigLoader::new().load(),
+            Err(ConfigError::MissingSetting("client_id"))
+        ));
+        assert!(matches!(
+            ConfigLoader::new().with_args(args(&["--client-id"])),
+            Err(ConfigError::MissingFlagValue(flag)) if flag == "client-id"
+        ));
+        assert!(matches!(
+            ConfigLoader::new().with_args(args(&["--client-id", "--client-secret=secret"])),
+            Err(ConfigError::MissingFlagValue(flag)) if flag == "client-id"
+        ));
+        assert!(matches!(
+            ConfigLoader::new()
+                .with_args(args(&[
+                    "--client-id=id",
+                    "--client-secret=secret",
+                    "--key-id=kid"
+                ]))
+                .unwrap()
+                .load(),
+            Err(ConfigError::MissingSetting("private_key"))
+        ));
+    }
+}
@@ -0,0 +1,66 @@
+//! Runtime notices about deprecated TrueLayer endpoints.
+//!
+//! TrueLayer may flag endpoints scheduled for removal with the `Deprecation` and `Sunset`
+//! response headers. The client logs a warning the first time it sees them for an endpoint and
+//! collects them in a report, available through
+//! [`TrueLayerClient::deprecations`](crate::client::TrueLayerClient::deprecations).
+
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Maximum number of distinct endpoints tracked, to keep memory bounded.
+const MAX_TRACKED_ENDPOINTS: usize = 256;
+
+/// Deprecation notice received from TrueLayer for an endpoint.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DeprecationNotice {
+    /// HTTP method of the request which received the notice.
+    pub method: String,
+    /// Path template of the endpoint which received the notice, with resource ids
+    /// replaced by `{id}`, e.g. `/payments/{id}`.
+    pub path: String,
+    /// Raw value of the `Deprecation` header, if present.
+    pub deprecation: Option<String>,
+    /// Date after which the endpoint may stop working, from the `Sunset` header.
+    pub sunset: Option<DateTime<Utc>>,
+    /// Link to the relevant documentation, from a `Link` header with `rel="deprecation"` or `rel="sunset"`.
+    pub link: Option<String>,
+    /// When the notice was last received.
+    pub last_seen_at: DateTime<Utc>,
+}
+
+/// Thread-safe collection of the deprecation notices received so far, shared by all the clients.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DeprecationRegistry {
+    notices: Arc<Mutex<HashMap<(String, String), DeprecationNotice>>>,
+}
+
+impl DeprecationRegistry {
+    /// Records a notice, returning `true` if this is the first one received for its endpoint.
+    pub(crate) fn record(&self, notice: DeprecationNotice) -> bool {
+        let mut notices = self.notices.lock().unwrap();
+        let key = (notice.method.clone(), notice.path.clone());
+
+        if let Some(existing) = notices.get_mut(&key) {
+            *existing = notice;
+            return false;
+        }
+
+        if notices.len() >= MAX_TRACKED_ENDPOINTS {
+            return false;
+        }
+
+        notices.insert(key, notice);
+        true
+    }
+
+    /// Returns all the notices received so far, sorted by endpoint.
+    pub(crate) fn snapshot(&self) -> Vec<DeprecationNotice> {
+        let mut notices: Vec<_> = self.notices.lock().unwrap().values().cloned().collect();
+        notices.sort_by(|a, b| (&a.path, &a.method).cmp(&(&b.path, &b.method)));
+        notices
+    }
+}
@@ -0,0 +1,27 @@
+//! Re-exports of the third party crates and types which appear in the public API of this crate.
+//!
+//! Prefer these re-exports over adding the same crates as direct dependencies when implementing
+//! customization hooks (e.g., retry policies), so that the versions always match the ones
+//! used internally by the client.
+//!
+//! The public API is limited to these crates, each for a specific reason:
+//! - `chrono`, for the timestamps of the models, and `uuid`, to generate idempotency keys;
+//! - `reqwest`, for [`Url`] and the HTTP client accepted by
+//!   [`with_http_client`](crate::client::TrueLayerClientBuilder::with_http_client);
+//! - `reqwest-middleware` and `task-local-extensions`, for the [`Middleware`] trait of
+//!   [`with_middleware`](crate::client::TrueLayerClientBuilder::with_middleware) and its [`Extensions`];
+//! - `retry-policies`, for the [`RetryPolicy`] trait of
+//!   [`with_retry_policy`](crate::client::TrueLayerClientBuilder::with_retry_policy).
+//!
+//! Other dependencies are wrapped instead, e.g. custom DNS resolvers implement
+//! [`DnsResolver`](crate::transport::DnsResolver) rather than the resolver trait of reqwest.
+
+pub use chrono;
+pub use reqwest;
+pub use reqwest::Url;
+pub use reqwest_middleware;
+pub use reqwest_middleware::{Middleware, Next};
+pub use retry_policies;
+pub use retry_policies::{policies::ExponentialBackoff, RetryDecision, RetryPolicy};
+pub use task_local_extensions::Extensions;
+pub use uuid::Uuid;
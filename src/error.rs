@@ -1,9 +1,17 @@
 //! Standard errors used by all functions in the crate.
 
-use std::{collections::HashMap, fmt};
+use crate::{
+    apis::payments::{flow::FlowAction, PaymentStatus},
+    client::ApiGroup,
+};
+use chrono::{DateTime, Utc};
+use std::{collections::HashMap, error::Error as StdError, fmt, io, time::Duration};
 
 /// Error collecting all possible failures of the TrueLayer client.
+///
+/// New variants can be added in any release, so matches on this enum need a wildcard arm.
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// Reqwest error.
     #[error("HTTP error: {0}")]
@@ -11,16 +19,143 @@ pub enum Error {
     /// Error returned by a TrueLayer API endpoint.
     #[error("{0}")]
     ApiError(#[from] ApiError),
+    /// An idempotency key was reused with a different request body, or while the request
+    /// which originally used it was still being processed.
+    ///
+    /// The original resource is left untouched. If it is a payment or a payout recently created
+    /// by the same process, it can be retrieved with
+    /// [`PaymentsApi::get_original`](crate::apis::payments::PaymentsApi::get_original)
+    /// or [`PayoutsApi::get_original`](crate::apis::payouts::PayoutsApi::get_original): the ids of
+    /// the created resources are only remembered in memory, and only for payments and payouts.
+    #[error("Idempotency key {idempotency_key} already used for a different request: {source}")]
+    IdempotencyConflict {
+        idempotency_key: String,
+        /// Trace ID of the rejected request, which TrueLayer support can use to investigate
+        /// the conflict.
+        trace_id: Option<String>,
+        #[source]
+        source: ApiError,
+    },
+    /// The request was not sent because the access token lacks the scope needed by its API,
+    /// according to the scopes configured with
+    /// [`with_scopes`](crate::client::TrueLayerClientBuilder::with_scopes).
+    #[error("Access token lacks the scope needed by the {api_group:?} APIs: expected one of {required:?}, granted {granted:?}")]
+    MissingScope {
+        api_group: ApiGroup,
+        /// Scopes any of which grants access to the API.
+        required: &'static [&'static str],
+        granted: Vec<String>,
+    },
+    /// The request was rejected with `429 Too Many Requests`.
+    ///
+    /// Idempotent requests can be retried automatically by configuring a
+    /// [`RateLimitPolicy`](crate::rate_limits::RateLimitPolicy).
+    #[error("Rate limited by TrueLayer, retry after {retry_after:?}: {source}")]
+    RateLimited {
+        /// Delay requested by TrueLayer with the `Retry-After` header, if any.
+        retry_after: Option<Duration>,
+        #[source]
+        source: ApiError,
+    },
+    /// An action of the authorization flow of a payment was rejected because the flow had already
+    /// moved past it, e.g. because the same action was submitted concurrently from another browser tab.
+    ///
+    /// `current` is the status of the payment retrieved right after the rejection, which UI backends
+    /// can use to resync with the actual next action instead of showing an error.
+    #[error("{action:?} is no longer expected for payment {payment_id}: {source}")]
+    StaleAuthorizationStep {
+        payment_id: String,
+        action: FlowAction,
+        current: Box<PaymentStatus>,
+        #[source]
+        source: ApiError,
+    },
+    /// A request was not sent because its [`RequestOptions`](crate::request::RequestOptions)
+    /// set a header managed by the library, like `Idempotency-Key` or `Authorization`.
+    #[error("Header {0} is managed by the library and cannot be set per call")]
+    ReservedHeader(String),
+    /// A payment needed by the operation does not exist.
+    #[error("Payment {payment_id} not found")]
+    PaymentNotFound { payment_id: String },
+    /// A payment needed by the operation failed instead of settling, e.g. the payment a payout
+    /// was scheduled after with [`PayoutsApi::schedule_after_settlement`](crate::apis::payouts::PayoutsApi::schedule_after_settlement).
+    #[error("Payment {payment_id} failed: {failure_reason}")]
+    PaymentFailed {
+        payment_id: String,
+        failure_reason: String,
+    },
+    /// A payout was not sent because it breaks the
+    /// [`Guardrails`](crate::guardrails::Guardrails) of the client.
+    #[error("Payout blocked by guardrails: {0}")]
+    PayoutBlocked(#[from] crate::guardrails::GuardrailViolation),
+    /// A mutating call to the Live environment was not sent because it is not allowed by the
+    /// [`Confirmation`](crate::safeguard::Confirmation) of the client.
+    #[error("Live call blocked by safeguard: {0}")]
+    LiveCallBlocked(#[from] crate::safeguard::SafeguardViolation),
     /// Error building request signature.
     ///
     /// Read more about signing here: <https://docs.truelayer.com/docs/signing-your-requests>
     #[error("Error signing request: {0}")]
     SigningError(#[from] truelayer_signing::Error),
+    /// A signed request was rejected by TrueLayer because of its signature,
+    /// usually because of a misconfigured signing key.
+    ///
+    /// Read more about signing here: <https://docs.truelayer.com/docs/signing-your-requests>
+    #[error("Request signature rejected, {reason}: {source}")]
+    SignatureRejected {
+        reason: SignatureRejection,
+        #[source]
+        source: ApiError,
+    },
+    /// Error verifying the signature of an incoming webhook.
+    ///
+    /// Read more about webhook signatures here: <https://docs.truelayer.com/docs/webhooks>
+    #[error("Error verifying webhook: {0}")]
+    WebhookVerificationError(#[from] WebhookVerificationError),
+    /// A response body exceeded the size configured with
+    /// [`TransportConfig::with_max_response_size`](crate::transport::TransportConfig::with_max_response_size),
+    /// or a provider asset exceeded the size configured with
+    /// [`ProviderAssetPrefetcher::with_max_asset_size`](crate::apis::payments::assets::ProviderAssetPrefetcher::with_max_asset_size).
+    #[error("Response body larger than {limit} bytes")]
+    ResponseTooLarge { limit: u64 },
+    /// A response body was not received within the time configured with
+    /// [`TransportConfig::with_body_read_timeout`](crate::transport::TransportConfig::with_body_read_timeout).
+    #[error("Response body not received within {timeout:?}")]
+    ResponseBodyTimeout { timeout: Duration },
+    /// A request body did not conform to the schema of its endpoint, and was not sent.
+    ///
+    /// Only returned when enabled with
+    /// [`with_schema_validation`](crate::client::TrueLayerClientBuilder::with_schema_validation).
+    #[cfg(feature = "schema-validation")]
+    #[error("{0}")]
+    SchemaValidation(#[from] crate::schema_validation::SchemaValidationError),
+    /// A provider asset URL points outside of the hosts allowed by
+    /// [`ProviderAssetPrefetcher::with_allowed_hosts`](crate::apis::payments::assets::ProviderAssetPrefetcher::with_allowed_hosts),
+    /// and was not downloaded.
+    #[error("Provider asset not hosted by TrueLayer: {0}")]
+    UntrustedAssetHost(String),
     /// Catch-all variant for unexpected errors.
     #[error(transparent)]
     Other(anyhow::Error),
 }
 
+impl Error {
+    /// Classifies failures to reach TrueLayer at the transport level, e.g. to route alerts
+    /// to the team owning the local network rather than to the one in touch with TrueLayer.
+    ///
+    /// Returns `None` for errors which are not caused by the transport, like API errors.
+    pub fn transport_error_kind(&self) -> Option<TransportErrorKind> {
+        match self {
+            Error::HttpError(e) => TransportErrorKind::classify(e),
+            Error::ResponseBodyTimeout { .. } => Some(TransportErrorKind::ReadTimeout),
+            Error::Other(e) => e
+                .downcast_ref::<reqwest::Error>()
+                .and_then(TransportErrorKind::classify),
+            _ => None,
+        }
+    }
+}
+
 impl From<reqwest_middleware::Error> for Error {
     fn from(e: reqwest_middleware::Error) -> Self {
         match e {
@@ -38,6 +173,131 @@ impl From<Error> for reqwest_middleware::Error {
     }
 }
 
+/// Longest difference between the local clock and the clock of TrueLayer before the rejection
+/// of a signed request is attributed to [`SignatureRejection::ClockSkew`].
+pub const MAX_CLOCK_SKEW: Duration = Duration::from_secs(5 * 60);
+
+/// Reason why TrueLayer rejected the signature of a request, see [`Error::SignatureRejected`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum SignatureRejection {
+    /// The key id of the signature does not match any public key uploaded to the TrueLayer console.
+    UnknownKeyId,
+    /// The signature does not match the request, usually because the private key does not match
+    /// the public key uploaded to the TrueLayer console with the same key id.
+    InvalidSignature,
+    /// The local clock differs from the clock of TrueLayer by more than [`MAX_CLOCK_SKEW`].
+    ClockSkew {
+        /// How far the local clock is ahead of the clock of TrueLayer, negative if behind.
+        skew: chrono::Duration,
+    },
+}
+
+impl fmt::Display for SignatureRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureRejection::UnknownKeyId => write!(f, "unknown signing key id"),
+            SignatureRejection::InvalidSignature => {
+                write!(f, "signing key does not match the key id")
+            }
+            SignatureRejection::ClockSkew { skew } => write!(
+                f,
+                "local clock is {}s {} the clock of TrueLayer",
+                skew.num_seconds().abs(),
+                if *skew < chrono::Duration::zero() {
+                    "behind"
+                } else {
+                    "ahead of"
+                }
+            ),
+        }
+    }
+}
+
+/// Kind of failure which prevented a request from reaching TrueLayer or its response from coming back,
+/// returned by [`Error::transport_error_kind`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum TransportErrorKind {
+    /// The host name of TrueLayer could not be resolved.
+    Dns,
+    /// The TLS handshake failed, e.g. because a proxy intercepts TLS with an untrusted certificate.
+    TlsHandshake,
+    /// No connection could be established within the connect timeout.
+    ConnectTimeout,
+    /// The connection was refused by the remote host.
+    ConnectionRefused,
+    /// The connection was established, but the response was not received in time.
+    ReadTimeout,
+    /// The connection was reset or closed before the response was received.
+    ConnectionReset,
+    /// The configured HTTP proxy rejected or failed the request.
+    Proxy,
+    /// Any other transport failure.
+    Other,
+}
+
+impl TransportErrorKind {
+    /// Returns `true` if this kind of failure usually originates in the local network
+    /// or infrastructure (DNS, proxies, TLS interception) rather than at TrueLayer.
+    pub fn is_local(&self) -> bool {
+        matches!(
+            self,
+            TransportErrorKind::Dns | TransportErrorKind::TlsHandshake | TransportErrorKind::Proxy
+        )
+    }
+
+    fn classify(e: &reqwest::Error) -> Option<TransportErrorKind> {
+        if e.is_builder() || e.is_status() || e.is_decode() || e.is_redirect() {
+            return None;
+        }
+
+        // Neither reqwest nor hyper expose the cause of connection failures as typed errors,
+        // so look for I/O errors and well-known messages along the chain of sources.
+        // The error itself is skipped, as its message includes the URL of the request.
+        let mut source = e.source();
+        while let Some(error) = source {
+            if let Some(io_error) = error.downcast_ref::<io::Error>() {
+                match io_error.kind() {
+                    io::ErrorKind::ConnectionRefused => {
+                        return Some(TransportErrorKind::ConnectionRefused)
+                    }
+                    io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::UnexpectedEof => {
+                        return Some(TransportErrorKind::ConnectionReset)
+                    }
+                    _ => {}
+                }
+            }
+
+            let message = error.to_string().to_lowercase();
+            if message.contains("dns error") || message.contains("failed to lookup address") {
+                return Some(TransportErrorKind::Dns);
+            }
+            if message.contains("proxy") || message.contains("tunnel") {
+                return Some(TransportErrorKind::Proxy);
+            }
+            if message.contains("certificate")
+                || message.contains("handshake")
+                || message.contains("tls")
+                || message.contains("ssl")
+            {
+                return Some(TransportErrorKind::TlsHandshake);
+            }
+
+            source = error.source();
+        }
+
+        Some(match (e.is_timeout(), e.is_connect()) {
+            (true, true) => TransportErrorKind::ConnectTimeout,
+            (true, false) => TransportErrorKind::ReadTimeout,
+            _ => TransportErrorKind::Other,
+        })
+    }
+}
+
 /// TrueLayer HTTP APIs error.
 #[derive(thiserror::Error, Debug)]
 pub struct ApiError {
@@ -59,6 +319,46 @@ pub struct ApiError {
     pub errors: HashMap<String, Vec<String>>,
 }
 
+impl ApiError {
+    /// Class of the error, i.e. the fragment of its `type` URL
+    /// (e.g. `invalid-parameters`), if any.
+    pub fn kind(&self) -> Option<&str> {
+        self.r#type
+            .split_once('#')
+            .map(|(_, kind)| kind)
+            .filter(|kind| !kind.is_empty())
+    }
+
+    /// Validation failures of the given field, empty if the field is valid.
+    pub fn field_errors(&self, field: &str) -> &[String] {
+        self.errors.get(field).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns `true` if the request was rejected because of invalid parameters,
+    /// which are listed in [`errors`](ApiError::errors).
+    pub fn is_invalid_parameters(&self) -> bool {
+        match self.kind() {
+            Some(kind) => kind == "invalid-parameters",
+            None => self.status == 400 && !self.errors.is_empty(),
+        }
+    }
+
+    /// Returns `true` if the request was rejected because of a missing, invalid or expired access token.
+    pub fn is_unauthenticated(&self) -> bool {
+        self.status == 401 || self.kind() == Some("unauthenticated")
+    }
+
+    /// Returns `true` if the credentials are not allowed to perform the request.
+    pub fn is_forbidden(&self) -> bool {
+        self.status == 403 || self.kind() == Some("forbidden")
+    }
+
+    /// Returns `true` if the requested resource does not exist.
+    pub fn is_not_found(&self) -> bool {
+        self.status == 404 || self.kind() == Some("not-found")
+    }
+}
+
 impl fmt::Display for ApiError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -85,3 +385,102 @@ impl fmt::Display for ApiError {
         Ok(())
     }
 }
+
+/// Reason why the signature of an incoming webhook could not be verified.
+///
+/// Use [`http_status`](WebhookVerificationError::http_status) to pick the response to send back,
+/// and [`is_suspicious`](WebhookVerificationError::is_suspicious) to decide whether to raise an alert.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum WebhookVerificationError {
+    /// A required header is missing or has an invalid value.
+    #[error("Missing or invalid {0} header")]
+    MissingHeader(String),
+    /// The signature is not a valid detached JWS.
+    #[error("Malformed JWS: {0}")]
+    MalformedJws(anyhow::Error),
+    /// The JWS references a set of keys not published by TrueLayer in its `jku` header.
+    #[error("Untrusted jku in JWS header: {0}")]
+    UntrustedJku(String),
+    /// The JWS is signed with a key id which TrueLayer does not publish.
+    ///
+    /// This usually points to a key rotation issue or to a forged webhook.
+    #[error("Webhook signed with unknown key id: {0}")]
+    UnknownKid(String),
+    /// The signature does not match the content of the webhook.
+    #[error("Signature mismatch: {0}")]
+    SignatureMismatch(anyhow::Error),
+    /// The webhook timestamp is further from the current time than the configured tolerance,
+    /// which might indicate a replayed webhook.
+    #[error("Webhook timestamp {timestamp} is outside the tolerance of {tolerance:?}")]
+    StaleTimestamp {
+        timestamp: DateTime<Utc>,
+        tolerance: Duration,
+    },
+    /// The body does not match the digest sent in the given header.
+    #[error("Body does not match the {0} header")]
+    BodyDigestMismatch(String),
+}
+
+impl WebhookVerificationError {
+    /// HTTP status code to respond with when rejecting the webhook:
+    /// `400 Bad Request` for malformed requests, `401 Unauthorized` otherwise.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            WebhookVerificationError::MissingHeader(_)
+            | WebhookVerificationError::MalformedJws(_) => 400,
+            _ => 401,
+        }
+    }
+
+    /// Whether this failure hints at a forged, tampered or replayed webhook rather than
+    /// at a malformed request, and should therefore be alerted on.
+    pub fn is_suspicious(&self) -> bool {
+        !matches!(
+            self,
+            WebhookVerificationError::MissingHeader(_) | WebhookVerificationError::MalformedJws(_)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn refused_connections_are_classified() {
+        // Bind a port, then free it so that nothing listens on it
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let err = reqwest::get(format!("http://{}", addr)).await.unwrap_err();
+
+        let kind = Error::HttpError(err).transport_error_kind();
+        assert_eq!(kind, Some(TransportErrorKind::ConnectionRefused));
+        assert!(!kind.unwrap().is_local());
+    }
+
+    #[tokio::test]
+    async fn slow_responses_are_classified_as_read_timeouts() {
+        let mock_server = MockServer::start().await;
+        Mock::given(wiremock::matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(1)))
+            .mount(&mock_server)
+            .await;
+
+        let err = reqwest::Client::new()
+            .get(mock_server.uri())
+            .timeout(Duration::from_millis(50))
+            .send()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            Error::HttpError(err).transport_error_kind(),
+            Some(TransportErrorKind::ReadTimeout)
+        );
+    }
+}
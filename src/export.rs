@@ -0,0 +1,443 @@
+//! Export of merchant account transactions to formats understood by accounting systems.
+//!
+//! Transactions returned by
+//! [`MerchantAccountsApi::list_transactions`](crate::apis::merchant_accounts::MerchantAccountsApi::list_transactions)
+//! are first flattened into [`LedgerEntry`]s, which can be written as CSV with [`to_csv`]
+//! or as an OFX bank statement with [`to_ofx`], or mapped onto any other format (e.g., CAMT.053).
+//!
+//! This module is only available with the `export` feature.
+
+use crate::apis::{
+    merchant_accounts::{
+        MerchantAccount, Transaction, TransactionPayinStatus, TransactionPayoutStatus,
+        TransactionType,
+    },
+    payments::Currency,
+    payouts::PayoutBeneficiary,
+};
+use chrono::{DateTime, Utc};
+use std::{fmt::Write as _, io};
+
+/// Direction of the money movement of a [`LedgerEntry`], from the point of view of the merchant account.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Direction {
+    Credit,
+    Debit,
+}
+
+/// Flat, format-agnostic view of a merchant account [`Transaction`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LedgerEntry {
+    /// Id of the merchant account transaction.
+    pub transaction_id: String,
+    /// Type of the transaction, e.g. `merchant_account_payment` or `payout`.
+    pub kind: &'static str,
+    /// Status of the transaction, e.g. `settled` or `pending`.
+    pub status: &'static str,
+    /// When the transaction settled, or when it was created if it's still pending.
+    pub booked_at: DateTime<Utc>,
+    pub direction: Direction,
+    pub amount_in_minor: u64,
+    pub currency: Currency,
+    /// Name of the other party of the transaction, if known.
+    pub counterparty: Option<String>,
+    /// Reference of the transaction, if any.
+    pub reference: Option<String>,
+    /// Id of the payment, payout or refund which originated the transaction.
+    pub resource_id: String,
+}
+
+impl LedgerEntry {
+    /// Amount in major units with two decimals, negative for debits (e.g. `-12.34`).
+    pub fn signed_amount(&self) -> String {
+        match self.direction {
+            Direction::Credit => format_minor(self.amount_in_minor),
+            Direction::Debit => format!("-{}", format_minor(self.amount_in_minor)),
+        }
+    }
+}
+
+impl From<&Transaction> for LedgerEntry {
+    fn from(transaction: &Transaction) -> Self {
+        let entry = |kind, status, booked_at, direction, counterparty, reference, resource_id| {
+            LedgerEntry {
+                transaction_id: transaction.id.clone(),
+                kind,
+                status,
+                booked_at,
+                direction,
+                amount_in_minor: transaction.amount_in_minor,
+                currency: transaction.currency.clone(),
+                counterparty,
+                reference,
+                resource_id,
+            }
+        };
+
+        match &transaction.r#type {
+            TransactionType::MerchantAccountPayment {
+                status,
+                settled_at,
+                payment_source,
+                payment_id,
+            } => entry(
+                "merchant_account_payment",
+                payin_status(status),
+                *settled_at,
+                Direction::Credit,
+                payment_source.account_holder_name.clone(),
+                None,
+                payment_id.clone(),
+            ),
+            TransactionType::ExternalPayment {
+                status,
+                settled_at,
+                remitter,
+            } => entry(
+                "external_payment",
+                payin_status(status),
+                *settled_at,
+                Direction::Credit,
+                remitter.account_holder_name.clone(),
+                None,
+                transaction.id.clone(),
+            ),
+            TransactionType::Payout {
+                status,
+                created_at,
+                beneficiary,
+                payout_id,
+                ..
+            } => {
+                let (counterparty, reference) = beneficiary_details(beneficiary);
+                entry(
+                    "payout",
+                    payout_status(status),
+                    payout_booked_at(status, *created_at),
+                    Direction::Debit,
+                    counterparty,
+                    reference,
+                    payout_id.clone(),
+                )
+            }
+            TransactionType::Refund {
+                status,
+                created_at,
+                beneficiary,
+                refund_id,
+                ..
+            } => {
+                let (counterparty, reference) = beneficiary_details(beneficiary);
+                entry(
+                    "refund",
+                    payout_status(status),
+                    payout_booked_at(status, *created_at),
+                    Direction::Debit,
+                    counterparty,
+                    reference,
+                    refund_id.clone(),
+                )
+            }
+        }
+    }
+}
+
+fn payin_status(status: &TransactionPayinStatus) -> &'static str {
+    match status {
+        TransactionPayinStatus::Settled => "settled",
+    }
+}
+
+fn payout_status(status: &TransactionPayoutStatus) -> &'static str {
+    match status {
+        TransactionPayoutStatus::Pending => "pending",
+        TransactionPayoutStatus::Settled { .. } => "settled",
+    }
+}
+
+fn payout_booked_at(status: &TransactionPayoutStatus, created_at: DateTime<Utc>) -> DateTime<Utc> {
+    match status {
+        TransactionPayoutStatus::Pending => created_at,
+        TransactionPayoutStatus::Settled { settled_at } => *settled_at,
+    }
+}
+
+fn beneficiary_details(beneficiary: &PayoutBeneficiary) -> (Option<String>, Option<String>) {
+    match beneficiary {
+        PayoutBeneficiary::ExternalAccount {
+            account_holder_name,
+            reference,
+            ..
+        } => (Some(account_holder_name.clone()), Some(reference.clone())),
+        PayoutBeneficiary::PaymentSource { reference, .. }
+        | PayoutBeneficiary::BusinessAccount { reference } => (None, Some(reference.clone())),
+    }
+}
+
+/// Writes the given transactions as CSV (RFC 4180), with a header row.
+///
+/// Columns are `transaction_id`, `type`, `status`, `booked_at` (RFC 3339), `amount` (signed, in major units),
+/// `currency`, `counterparty`, `reference` and `resource_id`.
+///
+/// Text fields starting with `=`, `+`, `-`, `@`, a tab or a carriage return are prefixed with `'`,
+/// so that spreadsheets do not evaluate them as formulas.
+pub fn to_csv<'a, W: io::Write>(
+    transactions: impl IntoIterator<Item = &'a Transaction>,
+    mut writer: W,
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        "transaction_id,type,status,booked_at,amount,currency,counterparty,reference,resource_id"
+    )?;
+
+    for entry in transactions.into_iter().map(LedgerEntry::from) {
+        let fields = [
+            entry.transaction_id.clone(),
+            entry.kind.to_string(),
+            entry.status.to_string(),
+            entry.booked_at.to_rfc3339(),
+            entry.signed_amount(),
+            entry.currency.to_string(),
+            entry.counterparty.clone().unwrap_or_default(),
+            entry.reference.clone().unwrap_or_default(),
+            entry.resource_id.clone(),
+        ];
+        let row: Vec<_> = fields.iter().map(|f| escape_csv(f)).collect();
+        writeln!(writer, "{}", row.join(","))?;
+    }
+
+    writer.flush()
+}
+
+/// Quotes a CSV field when needed, and neutralizes the ones spreadsheets would evaluate as formulas
+/// (e.g. a reference like `=HYPERLINK(...)`) by prefixing them with `'`. Amounts are left untouched.
+fn escape_csv(field: &str) -> String {
+    let field =
+        if field.starts_with(['=', '+', '-', '@', '\t', '\r']) && field.parse::<f64>().is_err() {
+            format!("'{}", field)
+        } else {
+            field.to_string()
+        };
+
+    if field.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+/// Builds an OFX 2.2 bank statement of a merchant account, covering the period from `from` to `to`.
+///
+/// The account is identified by its sort code and account number if it has them, or else by
+/// its IBAN (with the merchant account id as bank id). The ledger balance is the current balance
+/// of the account. Each transaction is reported with its id as `FITID`, so that accounting systems
+/// can deduplicate transactions across overlapping statements.
+pub fn to_ofx<'a>(
+    account: &MerchantAccount,
+    transactions: impl IntoIterator<Item = &'a Transaction>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> String {
+    let (bank_id, account_id) = match (account.sort_code_account_number(), account.iban()) {
+        (Some((sort_code, account_number)), _) => (sort_code, account_number),
+        (None, Some(iban)) => (account.id.as_str(), iban),
+        (None, None) => (account.id.as_str(), account.id.as_str()),
+    };
+
+    let mut ofx = String::new();
+    ofx.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    ofx.push_str("<?OFX OFXHEADER=\"200\" VERSION=\"220\" SECURITY=\"NONE\" OLDFILEUID=\"NONE\" NEWFILEUID=\"NONE\"?>\n");
+    ofx.push_str("<OFX><BANKMSGSRSV1><STMTTRNRS><TRNUID>0</TRNUID>");
+    ofx.push_str("<STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS><STMTRS>");
+    let _ = write!(
+        ofx,
+        "<CURDEF>{}</CURDEF><BANKACCTFROM><BANKID>{}</BANKID><ACCTID>{}</ACCTID><ACCTTYPE>CHECKING</ACCTTYPE></BANKACCTFROM>",
+        account.currency,
+        escape_xml(bank_id),
+        escape_xml(account_id)
+    );
+    let _ = write!(
+        ofx,
+        "<BANKTRANLIST><DTSTART>{}</DTSTART><DTEND>{}</DTEND>",
+        ofx_date(from),
+        ofx_date(to)
+    );
+
+    for entry in transactions.into_iter().map(LedgerEntry::from) {
+        let trn_type = match entry.direction {
+            Direction::Credit => "CREDIT",
+            Direction::Debit => "DEBIT",
+        };
+        let _ = write!(
+            ofx,
+            "<STMTTRN><TRNTYPE>{}</TRNTYPE><DTPOSTED>{}</DTPOSTED><TRNAMT>{}</TRNAMT><FITID>{}</FITID>",
+            trn_type,
+            ofx_date(entry.booked_at),
+            entry.signed_amount(),
+            escape_xml(&entry.transaction_id)
+        );
+        if let Some(counterparty) = &entry.counterparty {
+            let _ = write!(ofx, "<NAME>{}</NAME>", escape_xml(counterparty));
+        }
+        if let Some(reference) = &entry.reference {
+            let _ = write!(ofx, "<MEMO>{}</MEMO>", escape_xml(reference));
+        }
+        ofx.push_str("</STMTTRN>");
+    }
+
+    let _ = write!(
+        ofx,
+        "</BANKTRANLIST><LEDGERBAL><BALAMT>{}</BALAMT><DTASOF>{}</DTASOF></LEDGERBAL>",
+        format_minor(account.current_balance_in_minor),
+        ofx_date(to)
+    );
+    ofx.push_str("</STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>\n");
+
+    ofx
+}
+
+/// Formats an amount in minor units as major units with two decimals,
+/// which is the exponent of all the currencies supported by TrueLayer.
+fn format_minor(amount_in_minor: u64) -> String {
+    format!("{}.{:02}", amount_in_minor / 100, amount_in_minor % 100)
+}
+
+fn ofx_date(date: DateTime<Utc>) -> String {
+    date.format("%Y%m%d%H%M%S.000[0:GMT]").to_string()
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::{
+        merchant_accounts::TransactionPayoutContextCode,
+        payments::{AccountIdentifier, PaymentSource},
+    };
+    use chrono::TimeZone;
+
+    fn transactions() -> Vec<Transaction> {
+        let at = |minute| Utc.with_ymd_and_hms(2022, 4, 1, 0, minute, 0).unwrap();
+
+        vec![
+            Transaction {
+                id: "transaction-1".into(),
+                currency: Currency::Gbp,
+                amount_in_minor: 1050,
+                r#type: TransactionType::MerchantAccountPayment {
+                    status: TransactionPayinStatus::Settled,
+                    settled_at: at(1),
+                    payment_source: PaymentSource {
+                        id: "payment-source-id".into(),
+                        user_id: None,
+                        account_identifiers: vec![],
+                        account_holder_name: Some("Holder, Mr.".into()),
+                    },
+                    payment_id: "payment-id".into(),
+                },
+            },
+            Transaction {
+                id: "transaction-2".into(),
+                currency: Currency::Gbp,
+                amount_in_minor: 7,
+                r#type: TransactionType::Payout {
+                    status: TransactionPayoutStatus::Pending,
+                    created_at: at(2),
+                    beneficiary: PayoutBeneficiary::ExternalAccount {
+                        account_holder_name: "Smith & Sons".into(),
+                        account_identifier: AccountIdentifier::Iban {
+                            iban: "GB33BUKB20201555555555".into(),
+                        },
+                        reference: "Invoice \"42\"".into(),
+                    },
+                    context_code: TransactionPayoutContextCode::Withdrawal,
+                    payout_id: "payout-id".into(),
+                },
+            },
+        ]
+    }
+
+    #[test]
+    fn ledger_entries() {
+        let entries: Vec<_> = transactions().iter().map(LedgerEntry::from).collect();
+
+        assert_eq!(entries[0].direction, Direction::Credit);
+        assert_eq!(entries[0].signed_amount(), "10.50");
+        assert_eq!(entries[0].resource_id, "payment-id");
+        assert_eq!(entries[1].direction, Direction::Debit);
+        assert_eq!(entries[1].signed_amount(), "-0.07");
+        assert_eq!(entries[1].status, "pending");
+        assert_eq!(
+            entries[1].booked_at,
+            Utc.with_ymd_and_hms(2022, 4, 1, 0, 2, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let mut out = Vec::new();
+        to_csv(&transactions(), &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "transaction_id,type,status,booked_at,amount,currency,counterparty,reference,resource_id\n\
+             transaction-1,merchant_account_payment,settled,2022-04-01T00:01:00+00:00,10.50,GBP,\"Holder, Mr.\",,payment-id\n\
+             transaction-2,payout,pending,2022-04-01T00:02:00+00:00,-0.07,GBP,Smith & Sons,\"Invoice \"\"42\"\"\",payout-id\n"
+        );
+    }
+
+    #[test]
+    fn csv_formulas_are_neutralized() {
+        assert_eq!(
+            escape_csv("=HYPERLINK(\"x\")"),
+            "\"'=HYPERLINK(\"\"x\"\")\""
+        );
+        assert_eq!(escape_csv("+44 20"), "'+44 20");
+        assert_eq!(escape_csv("-1+1"), "'-1+1");
+        assert_eq!(escape_csv("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(escape_csv("\tcmd"), "'\tcmd");
+        assert_eq!(escape_csv("\rcmd"), "\"'\rcmd\"");
+        assert_eq!(escape_csv("-0.07"), "-0.07");
+        assert_eq!(escape_csv("Invoice 42"), "Invoice 42");
+    }
+
+    #[test]
+    fn ofx() {
+        let account = MerchantAccount {
+            id: "merchant-account-id".into(),
+            currency: Currency::Gbp,
+            account_identifiers: vec![AccountIdentifier::SortCodeAccountNumber {
+                sort_code: "040668".into(),
+                account_number: "00000871".into(),
+            }],
+            available_balance_in_minor: 100,
+            current_balance_in_minor: 12345,
+            account_holder_name: "Merchant".into(),
+        };
+        let from = Utc.with_ymd_and_hms(2022, 4, 1, 0, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2022, 4, 2, 0, 0, 0).unwrap();
+
+        let ofx = to_ofx(&account, &transactions(), from, to);
+
+        assert!(ofx.contains(
+            "<CURDEF>GBP</CURDEF><BANKACCTFROM><BANKID>040668</BANKID><ACCTID>00000871</ACCTID>"
+        ));
+        assert!(ofx.contains("<DTSTART>20220401000000.000[0:GMT]</DTSTART>"));
+        assert!(ofx.contains(
+            "<STMTTRN><TRNTYPE>CREDIT</TRNTYPE><DTPOSTED>20220401000100.000[0:GMT]</DTPOSTED>\
+             <TRNAMT>10.50</TRNAMT><FITID>transaction-1</FITID><NAME>Holder, Mr.</NAME></STMTTRN>"
+        ));
+        assert!(ofx.contains(
+            "<TRNAMT>-0.07</TRNAMT><FITID>transaction-2</FITID>\
+             <NAME>Smith &amp; Sons</NAME><MEMO>Invoice &quot;42&quot;</MEMO>"
+        ));
+        assert!(ofx.contains("<LEDGERBAL><BALAMT>123.45</BALAMT>"));
+    }
+}
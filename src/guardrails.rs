@@ -0,0 +1,429 @@
+//! Client-side limits on the payouts sent by automated systems.
+//!
+//! [`Guardrails`] attached to a client with
+//! [`with_payout_guardrails`](crate::client::TrueLayerClientBuilder::with_payout_guardrails)
+//! are checked before every payout is sent: payouts breaking any of the rules are not sent and fail
+//! with [`Error::PayoutBlocked`](crate::Error::PayoutBlocked). This is a safety net against bugs
+//! in the systems deciding the payouts, not a replacement for the controls configured on TrueLayer.
+//!
+//! ```rust
+//! # use truelayer_rust::{
+//! #     apis::{auth::Credentials, payments::Currency, payouts::PayoutBeneficiaryType},
+//! #     guardrails::Guardrails,
+//! #     TrueLayerClient,
+//! # };
+//! let guardrails = Guardrails::new()
+//!     .with_allowed_currencies([Currency::Gbp, Currency::Eur])
+//!     .with_allowed_beneficiary_types([PayoutBeneficiaryType::PaymentSource])
+//!     .with_max_amount(Currency::Gbp, 500_00)
+//!     .with_max_daily_total(Currency::Gbp, 10_000_00)
+//!     .on_block(|blocked| tracing::error!(violation = %blocked.violation, "Payout blocked"));
+//!
+//! let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
+//!     client_id: "some-client-id".into(),
+//!     client_secret: "some-client-secret".into(),
+//!     scope: "payments".into(),
+//! })
+//! .with_payout_guardrails(guardrails)
+//! .build();
+//! ```
+
+use crate::{
+    amounts::checked_add_minor,
+    apis::{
+        payments::Currency,
+        payouts::{CreatePayoutRequest, PayoutBeneficiaryType},
+    },
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Debug, Formatter},
+    sync::{Arc, Mutex},
+};
+
+/// Rule of the [`Guardrails`] broken by a payout.
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum GuardrailViolation {
+    #[error("Payouts in {0} are not allowed")]
+    CurrencyNotAllowed(Currency),
+    #[error("Payouts to {0:?} beneficiaries are not allowed")]
+    BeneficiaryTypeNotAllowed(PayoutBeneficiaryType),
+    #[error("Payout of {amount_in_minor} exceeds the maximum of {max_in_minor} {currency}")]
+    AmountTooLarge {
+        currency: Currency,
+        amount_in_minor: u64,
+        max_in_minor: u64,
+    },
+    #[error("Payout of {amount_in_minor} would bring the total of the day to more than {max_in_minor} {currency}, {total_in_minor} already paid out")]
+    DailyTotalExceeded {
+        currency: Currency,
+        amount_in_minor: u64,
+        /// Total of the payouts already sent during the current day (UTC).
+        total_in_minor: u64,
+        max_in_minor: u64,
+    },
+}
+
+/// Audit event emitted when [`Guardrails`] block a payout.
+#[derive(Debug, Clone)]
+pub struct BlockedPayout {
+    pub merchant_account_id: String,
+    pub amount_in_minor: u64,
+    pub currency: Currency,
+    pub beneficiary_type: PayoutBeneficiaryType,
+    pub violation: GuardrailViolation,
+    pub blocked_at: DateTime<Utc>,
+}
+
+/// Rules checked before sending each payout. No rule is enforced by default.
+///
+/// Daily totals are tracked in memory from the first payout of each day (UTC), so they only cover
+/// the payouts sent by the clients sharing these guardrails in the current process. Payouts which
+/// are not sent or are rejected by TrueLayer do not count towards the totals, unlike the ones whose
+/// outcome is unknown because of a transport failure. Retries of a payout with the same idempotency
+/// key are counted once.
+pub struct Guardrails {
+    allowed_currencies: Option<HashSet<Currency>>,
+    allowed_beneficiary_types: Option<HashSet<PayoutBeneficiaryType>>,
+    max_amounts: HashMap<Currency, u64>,
+    max_daily_totals: HashMap<Currency, u64>,
+    daily_totals: Mutex<DailyTotals>,
+    listeners: Vec<Arc<dyn Fn(&BlockedPayout) + Send + Sync>>,
+}
+
+impl Default for Guardrails {
+    fn default() -> Self {
+        Self {
+            allowed_currencies: None,
+            allowed_beneficiary_types: None,
+            max_amounts: HashMap::new(),
+            max_daily_totals: HashMap::new(),
+            daily_totals: Mutex::new(DailyTotals::new(Utc::now().date_naive())),
+            listeners: Vec::new(),
+        }
+    }
+}
+
+impl Guardrails {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only allows payouts in the given currencies.
+    pub fn with_allowed_currencies(
+        mut self,
+        currencies: impl IntoIterator<Item = Currency>,
+    ) -> Self {
+        self.allowed_currencies = Some(currencies.into_iter().collect());
+        self
+    }
+
+    /// Only allows payouts to the given types of beneficiaries.
+    pub fn with_allowed_beneficiary_types(
+        mut self,
+        types: impl IntoIterator<Item = PayoutBeneficiaryType>,
+    ) -> Self {
+        self.allowed_beneficiary_types = Some(types.into_iter().collect());
+        self
+    }
+
+    /// Limits the amount of each payout in the given currency.
+    pub fn with_max_amount(mut self, currency: Currency, max_in_minor: u64) -> Self {
+        self.max_amounts.insert(currency, max_in_minor);
+        self
+    }
+
+    /// Limits the total amount paid out in the given currency each day (UTC).
+    pub fn with_max_daily_total(mut self, currency: Currency, max_in_minor: u64) -> Self {
+        self.max_daily_totals.insert(currency, max_in_minor);
+        self
+    }
+
+    /// Registers a callback invoked every time a payout is blocked, e.g. to feed an audit log.
+    ///
+    /// Multiple callbacks are invoked in registration order.
+    pub fn on_block(mut self, callback: impl Fn(&BlockedPayout) + Send + Sync + 'static) -> Self {
+        self.listeners.push(Arc::new(callback));
+        self
+    }
+
+    /// Checks a payout against all the rules, counting it towards the daily total if allowed.
+    ///
+    /// A payout whose idempotency key was already counted today is not counted again.
+    pub(crate) fn check(
+        &self,
+        request: &CreatePayoutRequest,
+        idempotency_key: &str,
+    ) -> Result<(), GuardrailViolation> {
+        let result = self.evaluate(request, idempotency_key);
+
+        if let Err(violation) = &result {
+            tracing::warn!(%violation, "Payout blocked by guardrails");
+            let blocked = BlockedPayout {
+                merchant_account_id: request.merchant_account_id.clone(),
+                amount_in_minor: request.amount_in_minor,
+                currency: request.currency.clone(),
+                beneficiary_type: request.beneficiary.r#type(),
+                violation: violation.clone(),
+                blocked_at: Utc::now(),
+            };
+            for listener in &self.listeners {
+                listener(&blocked);
+            }
+        }
+
+        result
+    }
+
+    /// Removes a payout which was not created by TrueLayer from the daily total.
+    ///
+    /// Payouts counted on a previous day are not part of the current total and are ignored.
+    pub(crate) fn release(&self, idempotency_key: &str) {
+        let mut daily_totals = self.daily_totals.lock().unwrap();
+        if let Some((currency, amount_in_minor)) = daily_totals.counted.remove(idempotency_key) {
+            if let Some(total) = daily_totals.totals.get_mut(&currency) {
+                *total = total.saturating_sub(amount_in_minor);
+            }
+        }
+    }
+
+    fn evaluate(
+        &self,
+        request: &CreatePayoutRequest,
+        idempotency_key: &str,
+    ) -> Result<(), GuardrailViolation> {
+        let currency = &request.currency;
+        let amount_in_minor = request.amount_in_minor;
+
+        if let Some(allowed) = &self.allowed_currencies {
+            if !allowed.contains(currency) {
+                return Err(GuardrailViolation::CurrencyNotAllowed(currency.clone()));
+            }
+        }
+
+        let beneficiary_type = request.beneficiary.r#type();
+        if let Some(allowed) = &self.allowed_beneficiary_types {
+            if !allowed.contains(&beneficiary_type) {
+                return Err(GuardrailViolation::BeneficiaryTypeNotAllowed(
+                    beneficiary_type,
+                ));
+            }
+        }
+
+        if let Some(&max_in_minor) = self.max_amounts.get(currency) {
+            if amount_in_minor > max_in_minor {
+                return Err(GuardrailViolation::AmountTooLarge {
+                    currency: currency.clone(),
+                    amount_in_minor,
+                    max_in_minor,
+                });
+            }
+        }
+
+        if let Some(&max_in_minor) = self.max_daily_totals.get(currency) {
+            let mut daily_totals = self.daily_totals.lock().unwrap();
+            let today = Utc::now().date_naive();
+            if daily_totals.day != today {
+                *daily_totals = DailyTotals::new(today);
+            }
+
+            // Retries of a payout already counted today
+            if daily_totals.counted.contains_key(idempotency_key) {
+                return Ok(());
+            }
+
+            let total_in_minor = daily_totals.totals.entry(currency.clone()).or_default();
+            match checked_add_minor(*total_in_minor, amount_in_minor) {
+                Some(new_total_in_minor) if new_total_in_minor <= max_in_minor => {
+                    *total_in_minor = new_total_in_minor;
+                }
+                // Totals which would overflow are necessarily beyond the maximum
+                _ => {
+                    return Err(GuardrailViolation::DailyTotalExceeded {
+                        currency: currency.clone(),
+                        amount_in_minor,
+                        total_in_minor: *total_in_minor,
+                        max_in_minor,
+                    });
+                }
+            }
+            daily_totals.counted.insert(
+                idempotency_key.to_string(),
+                (currency.clone(), amount_in_minor),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Totals of the payouts sent during a day (UTC).
+struct DailyTotals {
+    day: NaiveDate,
+    totals: HashMap<Currency, u64>,
+    /// Currency and amount of the payouts counted in the totals, by idempotency key.
+    counted: HashMap<String, (Currency, u64)>,
+}
+
+impl DailyTotals {
+    fn new(day: NaiveDate) -> Self {
+        Self {
+            day,
+            totals: HashMap::new(),
+            counted: HashMap::new(),
+        }
+    }
+}
+
+impl Debug for Guardrails {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Guardrails")
+            .field("allowed_currencies", &self.allowed_currencies)
+            .field("allowed_beneficiary_types", &self.allowed_beneficiary_types)
+            .field("max_amounts", &self.max_amounts)
+            .field("max_daily_totals", &self.max_daily_totals)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::payouts::PayoutBeneficiary;
+
+    fn payout(amount_in_minor: u64, currency: Currency) -> CreatePayoutRequest {
+        CreatePayoutRequest {
+            merchant_account_id: "merchant-account-id".to_string(),
+            amount_in_minor,
+            currency,
+            beneficiary: PayoutBeneficiary::BusinessAccount {
+                reference: "reference".to_string(),
+            },
+            scheme_selection: None,
+        }
+    }
+
+    #[test]
+    fn payouts_breaking_the_rules_are_blocked_and_reported() {
+        let blocked = Arc::new(Mutex::new(Vec::new()));
+        let guardrails = Guardrails::new()
+            .with_allowed_currencies([Currency::Gbp, Currency::Eur])
+            .with_allowed_beneficiary_types([PayoutBeneficiaryType::BusinessAccount])
+            .with_max_amount(Currency::Gbp, 100)
+            .with_max_daily_total(Currency::Gbp, 150)
+            .on_block({
+                let blocked = blocked.clone();
+                move |b| blocked.lock().unwrap().push(b.violation.clone())
+            });
+
+        assert_eq!(
+            guardrails.check(&payout(10, Currency::Pln), "key-1"),
+            Err(GuardrailViolation::CurrencyNotAllowed(Currency::Pln))
+        );
+        assert_eq!(
+            guardrails.check(&payout(101, Currency::Gbp), "key-2"),
+            Err(GuardrailViolation::AmountTooLarge {
+                currency: Currency::Gbp,
+                amount_in_minor: 101,
+                max_in_minor: 100
+            })
+        );
+
+        // Limits are per currency
+        assert_eq!(
+            guardrails.check(&payout(1000, Currency::Eur), "key-3"),
+            Ok(())
+        );
+
+        // The daily total includes the payouts allowed so far, minus the ones released
+        assert_eq!(
+            guardrails.check(&payout(100, Currency::Gbp), "key-4"),
+            Ok(())
+        );
+        let exceeding = GuardrailViolation::DailyTotalExceeded {
+            currency: Currency::Gbp,
+            amount_in_minor: 60,
+            total_in_minor: 100,
+            max_in_minor: 150,
+        };
+        assert_eq!(
+            guardrails.check(&payout(60, Currency::Gbp), "key-5"),
+            Err(exceeding.clone())
+        );
+        guardrails.release("key-4");
+        assert_eq!(
+            guardrails.check(&payout(60, Currency::Gbp), "key-5"),
+            Ok(())
+        );
+
+        assert_eq!(blocked.lock().unwrap().len(), 3);
+        assert_eq!(blocked.lock().unwrap()[2], exceeding);
+    }
+
+    #[test]
+    fn retries_are_counted_once() {
+        let guardrails = Guardrails::new().with_max_daily_total(Currency::Gbp, 150);
+
+        assert_eq!(guardrails.check(&payout(100, Currency::Gbp), "key"), Ok(()));
+        assert_eq!(guardrails.check(&payout(100, Currency::Gbp), "key"), Ok(()));
+        assert!(guardrails
+            .check(&payout(100, Currency::Gbp), "other-key")
+            .is_err());
+
+        // Releasing a payout twice only removes it once
+        guardrails.release("key");
+        guardrails.release("key");
+        assert_eq!(
+            guardrails.check(&payout(100, Currency::Gbp), "other-key"),
+            Ok(())
+        );
+        assert!(guardrails
+            .check(&payout(100, Currency::Gbp), "third-key")
+            .is_err());
+    }
+
+    #[test]
+    fn overflowing_totals_exceed_the_maximum() {
+        // No maximum amount per payout
+        let guardrails = Guardrails::new().with_max_daily_total(Currency::Gbp, 150);
+
+        assert_eq!(
+            guardrails.check(&payout(100, Currency::Gbp), "key-1"),
+            Ok(())
+        );
+        assert_eq!(
+            guardrails.check(&payout(u64::MAX, Currency::Gbp), "key-2"),
+            Err(GuardrailViolation::DailyTotalExceeded {
+                currency: Currency::Gbp,
+                amount_in_minor: u64::MAX,
+                total_in_minor: 100,
+                max_in_minor: 150,
+            })
+        );
+    }
+
+    #[test]
+    fn payouts_of_previous_days_are_not_released_from_the_current_total() {
+        let guardrails = Guardrails::new().with_max_daily_total(Currency::Gbp, 150);
+        assert_eq!(
+            guardrails.check(&payout(100, Currency::Gbp), "yesterday"),
+            Ok(())
+        );
+
+        // Simulate the day rollover
+        {
+            let mut daily_totals = guardrails.daily_totals.lock().unwrap();
+            daily_totals.day = daily_totals.day.pred_opt().unwrap();
+        }
+        assert_eq!(
+            guardrails.check(&payout(100, Currency::Gbp), "today"),
+            Ok(())
+        );
+
+        guardrails.release("yesterday");
+        assert!(guardrails
+            .check(&payout(100, Currency::Gbp), "later-today")
+            .is_err());
+    }
+}
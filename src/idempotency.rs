@@ -0,0 +1,111 @@
+//! Storage for the intents of operations which must be carried out exactly once,
+//! even across process restarts.
+
+use crate::Error;
+use async_trait::async_trait;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+/// Key-value store used to persist idempotency keys and the ids of the resources they created.
+///
+/// The default [`InMemoryIdempotencyStore`] does not survive process restarts.
+/// Implement this trait on top of a database to get exactly-once guarantees across deployments.
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    /// Returns the value stored for the given key, if any.
+    async fn get(&self, key: &str) -> Result<Option<String>, Error>;
+
+    /// Stores a value for the given key, overwriting any previous value.
+    async fn set(&self, key: &str, value: &str) -> Result<(), Error>;
+
+    /// Stores a value for the given key only if it has none, and returns the value stored
+    /// for the key afterwards: either the given one or the one stored previously.
+    ///
+    /// Must be atomic, so that concurrent callers with different values all get the same one back.
+    async fn set_if_absent(&self, key: &str, value: &str) -> Result<String, Error>;
+}
+
+/// [`IdempotencyStore`] which keeps all the values in memory.
+#[derive(Debug, Default)]
+pub struct InMemoryIdempotencyStore {
+    values: Mutex<HashMap<String, String>>,
+}
+
+impl InMemoryIdempotencyStore {
+    /// Creates a new empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for InMemoryIdempotencyStore {
+    async fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        Ok(self.values.lock().unwrap().get(key).cloned())
+    }
+
+    async fn set(&self, key: &str, value: &str) -> Result<(), Error> {
+        self.values
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    async fn set_if_absent(&self, key: &str, value: &str) -> Result<String, Error> {
+        Ok(self
+            .values
+            .lock()
+            .unwrap()
+            .entry(key.to_string())
+            .or_insert_with(|| value.to_string())
+            .clone())
+    }
+}
+
+/// Maximum number of idempotency keys remembered by an [`IdempotencyLedger`].
+const LEDGER_CAPACITY: usize = 10_000;
+
+/// Ids of the resources recently created by a client, by idempotency key.
+///
+/// Used to find the original resource when TrueLayer reports an
+/// [`IdempotencyConflict`](crate::Error::IdempotencyConflict). Only the most recent keys are kept.
+#[derive(Debug, Default)]
+pub(crate) struct IdempotencyLedger {
+    entries: Mutex<(HashMap<String, String>, VecDeque<String>)>,
+}
+
+impl IdempotencyLedger {
+    /// Remembers the id of the resource created with the given idempotency key.
+    pub(crate) fn record(&self, idempotency_key: &str, resource_id: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        let (ids, keys) = &mut *entries;
+        if ids
+            .insert(idempotency_key.to_string(), resource_id.to_string())
+            .is_none()
+        {
+            keys.push_back(idempotency_key.to_string());
+        }
+
+        // Forget the oldest keys
+        while keys.len() > LEDGER_CAPACITY {
+            if let Some(key) = keys.pop_front() {
+                ids.remove(&key);
+            }
+        }
+    }
+
+    /// Returns the id of the resource created with the given idempotency key, if known.
+    ///
+    /// Only works with [`Error::IdempotencyConflict`](crate::Error::IdempotencyConflict).
+    pub(crate) fn original_resource_id(&self, error: &Error) -> Option<String> {
+        match error {
+            Error::IdempotencyConflict {
+                idempotency_key, ..
+            } => self.entries.lock().unwrap().0.get(idempotency_key).cloned(),
+            _ => None,
+        }
+    }
+}
@@ -30,7 +30,7 @@
 //! Create a new [`TrueLayerClient`](crate::client::TrueLayerClient) and provide your client ID and client secret.
 //!
 //! ```rust,no_run
-//! # use truelayer_rust::{TrueLayerClient, apis::auth::*};
+//! # use truelayer_rust::prelude::*;
 //! # let private_key = vec![];
 //! let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
 //!     client_id: "some-client-id".into(),
@@ -44,10 +44,12 @@
 //! By default, a `TrueLayerClient` connects to the Live environment.
 //! To connect to TrueLayer Sandbox, use [`with_environment(Environment::Sandbox)`](crate::client::TrueLayerClientBuilder::with_environment).
 //!
+//! The most common types can be imported at once from the [`prelude`](crate::prelude).
+//!
 //! ## Create a payment
 //!
 //! ```rust,no_run
-//! # use truelayer_rust::{TrueLayerClient, Error, apis::payments::*};
+//! # use truelayer_rust::prelude::*;
 //! # use uuid::Uuid;
 //! #
 //! # #[tokio::main]
@@ -71,7 +73,9 @@
 //!             email: Some("some.one@email.com".to_string()),
 //!             phone: None,
 //!         },
-//!         metadata: None
+//!         metadata: None,
+//!         retry: None,
+//!         related_products: None
 //!     })
 //!     .await?;
 //!
@@ -96,8 +100,10 @@
 //! let res = tl.payments.create(&create_payment_request).await?;
 //!
 //! let hpp_link = tl.payments
-//!     .get_hosted_payments_page_link(&res.id, &res.resource_token, "https://my.return.uri")
-//!     .await;
+//!     .hosted_payments_page_link(&res.id, &res.resource_token)
+//!     .return_uri("https://my.return.uri")
+//!     .build()
+//!     .unwrap();
 //!
 //! println!("HPP Link: {}", hpp_link);
 //! # Ok(())
@@ -139,13 +145,36 @@
 #![deny(missing_debug_implementations)]
 #![forbid(unsafe_code)]
 
+pub mod amounts;
 pub mod apis;
+pub mod audit;
 pub(crate) mod authenticator;
 pub mod client;
 mod common;
+pub mod config;
+pub mod deprecations;
+pub mod deps;
 pub mod error;
+#[cfg(feature = "export")]
+pub mod export;
+pub mod guardrails;
+pub mod idempotency;
 mod middlewares;
+pub mod migration;
+pub mod observer;
+pub mod pagination;
 pub mod pollable;
+pub mod prelude;
+pub mod rate_limits;
+pub mod redaction;
+pub mod reports;
+pub mod request;
+pub(crate) mod runtime;
+pub mod safeguard;
+#[cfg(feature = "schema-validation")]
+pub mod schema_validation;
+pub mod support;
+pub mod transport;
 
 pub use client::TrueLayerClient;
 pub use error::Error;
@@ -1,13 +1,41 @@
-use crate::authenticator::Authenticator;
+use crate::{
+    apis::auth::AccessToken,
+    authenticator::Authenticator,
+    middlewares::{
+        error_handling::{peek_api_error, server_time, signature_rejection},
+        signing::SignedRequest,
+    },
+};
 use async_trait::async_trait;
-use reqwest::{header::HeaderValue, Request, Response};
+use reqwest::{header::HeaderValue, Request, Response, StatusCode};
 use reqwest_middleware::{Middleware, Next};
 use task_local_extensions::Extensions;
 
 /// Reqwest middleware to inject the access token into outgoing HTTP requests.
 /// On the first request, an additional HTTP request will be fired to get a new access token.
+///
+/// If TrueLayer rejects the access token with a `401 Unauthorized` (e.g., because it was revoked
+/// before its expiration), the token is discarded and, if `retry_unauthorized` is set,
+/// the request is retried once with a new one. The token is kept when TrueLayer rejects the
+/// signature of a signed request instead, as a new token would not fix it.
 pub struct AuthenticationMiddleware {
     pub(crate) authenticator: Authenticator,
+    pub(crate) retry_unauthorized: bool,
+}
+
+impl AuthenticationMiddleware {
+    /// Requests an access token from the authenticator and injects it as a header.
+    async fn authenticate(&self, req: &mut Request) -> reqwest_middleware::Result<AccessToken> {
+        let access_token = self.authenticator.get_access_token().await?.access_token;
+
+        let mut header_value =
+            HeaderValue::from_str(&format!("Bearer {}", access_token.expose_secret()))
+                .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?;
+        header_value.set_sensitive(true);
+        req.headers_mut().insert("Authorization", header_value);
+
+        Ok(access_token)
+    }
 }
 
 #[async_trait]
@@ -18,20 +46,41 @@ impl Middleware for AuthenticationMiddleware {
         extensions: &mut Extensions,
         next: Next<'_>,
     ) -> reqwest_middleware::Result<Response> {
-        // Request an access token from the authenticator
-        let access_token = self.authenticator.get_access_token().await?;
-
-        // Inject the access token as a header
-        let mut header_value = HeaderValue::from_str(&format!(
-            "Bearer {}",
-            access_token.access_token.expose_secret()
-        ))
-        .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?;
-        header_value.set_sensitive(true);
-        req.headers_mut().insert("Authorization", header_value);
+        let access_token = self.authenticate(&mut req).await?;
+
+        // Keep a copy of the request to retry it with a new token, unless its body is a stream
+        let retry = req.try_clone();
 
         //Run the rest of the middlewares
-        next.run(req, extensions).await
+        let mut res = next.clone().run(req, extensions).await?;
+
+        if res.status() != StatusCode::UNAUTHORIZED {
+            return Ok(res);
+        }
+
+        // Signatures are rejected with the same status as access tokens
+        if extensions.get::<SignedRequest>().is_some() {
+            let server_time = server_time(res.headers());
+            let (peeked, api_error) = peek_api_error(res).await?;
+            if signature_rejection(&api_error, server_time).is_some() {
+                return Ok(peeked);
+            }
+            res = peeked;
+        }
+
+        self.authenticator.invalidate_access_token(&access_token);
+
+        match retry {
+            Some(mut retry) if self.retry_unauthorized => {
+                tracing::warn!("Access token rejected, retrying with a new one");
+                self.authenticate(&mut retry).await?;
+                next.run(retry, extensions).await
+            }
+            _ => {
+                tracing::warn!("Access token rejected, the next request will use a new one");
+                Ok(res)
+            }
+        }
     }
 }
 
@@ -42,6 +91,7 @@ mod tests {
     use reqwest::Url;
     use reqwest_middleware::ClientBuilder;
     use serde_json::json;
+    use std::sync::atomic::{AtomicU32, Ordering};
     use wiremock::{
         matchers::{header, method, path},
         Mock, MockServer, ResponseTemplate,
@@ -96,7 +146,10 @@ mod tests {
 
         // Setup a client using the auth middleware
         let client = ClientBuilder::new(reqwest::Client::new())
-            .with(AuthenticationMiddleware { authenticator })
+            .with(AuthenticationMiddleware {
+                authenticator,
+                retry_unauthorized: true,
+            })
             .build();
 
         // Send a test request
@@ -108,4 +161,162 @@ mod tests {
 
         // Expectations are verified here before the mock server is dropped
     }
+
+    #[tokio::test]
+    async fn rejected_access_token_is_refreshed_and_request_retried() {
+        let mock_server = MockServer::start().await;
+        let token_requests = AtomicU32::new(0);
+        Mock::given(method("POST"))
+            .and(path("/connect/token"))
+            .respond_with(move |_: &wiremock::Request| {
+                let i = token_requests.fetch_add(1, Ordering::SeqCst);
+                ResponseTemplate::new(200).set_body_json(json!({
+                    "token_type": "Bearer",
+                    "access_token": format!("{}-{}", MOCK_ACCESS_TOKEN, i),
+                    "expires_in": 3600
+                }))
+            })
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/test"))
+            .and(header(
+                "Authorization",
+                format!("Bearer {}-0", MOCK_ACCESS_TOKEN).as_str(),
+            ))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/test"))
+            .and(header(
+                "Authorization",
+                format!("Bearer {}-1", MOCK_ACCESS_TOKEN).as_str(),
+            ))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let authenticator = mock_authenticator(&mock_server.uri());
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(AuthenticationMiddleware {
+                authenticator,
+                retry_unauthorized: true,
+            })
+            .build();
+
+        let res = client
+            .get(format!("{}/test", mock_server.uri()))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    /// Marks the requests as signed, like the signing middleware.
+    struct MarkSigned;
+
+    #[async_trait]
+    impl Middleware for MarkSigned {
+        async fn handle(
+            &self,
+            req: Request,
+            extensions: &mut Extensions,
+            next: Next<'_>,
+        ) -> reqwest_middleware::Result<Response> {
+            extensions.insert(SignedRequest);
+            next.run(req, extensions).await
+        }
+    }
+
+    #[tokio::test]
+    async fn rejected_signatures_keep_the_access_token() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/connect/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "token_type": "Bearer",
+                "access_token": MOCK_ACCESS_TOKEN,
+                "expires_in": 3600
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/test"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "type": "https://docs.truelayer.com/docs/error-types#unauthenticated",
+                "title": "Unauthenticated",
+                "status": 401,
+                "trace_id": "trace-id",
+                "detail": "Invalid request signature."
+            })))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let authenticator = mock_authenticator(&mock_server.uri());
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(AuthenticationMiddleware {
+                authenticator,
+                retry_unauthorized: true,
+            })
+            .with(MarkSigned)
+            .build();
+
+        // Neither retried nor sent with a new access token
+        for _ in 0..2 {
+            let res = client
+                .post(format!("{}/test", mock_server.uri()))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(
+                res.json::<serde_json::Value>().await.unwrap()["detail"],
+                "Invalid request signature."
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejected_access_token_is_not_retried_unless_enabled() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/connect/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "token_type": "Bearer",
+                "access_token": MOCK_ACCESS_TOKEN,
+                "expires_in": 3600
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/test"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let authenticator = mock_authenticator(&mock_server.uri());
+        let client = ClientBuilder::new(reqwest::Client::new())
+            .with(AuthenticationMiddleware {
+                authenticator,
+                retry_unauthorized: false,
+            })
+            .build();
+
+        let res = client
+            .get(format!("{}/test", mock_server.uri()))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
 }
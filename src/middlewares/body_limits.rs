@@ -0,0 +1,167 @@
+use crate::Error;
+use async_trait::async_trait;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use std::time::Duration;
+use task_local_extensions::Extensions;
+
+/// Middleware which reads response bodies upfront, failing with
+/// [`Error::ResponseTooLarge`](crate::Error::ResponseTooLarge) or
+/// [`Error::ResponseBodyTimeout`](crate::Error::ResponseBodyTimeout) if they exceed the configured limits.
+#[derive(Clone)]
+pub struct BodyLimitsMiddleware {
+    max_size: Option<u64>,
+    read_timeout: Option<Duration>,
+}
+
+impl BodyLimitsMiddleware {
+    pub fn new(max_size: Option<u64>, read_timeout: Option<Duration>) -> Self {
+        Self {
+            max_size,
+            read_timeout,
+        }
+    }
+
+    async fn read_body(&self, response: &mut Response) -> Result<Vec<u8>, Error> {
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+
+            match self.max_size {
+                Some(limit) if body.len() as u64 > limit => {
+                    return Err(Error::ResponseTooLarge { limit })
+                }
+                _ => {}
+            }
+        }
+
+        Ok(body)
+    }
+}
+
+#[async_trait]
+impl Middleware for BodyLimitsMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let mut response = next.run(req, extensions).await?;
+
+        // Fail fast if the server announces an oversized body
+        if let (Some(limit), Some(len)) = (self.max_size, response.content_length()) {
+            if len > limit {
+                return Err(Error::ResponseTooLarge { limit }.into());
+            }
+        }
+
+        let body = match self.read_timeout {
+            Some(timeout) => crate::runtime::timeout(timeout, self.read_body(&mut response))
+                .await
+                .map_err(|_| Error::ResponseBodyTimeout { timeout })??,
+            None => self.read_body(&mut response).await?,
+        };
+
+        // Rebuild the response around the buffered body
+        let mut builder = http::Response::builder()
+            .status(response.status())
+            .version(response.version());
+        if let Some(headers) = builder.headers_mut() {
+            *headers = response.headers().clone();
+        }
+        let rebuilt = builder.body(body).map_err(|e| Error::Other(e.into()))?;
+
+        Ok(rebuilt.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+    use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+    async fn client_and_server(
+        max_size: Option<u64>,
+        read_timeout: Option<Duration>,
+    ) -> (reqwest_middleware::ClientWithMiddleware, MockServer) {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/small"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{}"))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/large"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("a".repeat(1024)))
+            .mount(&mock_server)
+            .await;
+
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(BodyLimitsMiddleware::new(max_size, read_timeout))
+            .build();
+
+        (client, mock_server)
+    }
+
+    #[tokio::test]
+    async fn bodies_within_limits_are_preserved() {
+        let (client, mock_server) = client_and_server(Some(16), Some(Duration::from_secs(5))).await;
+
+        let res = client
+            .get(format!("{}/small", mock_server.uri()))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.text().await.unwrap(), "{}");
+    }
+
+    #[tokio::test]
+    async fn oversized_bodies_are_rejected() {
+        let (client, mock_server) = client_and_server(Some(16), None).await;
+
+        let res = client
+            .get(format!("{}/large", mock_server.uri()))
+            .send()
+            .await
+            .map_err(Error::from);
+
+        assert!(matches!(res, Err(Error::ResponseTooLarge { limit: 16 })));
+    }
+
+    #[tokio::test]
+    async fn slow_bodies_time_out() {
+        // Server which sends the headers straight away, but never completes the body
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n{\"a\"");
+            std::thread::sleep(std::time::Duration::from_secs(2));
+        });
+
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(BodyLimitsMiddleware::new(
+                None,
+                Some(Duration::from_millis(100)),
+            ))
+            .build();
+
+        let res = client
+            .get(format!("http://{}/slow", addr))
+            .send()
+            .await
+            .map_err(Error::from);
+
+        assert!(matches!(
+            res,
+            Err(Error::ResponseBodyTimeout { timeout }) if timeout == Duration::from_millis(100)
+        ));
+    }
+}
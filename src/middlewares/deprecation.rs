@@ -0,0 +1,173 @@
+use crate::{
+    audit::templatize_path,
+    deprecations::{DeprecationNotice, DeprecationRegistry},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::{header::LINK, Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use task_local_extensions::Extensions;
+
+static DEPRECATION_HEADER: &str = "Deprecation";
+static SUNSET_HEADER: &str = "Sunset";
+
+/// Middleware which inspects responses for `Deprecation` and `Sunset` headers,
+/// logging a warning and recording a [`DeprecationNotice`](crate::deprecations::DeprecationNotice)
+/// when they are present.
+pub struct DeprecationMiddleware {
+    registry: DeprecationRegistry,
+}
+
+impl DeprecationMiddleware {
+    pub fn new(registry: DeprecationRegistry) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl Middleware for DeprecationMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        // Notices are recorded once per endpoint, not once per resource
+        let method = req.method().to_string();
+        let (path, _) = templatize_path(req.url().path());
+
+        let response = next.run(req, extensions).await?;
+
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        let deprecation = header(DEPRECATION_HEADER);
+        let sunset = header(SUNSET_HEADER);
+
+        if deprecation.is_some() || sunset.is_some() {
+            let notice = DeprecationNotice {
+                method,
+                path,
+                deprecation,
+                sunset: sunset
+                    .as_deref()
+                    .and_then(|s| DateTime::parse_from_rfc2822(s).ok())
+                    .map(|s| s.with_timezone(&Utc)),
+                link: response
+                    .headers()
+                    .get_all(LINK)
+                    .iter()
+                    .filter_map(|v| v.to_str().ok())
+                    .find_map(deprecation_link),
+                last_seen_at: Utc::now(),
+            };
+
+            if self.registry.record(notice.clone()) {
+                tracing::warn!(
+                    method = %notice.method,
+                    path = %notice.path,
+                    deprecation = ?notice.deprecation,
+                    sunset = ?notice.sunset,
+                    link = ?notice.link,
+                    "TrueLayer flagged this endpoint as deprecated"
+                );
+            }
+        }
+
+        Ok(response)
+    }
+}
+
+/// Extracts the target of a `Link` header with a `deprecation` or `sunset` relation.
+fn deprecation_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let (target, params) = link.split_once(';')?;
+        let is_deprecation = params
+            .split(';')
+            .filter_map(|param| param.trim().strip_prefix("rel="))
+            .any(|rel| matches!(rel.trim_matches('"'), "deprecation" | "sunset"));
+
+        is_deprecation.then(|| {
+            target
+                .trim()
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string()
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use wiremock::{
+        matchers::{path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn deprecation_headers_are_recorded() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/deprecated"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .append_header("Deprecation", "true")
+                    .append_header("Sunset", "Wed, 01 Jan 2025 00:00:00 GMT")
+                    .append_header(
+                        "Link",
+                        "<https://docs.truelayer.com/changelog>; rel=\"deprecation\"",
+                    ),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/current"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        Mock::given(path_regex("^/payments/[^/]+$"))
+            .respond_with(ResponseTemplate::new(200).append_header("Deprecation", "true"))
+            .mount(&mock_server)
+            .await;
+
+        let registry = DeprecationRegistry::default();
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(DeprecationMiddleware::new(registry.clone()))
+            .build();
+
+        for p in [
+            "/deprecated",
+            "/current",
+            "/deprecated",
+            "/payments/payment-1",
+            "/payments/payment-2",
+        ] {
+            client
+                .get(format!("{}{}", mock_server.uri(), p))
+                .send()
+                .await
+                .unwrap();
+        }
+
+        let notices = registry.snapshot();
+        assert_eq!(notices.len(), 2);
+        assert_eq!(notices[0].method, "GET");
+        assert_eq!(notices[0].path, "/deprecated");
+        assert_eq!(notices[0].deprecation.as_deref(), Some("true"));
+        assert_eq!(
+            notices[0].sunset,
+            Some(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            notices[0].link.as_deref(),
+            Some("https://docs.truelayer.com/changelog")
+        );
+
+        // Resource ids are not part of the endpoint
+        assert_eq!(notices[1].path, "/payments/{id}");
+    }
+}
@@ -1,15 +1,24 @@
 use crate::{
-    common::TL_CORRELATION_ID_HEADER,
-    error::{ApiError, Error},
+    common::{IDEMPOTENCY_KEY_HEADER, TL_CORRELATION_ID_HEADER},
+    error::{ApiError, Error, SignatureRejection, MAX_CLOCK_SKEW},
+    middlewares::signing::SignedRequest,
+    rate_limits::retry_after,
 };
 use async_trait::async_trait;
-use reqwest::{Request, Response};
+use chrono::{DateTime, Utc};
+use reqwest::{
+    header::{HeaderMap, DATE},
+    Request, Response,
+};
 use reqwest_middleware::{Middleware, Next};
 use std::collections::HashMap;
 use task_local_extensions::Extensions;
 
 /// Reqwest middleware which translates JSON error responses returned from TrueLayer APIs
-/// into [`Error::ApiError`](crate::error::Error)s.
+/// into [`Error::ApiError`](crate::error::Error)s, or into
+/// [`Error::IdempotencyConflict`](crate::error::Error)s when an idempotency key is reused,
+/// or into [`Error::RateLimited`](crate::error::Error)s when rate limited,
+/// or into [`Error::SignatureRejected`](crate::error::Error)s when the signature of a request is rejected.
 pub struct ErrorHandlingMiddleware;
 
 #[async_trait]
@@ -20,6 +29,12 @@ impl Middleware for ErrorHandlingMiddleware {
         extensions: &mut Extensions,
         next: Next<'_>,
     ) -> reqwest_middleware::Result<Response> {
+        let idempotency_key = req
+            .headers()
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+
         // Capture the response
         let response = next.run(req, extensions).await?;
 
@@ -27,8 +42,35 @@ impl Middleware for ErrorHandlingMiddleware {
         if !response.status().is_success() {
             tracing::debug!("Failed HTTP request. Status code: {}", response.status());
 
+            let retry_after = retry_after(response.headers());
+            let server_time = server_time(response.headers());
             let api_error = api_error_from_response(response).await?;
-            return Err(Error::ApiError(api_error).into());
+
+            if extensions.get::<SignedRequest>().is_some() {
+                if let Some(reason) = signature_rejection(&api_error, server_time) {
+                    return Err(Error::SignatureRejected {
+                        reason,
+                        source: api_error,
+                    }
+                    .into());
+                }
+            }
+
+            return Err(match idempotency_key {
+                _ if api_error.status == 429 => Error::RateLimited {
+                    retry_after,
+                    source: api_error,
+                },
+                Some(idempotency_key) if is_idempotency_conflict(&api_error) => {
+                    Error::IdempotencyConflict {
+                        idempotency_key,
+                        trace_id: api_error.trace_id.clone(),
+                        source: api_error,
+                    }
+                }
+                _ => Error::ApiError(api_error),
+            }
+            .into());
         }
 
         Ok(response)
@@ -42,9 +84,9 @@ enum ErrorResponseBody {
     V3ErrorResponse {
         r#type: String,
         title: String,
-        trace_id: String,
-        detail: String,
-        errors: Option<HashMap<String, Vec<String>>>,
+        trace_id: Option<String>,
+        detail: Option<String>,
+        errors: Option<HashMap<String, FieldErrors>>,
     },
     V1ErrorResponse {
         error: String,
@@ -54,6 +96,100 @@ enum ErrorResponseBody {
     Unknown,
 }
 
+/// Validation failures of a single field, sent either as a list or as a single message.
+#[derive(serde::Deserialize, Debug)]
+#[serde(untagged)]
+enum FieldErrors {
+    Many(Vec<String>),
+    One(String),
+}
+
+impl From<FieldErrors> for Vec<String> {
+    fn from(errors: FieldErrors) -> Self {
+        match errors {
+            FieldErrors::Many(errors) => errors,
+            FieldErrors::One(error) => vec![error],
+        }
+    }
+}
+
+/// Tells why a signed request was rejected, if it was because of its signature.
+///
+/// Clock skews are detected from the `Date` header of the response, the other reasons
+/// from the description of the error.
+pub(crate) fn signature_rejection(
+    api_error: &ApiError,
+    server_time: Option<DateTime<Utc>>,
+) -> Option<SignatureRejection> {
+    if api_error.status != 401 {
+        return None;
+    }
+
+    if let Some(server_time) = server_time {
+        let skew = Utc::now() - server_time;
+        if skew.abs() > chrono::Duration::from_std(MAX_CLOCK_SKEW).unwrap() {
+            return Some(SignatureRejection::ClockSkew { skew });
+        }
+    }
+
+    let description = format!(
+        "{} {}",
+        api_error.title,
+        api_error.detail.as_deref().unwrap_or_default()
+    )
+    .to_lowercase();
+    if description.contains("key id") || description.contains("kid") {
+        Some(SignatureRejection::UnknownKeyId)
+    } else if description.contains("signature") {
+        Some(SignatureRejection::InvalidSignature)
+    } else {
+        None
+    }
+}
+
+/// Time of the server which sent a response, from its `Date` header.
+pub(crate) fn server_time(headers: &HeaderMap) -> Option<DateTime<Utc>> {
+    headers
+        .get(DATE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// Reads the error of a failed response, returning it along with an identical response
+/// which can still be handled by the outer middlewares.
+pub(crate) async fn peek_api_error(
+    response: Response,
+) -> reqwest_middleware::Result<(Response, ApiError)> {
+    let status = response.status();
+    let version = response.version();
+    let headers = response.headers().clone();
+    let body = response.bytes().await?;
+
+    let rebuild = || -> reqwest_middleware::Result<Response> {
+        let mut builder = http::Response::builder().status(status).version(version);
+        if let Some(builder_headers) = builder.headers_mut() {
+            *builder_headers = headers.clone();
+        }
+        let rebuilt = builder
+            .body(body.clone())
+            .map_err(|e| Error::Other(e.into()))?;
+        Ok(rebuilt.into())
+    };
+
+    let api_error = api_error_from_response(rebuild()?).await?;
+    Ok((rebuild()?, api_error))
+}
+
+/// Returns `true` if the error was caused by an idempotency key reused for a different request,
+/// or while the request which originally used it was still being processed.
+fn is_idempotency_conflict(api_error: &ApiError) -> bool {
+    api_error.r#type.ends_with("#idempotency-key-reuse")
+        || api_error
+            .r#type
+            .ends_with("#idempotency-key-concurrency-conflict")
+}
+
 async fn api_error_from_response(response: Response) -> reqwest_middleware::Result<ApiError> {
     let status = response.status().as_u16();
     let tl_correlation_id = response
@@ -79,9 +215,11 @@ async fn api_error_from_response(response: Response) -> reqwest_middleware::Resu
             r#type,
             title,
             status,
-            trace_id: Some(trace_id),
-            detail: Some(detail),
-            errors: errors.unwrap_or_default(),
+            trace_id: trace_id.or(tl_correlation_id),
+            detail,
+            errors: errors
+                .map(|errors| errors.into_iter().map(|(k, v)| (k, v.into())).collect())
+                .unwrap_or_default(),
         },
         ErrorResponseBody::V1ErrorResponse {
             error,
@@ -241,6 +379,55 @@ mod tests {
             .collect()
         );
         assert_eq!(api_error.trace_id, Some("trace-id".to_string()));
+        assert_eq!(api_error.kind(), Some("invalid-parameters"));
+        assert!(api_error.is_invalid_parameters());
+        assert!(api_error.field_errors("other").is_empty());
+    }
+
+    #[tokio::test]
+    async fn partial_json_errors_v3_are_mapped_correctly() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(401)
+                    .append_header(TL_CORRELATION_ID_HEADER, "correlation-id")
+                    .set_body_json(json!({
+                        "type": "https://docs.truelayer.com/docs/error-types#unauthenticated",
+                        "title": "Unauthenticated",
+                        "status": 401,
+                        "errors": {
+                            "authorization": "Missing or invalid token"
+                        }
+                    })),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(ErrorHandlingMiddleware)
+            .build();
+
+        let err: Error = client
+            .get(mock_server.uri())
+            .send()
+            .await
+            .expect_err("Call succeeded")
+            .into();
+
+        let api_error = match err {
+            Error::ApiError(api_error) => api_error,
+            e => panic!("Unexpected error: {}", e),
+        };
+
+        assert_eq!(api_error.title, "Unauthenticated");
+        assert_eq!(api_error.detail, None);
+        assert_eq!(api_error.trace_id.as_deref(), Some("correlation-id"));
+        assert_eq!(
+            api_error.field_errors("authorization"),
+            ["Missing or invalid token".to_string()]
+        );
+        assert!(api_error.is_unauthenticated());
+        assert!(!api_error.is_invalid_parameters());
     }
 
     #[tokio::test]
@@ -281,4 +468,72 @@ mod tests {
         assert_eq!(api_error.errors, HashMap::new());
         assert_eq!(api_error.trace_id.as_deref(), Some("correlation-id"));
     }
+
+    #[tokio::test]
+    async fn reused_idempotency_keys_are_mapped_to_conflicts() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+                "type": "https://docs.truelayer.com/docs/error-types#idempotency-key-reuse",
+                "title": "Idempotency-Key Reuse",
+                "status": 422,
+                "trace_id": "trace-id",
+                "detail": "The Idempotency-Key value has already been used for a different request."
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(ErrorHandlingMiddleware)
+            .build();
+
+        let err: Error = client
+            .post(mock_server.uri())
+            .header(IDEMPOTENCY_KEY_HEADER, "some-idempotency-key")
+            .send()
+            .await
+            .expect_err("Call succeeded")
+            .into();
+
+        match err {
+            Error::IdempotencyConflict {
+                idempotency_key,
+                trace_id,
+                source,
+            } => {
+                assert_eq!(idempotency_key, "some-idempotency-key");
+                assert_eq!(trace_id.as_deref(), Some("trace-id"));
+                assert_eq!(source.status, 422);
+            }
+            e => panic!("Unexpected error: {}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn other_conflicts_are_not_idempotency_conflicts() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(409).set_body_json(json!({
+                "type": "https://docs.truelayer.com/docs/error-types#mandate-already-revoked",
+                "title": "Conflict",
+                "status": 409,
+                "trace_id": "trace-id"
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(ErrorHandlingMiddleware)
+            .build();
+
+        let err: Error = client
+            .post(mock_server.uri())
+            .header(IDEMPOTENCY_KEY_HEADER, "some-idempotency-key")
+            .send()
+            .await
+            .expect_err("Call succeeded")
+            .into();
+
+        assert!(matches!(err, Error::ApiError(api_error) if api_error.status == 409));
+    }
 }
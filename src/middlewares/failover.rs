@@ -0,0 +1,337 @@
+use crate::{
+    observer::RequestObserver,
+    runtime::{self, Instant},
+};
+use async_trait::async_trait;
+use reqwest::{Request, Response, Url};
+use reqwest_middleware::{Middleware, Next};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use task_local_extensions::Extensions;
+
+/// Middleware which redirects outgoing requests to fallback environments
+/// when the primary one becomes unreachable.
+///
+/// After `failure_threshold` consecutive connection failures, all subsequent requests are sent
+/// to the next environment in the list. Once every `probe_interval`, a `GET` request is sent
+/// in the background to `probe_url` (the JWKS of the primary environment) with `probe_client`,
+/// and if it gets any response the client fails back to the primary environment.
+/// Requests never wait for the probe: they keep going to the fallback until it succeeds.
+#[derive(Clone)]
+pub struct FailoverMiddleware {
+    /// Base URLs (auth, payments, hpp) of each environment, starting with the primary one.
+    environments: Vec<[Url; 3]>,
+    failure_threshold: u32,
+    probe_interval: Duration,
+    probe_url: Url,
+    probe_client: reqwest::Client,
+    observers: Vec<Arc<dyn RequestObserver>>,
+    state: Arc<Mutex<FailoverState>>,
+}
+
+struct FailoverState {
+    active: usize,
+    consecutive_failures: u32,
+    switched_at: Instant,
+    /// Whether a probe of the primary environment is in flight.
+    probing: bool,
+}
+
+impl FailoverMiddleware {
+    pub fn new(
+        environments: Vec<[Url; 3]>,
+        failure_threshold: u32,
+        probe_interval: Duration,
+        probe_url: Url,
+        probe_client: reqwest::Client,
+        observers: Vec<Arc<dyn RequestObserver>>,
+    ) -> Self {
+        Self {
+            environments,
+            failure_threshold,
+            probe_interval,
+            probe_url,
+            probe_client,
+            observers,
+            state: Arc::new(Mutex::new(FailoverState {
+                active: 0,
+                consecutive_failures: 0,
+                switched_at: Instant::now(),
+                probing: false,
+            })),
+        }
+    }
+
+    /// Rewrites a request targeting the primary environment to target the environment at `index`.
+    ///
+    /// The path of the request relative to the base URL of the primary environment is appended
+    /// to the base URL of the target environment, keeping its base path.
+    fn rewrite(&self, mut req: Request, index: usize) -> Request {
+        if index == 0 {
+            return req;
+        }
+
+        let base_url_index = self.environments[0]
+            .iter()
+            .position(|base_url| base_url.origin() == req.url().origin());
+
+        if let Some(i) = base_url_index {
+            let primary_base_path = self.environments[0][i].path().trim_end_matches('/');
+            let relative_path = req
+                .url()
+                .path()
+                .strip_prefix(primary_base_path)
+                .filter(|path| path.is_empty() || path.starts_with('/'))
+                .unwrap_or_else(|| req.url().path());
+
+            let mut url = self.environments[index][i].clone();
+            let path = format!("{}{}", url.path().trim_end_matches('/'), relative_path);
+            url.set_path(&path);
+            url.set_query(req.url().query());
+            *req.url_mut() = url;
+        }
+
+        req
+    }
+
+    fn record_outcome(&self, index: usize, result: &reqwest_middleware::Result<Response>) {
+        let mut state = self.state.lock().unwrap();
+        if state.active != index {
+            // Another request already changed the active environment
+            return;
+        }
+
+        if !is_connection_failure(result) {
+            state.consecutive_failures = 0;
+            return;
+        }
+
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.failure_threshold
+            && state.active + 1 < self.environments.len()
+        {
+            state.active += 1;
+            state.consecutive_failures = 0;
+            state.switched_at = Instant::now();
+            let active = state.active;
+            drop(state);
+
+            tracing::warn!(
+                environment_index = active,
+                host = self.host(active),
+                "Failing over to fallback environment after repeated connection failures"
+            );
+            self.notify_switch(index, active);
+        }
+    }
+
+    /// Probes the primary environment with a lightweight request,
+    /// failing back to it if it is reachable and the fallback at `active` is still in use.
+    async fn probe(&self, active: usize) {
+        let result = self
+            .probe_client
+            .get(self.probe_url.clone())
+            .send()
+            .await
+            .map_err(reqwest_middleware::Error::Reqwest);
+
+        let mut state = self.state.lock().unwrap();
+        state.probing = false;
+        if is_connection_failure(&result) {
+            state.switched_at = Instant::now();
+            drop(state);
+            tracing::debug!("Primary environment is still unreachable");
+        } else if state.active == active {
+            state.active = 0;
+            state.consecutive_failures = 0;
+            state.switched_at = Instant::now();
+            drop(state);
+
+            tracing::info!(host = self.host(0), "Failing back to primary environment");
+            self.notify_switch(active, 0);
+        }
+    }
+
+    /// Host of the payments APIs of the environment at `index`.
+    fn host(&self, index: usize) -> &str {
+        self.environments[index][1].host_str().unwrap_or_default()
+    }
+
+    fn notify_switch(&self, from: usize, to: usize) {
+        for observer in &self.observers {
+            observer.on_failover(self.host(from), self.host(to));
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for FailoverMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let (active, should_probe) = {
+            let mut state = self.state.lock().unwrap();
+            let should_probe = state.active != 0
+                && !state.probing
+                && state.switched_at.elapsed() >= self.probe_interval;
+            state.probing |= should_probe;
+            (state.active, should_probe)
+        };
+
+        // Periodically probe the primary environment, off the path of the request
+        if should_probe {
+            let middleware = self.clone();
+            runtime::spawn(async move { middleware.probe(active).await });
+        }
+
+        let result = next.run(self.rewrite(req, active), extensions).await;
+        self.record_outcome(active, &result);
+
+        result
+    }
+}
+
+fn is_connection_failure(result: &reqwest_middleware::Result<Response>) -> bool {
+    matches!(result, Err(reqwest_middleware::Error::Reqwest(e)) if e.is_connect() || e.is_timeout())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[derive(Debug, Default)]
+    struct Switches(Mutex<Vec<(String, String)>>);
+
+    impl RequestObserver for Switches {
+        fn on_failover(&self, from_host: &str, to_host: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((from_host.to_string(), to_host.to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn fails_over_after_threshold() {
+        // Nothing listens on the primary, the fallback is a mock server behind a base path
+        let primary = Url::parse("http://127.0.0.1:1").unwrap();
+        let mock_server = MockServer::start().await;
+        let fallback = Url::parse(&format!("{}/eu/", mock_server.uri())).unwrap();
+        Mock::given(path("/eu/test"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let switches = Arc::new(Switches::default());
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(FailoverMiddleware::new(
+                vec![
+                    [primary.clone(), primary.clone(), primary.clone()],
+                    [fallback.clone(), fallback.clone(), fallback],
+                ],
+                2,
+                Duration::from_secs(60),
+                primary.join("/.well-known/jwks").unwrap(),
+                reqwest::Client::new(),
+                vec![switches.clone()],
+            ))
+            .build();
+
+        // The first two requests fail against the primary
+        for _ in 0..2 {
+            assert!(client
+                .get(primary.join("/test").unwrap())
+                .send()
+                .await
+                .is_err());
+        }
+
+        // The third one goes to the fallback
+        let res = client
+            .get(primary.join("/test").unwrap())
+            .send()
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+        assert_eq!(
+            *switches.0.lock().unwrap(),
+            vec![("127.0.0.1".to_string(), "127.0.0.1".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn probes_primary_in_the_background() {
+        let primary_server = MockServer::start().await;
+        let primary = Url::parse(&primary_server.uri()).unwrap();
+        let fallback_server = MockServer::start().await;
+        let fallback = Url::parse(&fallback_server.uri()).unwrap();
+        Mock::given(method("GET"))
+            .and(path("/.well-known/jwks"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&primary_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/test"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&primary_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/test"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&fallback_server)
+            .await;
+
+        let switches = Arc::new(Switches::default());
+        let middleware = FailoverMiddleware::new(
+            vec![
+                [primary.clone(), primary.clone(), primary.clone()],
+                [fallback.clone(), fallback.clone(), fallback],
+            ],
+            1,
+            Duration::ZERO,
+            primary.join("/.well-known/jwks").unwrap(),
+            reqwest::Client::new(),
+            vec![switches.clone()],
+        );
+        middleware.state.lock().unwrap().active = 1;
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(middleware)
+            .build();
+
+        // The request does not wait for the probe and goes to the fallback
+        let res = client
+            .post(primary.join("/test").unwrap())
+            .send()
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+
+        // Once the probe succeeds, requests go to the primary again
+        for _ in 0..100 {
+            if !switches.0.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(switches.0.lock().unwrap().len(), 1);
+        let res = client
+            .post(primary.join("/test").unwrap())
+            .send()
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+    }
+}
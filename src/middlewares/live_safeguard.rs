@@ -0,0 +1,41 @@
+use crate::{safeguard::Confirmation, Error};
+use async_trait::async_trait;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use task_local_extensions::Extensions;
+
+/// Middleware which fails mutating requests to the Live environment not allowed by the
+/// [`Confirmation`] configured with
+/// [`with_live_safeguard`](crate::client::TrueLayerClientBuilder::with_live_safeguard),
+/// without sending them.
+#[derive(Debug, Clone)]
+pub struct LiveSafeguardMiddleware {
+    confirmation: Confirmation,
+}
+
+impl LiveSafeguardMiddleware {
+    pub fn new(confirmation: Confirmation) -> Self {
+        Self { confirmation }
+    }
+}
+
+#[async_trait]
+impl Middleware for LiveSafeguardMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if let Err(violation) = self.confirmation.check(
+            req.method(),
+            req.url().path(),
+            req.body().and_then(|b| b.as_bytes()),
+        ) {
+            tracing::error!(%violation, "Live call blocked by safeguard");
+            return Err(Error::LiveCallBlocked(violation).into());
+        }
+
+        next.run(req, extensions).await
+    }
+}
@@ -1,5 +1,18 @@
 pub mod authentication;
+pub mod body_limits;
+pub mod deprecation;
 pub mod error_handling;
+pub mod failover;
 pub mod inject_user_agent;
+pub mod live_safeguard;
+pub mod observer;
+pub mod rate_limiter;
+pub mod rate_limits;
+pub mod response_interceptor;
+pub mod retry_after;
 pub mod retry_idempotent;
+#[cfg(feature = "schema-validation")]
+pub mod schema_validation;
+pub mod scopes;
 pub mod signing;
+pub mod timeout;
@@ -0,0 +1,154 @@
+use crate::{
+    observer::{ObservedRequest, RequestObserver},
+    runtime::Instant,
+};
+use async_trait::async_trait;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use std::sync::Arc;
+use task_local_extensions::Extensions;
+
+/// Number of attempts of a call sent so far, shared by its retries through the [`Extensions`].
+#[derive(Debug, Clone, Copy)]
+struct Attempts(u32);
+
+/// Middleware which notifies the user-defined [`RequestObserver`]s of each attempt of each call.
+pub struct ObserverMiddleware {
+    observers: Vec<Arc<dyn RequestObserver>>,
+}
+
+impl ObserverMiddleware {
+    pub fn new(observers: Vec<Arc<dyn RequestObserver>>) -> Self {
+        Self { observers }
+    }
+}
+
+#[async_trait]
+impl Middleware for ObserverMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let attempt = extensions.get::<Attempts>().map_or(1, |a| a.0 + 1);
+        extensions.insert(Attempts(attempt));
+
+        let request = ObservedRequest::new(req.method().clone(), req.url(), attempt);
+        for observer in &self.observers {
+            if attempt > 1 {
+                observer.on_retry(&request);
+            }
+            observer.on_request(&request);
+        }
+
+        let start = Instant::now();
+        let res = next.run(req, extensions).await;
+        let latency = start.elapsed();
+
+        for observer in &self.observers {
+            match &res {
+                Ok(response) => observer.on_response(&request, response.status().as_u16(), latency),
+                Err(e) => observer.on_error(&request, e, latency),
+            }
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middlewares::retry_idempotent::{DynRetryPolicy, RetryIdempotentMiddleware};
+    use reqwest_retry::policies::ExponentialBackoff;
+    use std::{sync::Mutex, time::Duration};
+    use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+    #[derive(Debug, Default)]
+    struct RecordingObserver {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl RequestObserver for RecordingObserver {
+        fn on_request(&self, request: &ObservedRequest) {
+            self.record(format!(
+                "request {} #{}",
+                request.path_template, request.attempt
+            ));
+        }
+
+        fn on_response(&self, _request: &ObservedRequest, status: u16, _latency: Duration) {
+            self.record(format!("response {}", status));
+        }
+
+        fn on_retry(&self, request: &ObservedRequest) {
+            self.record(format!("retry #{}", request.attempt));
+        }
+
+        fn on_error(
+            &self,
+            _request: &ObservedRequest,
+            _error: &reqwest_middleware::Error,
+            _latency: Duration,
+        ) {
+            self.record("error".to_string());
+        }
+    }
+
+    impl RecordingObserver {
+        fn record(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    #[tokio::test]
+    async fn observers_see_every_attempt() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/payments/payment-id"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/payments/payment-id"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+
+        let observer = Arc::new(RecordingObserver::default());
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(RetryIdempotentMiddleware::new(DynRetryPolicy(Arc::new(
+                ExponentialBackoff::builder().build_with_max_retries(1),
+            ))))
+            .with(ObserverMiddleware::new(vec![
+                observer.clone() as Arc<dyn RequestObserver>
+            ]))
+            .build();
+
+        client
+            .get(format!("{}/payments/payment-id", mock_server.uri()))
+            .send()
+            .await
+            .unwrap();
+        assert!(client
+            .get("http://127.0.0.1:1/payouts")
+            .send()
+            .await
+            .is_err());
+
+        let events = observer.events.lock().unwrap();
+        assert_eq!(
+            events[..5],
+            [
+                "request /payments/{id} #1",
+                "response 503",
+                "retry #2",
+                "request /payments/{id} #2",
+                "response 200",
+            ]
+        );
+        // Calls which never receive a response are reported as errors, after being retried
+        assert_eq!(events[5..7], ["request /payouts #1", "error"]);
+        assert_eq!(events.last().unwrap(), "error");
+    }
+}
@@ -0,0 +1,110 @@
+use crate::runtime::Instant;
+use async_trait::async_trait;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use task_local_extensions::Extensions;
+
+/// Middleware which limits the rate of requests sent to TrueLayer with a token bucket,
+/// so that batch jobs queue up locally instead of tripping server-side rate limits.
+///
+/// The bucket holds up to one second worth of requests: short bursts are sent right away,
+/// then requests are spaced evenly. Clones share the same bucket.
+#[derive(Debug, Clone)]
+pub struct RateLimiterMiddleware {
+    requests_per_second: u32,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Available tokens. Negative when requests are waiting for tokens already reserved.
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiterMiddleware {
+    /// Allows at most `requests_per_second` requests per second, with a minimum of one.
+    pub fn new(requests_per_second: u32) -> Self {
+        let requests_per_second = requests_per_second.max(1);
+        Self {
+            requests_per_second,
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: requests_per_second as f64,
+                refilled_at: Instant::now(),
+            })),
+        }
+    }
+
+    /// Reserves a token, returning how long to wait before it becomes available.
+    fn reserve(&self) -> Duration {
+        let rate = self.requests_per_second as f64;
+        let mut bucket = self.bucket.lock().unwrap();
+
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.refilled_at).as_secs_f64() * rate;
+        bucket.tokens = (bucket.tokens + refill).min(rate);
+        bucket.refilled_at = now;
+
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / rate)
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for RateLimiterMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            tracing::debug!(?wait, "Client-side rate limit reached, delaying request");
+            crate::runtime::sleep(wait).await;
+        }
+
+        next.run(req, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn requests_beyond_the_burst_are_spaced_evenly() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(4)
+            .mount(&mock_server)
+            .await;
+
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(RateLimiterMiddleware::new(2))
+            .build();
+
+        // The first two requests fit in the burst
+        let started = Instant::now();
+        for _ in 0..2 {
+            client.get(mock_server.uri()).send().await.unwrap();
+        }
+        assert!(started.elapsed() < Duration::from_millis(400));
+
+        // The next two are sent half a second apart
+        for _ in 0..2 {
+            client.get(mock_server.uri()).send().await.unwrap();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(950));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{
+    observer::RequestObserver,
+    rate_limits::{RateLimitRegistry, RateLimitStatus},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use std::sync::Arc;
+use task_local_extensions::Extensions;
+
+/// Values of `RateLimit-Reset` above this threshold are Unix timestamps rather than delays in seconds.
+const RESET_EPOCH_THRESHOLD: i64 = 1_000_000_000;
+
+/// Middleware which inspects responses for rate limit headers and records the latest
+/// [`RateLimitStatus`](crate::rate_limits::RateLimitStatus) of each host, notifying the [`RequestObserver`]s.
+pub struct RateLimitHeadersMiddleware {
+    registry: RateLimitRegistry,
+    observers: Vec<Arc<dyn RequestObserver>>,
+}
+
+impl RateLimitHeadersMiddleware {
+    pub fn new(registry: RateLimitRegistry, observers: Vec<Arc<dyn RequestObserver>>) -> Self {
+        Self {
+            registry,
+            observers,
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for RateLimitHeadersMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let host = req.url().host_str().unwrap_or_default().to_string();
+
+        let response = next.run(req, extensions).await?;
+
+        // Support both the standard headers and their widespread `X-` variants
+        let header = |name: &str| {
+            [name.to_string(), format!("X-{}", name)]
+                .iter()
+                .find_map(|name| response.headers().get(name.as_str()))
+                .and_then(|v| v.to_str().ok())
+                .and_then(leading_number)
+        };
+        let limit = header("RateLimit-Limit");
+        let remaining = header("RateLimit-Remaining");
+        let reset = header("RateLimit-Reset");
+
+        if limit.is_some() || remaining.is_some() || reset.is_some() {
+            let now = Utc::now();
+            let status = RateLimitStatus {
+                host,
+                limit,
+                remaining,
+                reset_at: reset.and_then(|reset| reset_at(now, reset)),
+                observed_at: now,
+            };
+
+            tracing::debug!(
+                host = %status.host,
+                limit = ?status.limit,
+                remaining = ?status.remaining,
+                reset_at = ?status.reset_at,
+                "Rate limit headroom"
+            );
+            for observer in &self.observers {
+                observer.on_rate_limit_status(&status);
+            }
+            self.registry.record(status);
+        }
+
+        Ok(response)
+    }
+}
+
+/// Parses the leading integer of a header, ignoring policy parameters like in `100, 100;w=60`.
+fn leading_number(value: &str) -> Option<u64> {
+    value
+        .split(|c: char| c == ',' || c == ';')
+        .next()
+        .and_then(|v| v.trim().parse().ok())
+}
+
+fn reset_at(now: DateTime<Utc>, reset: u64) -> Option<DateTime<Utc>> {
+    let reset = i64::try_from(reset).ok()?;
+    if reset >= RESET_EPOCH_THRESHOLD {
+        Utc.timestamp_opt(reset, 0).single()
+    } else {
+        Some(now + Duration::seconds(reset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+    #[derive(Debug, Default)]
+    struct RecordingObserver {
+        statuses: Mutex<Vec<RateLimitStatus>>,
+    }
+
+    impl RequestObserver for RecordingObserver {
+        fn on_rate_limit_status(&self, status: &RateLimitStatus) {
+            self.statuses.lock().unwrap().push(status.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn rate_limit_headers_are_recorded() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/standard"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .append_header("RateLimit-Limit", "100, 100;w=60")
+                    .append_header("RateLimit-Remaining", "25")
+                    .append_header("RateLimit-Reset", "30"),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/legacy"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .append_header("X-RateLimit-Limit", "10")
+                    .append_header("X-RateLimit-Remaining", "0")
+                    .append_header("X-RateLimit-Reset", "1893456000"),
+            )
+            .mount(&mock_server)
+            .await;
+
+        let registry = RateLimitRegistry::default();
+        let observer = Arc::new(RecordingObserver::default());
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(RateLimitHeadersMiddleware::new(
+                registry.clone(),
+                vec![observer.clone() as Arc<dyn RequestObserver>],
+            ))
+            .build();
+
+        client
+            .get(format!("{}/standard", mock_server.uri()))
+            .send()
+            .await
+            .unwrap();
+        let statuses = registry.snapshot();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].host, "127.0.0.1");
+        assert_eq!(statuses[0].limit, Some(100));
+        assert_eq!(statuses[0].remaining, Some(25));
+        assert_eq!(statuses[0].headroom(), Some(0.25));
+        assert!(statuses[0].reset_at.unwrap() > statuses[0].observed_at);
+
+        // The latest status of each host replaces the previous one
+        client
+            .get(format!("{}/legacy", mock_server.uri()))
+            .send()
+            .await
+            .unwrap();
+        let statuses = registry.snapshot();
+        assert_eq!(statuses.len(), 1);
+        assert_eq!(statuses[0].headroom(), Some(0.0));
+        assert_eq!(
+            statuses[0].reset_at,
+            Some(Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap())
+        );
+
+        // Observers are notified of every status received
+        let observed = observer.statuses.lock().unwrap();
+        assert_eq!(observed.len(), 2);
+        assert_eq!(observed[0].remaining, Some(25));
+        assert_eq!(observed[1], statuses[0]);
+    }
+}
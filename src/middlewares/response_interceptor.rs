@@ -0,0 +1,92 @@
+use crate::{
+    audit::{ResponseInterceptor, ResponseSummary},
+    runtime::Instant,
+};
+use async_trait::async_trait;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use task_local_extensions::Extensions;
+
+/// Middleware which invokes the user-defined [`ResponseInterceptor`]s
+/// with a [`ResponseSummary`](crate::audit::ResponseSummary) of each completed call.
+pub struct ResponseInterceptorMiddleware {
+    interceptors: Vec<ResponseInterceptor>,
+}
+
+impl ResponseInterceptorMiddleware {
+    pub fn new(interceptors: Vec<ResponseInterceptor>) -> Self {
+        Self { interceptors }
+    }
+}
+
+#[async_trait]
+impl Middleware for ResponseInterceptorMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let method = req.method().clone();
+        let url = req.url().clone();
+
+        let start = Instant::now();
+        let res = next.run(req, extensions).await;
+        let duration = start.elapsed();
+
+        let status = res.as_ref().ok().map(|r| r.status().as_u16());
+        let summary = ResponseSummary::new(method, &url, status, duration);
+        for interceptor in &self.interceptors {
+            (interceptor.0)(&summary);
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::Method;
+    use std::sync::{Arc, Mutex};
+    use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn interceptors_receive_summaries() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/payments/payment-id"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+
+        let summaries = Arc::new(Mutex::new(Vec::new()));
+        let summaries_clone = summaries.clone();
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(ResponseInterceptorMiddleware::new(vec![
+                ResponseInterceptor(Arc::new(move |summary: &ResponseSummary| {
+                    summaries_clone.lock().unwrap().push(summary.clone())
+                })),
+            ]))
+            .build();
+
+        client
+            .get(format!("{}/payments/payment-id", mock_server.uri()))
+            .send()
+            .await
+            .unwrap();
+
+        // Calls which never receive a response are reported too
+        let unreachable = client.get("http://127.0.0.1:1/payouts").send().await;
+        assert!(unreachable.is_err());
+
+        let summaries = summaries.lock().unwrap();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].method, Method::GET);
+        assert_eq!(summaries[0].host, "127.0.0.1");
+        assert_eq!(summaries[0].path_template, "/payments/{id}");
+        assert_eq!(summaries[0].resource_ids, vec!["payment-id"]);
+        assert_eq!(summaries[0].status, Some(404));
+        assert_eq!(summaries[1].path_template, "/payouts");
+        assert_eq!(summaries[1].status, None);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::{
+    middlewares::retry_idempotent::is_idempotent,
+    rate_limits::{retry_after, RateLimitPolicy},
+};
+use async_trait::async_trait;
+use reqwest::{Request, Response, StatusCode};
+use reqwest_middleware::{Middleware, Next};
+use task_local_extensions::Extensions;
+
+/// Middleware which retries the idempotent requests rejected with `429 Too Many Requests`
+/// after the delay indicated by TrueLayer, according to a [`RateLimitPolicy`].
+#[derive(Clone)]
+pub struct RetryAfterMiddleware {
+    policy: RateLimitPolicy,
+}
+
+impl RetryAfterMiddleware {
+    pub fn new(policy: RateLimitPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+#[async_trait]
+impl Middleware for RetryAfterMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if !is_idempotent(&req) {
+            return next.run(req, extensions).await;
+        }
+
+        let mut retries = 0;
+        loop {
+            // Streaming bodies cannot be sent again
+            let attempt = match req.try_clone() {
+                Some(attempt) => attempt,
+                None => return next.run(req, extensions).await,
+            };
+
+            let response = next.clone().run(attempt, extensions).await?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS
+                || retries >= self.policy.max_retries
+            {
+                return Ok(response);
+            }
+
+            let wait = retry_after(response.headers()).unwrap_or(self.policy.default_wait);
+            if wait > self.policy.max_wait {
+                tracing::warn!(?wait, "Rate limited for too long, not retrying");
+                return Ok(response);
+            }
+
+            retries += 1;
+            tracing::info!(
+                ?wait,
+                retries,
+                "Rate limited, retrying after the requested delay"
+            );
+            crate::runtime::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{middlewares::error_handling::ErrorHandlingMiddleware, Error};
+    use std::time::Duration;
+    use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limited_requests_are_retried_after_the_requested_delay() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/"))
+            .respond_with(ResponseTemplate::new(429).append_header("Retry-After", "1"))
+            .expect(1)
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(RetryAfterMiddleware::new(RateLimitPolicy::new(1)))
+            .build();
+
+        let started = tokio::time::Instant::now();
+        let res = client.get(mock_server.uri()).send().await.unwrap();
+        assert!(res.status().is_success());
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn long_delays_are_surfaced_as_errors() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/"))
+            .respond_with(ResponseTemplate::new(429).append_header("Retry-After", "120"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(ErrorHandlingMiddleware)
+            .with(RetryAfterMiddleware::new(RateLimitPolicy::default()))
+            .build();
+
+        let err: Error = client
+            .get(mock_server.uri())
+            .send()
+            .await
+            .expect_err("Call succeeded")
+            .into();
+
+        assert!(matches!(
+            err,
+            Error::RateLimited {
+                retry_after: Some(retry_after),
+                ..
+            } if retry_after == Duration::from_secs(120)
+        ));
+    }
+}
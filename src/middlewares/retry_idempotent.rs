@@ -1,8 +1,8 @@
 use crate::common::IDEMPOTENCY_KEY_HEADER;
 use async_trait::async_trait;
-use reqwest::{Method, Request, Response};
+use chrono::Utc;
+use reqwest::{Method, Request, Response, StatusCode};
 use reqwest_middleware::{Middleware, Next};
-use reqwest_retry::RetryTransientMiddleware;
 use retry_policies::{RetryDecision, RetryPolicy};
 use std::{
     fmt::{Debug, Formatter},
@@ -18,15 +18,17 @@ use task_local_extensions::Extensions;
 ///
 /// For more information regarding idempotent methods, check section 4.2.2 of
 /// [RFC 7231](https://datatracker.ietf.org/doc/html/rfc7231#section-4.2.2).
+///
+/// `429 Too Many Requests` is not a transient failure here: it is either retried after the delay
+/// requested by TrueLayer by the [`RetryAfterMiddleware`](super::retry_after::RetryAfterMiddleware),
+/// or surfaced as [`Error::RateLimited`](crate::Error::RateLimited), but never retried with backoffs.
 pub struct RetryIdempotentMiddleware {
-    inner: RetryTransientMiddleware<DynRetryPolicy>,
+    retry_policy: DynRetryPolicy,
 }
 
 impl RetryIdempotentMiddleware {
     pub fn new(retry_policy: DynRetryPolicy) -> Self {
-        Self {
-            inner: RetryTransientMiddleware::new_with_policy(retry_policy),
-        }
+        Self { retry_policy }
     }
 }
 
@@ -38,29 +40,70 @@ impl Middleware for RetryIdempotentMiddleware {
         extensions: &mut Extensions,
         next: Next<'_>,
     ) -> reqwest_middleware::Result<Response> {
-        let is_idempotent = match *req.method() {
-            Method::GET
-            | Method::HEAD
-            | Method::OPTIONS
-            | Method::TRACE
-            | Method::PUT
-            | Method::DELETE => true,
-            Method::POST | Method::PATCH => req
-                .headers()
-                .get(IDEMPOTENCY_KEY_HEADER)
-                .map_or(false, |v| !v.is_empty()),
-            _ => false,
-        };
-
-        // If the request is idempotent, use the retry middleware, otherwise, do nothing
-        if is_idempotent {
-            self.inner.handle(req, extensions, next).await
-        } else {
-            next.run(req, extensions).await
+        // If the request is not idempotent, do nothing
+        if !is_idempotent(&req) {
+            return next.run(req, extensions).await;
+        }
+
+        let mut n_past_retries = 0;
+        loop {
+            // Streaming bodies cannot be sent again
+            let attempt = match req.try_clone() {
+                Some(attempt) => attempt,
+                None => return next.run(req, extensions).await,
+            };
+
+            let result = next.clone().run(attempt, extensions).await;
+            if !is_transient(&result) {
+                return result;
+            }
+
+            match self.retry_policy.should_retry(n_past_retries) {
+                RetryDecision::Retry { execute_after } => {
+                    let wait = (execute_after - Utc::now()).to_std().unwrap_or_default();
+                    n_past_retries += 1;
+                    tracing::warn!(
+                        ?wait,
+                        retries = n_past_retries,
+                        "Transient failure, retrying after a backoff"
+                    );
+                    crate::runtime::sleep(wait).await;
+                }
+                RetryDecision::DoNotRetry => return result,
+            }
         }
     }
 }
 
+/// Returns `true` for the failures worth retrying with backoffs: connection errors, timeouts,
+/// `408 Request Timeout` and `5xx` responses.
+fn is_transient(result: &reqwest_middleware::Result<Response>) -> bool {
+    match result {
+        Ok(response) => {
+            response.status().is_server_error() || response.status() == StatusCode::REQUEST_TIMEOUT
+        }
+        Err(reqwest_middleware::Error::Reqwest(e)) => e.is_connect() || e.is_timeout(),
+        Err(reqwest_middleware::Error::Middleware(_)) => false,
+    }
+}
+
+/// Returns `true` if the request can be safely sent more than once, see [`RetryIdempotentMiddleware`].
+pub(crate) fn is_idempotent(req: &Request) -> bool {
+    match *req.method() {
+        Method::GET
+        | Method::HEAD
+        | Method::OPTIONS
+        | Method::TRACE
+        | Method::PUT
+        | Method::DELETE => true,
+        Method::POST | Method::PATCH => req
+            .headers()
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .map_or(false, |v| !v.is_empty()),
+        _ => false,
+    }
+}
+
 /// Wrapper type around a retry policy because `dyn RetryPolicy` does not implement `RetryPolicy`.
 #[derive(Clone)]
 pub struct DynRetryPolicy(pub Arc<dyn RetryPolicy + Send + Sync + 'static>);
@@ -85,11 +128,20 @@ mod tests {
     use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
 
     async fn mock_client_and_server(expects_retry: bool) -> (ClientWithMiddleware, MockServer) {
-        // Configure a mock server that returns 429 Too Many Requests on the first request,
+        // Configure a mock server that returns 503 Service Unavailable on the first request,
+        // and 200 on the second one.
+        mock_client_and_server_with_status(503, expects_retry).await
+    }
+
+    async fn mock_client_and_server_with_status(
+        first_status: u16,
+        expects_retry: bool,
+    ) -> (ClientWithMiddleware, MockServer) {
+        // Configure a mock server that returns `first_status` on the first request,
         // and 200 on the second one.
         let mock_server = MockServer::start().await;
         Mock::given(path("/"))
-            .respond_with(ResponseTemplate::new(429)) // Too Many Requests
+            .respond_with(ResponseTemplate::new(first_status))
             .expect(1)
             .up_to_n_times(1)
             .mount(&mock_server)
@@ -147,6 +199,37 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn retries_transient_server_errors() {
+        for status in [500, 502, 503, 504] {
+            let (client, mock_server) = mock_client_and_server_with_status(status, true).await;
+
+            let res = client
+                .post(mock_server.uri())
+                .header(IDEMPOTENCY_KEY_HEADER, "some-idempotency-key")
+                .send()
+                .await
+                .unwrap();
+            assert!(res.status().is_success(), "Status: {}", status);
+        }
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let (client, mock_server) = mock_client_and_server_with_status(400, false).await;
+
+        let res = client.get(mock_server.uri()).send().await.unwrap();
+        assert_eq!(res.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_rate_limited_requests() {
+        let (client, mock_server) = mock_client_and_server_with_status(429, false).await;
+
+        let res = client.get(mock_server.uri()).send().await.unwrap();
+        assert_eq!(res.status(), 429);
+    }
+
     #[tokio::test]
     async fn does_not_retry_post_patch_without_idempotency_key() {
         for method in [Method::POST, Method::PATCH] {
@@ -157,7 +240,7 @@ mod tests {
                 .send()
                 .await
                 .unwrap();
-            assert!(res.status().is_client_error());
+            assert!(res.status().is_server_error());
         }
     }
 
@@ -172,7 +255,7 @@ mod tests {
                 .send()
                 .await
                 .unwrap();
-            assert!(res.status().is_client_error());
+            assert!(res.status().is_server_error());
         }
     }
 }
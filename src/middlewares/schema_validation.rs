@@ -0,0 +1,34 @@
+use crate::{schema_validation::validate_request, Error};
+use async_trait::async_trait;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use serde_json::Value;
+use task_local_extensions::Extensions;
+
+/// Middleware which rejects requests whose JSON body does not conform to the schema of their endpoint,
+/// see [`schema_validation`](crate::schema_validation).
+#[derive(Debug, Clone)]
+pub struct SchemaValidationMiddleware;
+
+#[async_trait]
+impl Middleware for SchemaValidationMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        // Streaming and non-JSON bodies cannot be validated
+        let body = req
+            .body()
+            .and_then(|body| body.as_bytes())
+            .and_then(|bytes| serde_json::from_slice::<Value>(bytes).ok());
+
+        if let Some(body) = body {
+            validate_request(req.method().as_str(), req.url().path(), &body)
+                .map_err(Error::from)?;
+        }
+
+        next.run(req, extensions).await
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{client::ApiGroup, Error};
+use async_trait::async_trait;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use task_local_extensions::Extensions;
+
+/// Middleware which fails requests to APIs not covered by the scopes configured with
+/// [`with_scopes`](crate::client::TrueLayerClientBuilder::with_scopes), without sending them.
+#[derive(Debug, Clone)]
+pub struct ScopesMiddleware {
+    scopes: Vec<String>,
+}
+
+impl ScopesMiddleware {
+    pub fn new(scopes: Vec<String>) -> Self {
+        Self { scopes }
+    }
+}
+
+#[async_trait]
+impl Middleware for ScopesMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let path = req.url().path();
+        if let Some(api_group) = ApiGroup::from_path(path) {
+            let required = api_group.required_scopes_for(path);
+            if !required
+                .iter()
+                .any(|scope| self.scopes.iter().any(|s| s == scope))
+            {
+                return Err(Error::MissingScope {
+                    api_group,
+                    required,
+                    granted: self.scopes.clone(),
+                }
+                .into());
+            }
+        }
+
+        next.run(req, extensions).await
+    }
+}
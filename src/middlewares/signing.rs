@@ -3,16 +3,25 @@ use crate::{
     error::Error,
 };
 use async_trait::async_trait;
-use reqwest::{header::HeaderValue, Method, Request, Response};
+use reqwest::{
+    header::{HeaderValue, CONTENT_TYPE},
+    Method, Request, Response,
+};
 use reqwest_middleware::{Middleware, Next};
+use serde_json::Value;
 use task_local_extensions::Extensions;
 
 /// Middleware to attach signatures to all outgoing `POST`, `PUT` and `DELETE` requests.
 ///
 /// Uses [`truelayer_signing`](truelayer_signing) to build the signatures.
+///
+/// If `canonicalize_json` is set, JSON bodies are rewritten in canonical form before being signed,
+/// so that the signature is computed over exactly the bytes that are sent.
+#[derive(Clone)]
 pub struct SigningMiddleware {
     pub(crate) key_id: String,
     pub(crate) private_key: Vec<u8>,
+    pub(crate) canonicalize_json: bool,
 }
 
 #[async_trait]
@@ -35,6 +44,18 @@ impl Middleware for SigningMiddleware {
                 signer = signer.header(IDEMPOTENCY_KEY_HEADER, idempotency_key.as_bytes());
             }
 
+            // Rewrite JSON bodies in canonical form
+            if self.canonicalize_json && is_json(&req) {
+                if let Some(body) = req.body() {
+                    let bytes = body
+                        .as_bytes()
+                        .ok_or_else(|| anyhow::anyhow!("Cannot sign a streaming request body"))?;
+                    let canonical = canonicalize_json(bytes)
+                        .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?;
+                    *req.body_mut() = Some(canonical.into());
+                }
+            }
+
             // Include the body
             if let Some(body) = req.body() {
                 let bytes = body
@@ -48,12 +69,69 @@ impl Middleware for SigningMiddleware {
             let header_value = HeaderValue::from_str(&signature)
                 .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?;
             req.headers_mut().insert(TL_SIGNATURE_HEADER, header_value);
+
+            // Let the error handling tell signature rejections apart
+            extensions.insert(SignedRequest);
         }
 
         next.run(req, extensions).await
     }
 }
 
+/// Marker added to the [`Extensions`] of the requests which have been signed.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SignedRequest;
+
+fn is_json(req: &Request) -> bool {
+    req.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("application/json"))
+        .unwrap_or(false)
+}
+
+/// Rewrites a JSON document in canonical form: object keys sorted lexicographically
+/// and no insignificant whitespace.
+pub(crate) fn canonicalize_json(bytes: &[u8]) -> Result<Vec<u8>, serde_json::Error> {
+    let value: Value = serde_json::from_slice(bytes)?;
+    let mut out = Vec::with_capacity(bytes.len());
+    write_canonical(&mut out, &value)?;
+    Ok(out)
+}
+
+fn write_canonical(out: &mut Vec<u8>, value: &Value) -> Result<(), serde_json::Error> {
+    match value {
+        // Do not rely on the ordering of `serde_json::Map`, which depends on the `preserve_order` feature
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+            out.push(b'{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                serde_json::to_writer(&mut *out, key)?;
+                out.push(b':');
+                write_canonical(out, value)?;
+            }
+            out.push(b'}');
+        }
+        Value::Array(values) => {
+            out.push(b'[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_canonical(out, value)?;
+            }
+            out.push(b']');
+        }
+        scalar => serde_json::to_writer(&mut *out, scalar)?,
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -66,7 +144,7 @@ mod tests {
     use std::str::FromStr;
     use wiremock::{http::HeaderName, matchers::path, Mock, MockServer, ResponseTemplate};
 
-    fn mock_client() -> (ClientWithMiddleware, EcKey<Private>) {
+    fn mock_client(canonicalize_json: bool) -> (ClientWithMiddleware, EcKey<Private>) {
         // Generate a new EC private key
         let key = EcKey::generate(&EcGroup::from_curve_name(Nid::SECP521R1).unwrap()).unwrap();
 
@@ -74,6 +152,7 @@ mod tests {
             .with(SigningMiddleware {
                 key_id: "mock-key-id".to_string(),
                 private_key: key.private_key_to_pem().unwrap(),
+                canonicalize_json,
             })
             .build();
 
@@ -105,7 +184,7 @@ mod tests {
         ];
 
         // Send a test request for all the method names
-        let (client, key) = mock_client();
+        let (client, key) = mock_client(false);
         for (method, expected_signature) in table {
             let idempotency_key = format!("idempotency-key-value-{}", method.as_str());
 
@@ -139,4 +218,87 @@ mod tests {
             }
         }
     }
+
+    // Signature of the canonical body below, created with the private key matching `KNOWN_GOOD_PUBLIC_KEY`.
+    // It must keep verifying regardless of how the models order their fields.
+    const KNOWN_GOOD_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIGbMBAGByqGSM49AgEGBSuBBAAjA4GGAAQAfclQr0AkXqBBVnSQ36sc/z4g/A/r
+L5B4KACZK2RxhpvaVKFB4uat+axlR8nsORB8+CkoEU85fNab5fnG20Ma0MEBcz+J
+q/jJXBhrLcoxzZc/rEV9xEmwP17w6bIHpOx3UQ/wbyKs9U6fzZCSXjjDvCJgt69I
+oS3AKafbu8Z9AiFo6oE=
+-----END PUBLIC KEY-----
+";
+    const KNOWN_GOOD_SIGNATURE: &str = "eyJhbGciOiJFUzUxMiIsImtpZCI6InRlc3Qta2V5LWlkIiwidGxfdmVyc2lvbiI6IjIiLCJ0bF9oZWFkZXJzIjoiSWRlbXBvdGVuY3ktS2V5In0..AQ95kbdZJkvfWoW4OYqMPD8pgu5xnk5XwbT-DTPqZp9EqAWNQ4ANASBc-vUH-JBpmUDd4MLrm8LjPI6ntHdXCsTbAFy8zaT92Mwn9huAr-pB2BwiHfRQeZYQZGM_GWDXfnJwd2w8jTZgA9eq_FLAGyQrsI3G919aj6MmZ4AdDr6qDBul";
+    const KNOWN_GOOD_BODY: &str = r#"{"amount_in_minor":100,"currency":"GBP","metadata":{"a":"1","b":"2"},"reference":"ref"}"#;
+
+    #[test]
+    fn canonical_json_matches_known_good_signature() {
+        let shuffled = br#"{
+            "reference": "ref",
+            "metadata": { "b": "2", "a": "1" },
+            "currency": "GBP",
+            "amount_in_minor": 100
+        }"#;
+
+        let canonical = canonicalize_json(shuffled).unwrap();
+        assert_eq!(std::str::from_utf8(&canonical).unwrap(), KNOWN_GOOD_BODY);
+
+        truelayer_signing::verify_with_pem(KNOWN_GOOD_PUBLIC_KEY.as_bytes())
+            .method("POST")
+            .path("/payments")
+            .header(IDEMPOTENCY_KEY_HEADER, b"idempotency-key")
+            .body(&canonical)
+            .verify(KNOWN_GOOD_SIGNATURE)
+            .unwrap();
+    }
+
+    #[test]
+    fn canonical_json_preserves_arrays_and_escapes() {
+        let canonical =
+            canonicalize_json(br#"{"b":[3,1,{"z":null,"y":"\u00e9\"q\""}],"a":1.5}"#).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&canonical).unwrap(),
+            r#"{"a":1.5,"b":[3,1,{"y":"é\"q\"","z":null}]}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn signs_the_transmitted_canonical_body() {
+        // Echo back the body and the signature
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/test"))
+            .respond_with(|req: &wiremock::Request| {
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                    "body": String::from_utf8(req.body.clone()).unwrap(),
+                    "signature": req.headers
+                        .get(&HeaderName::from_str(TL_SIGNATURE_HEADER).unwrap())
+                        .map(|v| v.last().to_string())
+                        .unwrap_or_default(),
+                }))
+            })
+            .mount(&mock_server)
+            .await;
+
+        let (client, key) = mock_client(true);
+        let res: Value = client
+            .post(format!("{}/test", mock_server.uri()))
+            .header(CONTENT_TYPE, "application/json")
+            .body(r#"{ "b": 1, "a": 2 }"#)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        let body = res["body"].as_str().unwrap();
+        assert_eq!(body, r#"{"a":2,"b":1}"#);
+
+        truelayer_signing::verify_with_pem(key.public_key_to_pem().unwrap().as_slice())
+            .method("POST")
+            .path("/test")
+            .body(body.as_bytes())
+            .verify(res["signature"].as_str().unwrap())
+            .unwrap();
+    }
 }
@@ -0,0 +1,31 @@
+use async_trait::async_trait;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+use std::time::Duration;
+use task_local_extensions::Extensions;
+
+/// Middleware applying a default timeout to the requests which were not given one for the call,
+/// whatever HTTP client they are sent with.
+#[derive(Clone)]
+pub struct DefaultTimeoutMiddleware {
+    timeout: Duration,
+}
+
+impl DefaultTimeoutMiddleware {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+#[async_trait]
+impl Middleware for DefaultTimeoutMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        req.timeout_mut().get_or_insert(self.timeout);
+        next.run(req, extensions).await
+    }
+}
@@ -0,0 +1,375 @@
+//! Helpers to migrate integrations built on the legacy PayDirect APIs
+//! onto the Payments V3 models exposed by this crate.
+//!
+//! PayDirect modeled money movements as *deposits* (a user funding their balance with a payment
+//! into the merchant account) and *withdrawals* (money leaving the merchant account, either back
+//! to the account the user deposited from or to any other account). In V3, deposits are regular
+//! payments to a [`Beneficiary::MerchantAccount`] and withdrawals are payouts.
+//!
+//! The conversions are checked: anything that cannot be expressed in V3 is reported
+//! as a [`MigrationError`] rather than silently dropped.
+
+use crate::apis::{
+    payments::{
+        AccountIdentifier, Beneficiary, CreatePaymentRequest, CreatePaymentUserRequest, Currency,
+        PaymentMethodRequest, ProviderSelectionRequest,
+    },
+    payouts::{CreatePayoutRequest, PayoutBeneficiary},
+};
+
+/// Error returned when a legacy PayDirect concept cannot be mapped onto V3 models.
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum MigrationError {
+    /// The currency is not supported by the V3 APIs.
+    #[error("Unsupported currency: {0}")]
+    UnsupportedCurrency(String),
+    /// The legacy beneficiary has no account details.
+    #[error("The beneficiary has neither an IBAN nor a sort code and account number")]
+    MissingAccountIdentifier,
+    /// The legacy beneficiary has more than one set of account details.
+    #[error("The beneficiary has both an IBAN and a sort code and account number")]
+    AmbiguousAccountIdentifier,
+    /// Only one of sort code and account number was provided.
+    #[error("Sort code and account number must be provided together")]
+    IncompleteSortCodeAccountNumber,
+    /// The deposit has neither an existing user id nor any detail to create a new user.
+    #[error("The deposit has neither a user id nor a user name, email or phone")]
+    MissingUserDetails,
+    /// The deposit preselected a provider without the payment scheme, which V3 requires.
+    #[error("A scheme id must be provided when preselecting a provider")]
+    MissingSchemeId,
+    /// The withdrawal context code has no equivalent in V3 payouts.
+    #[error("Unsupported withdrawal context code: {0}")]
+    UnsupportedContextCode(String),
+}
+
+/// Legacy PayDirect withdrawal.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LegacyWithdrawal {
+    pub user_id: String,
+    pub merchant_account_id: String,
+    pub amount_in_minor: u64,
+    /// ISO 4217 currency code.
+    pub currency: String,
+    /// Optional context code of the withdrawal (e.g., `withdrawal` or `service_payment`).
+    pub context_code: Option<String>,
+    pub beneficiary: LegacyWithdrawalBeneficiary,
+}
+
+/// Destination of a legacy PayDirect withdrawal.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LegacyWithdrawalBeneficiary {
+    /// Closed-loop withdrawal, back to an account the user previously deposited from.
+    ///
+    /// The legacy account id maps onto the id of a V3 payment source.
+    ClosedLoop {
+        account_id: String,
+        reference: String,
+    },
+    /// Open-loop withdrawal, to any account.
+    OpenLoop {
+        beneficiary_name: String,
+        beneficiary_iban: Option<String>,
+        beneficiary_sort_code: Option<String>,
+        beneficiary_account_number: Option<String>,
+        beneficiary_reference: String,
+    },
+}
+
+impl TryFrom<LegacyWithdrawal> for CreatePayoutRequest {
+    type Error = MigrationError;
+
+    fn try_from(withdrawal: LegacyWithdrawal) -> Result<Self, Self::Error> {
+        // V3 payouts do not carry a context code: only plain withdrawals can be migrated
+        if let Some(context_code) = withdrawal.context_code {
+            if context_code != "withdrawal" {
+                return Err(MigrationError::UnsupportedContextCode(context_code));
+            }
+        }
+
+        let beneficiary = match withdrawal.beneficiary {
+            LegacyWithdrawalBeneficiary::ClosedLoop {
+                account_id,
+                reference,
+            } => PayoutBeneficiary::PaymentSource {
+                user_id: withdrawal.user_id,
+                payment_source_id: account_id,
+                reference,
+            },
+            LegacyWithdrawalBeneficiary::OpenLoop {
+                beneficiary_name,
+                beneficiary_iban,
+                beneficiary_sort_code,
+                beneficiary_account_number,
+                beneficiary_reference,
+            } => PayoutBeneficiary::ExternalAccount {
+                account_holder_name: beneficiary_name,
+                account_identifier: account_identifier(
+                    beneficiary_iban,
+                    beneficiary_sort_code,
+                    beneficiary_account_number,
+                )?,
+                reference: beneficiary_reference,
+            },
+        };
+
+        Ok(CreatePayoutRequest {
+            merchant_account_id: withdrawal.merchant_account_id,
+            amount_in_minor: withdrawal.amount_in_minor,
+            currency: currency(&withdrawal.currency)?,
+            beneficiary,
+            scheme_selection: None,
+        })
+    }
+}
+
+/// Legacy PayDirect deposit.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LegacyDeposit {
+    /// Id of an existing user. If `None`, a new user is created from the other details.
+    pub user_id: Option<String>,
+    pub user_name: Option<String>,
+    pub user_email: Option<String>,
+    pub user_phone: Option<String>,
+    pub merchant_account_id: String,
+    pub amount_in_minor: u64,
+    /// ISO 4217 currency code.
+    pub currency: String,
+    pub provider_id: Option<String>,
+    pub scheme_id: Option<String>,
+}
+
+impl TryFrom<LegacyDeposit> for CreatePaymentRequest {
+    type Error = MigrationError;
+
+    fn try_from(deposit: LegacyDeposit) -> Result<Self, Self::Error> {
+        let user = match deposit.user_id {
+            Some(id) => CreatePaymentUserRequest::ExistingUser { id },
+            None if deposit.user_name.is_none()
+                && deposit.user_email.is_none()
+                && deposit.user_phone.is_none() =>
+            {
+                return Err(MigrationError::MissingUserDetails)
+            }
+            None => CreatePaymentUserRequest::NewUser {
+                name: deposit.user_name,
+                email: deposit.user_email,
+                phone: deposit.user_phone,
+            },
+        };
+
+        let provider_selection = match (deposit.provider_id, deposit.scheme_id) {
+            (Some(provider_id), Some(scheme_id)) => ProviderSelectionRequest::Preselected {
+                provider_id,
+                scheme_id,
+                remitter: None,
+            },
+            (Some(_), None) => return Err(MigrationError::MissingSchemeId),
+            (None, _) => ProviderSelectionRequest::UserSelected {
+                filter: None,
+                scheme_selection: None,
+            },
+        };
+
+        Ok(CreatePaymentRequest {
+            amount_in_minor: deposit.amount_in_minor,
+            currency: currency(&deposit.currency)?,
+            payment_method: PaymentMethodRequest::BankTransfer {
+                provider_selection,
+                beneficiary: Beneficiary::MerchantAccount {
+                    merchant_account_id: deposit.merchant_account_id,
+                    account_holder_name: None,
+                },
+            },
+            user,
+            metadata: None,
+            retry: None,
+            related_products: None,
+        })
+    }
+}
+
+fn currency(code: &str) -> Result<Currency, MigrationError> {
+    match code.to_ascii_uppercase().as_str() {
+        "EUR" => Ok(Currency::Eur),
+        "GBP" => Ok(Currency::Gbp),
+        "NOK" => Ok(Currency::Nok),
+        "PLN" => Ok(Currency::Pln),
+        _ => Err(MigrationError::UnsupportedCurrency(code.to_string())),
+    }
+}
+
+fn account_identifier(
+    iban: Option<String>,
+    sort_code: Option<String>,
+    account_number: Option<String>,
+) -> Result<AccountIdentifier, MigrationError> {
+    match (iban, sort_code, account_number) {
+        (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+            Err(MigrationError::AmbiguousAccountIdentifier)
+        }
+        (Some(iban), None, None) => Ok(AccountIdentifier::Iban { iban }),
+        (None, Some(sort_code), Some(account_number)) => {
+            Ok(AccountIdentifier::SortCodeAccountNumber {
+                sort_code,
+                account_number,
+            })
+        }
+        (None, Some(_), None) | (None, None, Some(_)) => {
+            Err(MigrationError::IncompleteSortCodeAccountNumber)
+        }
+        (None, None, None) => Err(MigrationError::MissingAccountIdentifier),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_loop_withdrawal(
+        iban: Option<&str>,
+        sort_code: Option<&str>,
+        account_number: Option<&str>,
+    ) -> LegacyWithdrawal {
+        LegacyWithdrawal {
+            user_id: "user-id".to_string(),
+            merchant_account_id: "merchant-account-id".to_string(),
+            amount_in_minor: 100,
+            currency: "gbp".to_string(),
+            context_code: Some("withdrawal".to_string()),
+            beneficiary: LegacyWithdrawalBeneficiary::OpenLoop {
+                beneficiary_name: "Mr. Holder".to_string(),
+                beneficiary_iban: iban.map(String::from),
+                beneficiary_sort_code: sort_code.map(String::from),
+                beneficiary_account_number: account_number.map(String::from),
+                beneficiary_reference: "some-reference".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn withdrawals_map_to_payouts() {
+        let payout = CreatePayoutRequest::try_from(open_loop_withdrawal(
+            None,
+            Some("123456"),
+            Some("12345678"),
+        ))
+        .unwrap();
+        assert_eq!(payout.currency, Currency::Gbp);
+        assert_eq!(
+            payout.beneficiary,
+            PayoutBeneficiary::ExternalAccount {
+                account_holder_name: "Mr. Holder".to_string(),
+                account_identifier: AccountIdentifier::SortCodeAccountNumber {
+                    sort_code: "123456".to_string(),
+                    account_number: "12345678".to_string(),
+                },
+                reference: "some-reference".to_string(),
+            }
+        );
+
+        let payout = CreatePayoutRequest::try_from(LegacyWithdrawal {
+            context_code: None,
+            beneficiary: LegacyWithdrawalBeneficiary::ClosedLoop {
+                account_id: "account-id".to_string(),
+                reference: "some-reference".to_string(),
+            },
+            ..open_loop_withdrawal(None, None, None)
+        })
+        .unwrap();
+        assert_eq!(
+            payout.beneficiary,
+            PayoutBeneficiary::PaymentSource {
+                user_id: "user-id".to_string(),
+                payment_source_id: "account-id".to_string(),
+                reference: "some-reference".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn unmappable_withdrawals_are_rejected() {
+        for (withdrawal, error) in [
+            (
+                open_loop_withdrawal(None, None, None),
+                MigrationError::MissingAccountIdentifier,
+            ),
+            (
+                open_loop_withdrawal(Some("some-iban"), Some("123456"), None),
+                MigrationError::AmbiguousAccountIdentifier,
+            ),
+            (
+                open_loop_withdrawal(None, Some("123456"), None),
+                MigrationError::IncompleteSortCodeAccountNumber,
+            ),
+            (
+                LegacyWithdrawal {
+                    currency: "USD".to_string(),
+                    ..open_loop_withdrawal(Some("some-iban"), None, None)
+                },
+                MigrationError::UnsupportedCurrency("USD".to_string()),
+            ),
+            (
+                LegacyWithdrawal {
+                    context_code: Some("service_payment".to_string()),
+                    ..open_loop_withdrawal(Some("some-iban"), None, None)
+                },
+                MigrationError::UnsupportedContextCode("service_payment".to_string()),
+            ),
+        ] {
+            assert_eq!(
+                CreatePayoutRequest::try_from(withdrawal).unwrap_err(),
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn deposits_map_to_payments() {
+        let deposit = LegacyDeposit {
+            user_id: None,
+            user_name: Some("Some One".to_string()),
+            user_email: None,
+            user_phone: None,
+            merchant_account_id: "merchant-account-id".to_string(),
+            amount_in_minor: 100,
+            currency: "EUR".to_string(),
+            provider_id: Some("provider-id".to_string()),
+            scheme_id: Some("scheme-id".to_string()),
+        };
+
+        let payment = CreatePaymentRequest::try_from(deposit.clone()).unwrap();
+        assert_eq!(payment.currency, Currency::Eur);
+        assert_eq!(
+            payment.payment_method,
+            PaymentMethodRequest::BankTransfer {
+                provider_selection: ProviderSelectionRequest::Preselected {
+                    provider_id: "provider-id".to_string(),
+                    scheme_id: "scheme-id".to_string(),
+                    remitter: None,
+                },
+                beneficiary: Beneficiary::MerchantAccount {
+                    merchant_account_id: "merchant-account-id".to_string(),
+                    account_holder_name: None,
+                },
+            }
+        );
+
+        assert_eq!(
+            CreatePaymentRequest::try_from(LegacyDeposit {
+                scheme_id: None,
+                ..deposit.clone()
+            })
+            .unwrap_err(),
+            MigrationError::MissingSchemeId
+        );
+        assert_eq!(
+            CreatePaymentRequest::try_from(LegacyDeposit {
+                user_name: None,
+                ..deposit
+            })
+            .unwrap_err(),
+            MigrationError::MissingUserDetails
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Hooks to observe the HTTP calls sent to TrueLayer, e.g. to feed Prometheus or OpenTelemetry metrics.
+//!
+//! [`RequestObserver`]s registered with
+//! [`with_request_observer`](crate::client::TrueLayerClientBuilder::with_request_observer)
+//! are notified of every attempt of every call, including calls to the Auth server, and of the
+//! [rate limit states](crate::rate_limits) reported by TrueLayer.
+//! Like [response interceptors](crate::audit), they never see request or response bodies, nor any header.
+//!
+//! ```rust,no_run
+//! # use truelayer_rust::{apis::auth::Credentials, observer::{ObservedRequest, RequestObserver}, TrueLayerClient};
+//! # use std::{sync::atomic::{AtomicU64, Ordering}, time::Duration};
+//! #[derive(Debug, Default)]
+//! struct Metrics {
+//!     retries: AtomicU64,
+//! }
+//!
+//! impl RequestObserver for Metrics {
+//!     fn on_response(&self, request: &ObservedRequest, status: u16, latency: Duration) {
+//!         tracing::info!(path = %request.path_template, status, latency_ms = latency.as_millis() as u64);
+//!     }
+//!
+//!     fn on_retry(&self, _request: &ObservedRequest) {
+//!         self.retries.fetch_add(1, Ordering::Relaxed);
+//!     }
+//! }
+//!
+//! # let credentials: Credentials = unreachable!();
+//! let tl = TrueLayerClient::builder(credentials)
+//!     .with_request_observer(Metrics::default())
+//!     .build();
+//! ```
+
+use crate::{audit::templatize_path, rate_limits::RateLimitStatus};
+use reqwest::{Method, Url};
+use std::{fmt::Debug, time::Duration};
+
+/// HTTP call observed by a [`RequestObserver`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ObservedRequest {
+    /// HTTP method of the request.
+    pub method: Method,
+    /// Host to which the request is sent.
+    pub host: String,
+    /// Path of the request, with all the resource ids replaced by `{id}`, e.g. `/payments/{id}`.
+    /// Suitable as a low-cardinality metric label.
+    pub path_template: String,
+    /// Attempt number of the call, starting from 1. Greater attempts are retries.
+    pub attempt: u32,
+}
+
+impl ObservedRequest {
+    pub(crate) fn new(method: Method, url: &Url, attempt: u32) -> Self {
+        Self {
+            method,
+            host: url.host_str().unwrap_or_default().to_string(),
+            path_template: templatize_path(url.path()).0,
+            attempt,
+        }
+    }
+}
+
+/// Observer notified of the lifecycle of every HTTP call sent to TrueLayer.
+///
+/// All the methods do nothing by default. They run synchronously on the task which sent
+/// the request, so they should not block.
+pub trait RequestObserver: Debug + Send + Sync {
+    /// Invoked right before each attempt is sent, including retries.
+    fn on_request(&self, _request: &ObservedRequest) {}
+
+    /// Invoked when a response is received, whatever its status code.
+    /// `latency` is the time elapsed between sending the attempt and receiving the response headers.
+    fn on_response(&self, _request: &ObservedRequest, _status: u16, _latency: Duration) {}
+
+    /// Invoked before an attempt retrying a previous one is sent, right before [`on_request`](RequestObserver::on_request).
+    fn on_retry(&self, _request: &ObservedRequest) {}
+
+    /// Invoked when no response is received, e.g. because of a network error or a timeout.
+    fn on_error(
+        &self,
+        _request: &ObservedRequest,
+        _error: &reqwest_middleware::Error,
+        _latency: Duration,
+    ) {
+    }
+
+    /// Invoked when the client switches between the environments configured with
+    /// [`Environment::with_fallbacks`](crate::client::Environment::with_fallbacks), either failing
+    /// over to a fallback or failing back to the primary environment.
+    ///
+    /// Hosts are the ones of the payments APIs of each environment.
+    fn on_failover(&self, _from_host: &str, _to_host: &str) {}
+
+    /// Invoked when a response reports the state of the rate limits of its host, e.g. to export
+    /// the [`headroom`](RateLimitStatus::headroom) of each host as a gauge.
+    ///
+    /// The latest state of each host is also available through
+    /// [`TrueLayerClient::rate_limit_snapshot`](crate::client::TrueLayerClient::rate_limit_snapshot).
+    fn on_rate_limit_status(&self, _status: &RateLimitStatus) {}
+}
@@ -0,0 +1,174 @@
+//! Cursor-based pagination of TrueLayer lists.
+//!
+//! Paginated endpoints return one [`Paginated`] page at a time, with the cursor of the next page.
+//! The API clients expose both the single pages and `*_stream` methods which return a
+//! [`Stream`](futures::Stream) of all the items, fetching the following pages lazily as the stream
+//! is consumed, so that long lists can be processed without holding them all in memory.
+
+use crate::Error;
+use futures::{
+    stream::{self, BoxStream},
+    Future, StreamExt, TryStreamExt,
+};
+use serde::{Deserialize, Serialize};
+
+/// Single page of a paginated list.
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    /// Cursor of the next page, or `None` if this is the last page.
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Paginated<T> {
+    /// Turns this page into a stream of its items followed by the items of all the next pages.
+    ///
+    /// `fetch_page` is called with the cursor of each following page, only once all the items
+    /// of the previous page have been consumed.
+    pub fn into_stream<'a, F, Fut>(self, mut fetch_page: F) -> BoxStream<'a, Result<T, Error>>
+    where
+        T: Send + 'a,
+        F: FnMut(String) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<Paginated<T>, Error>> + Send + 'a,
+    {
+        unfold_pages(Next::Page(self), move |cursor: Option<String>| {
+            fetch_page(cursor.unwrap_or_default())
+        })
+    }
+}
+
+/// Returns a stream of all the items of a paginated list, starting from the first page.
+///
+/// `fetch_page` is called with `None` to fetch the first page, then with the cursor of each
+/// following page, only once all the items of the previous page have been consumed.
+pub fn paginate<'a, T, F, Fut>(fetch_page: F) -> BoxStream<'a, Result<T, Error>>
+where
+    T: Send + 'a,
+    F: FnMut(Option<String>) -> Fut + Send + 'a,
+    Fut: Future<Output = Result<Paginated<T>, Error>> + Send + 'a,
+{
+    unfold_pages(Next::First, fetch_page)
+}
+
+enum Next<T> {
+    First,
+    Page(Paginated<T>),
+    Cursor(String),
+    Done,
+}
+
+fn unfold_pages<'a, T, F, Fut>(first: Next<T>, fetch_page: F) -> BoxStream<'a, Result<T, Error>>
+where
+    T: Send + 'a,
+    F: FnMut(Option<String>) -> Fut + Send + 'a,
+    Fut: Future<Output = Result<Paginated<T>, Error>> + Send + 'a,
+{
+    stream::try_unfold((first, fetch_page), |(next, mut fetch_page)| async move {
+        let page = match next {
+            Next::First => fetch_page(None).await?,
+            Next::Page(page) => page,
+            Next::Cursor(cursor) => fetch_page(Some(cursor)).await?,
+            Next::Done => return Ok(None),
+        };
+
+        let next = match page.next_cursor {
+            Some(cursor) => Next::Cursor(cursor),
+            None => Next::Done,
+        };
+        let items = stream::iter(page.items.into_iter().map(Ok));
+
+        Ok(Some((items, (next, fetch_page))))
+    })
+    .try_flatten()
+    .boxed()
+}
+
+/// Wire format of a paginated list returned by TrueLayer.
+#[derive(Deserialize)]
+pub(crate) struct PaginatedListResponse<T> {
+    pub items: Vec<T>,
+    pub pagination: Option<Pagination>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Pagination {
+    pub next_cursor: Option<String>,
+}
+
+impl<T> From<PaginatedListResponse<T>> for Paginated<T> {
+    fn from(res: PaginatedListResponse<T>) -> Self {
+        Paginated {
+            items: res.items,
+            next_cursor: res.pagination.and_then(|p| p.next_cursor),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    fn page(items: &[u32], next_cursor: Option<&str>) -> Paginated<u32> {
+        Paginated {
+            items: items.to_vec(),
+            next_cursor: next_cursor.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn paginate_follows_cursors() {
+        let items: Vec<u32> = paginate(|cursor| async move {
+            Ok(match cursor.as_deref() {
+                None => page(&[1, 2], Some("second")),
+                Some("second") => page(&[], Some("third")),
+                Some("third") => page(&[3], None),
+                Some(other) => panic!("Unexpected cursor {}", other),
+            })
+        })
+        .try_collect()
+        .await
+        .unwrap();
+
+        assert_eq!(items, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn into_stream_fetches_pages_lazily() {
+        let fetched = Arc::new(AtomicU32::new(0));
+        let fetched_clone = fetched.clone();
+        let mut stream = page(&[1], Some("second")).into_stream(move |cursor| {
+            fetched_clone.fetch_add(1, Ordering::SeqCst);
+            async move {
+                assert_eq!(cursor, "second");
+                Ok(page(&[2], None))
+            }
+        });
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), 1);
+        assert_eq!(fetched.load(Ordering::SeqCst), 0);
+        assert_eq!(stream.next().await.unwrap().unwrap(), 2);
+        assert_eq!(fetched.load(Ordering::SeqCst), 1);
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn paginate_stops_at_first_error() {
+        let items: Vec<Result<u32, Error>> = paginate(|cursor| async move {
+            match cursor {
+                None => Ok(page(&[1], Some("second"))),
+                Some(_) => Err(Error::Other(anyhow!("Test error"))),
+            }
+        })
+        .collect()
+        .await;
+
+        assert_eq!(items.len(), 2);
+        assert!(matches!(items[0], Ok(1)));
+        assert!(matches!(items[1], Err(Error::Other(_))));
+    }
+}
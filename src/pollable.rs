@@ -1,17 +1,89 @@
 //! Common logic to poll for updates on resources.
-
-use crate::{Error, TrueLayerClient};
+//!
+//! Payments, refunds, payouts, mandates and verifications, as well as the responses returned when
+//! creating them, implement [`Pollable`]. Refunds are polled as a `(payment_id, refund)` tuple,
+//! since they can only be retrieved through their payment.
+//!
+//! Every [`Pollable`] resource whose output implements [`IsInTerminalState`] gets
+//! [`poll_until_terminal_state`](PollableUntilTerminalState::poll_until_terminal_state) through a blanket
+//! implementation of [`PollableUntilTerminalState`], so new resources only need to define their terminal states.
+
+pub use crate::middlewares::retry_idempotent::DynRetryPolicy;
+use crate::{
+    apis::webhooks::Webhook,
+    runtime::{
+        sync::{self, watch, Semaphore},
+        Instant,
+    },
+    Error, TrueLayerClient,
+};
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use futures::future::Either;
 use retry_policies::{policies::ExponentialBackoff, RetryDecision, RetryPolicy};
-use std::time::Duration;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
 /// Options to configure the behaviour of [`Pollable::poll_until`](crate::pollable::Pollable::poll_until).
 ///
 /// The default is an exponential backoff between retries from 1 to 30 seconds for a total of 5 minutes.
-#[derive(Debug)]
-pub struct PollOptions<R: RetryPolicy> {
+///
+/// Default options for all the polls made with a client can be configured with
+/// [`with_poll_options`](crate::client::TrueLayerClientBuilder::with_poll_options)
+/// and retrieved with [`TrueLayerClient::poll_options`](crate::TrueLayerClient::poll_options).
+#[derive(Debug, Clone)]
+pub struct PollOptions<R: RetryPolicy, C = NoStatusChangeCallback> {
     retry_policy: R,
+    concurrency_budget: Option<Arc<Semaphore>>,
+    on_status_change: C,
+}
+
+/// Reacts to the changes of status of resources of type `T` observed while polling them.
+///
+/// Implemented by the callbacks registered with [`PollOptions::on_status_change`], and by
+/// [`NoStatusChangeCallback`] for any resource.
+pub trait OnStatusChange<T> {
+    /// Called with the previous and the new version of the polled resource.
+    fn status_changed(&self, previous: &T, current: &T);
+}
+
+/// Status change callback of the [`PollOptions`] without one, usable to poll any resource.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoStatusChangeCallback;
+
+impl<T> OnStatusChange<T> for NoStatusChangeCallback {
+    fn status_changed(&self, _previous: &T, _current: &T) {}
+}
+
+/// Callback registered with [`PollOptions::on_status_change`], only usable to poll resources of type `T`.
+pub struct StatusChangeCallback<T>(Arc<dyn Fn(&T, &T) + Send + Sync>);
+
+impl<T> OnStatusChange<T> for StatusChangeCallback<T> {
+    fn status_changed(&self, previous: &T, current: &T) {
+        (self.0)(previous, current)
+    }
+}
+
+impl<T> Clone for StatusChangeCallback<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> fmt::Debug for StatusChangeCallback<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StatusChangeCallback")
+            .field(&std::any::type_name::<T>())
+            .finish()
+    }
 }
 
 impl Default for PollOptions<ExponentialBackoff> {
@@ -20,17 +92,184 @@ impl Default for PollOptions<ExponentialBackoff> {
             retry_policy: ExponentialBackoff::builder()
                 .retry_bounds(Duration::from_secs(1), Duration::from_secs(30))
                 .build_with_total_retry_duration(Duration::from_secs(60 * 5 /* 5 mins */)),
+            concurrency_budget: None,
+            on_status_change: NoStatusChangeCallback,
         }
     }
 }
 
-impl<R: RetryPolicy> PollOptions<R> {
+impl<R: RetryPolicy, C> PollOptions<R, C> {
     /// Sets a retry policy.
-    pub fn with_retry_policy<T: RetryPolicy>(self, retry_policy: T) -> PollOptions<T> {
-        PollOptions { retry_policy }
+    pub fn with_retry_policy<T: RetryPolicy>(self, retry_policy: T) -> PollOptions<T, C> {
+        PollOptions {
+            retry_policy,
+            concurrency_budget: self.concurrency_budget,
+            on_status_change: self.on_status_change,
+        }
+    }
+
+    /// Limits how many poll requests can be in flight at the same time.
+    ///
+    /// The budget is shared by all the clones of these options, so that the same options
+    /// can be used to poll many resources concurrently without flooding TrueLayer with requests.
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.concurrency_budget = Some(Arc::new(Semaphore::new(max_concurrency)));
+        self
+    }
+
+    /// Calls `callback` with the previous and the new version of the polled resource every time
+    /// a change of status is observed, e.g. to log the transitions of a payment while waiting for it to settle.
+    ///
+    /// ```rust
+    /// # use truelayer_rust::{apis::payments::Payment, pollable::PollOptions};
+    /// let poll_options = PollOptions::default().on_status_change(|previous: &Payment, current: &Payment| {
+    ///     tracing::info!(payment_id = %current.id, "{:?} -> {:?}", previous.status, current.status);
+    /// });
+    /// ```
+    ///
+    /// The options can then only be used to poll resources of type `T`, which is checked at compile time:
+    ///
+    /// ```rust,compile_fail
+    /// # use truelayer_rust::{apis::{mandates::Mandate, payments::Payment}, pollable::PollOptions, Pollable, TrueLayerClient};
+    /// # async fn run(tl: TrueLayerClient, mandate: Mandate) {
+    /// let poll_options = PollOptions::default().on_status_change(|_: &Payment, _: &Payment| {});
+    /// let mandate = mandate.poll_until(&tl, poll_options, |_| true).await;
+    /// # }
+    /// ```
+    pub fn on_status_change<T>(
+        self,
+        callback: impl Fn(&T, &T) + Send + Sync + 'static,
+    ) -> PollOptions<R, StatusChangeCallback<T>> {
+        PollOptions {
+            retry_policy: self.retry_policy,
+            concurrency_budget: self.concurrency_budget,
+            on_status_change: StatusChangeCallback(Arc::new(callback)),
+        }
+    }
+}
+
+impl<R: RetryPolicy + Send + Sync + 'static> PollOptions<R> {
+    /// Erases the type of the retry policy, so that options with different policies
+    /// can be stored in the same place.
+    pub(crate) fn into_dyn(self) -> PollOptions<DynRetryPolicy> {
+        PollOptions {
+            retry_policy: DynRetryPolicy(Arc::new(self.retry_policy)),
+            concurrency_budget: self.concurrency_budget,
+            on_status_change: self.on_status_change,
+        }
     }
 }
 
+/// Client-wide limit on the number of polls per minute, shared by all the polls made with the same client
+/// regardless of their [`PollOptions`]. Configured with
+/// [`with_poll_budget`](crate::client::TrueLayerClientBuilder::with_poll_budget).
+///
+/// Polls exceeding the budget wait for their turn in FIFO order, so that a workload polling many resources
+/// delays the others instead of starving them, and the client never exceeds TrueLayer read rate limits.
+#[derive(Debug, Clone)]
+pub struct PollBudget {
+    max_polls_per_minute: u32,
+    /// Times of the polls made during the last minute, oldest first.
+    window: Arc<Mutex<VecDeque<Instant>>>,
+    /// Held by the poll waiting for a slot, so that the next ones queue up behind it.
+    queue: Arc<sync::Mutex<()>>,
+    consumed: Arc<AtomicU64>,
+    throttled: Arc<AtomicU64>,
+}
+
+/// Consumption of a [`PollBudget`], returned by
+/// [`TrueLayerClient::poll_budget_metrics`](crate::TrueLayerClient::poll_budget_metrics).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+pub struct PollBudgetMetrics {
+    pub max_polls_per_minute: u32,
+    /// Polls which can be made right now without waiting.
+    pub available: u32,
+    /// Total number of polls made.
+    pub consumed: u64,
+    /// Number of polls which had to wait because the budget was exhausted.
+    pub throttled: u64,
+}
+
+impl PollBudget {
+    /// Allows at most `max_polls_per_minute` polls in any window of one minute.
+    ///
+    /// A budget of `0` is unlimited: polls are counted, but never delayed.
+    pub fn per_minute(max_polls_per_minute: u32) -> Self {
+        Self {
+            max_polls_per_minute,
+            window: Arc::new(Mutex::new(VecDeque::new())),
+            queue: Arc::new(sync::Mutex::new(())),
+            consumed: Arc::new(AtomicU64::new(0)),
+            throttled: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns the current consumption of this budget.
+    pub fn metrics(&self) -> PollBudgetMetrics {
+        let available = if self.max_polls_per_minute == 0 {
+            u32::MAX
+        } else {
+            let now = Instant::now();
+            let window = self.window.lock().unwrap();
+            let recent = window
+                .iter()
+                .filter(|polled_at| now.duration_since(**polled_at) < POLL_BUDGET_WINDOW)
+                .count();
+            self.max_polls_per_minute
+                .saturating_sub(recent.try_into().unwrap_or(u32::MAX))
+        };
+
+        PollBudgetMetrics {
+            max_polls_per_minute: self.max_polls_per_minute,
+            available,
+            consumed: self.consumed.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Waits until a poll fits in the budget, then consumes one slot for the next minute.
+    async fn acquire(&self) {
+        if self.max_polls_per_minute > 0 {
+            let _turn = self.queue.lock().await;
+            let mut throttled = false;
+            while let Some(wait) = self.try_consume() {
+                if !throttled {
+                    throttled = true;
+                    self.throttled.fetch_add(1, Ordering::Relaxed);
+                    tracing::debug!("Poll budget exhausted, waiting for a free slot");
+                }
+                crate::runtime::sleep(wait).await;
+            }
+        }
+
+        self.consumed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Consumes a slot if one is free, otherwise returns how long until the oldest one is freed.
+    fn try_consume(&self) -> Option<Duration> {
+        let now = Instant::now();
+        let mut window = self.window.lock().unwrap();
+        while window.front().map_or(false, |polled_at| {
+            now.duration_since(*polled_at) >= POLL_BUDGET_WINDOW
+        }) {
+            window.pop_front();
+        }
+
+        match window.front() {
+            Some(oldest) if window.len() >= self.max_polls_per_minute as usize => {
+                Some(POLL_BUDGET_WINDOW - now.duration_since(*oldest))
+            }
+            _ => {
+                window.push_back(now);
+                None
+            }
+        }
+    }
+}
+
+/// Window over which the polls are counted by a [`PollBudget`].
+const POLL_BUDGET_WINDOW: Duration = Duration::from_secs(60);
+
 /// Error returned from [`Pollable::poll_until`](crate::pollable::Pollable::poll_until).
 #[derive(thiserror::Error, Debug)]
 pub enum PollError {
@@ -45,56 +284,277 @@ pub enum PollError {
 /// A resource that can be continuously polled for updates.
 #[async_trait]
 pub trait Pollable: private::Sealed {
-    type Output: Send;
+    type Output: HasId + HasStatus + Send + 'static;
 
     /// Makes a single request to retrieve the most up-to-date version of this resource from the server.
     async fn poll_once(&self, tl: &TrueLayerClient) -> Result<Self::Output, Error>;
 
-    /// Continuously polls the server for updates on this resource until the given condition is met.
+    /// Continuously polls the server for updates on this resource until the given condition is met,
+    /// e.g. to wait for an intermediate status rather than for a terminal one.
     #[tracing::instrument(name = "Poll for updates", skip_all)]
-    async fn poll_until<R, F>(
+    async fn poll_until<R, C, F>(
         &self,
         tl: &TrueLayerClient,
-        poll_options: PollOptions<R>,
+        poll_options: PollOptions<R, C>,
         predicate: F,
     ) -> Result<Self::Output, PollError>
     where
         R: RetryPolicy + Send + Sync,
+        C: OnStatusChange<Self::Output> + Send + Sync,
         F: for<'a> Fn(&'a Self::Output) -> bool + Send,
     {
-        // Loop until we match the predicate
-        let mut i = 0;
-        loop {
-            // Update the resource
-            let res = self.poll_once(tl).await?;
-
-            // Check predicate
-            if predicate(&res) {
-                return Ok(res);
-            }
+        poll(self, tl, poll_options, predicate, None)
+            .await
+            .map(|polled| polled.resource)
+    }
+
+    /// Same as [`poll_until`](Pollable::poll_until), but also returns telemetry about the polling.
+    #[tracing::instrument(name = "Poll for updates", skip_all)]
+    async fn poll_until_with_telemetry<R, C, F>(
+        &self,
+        tl: &TrueLayerClient,
+        poll_options: PollOptions<R, C>,
+        predicate: F,
+    ) -> Result<PolledResult<Self::Output>, PollError>
+    where
+        R: RetryPolicy + Send + Sync,
+        C: OnStatusChange<Self::Output> + Send + Sync,
+        F: for<'a> Fn(&'a Self::Output) -> bool + Send,
+    {
+        poll(self, tl, poll_options, predicate, None).await
+    }
+}
 
-            // Wait
-            match poll_options.retry_policy.should_retry(i) {
-                RetryDecision::Retry { execute_after } => {
-                    // Wait at least 1 second between each retry
-                    let wait_time = Duration::from_secs(1)
-                        .max((execute_after - Utc::now()).to_std().unwrap_or_default());
+/// Resource returned by a poll, together with telemetry about how it was obtained.
+#[derive(Debug, Clone)]
+pub struct PolledResult<T> {
+    /// The most up-to-date version of the resource.
+    pub resource: T,
+    /// Number of requests made to retrieve the resource, including the last one.
+    pub attempts: u32,
+    /// Total time spent waiting between requests.
+    pub total_wait: Duration,
+    /// When a change of status was last observed, or when the resource was first retrieved
+    /// if its status never changed while polling.
+    pub last_status_change_at: DateTime<Utc>,
+}
 
-                    tracing::debug!(
-                        "Waiting {} seconds before trying again",
-                        wait_time.as_secs_f64()
-                    );
+/// A resource with a unique id.
+pub trait HasId {
+    /// Returns the id of this resource.
+    fn id(&self) -> &str;
+}
 
-                    tokio::time::sleep(wait_time).await;
-                }
-                RetryDecision::DoNotRetry => {
-                    return Err(PollError::Timeout);
+/// A resource with a status which can change over time.
+pub trait HasStatus {
+    /// Returns `true` if both versions of this resource are in the same status,
+    /// regardless of the details attached to it.
+    fn has_same_status(&self, other: &Self) -> bool;
+}
+
+async fn poll<P, R, C, F>(
+    pollable: &P,
+    tl: &TrueLayerClient,
+    poll_options: PollOptions<R, C>,
+    predicate: F,
+    waiter: Option<&WebhookWaiter>,
+) -> Result<PolledResult<P::Output>, PollError>
+where
+    P: Pollable + Sync + ?Sized,
+    R: RetryPolicy + Send + Sync,
+    C: OnStatusChange<P::Output> + Send + Sync,
+    F: for<'a> Fn(&'a P::Output) -> bool + Send,
+{
+    let mut total_wait = Duration::ZERO;
+    let mut last_status_change_at = Utc::now();
+    let mut previous: Option<P::Output> = None;
+    let mut updates: Option<watch::Receiver<()>> = None;
+
+    // Loop until we match the predicate
+    let mut i = 0;
+    loop {
+        // Update the resource, waiting for a slot in the client and concurrency budgets if there are any
+        if let Some(budget) = &tl.inner.poll_budget {
+            budget.acquire().await;
+        }
+        if let Some(updates) = &mut updates {
+            // Webhooks received from now on are not reflected by the response
+            updates.borrow_and_update();
+        }
+        let res = {
+            let _permit = match &poll_options.concurrency_budget {
+                Some(budget) => Some(budget.acquire().await.expect("Semaphore is never closed")),
+                None => None,
+            };
+            pollable.poll_once(tl).await?
+        };
+        if let (Some(waiter), None) = (waiter, &updates) {
+            updates = Some(waiter.subscribe(res.id()));
+        }
+
+        match &previous {
+            Some(previous) if previous.has_same_status(&res) => {}
+            Some(previous) => {
+                last_status_change_at = Utc::now();
+                poll_options.on_status_change.status_changed(previous, &res);
+            }
+            None => last_status_change_at = Utc::now(),
+        }
+
+        // Check predicate
+        if predicate(&res) {
+            return Ok(PolledResult {
+                resource: res,
+                attempts: i + 1,
+                total_wait,
+                last_status_change_at,
+            });
+        }
+        previous = Some(res);
+
+        // Wait
+        match poll_options.retry_policy.should_retry(i) {
+            RetryDecision::Retry { execute_after } => {
+                // Wait at least 1 second between each retry
+                let wait_time = Duration::from_secs(1)
+                    .max((execute_after - Utc::now()).to_std().unwrap_or_default());
+
+                tracing::debug!(
+                    "Waiting {} seconds before trying again",
+                    wait_time.as_secs_f64()
+                );
+
+                match &mut updates {
+                    Some(updates) => {
+                        let started = Instant::now();
+                        if let Either::Right(_) = crate::runtime::race(
+                            crate::runtime::sleep(wait_time),
+                            updates.changed(),
+                        )
+                        .await
+                        {
+                            tracing::debug!("Webhook received, trying again");
+                        }
+                        total_wait += started.elapsed();
+                    }
+                    None => {
+                        crate::runtime::sleep(wait_time).await;
+                        total_wait += wait_time;
+                    }
                 }
             }
+            RetryDecision::DoNotRetry => {
+                return Err(PollError::Timeout);
+            }
+        }
+
+        i += 1;
+    }
+}
+
+/// Shortens polls with the webhooks sent by TrueLayer.
+///
+/// Services receiving webhooks can feed them to a waiter, then poll through it: every time a webhook
+/// refers to a resource being polled, the resource is retrieved again straight away instead of at the
+/// next retry of the poll options. Polling remains as a fallback for missed or delayed webhooks,
+/// so the retry policy can wait much longer between requests.
+///
+/// ```rust,no_run
+/// # use truelayer_rust::{apis::payments::CreatePaymentResponse, pollable::{PollOptions, WebhookWaiter}, TrueLayerClient};
+/// # use retry_policies::policies::ExponentialBackoff;
+/// # use std::time::Duration;
+/// # async fn run(tl: TrueLayerClient, waiter: WebhookWaiter, res: CreatePaymentResponse) {
+/// // In the webhook handler: `waiter.feed(&webhook);`
+///
+/// let payment = waiter
+///     .poll_until_terminal_state(
+///         &res,
+///         &tl,
+///         PollOptions::default().with_retry_policy(
+///             ExponentialBackoff::builder()
+///                 .retry_bounds(Duration::from_secs(60), Duration::from_secs(300))
+///                 .build_with_total_retry_duration(Duration::from_secs(3600)),
+///         ),
+///     )
+///     .await;
+/// # }
+/// ```
+///
+/// Clones share the same webhooks.
+#[derive(Debug, Clone, Default)]
+pub struct WebhookWaiter {
+    waiting: Arc<Mutex<HashMap<String, watch::Sender<()>>>>,
+}
+
+impl WebhookWaiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-            i += 1;
+    /// Notifies the polls of the resource the webhook refers to, if any.
+    ///
+    /// Webhooks must be verified first, e.g. with a
+    /// [`WebhookVerifier`](crate::apis::webhooks::WebhookVerifier).
+    pub fn feed(&self, webhook: &Webhook) {
+        let mut waiting = self.waiting.lock().unwrap();
+        waiting.retain(|_, sender| sender.receiver_count() > 0);
+
+        if let Some(sender) = webhook.event.resource_id().and_then(|id| waiting.get(id)) {
+            sender.send_replace(());
         }
     }
+
+    /// Same as [`Pollable::poll_until`], but also retrieves the resource again
+    /// every time a webhook refers to it.
+    pub async fn poll_until<P, R, C, F>(
+        &self,
+        pollable: &P,
+        tl: &TrueLayerClient,
+        poll_options: PollOptions<R, C>,
+        predicate: F,
+    ) -> Result<P::Output, PollError>
+    where
+        P: Pollable + Sync,
+        R: RetryPolicy + Send + Sync,
+        C: OnStatusChange<P::Output> + Send + Sync,
+        F: for<'a> Fn(&'a P::Output) -> bool + Send,
+    {
+        poll(pollable, tl, poll_options, predicate, Some(self))
+            .await
+            .map(|polled| polled.resource)
+    }
+
+    /// Same as [`PollableUntilTerminalState::poll_until_terminal_state`], but also retrieves the resource
+    /// again every time a webhook refers to it.
+    pub async fn poll_until_terminal_state<P, R, C>(
+        &self,
+        pollable: &P,
+        tl: &TrueLayerClient,
+        poll_options: PollOptions<R, C>,
+    ) -> Result<P::Output, PollError>
+    where
+        P: Pollable + Sync,
+        P::Output: IsInTerminalState,
+        R: RetryPolicy + Send + Sync,
+        C: OnStatusChange<P::Output> + Send + Sync,
+    {
+        self.poll_until(
+            pollable,
+            tl,
+            poll_options,
+            <P::Output as IsInTerminalState>::is_in_terminal_state,
+        )
+        .await
+    }
+
+    fn subscribe(&self, id: &str) -> watch::Receiver<()> {
+        self.waiting
+            .lock()
+            .unwrap()
+            .entry(id.to_string())
+            .or_insert_with(|| watch::channel(()).0)
+            .subscribe()
+    }
 }
 
 /// A resource that can be in a terminal state.
@@ -107,11 +567,25 @@ pub trait IsInTerminalState {
 #[async_trait]
 pub trait PollableUntilTerminalState: Pollable {
     /// Continuously polls the server for updates on this resource until it reaches a terminal state.
-    async fn poll_until_terminal_state<R: RetryPolicy + Send + Sync>(
+    async fn poll_until_terminal_state<R, C>(
+        &self,
+        tl: &TrueLayerClient,
+        poll_options: PollOptions<R, C>,
+    ) -> Result<Self::Output, PollError>
+    where
+        R: RetryPolicy + Send + Sync,
+        C: OnStatusChange<Self::Output> + Send + Sync;
+
+    /// Same as [`poll_until_terminal_state`](PollableUntilTerminalState::poll_until_terminal_state),
+    /// but also returns telemetry about the polling, e.g. to measure settlement latency.
+    async fn poll_until_terminal_state_with_telemetry<R, C>(
         &self,
         tl: &TrueLayerClient,
-        poll_options: PollOptions<R>,
-    ) -> Result<Self::Output, PollError>;
+        poll_options: PollOptions<R, C>,
+    ) -> Result<PolledResult<Self::Output>, PollError>
+    where
+        R: RetryPolicy + Send + Sync,
+        C: OnStatusChange<Self::Output> + Send + Sync;
 }
 
 #[async_trait]
@@ -120,14 +594,31 @@ where
     T: Pollable + Send + Sync,
     <T as Pollable>::Output: IsInTerminalState,
 {
-    async fn poll_until_terminal_state<R: RetryPolicy + Send + Sync>(
+    async fn poll_until_terminal_state<R, C>(
         &self,
         tl: &TrueLayerClient,
-        poll_options: PollOptions<R>,
-    ) -> Result<Self::Output, PollError> {
+        poll_options: PollOptions<R, C>,
+    ) -> Result<Self::Output, PollError>
+    where
+        R: RetryPolicy + Send + Sync,
+        C: OnStatusChange<Self::Output> + Send + Sync,
+    {
         self.poll_until(tl, poll_options, Self::Output::is_in_terminal_state)
             .await
     }
+
+    async fn poll_until_terminal_state_with_telemetry<R, C>(
+        &self,
+        tl: &TrueLayerClient,
+        poll_options: PollOptions<R, C>,
+    ) -> Result<PolledResult<Self::Output>, PollError>
+    where
+        R: RetryPolicy + Send + Sync,
+        C: OnStatusChange<Self::Output> + Send + Sync,
+    {
+        self.poll_until_with_telemetry(tl, poll_options, Self::Output::is_in_terminal_state)
+            .await
+    }
 }
 
 // Prevent users from implementing the `Pollable` trait.
@@ -140,6 +631,10 @@ mod private {
     impl Sealed for (&str, crate::apis::payments::refunds::CreateRefundResponse) {}
     impl Sealed for crate::apis::payouts::Payout {}
     impl Sealed for crate::apis::payouts::CreatePayoutResponse {}
+    impl Sealed for crate::apis::mandates::Mandate {}
+    impl Sealed for crate::apis::mandates::CreateMandateResponse {}
+    impl Sealed for crate::apis::verification::Verification {}
+    impl Sealed for crate::apis::verification::CreateVerificationResponse {}
 
     #[cfg(test)]
     impl<F> Sealed for super::tests::PollableMock<F> {}
@@ -148,21 +643,19 @@ mod private {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{apis::auth::Credentials, client::Environment};
+    use crate::{
+        apis::{auth::Credentials, webhooks::WebhookEvent},
+        client::Environment,
+    };
     use anyhow::anyhow;
     use reqwest::Url;
-    use std::{
-        sync::{
-            atomic::{AtomicU32, Ordering},
-            Arc, Mutex,
-        },
-        time::Instant,
-    };
+    use std::sync::atomic::AtomicU32;
 
     /// Mock for `Pollable` tests.
     pub struct PollableMock<F> {
         f: Arc<Mutex<F>>,
         polled_count: Arc<AtomicU32>,
+        observed_count: u32,
         terminal_state_after: u32,
     }
 
@@ -171,6 +664,7 @@ mod tests {
             Self {
                 f: Arc::new(Mutex::new(f)),
                 polled_count: Arc::new(AtomicU32::new(0)),
+                observed_count: 0,
                 terminal_state_after: u32::MAX,
             }
         }
@@ -190,6 +684,7 @@ mod tests {
             Self {
                 f: self.f.clone(),
                 polled_count: self.polled_count.clone(),
+                observed_count: self.observed_count,
                 terminal_state_after: self.terminal_state_after,
             }
         }
@@ -201,10 +696,23 @@ mod tests {
         }
     }
 
+    impl<F> HasId for PollableMock<F> {
+        fn id(&self) -> &str {
+            "mock-id"
+        }
+    }
+
+    impl<F> HasStatus for PollableMock<F> {
+        /// The status of the mock changes once, the second time it is polled.
+        fn has_same_status(&self, other: &Self) -> bool {
+            (self.observed_count >= 2) == (other.observed_count >= 2)
+        }
+    }
+
     #[async_trait]
     impl<F> Pollable for PollableMock<F>
     where
-        F: FnMut(u32) -> Option<Error> + Send + Sync,
+        F: FnMut(u32) -> Option<Error> + Send + Sync + 'static,
     {
         type Output = PollableMock<F>;
 
@@ -212,7 +720,10 @@ mod tests {
             self.polled_count.fetch_add(1, Ordering::SeqCst);
 
             match (self.f.lock().unwrap())(self.polled_count()) {
-                None => Ok(self.clone()),
+                None => Ok(Self {
+                    observed_count: self.polled_count(),
+                    ..self.clone()
+                }),
                 Some(e) => Err(e),
             }
         }
@@ -318,4 +829,184 @@ mod tests {
         assert!(pollable.is_in_terminal_state());
         assert!(elapsed >= Duration::from_secs(1));
     }
+
+    #[tokio::test]
+    async fn poll_until_with_telemetry() {
+        let pollable = PollableMock::new(|_| None);
+
+        // This will poll three times, and the status changes the second time.
+        let start = Utc::now();
+        let polled = pollable
+            .poll_until_with_telemetry(&mock_tl_client(), PollOptions::default(), |_| {
+                pollable.polled_count() >= 3
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(polled.resource.observed_count, 3);
+        assert_eq!(polled.attempts, 3);
+        assert!(polled.total_wait >= Duration::from_secs(2));
+        assert!(polled.last_status_change_at - start >= chrono::Duration::seconds(1));
+        assert!(polled.last_status_change_at <= Utc::now() - chrono::Duration::seconds(1));
+    }
+
+    #[tokio::test]
+    async fn status_changes_are_reported() {
+        type Mock = PollableMock<fn(u32) -> Option<Error>>;
+        let pollable: Mock = PollableMock::new(|_| None);
+        let changes = Arc::new(Mutex::new(Vec::new()));
+
+        let poll_options = PollOptions::default().on_status_change({
+            let changes = changes.clone();
+            move |previous: &Mock, current: &Mock| {
+                changes
+                    .lock()
+                    .unwrap()
+                    .push((previous.observed_count, current.observed_count));
+            }
+        });
+        pollable
+            .poll_until(&mock_tl_client(), poll_options, |_| {
+                pollable.polled_count() >= 3
+            })
+            .await
+            .unwrap();
+
+        // The status of the mock changes once, the second time it is polled
+        assert_eq!(*changes.lock().unwrap(), vec![(1, 2)]);
+    }
+
+    #[tokio::test]
+    async fn webhooks_shorten_polls() {
+        let pollable = PollableMock::new(|_| None).with_terminal_state_after(2);
+        let waiter = WebhookWaiter::new();
+
+        // The webhook arrives long before the next poll would have been made
+        tokio::spawn({
+            let waiter = waiter.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                waiter.feed(&Webhook {
+                    event_id: "event-id".to_string(),
+                    event_version: 1,
+                    event: WebhookEvent::PaymentExecuted {
+                        payment_id: "mock-id".to_string(),
+                        executed_at: Utc::now(),
+                        metadata: None,
+                    },
+                });
+            }
+        });
+
+        let start = Instant::now();
+        waiter
+            .poll_until_terminal_state(
+                &pollable,
+                &mock_tl_client(),
+                PollOptions::default().with_retry_policy(
+                    ExponentialBackoff::builder()
+                        .retry_bounds(Duration::from_secs(60), Duration::from_secs(60))
+                        .build_with_max_retries(1),
+                ),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(pollable.polled_count(), 2);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn poll_until_with_client_default_options() {
+        let pollable = PollableMock::new(|_| None);
+
+        let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
+            client_id: "".into(),
+            client_secret: "".into(),
+            scope: "".into(),
+        })
+        .with_environment(Environment::from_single_url(
+            &Url::parse("https://non.existent.domain").unwrap(),
+        ))
+        .with_poll_options(
+            PollOptions::default()
+                .with_retry_policy(ExponentialBackoff::builder().build_with_max_retries(0))
+                .with_max_concurrency(1),
+        )
+        .build();
+
+        // The default options of the client do not allow any retry
+        let res = pollable.poll_until(&tl, tl.poll_options(), |_| false).await;
+
+        assert!(matches!(res, Err(PollError::Timeout)));
+        assert_eq!(pollable.polled_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn poll_budget_is_shared_by_all_polls() {
+        let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
+            client_id: "".into(),
+            client_secret: "".into(),
+            scope: "".into(),
+        })
+        .with_environment(Environment::from_single_url(
+            &Url::parse("https://non.existent.domain").unwrap(),
+        ))
+        .with_poll_budget(2)
+        .build();
+
+        // Two polls with different options fit in the budget
+        PollableMock::new(|_| None)
+            .poll_until(&tl, PollOptions::default(), |_| true)
+            .await
+            .unwrap();
+        PollableMock::new(|_| None)
+            .poll_until(&tl, tl.poll_options(), |_| true)
+            .await
+            .unwrap();
+
+        // The third one has to wait for a minute
+        let pollable = PollableMock::new(|_| None);
+        let res = tokio::time::timeout(
+            Duration::from_millis(100),
+            pollable.poll_until(&tl, PollOptions::default(), |_| true),
+        )
+        .await;
+
+        assert!(res.is_err());
+        assert_eq!(pollable.polled_count(), 0);
+        assert_eq!(
+            tl.poll_budget_metrics(),
+            Some(PollBudgetMetrics {
+                max_polls_per_minute: 2,
+                available: 0,
+                consumed: 2,
+                throttled: 1
+            })
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn poll_budget_frees_slots_after_a_minute() {
+        let budget = PollBudget::per_minute(1);
+        budget.acquire().await;
+
+        let start = Instant::now();
+        budget.acquire().await;
+
+        assert!(start.elapsed() >= Duration::from_secs(60));
+        assert_eq!(budget.metrics().consumed, 2);
+        assert_eq!(budget.metrics().throttled, 1);
+    }
+
+    #[tokio::test]
+    async fn zero_poll_budget_is_unlimited() {
+        let budget = PollBudget::per_minute(0);
+        for _ in 0..100 {
+            budget.acquire().await;
+        }
+
+        assert_eq!(budget.metrics().consumed, 100);
+        assert_eq!(budget.metrics().throttled, 0);
+    }
 }
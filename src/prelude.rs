@@ -0,0 +1,26 @@
+//! The types most integrations need, importable with a single line:
+//!
+//! ```rust
+//! use truelayer_rust::prelude::*;
+//! ```
+//!
+//! Less common types are still available from their own modules, e.g. [`apis::payments`](crate::apis::payments).
+
+pub use crate::{
+    apis::{
+        auth::Credentials,
+        payments::{
+            refunds::{CreateRefundRequest, Refund, RefundStatus},
+            AccountIdentifier, Beneficiary, CreatePaymentRequest, CreatePaymentRequestBuilder,
+            CreatePaymentResponse, CreatePaymentUserRequest, Currency, Payment,
+            PaymentMethodRequest, PaymentStatus, ProviderSelectionRequest,
+        },
+        payouts::{
+            CreatePayoutRequest, CreatePayoutResponse, Payout, PayoutBeneficiary, PayoutStatus,
+        },
+    },
+    client::{Environment, TrueLayerClientBuilder},
+    error::ApiError,
+    pollable::{PollError, PollOptions},
+    Error, Pollable, PollableUntilTerminalState, TrueLayerClient,
+};
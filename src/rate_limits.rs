@@ -0,0 +1,139 @@
+//! Rate limit headroom reported by TrueLayer.
+//!
+//! TrueLayer may report the state of the rate limits applied to a client with the `RateLimit-Limit`,
+//! `RateLimit-Remaining` and `RateLimit-Reset` response headers (or their `X-RateLimit-` variants).
+//! The client records the latest values received from each host, available through
+//! [`TrueLayerClient::rate_limit_snapshot`](crate::client::TrueLayerClient::rate_limit_snapshot).
+//!
+//! Each [`RateLimitStatus`] maps naturally onto gauges labelled by host, e.g.
+//! `truelayer_rate_limit_remaining{host="api.truelayer.com"}`, which can be exported periodically
+//! to Prometheus or any other metrics system to plan capacity before hitting `429 Too Many Requests`.
+//! Each state is also passed to the [`RequestObserver`](crate::observer::RequestObserver)s as soon as
+//! it is received, see [`on_rate_limit_status`](crate::observer::RequestObserver::on_rate_limit_status).
+//!
+//! Requests rejected with `429 Too Many Requests` fail with [`Error::RateLimited`](crate::Error::RateLimited),
+//! unless a [`RateLimitPolicy`] is configured with
+//! [`with_rate_limit_policy`](crate::client::TrueLayerClientBuilder::with_rate_limit_policy)
+//! to retry them after the delay requested by TrueLayer.
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Policy to retry the idempotent requests rejected with `429 Too Many Requests`
+/// after the delay indicated by their `Retry-After` header.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RateLimitPolicy {
+    pub(crate) max_retries: u32,
+    pub(crate) max_wait: Duration,
+    pub(crate) default_wait: Duration,
+}
+
+impl Default for RateLimitPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            max_wait: Duration::from_secs(60),
+            default_wait: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RateLimitPolicy {
+    /// Retries each request at most `max_retries` times.
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Default::default()
+        }
+    }
+
+    /// Gives up without waiting if TrueLayer asks to wait longer than `max_wait`. Defaults to one minute.
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// Sets how long to wait when TrueLayer does not send a `Retry-After` header. Defaults to one second.
+    pub fn with_default_wait(mut self, default_wait: Duration) -> Self {
+        self.default_wait = default_wait;
+        self
+    }
+}
+
+/// Parses the `Retry-After` header, either a delay in seconds or an HTTP date.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    match value.parse::<u64>() {
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(_) => {
+            let at = DateTime::parse_from_rfc2822(value).ok()?;
+            Some(
+                (at.with_timezone(&Utc) - Utc::now())
+                    .to_std()
+                    .unwrap_or_default(),
+            )
+        }
+    }
+}
+
+/// Latest rate limit state reported by a TrueLayer host.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RateLimitStatus {
+    /// Host which reported the rate limit.
+    pub host: String,
+    /// Maximum number of requests allowed in the current window.
+    pub limit: Option<u64>,
+    /// Number of requests left in the current window.
+    pub remaining: Option<u64>,
+    /// When the current window ends and the limit is reset.
+    pub reset_at: Option<DateTime<Utc>>,
+    /// When this state was received.
+    pub observed_at: DateTime<Utc>,
+}
+
+impl RateLimitStatus {
+    /// Fraction of the limit still available in the current window, between `0.0` and `1.0`.
+    ///
+    /// Returns `None` unless both the limit and the remaining requests are known.
+    pub fn headroom(&self) -> Option<f64> {
+        match (self.limit, self.remaining) {
+            (Some(0), Some(_)) => Some(0.0),
+            (Some(limit), Some(remaining)) => Some((remaining as f64 / limit as f64).min(1.0)),
+            _ => None,
+        }
+    }
+}
+
+/// Thread-safe collection of the latest rate limit state of each host, shared by all the clients.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RateLimitRegistry {
+    statuses: Arc<Mutex<HashMap<String, RateLimitStatus>>>,
+}
+
+impl RateLimitRegistry {
+    /// Records the latest state reported by a host.
+    pub(crate) fn record(&self, status: RateLimitStatus) {
+        self.statuses
+            .lock()
+            .unwrap()
+            .insert(status.host.clone(), status);
+    }
+
+    /// Returns the latest state reported by the given host, if any.
+    pub(crate) fn get(&self, host: &str) -> Option<RateLimitStatus> {
+        self.statuses.lock().unwrap().get(host).cloned()
+    }
+
+    /// Returns the latest state of all the hosts, sorted by host.
+    pub(crate) fn snapshot(&self) -> Vec<RateLimitStatus> {
+        let mut statuses: Vec<_> = self.statuses.lock().unwrap().values().cloned().collect();
+        statuses.sort_by(|a, b| a.host.cmp(&b.host));
+        statuses
+    }
+}
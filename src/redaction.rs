@@ -0,0 +1,226 @@
+//! Redaction of personal data and secrets from the models of this library.
+//!
+//! [`to_redacted_json`] serializes any request or response model to JSON with the values
+//! of all the sensitive fields masked, so that payloads can be shipped to logging or SIEM systems
+//! without writing custom scrubbing code for each type.
+//!
+//! ```rust
+//! # use truelayer_rust::{apis::payments::CreatePaymentUserRequest, redaction::to_redacted_json};
+//! let user = CreatePaymentUserRequest::NewUser {
+//!     name: Some("Some One".to_string()),
+//!     email: Some("some.one@email.com".to_string()),
+//!     phone: None,
+//! };
+//!
+//! let json = to_redacted_json(&user).unwrap();
+//! assert!(!json.contains("Some One"));
+//! assert!(!json.contains("some.one@email.com"));
+//! ```
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Value which replaces sensitive values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Fields masked by the default [`RedactionPolicy`].
+pub const DEFAULT_SENSITIVE_FIELDS: &[&str] = &[
+    // Secrets
+    "access_token",
+    "client_secret",
+    "code",
+    "refresh_token",
+    "resource_token",
+    // Authorization flow payloads, which hold the answers of the payer and the parameters
+    // returned by their bank (e.g. authorization codes)
+    "fragment",
+    "inputs",
+    "query",
+    // Personal data
+    "account_holder_name",
+    "account_identifier",
+    "account_identifiers",
+    "account_number",
+    "address",
+    "bban",
+    "date_of_birth",
+    "email",
+    "first_name",
+    "iban",
+    "last_name",
+    "name",
+    "nrb",
+    "phone",
+    "sort_code",
+];
+
+/// Set of fields whose values must be masked, wherever they appear in a payload.
+///
+/// The default policy masks [`DEFAULT_SENSITIVE_FIELDS`].
+#[derive(Debug, Clone)]
+pub struct RedactionPolicy {
+    sensitive_fields: HashSet<String>,
+}
+
+impl Default for RedactionPolicy {
+    fn default() -> Self {
+        Self {
+            sensitive_fields: DEFAULT_SENSITIVE_FIELDS
+                .iter()
+                .map(|f| f.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl RedactionPolicy {
+    /// Also masks the given field, e.g. a key of the metadata which holds personal data.
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.sensitive_fields.insert(field.into());
+        self
+    }
+
+    /// Stops masking the given field.
+    pub fn without_field(mut self, field: &str) -> Self {
+        self.sensitive_fields.remove(field);
+        self
+    }
+
+    /// Returns `true` if the values of the given field are masked.
+    pub fn is_sensitive(&self, field: &str) -> bool {
+        self.sensitive_fields.contains(field)
+    }
+
+    /// Masks the sensitive values of a JSON payload in place.
+    ///
+    /// Missing values (i.e. `null`) are left untouched.
+    pub fn redact(&self, value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (field, value) in map.iter_mut() {
+                    if self.is_sensitive(field) && !value.is_null() {
+                        *value = Value::String(REDACTED.to_string());
+                    } else {
+                        self.redact(value);
+                    }
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact(item)),
+            _ => {}
+        }
+    }
+
+    /// Serializes a value to JSON, masking its sensitive values according to this policy.
+    pub fn to_redacted_json<T>(&self, value: &T) -> Result<String, serde_json::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let mut value = serde_json::to_value(value)?;
+        self.redact(&mut value);
+        serde_json::to_string(&value)
+    }
+}
+
+/// Serializes a value to JSON, masking its sensitive values according to the default [`RedactionPolicy`].
+pub fn to_redacted_json<T>(value: &T) -> Result<String, serde_json::Error>
+where
+    T: Serialize + ?Sized,
+{
+    RedactionPolicy::default().to_redacted_json(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::{
+        auth::Credentials,
+        payments::{
+            AccountIdentifier, Beneficiary, SubmitFormActionRequest,
+            SubmitProviderReturnParametersRequest,
+        },
+    };
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn sensitive_values_are_masked_at_any_depth() {
+        let beneficiary = Beneficiary::ExternalAccount {
+            account_holder_name: "Mr. Holder".to_string(),
+            account_identifier: AccountIdentifier::Iban {
+                iban: "GB33BUKB20201555555555".to_string(),
+            },
+            reference: "some-reference".to_string(),
+        };
+
+        let json: Value =
+            serde_json::from_str(&to_redacted_json(&vec![beneficiary]).unwrap()).unwrap();
+
+        assert_eq!(
+            json,
+            json!([{
+                "type": "external_account",
+                "account_holder_name": REDACTED,
+                "account_identifier": REDACTED,
+                "reference": "some-reference"
+            }])
+        );
+    }
+
+    #[test]
+    fn secrets_are_masked() {
+        let credentials = Credentials::ClientCredentials {
+            client_id: "client-id".to_string(),
+            client_secret: "client-secret".into(),
+            scope: "payments".to_string(),
+        };
+
+        let json = to_redacted_json(&credentials).unwrap();
+
+        assert!(json.contains("client-id"));
+        assert!(!json.contains("client-secret"));
+    }
+
+    #[test]
+    fn authorization_flow_payloads_are_masked() {
+        let form = SubmitFormActionRequest {
+            inputs: HashMap::from([("psu-branch-code".to_string(), "123".to_string())]),
+        };
+        let return_parameters = SubmitProviderReturnParametersRequest {
+            query: "?code=some-code&state=some-state".to_string(),
+            fragment: "#id_token=some-token".to_string(),
+        };
+
+        assert_eq!(
+            serde_json::from_str::<Value>(&to_redacted_json(&form).unwrap()).unwrap(),
+            json!({ "inputs": REDACTED })
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&to_redacted_json(&return_parameters).unwrap()).unwrap(),
+            json!({ "query": REDACTED, "fragment": REDACTED })
+        );
+    }
+
+    #[test]
+    fn policy_can_be_customized() {
+        let policy = RedactionPolicy::default()
+            .with_field("customer_ref")
+            .without_field("account_holder_name");
+        let value = json!({
+            "account_holder_name": "Mr. Holder",
+            "email": null,
+            "metadata": { "customer_ref": "some-customer" }
+        });
+
+        let json: Value = serde_json::from_str(&policy.to_redacted_json(&value).unwrap()).unwrap();
+
+        assert_eq!(
+            json,
+            json!({
+                "account_holder_name": "Mr. Holder",
+                "email": null,
+                "metadata": { "customer_ref": REDACTED }
+            })
+        );
+    }
+}
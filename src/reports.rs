@@ -0,0 +1,495 @@
+//! Dashboard-style aggregates of the activity of a merchant account.
+//!
+//! [`summarize`] walks the payments and the merchant account transactions of a period,
+//! page by page, and only keeps running totals: memory usage does not depend on the number
+//! of payments, so that internal dashboards can be fed without exporting the raw data first.
+//!
+//! ```rust,no_run
+//! # use truelayer_rust::{reports::{summarize, Period}, TrueLayerClient};
+//! # use chrono::{Duration, Utc};
+//! # async fn run(tl: TrueLayerClient) -> Result<(), truelayer_rust::Error> {
+//! let summary = summarize(&tl, "merchant-account-id", Period::last(Duration::days(7))).await?;
+//! for (currency, totals) in &summary.currencies {
+//!     println!(
+//!         "{}: {} payments executed, {:?} failure rate",
+//!         currency,
+//!         totals.executed_payments().count,
+//!         totals.payment_failure_rate()
+//!     );
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    apis::{
+        merchant_accounts::{
+            ListTransactionsRequest, Transaction, TransactionPayoutStatus, TransactionType,
+        },
+        payments::{
+            Beneficiary, Currency, ListPaymentsRequest, Payment, PaymentMethod, PaymentStatus,
+        },
+    },
+    Error, TrueLayerClient,
+};
+use chrono::{DateTime, Duration, Utc};
+use futures::TryStreamExt;
+use std::collections::{BTreeMap, HashMap};
+
+/// Time range covered by a [`Summary`], from `from` (inclusive) to `to` (exclusive).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Period {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+impl Period {
+    pub fn new(from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        Self { from, to }
+    }
+
+    /// Period of the given length ending now.
+    pub fn last(length: Duration) -> Self {
+        let to = Utc::now();
+        Self {
+            from: to - length,
+            to,
+        }
+    }
+}
+
+/// Number and total amount of a set of payments or transactions.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Totals {
+    pub count: u64,
+    pub amount_in_minor: u64,
+}
+
+impl Totals {
+    fn add(&mut self, amount_in_minor: u64) {
+        self.count += 1;
+        self.amount_in_minor += amount_in_minor;
+    }
+
+    fn merge(mut self, other: &Totals) -> Totals {
+        self.count += other.count;
+        self.amount_in_minor += other.amount_in_minor;
+        self
+    }
+}
+
+/// Activity of a merchant account in a single currency, by status.
+///
+/// Statuses are named as in the TrueLayer APIs, e.g. `executed` or `settled`.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct CurrencySummary {
+    /// Payments into the merchant account created during the period, by payment status.
+    pub payments: BTreeMap<&'static str, Totals>,
+    /// Payments received from outside TrueLayer, i.e. top-ups, settled during the period.
+    pub external_payments: Totals,
+    /// Payouts created during the period, by transaction status (`pending` or `settled`).
+    pub payouts: BTreeMap<&'static str, Totals>,
+    /// Refunds created during the period, by transaction status (`pending` or `settled`).
+    pub refunds: BTreeMap<&'static str, Totals>,
+}
+
+impl CurrencySummary {
+    /// Payments which reached the bank, whether already settled or not.
+    pub fn executed_payments(&self) -> Totals {
+        ["executed", "settled"]
+            .iter()
+            .filter_map(|status| self.payments.get(status))
+            .fold(Totals::default(), Totals::merge)
+    }
+
+    /// Share of failed payments among the payments which completed, either way.
+    ///
+    /// Returns `None` if no payment completed in the period.
+    pub fn payment_failure_rate(&self) -> Option<f64> {
+        let failed = self.payments.get("failed").map_or(0, |t| t.count);
+        let completed = failed + self.executed_payments().count;
+        (completed > 0).then(|| failed as f64 / completed as f64)
+    }
+}
+
+/// Activity of a merchant account during a [`Period`], returned by [`summarize`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Summary {
+    pub merchant_account_id: String,
+    pub period: Period,
+    pub currencies: HashMap<Currency, CurrencySummary>,
+}
+
+impl Summary {
+    fn currency(&mut self, currency: &Currency) -> &mut CurrencySummary {
+        self.currencies.entry(currency.clone()).or_default()
+    }
+
+    fn add_payment(&mut self, payment: &Payment) {
+        let PaymentMethod::BankTransfer { beneficiary, .. } = &payment.payment_method;
+        match beneficiary {
+            Beneficiary::MerchantAccount {
+                merchant_account_id,
+                ..
+            } if *merchant_account_id == self.merchant_account_id => {}
+            _ => return,
+        }
+
+        self.currency(&payment.currency)
+            .payments
+            .entry(payment_status(&payment.status))
+            .or_default()
+            .add(payment.amount_in_minor);
+    }
+
+    fn add_transaction(&mut self, transaction: &Transaction) {
+        let amount = transaction.amount_in_minor;
+        let summary = self.currency(&transaction.currency);
+        match &transaction.r#type {
+            // Covered by the payments, which include the failed ones
+            TransactionType::MerchantAccountPayment { .. } => {}
+            TransactionType::ExternalPayment { .. } => summary.external_payments.add(amount),
+            TransactionType::Payout { status, .. } => summary
+                .payouts
+                .entry(payout_status(status))
+                .or_default()
+                .add(amount),
+            TransactionType::Refund { status, .. } => summary
+                .refunds
+                .entry(payout_status(status))
+                .or_default()
+                .add(amount),
+        }
+    }
+}
+
+/// Computes the totals of the payments, payouts and refunds of a merchant account during a period.
+///
+/// All the pages of payments and transactions of the period are fetched, so this can take a while
+/// for busy merchant accounts, but only the totals are kept in memory.
+#[tracing::instrument(name = "Summarize Merchant Account", skip(tl))]
+pub async fn summarize(
+    tl: &TrueLayerClient,
+    merchant_account_id: &str,
+    period: Period,
+) -> Result<Summary, Error> {
+    let mut summary = Summary {
+        merchant_account_id: merchant_account_id.to_string(),
+        period,
+        currencies: HashMap::new(),
+    };
+
+    let mut payments = tl.payments.list_stream(&ListPaymentsRequest {
+        from: Some(period.from),
+        to: Some(period.to),
+        ..Default::default()
+    });
+    while let Some(payment) = payments.try_next().await? {
+        summary.add_payment(&payment);
+    }
+
+    let request = ListTransactionsRequest {
+        from: period.from,
+        to: period.to,
+        r#type: None,
+        cursor: None,
+        limit: None,
+    };
+    let mut transactions = tl
+        .merchant_accounts
+        .list_transactions_stream(merchant_account_id, &request);
+    while let Some(transaction) = transactions.try_next().await? {
+        summary.add_transaction(&transaction);
+    }
+
+    Ok(summary)
+}
+
+fn payment_status(status: &PaymentStatus) -> &'static str {
+    match status {
+        PaymentStatus::AuthorizationRequired => "authorization_required",
+        PaymentStatus::Authorizing { .. } => "authorizing",
+        PaymentStatus::Authorized { .. } => "authorized",
+        PaymentStatus::Executed { .. } => "executed",
+        PaymentStatus::Settled { .. } => "settled",
+        PaymentStatus::Failed { .. } => "failed",
+        PaymentStatus::AttemptFailed { .. } => "attempt_failed",
+    }
+}
+
+fn payout_status(status: &TransactionPayoutStatus) -> &'static str {
+    match status {
+        TransactionPayoutStatus::Pending => "pending",
+        TransactionPayoutStatus::Settled { .. } => "settled",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{apis::auth::Credentials, client::Environment};
+    use reqwest::Url;
+    use serde_json::{json, Value};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn totals(count: u64, amount_in_minor: u64) -> Totals {
+        Totals {
+            count,
+            amount_in_minor,
+        }
+    }
+
+    fn payment_json(merchant_account_id: &str, amount_in_minor: u64, status: Value) -> Value {
+        let mut payment = json!({
+            "id": "payment-id",
+            "amount_in_minor": amount_in_minor,
+            "currency": "GBP",
+            "payment_method": {
+                "type": "bank_transfer",
+                "provider_selection": {
+                    "type": "user_selected"
+                },
+                "beneficiary": {
+                    "type": "merchant_account",
+                    "merchant_account_id": merchant_account_id
+                }
+            },
+            "user": {
+                "id": "user-id"
+            },
+            "created_at": "2022-04-01T00:00:00Z"
+        });
+        payment
+            .as_object_mut()
+            .unwrap()
+            .extend(status.as_object().unwrap().clone());
+        payment
+    }
+
+    fn executed() -> Value {
+        json!({ "status": "executed", "executed_at": "2022-04-01T00:01:00Z" })
+    }
+
+    fn failed() -> Value {
+        json!({
+            "status": "failed",
+            "failed_at": "2022-04-01T00:01:00Z",
+            "failure_stage": "authorizing",
+            "failure_reason": "authorization_failed"
+        })
+    }
+
+    fn transactions_json() -> Value {
+        let beneficiary = json!({
+            "type": "payment_source",
+            "user_id": "user-id",
+            "payment_source_id": "payment-source-id",
+            "reference": "some-reference"
+        });
+
+        json!([
+            {
+                "id": "transaction-1",
+                "currency": "GBP",
+                "amount_in_minor": 100,
+                "type": "merchant_account_payment",
+                "status": "settled",
+                "settled_at": "2022-04-01T00:02:00Z",
+                "payment_source": {
+                    "id": "payment-source-id",
+                    "account_identifiers": []
+                },
+                "payment_id": "payment-id"
+            },
+            {
+                "id": "transaction-2",
+                "currency": "EUR",
+                "amount_in_minor": 300,
+                "type": "external_payment",
+                "status": "settled",
+                "settled_at": "2022-04-01T00:03:00Z",
+                "remitter": {
+                    "account_holder_name": "Mr. Holder"
+                }
+            },
+            {
+                "id": "transaction-3",
+                "currency": "GBP",
+                "amount_in_minor": 40,
+                "type": "payout",
+                "status": "pending",
+                "created_at": "2022-04-01T00:04:00Z",
+                "beneficiary": beneficiary,
+                "context_code": "withdrawal",
+                "payout_id": "payout-id"
+            },
+            {
+                "id": "transaction-4",
+                "currency": "GBP",
+                "amount_in_minor": 60,
+                "type": "payout",
+                "status": "settled",
+                "created_at": "2022-04-01T00:05:00Z",
+                "settled_at": "2022-04-01T00:06:00Z",
+                "beneficiary": beneficiary,
+                "context_code": "withdrawal",
+                "payout_id": "payout-id"
+            },
+            {
+                "id": "transaction-5",
+                "currency": "GBP",
+                "amount_in_minor": 25,
+                "type": "refund",
+                "status": "settled",
+                "created_at": "2022-04-01T00:07:00Z",
+                "settled_at": "2022-04-01T00:08:00Z",
+                "beneficiary": beneficiary,
+                "context_code": "internal",
+                "refund_id": "refund-id",
+                "payment_id": "payment-id"
+            }
+        ])
+    }
+
+    fn empty_summary(merchant_account_id: &str) -> Summary {
+        let at = Utc::now();
+        Summary {
+            merchant_account_id: merchant_account_id.to_string(),
+            period: Period::new(at, at),
+            currencies: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn only_payments_into_the_merchant_account_are_counted() {
+        let mut summary = empty_summary("merchant-account-id");
+        for payment in [
+            payment_json("merchant-account-id", 100, executed()),
+            payment_json("merchant-account-id", 200, failed()),
+            payment_json("other-merchant-account-id", 400, executed()),
+        ] {
+            summary.add_payment(&serde_json::from_value(payment).unwrap());
+        }
+
+        let mut external = payment_json("", 800, executed());
+        external["payment_method"]["beneficiary"] = json!({
+            "type": "external_account",
+            "account_holder_name": "Mr. Holder",
+            "account_identifier": {
+                "type": "iban",
+                "iban": "GB33BUKB20201555555555"
+            },
+            "reference": "some-reference"
+        });
+        summary.add_payment(&serde_json::from_value(external).unwrap());
+
+        assert_eq!(
+            summary.currencies[&Currency::Gbp].payments,
+            BTreeMap::from([("executed", totals(1, 100)), ("failed", totals(1, 200))])
+        );
+    }
+
+    #[test]
+    fn transactions_are_classified_by_type_and_status() {
+        let mut summary = empty_summary("merchant-account-id");
+        let transactions: Vec<Transaction> = serde_json::from_value(transactions_json()).unwrap();
+        for transaction in &transactions {
+            summary.add_transaction(transaction);
+        }
+
+        assert_eq!(
+            summary.currencies[&Currency::Gbp],
+            CurrencySummary {
+                // Payments into the merchant account are counted from the payments instead
+                payments: BTreeMap::new(),
+                external_payments: Totals::default(),
+                payouts: BTreeMap::from([("pending", totals(1, 40)), ("settled", totals(1, 60))]),
+                refunds: BTreeMap::from([("settled", totals(1, 25))]),
+            }
+        );
+        assert_eq!(
+            summary.currencies[&Currency::Eur].external_payments,
+            totals(1, 300)
+        );
+    }
+
+    #[tokio::test]
+    async fn summarize_combines_payments_and_transactions() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/connect/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "token_type": "Bearer",
+                "access_token": "access-token",
+                "expires_in": 3600
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/payments"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [
+                    payment_json("merchant-account-id", 100, executed()),
+                    payment_json("merchant-account-id", 200, failed())
+                ],
+                "pagination": {}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/merchant-accounts/merchant-account-id/transactions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": transactions_json(),
+                "pagination": {}
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
+            client_id: "client-id".into(),
+            client_secret: "client-secret".into(),
+            scope: "mock".into(),
+        })
+        .with_environment(Environment::from_single_url(
+            &Url::parse(&mock_server.uri()).unwrap(),
+        ))
+        .build();
+
+        let period = Period::last(Duration::days(7));
+        let summary = summarize(&tl, "merchant-account-id", period).await.unwrap();
+
+        assert_eq!(summary.merchant_account_id, "merchant-account-id");
+        assert_eq!(summary.period, period);
+        let gbp = &summary.currencies[&Currency::Gbp];
+        assert_eq!(gbp.executed_payments(), totals(1, 100));
+        assert_eq!(gbp.payment_failure_rate(), Some(0.5));
+        assert_eq!(gbp.payouts["settled"], totals(1, 60));
+        assert_eq!(gbp.refunds["settled"], totals(1, 25));
+        assert_eq!(
+            summary.currencies[&Currency::Eur].external_payments,
+            totals(1, 300)
+        );
+    }
+
+    #[test]
+    fn failure_rate_counts_completed_payments_only() {
+        let summary = CurrencySummary {
+            payments: BTreeMap::from([
+                ("authorizing", totals(5, 500)),
+                ("executed", totals(2, 200)),
+                ("settled", totals(5, 500)),
+                ("failed", totals(3, 300)),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(summary.executed_payments(), totals(7, 700));
+        assert_eq!(summary.payment_failure_rate(), Some(0.3));
+        assert_eq!(CurrencySummary::default().payment_failure_rate(), None);
+    }
+}
@@ -0,0 +1,173 @@
+//! Per-call configuration of the requests creating resources.
+//!
+//! Methods like [`PaymentsApi::create`](crate::apis::payments::PaymentsApi::create) return an
+//! [`ApiRequest`], which can be configured before being awaited:
+//!
+//! ```rust,no_run
+//! # use std::time::Duration;
+//! # use truelayer_rust::{apis::payments::CreatePaymentRequest, TrueLayerClient};
+//! # async fn run(tl: TrueLayerClient, create_payment_request: CreatePaymentRequest) -> Result<(), truelayer_rust::Error> {
+//! let payment = tl
+//!     .payments
+//!     .create(&create_payment_request)
+//!     .idempotency_key("order-1234")
+//!     .timeout(Duration::from_secs(5))
+//!     .correlation_id("checkout-5678")
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Awaiting an [`ApiRequest`] without configuring it behaves like a plain `async` method.
+//! The same options can also be passed as a [`RequestOptions`] to the `*_with_options` methods,
+//! e.g. [`PaymentsApi::create_with_options`](crate::apis::payments::PaymentsApi::create_with_options).
+
+use crate::{
+    common::{CORRELATION_ID_HEADER, IDEMPOTENCY_KEY_HEADER, TL_SIGNATURE_HEADER},
+    Error,
+};
+use futures::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest_middleware::RequestBuilder;
+use std::{
+    fmt::{Debug, Formatter},
+    future::{Future, IntoFuture},
+    time::Duration,
+};
+use uuid::Uuid;
+
+/// Headers managed by the library, which cannot be set through [`RequestOptions::extra_headers`].
+const RESERVED_HEADERS: [&str; 5] = [
+    IDEMPOTENCY_KEY_HEADER,
+    "Authorization",
+    TL_SIGNATURE_HEADER,
+    "X-Tl-Signature",
+    "Content-Type",
+];
+
+/// Options of a single call, passed to the `*_with_options` methods or set through the
+/// methods of [`ApiRequest`].
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RequestOptions {
+    /// Idempotency key of the request. A new random one is generated if `None`.
+    pub idempotency_key: Option<String>,
+    /// Timeout of the request, overriding the default one of the client
+    /// (see [`with_timeout`](crate::client::TrueLayerClientBuilder::with_timeout)).
+    pub timeout: Option<Duration>,
+    /// Id sent in the `X-Correlation-Id` header and recorded on the tracing span of the call.
+    pub correlation_id: Option<String>,
+    /// Additional headers sent with the request.
+    ///
+    /// Headers managed by the library (`Idempotency-Key`, `Authorization`, `Tl-Signature`,
+    /// `X-Tl-Signature` and `Content-Type`) are rejected with [`Error::ReservedHeader`] when the
+    /// request is sent. Set the idempotency key with [`ApiRequest::idempotency_key`] or the
+    /// `idempotency_key` field instead.
+    pub extra_headers: HeaderMap,
+}
+
+impl RequestOptions {
+    /// Returns the idempotency key of the call, generating a new random one if none was set.
+    pub(crate) fn idempotency_key(&mut self) -> String {
+        self.idempotency_key
+            .get_or_insert_with(|| Uuid::new_v4().to_string())
+            .clone()
+    }
+
+    /// Applies the options to a request about to be sent.
+    ///
+    /// Fails if [`extra_headers`](RequestOptions::extra_headers) contains a reserved header.
+    pub(crate) fn apply(&mut self, mut request: RequestBuilder) -> Result<RequestBuilder, Error> {
+        if let Some(name) = self.extra_headers.keys().find(|name| {
+            RESERVED_HEADERS
+                .iter()
+                .any(|reserved| name.as_str().eq_ignore_ascii_case(reserved))
+        }) {
+            return Err(Error::ReservedHeader(name.to_string()));
+        }
+
+        request = request.header(IDEMPOTENCY_KEY_HEADER, self.idempotency_key());
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        if let Some(correlation_id) = &self.correlation_id {
+            request = request.header(CORRELATION_ID_HEADER, correlation_id);
+        }
+        Ok(request.headers(self.extra_headers.clone()))
+    }
+}
+
+/// A call to TrueLayer which has not been sent yet.
+///
+/// The call is sent when the request is awaited. See [`request`](crate::request) for more details.
+#[must_use = "requests do nothing unless awaited"]
+pub struct ApiRequest<'a, T> {
+    options: RequestOptions,
+    send: Box<dyn FnOnce(RequestOptions) -> BoxFuture<'a, Result<T, Error>> + Send + 'a>,
+}
+
+impl<'a, T> ApiRequest<'a, T> {
+    pub(crate) fn new<F, Fut>(send: F) -> Self
+    where
+        F: FnOnce(RequestOptions) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<T, Error>> + Send + 'a,
+    {
+        Self {
+            options: RequestOptions::default(),
+            send: Box::new(move |options| Box::pin(send(options))),
+        }
+    }
+
+    /// Sends the request with the given idempotency key, instead of a new random one.
+    ///
+    /// Sending again a request with the same idempotency key and body returns the original response
+    /// instead of creating a duplicate, which makes it safe to retry after a timeout or a crash.
+    /// Each idempotency key must be used only for one request body.
+    pub fn idempotency_key(mut self, idempotency_key: impl Into<String>) -> Self {
+        self.options.idempotency_key = Some(idempotency_key.into());
+        self
+    }
+
+    /// Fails the call with a timeout if no response is received within the given duration.
+    ///
+    /// The timeout applies to each attempt separately when the request is retried.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends the given id in the `X-Correlation-Id` header and records it on the tracing span
+    /// of the call, to tie it to the operation of the caller which triggered it.
+    pub fn correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.options.correlation_id = Some(correlation_id.into());
+        self
+    }
+
+    /// Sends an additional header with the request. See [`RequestOptions::extra_headers`].
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.options.extra_headers.insert(name, value);
+        self
+    }
+
+    /// Replaces all the options of the request.
+    pub fn with_options(mut self, options: RequestOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl<'a, T: 'a> IntoFuture for ApiRequest<'a, T> {
+    type Output = Result<T, Error>;
+    type IntoFuture = BoxFuture<'a, Result<T, Error>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        (self.send)(self.options)
+    }
+}
+
+impl<'a, T> Debug for ApiRequest<'a, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiRequest")
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
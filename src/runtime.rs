@@ -0,0 +1,75 @@
+//! Async runtime primitives used by the client.
+//!
+//! Every timer, clock, synchronization primitive and background task of the crate goes through
+//! this module, so that supporting another runtime only requires a new implementation of it.
+//!
+//! Tokio, enabled by the default `tokio-runtime` feature, is the only implementation for now.
+//! `wasm32-unknown-unknown` is not supported: request signing depends on OpenSSL, and the
+//! middlewares and requests of the client are `Send`, unlike the futures of reqwest on wasm32.
+//!
+//! The synchronization primitives are the ones of `tokio::sync`, which do not depend on the Tokio
+//! runtime and work with any executor.
+
+use futures::future::{self, Either};
+use std::{future::Future, time::Duration};
+
+pub(crate) use imp::spawn;
+pub(crate) use imp::Instant;
+
+/// Synchronization primitives, usable with any runtime.
+pub(crate) mod sync {
+    pub(crate) use tokio::sync::{mpsc, oneshot, watch, Mutex, RwLock, Semaphore};
+}
+
+/// Returned by [`timeout`] when the future did not complete in time.
+#[derive(Debug)]
+pub(crate) struct Elapsed;
+
+/// Waits until `duration` has elapsed.
+pub(crate) async fn sleep(duration: Duration) {
+    imp::sleep(duration).await
+}
+
+/// Runs `future`, giving up if it does not complete within `duration`.
+pub(crate) async fn timeout<F: Future>(
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, Elapsed> {
+    match race(future, sleep(duration)).await {
+        Either::Left(output) => Ok(output),
+        Either::Right(()) => Err(Elapsed),
+    }
+}
+
+/// Runs both futures concurrently and returns the output of the first one to complete,
+/// dropping the other one.
+pub(crate) async fn race<A: Future, B: Future>(a: A, b: B) -> Either<A::Output, B::Output> {
+    futures::pin_mut!(a, b);
+    match future::select(a, b).await {
+        Either::Left((output, _)) => Either::Left(output),
+        Either::Right((output, _)) => Either::Right(output),
+    }
+}
+
+#[cfg(feature = "tokio-runtime")]
+mod imp {
+    use std::{future::Future, time::Duration};
+
+    // The clock of Tokio follows the paused time of tests, like its timers
+    pub(crate) use tokio::time::Instant;
+
+    pub(crate) async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+
+    /// Runs `future` in the background.
+    pub(crate) fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        tokio::spawn(future);
+    }
+}
+
+#[cfg(not(feature = "tokio-runtime"))]
+compile_error!("The `tokio-runtime` feature is required, no other runtime is supported yet");
@@ -0,0 +1,313 @@
+//! Interlock against mutating calls reaching the Live environment by mistake.
+//!
+//! Teams running the same binaries in staging and production can attach a [`Confirmation`] to
+//! their clients with [`with_live_safeguard`](crate::client::TrueLayerClientBuilder::with_live_safeguard):
+//! against the Live environment, requests creating or changing resources then fail with
+//! [`Error::LiveCallBlocked`](crate::Error::LiveCallBlocked) without being sent, unless the
+//! deployment explicitly opted in. Clients connected to any other environment are not affected.
+//!
+//! Confirmed deployments can additionally run in "pilot mode", only sending payments, payouts
+//! and mandates to an allowlist of beneficiaries.
+//!
+//! ```rust
+//! # use truelayer_rust::{apis::auth::Credentials, client::Environment, safeguard::Confirmation, TrueLayerClient};
+//! let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
+//!     client_id: "some-client-id".into(),
+//!     client_secret: "some-client-secret".into(),
+//!     scope: "payments".into(),
+//! })
+//! .with_environment(Environment::Live)
+//! .with_live_safeguard(
+//!     Confirmation::required()
+//!         .confirmed_by_env("TRUELAYER_LIVE_CONFIRMED")
+//!         .with_pilot_beneficiaries(["merchant-account-id", "GB33BUKB20201555555555"]),
+//! )
+//! .build();
+//! ```
+
+use reqwest::Method;
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Endpoints creating payments, payouts, mandates and payment links, which must carry a beneficiary
+/// from the allowlist in pilot mode.
+const BENEFICIARY_ENDPOINTS: [&str; 4] = ["/payments", "/payouts", "/mandates", "/payment-links"];
+
+/// Reason why the [`Confirmation`] of a client blocked a call to the Live environment.
+#[derive(thiserror::Error, Debug, Clone, Eq, PartialEq)]
+pub enum SafeguardViolation {
+    #[error("{method} {path} was not sent: mutating calls to the Live environment have not been confirmed")]
+    NotConfirmed { method: String, path: String },
+    #[error("{method} {path} was not sent: beneficiary {beneficiary:?} is not part of the pilot")]
+    BeneficiaryNotInPilot {
+        method: String,
+        path: String,
+        /// Identifier of the beneficiary, if it has one.
+        beneficiary: Option<String>,
+    },
+}
+
+/// Opt-in required before a client sends mutating calls to the Live environment.
+///
+/// See [`safeguard`](crate::safeguard) for more details.
+#[derive(Debug, Clone)]
+pub struct Confirmation {
+    confirmed: bool,
+    pilot_beneficiaries: Option<HashSet<String>>,
+}
+
+impl Confirmation {
+    /// Blocks all the mutating calls to the Live environment until [`confirmed`](Confirmation::confirmed).
+    pub fn required() -> Self {
+        Self {
+            confirmed: false,
+            pilot_beneficiaries: None,
+        }
+    }
+
+    /// Explicitly allows mutating calls to the Live environment, e.g. from a command line flag
+    /// only passed in production.
+    pub fn confirmed(mut self, confirmed: bool) -> Self {
+        self.confirmed = confirmed;
+        self
+    }
+
+    /// Allows mutating calls to the Live environment if the given environment variable
+    /// is set to `true` or `1`.
+    pub fn confirmed_by_env(self, var: &str) -> Self {
+        let confirmed = std::env::var(var)
+            .map(|value| matches!(value.trim(), "true" | "1"))
+            .unwrap_or(false);
+        self.confirmed(confirmed)
+    }
+
+    /// Enables pilot mode: payments, payouts and mandates are only sent to the given beneficiaries.
+    ///
+    /// Beneficiaries are identified by their merchant account id or payment source id
+    /// depending on their type, and external accounts by their IBAN, BBAN, NRB, or sort code and
+    /// account number joined by a dash (`040004-12345678`). Payouts to the business account of the
+    /// merchant are always allowed.
+    ///
+    /// Requests creating payments, payouts, mandates or payment links are blocked if their
+    /// beneficiary cannot be found, e.g. because the body could not be parsed.
+    pub fn with_pilot_beneficiaries(
+        mut self,
+        ids: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.pilot_beneficiaries = Some(ids.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Checks a request about to be sent to the Live environment.
+    pub(crate) fn check(
+        &self,
+        method: &Method,
+        path: &str,
+        body: Option<&[u8]>,
+    ) -> Result<(), SafeguardViolation> {
+        if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+            return Ok(());
+        }
+
+        if !self.confirmed {
+            return Err(SafeguardViolation::NotConfirmed {
+                method: method.to_string(),
+                path: path.to_string(),
+            });
+        }
+
+        let pilot_beneficiaries = match &self.pilot_beneficiaries {
+            Some(pilot_beneficiaries) => pilot_beneficiaries,
+            None => return Ok(()),
+        };
+
+        let creates_resource_with_beneficiary = *method == Method::POST
+            && BENEFICIARY_ENDPOINTS
+                .iter()
+                .any(|endpoint| path.trim_end_matches('/').ends_with(endpoint));
+        let beneficiary_not_in_pilot = |beneficiary| SafeguardViolation::BeneficiaryNotInPilot {
+            method: method.to_string(),
+            path: path.to_string(),
+            beneficiary,
+        };
+
+        let body: Option<Value> = body.and_then(|b| serde_json::from_slice(b).ok());
+        let beneficiary = body.as_ref().and_then(|body| {
+            [
+                "/payment_method/beneficiary",
+                "/payment_configuration/payment_method/beneficiary",
+                "/beneficiary",
+                "/mandate/beneficiary",
+            ]
+            .into_iter()
+            .find_map(|pointer| body.pointer(pointer))
+        });
+
+        match beneficiary {
+            None if creates_resource_with_beneficiary => Err(beneficiary_not_in_pilot(None)),
+            None => Ok(()),
+            Some(beneficiary)
+                if beneficiary.get("type").and_then(Value::as_str) == Some("business_account") =>
+            {
+                Ok(())
+            }
+            Some(beneficiary) => {
+                let id = beneficiary_id(beneficiary);
+                if matches!(&id, Some(id) if pilot_beneficiaries.contains(id)) {
+                    Ok(())
+                } else {
+                    Err(beneficiary_not_in_pilot(id))
+                }
+            }
+        }
+    }
+}
+
+/// Returns the identifier of a beneficiary in a request body, as documented in
+/// [`Confirmation::with_pilot_beneficiaries`].
+fn beneficiary_id(beneficiary: &Value) -> Option<String> {
+    let field =
+        |value: &Value, name: &str| value.get(name).and_then(Value::as_str).map(str::to_string);
+
+    match beneficiary.get("type").and_then(Value::as_str)? {
+        "merchant_account" => field(beneficiary, "merchant_account_id"),
+        "payment_source" => field(beneficiary, "payment_source_id"),
+        _ => {
+            let identifier = beneficiary.get("account_identifier")?;
+            match identifier.get("type").and_then(Value::as_str)? {
+                "sort_code_account_number" => Some(format!(
+                    "{}-{}",
+                    field(identifier, "sort_code")?,
+                    field(identifier, "account_number")?
+                )),
+                r#type => field(identifier, r#type),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn check(
+        confirmation: &Confirmation,
+        method: Method,
+        body: Value,
+    ) -> Result<(), SafeguardViolation> {
+        confirmation.check(
+            &method,
+            "/payments",
+            Some(&serde_json::to_vec(&body).unwrap()),
+        )
+    }
+
+    #[test]
+    fn mutating_calls_require_confirmation_and_pilot_beneficiaries() {
+        let payment =
+            |beneficiary: Value| json!({ "payment_method": { "beneficiary": beneficiary } });
+        let merchant_account = payment(json!({
+            "type": "merchant_account",
+            "merchant_account_id": "merchant-account-id"
+        }));
+        let external_account = payment(json!({
+            "type": "external_account",
+            "account_identifier": {
+                "type": "sort_code_account_number",
+                "sort_code": "040004",
+                "account_number": "12345678"
+            }
+        }));
+
+        // Nothing but reads without confirmation
+        let unconfirmed = Confirmation::required();
+        assert_eq!(check(&unconfirmed, Method::GET, json!(null)), Ok(()));
+        assert_eq!(
+            check(&unconfirmed, Method::POST, merchant_account.clone()),
+            Err(SafeguardViolation::NotConfirmed {
+                method: "POST".to_string(),
+                path: "/payments".to_string()
+            })
+        );
+
+        // Everything once confirmed
+        let confirmed = Confirmation::required().confirmed(true);
+        assert_eq!(
+            check(&confirmed, Method::POST, external_account.clone()),
+            Ok(())
+        );
+
+        // Only the allowlisted beneficiaries in pilot mode
+        let pilot = confirmed.with_pilot_beneficiaries(["040004-12345678"]);
+        assert_eq!(check(&pilot, Method::POST, external_account), Ok(()));
+        assert_eq!(
+            check(&pilot, Method::POST, merchant_account),
+            Err(SafeguardViolation::BeneficiaryNotInPilot {
+                method: "POST".to_string(),
+                path: "/payments".to_string(),
+                beneficiary: Some("merchant-account-id".to_string())
+            })
+        );
+        assert_eq!(
+            check(
+                &pilot,
+                Method::POST,
+                json!({ "beneficiary": { "type": "business_account", "reference": "ref" } })
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            check(
+                &pilot,
+                Method::POST,
+                json!({
+                    "payment_configuration": {
+                        "payment_method": {
+                            "beneficiary": {
+                                "type": "external_account",
+                                "account_identifier": {
+                                    "type": "sort_code_account_number",
+                                    "sort_code": "040004",
+                                    "account_number": "12345678"
+                                }
+                            }
+                        }
+                    }
+                })
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn pilot_mode_blocks_creations_without_a_beneficiary() {
+        let pilot = Confirmation::required()
+            .confirmed(true)
+            .with_pilot_beneficiaries(["merchant-account-id"]);
+        let not_in_pilot = |path: &str| {
+            Err(SafeguardViolation::BeneficiaryNotInPilot {
+                method: "POST".to_string(),
+                path: path.to_string(),
+                beneficiary: None,
+            })
+        };
+
+        // Unknown body shape
+        assert_eq!(
+            check(&pilot, Method::POST, json!({ "amount_in_minor": 100 })),
+            not_in_pilot("/payments")
+        );
+        // Unparseable body
+        assert_eq!(
+            pilot.check(&Method::POST, "/proxy/v3/payouts", Some(b"not json")),
+            not_in_pilot("/proxy/v3/payouts")
+        );
+
+        // Other mutating calls don't need a beneficiary
+        assert_eq!(
+            pilot.check(&Method::POST, "/payments/some-id/refunds/", None),
+            Ok(())
+        );
+    }
+}
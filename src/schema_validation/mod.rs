@@ -0,0 +1,570 @@
+//! Validation of outbound request bodies against the JSON Schemas of the TrueLayer endpoints.
+//!
+//! When enabled with [`with_schema_validation`](crate::client::TrueLayerClientBuilder::with_schema_validation),
+//! the serialized body of each request to an endpoint with a bundled schema is validated before being sent,
+//! and requests which do not conform are rejected with an
+//! [`Error::SchemaValidation`](crate::Error::SchemaValidation) listing every violation.
+//! This is meant to catch drifts between the SDK and the APIs in staging environments.
+//!
+//! Schemas are bundled for:
+//! - `POST /payments`
+//! - `POST /payments/{id}/refunds`
+//! - `POST /payouts`
+//!
+//! Only the subset of JSON Schema needed by the bundled schemas is supported: `type`, `enum`, `const`,
+//! `required`, `properties`, `items`, `oneOf`, `minimum`, `minLength`, `maxLength` and local `$ref`s.
+//!
+//! This module is only available with the `schema-validation` feature.
+
+use serde_json::Value;
+use std::fmt;
+
+/// A value of a request body which does not conform to the schema of the endpoint.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SchemaViolation {
+    /// JSON Pointer ([RFC 6901](https://datatracker.ietf.org/doc/html/rfc6901)) to the offending value.
+    pub pointer: String,
+    /// Description of the violated constraint.
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() {
+            "/"
+        } else {
+            &self.pointer
+        };
+        write!(f, "{}: {}", pointer, self.message)
+    }
+}
+
+/// Error returned when a request body does not conform to the schema of its endpoint.
+#[derive(thiserror::Error, Debug, Clone)]
+#[error("Request body does not conform to the schema of {endpoint}: {}", .violations.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+pub struct SchemaValidationError {
+    /// Method and path template of the endpoint, e.g. `POST /payments`.
+    pub endpoint: String,
+    /// All the violations found in the body.
+    pub violations: Vec<SchemaViolation>,
+}
+
+/// Bundled schemas, by method and path template.
+const SCHEMAS: &[(&str, &str, &str)] = &[
+    (
+        "POST",
+        "/payments",
+        include_str!("schemas/create_payment.json"),
+    ),
+    (
+        "POST",
+        "/payments/{id}/refunds",
+        include_str!("schemas/create_refund.json"),
+    ),
+    (
+        "POST",
+        "/payouts",
+        include_str!("schemas/create_payout.json"),
+    ),
+];
+
+/// Validates the body of a request to the given endpoint.
+///
+/// Requests to endpoints without a bundled schema are always valid.
+pub fn validate_request(
+    method: &str,
+    path: &str,
+    body: &Value,
+) -> Result<(), SchemaValidationError> {
+    let (method, template, schema) = match SCHEMAS
+        .iter()
+        .find(|(m, template, _)| m.eq_ignore_ascii_case(method) && matches_template(template, path))
+    {
+        Some(entry) => entry,
+        None => return Ok(()),
+    };
+
+    let schema: Value = serde_json::from_str(schema).expect("Bundled schemas are valid JSON");
+    let mut violations = Vec::new();
+    Validator { root: &schema }.validate(&schema, body, "", &mut violations);
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(SchemaValidationError {
+            endpoint: format!("{} {}", method, template),
+            violations,
+        })
+    }
+}
+
+/// Returns `true` if the path matches the template, where `{...}` segments match any single segment.
+fn matches_template(template: &str, path: &str) -> bool {
+    let template = template.trim_end_matches('/').split('/');
+    let path = path.trim_end_matches('/').split('/');
+
+    template.clone().count() == path.clone().count()
+        && template
+            .zip(path)
+            .all(|(t, p)| (t.starts_with('{') && !p.is_empty()) || t == p)
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+}
+
+impl<'a> Validator<'a> {
+    fn validate(
+        &self,
+        schema: &'a Value,
+        value: &Value,
+        pointer: &str,
+        violations: &mut Vec<SchemaViolation>,
+    ) {
+        let violation = |message: String| SchemaViolation {
+            pointer: pointer.to_string(),
+            message,
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match self.resolve(reference) {
+                Some(schema) => self.validate(schema, value, pointer, violations),
+                None => violations.push(violation(format!(
+                    "unresolvable schema reference {}",
+                    reference
+                ))),
+            }
+            return;
+        }
+
+        if let Some(types) = schema.get("type") {
+            let matches = match types {
+                Value::String(t) => has_type(value, t),
+                Value::Array(ts) => ts
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .any(|t| has_type(value, t)),
+                _ => true,
+            };
+            if !matches {
+                violations.push(violation(format!(
+                    "expected {}, found {}",
+                    types,
+                    type_name(value)
+                )));
+                return;
+            }
+        }
+
+        // Further constraints do not apply to missing optional values
+        if value.is_null() {
+            return;
+        }
+
+        if let Some(expected) = schema.get("const") {
+            if value != expected {
+                violations.push(violation(format!("expected {}, found {}", expected, value)));
+            }
+        }
+
+        if let Some(Value::Array(allowed)) = schema.get("enum") {
+            if !allowed.contains(value) {
+                violations.push(violation(format!(
+                    "expected one of {}, found {}",
+                    Value::Array(allowed.clone()),
+                    value
+                )));
+            }
+        }
+
+        if let (Some(minimum), Some(n)) = (
+            schema.get("minimum").and_then(Value::as_f64),
+            value.as_f64(),
+        ) {
+            if n < minimum {
+                violations.push(violation(format!(
+                    "must be at least {}, found {}",
+                    minimum, n
+                )));
+            }
+        }
+
+        if let Some(s) = value.as_str() {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    violations.push(violation(format!(
+                        "must be at least {} characters long, found {}",
+                        min, len
+                    )));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    violations.push(violation(format!(
+                        "must be at most {} characters long, found {}",
+                        max, len
+                    )));
+                }
+            }
+        }
+
+        if let Some(object) = value.as_object() {
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for field in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(field) {
+                        violations.push(SchemaViolation {
+                            pointer: format!("{}/{}", pointer, escape(field)),
+                            message: "missing required field".to_string(),
+                        });
+                    }
+                }
+            }
+            if let Some(Value::Object(properties)) = schema.get("properties") {
+                for (field, property_schema) in properties {
+                    if let Some(property) = object.get(field) {
+                        let pointer = format!("{}/{}", pointer, escape(field));
+                        self.validate(property_schema, property, &pointer, violations);
+                    }
+                }
+            }
+        }
+
+        if let (Some(items_schema), Some(items)) = (schema.get("items"), value.as_array()) {
+            for (i, item) in items.iter().enumerate() {
+                self.validate(
+                    items_schema,
+                    item,
+                    &format!("{}/{}", pointer, i),
+                    violations,
+                );
+            }
+        }
+
+        if let Some(Value::Array(alternatives)) = schema.get("oneOf") {
+            self.validate_one_of(alternatives, value, pointer, violations);
+        }
+    }
+
+    fn validate_one_of(
+        &self,
+        alternatives: &'a [Value],
+        value: &Value,
+        pointer: &str,
+        violations: &mut Vec<SchemaViolation>,
+    ) {
+        let results: Vec<Vec<SchemaViolation>> = alternatives
+            .iter()
+            .map(|alternative| {
+                let mut alternative_violations = Vec::new();
+                self.validate(alternative, value, pointer, &mut alternative_violations);
+                alternative_violations
+            })
+            .collect();
+
+        match results.iter().filter(|r| r.is_empty()).count() {
+            1 => {}
+            0 => {
+                // Report the violations of the alternative selected by the `type` discriminator, if any,
+                // as they are far more useful than a generic message
+                let discriminated = value.get("type").and_then(|t| {
+                    alternatives.iter().position(|alternative| {
+                        self.resolve_schema(alternative)
+                            .pointer("/properties/type/const")
+                            .map_or(false, |c| c == t)
+                    })
+                });
+                match discriminated {
+                    Some(i) => violations.extend(results[i].iter().cloned()),
+                    None => violations.push(SchemaViolation {
+                        pointer: pointer.to_string(),
+                        message: "does not match any of the allowed variants".to_string(),
+                    }),
+                }
+            }
+            _ => violations.push(SchemaViolation {
+                pointer: pointer.to_string(),
+                message: "matches more than one of the allowed variants".to_string(),
+            }),
+        }
+    }
+
+    fn resolve_schema(&self, schema: &'a Value) -> &'a Value {
+        schema
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|reference| self.resolve(reference))
+            .unwrap_or(schema)
+    }
+
+    fn resolve(&self, reference: &str) -> Option<&'a Value> {
+        self.root.pointer(reference.strip_prefix('#')?)
+    }
+}
+
+fn has_type(value: &Value, t: &str) -> bool {
+    match t {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Escapes a field name to be used as a JSON Pointer segment.
+fn escape(field: &str) -> String {
+    field.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apis::{
+        payments::{
+            refunds::CreateRefundRequest, AccountIdentifier, Beneficiary, CreatePaymentRequest,
+            CreatePaymentUserRequest, Currency, PaymentMethodRequest, PaymentRetry, ProviderFilter,
+            ProviderSelectionRequest, RelatedProducts, Remitter, SchemeSelection, SignupPlus,
+        },
+        payouts::{CreatePayoutRequest, PayoutBeneficiary, PayoutSchemeSelection},
+    };
+    use serde::Serialize;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn assert_conforms(method: &str, path: &str, request: &impl Serialize) {
+        let body = serde_json::to_value(request).unwrap();
+        if let Err(e) = validate_request(method, path, &body) {
+            panic!("{}\n{:#}", e, body);
+        }
+    }
+
+    fn sort_code_account_number() -> AccountIdentifier {
+        AccountIdentifier::SortCodeAccountNumber {
+            sort_code: "123456".to_string(),
+            account_number: "12345678".to_string(),
+        }
+    }
+
+    #[test]
+    fn sdk_payment_requests_conform_to_the_schema() {
+        let user_selected = CreatePaymentRequest {
+            amount_in_minor: 100,
+            currency: Currency::Gbp,
+            payment_method: PaymentMethodRequest::BankTransfer {
+                provider_selection: ProviderSelectionRequest::UserSelected {
+                    filter: Some(ProviderFilter::default()),
+                    scheme_selection: Some(SchemeSelection::InstantPreferred {
+                        allow_remitter_fee: Some(false),
+                    }),
+                },
+                beneficiary: Beneficiary::ExternalAccount {
+                    account_holder_name: "Mr. Holder".to_string(),
+                    account_identifier: sort_code_account_number(),
+                    reference: "some-reference".to_string(),
+                },
+            },
+            user: CreatePaymentUserRequest::ExistingUser {
+                id: "user-id".to_string(),
+            },
+            metadata: Some(HashMap::from([("key".to_string(), "value".to_string())])),
+            retry: Some(PaymentRetry::Standard {}),
+            related_products: Some(RelatedProducts {
+                signup_plus: Some(SignupPlus {}),
+            }),
+        };
+        assert_conforms("POST", "/payments", &user_selected);
+
+        let preselected = CreatePaymentRequest {
+            amount_in_minor: 100,
+            currency: Currency::Eur,
+            payment_method: PaymentMethodRequest::BankTransfer {
+                provider_selection: ProviderSelectionRequest::Preselected {
+                    provider_id: "provider-id".to_string(),
+                    scheme_id: "sepa_credit_transfer_instant".to_string(),
+                    remitter: Some(Remitter::new(
+                        "Mr. Remitter",
+                        AccountIdentifier::Iban {
+                            iban: "DE89370400440532013000".to_string(),
+                        },
+                    )),
+                },
+                beneficiary: Beneficiary::MerchantAccount {
+                    merchant_account_id: "merchant-account-id".to_string(),
+                    account_holder_name: None,
+                },
+            },
+            user: CreatePaymentUserRequest::NewUser {
+                name: Some("Mr. Payer".to_string()),
+                email: Some("payer@example.com".to_string()),
+                phone: None,
+            },
+            metadata: None,
+            retry: None,
+            related_products: None,
+        };
+        assert_conforms("POST", "/payments", &preselected);
+    }
+
+    #[test]
+    fn sdk_refund_requests_conform_to_the_schema() {
+        for amount_in_minor in [None, Some(50)] {
+            assert_conforms(
+                "POST",
+                "/payments/payment-id/refunds",
+                &CreateRefundRequest {
+                    amount_in_minor,
+                    reference: "some-reference".to_string(),
+                    metadata: None,
+                },
+            );
+        }
+    }
+
+    #[test]
+    fn sdk_payout_requests_conform_to_the_schema() {
+        let beneficiaries = [
+            PayoutBeneficiary::ExternalAccount {
+                account_holder_name: "Mr. Holder".to_string(),
+                account_identifier: sort_code_account_number(),
+                reference: "some-reference".to_string(),
+            },
+            PayoutBeneficiary::PaymentSource {
+                user_id: "user-id".to_string(),
+                payment_source_id: "payment-source-id".to_string(),
+                reference: "some-reference".to_string(),
+            },
+            PayoutBeneficiary::BusinessAccount {
+                reference: "some-reference".to_string(),
+            },
+        ];
+        let scheme_selections = [
+            None,
+            Some(PayoutSchemeSelection::InstantOnly),
+            Some(PayoutSchemeSelection::InstantPreferred),
+            Some(PayoutSchemeSelection::Preselected {
+                scheme_id: "faster_payments_service".to_string(),
+            }),
+        ];
+
+        for beneficiary in beneficiaries {
+            for scheme_selection in scheme_selections.clone() {
+                assert_conforms(
+                    "POST",
+                    "/payouts",
+                    &CreatePayoutRequest {
+                        merchant_account_id: "merchant-account-id".to_string(),
+                        amount_in_minor: 100,
+                        currency: Currency::Gbp,
+                        beneficiary: beneficiary.clone(),
+                        scheme_selection,
+                    },
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn valid_requests_pass() {
+        assert!(validate_request(
+            "POST",
+            "/payments",
+            &json!({
+                "amount_in_minor": 100,
+                "currency": "GBP",
+                "payment_method": {
+                    "type": "bank_transfer",
+                    "provider_selection": {
+                        "type": "user_selected",
+                        "filter": null,
+                        "scheme_selection": null
+                    },
+                    "beneficiary": {
+                        "type": "external_account",
+                        "account_holder_name": "Mr. Holder",
+                        "account_identifier": {
+                            "type": "sort_code_account_number",
+                            "sort_code": "123456",
+                            "account_number": "12345678"
+                        },
+                        "reference": "some-reference"
+                    }
+                },
+                "user": { "id": "user-id" },
+                "metadata": null
+            })
+        )
+        .is_ok());
+
+        // Endpoints without a schema are not validated
+        assert!(validate_request("POST", "/mandates", &json!({ "anything": 1 })).is_ok());
+    }
+
+    #[test]
+    fn violations_are_reported_with_pointers() {
+        let err = validate_request(
+            "POST",
+            "/payments/payment-id/refunds",
+            &json!({ "amount_in_minor": 0 }),
+        )
+        .unwrap_err();
+        assert_eq!(err.endpoint, "POST /payments/{id}/refunds");
+        assert_eq!(
+            err.violations,
+            vec![
+                SchemaViolation {
+                    pointer: "/reference".to_string(),
+                    message: "missing required field".to_string()
+                },
+                SchemaViolation {
+                    pointer: "/amount_in_minor".to_string(),
+                    message: "must be at least 1, found 0".to_string()
+                }
+            ]
+        );
+
+        let err = validate_request(
+            "POST",
+            "/payouts",
+            &json!({
+                "merchant_account_id": "merchant-account-id",
+                "amount_in_minor": 100,
+                "currency": "USD",
+                "beneficiary": {
+                    "type": "business_account",
+                    "reference": 42
+                }
+            }),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.violations,
+            vec![
+                SchemaViolation {
+                    pointer: "/beneficiary/reference".to_string(),
+                    message: r#"expected "string", found integer"#.to_string()
+                },
+                SchemaViolation {
+                    pointer: "/currency".to_string(),
+                    message: r#"expected one of ["EUR","GBP","NOK","PLN"], found "USD""#
+                        .to_string()
+                }
+            ]
+        );
+    }
+}
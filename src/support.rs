@@ -0,0 +1,316 @@
+//! Support bundles gathering everything known about a payment, to attach to TrueLayer support tickets.
+//!
+//! [`build_bundle`] collects the payment, its refunds, the related transactions of the beneficiary
+//! merchant account, the consumption of the poll budget and the most recent HTTP calls to TrueLayer
+//! concerning the payment into a single serializable [`SupportBundle`].
+//!
+//! ```rust,no_run
+//! # use truelayer_rust::{support::build_bundle, TrueLayerClient};
+//! # async fn run(tl: TrueLayerClient) {
+//! let bundle = build_bundle(&tl, "payment-id").await;
+//! std::fs::write("support-bundle.json", bundle.to_redacted_json().unwrap()).unwrap();
+//! # }
+//! ```
+
+use crate::{
+    apis::{
+        merchant_accounts::{ListTransactionsRequest, Transaction, TransactionType},
+        payments::{refunds::Refund, Beneficiary, Payment, PaymentMethod},
+    },
+    audit::{ResponseInterceptor, ResponseSummary},
+    pollable::PollBudgetMetrics,
+    redaction::to_redacted_json,
+    TrueLayerClient,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+/// Number of HTTP calls remembered by each client for support bundles.
+pub const MAX_REQUEST_TRACES: usize = 200;
+
+/// How long after the creation of a payment its merchant account transactions are looked for.
+const TRANSACTIONS_WINDOW_DAYS: i64 = 7;
+
+/// Summary of an HTTP call to TrueLayer included in a [`SupportBundle`].
+///
+/// Like [`ResponseSummary`], traces never contain request or response bodies, nor any header.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct RequestTrace {
+    pub method: String,
+    pub host: String,
+    /// Path of the request, with all the resource ids replaced by `{id}`.
+    pub path_template: String,
+    /// Ids of the resources found in the path, in order of appearance.
+    pub resource_ids: Vec<String>,
+    /// HTTP status code of the response, or `None` if no response was received.
+    pub status: Option<u16>,
+    pub duration_ms: u64,
+    /// When the response was received, or the call failed.
+    pub completed_at: DateTime<Utc>,
+}
+
+/// Ring buffer of the most recent HTTP calls sent by a client, fed by a [`ResponseInterceptor`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RequestTraceLog(Arc<Mutex<VecDeque<RequestTrace>>>);
+
+impl RequestTraceLog {
+    pub(crate) fn interceptor(&self) -> ResponseInterceptor {
+        let log = self.clone();
+        ResponseInterceptor(Arc::new(move |summary: &ResponseSummary| {
+            log.record(summary)
+        }))
+    }
+
+    fn record(&self, summary: &ResponseSummary) {
+        let mut traces = self.0.lock().unwrap();
+        if traces.len() == MAX_REQUEST_TRACES {
+            traces.pop_front();
+        }
+        traces.push_back(RequestTrace {
+            method: summary.method.to_string(),
+            host: summary.host.clone(),
+            path_template: summary.path_template.clone(),
+            resource_ids: summary.resource_ids.clone(),
+            status: summary.status,
+            duration_ms: summary.duration.as_millis() as u64,
+            completed_at: Utc::now(),
+        });
+    }
+
+    /// Returns the recorded calls concerning any of the given resources, oldest first.
+    fn concerning(&self, resource_ids: &[&str]) -> Vec<RequestTrace> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|trace| {
+                trace
+                    .resource_ids
+                    .iter()
+                    .any(|id| resource_ids.contains(&id.as_str()))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Everything known about a payment, built by [`build_bundle`].
+///
+/// The bundle contains personal data of the payer and of the beneficiary: use
+/// [`to_redacted_json`](SupportBundle::to_redacted_json) to export it.
+#[derive(Debug, Clone, Serialize)]
+pub struct SupportBundle {
+    pub payment_id: String,
+    pub generated_at: DateTime<Utc>,
+    /// Version of this library.
+    pub sdk_version: String,
+    /// Base URL of the Payments APIs the client is connected to.
+    pub payments_url: String,
+    /// The payment, or `None` if it was not found or could not be fetched.
+    pub payment: Option<Payment>,
+    pub refunds: Vec<Refund>,
+    /// Transactions of the payment and its refunds on the beneficiary merchant account, if any,
+    /// settled within seven days of the creation of the payment.
+    pub transactions: Vec<Transaction>,
+    /// Consumption of the poll budget of the client, if one is configured.
+    pub poll_budget: Option<PollBudgetMetrics>,
+    /// Most recent HTTP calls concerning the payment and its refunds, oldest first,
+    /// including the polls of their status.
+    pub request_traces: Vec<RequestTrace>,
+    /// Parts of the bundle which could not be gathered, with the reason.
+    pub errors: Vec<String>,
+}
+
+impl SupportBundle {
+    /// Serializes the bundle to JSON with all the personal data masked,
+    /// see [`redaction`](crate::redaction).
+    pub fn to_redacted_json(&self) -> Result<String, serde_json::Error> {
+        to_redacted_json(self)
+    }
+}
+
+/// Gathers everything known about a payment into a [`SupportBundle`].
+///
+/// Parts which cannot be fetched from TrueLayer are listed in [`SupportBundle::errors`]
+/// rather than failing the whole bundle, since support bundles are mostly built during incidents.
+#[tracing::instrument(name = "Build Support Bundle", skip(tl))]
+pub async fn build_bundle(tl: &TrueLayerClient, payment_id: &str) -> SupportBundle {
+    let mut errors = Vec::new();
+
+    let payment = match tl.payments.get_by_id(payment_id).await {
+        Ok(Some(payment)) => Some(payment),
+        Ok(None) => {
+            errors.push("payment: not found".to_string());
+            None
+        }
+        Err(e) => {
+            errors.push(format!("payment: {}", e));
+            None
+        }
+    };
+
+    let refunds = tl
+        .payments
+        .list_refunds(payment_id)
+        .await
+        .unwrap_or_else(|e| {
+            errors.push(format!("refunds: {}", e));
+            Vec::new()
+        });
+
+    let mut transactions = Vec::new();
+    if let Some(payment) = &payment {
+        let PaymentMethod::BankTransfer { beneficiary, .. } = &payment.payment_method;
+        if let Beneficiary::MerchantAccount {
+            merchant_account_id,
+            ..
+        } = beneficiary
+        {
+            let request = ListTransactionsRequest {
+                from: payment.created_at,
+                to: Utc::now().min(payment.created_at + Duration::days(TRANSACTIONS_WINDOW_DAYS)),
+                r#type: None,
+                cursor: None,
+                limit: None,
+            };
+            match tl
+                .merchant_accounts
+                .list_transactions(merchant_account_id, &request)
+                .await
+            {
+                Ok(all) => {
+                    transactions = all
+                        .into_iter()
+                        .filter(|transaction| concerns_payment(transaction, payment_id))
+                        .collect()
+                }
+                Err(e) => errors.push(format!("transactions: {}", e)),
+            }
+        }
+    }
+
+    let mut resource_ids = vec![payment_id];
+    resource_ids.extend(refunds.iter().map(|refund| refund.id.as_str()));
+    let request_traces = tl.inner.request_traces.concerning(&resource_ids);
+
+    SupportBundle {
+        payment_id: payment_id.to_string(),
+        generated_at: Utc::now(),
+        sdk_version: env!("CARGO_PKG_VERSION").to_string(),
+        payments_url: tl.inner.environment.payments_url().to_string(),
+        payment,
+        refunds,
+        transactions,
+        poll_budget: tl.poll_budget_metrics(),
+        request_traces,
+        errors,
+    }
+}
+
+/// Returns `true` if the transaction is the settlement of the payment or of one of its refunds.
+fn concerns_payment(transaction: &Transaction, payment_id: &str) -> bool {
+    match &transaction.r#type {
+        TransactionType::MerchantAccountPayment { payment_id: id, .. }
+        | TransactionType::Refund { payment_id: id, .. } => id == payment_id,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{apis::auth::Credentials, client::Environment};
+    use reqwest::Url;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn bundles_gather_the_payment_and_its_history() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/connect/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "token_type": "Bearer",
+                "access_token": "access-token",
+                "expires_in": 3600
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/payments/payment-id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "payment-id",
+                "amount_in_minor": 100,
+                "currency": "GBP",
+                "payment_method": {
+                    "type": "bank_transfer",
+                    "provider_selection": {
+                        "type": "user_selected"
+                    },
+                    "beneficiary": {
+                        "type": "merchant_account",
+                        "merchant_account_id": "merchant-account-id",
+                        "account_holder_name": "Some One"
+                    }
+                },
+                "user": {
+                    "id": "user-id"
+                },
+                "created_at": "2022-04-01T00:00:00Z",
+                "status": "executed",
+                "executed_at": "2022-04-01T00:01:00Z"
+            })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/payments/payment-id/refunds"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "items": [] })))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/merchant-accounts/merchant-account-id/transactions"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&mock_server)
+            .await;
+
+        let tl = TrueLayerClient::builder(Credentials::ClientCredentials {
+            client_id: "client-id".into(),
+            client_secret: "client-secret".into(),
+            scope: "mock".into(),
+        })
+        .with_environment(Environment::from_single_url(
+            &Url::parse(&mock_server.uri()).unwrap(),
+        ))
+        .with_retry_policy(None)
+        .build();
+        tl.payments.get_by_id("payment-id").await.unwrap();
+
+        let bundle = build_bundle(&tl, "payment-id").await;
+
+        assert!(bundle.payment.is_some());
+        assert!(bundle.refunds.is_empty());
+        assert!(bundle.transactions.is_empty());
+        assert_eq!(bundle.errors.len(), 1);
+        assert!(bundle.errors[0].starts_with("transactions: "));
+
+        // The poll made before building the bundle is traced too, the token requests are not
+        let paths: Vec<_> = bundle
+            .request_traces
+            .iter()
+            .map(|trace| trace.path_template.as_str())
+            .collect();
+        assert_eq!(
+            paths,
+            ["/payments/{id}", "/payments/{id}", "/payments/{id}/refunds"]
+        );
+
+        assert!(!bundle.to_redacted_json().unwrap().contains("Some One"));
+    }
+}
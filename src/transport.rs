@@ -0,0 +1,224 @@
+//! Advanced configuration of the HTTP transport used to reach TrueLayer.
+
+use crate::middlewares::body_limits::BodyLimitsMiddleware;
+use async_trait::async_trait;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    fmt::{Debug, Formatter},
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
+
+/// Custom DNS resolver, see [`TransportConfig::with_dns_resolver`].
+#[async_trait]
+pub trait DnsResolver: Send + Sync + 'static {
+    /// Resolves `host` to its IP addresses. Their ports are ignored: the port of the request URL is always used.
+    async fn resolve(&self, host: &str)
+        -> Result<Vec<SocketAddr>, Box<dyn StdError + Send + Sync>>;
+}
+
+/// Low-level settings of the HTTP client used to connect to TrueLayer.
+///
+/// Use it through [`TrueLayerClientBuilder::with_transport`](crate::client::TrueLayerClientBuilder::with_transport).
+/// This is meant for deployments with restrictive egress firewalls: hosts can be pinned to static IP
+/// addresses, or resolved with a custom DNS resolver. In both cases, TLS still validates certificates
+/// against the original host name (which is also sent as SNI), so pinning does not weaken security.
+#[derive(Clone, Default)]
+pub struct TransportConfig {
+    pinned_hosts: HashMap<String, Vec<SocketAddr>>,
+    dns_resolver: Option<Arc<dyn DnsResolver>>,
+    connect_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    max_response_size: Option<u64>,
+    body_read_timeout: Option<Duration>,
+}
+
+impl TransportConfig {
+    /// Pins `host` (e.g., `api.truelayer.com`) to a static set of IP addresses, bypassing DNS.
+    ///
+    /// Ports in `addrs` are ignored: the port of the request URL is always used.
+    pub fn pin_host(
+        mut self,
+        host: impl Into<String>,
+        addrs: impl IntoIterator<Item = SocketAddr>,
+    ) -> Self {
+        self.pinned_hosts
+            .insert(host.into(), addrs.into_iter().collect());
+        self
+    }
+
+    /// Sets a custom DNS resolver used for all the hosts which are not pinned.
+    pub fn with_dns_resolver(mut self, resolver: Arc<dyn DnsResolver>) -> Self {
+        self.dns_resolver = Some(resolver);
+        self
+    }
+
+    /// Sets a timeout for establishing new connections.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets a timeout for each HTTP request, from when it starts connecting until the response body is read.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the maximum size of a response body, in bytes.
+    ///
+    /// Larger responses are rejected with [`Error::ResponseTooLarge`](crate::Error::ResponseTooLarge)
+    /// as soon as the limit is exceeded, without buffering the rest of the body.
+    /// Unlike the other settings, this limit also applies to clients configured with
+    /// [`with_http_client`](crate::client::TrueLayerClientBuilder::with_http_client).
+    pub fn with_max_response_size(mut self, max_bytes: u64) -> Self {
+        self.max_response_size = Some(max_bytes);
+        self
+    }
+
+    /// Sets a timeout for reading a response body, starting when the response headers are received.
+    ///
+    /// Slower bodies, e.g. trickled by a misbehaving proxy, fail with
+    /// [`Error::ResponseBodyTimeout`](crate::Error::ResponseBodyTimeout).
+    /// Unlike the other settings, this timeout also applies to clients configured with
+    /// [`with_http_client`](crate::client::TrueLayerClientBuilder::with_http_client).
+    pub fn with_body_read_timeout(mut self, timeout: Duration) -> Self {
+        self.body_read_timeout = Some(timeout);
+        self
+    }
+
+    /// Middleware enforcing the response body limits, if any is configured.
+    pub(crate) fn body_limits_middleware(&self) -> Option<BodyLimitsMiddleware> {
+        if self.max_response_size.is_none() && self.body_read_timeout.is_none() {
+            return None;
+        }
+
+        Some(BodyLimitsMiddleware::new(
+            self.max_response_size,
+            self.body_read_timeout,
+        ))
+    }
+
+    /// Builds a reqwest [`Client`](reqwest::Client) with these settings.
+    pub(crate) fn build_http_client(&self) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder();
+
+        for (host, addrs) in &self.pinned_hosts {
+            builder = builder.resolve_to_addrs(host, addrs);
+        }
+
+        if let Some(resolver) = &self.dns_resolver {
+            builder = builder.dns_resolver(Arc::new(SharedResolver(resolver.clone())));
+        }
+
+        if let Some(connect_timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(connect_timeout);
+        }
+
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        builder.build().expect("Failed to build HTTP client")
+    }
+}
+
+impl Debug for TransportConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TransportConfig")
+            .field("pinned_hosts", &self.pinned_hosts)
+            .field(
+                "dns_resolver",
+                &self.dns_resolver.as_ref().map(|_| "<custom>"),
+            )
+            .field("connect_timeout", &self.connect_timeout)
+            .field("timeout", &self.timeout)
+            .field("max_response_size", &self.max_response_size)
+            .field("body_read_timeout", &self.body_read_timeout)
+            .finish()
+    }
+}
+
+/// Adapter to pass a [`DnsResolver`] to reqwest, so that its DNS types stay out of the public API.
+struct SharedResolver(Arc<dyn DnsResolver>);
+
+impl Resolve for SharedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.0.clone();
+        Box::pin(async move {
+            let addrs = resolver.resolve(name.as_str()).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn pinned_hosts_bypass_dns() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/test"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // This host does not exist, but it's pinned to the mock server address
+        let client = TransportConfig::default()
+            .pin_host("api.truelayer.invalid", [*mock_server.address()])
+            .build_http_client();
+
+        let res = client
+            .get(format!(
+                "http://api.truelayer.invalid:{}/test",
+                mock_server.address().port()
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+    }
+
+    struct StaticResolver(SocketAddr);
+
+    #[async_trait]
+    impl DnsResolver for StaticResolver {
+        async fn resolve(
+            &self,
+            host: &str,
+        ) -> Result<Vec<SocketAddr>, Box<dyn StdError + Send + Sync>> {
+            assert_eq!(host, "api.truelayer.invalid");
+            Ok(vec![self.0])
+        }
+    }
+
+    #[tokio::test]
+    async fn custom_dns_resolvers_resolve_unpinned_hosts() {
+        let mock_server = MockServer::start().await;
+        Mock::given(path("/test"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = TransportConfig::default()
+            .with_dns_resolver(Arc::new(StaticResolver(*mock_server.address())))
+            .build_http_client();
+
+        let res = client
+            .get(format!(
+                "http://api.truelayer.invalid:{}/test",
+                mock_server.address().port()
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+    }
+}
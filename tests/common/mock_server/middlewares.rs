@@ -1,4 +1,4 @@
-use crate::common::mock_server::MockServerConfiguration;
+use crate::common::mock_server::{MockServerConfiguration, MockServerStorage};
 use actix_web::{
     body::BoxBody,
     dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform},
@@ -8,10 +8,12 @@ use actix_web::{
     Error, HttpMessage, HttpResponse,
 };
 use anyhow::anyhow;
+use chrono::{DateTime, Utc};
 use futures::{
     future::{LocalBoxFuture, Ready},
     FutureExt, StreamExt, TryFutureExt, TryStreamExt,
 };
+use serde_json::json;
 use std::{
     future::Future,
     sync::Arc,
@@ -33,6 +35,56 @@ pub(super) async fn validate_user_agent(req: &mut ServiceRequest) -> Result<(),
     Ok(())
 }
 
+/// Error returned by [`validate_access_token`] to reject a request with `401 Unauthorized`.
+#[derive(thiserror::Error, Debug)]
+#[error("Invalid or expired access token")]
+pub(super) struct InvalidAccessToken;
+
+/// Rejects requests authenticated with an access token which was not issued by the mock server,
+/// or which has since been expired. Requests without an access token are let through.
+pub(super) fn validate_access_token(
+    storage: MockServerStorage,
+) -> impl Fn(&mut ServiceRequest) -> LocalBoxFuture<'_, Result<(), anyhow::Error>> {
+    move |req: &mut ServiceRequest| {
+        let storage = storage.clone();
+
+        Box::pin(async move {
+            let access_token = match req.headers().get("Authorization") {
+                Some(header) => header
+                    .to_str()?
+                    .strip_prefix("Bearer ")
+                    .ok_or(InvalidAccessToken)?
+                    .to_string(),
+                None => return Ok(()),
+            };
+
+            if !storage
+                .read()
+                .unwrap()
+                .access_tokens
+                .contains(&access_token)
+            {
+                return Err(InvalidAccessToken.into());
+            }
+
+            Ok(())
+        })
+    }
+}
+
+/// Longest difference tolerated between the clock of the mock server and the clock of the clients
+/// signing requests.
+const MAX_SIGNATURE_CLOCK_SKEW: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// Error returned by [`validate_signature`] to reject a request with `401 Unauthorized`.
+#[derive(thiserror::Error, Debug)]
+#[error("{detail}")]
+pub(super) struct InvalidSignature {
+    detail: String,
+    /// Time on the wall clock of the mock server, reported in the `Date` header.
+    server_time: DateTime<Utc>,
+}
+
 /// Ensures that the incoming request has an idempotency key set
 pub(super) async fn ensure_idempotency_key(req: &mut ServiceRequest) -> Result<(), anyhow::Error> {
     // Skip this middleware for GETs
@@ -55,12 +107,14 @@ pub(super) async fn ensure_idempotency_key(req: &mut ServiceRequest) -> Result<(
 /// Validates a full request signature
 pub(super) fn validate_signature(
     configuration: MockServerConfiguration,
+    storage: MockServerStorage,
     require_idempotency_key: bool,
 ) -> impl Fn(&mut ServiceRequest) -> LocalBoxFuture<'_, Result<(), anyhow::Error>> {
     let configuration = Arc::new(configuration);
 
     move |req: &mut ServiceRequest| {
         let configuration = configuration.clone();
+        let storage = storage.clone();
 
         Box::pin(async move {
             // Skip this middleware for GETs
@@ -68,6 +122,13 @@ pub(super) fn validate_signature(
                 return Ok(());
             }
 
+            let clock_skew = storage.read().unwrap().signature_clock_skew;
+            let server_time = Utc::now() + chrono::Duration::from_std(clock_skew).unwrap();
+            let invalid_signature = |detail: &str| InvalidSignature {
+                detail: detail.to_string(),
+                server_time,
+            };
+
             // Buffer all the body in memory
             let body = req
                 .take_payload()
@@ -100,12 +161,21 @@ pub(super) fn validate_signature(
                 .get("Tl-Signature")
                 .map(|v| v.to_str())
                 .transpose()?
-                .ok_or_else(|| anyhow!("Missing required signature"))?;
-            if truelayer_signing::extract_jws_header(signature)?.kid != configuration.signing_key_id
+                .ok_or_else(|| invalid_signature("Missing required signature"))?;
+            if truelayer_signing::extract_jws_header(signature)
+                .map_err(|_| invalid_signature("Malformed signature"))?
+                .kid
+                != configuration.signing_key_id
             {
-                return Err(anyhow!("Invalid key id"));
+                return Err(invalid_signature("Signature key id not found").into());
+            }
+            if clock_skew > MAX_SIGNATURE_CLOCK_SKEW {
+                return Err(invalid_signature("Signature expired").into());
             }
-            verifier.body(&body).verify(signature)?;
+            verifier
+                .body(&body)
+                .verify(signature)
+                .map_err(|_| invalid_signature("Invalid signature"))?;
 
             // Put the body back into the request so that it can be consumed by other middlewares
             req.set_payload(Payload::Stream {
@@ -202,6 +272,34 @@ where
 
         async move {
             match inner.call(&mut req).await {
+                Err(e) if e.is::<InvalidAccessToken>() => {
+                    Ok(req.into_response(HttpResponse::Unauthorized().json(json!({
+                        "type": "https://docs.truelayer.com/docs/error-types#unauthenticated",
+                        "title": "Unauthenticated",
+                        "status": 401,
+                        "trace_id": "mock-trace-id",
+                        "detail": e.to_string()
+                    }))))
+                }
+                Err(e) if e.is::<InvalidSignature>() => {
+                    let e = e.downcast::<InvalidSignature>().unwrap();
+                    Ok(req.into_response(
+                        HttpResponse::Unauthorized()
+                            .insert_header((
+                                "Date",
+                                e.server_time
+                                    .format("%a, %d %b %Y %H:%M:%S GMT")
+                                    .to_string(),
+                            ))
+                            .json(json!({
+                                "type": "https://docs.truelayer.com/docs/error-types#unauthenticated",
+                                "title": "Unauthenticated",
+                                "status": 401,
+                                "trace_id": "mock-trace-id",
+                                "detail": e.detail
+                            })),
+                    ))
+                }
                 Err(e) => Ok(
                     req.into_response(HttpResponse::InternalServerError().body(format!("{:?}", e)))
                 ),
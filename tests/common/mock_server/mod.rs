@@ -4,20 +4,26 @@ mod routes;
 use crate::common::{mock_server::middlewares::MiddlewareFn, MockBankAction};
 use actix_web::{web, App, HttpServer};
 use anyhow::Context;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use reqwest::Url;
+use serde_json::json;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     str::FromStr,
     sync::{Arc, RwLock},
+    time::Duration,
 };
 use tokio::sync::oneshot;
 use truelayer_rust::apis::{
+    mandates::{
+        Constraints, Mandate, MandateDetail, MandateProviderSelection, MandateStatus, MandateType,
+        RevocationSource,
+    },
     merchant_accounts::{MerchantAccount, SweepingSettings},
     payments::{
         refunds::Refund, AccountIdentifier, AuthorizationFlow, AuthorizationFlowActions,
         AuthorizationFlowNextAction, Beneficiary, CountryCode, Currency, FailureStage, Payment,
-        PaymentMethod, PaymentSource, PaymentStatus, ReleaseChannel,
+        PaymentMethod, PaymentSource, PaymentStatus, ReleaseChannel, User,
     },
     payments_providers::{capabilities, Capabilities, PaymentScheme, Provider},
     payouts::Payout,
@@ -29,6 +35,8 @@ const MOCK_PROVIDER_PL_REDIRECT_ADDITIONAL_INPUTS: &str = "mock-payments-pl-redi
 const MOCK_PROVIDER_NO_REDIRECT_ADDITIONAL_INPUTS: &str = "mock-payments-no-redirect";
 const MOCK_PROVIDER_DE_ADDITIONAL_INPUTS: &str = "mock-payments-de-redirect-additional-input-text";
 const MOCK_REDIRECT_URI: &str = "https://mock.redirect.uri/";
+const MOCK_SEPA_INSTANT_LIMIT_IN_MINOR: u64 = 100_000;
+const MOCK_RETURNED_PAYOUT_REFERENCE: &str = "mock-returned-payout";
 
 #[derive(Clone)]
 struct MockServerConfiguration {
@@ -36,7 +44,6 @@ struct MockServerConfiguration {
     client_secret: String,
     signing_key_id: String,
     signing_public_key: Vec<u8>,
-    access_token: String,
     merchant_accounts: HashMap<Currency, MerchantAccount>,
     payments_providers: Vec<Provider>,
     sweeping_approved_ibans: HashMap<String, String>,
@@ -44,9 +51,129 @@ struct MockServerConfiguration {
 
 #[derive(Clone, Default)]
 struct MockServerStorageInner {
+    access_tokens: HashSet<String>,
     payments: HashMap<String, (Payment, HashMap<String, Refund>)>,
     payouts: HashMap<String, Payout>,
     sweeping: HashMap<String, SweepingSettings>,
+    mandates: HashMap<String, Mandate>,
+    webhooks: Vec<serde_json::Value>,
+    /// How far the virtual clock of the mock server is ahead of the real one.
+    clock_offset: Duration,
+    /// How far the wall clock used by the mock server to check signatures is ahead of the clock
+    /// of the clients. Unlike `clock_offset`, it does not move the settlements forward.
+    signature_clock_skew: Duration,
+    settlement_delays: SettlementDelays,
+    scheduled_settlements: Vec<ScheduledSettlement>,
+}
+
+/// Delays simulated between the authorization of a payment into a merchant account at the bank,
+/// its execution and its settlement. Both default to zero, i.e. payments settle immediately.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SettlementDelays {
+    /// From the authorization to the execution of the payment.
+    pub execution: Duration,
+    /// From the execution to the moment the funds can be credited to the user, announced with
+    /// a `payment_creditable` webhook, no later than the settlement. `None` skips the webhook.
+    pub creditable: Option<Duration>,
+    /// From the execution to the settlement of the payment.
+    pub settlement: Duration,
+}
+
+/// Payment authorized at the bank which is still waiting to be executed or settled.
+#[derive(Clone)]
+struct ScheduledSettlement {
+    payment_id: String,
+    authorized_at: DateTime<Utc>,
+    authorization_flow: AuthorizationFlow,
+    /// Delays in effect when the payment was authorized.
+    delays: SettlementDelays,
+    creditable_notified: bool,
+}
+
+impl MockServerStorageInner {
+    /// Current time on the virtual clock of the mock server.
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now() + chrono::Duration::from_std(self.clock_offset).unwrap()
+    }
+
+    /// Executes and settles the scheduled payments whose time has come, recording the matching webhooks.
+    fn run_scheduled_settlements(&mut self) {
+        for mut settlement in std::mem::take(&mut self.scheduled_settlements) {
+            if !self.advance_settlement(&mut settlement) {
+                self.scheduled_settlements.push(settlement);
+            }
+        }
+    }
+
+    /// Moves a scheduled payment forward, returning `true` once it is settled.
+    fn advance_settlement(&mut self, settlement: &mut ScheduledSettlement) -> bool {
+        let now = self.now();
+        let delays = settlement.delays;
+        let executed_at =
+            settlement.authorized_at + chrono::Duration::from_std(delays.execution).unwrap();
+        let settled_at = executed_at + chrono::Duration::from_std(delays.settlement).unwrap();
+        let creditable_at = delays.creditable.map(|creditable| {
+            settled_at.min(executed_at + chrono::Duration::from_std(creditable).unwrap())
+        });
+
+        let payment = match self.payments.get_mut(&settlement.payment_id) {
+            Some((payment, _)) => payment,
+            None => return true,
+        };
+
+        if now >= executed_at && matches!(payment.status, PaymentStatus::Authorized { .. }) {
+            payment.status = PaymentStatus::Executed {
+                executed_at,
+                authorization_flow: Some(settlement.authorization_flow.clone()),
+                settlement_risk: None,
+            };
+            self.webhooks.push(json!({
+                "type": "payment_executed",
+                "event_version": 1,
+                "event_id": Uuid::new_v4().to_string(),
+                "payment_id": settlement.payment_id,
+                "executed_at": executed_at,
+            }));
+        }
+
+        if let Some(creditable_at) = creditable_at {
+            if now >= creditable_at && !settlement.creditable_notified {
+                settlement.creditable_notified = true;
+                self.webhooks.push(json!({
+                    "type": "payment_creditable",
+                    "event_version": 1,
+                    "event_id": Uuid::new_v4().to_string(),
+                    "payment_id": settlement.payment_id,
+                    "creditable_at": creditable_at,
+                }));
+            }
+        }
+
+        if now < settled_at {
+            return false;
+        }
+
+        payment.status = PaymentStatus::Settled {
+            payment_source: PaymentSource {
+                id: "source-id".into(),
+                user_id: None,
+                account_identifiers: vec![],
+                account_holder_name: None,
+            },
+            executed_at,
+            settled_at,
+            authorization_flow: Some(settlement.authorization_flow.clone()),
+            settlement_risk: None,
+        };
+        self.webhooks.push(json!({
+            "type": "payment_settled",
+            "event_version": 1,
+            "event_id": Uuid::new_v4().to_string(),
+            "payment_id": settlement.payment_id,
+            "settled_at": settled_at,
+        }));
+        true
+    }
 }
 
 /// In-memory storage for payments created on the mock server.
@@ -74,7 +201,6 @@ impl TrueLayerMockServer {
             client_secret: client_secret.to_string(),
             signing_key_id: signing_key_id.to_string(),
             signing_public_key,
-            access_token: Uuid::new_v4().to_string(),
             merchant_accounts: [
                 (
                     Currency::Gbp,
@@ -124,6 +250,7 @@ impl TrueLayerMockServer {
                             }),
                         },
                     },
+                    availability: None,
                 },
                 Provider {
                     id: MOCK_PROVIDER_NO_REDIRECT_ADDITIONAL_INPUTS.into(),
@@ -142,6 +269,7 @@ impl TrueLayerMockServer {
                             }),
                         },
                     },
+                    availability: None,
                 },
                 Provider {
                     id: MOCK_PROVIDER_GB_REDIRECT.into(),
@@ -160,6 +288,7 @@ impl TrueLayerMockServer {
                             }),
                         },
                     },
+                    availability: None,
                 },
                 Provider {
                     id: MOCK_PROVIDER_DE_ADDITIONAL_INPUTS.into(),
@@ -180,6 +309,7 @@ impl TrueLayerMockServer {
                             }),
                         },
                     },
+                    availability: None,
                 },
             ],
             sweeping_approved_ibans: [
@@ -199,7 +329,10 @@ impl TrueLayerMockServer {
             App::new()
                 .app_data(web::Data::new(configuration.clone()))
                 .app_data(web::Data::new(storage.clone()))
-                // User agent must be validated for each request
+                // User agent and access token must be validated for each request
+                .wrap(MiddlewareFn::new(middlewares::validate_access_token(
+                    storage.clone(),
+                )))
                 .wrap(MiddlewareFn::new(middlewares::validate_user_agent))
                 // Mock routes
                 .service(web::resource("/connect/token").route(web::post().to(routes::post_auth)))
@@ -208,6 +341,7 @@ impl TrueLayerMockServer {
                         .wrap(MiddlewareFn::new(middlewares::ensure_idempotency_key))
                         .wrap(MiddlewareFn::new(middlewares::validate_signature(
                             configuration.clone(),
+                            storage.clone(),
                             true,
                         )))
                         .route(web::post().to(routes::create_payment))
@@ -221,6 +355,7 @@ impl TrueLayerMockServer {
                         .wrap(MiddlewareFn::new(middlewares::ensure_idempotency_key))
                         .wrap(MiddlewareFn::new(middlewares::validate_signature(
                             configuration.clone(),
+                            storage.clone(),
                             true,
                         )))
                         .route(web::post().to(routes::start_authorization_flow)),
@@ -230,6 +365,7 @@ impl TrueLayerMockServer {
                         .wrap(MiddlewareFn::new(middlewares::ensure_idempotency_key))
                         .wrap(MiddlewareFn::new(middlewares::validate_signature(
                             configuration.clone(),
+                            storage.clone(),
                             true,
                         )))
                         .route(web::post().to(routes::submit_provider_selection)),
@@ -239,6 +375,7 @@ impl TrueLayerMockServer {
                         .wrap(MiddlewareFn::new(middlewares::ensure_idempotency_key))
                         .wrap(MiddlewareFn::new(middlewares::validate_signature(
                             configuration.clone(),
+                            storage.clone(),
                             true,
                         )))
                         .route(web::post().to(routes::submit_consent)),
@@ -248,6 +385,7 @@ impl TrueLayerMockServer {
                         .wrap(MiddlewareFn::new(middlewares::ensure_idempotency_key))
                         .wrap(MiddlewareFn::new(middlewares::validate_signature(
                             configuration.clone(),
+                            storage.clone(),
                             true,
                         )))
                         .route(web::post().to(routes::submit_form)),
@@ -257,6 +395,7 @@ impl TrueLayerMockServer {
                         .wrap(MiddlewareFn::new(middlewares::ensure_idempotency_key))
                         .wrap(MiddlewareFn::new(middlewares::validate_signature(
                             configuration.clone(),
+                            storage.clone(),
                             true,
                         )))
                         .route(web::post().to(routes::cancel_payment)),
@@ -266,6 +405,7 @@ impl TrueLayerMockServer {
                         .wrap(MiddlewareFn::new(middlewares::ensure_idempotency_key))
                         .wrap(MiddlewareFn::new(middlewares::validate_signature(
                             configuration.clone(),
+                            storage.clone(),
                             true,
                         )))
                         .route(web::post().to(routes::create_refund))
@@ -276,10 +416,15 @@ impl TrueLayerMockServer {
                         .wrap(MiddlewareFn::new(middlewares::ensure_idempotency_key))
                         .wrap(MiddlewareFn::new(middlewares::validate_signature(
                             configuration.clone(),
+                            storage.clone(),
                             true,
                         )))
                         .route(web::get().to(routes::get_refund_by_id)),
                 )
+                .service(
+                    web::resource("/payments-providers/search")
+                        .route(web::post().to(routes::search_payments_providers)),
+                )
                 .service(
                     web::resource("/payments-providers/{id}")
                         .route(web::get().to(routes::get_payments_provider_by_id)),
@@ -297,6 +442,7 @@ impl TrueLayerMockServer {
                         .wrap(MiddlewareFn::new(middlewares::ensure_idempotency_key))
                         .wrap(MiddlewareFn::new(middlewares::validate_signature(
                             configuration.clone(),
+                            storage.clone(),
                             true,
                         )))
                         .route(web::get().to(routes::get_merchant_account_sweeping_by_id))
@@ -312,6 +458,39 @@ impl TrueLayerMockServer {
                         .route(web::get().to(routes::list_payment_sources)),
                 )
                 .service(web::resource("/payouts").route(web::post().to(routes::create_payout)))
+                .service(
+                    web::resource("/mandates")
+                        .wrap(MiddlewareFn::new(middlewares::ensure_idempotency_key))
+                        .wrap(MiddlewareFn::new(middlewares::validate_signature(
+                            configuration.clone(),
+                            storage.clone(),
+                            true,
+                        )))
+                        .route(web::post().to(routes::create_mandate)),
+                )
+                .service(
+                    web::resource("/mandates/{id}").route(web::get().to(routes::get_mandate_by_id)),
+                )
+                .service(
+                    web::resource("/mandates/{id}/authorization-flow")
+                        .wrap(MiddlewareFn::new(middlewares::ensure_idempotency_key))
+                        .wrap(MiddlewareFn::new(middlewares::validate_signature(
+                            configuration.clone(),
+                            storage.clone(),
+                            true,
+                        )))
+                        .route(web::post().to(routes::start_mandate_authorization_flow)),
+                )
+                .service(
+                    web::resource("/mandates/{id}/revoke")
+                        .wrap(MiddlewareFn::new(middlewares::ensure_idempotency_key))
+                        .wrap(MiddlewareFn::new(middlewares::validate_signature(
+                            configuration.clone(),
+                            storage.clone(),
+                            true,
+                        )))
+                        .route(web::post().to(routes::revoke_mandate)),
+                )
                 .service(
                     web::resource("/payouts/{id}").route(web::get().to(routes::get_payout_by_id)),
                 )
@@ -400,53 +579,56 @@ impl TrueLayerMockServer {
             configuration: auth_flow_configuration,
         };
 
-        // Change payment status
-        payment.status = match action {
-            MockBankAction::Execute => {
-                if let PaymentMethod::BankTransfer {
-                    beneficiary: Beneficiary::MerchantAccount { .. },
-                    ..
-                } = payment.payment_method
-                {
-                    PaymentStatus::Settled {
-                        payment_source: PaymentSource {
-                            id: "source-id".into(),
-                            user_id: None,
-                            account_identifiers: vec![],
-                            account_holder_name: None,
-                        },
-                        executed_at: Utc::now(),
-                        settled_at: Utc::now(),
-                        authorization_flow: Some(next_auth_flow),
-                        settlement_risk: None,
-                    }
-                } else {
-                    PaymentStatus::Executed {
-                        executed_at: Utc::now(),
-                        authorization_flow: Some(next_auth_flow),
-                        settlement_risk: None,
-                    }
-                }
+        // Payments into a merchant account are executed and settled according to the configured delays
+        let into_merchant_account = matches!(
+            payment.payment_method,
+            PaymentMethod::BankTransfer {
+                beneficiary: Beneficiary::MerchantAccount { .. },
+                ..
             }
-            MockBankAction::RejectAuthorisation => PaymentStatus::Failed {
-                failed_at: Utc::now(),
-                failure_stage: FailureStage::Authorizing,
-                failure_reason: "authorization_failed".to_string(),
-                authorization_flow: Some(next_auth_flow),
-            },
-            MockBankAction::RejectExecution => PaymentStatus::Failed {
-                failed_at: Utc::now(),
-                failure_stage: FailureStage::Authorized,
-                failure_reason: "provider_rejected".to_string(),
-                authorization_flow: Some(next_auth_flow),
-            },
-            MockBankAction::Cancel => PaymentStatus::Failed {
-                failed_at: Utc::now(),
-                failure_stage: FailureStage::Authorizing,
-                failure_reason: "not_authorized".to_string(),
-                authorization_flow: Some(next_auth_flow),
-            },
-        };
+        );
+        if action == MockBankAction::Execute && into_merchant_account {
+            payment.status = PaymentStatus::Authorized {
+                authorization_flow: Some(next_auth_flow.clone()),
+            };
+            let authorized_at = storage.now();
+            let delays = storage.settlement_delays;
+            storage.scheduled_settlements.push(ScheduledSettlement {
+                payment_id: payment_id.to_string(),
+                authorized_at,
+                authorization_flow: next_auth_flow,
+                delays,
+                creditable_notified: false,
+            });
+            storage.run_scheduled_settlements();
+        } else {
+            // Change payment status
+            payment.status = match action {
+                MockBankAction::Execute => PaymentStatus::Executed {
+                    executed_at: Utc::now(),
+                    authorization_flow: Some(next_auth_flow),
+                    settlement_risk: None,
+                },
+                MockBankAction::RejectAuthorisation => PaymentStatus::Failed {
+                    failed_at: Utc::now(),
+                    failure_stage: FailureStage::Authorizing,
+                    failure_reason: "authorization_failed".to_string(),
+                    authorization_flow: Some(next_auth_flow),
+                },
+                MockBankAction::RejectExecution => PaymentStatus::Failed {
+                    failed_at: Utc::now(),
+                    failure_stage: FailureStage::Authorized,
+                    failure_reason: "provider_rejected".to_string(),
+                    authorization_flow: Some(next_auth_flow),
+                },
+                MockBankAction::Cancel => PaymentStatus::Failed {
+                    failed_at: Utc::now(),
+                    failure_stage: FailureStage::Authorizing,
+                    failure_reason: "not_authorized".to_string(),
+                    authorization_flow: Some(next_auth_flow),
+                },
+            };
+        }
 
         Ok(Url::from_str(&format!(
             "https://mock.return.uri/#{}",
@@ -455,6 +637,242 @@ impl TrueLayerMockServer {
     }
 }
 
+impl TrueLayerMockServer {
+    /// Creates a new sweeping mandate waiting for authorization, returning its id.
+    pub fn create_mandate(&self, maximum_individual_amount_in_minor: u64) -> String {
+        let id = Uuid::new_v4().to_string();
+        let merchant_account_id = self.configuration.merchant_accounts[&Currency::Gbp]
+            .id
+            .clone();
+
+        self.storage.write().unwrap().mandates.insert(
+            id.clone(),
+            Mandate {
+                id: id.clone(),
+                currency: Currency::Gbp,
+                mandate: MandateDetail {
+                    r#type: MandateType::Sweeping,
+                    provider_selection: MandateProviderSelection::Preselected {
+                        provider_id: MOCK_PROVIDER_GB_REDIRECT.to_string(),
+                        remitter: None,
+                    },
+                    beneficiary: Beneficiary::MerchantAccount {
+                        merchant_account_id,
+                        account_holder_name: None,
+                    },
+                    reference: None,
+                },
+                constraints: Constraints {
+                    valid_from: None,
+                    valid_to: None,
+                    maximum_individual_amount: maximum_individual_amount_in_minor,
+                    periodic_limits: Default::default(),
+                },
+                user: Some(User {
+                    id: "payment-source-user-id".to_string(),
+                }),
+                created_at: Utc::now(),
+                metadata: None,
+                status: MandateStatus::AuthorizationRequired,
+            },
+        );
+
+        id
+    }
+
+    /// Simulates the user completing (or abandoning) the mandate authorization at their bank.
+    pub fn complete_mandate_authorization(
+        &self,
+        mandate_id: &str,
+        action: MockBankAction,
+    ) -> Result<(), anyhow::Error> {
+        let mut storage = self.storage.write().unwrap();
+        let mandate = storage
+            .mandates
+            .get_mut(mandate_id)
+            .context("Mandate not found")?;
+        anyhow::ensure!(
+            matches!(
+                mandate.status,
+                MandateStatus::AuthorizationRequired | MandateStatus::Authorizing { .. }
+            ),
+            "Invalid mandate state"
+        );
+
+        let webhook = match action {
+            MockBankAction::Execute => {
+                let authorized_at = Utc::now();
+                mandate.status = MandateStatus::Authorized {
+                    authorized_at,
+                    authorization_flow: None,
+                };
+                json!({
+                    "type": "mandate_authorized",
+                    "event_version": 1,
+                    "event_id": Uuid::new_v4().to_string(),
+                    "mandate_id": mandate_id,
+                    "authorized_at": authorized_at,
+                })
+            }
+            MockBankAction::RejectAuthorisation
+            | MockBankAction::RejectExecution
+            | MockBankAction::Cancel => {
+                let failed_at = Utc::now();
+                let failure_reason = if action == MockBankAction::Cancel {
+                    "not_authorized"
+                } else {
+                    "authorization_failed"
+                };
+                mandate.status = MandateStatus::Failed {
+                    failed_at,
+                    failure_stage: FailureStage::Authorizing,
+                    failure_reason: failure_reason.to_string(),
+                    authorization_flow: None,
+                };
+                json!({
+                    "type": "mandate_failed",
+                    "event_version": 1,
+                    "event_id": Uuid::new_v4().to_string(),
+                    "mandate_id": mandate_id,
+                    "failed_at": failed_at,
+                    "failure_stage": "authorizing",
+                    "failure_reason": failure_reason,
+                })
+            }
+        };
+        storage.webhooks.push(webhook);
+
+        Ok(())
+    }
+
+    /// Simulates the user revoking an authorized mandate directly from their bank.
+    pub fn revoke_mandate_from_bank(&self, mandate_id: &str) -> Result<(), anyhow::Error> {
+        revoke_stored_mandate(
+            &mut self.storage.write().unwrap(),
+            mandate_id,
+            RevocationSource::Provider,
+        )
+    }
+
+    /// Simulates a payment on a mandate, returning the id of the new payment.
+    ///
+    /// The payment fails with a `constraint_violation` if the amount exceeds the mandate constraints,
+    /// or with a `mandate_revoked` if the mandate is not authorized.
+    pub fn charge_mandate(
+        &self,
+        mandate_id: &str,
+        amount_in_minor: u64,
+    ) -> Result<String, anyhow::Error> {
+        let mut storage = self.storage.write().unwrap();
+        let mandate = storage
+            .mandates
+            .get(mandate_id)
+            .context("Mandate not found")?
+            .clone();
+
+        let payment_id = Uuid::new_v4().to_string();
+        let failure_reason = if !matches!(mandate.status, MandateStatus::Authorized { .. }) {
+            Some("mandate_revoked")
+        } else if amount_in_minor > mandate.constraints.maximum_individual_amount {
+            Some("constraint_violation")
+        } else {
+            None
+        };
+
+        let mut webhook = json!({
+            "event_version": 1,
+            "event_id": Uuid::new_v4().to_string(),
+            "payment_id": payment_id,
+            "payment_method": {
+                "type": "mandate",
+                "mandate_id": mandate_id,
+            },
+        });
+        match failure_reason {
+            Some(failure_reason) => {
+                webhook["type"] = json!("payment_failed");
+                webhook["failed_at"] = json!(Utc::now());
+                webhook["failure_stage"] = json!("authorized");
+                webhook["failure_reason"] = json!(failure_reason);
+            }
+            None => {
+                webhook["type"] = json!("payment_executed");
+                webhook["executed_at"] = json!(Utc::now());
+            }
+        }
+        storage.webhooks.push(webhook);
+
+        Ok(payment_id)
+    }
+
+    /// Expires all the access tokens issued so far, before their advertised expiration.
+    ///
+    /// Requests authenticated with any of them are rejected with `401 Unauthorized`
+    /// until the client requests a new token.
+    pub fn expire_access_tokens(&self) {
+        self.storage.write().unwrap().access_tokens.clear();
+    }
+
+    /// Returns all the webhooks the mock server would have delivered so far, oldest first.
+    pub fn webhooks(&self) -> Vec<serde_json::Value> {
+        self.storage.read().unwrap().webhooks.clone()
+    }
+
+    /// Sets the delays simulated for the payments into a merchant account authorized from now on.
+    pub fn set_settlement_delays(&self, settlement_delays: SettlementDelays) {
+        self.storage.write().unwrap().settlement_delays = settlement_delays;
+    }
+
+    /// Skews the wall clock used to check signatures, so that signed requests are rejected
+    /// if it is more than five minutes ahead of the clock of the clients.
+    pub fn skew_signature_clock(&self, skew: Duration) {
+        self.storage.write().unwrap().signature_clock_skew = skew;
+    }
+
+    /// Moves the virtual clock of the mock server forward, executing, making creditable and settling
+    /// the payments which became due in the meantime.
+    ///
+    /// This lets tests go through timelines of minutes or hours without actually waiting.
+    pub fn advance_time(&self, duration: Duration) {
+        let mut storage = self.storage.write().unwrap();
+        storage.clock_offset += duration;
+        storage.run_scheduled_settlements();
+    }
+}
+
+/// Moves an authorized mandate to the revoked state, recording the matching webhook.
+fn revoke_stored_mandate(
+    storage: &mut MockServerStorageInner,
+    mandate_id: &str,
+    revocation_source: RevocationSource,
+) -> Result<(), anyhow::Error> {
+    let mandate = storage
+        .mandates
+        .get_mut(mandate_id)
+        .context("Mandate not found")?;
+    anyhow::ensure!(
+        matches!(mandate.status, MandateStatus::Authorized { .. }),
+        "Invalid mandate state"
+    );
+
+    let revoked_at = Utc::now();
+    mandate.status = MandateStatus::Revoked {
+        revoked_at,
+        revocation_source: revocation_source.clone(),
+        authorization_flow: None,
+    };
+    storage.webhooks.push(json!({
+        "type": "mandate_revoked",
+        "event_version": 1,
+        "event_id": Uuid::new_v4().to_string(),
+        "mandate_id": mandate_id,
+        "revoked_at": revoked_at,
+        "revocation_source": revocation_source,
+    }));
+
+    Ok(())
+}
+
 impl Drop for TrueLayerMockServer {
     fn drop(&mut self) {
         // Send a shutdown signal to the actix server on drop
@@ -1,15 +1,17 @@
 use std::collections::HashMap;
 
 use crate::common::mock_server::{
-    MockServerConfiguration, MockServerStorage, MOCK_PROVIDER_DE_ADDITIONAL_INPUTS,
-    MOCK_PROVIDER_GB_REDIRECT, MOCK_PROVIDER_NO_REDIRECT_ADDITIONAL_INPUTS,
-    MOCK_PROVIDER_PL_REDIRECT_ADDITIONAL_INPUTS, MOCK_REDIRECT_URI,
+    revoke_stored_mandate, MockServerConfiguration, MockServerStorage,
+    MOCK_PROVIDER_DE_ADDITIONAL_INPUTS, MOCK_PROVIDER_GB_REDIRECT,
+    MOCK_PROVIDER_NO_REDIRECT_ADDITIONAL_INPUTS, MOCK_PROVIDER_PL_REDIRECT_ADDITIONAL_INPUTS,
+    MOCK_REDIRECT_URI, MOCK_RETURNED_PAYOUT_REFERENCE, MOCK_SEPA_INSTANT_LIMIT_IN_MINOR,
 };
 use actix_web::{web, HttpResponse};
 use chrono::offset::Utc;
 use serde_json::json;
 use truelayer_rust::apis::{
     auth::Credentials,
+    mandates::{CreateMandateRequest, Mandate, MandateStatus, RevocationSource},
     merchant_accounts::{
         ListPaymentSourcesRequest, SetupSweepingRequest, SweepingSettings, Transaction,
         TransactionPayinStatus, TransactionType,
@@ -25,13 +27,17 @@ use truelayer_rust::apis::{
         SubmitProviderReturnParametersRequest, SubmitProviderSelectionActionRequest,
         SubsequentAction, User,
     },
-    payouts::{CreatePayoutRequest, Payout, PayoutStatus},
+    payments_providers::SearchProvidersRequest,
+    payouts::{
+        CreatePayoutRequest, Payout, PayoutBeneficiary, PayoutSchemeSelection, PayoutStatus,
+    },
 };
 use uuid::Uuid;
 
 /// POST /connect/token
 pub(super) async fn post_auth(
     configuration: web::Data<MockServerConfiguration>,
+    storage: web::Data<MockServerStorage>,
     incoming: web::Json<Credentials>,
 ) -> HttpResponse {
     match incoming.into_inner() {
@@ -42,9 +48,16 @@ pub(super) async fn post_auth(
         } if client_id == configuration.client_id
             && client_secret.expose_secret() == configuration.client_secret =>
         {
+            let access_token = Uuid::new_v4().to_string();
+            storage
+                .write()
+                .unwrap()
+                .access_tokens
+                .insert(access_token.clone());
+
             HttpResponse::Ok().json(json!({
                 "token_type": "Bearer",
-                "access_token": configuration.access_token,
+                "access_token": access_token,
                 "expires_in": 3600
             }))
         }
@@ -112,6 +125,8 @@ pub(super) async fn create_payment(
                 created_at: Utc::now(),
                 status: PaymentStatus::AuthorizationRequired,
                 metadata: create_payment_request.metadata.clone(),
+                retry: create_payment_request.retry.clone(),
+                attempts: Vec::new(),
             },
             HashMap::new(),
         ),
@@ -518,6 +533,24 @@ pub(super) async fn get_payments_provider_by_id(
     }
 }
 
+/// POST /payments-providers/search
+pub(super) async fn search_payments_providers(
+    configuration: web::Data<MockServerConfiguration>,
+    request: web::Json<SearchProvidersRequest>,
+) -> HttpResponse {
+    let items: Vec<_> = configuration
+        .payments_providers
+        .iter()
+        .filter(|p| match (&request.countries, &p.country_code) {
+            (Some(countries), Some(country_code)) => countries.contains(country_code),
+            (Some(_), None) => false,
+            (None, _) => true,
+        })
+        .collect();
+
+    HttpResponse::Ok().json(json!({ "items": items }))
+}
+
 /// POST /payments/{id}/refunds
 pub(super) async fn create_refund(
     storage: web::Data<MockServerStorage>,
@@ -746,6 +779,7 @@ pub(super) async fn create_payout(
     }
 
     let payout_id = Uuid::new_v4().to_string();
+    let (scheme_id, final_status) = mock_payout_outcome(&request);
     storage.write().unwrap().payouts.insert(
         payout_id.clone(),
         Payout {
@@ -755,22 +789,67 @@ pub(super) async fn create_payout(
             currency: request.currency.clone(),
             beneficiary: request.beneficiary.clone(),
             created_at: Utc::now(),
+            scheme_id,
             status: PayoutStatus::Pending,
         },
     );
 
-    // Automatically make the payout executed after 1 second
+    // Automatically complete the payout
     let payout_id_clone = payout_id.clone();
     tokio::spawn(async move {
         let mut guard = storage.write().unwrap();
-        guard.payouts.get_mut(&payout_id_clone).unwrap().status = PayoutStatus::Executed {
-            executed_at: Utc::now(),
-        };
+        guard.payouts.get_mut(&payout_id_clone).unwrap().status = final_status;
     });
 
     HttpResponse::Created().json(json!({ "id": payout_id }))
 }
 
+/// Picks the scheme and the final status of a payout, simulating the SEPA schemes for EUR payouts:
+/// - payouts above [`MOCK_SEPA_INSTANT_LIMIT_IN_MINOR`] cannot use SEPA Instant, so they fall back to
+///   the regular SEPA Credit Transfer, or fail if an instant scheme is required,
+/// - payouts to an external account with the [`MOCK_RETURNED_PAYOUT_REFERENCE`] are returned
+///   by the beneficiary's bank after being executed.
+fn mock_payout_outcome(request: &CreatePayoutRequest) -> (Option<String>, PayoutStatus) {
+    let instant_available = request.currency != Currency::Eur
+        || request.amount_in_minor <= MOCK_SEPA_INSTANT_LIMIT_IN_MINOR;
+    let (instant_scheme, regular_scheme) = match request.currency {
+        Currency::Eur => ("sepa_credit_transfer_instant", "sepa_credit_transfer"),
+        _ => ("faster_payments_service", "faster_payments_service"),
+    };
+
+    let scheme_id = match &request.scheme_selection {
+        Some(PayoutSchemeSelection::Preselected { scheme_id }) => scheme_id.clone(),
+        Some(PayoutSchemeSelection::InstantOnly) if !instant_available => {
+            return (
+                None,
+                PayoutStatus::Failed {
+                    failed_at: Utc::now(),
+                    failure_reason: "scheme_unavailable".to_string(),
+                },
+            )
+        }
+        _ if instant_available => instant_scheme.to_string(),
+        _ => regular_scheme.to_string(),
+    };
+
+    let status = match &request.beneficiary {
+        PayoutBeneficiary::ExternalAccount { reference, .. }
+            if reference == MOCK_RETURNED_PAYOUT_REFERENCE =>
+        {
+            PayoutStatus::Returned {
+                executed_at: Utc::now(),
+                returned_at: Utc::now(),
+                return_reason: "account_closed".to_string(),
+            }
+        }
+        _ => PayoutStatus::Executed {
+            executed_at: Utc::now(),
+        },
+    };
+
+    (Some(scheme_id), status)
+}
+
 /// GET /payouts/{id}
 pub(super) async fn get_payout_by_id(
     storage: web::Data<MockServerStorage>,
@@ -793,3 +872,108 @@ pub(super) async fn submit_provider_return_parameters(
         }
     }))
 }
+
+/// POST /mandates
+pub(super) async fn create_mandate(
+    storage: web::Data<MockServerStorage>,
+    request: web::Json<CreateMandateRequest>,
+) -> HttpResponse {
+    let id = Uuid::new_v4().to_string();
+    let user = match request.user.clone() {
+        CreatePaymentUserRequest::NewUser { .. } => User {
+            id: "payment-source-user-id".to_string(),
+        },
+        CreatePaymentUserRequest::ExistingUser { id } => User { id },
+    };
+
+    storage.write().unwrap().mandates.insert(
+        id.clone(),
+        Mandate {
+            id: id.clone(),
+            currency: request.currency.clone(),
+            mandate: request.mandate.clone(),
+            constraints: request.constraints.clone(),
+            user: Some(user.clone()),
+            created_at: Utc::now(),
+            metadata: request.metadata.clone(),
+            status: MandateStatus::AuthorizationRequired,
+        },
+    );
+
+    HttpResponse::Created().json(json!({
+        "id": id,
+        "resource_token": format!("resource-token-{}", id),
+        "user": {
+            "id": user.id
+        }
+    }))
+}
+
+/// GET /mandates/{id}
+pub(super) async fn get_mandate_by_id(
+    storage: web::Data<MockServerStorage>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let id = path.into_inner();
+
+    storage.read().unwrap().mandates.get(&id).map_or_else(
+        || HttpResponse::NotFound().finish(),
+        |mandate| HttpResponse::Ok().json(mandate),
+    )
+}
+
+/// POST /mandates/{id}/authorization-flow
+pub(super) async fn start_mandate_authorization_flow(
+    storage: web::Data<MockServerStorage>,
+    path: web::Path<String>,
+    _body: web::Json<StartAuthorizationFlowRequest>, // Just for validation of the body
+) -> HttpResponse {
+    let id = path.into_inner();
+
+    let mut map = storage.write().unwrap();
+    let mandate = match map.mandates.get_mut(&id) {
+        Some(mandate) => mandate,
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    if mandate.status != MandateStatus::AuthorizationRequired {
+        return HttpResponse::BadRequest().finish();
+    }
+
+    // The mock bank authorization is completed with `TestContext::complete_mock_mandate_authorization`
+    let authorization_flow = AuthorizationFlow {
+        actions: Some(AuthorizationFlowActions {
+            next: AuthorizationFlowNextAction::Redirect {
+                uri: format!("{}{}", MOCK_REDIRECT_URI, mandate.id),
+                metadata: None,
+            },
+        }),
+        configuration: None,
+    };
+    mandate.status = MandateStatus::Authorizing {
+        authorization_flow: authorization_flow.clone(),
+    };
+
+    HttpResponse::Ok().json(StartAuthorizationFlowResponse {
+        authorization_flow: Some(authorization_flow),
+        status: AuthorizationFlowResponseStatus::Authorizing,
+    })
+}
+
+/// POST /mandates/{id}/revoke
+pub(super) async fn revoke_mandate(
+    storage: web::Data<MockServerStorage>,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let id = path.into_inner();
+
+    let mut map = storage.write().unwrap();
+    if !map.mandates.contains_key(&id) {
+        return HttpResponse::NotFound().finish();
+    }
+
+    match revoke_stored_mandate(&mut map, &id, RevocationSource::Client) {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::BadRequest().finish(),
+    }
+}
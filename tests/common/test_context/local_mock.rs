@@ -1,8 +1,12 @@
-use crate::common::{mock_server::TrueLayerMockServer, MockBankAction};
+use crate::common::{
+    mock_server::{SettlementDelays, TrueLayerMockServer},
+    MockBankAction,
+};
 use openssl::{
     ec::{EcGroup, EcKey},
     nid::Nid,
 };
+use std::time::Duration;
 use truelayer_rust::{
     apis::{auth::Credentials, payments::Currency},
     client::Environment,
@@ -15,6 +19,10 @@ pub struct TestContext {
     pub client: TrueLayerClient,
     pub merchant_account_gbp_id: String,
     pub merchant_account_gbp_sweeping_iban: String,
+    pub merchant_account_eur_id: String,
+    client_id: String,
+    client_secret: String,
+    signing_key_id: String,
     mock_server: TrueLayerMockServer,
 }
 
@@ -37,23 +45,22 @@ impl TestContext {
         .await;
 
         // Configure a new TrueLayerClient to point to the mock server
-        let client = TrueLayerClient::builder(Credentials::ClientCredentials {
-            client_id: client_id.clone(),
-            client_secret: client_secret.clone().into(),
-            scope: "payments paydirect".to_string(),
-        })
-        .with_signing_key(
+        let client = build_client(
+            &mock_server,
+            &client_id,
+            &client_secret,
             &signing_key_id,
             signing_private_key.private_key_to_pem().unwrap(),
-        )
-        .with_retry_policy(None) // Disable retries against the mock server
-        .with_environment(Environment::from_single_url(mock_server.url()))
-        .build();
+        );
 
         let merchant_account_gbp_id = mock_server
             .merchant_account(Currency::Gbp)
             .map(|m| m.id.clone())
             .unwrap();
+        let merchant_account_eur_id = mock_server
+            .merchant_account(Currency::Eur)
+            .map(|m| m.id.clone())
+            .unwrap();
 
         Self {
             client,
@@ -61,10 +68,35 @@ impl TestContext {
                 .sweeping_iban(&merchant_account_gbp_id)
                 .unwrap(),
             merchant_account_gbp_id,
+            merchant_account_eur_id,
+            client_id,
+            client_secret,
+            signing_key_id,
             mock_server,
         }
     }
 
+    /// Id of the signing key whose public key is known to the mock server.
+    pub fn signing_key_id(&self) -> &str {
+        &self.signing_key_id
+    }
+
+    /// Builds a new client for the mock server, with the same credentials as [`client`](Self::client)
+    /// but signing requests with the given key.
+    pub fn client_with_signing_key(
+        &self,
+        key_id: &str,
+        private_key_pem: Vec<u8>,
+    ) -> TrueLayerClient {
+        build_client(
+            &self.mock_server,
+            &self.client_id,
+            &self.client_secret,
+            key_id,
+            private_key_pem,
+        )
+    }
+
     pub fn tl_environment(&self) -> Environment {
         Environment::from_single_url(self.mock_server.url())
     }
@@ -88,4 +120,93 @@ impl TestContext {
         // This work is usually done by TrueLayer's SPA upon redirect from the provider.
         Ok(())
     }
+
+    pub fn create_mock_mandate(&self, maximum_individual_amount_in_minor: u64) -> String {
+        self.mock_server
+            .create_mandate(maximum_individual_amount_in_minor)
+    }
+
+    pub fn complete_mock_mandate_authorization(
+        &self,
+        mandate_id: &str,
+        action: MockBankAction,
+    ) -> Result<(), anyhow::Error> {
+        self.mock_server
+            .complete_mandate_authorization(mandate_id, action)
+    }
+
+    pub fn revoke_mock_mandate_from_bank(&self, mandate_id: &str) -> Result<(), anyhow::Error> {
+        self.mock_server.revoke_mandate_from_bank(mandate_id)
+    }
+
+    pub fn charge_mock_mandate(
+        &self,
+        mandate_id: &str,
+        amount_in_minor: u64,
+    ) -> Result<String, anyhow::Error> {
+        self.mock_server.charge_mandate(mandate_id, amount_in_minor)
+    }
+
+    /// Expires the access tokens held by the client, as if they had been revoked by TrueLayer.
+    pub fn expire_access_tokens(&self) {
+        self.mock_server.expire_access_tokens()
+    }
+
+    pub fn webhooks(&self) -> Vec<serde_json::Value> {
+        self.mock_server.webhooks()
+    }
+
+    /// Delays the execution and the settlement of the payments into a merchant account
+    /// authorized from now on, as measured by the virtual clock of the mock server.
+    pub fn set_mock_settlement_delays(&self, execution: Duration, settlement: Duration) {
+        self.mock_server.set_settlement_delays(SettlementDelays {
+            execution,
+            creditable: None,
+            settlement,
+        })
+    }
+
+    /// Same as [`set_mock_settlement_delays`](Self::set_mock_settlement_delays), but the payments
+    /// also become creditable to the user `creditable` after their execution, before they settle.
+    pub fn set_mock_partial_settlement_delays(
+        &self,
+        execution: Duration,
+        creditable: Duration,
+        settlement: Duration,
+    ) {
+        self.mock_server.set_settlement_delays(SettlementDelays {
+            execution,
+            creditable: Some(creditable),
+            settlement,
+        })
+    }
+
+    /// Skews the clock used by the mock server to check signatures, as if the clock
+    /// of the clients was behind by `skew`.
+    pub fn skew_mock_signature_clock(&self, skew: Duration) {
+        self.mock_server.skew_signature_clock(skew)
+    }
+
+    /// Moves the virtual clock of the mock server forward.
+    pub fn advance_mock_time(&self, duration: Duration) {
+        self.mock_server.advance_time(duration)
+    }
+}
+
+fn build_client(
+    mock_server: &TrueLayerMockServer,
+    client_id: &str,
+    client_secret: &str,
+    signing_key_id: &str,
+    signing_private_key_pem: Vec<u8>,
+) -> TrueLayerClient {
+    TrueLayerClient::builder(Credentials::ClientCredentials {
+        client_id: client_id.to_string(),
+        client_secret: client_secret.to_string().into(),
+        scope: "payments paydirect".to_string(),
+    })
+    .with_signing_key(signing_key_id, signing_private_key_pem)
+    .with_retry_policy(None) // Disable retries against the mock server
+    .with_environment(Environment::from_single_url(mock_server.url()))
+    .build()
 }
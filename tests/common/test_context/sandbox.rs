@@ -11,6 +11,7 @@ pub struct TestContext {
     pub client: TrueLayerClient,
     pub merchant_account_gbp_id: String,
     pub merchant_account_gbp_sweeping_iban: String,
+    pub merchant_account_eur_id: String,
 }
 
 impl TestContext {
@@ -24,6 +25,8 @@ impl TestContext {
             std::env::var("ACCEPTANCE_TESTS_MERCHANT_ACCOUNT_GBP_ID").unwrap();
         let merchant_account_gbp_sweeping_iban =
             std::env::var("ACCEPTANCE_TESTS_MERCHANT_ACCOUNT_GBP_SWEEPING_IBAN").unwrap();
+        let merchant_account_eur_id =
+            std::env::var("ACCEPTANCE_TESTS_MERCHANT_ACCOUNT_EUR_ID").unwrap();
 
         // Configure a new TrueLayerClient to point to Sandbox
         let client = TrueLayerClient::builder(Credentials::ClientCredentials {
@@ -39,6 +42,7 @@ impl TestContext {
             client,
             merchant_account_gbp_id,
             merchant_account_gbp_sweeping_iban,
+            merchant_account_eur_id,
         }
     }
 
@@ -0,0 +1,518 @@
+//! Forward-compatibility harness for the models deserialized from TrueLayer responses.
+//!
+//! TrueLayer evolves its APIs without bumping their version by adding fields, adding enum values
+//! and serializing keys in any order. Each payload is mutated in all these ways and passed through
+//! the deserializer of its model, which must keep accepting it.
+//!
+//! This file is compiled as its own test target, it must not be declared as a module of `main.rs`.
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Map, Value};
+use truelayer_rust::apis::{
+    mandates::Mandate,
+    merchant_accounts::Transaction,
+    payment_links::{PaymentLink, PaymentLinkDisablementReason, PaymentLinkStatus},
+    payments::{refunds::Refund, CountryCode, Payment, StartAuthorizationFlowResponse},
+    payments_providers::{Provider, ProviderAvailabilityStatus},
+    payouts::Payout,
+    verification::{Verification, VerificationOutcome, VerificationStatus},
+    webhooks::{Webhook, WebhookEvent},
+};
+
+/// Name of the field added to every object of a payload.
+const EXTRA_FIELD: &str = "some_future_field";
+
+/// Value which no enum of this library knows about.
+const UNKNOWN_ENUM_VALUE: &str = "some_future_value";
+
+/// Collects the JSON pointers of the string values of the payload which look like enum values,
+/// i.e. snake case identifiers outside of `id` fields.
+fn collect_enum_values(value: &Value, pointer: &str, field: Option<&str>, found: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                let pointer = format!("{}/{}", pointer, k.replace('~', "~0").replace('/', "~1"));
+                collect_enum_values(v, &pointer, Some(k), found);
+            }
+        }
+        Value::Array(items) => {
+            for (i, v) in items.iter().enumerate() {
+                collect_enum_values(v, &format!("{}/{}", pointer, i), field, found);
+            }
+        }
+        Value::String(s) => {
+            let looks_like_enum_value = s.starts_with(|c: char| c.is_ascii_lowercase())
+                && s.chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+            match field {
+                Some(field)
+                    if looks_like_enum_value && field != "id" && !field.ends_with("_id") =>
+                {
+                    found.push(pointer.to_string())
+                }
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Adds an unknown field to every object of the payload, recursively.
+fn with_extra_fields(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut map: Map<String, Value> = map
+                .iter()
+                .map(|(k, v)| (k.clone(), with_extra_fields(v)))
+                .collect();
+            map.insert(EXTRA_FIELD.to_string(), json!("forward-compat"));
+            Value::Object(map)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(with_extra_fields).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Serializes the payload with the keys of every object in reverse order,
+/// so that the tags of internally tagged enums come after the fields of the variants.
+fn to_string_with_reversed_keys(value: &Value) -> String {
+    match value {
+        Value::Object(map) => format!(
+            "{{{}}}",
+            map.iter()
+                .rev()
+                .map(|(k, v)| format!(
+                    "{}:{}",
+                    Value::String(k.clone()),
+                    to_string_with_reversed_keys(v)
+                ))
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        Value::Array(items) => format!(
+            "[{}]",
+            items
+                .iter()
+                .map(to_string_with_reversed_keys)
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        other => other.to_string(),
+    }
+}
+
+/// Asserts that the mutated versions of the payload are accepted by the deserializer of `T`.
+///
+/// `closed` lists the pointers to the enum values which are still allowed to reject unknown
+/// values: the tags of internally tagged enums without a catch-all variant, and the enums
+/// without a catch-all variant yet. Every other enum value must accept unknown values.
+fn assert_forward_compatible<T>(payload: Value, closed: &[&str])
+where
+    T: DeserializeOwned + Serialize,
+{
+    let type_name = std::any::type_name::<T>();
+    let baseline: T = serde_json::from_value(payload.clone())
+        .unwrap_or_else(|e| panic!("{} rejected its baseline payload: {}", type_name, e));
+    let baseline = serde_json::to_value(&baseline).unwrap();
+
+    serde_json::from_value::<T>(with_extra_fields(&payload))
+        .unwrap_or_else(|e| panic!("{} rejected unknown fields: {}", type_name, e));
+
+    let reordered: T = serde_json::from_str(&to_string_with_reversed_keys(&payload))
+        .unwrap_or_else(|e| panic!("{} rejected reordered keys: {}", type_name, e));
+    assert_eq!(
+        serde_json::to_value(&reordered).unwrap(),
+        baseline,
+        "{} changed when its keys were reordered",
+        type_name
+    );
+
+    let mut enum_values = Vec::new();
+    collect_enum_values(&payload, "", None, &mut enum_values);
+    for pointer in closed {
+        assert!(
+            enum_values.iter().any(|p| p == pointer),
+            "{} is not an enum value of the {} payload",
+            pointer,
+            type_name
+        );
+    }
+    for pointer in enum_values {
+        if closed.contains(&pointer.as_str()) {
+            continue;
+        }
+
+        let mut mutated = payload.clone();
+        *mutated.pointer_mut(&pointer).unwrap() = json!(UNKNOWN_ENUM_VALUE);
+        serde_json::from_value::<T>(mutated).unwrap_or_else(|e| {
+            panic!(
+                "{} rejected an unknown value at {}: {}",
+                type_name, pointer, e
+            )
+        });
+    }
+}
+
+fn payment_json(status: Value) -> Value {
+    let mut payment = json!({
+        "id": "payment-id",
+        "amount_in_minor": 100,
+        "currency": "GBP",
+        "payment_method": {
+            "type": "bank_transfer",
+            "provider_selection": {
+                "type": "user_selected"
+            },
+            "beneficiary": {
+                "type": "merchant_account",
+                "merchant_account_id": "merchant-account-id"
+            }
+        },
+        "user": {
+            "id": "user-id"
+        },
+        "created_at": "2022-04-01T00:00:00Z",
+        "metadata": {
+            "key": "value"
+        }
+    });
+    payment
+        .as_object_mut()
+        .unwrap()
+        .extend(status.as_object().unwrap().clone());
+    payment
+}
+
+/// Tags of the payment method of a payment.
+const PAYMENT_METHOD_TAGS: [&str; 3] = [
+    "/payment_method/type",
+    "/payment_method/provider_selection/type",
+    "/payment_method/beneficiary/type",
+];
+
+#[test]
+fn payments() {
+    let closed = [&["/status"][..], &PAYMENT_METHOD_TAGS].concat();
+    assert_forward_compatible::<Payment>(
+        payment_json(json!({
+            "status": "authorization_required"
+        })),
+        &closed,
+    );
+    assert_forward_compatible::<Payment>(
+        payment_json(json!({
+            "status": "settled",
+            "payment_source": {
+                "id": "payment-source-id",
+                "account_holder_name": "Mr. Holder",
+                "account_identifiers": [{
+                    "type": "sort_code_account_number",
+                    "sort_code": "123456",
+                    "account_number": "12345678"
+                }]
+            },
+            "executed_at": "2022-04-01T00:01:00Z",
+            "settled_at": "2022-04-01T00:02:00Z"
+        })),
+        &[&closed[..], &["/payment_source/account_identifiers/0/type"]].concat(),
+    );
+    assert_forward_compatible::<Payment>(
+        payment_json(json!({
+            "status": "failed",
+            "failed_at": "2022-04-01T00:01:00Z",
+            "failure_stage": "authorizing",
+            "failure_reason": "authorization_failed"
+        })),
+        &closed,
+    );
+}
+
+#[test]
+fn authorization_flows() {
+    let payload = json!({
+        "status": "authorizing",
+        "authorization_flow": {
+            "actions": {
+                "next": {
+                    "type": "form",
+                    "inputs": [{
+                        "type": "select",
+                        "id": "branch",
+                        "mandatory": true,
+                        "display_text": {
+                            "key": "branch.display_text",
+                            "default": "Branch"
+                        },
+                        "options": [{
+                            "id": "branch-id",
+                            "display_text": {
+                                "key": "branch-id.display_text",
+                                "default": "Some Branch"
+                            }
+                        }]
+                    }]
+                }
+            }
+        }
+    });
+    assert_forward_compatible::<StartAuthorizationFlowResponse>(
+        payload,
+        &[
+            "/status",
+            "/authorization_flow/actions/next/type",
+            "/authorization_flow/actions/next/inputs/0/type",
+        ],
+    );
+}
+
+#[test]
+fn refunds() {
+    let payload = json!({
+        "id": "refund-id",
+        "amount_in_minor": 100,
+        "currency": "GBP",
+        "reference": "some-reference",
+        "created_at": "2022-04-01T00:00:00Z",
+        "status": "pending"
+    });
+    assert_forward_compatible::<Refund>(payload, &["/status"]);
+}
+
+#[test]
+fn payouts() {
+    let payload = json!({
+        "id": "payout-id",
+        "merchant_account_id": "merchant-account-id",
+        "amount_in_minor": 100,
+        "currency": "EUR",
+        "beneficiary": {
+            "type": "external_account",
+            "account_holder_name": "Mr. Holder",
+            "account_identifier": {
+                "type": "iban",
+                "iban": "some-iban"
+            },
+            "reference": "some-reference"
+        },
+        "created_at": "2022-04-01T00:00:00Z",
+        "scheme_id": "sepa_credit_transfer_instant",
+        "status": "executed",
+        "executed_at": "2022-04-01T00:01:00Z"
+    });
+    assert_forward_compatible::<Payout>(
+        payload,
+        &[
+            "/beneficiary/type",
+            "/beneficiary/account_identifier/type",
+            "/status",
+        ],
+    );
+}
+
+#[test]
+fn mandates() {
+    let payload = json!({
+        "id": "mandate-id",
+        "currency": "GBP",
+        "mandate": {
+            "type": "commercial",
+            "provider_selection": {
+                "type": "user_selected",
+                "provider_id": "provider-id"
+            },
+            "beneficiary": {
+                "type": "merchant_account",
+                "merchant_account_id": "merchant-account-id"
+            },
+            "reference": "some-reference"
+        },
+        "constraints": {
+            "maximum_individual_amount": 1000
+        },
+        "user": {
+            "id": "user-id"
+        },
+        "created_at": "2022-04-01T00:00:00Z",
+        "status": "revoked",
+        "revoked_at": "2022-04-02T00:00:00Z",
+        "revocation_source": "provider"
+    });
+    assert_forward_compatible::<Mandate>(
+        payload,
+        &[
+            "/mandate/type",
+            "/mandate/provider_selection/type",
+            "/mandate/beneficiary/type",
+            "/status",
+        ],
+    );
+}
+
+#[test]
+fn merchant_account_transactions() {
+    let payload = json!({
+        "id": "transaction-id",
+        "currency": "GBP",
+        "amount_in_minor": 100,
+        "type": "payout",
+        "status": "settled",
+        "created_at": "2022-04-01T00:00:00Z",
+        "settled_at": "2022-04-01T00:01:00Z",
+        "beneficiary": {
+            "type": "payment_source",
+            "user_id": "user-id",
+            "payment_source_id": "payment-source-id",
+            "reference": "some-reference"
+        },
+        "context_code": "withdrawal",
+        "payout_id": "payout-id"
+    });
+    assert_forward_compatible::<Transaction>(
+        payload,
+        &["/type", "/status", "/beneficiary/type", "/context_code"],
+    );
+}
+
+#[test]
+fn payment_links() {
+    let payload = json!({
+        "id": "payment-link-id",
+        "type": "single_use",
+        "created_at": "2022-04-01T00:00:00Z",
+        "payment_configuration": {
+            "amount_in_minor": 100,
+            "currency": "GBP",
+            "payment_method": {
+                "type": "bank_transfer",
+                "provider_selection": {
+                    "type": "user_selected"
+                },
+                "beneficiary": {
+                    "type": "merchant_account",
+                    "merchant_account_id": "merchant-account-id"
+                }
+            },
+            "user": {
+                "id": "user-id"
+            }
+        },
+        "status": "disabled",
+        "disabled_at": "2022-04-01T00:01:00Z",
+        "disablement_reason": "expired"
+    });
+    assert_forward_compatible::<PaymentLink>(
+        payload.clone(),
+        &[
+            "/type",
+            "/payment_configuration/payment_method/type",
+            "/payment_configuration/payment_method/provider_selection/type",
+            "/payment_configuration/payment_method/beneficiary/type",
+            "/status",
+        ],
+    );
+
+    // Disablement reasons are documented as open-ended
+    let mut payload = payload;
+    payload["disablement_reason"] = json!(UNKNOWN_ENUM_VALUE);
+    let payment_link: PaymentLink = serde_json::from_value(payload).unwrap();
+    assert!(matches!(
+        payment_link.status,
+        PaymentLinkStatus::Disabled {
+            disablement_reason: PaymentLinkDisablementReason::Unknown,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn payments_providers() {
+    let payload = json!({
+        "id": "provider-id",
+        "display_name": "Some Bank",
+        "country_code": "GB",
+        "capabilities": {
+            "payments": {
+                "bank_transfer": {
+                    "release_channel": "general_availability",
+                    "schemes": [{
+                        "id": "faster_payments_service"
+                    }]
+                }
+            }
+        },
+        "availability": {
+            "recommended_status": "healthy",
+            "updated_at": "2022-04-01T00:00:00Z"
+        }
+    });
+    assert_forward_compatible::<Provider>(
+        payload.clone(),
+        &["/capabilities/payments/bank_transfer/release_channel"],
+    );
+
+    // New availability statuses are deserialized as unknown
+    let mut unknown_status = payload.clone();
+    unknown_status["availability"]["recommended_status"] = json!(UNKNOWN_ENUM_VALUE);
+    let provider: Provider = serde_json::from_value(unknown_status).unwrap();
+    assert_eq!(
+        provider.availability.unwrap().recommended_status,
+        ProviderAvailabilityStatus::Unknown
+    );
+
+    // New countries are preserved
+    let mut payload = payload;
+    payload["country_code"] = json!("ZZ");
+    let provider: Provider = serde_json::from_value(payload).unwrap();
+    assert_eq!(provider.country_code, Some(CountryCode::other("ZZ")));
+}
+
+#[test]
+fn verifications() {
+    let payload = json!({
+        "id": "verification-id",
+        "account_holder_name": "Mr. Holder",
+        "account_identifier": {
+            "type": "sort_code_account_number",
+            "sort_code": "123456",
+            "account_number": "12345678"
+        },
+        "created_at": "2022-04-01T00:00:00Z",
+        "status": "completed",
+        "completed_at": "2022-04-01T00:01:00Z",
+        "outcome": {
+            "type": "match"
+        }
+    });
+    assert_forward_compatible::<Verification>(payload.clone(), &["/account_identifier/type"]);
+
+    // New outcomes and statuses are deserialized as unknown
+    let mut unknown_outcome = payload.clone();
+    unknown_outcome["outcome"]["type"] = json!(UNKNOWN_ENUM_VALUE);
+    let verification: Verification = serde_json::from_value(unknown_outcome).unwrap();
+    assert_eq!(verification.outcome(), Some(&VerificationOutcome::Unknown));
+
+    let mut unknown_status = payload;
+    unknown_status["status"] = json!(UNKNOWN_ENUM_VALUE);
+    let verification: Verification = serde_json::from_value(unknown_status).unwrap();
+    assert_eq!(verification.status, VerificationStatus::Unknown);
+}
+
+#[test]
+fn webhooks() {
+    let payload = json!({
+        "type": "mandate_revoked",
+        "event_id": "event-id",
+        "event_version": 1,
+        "mandate_id": "mandate-id",
+        "revoked_at": "2022-03-01T12:00:00Z",
+        "revocation_source": "client",
+        "metadata": { "key": "value" }
+    });
+    assert_forward_compatible::<Webhook>(payload.clone(), &[]);
+
+    // New event types are deserialized as unknown
+    let mut payload = payload;
+    payload["type"] = json!(UNKNOWN_ENUM_VALUE);
+    let webhook: Webhook = serde_json::from_value(payload).unwrap();
+    assert_eq!(webhook.event, WebhookEvent::Unknown);
+}
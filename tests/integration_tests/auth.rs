@@ -1,4 +1,11 @@
 use crate::common::test_context::TestContext;
+#[cfg(not(feature = "acceptance-tests"))]
+use crate::integration_tests::helpers::create_closed_loop_payment;
+#[cfg(not(feature = "acceptance-tests"))]
+use truelayer_rust::apis::payments::{
+    AuthorizationFlowResponseStatus, ConsentSupported, RedirectSupported,
+    StartAuthorizationFlowRequest,
+};
 use truelayer_rust::{apis::auth::Credentials, error::ApiError, Error, TrueLayerClient};
 
 #[tokio::test]
@@ -38,3 +45,55 @@ async fn invalid_credentials() {
         .expect_err("Expected error");
     assert!(matches!(err, Error::ApiError(ApiError { title, .. }) if title == "invalid_client"));
 }
+
+#[cfg(not(feature = "acceptance-tests"))]
+#[tokio::test]
+async fn access_token_expired_mid_flow() {
+    let ctx = TestContext::start().await;
+
+    let res = create_closed_loop_payment(&ctx).await.unwrap();
+    let access_token = ctx
+        .client
+        .auth
+        .get_access_token()
+        .await
+        .unwrap()
+        .access_token()
+        .clone();
+
+    // The token is rejected before its expiration, between two steps of the flow
+    ctx.expire_access_tokens();
+
+    let res = ctx
+        .client
+        .payments
+        .start_authorization_flow(
+            &res.id,
+            &StartAuthorizationFlowRequest {
+                provider_selection: None,
+                redirect: Some(RedirectSupported {
+                    return_uri: "http://localhost:3000/callback".to_string(),
+                    direct_return_uri: None,
+                }),
+                consent: Some(ConsentSupported {}),
+                form: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(res.status, AuthorizationFlowResponseStatus::Authorizing);
+
+    // The client transparently switched to a new token
+    let new_access_token = ctx
+        .client
+        .auth
+        .get_access_token()
+        .await
+        .unwrap()
+        .access_token()
+        .clone();
+    assert_ne!(
+        access_token.expose_secret(),
+        new_access_token.expose_secret()
+    );
+}
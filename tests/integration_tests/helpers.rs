@@ -42,6 +42,8 @@ pub async fn create_closed_loop_payment(
                 phone: None,
             },
             metadata: None,
+            retry: None,
+            related_products: None,
         })
         .await?;
     Ok(res)
@@ -51,11 +53,40 @@ pub async fn create_and_authorize_closed_loop_payment(
     ctx: &TestContext,
 ) -> anyhow::Result<Payment> {
     let res = create_closed_loop_payment(ctx).await?;
+    authorize_closed_loop_payment(ctx, &res.id).await?;
 
+    let payment = res
+        .poll_until(
+            &ctx.client,
+            PollOptions::default().with_retry_policy(
+                ExponentialBackoff::builder()
+                    .build_with_total_retry_duration(Duration::from_secs(20)),
+            ),
+            |payment| {
+                matches!(
+                    payment.status,
+                    PaymentStatus::Failed { .. } | PaymentStatus::Settled { .. }
+                )
+            },
+        )
+        .await
+        .unwrap();
+
+    anyhow::ensure!(matches!(payment.status, PaymentStatus::Settled { .. }));
+
+    Ok(payment)
+}
+
+/// Drives a payment created with [`create_closed_loop_payment`] through the authorization flow,
+/// executing it at the mock bank.
+pub async fn authorize_closed_loop_payment(
+    ctx: &TestContext,
+    payment_id: &str,
+) -> anyhow::Result<()> {
     ctx.client
         .payments
         .start_authorization_flow(
-            &res.id,
+            payment_id,
             &StartAuthorizationFlowRequest {
                 provider_selection: None,
                 redirect: Some(RedirectSupported {
@@ -68,7 +99,7 @@ pub async fn create_and_authorize_closed_loop_payment(
         )
         .await?;
 
-    let payment = ctx.client.payments.submit_consent(&res.id).await?;
+    let payment = ctx.client.payments.submit_consent(payment_id).await?;
 
     let redirect_uri = match payment
         .authorization_flow
@@ -89,26 +120,5 @@ pub async fn create_and_authorize_closed_loop_payment(
         provider_return_uri.query().unwrap_or(""),
         provider_return_uri.fragment().unwrap_or(""),
     )
-    .await?;
-
-    let payment = res
-        .poll_until(
-            &ctx.client,
-            PollOptions::default().with_retry_policy(
-                ExponentialBackoff::builder()
-                    .build_with_total_retry_duration(Duration::from_secs(20)),
-            ),
-            |payment| {
-                matches!(
-                    payment.status,
-                    PaymentStatus::Failed { .. } | PaymentStatus::Settled { .. }
-                )
-            },
-        )
-        .await
-        .unwrap();
-
-    anyhow::ensure!(matches!(payment.status, PaymentStatus::Settled { .. }));
-
-    Ok(payment)
+    .await
 }
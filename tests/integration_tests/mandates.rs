@@ -0,0 +1,196 @@
+//! Mandate lifecycle scenarios, simulated by the local mock server
+//! since Sandbox does not offer a mock bank supporting VRP.
+
+use crate::common::{test_context::TestContext, MockBankAction};
+use serde_json::Value;
+use truelayer_rust::{
+    apis::{
+        mandates::{
+            Constraints, CreateMandateRequest, MandateDetail, MandateProviderSelection,
+            MandateStatus, MandateType, RevocationSource,
+        },
+        payments::{
+            AuthorizationFlowResponseStatus, Beneficiary, CreatePaymentUserRequest, Currency,
+            ProviderSelectionSupported, RedirectSupported, StartAuthorizationFlowRequest,
+        },
+    },
+    pollable::PollOptions,
+    PollableUntilTerminalState,
+};
+
+fn webhook_types(webhooks: &[Value]) -> Vec<&str> {
+    webhooks
+        .iter()
+        .map(|webhook| webhook["type"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn mandate_authorized() {
+    let ctx = TestContext::start().await;
+
+    let mandate_id = ctx.create_mock_mandate(1000);
+    ctx.complete_mock_mandate_authorization(&mandate_id, MockBankAction::Execute)
+        .unwrap();
+
+    let webhooks = ctx.webhooks();
+    assert_eq!(webhook_types(&webhooks), vec!["mandate_authorized"]);
+    assert_eq!(webhooks[0]["mandate_id"], mandate_id.as_str());
+
+    // Authorizing the mandate twice is not allowed
+    assert!(ctx
+        .complete_mock_mandate_authorization(&mandate_id, MockBankAction::Execute)
+        .is_err());
+}
+
+#[tokio::test]
+async fn mandate_authorization_rejected() {
+    let ctx = TestContext::start().await;
+
+    let mandate_id = ctx.create_mock_mandate(1000);
+    ctx.complete_mock_mandate_authorization(&mandate_id, MockBankAction::RejectAuthorisation)
+        .unwrap();
+
+    let webhooks = ctx.webhooks();
+    assert_eq!(webhook_types(&webhooks), vec!["mandate_failed"]);
+    assert_eq!(webhooks[0]["failure_reason"], "authorization_failed");
+}
+
+#[tokio::test]
+async fn mandate_revoked_by_bank() {
+    let ctx = TestContext::start().await;
+
+    let mandate_id = ctx.create_mock_mandate(1000);
+    ctx.complete_mock_mandate_authorization(&mandate_id, MockBankAction::Execute)
+        .unwrap();
+    ctx.revoke_mock_mandate_from_bank(&mandate_id).unwrap();
+
+    // Payments on a revoked mandate fail
+    let payment_id = ctx.charge_mock_mandate(&mandate_id, 100).unwrap();
+
+    let webhooks = ctx.webhooks();
+    assert_eq!(
+        webhook_types(&webhooks),
+        vec!["mandate_authorized", "mandate_revoked", "payment_failed"]
+    );
+    assert_eq!(webhooks[1]["revocation_source"], "provider");
+    assert_eq!(webhooks[2]["payment_id"], payment_id.as_str());
+    assert_eq!(webhooks[2]["failure_reason"], "mandate_revoked");
+}
+
+#[tokio::test]
+async fn mandate_constraint_exceeded_on_charge() {
+    let ctx = TestContext::start().await;
+
+    let mandate_id = ctx.create_mock_mandate(1000);
+    ctx.complete_mock_mandate_authorization(&mandate_id, MockBankAction::Execute)
+        .unwrap();
+
+    ctx.charge_mock_mandate(&mandate_id, 1000).unwrap();
+    ctx.charge_mock_mandate(&mandate_id, 1001).unwrap();
+
+    let webhooks = ctx.webhooks();
+    assert_eq!(
+        webhook_types(&webhooks),
+        vec!["mandate_authorized", "payment_executed", "payment_failed"]
+    );
+    assert_eq!(
+        webhooks[2]["payment_method"]["mandate_id"],
+        mandate_id.as_str()
+    );
+    assert_eq!(webhooks[2]["failure_stage"], "authorized");
+    assert_eq!(webhooks[2]["failure_reason"], "constraint_violation");
+}
+
+#[tokio::test]
+async fn create_authorize_and_revoke_mandate() {
+    let ctx = TestContext::start().await;
+
+    // Create a sweeping mandate
+    let res = ctx
+        .client
+        .mandates
+        .create(&CreateMandateRequest {
+            mandate: MandateDetail {
+                r#type: MandateType::Sweeping,
+                provider_selection: MandateProviderSelection::UserSelected {
+                    filter: None,
+                    provider_id: None,
+                },
+                beneficiary: Beneficiary::MerchantAccount {
+                    merchant_account_id: ctx.merchant_account_gbp_id.clone(),
+                    account_holder_name: None,
+                },
+                reference: None,
+            },
+            currency: Currency::Gbp,
+            user: CreatePaymentUserRequest::NewUser {
+                name: Some("Some One".to_string()),
+                email: Some("some.one@email.com".to_string()),
+                phone: None,
+            },
+            constraints: Constraints {
+                valid_from: None,
+                valid_to: None,
+                maximum_individual_amount: 1000,
+                periodic_limits: Default::default(),
+            },
+            metadata: None,
+        })
+        .await
+        .unwrap();
+
+    // Start the authorization flow
+    let auth_flow = ctx
+        .client
+        .mandates
+        .start_authorization_flow(
+            &res.id,
+            &StartAuthorizationFlowRequest {
+                provider_selection: Some(ProviderSelectionSupported {}),
+                redirect: Some(RedirectSupported {
+                    return_uri: "https://my.return.uri".to_string(),
+                    direct_return_uri: None,
+                }),
+                consent: None,
+                form: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        auth_flow.status,
+        AuthorizationFlowResponseStatus::Authorizing
+    );
+
+    // Authorize it
+    ctx.complete_mock_mandate_authorization(&res.id, MockBankAction::Execute)
+        .unwrap();
+    let mandate = res
+        .poll_until_terminal_state(&ctx.client, PollOptions::default())
+        .await
+        .unwrap();
+    assert!(matches!(mandate.status, MandateStatus::Authorized { .. }));
+    assert_eq!(mandate.constraints.maximum_individual_amount, 1000);
+
+    // Revoke it
+    ctx.client.mandates.revoke(&res.id).await.unwrap();
+    let mandate = ctx
+        .client
+        .mandates
+        .get_by_id(&res.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        mandate.status,
+        MandateStatus::Revoked {
+            revocation_source: RevocationSource::Client,
+            ..
+        }
+    ));
+    assert_eq!(
+        webhook_types(&ctx.webhooks()),
+        vec!["mandate_authorized", "mandate_revoked"]
+    );
+}
@@ -135,6 +135,8 @@ async fn list_transactions() {
                     .unwrap()
                     .with_timezone(&Utc),
                 r#type: None,
+                cursor: None,
+                limit: None,
             },
         )
         .await
@@ -161,6 +163,8 @@ async fn list_payment_sources() {
                     .unwrap()
                     .with_timezone(&Utc),
                 r#type: None,
+                cursor: None,
+                limit: None,
             },
         )
         .await
@@ -1,7 +1,13 @@
 mod auth;
 mod helpers;
+#[cfg(not(feature = "acceptance-tests"))]
+mod mandates;
 mod merchant_accounts;
 mod payments;
 mod payments_providers;
 mod payouts;
 mod refunds;
+#[cfg(not(feature = "acceptance-tests"))]
+mod settlement;
+#[cfg(not(feature = "acceptance-tests"))]
+mod signing;
@@ -69,6 +69,8 @@ async fn hpp_link_returns_200() {
                 phone: None,
             },
             metadata: None,
+            retry: None,
+            related_products: None,
         })
         .await
         .unwrap();
@@ -77,8 +79,10 @@ async fn hpp_link_returns_200() {
     let hpp_url = ctx
         .client
         .payments
-        .get_hosted_payments_page_link(&res.id, &res.resource_token, MOCK_RETURN_URI)
-        .await;
+        .hosted_payments_page_link(&res.id, &res.resource_token)
+        .return_uri(MOCK_RETURN_URI)
+        .build()
+        .unwrap();
 
     // Make a request and assert we get back a 200
     assert!(reqwest::Client::new()
@@ -209,6 +213,8 @@ impl CreatePaymentScenario {
                 phone: None,
             },
             metadata: Some(HashMap::from([("some".into(), "metadata".into())])),
+            retry: None,
+            related_products: None,
         };
         let res = ctx
             .client
@@ -871,3 +877,28 @@ async fn cancel_payment() {
             PaymentStatus::Failed { failure_reason, failure_stage, .. }
             if failure_reason == *"canceled" && failure_stage == FailureStage::AuthorizationRequired));
 }
+
+#[tokio::test]
+async fn cancel_and_get_payment() {
+    let ctx = TestContext::start().await;
+
+    // Create a closed-loop payment
+    let payment = helpers::create_closed_loop_payment(&ctx).await.unwrap();
+
+    let payment = ctx
+        .client
+        .payments
+        .cancel_and_get(&payment.id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert!(payment.status.is_cancelled());
+    assert!(matches!(
+        payment.status,
+        PaymentStatus::Failed {
+            failure_stage: FailureStage::AuthorizationRequired,
+            ..
+        }
+    ));
+}
@@ -1,7 +1,12 @@
 use crate::common::test_context::TestContext;
 use truelayer_rust::apis::{
-    payments::{CountryCode, ReleaseChannel},
-    payments_providers::{capabilities, Capabilities, PaymentScheme},
+    payments::{
+        CountryCode, ProviderSelectionSupported, ReleaseChannel, StartAuthorizationFlowRequest,
+    },
+    payments_providers::{
+        capabilities, Capabilities, PaymentScheme, SearchProvidersAuthorizationFlow,
+        SearchProvidersRequest,
+    },
 };
 
 #[tokio::test]
@@ -39,3 +44,33 @@ async fn get_by_id_successful() {
         }
     );
 }
+
+#[tokio::test]
+async fn search() {
+    let ctx = TestContext::start().await;
+
+    let providers = ctx
+        .client
+        .payments_providers
+        .search(&SearchProvidersRequest {
+            countries: Some(vec![CountryCode::GB]),
+            currencies: None,
+            release_channel: Some(ReleaseChannel::GeneralAvailability),
+            customer_segments: None,
+            authorization_flow: SearchProvidersAuthorizationFlow {
+                configuration: StartAuthorizationFlowRequest {
+                    provider_selection: Some(ProviderSelectionSupported {}),
+                    redirect: None,
+                    consent: None,
+                    form: None,
+                },
+            },
+        })
+        .await
+        .unwrap();
+
+    assert!(!providers.is_empty());
+    assert!(providers
+        .iter()
+        .all(|p| p.country_code == Some(CountryCode::GB)));
+}
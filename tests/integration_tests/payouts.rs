@@ -10,7 +10,9 @@ use truelayer_rust::{
     apis::{
         merchant_accounts::ListPaymentSourcesRequest,
         payments::{AccountIdentifier, Currency},
-        payouts::{CreatePayoutRequest, PayoutBeneficiary, PayoutStatus},
+        payouts::{
+            CreatePayoutRequest, Payout, PayoutBeneficiary, PayoutSchemeSelection, PayoutStatus,
+        },
     },
     pollable::PollOptions,
     PollableUntilTerminalState,
@@ -56,6 +58,7 @@ async fn closed_loop_payout() {
                 payment_source_id: payment_source.id,
                 reference: "rust-sdk-test".to_string(),
             },
+            scheme_selection: None,
         })
         .await
         .unwrap();
@@ -109,6 +112,7 @@ async fn open_loop_payout() {
                 account_identifier: account_identifier.clone(),
                 reference: "rust-sdk-test".to_string(),
             },
+            scheme_selection: None,
         })
         .await
         .unwrap();
@@ -135,3 +139,147 @@ async fn open_loop_payout() {
         } if reference == "rust-sdk-test"
     ));
 }
+
+/// Reference which makes the local mock return a payout after executing it.
+#[cfg(not(feature = "acceptance-tests"))]
+static MOCK_RETURNED_PAYOUT_REFERENCE: &str = "mock-returned-payout";
+
+/// Creates an open-loop EUR payout to the IBAN of the EUR merchant account and waits for it to complete.
+async fn create_sepa_payout(
+    ctx: &TestContext,
+    amount_in_minor: u64,
+    scheme_selection: Option<PayoutSchemeSelection>,
+    reference: &str,
+) -> Payout {
+    let merchant_account = ctx
+        .client
+        .merchant_accounts
+        .get_by_id(&ctx.merchant_account_eur_id)
+        .await
+        .unwrap()
+        .unwrap();
+    let iban = merchant_account
+        .account_identifiers
+        .iter()
+        .find(|id| matches!(id, AccountIdentifier::Iban { .. }))
+        .cloned()
+        .expect("EUR merchant account without an IBAN");
+
+    let res = ctx
+        .client
+        .payouts
+        .create(&CreatePayoutRequest {
+            merchant_account_id: ctx.merchant_account_eur_id.clone(),
+            amount_in_minor,
+            currency: Currency::Eur,
+            beneficiary: PayoutBeneficiary::ExternalAccount {
+                account_holder_name: merchant_account.account_holder_name,
+                account_identifier: iban,
+                reference: reference.to_string(),
+            },
+            scheme_selection,
+        })
+        .await
+        .unwrap();
+
+    res.poll_until_terminal_state(
+        &ctx.client,
+        PollOptions::default().with_retry_policy(
+            ExponentialBackoff::builder().build_with_total_retry_duration(Duration::from_secs(60)),
+        ),
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn sepa_instant_payout() {
+    let ctx = TestContext::start().await;
+
+    let payout = create_sepa_payout(
+        &ctx,
+        1,
+        Some(PayoutSchemeSelection::InstantPreferred),
+        "rust-sdk-test",
+    )
+    .await;
+
+    assert_eq!(payout.currency, Currency::Eur);
+    assert!(matches!(payout.status, PayoutStatus::Executed { .. }));
+}
+
+#[cfg(not(feature = "acceptance-tests"))]
+#[tokio::test]
+async fn sepa_payout_uses_instant_scheme_when_available() {
+    let ctx = TestContext::start().await;
+
+    let payout = create_sepa_payout(&ctx, 1, None, "rust-sdk-test").await;
+
+    assert!(matches!(payout.status, PayoutStatus::Executed { .. }));
+    assert_eq!(
+        payout.scheme_id.as_deref(),
+        Some("sepa_credit_transfer_instant")
+    );
+}
+
+#[cfg(not(feature = "acceptance-tests"))]
+#[tokio::test]
+async fn sepa_payout_falls_back_to_regular_scheme() {
+    let ctx = TestContext::start().await;
+
+    // Above the SEPA Instant limit of the mock
+    let payout = create_sepa_payout(
+        &ctx,
+        200_000,
+        Some(PayoutSchemeSelection::InstantPreferred),
+        "rust-sdk-test",
+    )
+    .await;
+
+    assert!(matches!(payout.status, PayoutStatus::Executed { .. }));
+    assert_eq!(payout.scheme_id.as_deref(), Some("sepa_credit_transfer"));
+}
+
+#[cfg(not(feature = "acceptance-tests"))]
+#[tokio::test]
+async fn sepa_instant_only_payout_fails_without_fallback() {
+    let ctx = TestContext::start().await;
+
+    // Above the SEPA Instant limit of the mock
+    let payout = create_sepa_payout(
+        &ctx,
+        200_000,
+        Some(PayoutSchemeSelection::InstantOnly),
+        "rust-sdk-test",
+    )
+    .await;
+
+    assert!(matches!(
+        payout.status,
+        PayoutStatus::Failed { failure_reason, .. } if failure_reason == "scheme_unavailable"
+    ));
+    assert_eq!(payout.scheme_id, None);
+}
+
+#[cfg(not(feature = "acceptance-tests"))]
+#[tokio::test]
+async fn sepa_payout_returned() {
+    let ctx = TestContext::start().await;
+
+    let payout = create_sepa_payout(
+        &ctx,
+        1,
+        Some(PayoutSchemeSelection::Preselected {
+            scheme_id: "sepa_credit_transfer".to_string(),
+        }),
+        MOCK_RETURNED_PAYOUT_REFERENCE,
+    )
+    .await;
+
+    assert_eq!(payout.scheme_id.as_deref(), Some("sepa_credit_transfer"));
+    assert!(matches!(
+        payout.status,
+        PayoutStatus::Returned { ref return_reason, .. } if return_reason == "account_closed"
+    ));
+    assert_eq!(payout.events().len(), 3);
+}
@@ -0,0 +1,280 @@
+//! Settlement timelines spanning minutes, simulated by moving the virtual clock
+//! of the local mock server forward instead of actually waiting.
+
+use crate::{common::test_context::TestContext, integration_tests::helpers};
+use chrono::Utc;
+use retry_policies::{policies::ExponentialBackoff, RetryDecision, RetryPolicy};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::watch;
+use truelayer_rust::{
+    apis::{
+        payments::{Payment, PaymentStatus},
+        webhooks::Webhook,
+    },
+    pollable::{PollOptions, WebhookWaiter},
+    Pollable, PollableUntilTerminalState,
+};
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// Retry policy which waits the given virtual delays between polls,
+/// moving the clock of the mock server forward instead of sleeping.
+struct VirtualSchedule<'a> {
+    ctx: &'a TestContext,
+    waits: Vec<Duration>,
+}
+
+impl RetryPolicy for VirtualSchedule<'_> {
+    fn should_retry(&self, n_past_retries: u32) -> RetryDecision {
+        match self.waits.get(n_past_retries as usize) {
+            Some(wait) => {
+                self.ctx.advance_mock_time(*wait);
+                RetryDecision::Retry {
+                    execute_after: Utc::now(),
+                }
+            }
+            None => RetryDecision::DoNotRetry,
+        }
+    }
+}
+
+fn webhook_types(webhooks: &[Value]) -> Vec<&str> {
+    webhooks
+        .iter()
+        .map(|webhook| webhook["type"].as_str().unwrap())
+        .collect()
+}
+
+/// Feeds all the webhooks recorded by the mock server to the waiter, as a webhook handler would.
+fn feed_webhooks(ctx: &TestContext, waiter: &WebhookWaiter) {
+    for webhook in ctx.webhooks() {
+        let webhook: Webhook = serde_json::from_value(webhook).unwrap();
+        waiter.feed(&webhook);
+    }
+}
+
+#[tokio::test]
+async fn delayed_settlement_is_observed_by_polling() {
+    let ctx = TestContext::start().await;
+    ctx.set_mock_settlement_delays(5 * MINUTE, 30 * MINUTE);
+
+    let res = helpers::create_closed_loop_payment(&ctx).await.unwrap();
+    helpers::authorize_closed_loop_payment(&ctx, &res.id)
+        .await
+        .unwrap();
+
+    // Authorized, but not executed yet
+    let payment = ctx
+        .client
+        .payments
+        .get_by_id(&res.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(payment.status, PaymentStatus::Authorized { .. }));
+
+    // Polling every 2 minutes observes the execution at the 6th minute
+    let polled = payment
+        .poll_until_terminal_state_with_telemetry(
+            &ctx.client,
+            PollOptions::default().with_retry_policy(VirtualSchedule {
+                ctx: &ctx,
+                waits: vec![2 * MINUTE; 10],
+            }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(polled.attempts, 4);
+    let executed_at = match polled.resource.status {
+        PaymentStatus::Executed { executed_at, .. } => executed_at,
+        _ => panic!("Payment not executed"),
+    };
+
+    // Polling every 10 minutes observes the settlement at the 36th minute
+    let polled = polled
+        .resource
+        .poll_until_with_telemetry(
+            &ctx.client,
+            PollOptions::default().with_retry_policy(VirtualSchedule {
+                ctx: &ctx,
+                waits: vec![10 * MINUTE; 10],
+            }),
+            |payment| matches!(payment.status, PaymentStatus::Settled { .. }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(polled.attempts, 4);
+    match polled.resource.status {
+        PaymentStatus::Settled {
+            executed_at: settled_payment_executed_at,
+            settled_at,
+            ..
+        } => {
+            assert_eq!(settled_payment_executed_at, executed_at);
+            assert_eq!(settled_at - executed_at, chrono::Duration::minutes(30));
+        }
+        _ => panic!("Payment not settled"),
+    }
+}
+
+#[tokio::test]
+async fn webhooks_announce_each_settlement_step() {
+    let ctx = TestContext::start().await;
+    ctx.set_mock_settlement_delays(5 * MINUTE, 60 * MINUTE);
+
+    let res = helpers::create_closed_loop_payment(&ctx).await.unwrap();
+    helpers::authorize_closed_loop_payment(&ctx, &res.id)
+        .await
+        .unwrap();
+    assert!(ctx.webhooks().is_empty());
+
+    ctx.advance_mock_time(4 * MINUTE);
+    assert!(ctx.webhooks().is_empty());
+
+    ctx.advance_mock_time(MINUTE);
+    assert_eq!(webhook_types(&ctx.webhooks()), vec!["payment_executed"]);
+
+    ctx.advance_mock_time(59 * MINUTE);
+    assert_eq!(webhook_types(&ctx.webhooks()), vec!["payment_executed"]);
+
+    ctx.advance_mock_time(MINUTE);
+    let webhooks = ctx.webhooks();
+    assert_eq!(
+        webhook_types(&webhooks),
+        vec!["payment_executed", "payment_settled"]
+    );
+    assert!(webhooks
+        .iter()
+        .all(|webhook| webhook["payment_id"] == res.id));
+
+    let payment = ctx
+        .client
+        .payments
+        .get_by_id(&res.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(
+        payment.status,
+        PaymentStatus::Settled { executed_at, settled_at, .. }
+            if settled_at - executed_at == chrono::Duration::minutes(60)
+    ));
+}
+
+#[tokio::test]
+async fn partial_settlement_is_announced_before_the_settlement() {
+    let ctx = TestContext::start().await;
+    ctx.set_mock_partial_settlement_delays(5 * MINUTE, 10 * MINUTE, 60 * MINUTE);
+
+    let res = helpers::create_closed_loop_payment(&ctx).await.unwrap();
+    helpers::authorize_closed_loop_payment(&ctx, &res.id)
+        .await
+        .unwrap();
+
+    ctx.advance_mock_time(5 * MINUTE);
+    assert_eq!(webhook_types(&ctx.webhooks()), vec!["payment_executed"]);
+
+    ctx.advance_mock_time(10 * MINUTE);
+    let webhooks = ctx.webhooks();
+    assert_eq!(
+        webhook_types(&webhooks),
+        vec!["payment_executed", "payment_creditable"]
+    );
+    let creditable: Webhook = serde_json::from_value(webhooks[1].clone()).unwrap();
+    assert_eq!(creditable.event.resource_id(), Some(res.id.as_str()));
+
+    // Creditable payments are still executed until they settle
+    let payment = ctx
+        .client
+        .payments
+        .get_by_id(&res.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(matches!(payment.status, PaymentStatus::Executed { .. }));
+
+    ctx.advance_mock_time(50 * MINUTE);
+    assert_eq!(
+        webhook_types(&ctx.webhooks()),
+        vec!["payment_executed", "payment_creditable", "payment_settled"]
+    );
+}
+
+#[tokio::test]
+async fn webhooks_cut_waits_for_settlement_short() {
+    let ctx = TestContext::start().await;
+    ctx.set_mock_settlement_delays(5 * MINUTE, 30 * MINUTE);
+
+    let res = helpers::create_closed_loop_payment(&ctx).await.unwrap();
+    helpers::authorize_closed_loop_payment(&ctx, &res.id)
+        .await
+        .unwrap();
+
+    // Without webhooks, the poll would only retrieve the payment again after an hour
+    let waiter = WebhookWaiter::new();
+    let (retrieved, mut retrieved_changes) = watch::channel(None);
+    let poll = waiter.poll_until(
+        &res,
+        &ctx.client,
+        PollOptions::default().with_retry_policy(
+            ExponentialBackoff::builder()
+                .retry_bounds(60 * MINUTE, 60 * MINUTE)
+                .build_with_max_retries(3),
+        ),
+        |payment: &Payment| {
+            retrieved.send_replace(Some(payment.status.clone()));
+            matches!(payment.status, PaymentStatus::Settled { .. })
+        },
+    );
+
+    // The poll listens to the webhooks of the payment from the first time it is retrieved
+    let timeline = async {
+        retrieved_changes
+            .wait_for(|status| matches!(status, Some(PaymentStatus::Authorized { .. })))
+            .await
+            .unwrap();
+        ctx.advance_mock_time(5 * MINUTE);
+        feed_webhooks(&ctx, &waiter);
+
+        retrieved_changes
+            .wait_for(|status| matches!(status, Some(PaymentStatus::Executed { .. })))
+            .await
+            .unwrap();
+        ctx.advance_mock_time(30 * MINUTE);
+        feed_webhooks(&ctx, &waiter);
+
+        futures::future::pending::<()>().await
+    };
+
+    let payment = tokio::time::timeout(Duration::from_secs(30), async {
+        tokio::select! {
+            payment = poll => payment,
+            _ = timeline => unreachable!(),
+        }
+    })
+    .await
+    .expect("Webhooks did not cut the wait short")
+    .unwrap();
+
+    assert!(matches!(
+        payment.status,
+        PaymentStatus::Settled { executed_at, settled_at, .. }
+            if settled_at - executed_at == chrono::Duration::minutes(30)
+    ));
+}
+
+#[tokio::test]
+async fn payments_settle_immediately_without_delays() {
+    let ctx = TestContext::start().await;
+
+    let payment = helpers::create_and_authorize_closed_loop_payment(&ctx)
+        .await
+        .unwrap();
+
+    assert!(matches!(payment.status, PaymentStatus::Settled { .. }));
+    assert_eq!(
+        webhook_types(&ctx.webhooks()),
+        vec!["payment_executed", "payment_settled"]
+    );
+}
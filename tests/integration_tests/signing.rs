@@ -0,0 +1,103 @@
+//! Misconfigured signing keys, which TrueLayer rejects with a `401 Unauthorized`.
+
+use crate::common::test_context::TestContext;
+use openssl::{
+    ec::{EcGroup, EcKey},
+    nid::Nid,
+};
+use std::time::Duration;
+use truelayer_rust::{
+    apis::payments::{
+        Beneficiary, CreatePaymentRequest, CreatePaymentUserRequest, Currency,
+        PaymentMethodRequest, ProviderSelectionRequest,
+    },
+    error::SignatureRejection,
+    Error, TrueLayerClient,
+};
+
+fn random_private_key_pem() -> Vec<u8> {
+    EcKey::generate(&EcGroup::from_curve_name(Nid::SECP521R1).unwrap())
+        .unwrap()
+        .private_key_to_pem()
+        .unwrap()
+}
+
+async fn create_payment(ctx: &TestContext, client: &TrueLayerClient) -> Result<(), Error> {
+    client
+        .payments
+        .create(&CreatePaymentRequest {
+            amount_in_minor: 100,
+            currency: Currency::Gbp,
+            payment_method: PaymentMethodRequest::BankTransfer {
+                provider_selection: ProviderSelectionRequest::UserSelected {
+                    filter: None,
+                    scheme_selection: None,
+                },
+                beneficiary: Beneficiary::MerchantAccount {
+                    merchant_account_id: ctx.merchant_account_gbp_id.clone(),
+                    account_holder_name: None,
+                },
+            },
+            user: CreatePaymentUserRequest::NewUser {
+                name: Some("someone".to_string()),
+                email: Some("some.one@email.com".to_string()),
+                phone: None,
+            },
+            metadata: None,
+            retry: None,
+            related_products: None,
+        })
+        .await
+        .map(|_| ())
+}
+
+fn rejection(result: Result<(), Error>) -> SignatureRejection {
+    match result {
+        Err(Error::SignatureRejected { reason, source }) => {
+            assert_eq!(source.status, 401);
+            reason
+        }
+        other => panic!("Expected a signature rejection, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn wrong_signing_key() {
+    let ctx = TestContext::start().await;
+    let client = ctx.client_with_signing_key(ctx.signing_key_id(), random_private_key_pem());
+
+    assert_eq!(
+        rejection(create_payment(&ctx, &client).await),
+        SignatureRejection::InvalidSignature
+    );
+}
+
+#[tokio::test]
+async fn unknown_signing_key_id() {
+    let ctx = TestContext::start().await;
+    let client = ctx.client_with_signing_key("unknown-key-id", random_private_key_pem());
+
+    assert_eq!(
+        rejection(create_payment(&ctx, &client).await),
+        SignatureRejection::UnknownKeyId
+    );
+}
+
+#[tokio::test]
+async fn clock_skew() {
+    let ctx = TestContext::start().await;
+    ctx.skew_mock_signature_clock(Duration::from_secs(10 * 60));
+
+    match rejection(create_payment(&ctx, &ctx.client).await) {
+        SignatureRejection::ClockSkew { skew } => {
+            // The local clock is behind the one of the server
+            assert!(skew < -chrono::Duration::minutes(9));
+            assert!(skew > -chrono::Duration::minutes(11));
+        }
+        other => panic!("Expected a clock skew, got {:?}", other),
+    }
+
+    // Correctly signed requests are accepted again once the clocks are in sync
+    ctx.skew_mock_signature_clock(Duration::ZERO);
+    create_payment(&ctx, &ctx.client).await.unwrap();
+}